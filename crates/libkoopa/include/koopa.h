@@ -8,6 +8,14 @@
 #include <Windows.h>
 #endif
 
+#if defined(__GNUC__) || defined(__clang__)
+#define KOOPA_DEPRECATED(msg) __attribute__((deprecated(msg)))
+#elif defined(_MSC_VER)
+#define KOOPA_DEPRECATED(msg) __declspec(deprecated(msg))
+#else
+#define KOOPA_DEPRECATED(msg)
+#endif
+
 #ifdef __cplusplus
 extern "C" {
 #endif  // __cplusplus
@@ -38,6 +46,8 @@ enum koopa_error_code {
   KOOPA_EC_TYPE_MISMATCH,
   /// Mismatch of function parameter number.
   KOOPA_EC_FUNC_PARAM_NUM_MISMATCH,
+  /// Value or type kind that has no raw representation.
+  KOOPA_EC_UNSUPPORTED_KIND,
 };
 
 ///
@@ -581,9 +591,31 @@ void koopa_delete_raw_program_builder(koopa_raw_program_builder_t builder);
 /// is still alive (not deleted). The generated raw program should
 /// not be modified manually.
 ///
+/// Deprecated, use `koopa_try_build_raw_program` instead. Aborts if
+/// the program contains values or types that have no raw
+/// representation, such as `switch` or named types.
+///
+KOOPA_DEPRECATED("use koopa_try_build_raw_program instead")
 koopa_raw_program_t koopa_build_raw_program(
     koopa_raw_program_builder_t builder, koopa_program_t program);
 
+///
+/// Builds a raw program of the given Koopa IR program
+/// using the given raw program builder.
+/// Updates the `raw` if no errors occurred.
+///
+/// The generated raw program is valid iff the raw program builder
+/// is still alive (not deleted). The generated raw program should
+/// not be modified manually.
+///
+/// Returns the error code. Returns `KOOPA_EC_UNSUPPORTED_KIND` if
+/// the program contains values or types that have no raw
+/// representation, such as `switch` or named types.
+///
+koopa_error_code_t koopa_try_build_raw_program(
+    koopa_raw_program_builder_t builder, koopa_program_t program,
+    koopa_raw_program_t *raw);
+
 ///
 /// Generates the given raw program to the Koopa IR program.
 /// Updates the `program` if no errors occurred.
@@ -23,6 +23,8 @@ pub enum ErrorCode {
   TypeMismatch,
   /// Mismatch of function parameter number.
   FuncParamNumMismatch,
+  /// Value or type kind that has no raw representation.
+  UnsupportedKind,
 }
 
 /// Unwraps a [`Result`], or returns the given error code on error.
@@ -1,4 +1,5 @@
 use super::entities::*;
+use crate::errors::ErrorCode;
 use crate::utils::new_uninit_box;
 use koopa::ir::entities::{BasicBlockData, ValueData};
 use koopa::ir::values::*;
@@ -28,8 +29,12 @@ impl RawProgramBuilder {
   }
 
   /// Builds on the given Koopa IR program.
+  ///
+  /// Returns [`ErrorCode::UnsupportedKind`] if the program contains
+  /// values or types that have no raw representation.
   #[allow(clippy::needless_collect)]
-  pub fn build_on(&mut self, program: &Program) -> RawProgram {
+  pub fn build_on(&mut self, program: &Program) -> Result<RawProgram<'_>, ErrorCode> {
+    check_program(program)?;
     let mut info = ProgramInfo::new(program);
     let raw = RawProgram {
       values: iter_into_raw(program.inst_layout().iter(), self, &mut info),
@@ -57,7 +62,54 @@ impl RawProgramBuilder {
     for (data, slice) in self.values.values_mut().zip(slices.into_iter()) {
       data.used_by = slice;
     }
-    raw
+    Ok(raw)
+  }
+}
+
+/// Checks if all values and types in the given program
+/// can be converted to their raw representations.
+fn check_program(program: &Program) -> Result<(), ErrorCode> {
+  for value in program.borrow_values().values() {
+    check_value(value)?;
+  }
+  for func in program.funcs().values() {
+    check_type(func.ty())?;
+    for value in func.dfg().values().values() {
+      check_value(value)?;
+    }
+  }
+  Ok(())
+}
+
+/// Checks if the given value can be converted to a raw value.
+fn check_value(value: &ValueData) -> Result<(), ErrorCode> {
+  match value.kind() {
+    // raw programs have no representation of aggregate operations,
+    // unary operations, overflow-checked operations, copies,
+    // assumptions, multi-way branches and unreachable terminators
+    ValueKind::ExtractValue(_)
+    | ValueKind::InsertValue(_)
+    | ValueKind::Unary(_)
+    | ValueKind::CheckedBinary(_)
+    | ValueKind::Copy(_)
+    | ValueKind::Assume(_)
+    | ValueKind::Switch(_)
+    | ValueKind::Unreachable(_) => Err(ErrorCode::UnsupportedKind),
+    _ => check_type(value.ty()),
+  }
+}
+
+/// Checks if the given type can be converted to a raw type.
+fn check_type(ty: &Type) -> Result<(), ErrorCode> {
+  match ty.kind() {
+    TypeKind::Int32 | TypeKind::Unit => Ok(()),
+    TypeKind::Array(base, _) | TypeKind::Pointer(base) => check_type(base),
+    TypeKind::Function(params, ret) => {
+      params.iter().try_for_each(check_type)?;
+      check_type(ret)
+    }
+    // raw programs have no representation of named types
+    TypeKind::Named(..) => Err(ErrorCode::UnsupportedKind),
   }
 }
 
@@ -188,6 +240,7 @@ impl BuildRaw for TypeKind {
         iter_into_raw(params.iter(), builder, info),
         ret.build(builder, info),
       ),
+      TypeKind::Named(..) => unreachable!("named types are rejected by `check_type`"),
     }
   }
 }
@@ -333,6 +386,7 @@ impl BuildRaw for ValueKind {
       ValueKind::Jump(v) => RawValueKind::Jump(v.build(builder, info)),
       ValueKind::Call(v) => RawValueKind::Call(v.build(builder, info)),
       ValueKind::Return(v) => RawValueKind::Return(v.build(builder, info)),
+      _ => unreachable!("unsupported values are rejected by `check_value`"),
     }
   }
 }
@@ -19,11 +19,42 @@ ffi! {
   ///
   /// The generated raw program is valid iff the raw program builder
   /// is still alive (not deleted).
+  ///
+  /// Deprecated, use [`koopa_try_build_raw_program`] instead.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the program contains values or types that have no raw
+  /// representation.
   fn koopa_build_raw_program<'rpb>(
     builder: &'rpb mut RawProgramBuilder,
     program: &Program,
   ) -> RawProgram<'rpb> {
-    builder.build_on(program)
+    builder
+      .build_on(program)
+      .expect("program can not be represented as a raw program")
+  }
+
+  /// Builds a raw program of the given Koopa IR program
+  /// using the given raw program builder.
+  /// Updates the `raw` if no errors occurred.
+  ///
+  /// The generated raw program is valid iff the raw program builder
+  /// is still alive (not deleted).
+  ///
+  /// Returns the error code.
+  fn koopa_try_build_raw_program<'rpb>(
+    builder: &'rpb mut RawProgramBuilder,
+    program: &Program,
+    raw: &mut RawProgram<'rpb>,
+  ) -> ErrorCode {
+    match builder.build_on(program) {
+      Ok(r) => {
+        *raw = r;
+        ErrorCode::Success
+      }
+      Err(e) => e,
+    }
   }
 
   /// Generates the given raw program to the Koopa IR program.
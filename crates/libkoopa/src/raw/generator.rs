@@ -434,7 +434,7 @@ impl GenerateOnRaw for RawGetPtr {
   fn generate(&self, program: &mut Program, info: &mut ProgramInfo) -> Result<Self::Entity> {
     let src = self.src.generate(program, info)?;
     let index = self.index.generate(program, info)?;
-    Ok(builder!(program, info).get_ptr(src, index))
+    Ok(build_value!(program, info, b, { b.get_ptr(src, index) }))
   }
 }
 
@@ -444,7 +444,9 @@ impl GenerateOnRaw for RawGetElemPtr {
   fn generate(&self, program: &mut Program, info: &mut ProgramInfo) -> Result<Self::Entity> {
     let src = self.src.generate(program, info)?;
     let index = self.index.generate(program, info)?;
-    Ok(builder!(program, info).get_elem_ptr(src, index))
+    Ok(build_value!(program, info, b, {
+      b.get_elem_ptr(src, index)
+    }))
   }
 }
 
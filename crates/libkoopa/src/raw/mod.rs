@@ -12,6 +12,7 @@ use generator::*;
 #[cfg(test)]
 mod test {
   use super::*;
+  use crate::errors::ErrorCode;
   use koopa::back::KoopaGenerator;
   use koopa::front::Driver;
   use koopa::ir::Program;
@@ -75,7 +76,7 @@ fun @main(): i32 {
 
   fn build_raw<'rpb>(builder: &'rpb mut RawProgramBuilder, program: &str) -> RawProgram<'rpb> {
     let program = Driver::from(program).generate_program().unwrap();
-    builder.build_on(&program)
+    builder.build_on(&program).unwrap()
   }
 
   fn build_and_generate(program: &str) -> Program {
@@ -157,4 +158,23 @@ fun @main(): i32 {
     gen.generate_on(&program).unwrap();
     assert_eq!(std::str::from_utf8(&gen.writer()).unwrap(), ALLOC_PROGRAM);
   }
+
+  #[test]
+  fn test_raw_builder_unsupported() {
+    let program = Driver::from(
+      r#"fun @main(): i32 {
+%entry:
+  switch 0, %end(1), [1: %end(2)]
+
+%end(%0: i32):
+  ret %0
+}
+"#,
+    )
+    .generate_program()
+    .unwrap();
+    let mut builder = RawProgramBuilder::new();
+    let result = builder.build_on(&program);
+    assert!(matches!(result, Err(ErrorCode::UnsupportedKind)));
+  }
 }
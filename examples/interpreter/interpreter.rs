@@ -5,13 +5,15 @@
 
 use super::ext_funcs::ExternFuncs;
 use koopa::back::{NameManager, Visitor};
+use koopa::ir::dfg::DataFlowGraph;
 use koopa::ir::entities::ValueData;
-use koopa::ir::layout::BasicBlockNode;
 use koopa::ir::values::*;
 use koopa::ir::{BasicBlock, BinaryOp, FunctionData, Program, Type, TypeKind, Value, ValueKind};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io::{Error, ErrorKind, Result, Write};
 use std::ptr::{null, NonNull};
+use std::rc::Rc;
+use std::{fmt, result};
 
 pub fn new_error(message: &str) -> Error {
   Error::new(ErrorKind::Other, message)
@@ -19,36 +21,329 @@ pub fn new_error(message: &str) -> Error {
 
 pub struct Interpreter {
   libs: Vec<String>,
+  div_mode: DivMode,
+  undef_mode: UndefMode,
+  step_limit: Option<u64>,
 }
 
 impl Interpreter {
   pub fn new(libs: Vec<String>) -> Self {
-    Self { libs }
+    Self {
+      libs,
+      div_mode: DivMode::default(),
+      undef_mode: UndefMode::default(),
+      step_limit: None,
+    }
+  }
+
+  /// Sets the rounding mode of `div` and `mod`.
+  pub fn set_div_mode(&mut self, div_mode: DivMode) {
+    self.div_mode = div_mode;
+  }
+
+  /// Sets how `undef` values are evaluated.
+  pub fn set_undef_mode(&mut self, undef_mode: UndefMode) {
+    self.undef_mode = undef_mode;
+  }
+
+  /// Sets the maximum number of instructions evaluated in each run of an
+  /// entry function, or `None` (the default) for no limit.
+  ///
+  /// Exceeding the limit is a runtime error, which stops programs that
+  /// loop or recurse forever.
+  pub fn set_step_limit(&mut self, step_limit: Option<u64>) {
+    self.step_limit = step_limit;
+  }
+
+  /// Runs function `@main` in the given program with the given arguments,
+  /// returns its return value as the exit code.
+  ///
+  /// `@main` must be defined, return an `i32`, and take exactly one `i32`
+  /// parameter for each argument.
+  pub fn run_main(&self, program: &Program, args: Vec<Val>) -> Result<i32> {
+    self.run_func(program, "@main", args)
+  }
+
+  /// Runs function `@main` in the given program once for each of the
+  /// given argument lists, returns the return values.
+  ///
+  /// Global allocations are evaluated once, and the interpreter is
+  /// restored to the state before the first run (see
+  /// [`InterpState`]) before each of the other runs, so runs do not
+  /// affect each other.
+  pub fn run_main_each(&self, program: &Program, args: Vec<Vec<Val>>) -> Result<Vec<i32>> {
+    let ext_funcs = unsafe { ExternFuncs::new(&self.libs) }
+      .map_err(|e| new_error(&format!("invalid library: {}", e)))?;
+    let mut interpreter = InterpreterImpl::new(program, ext_funcs);
+    interpreter.div_mode = self.div_mode;
+    interpreter.undefs = Undefs::new(self.undef_mode);
+    interpreter.step_limit = self.step_limit;
+    interpreter.init()?;
+    let state = interpreter.snapshot();
+    let mut exits = Vec::new();
+    for (i, args) in args.into_iter().enumerate() {
+      if i != 0 {
+        interpreter.restore(state.clone());
+      }
+      exits.push(interpreter.eval_entry_int("@main", args)?);
+    }
+    Ok(exits)
+  }
+
+  /// Runs the function with the given name (like `@main`) in the given
+  /// program with the given arguments, returns its return value.
+  ///
+  /// The function has the same requirements as `@main` in
+  /// [`run_main`](Self::run_main).
+  pub fn run_func(&self, program: &Program, name: &str, args: Vec<Val>) -> Result<i32> {
+    self.run(program, name, args).exit
+  }
+
+  /// Runs the function with the given name in both programs with the
+  /// same arguments, returns the first point where the two runs diverge,
+  /// or `None` if they agree.
+  ///
+  /// This is for differential testing, for example, running a program
+  /// before and after an optimization pass. Runs are compared by:
+  ///
+  /// 1. Calls to external functions (like `@putint`), which are the
+  ///    observable outputs of the program.
+  /// 2. Return values. Runtime errors are considered equal regardless of
+  ///    their messages, since locations in messages differ between
+  ///    programs.
+  /// 3. Final values of global allocations with the same name in both
+  ///    programs, if both runs returned normally.
+  ///
+  /// Note that external functions are actually called in both runs, so
+  /// outputs of the program are printed twice.
+  pub fn compare(
+    &self,
+    lhs: &Program,
+    rhs: &Program,
+    name: &str,
+    args: Vec<Val>,
+  ) -> Option<Divergence> {
+    let lhs = self.run(lhs, name, args.clone());
+    let rhs = self.run(rhs, name, args);
+    // external calls until one of the runs stops
+    let mut calls = lhs.ext_calls.iter().zip(&rhs.ext_calls).enumerate();
+    if let Some((index, (l, r))) = calls.find(|(_, (l, r))| l != r) {
+      return Some(Divergence::ExtCall(index, Some(l.clone()), Some(r.clone())));
+    }
+    let same_exit = match (&lhs.exit, &rhs.exit) {
+      (Ok(l), Ok(r)) => l == r,
+      (Err(_), Err(_)) => true,
+      _ => false,
+    };
+    if !same_exit {
+      let exit = |e: Result<i32>| e.map_err(|e| e.to_string());
+      return Some(Divergence::Exit(exit(lhs.exit), exit(rhs.exit)));
+    }
+    if lhs.ext_calls.len() != rhs.ext_calls.len() {
+      let index = lhs.ext_calls.len().min(rhs.ext_calls.len());
+      return Some(Divergence::ExtCall(
+        index,
+        lhs.ext_calls.get(index).cloned(),
+        rhs.ext_calls.get(index).cloned(),
+      ));
+    }
+    if lhs.exit.is_err() {
+      return None;
+    }
+    lhs.globals.into_iter().find_map(|(name, l)| {
+      let (_, r) = rhs.globals.iter().find(|(n, _)| *n == name)?;
+      (l != *r).then(|| Divergence::Global(name, l, r.clone()))
+    })
+  }
+
+  /// Runs the function with the given name, returns the whole record of
+  /// the run.
+  fn run(&self, program: &Program, name: &str, args: Vec<Val>) -> Run {
+    let ext_funcs = match unsafe { ExternFuncs::new(&self.libs) } {
+      Ok(ext_funcs) => ext_funcs,
+      Err(e) => {
+        return Run {
+          exit: Err(new_error(&format!("invalid library: {}", e))),
+          ext_calls: Vec::new(),
+          globals: Vec::new(),
+        }
+      }
+    };
+    let mut interpreter = InterpreterImpl::new(program, ext_funcs);
+    interpreter.div_mode = self.div_mode;
+    interpreter.undefs = Undefs::new(self.undef_mode);
+    interpreter.step_limit = self.step_limit;
+    let exit = interpreter.interpret(name, args);
+    let globals = interpreter.globals();
+    Run {
+      exit,
+      ext_calls: interpreter.ext_calls,
+      globals,
+    }
+  }
+}
+
+/// Record of a run of the interpreter.
+struct Run {
+  exit: Result<i32>,
+  ext_calls: Vec<ExtCall>,
+  globals: Vec<(String, Vec<Option<i32>>)>,
+}
+
+/// A call to an external function made by the interpreter.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExtCall {
+  /// Name of the callee, like `@putint`.
+  pub name: String,
+  /// Arguments, or `None` for non-integer arguments (like pointers),
+  /// whose values differ between runs.
+  pub args: Vec<Option<i32>>,
+  /// Return value, or `None` if the callee does not return an integer,
+  /// or the call failed.
+  pub ret: Option<i32>,
+}
+
+/// The first point where two runs diverge, returned by
+/// [`Interpreter::compare`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Divergence {
+  /// The external calls at the given index differ, the call is `None`
+  /// if the run made fewer calls.
+  ExtCall(usize, Option<ExtCall>, Option<ExtCall>),
+  /// Return values differ, or only one of the runs failed. Contains the
+  /// return values or the error messages.
+  Exit(result::Result<i32, String>, result::Result<i32, String>),
+  /// Final values of the global allocation with the given name differ.
+  /// Contains integers in the global allocation in row-major order, or
+  /// `None` for non-integer values.
+  Global(String, Vec<Option<i32>>, Vec<Option<i32>>),
+}
+
+/// Formats optional integers, `None` is formatted as `?`.
+fn fmt_ints(ints: &[Option<i32>]) -> String {
+  let ints: Vec<_> = (ints.iter())
+    .map(|i| i.map_or_else(|| "?".into(), |i| i.to_string()))
+    .collect();
+  ints.join(", ")
+}
+
+impl fmt::Display for ExtCall {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}({})", self.name, fmt_ints(&self.args))?;
+    match self.ret {
+      Some(ret) => write!(f, " = {}", ret),
+      None => Ok(()),
+    }
+  }
+}
+
+impl fmt::Display for Divergence {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let call = |c: &Option<ExtCall>| {
+      c.as_ref()
+        .map_or_else(|| "no call".into(), |c| c.to_string())
+    };
+    let exit = |e: &result::Result<i32, String>| match e {
+      Ok(ret) => ret.to_string(),
+      Err(e) => format!("error ({})", e),
+    };
+    match self {
+      Self::ExtCall(i, l, r) => {
+        write!(f, "external call {} differs: {} vs {}", i, call(l), call(r))
+      }
+      Self::Exit(l, r) => write!(f, "return values differ: {} vs {}", exit(l), exit(r)),
+      Self::Global(name, l, r) => write!(
+        f,
+        "final values of {} differ: [{}] vs [{}]",
+        name,
+        fmt_ints(l),
+        fmt_ints(r)
+      ),
+    }
+  }
+}
+
+/// Rounding mode of integer division (`div`) and modulo (`mod`).
+///
+/// In all modes, `lhs == (lhs div rhs) * rhs + (lhs mod rhs)` holds,
+/// division by zero is a runtime error, and `-2147483648 div -1` wraps
+/// around to `-2147483648`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DivMode {
+  /// Rounds the quotient toward zero, the remainder has the sign of the
+  /// dividend, like C. This is the semantics of Koopa IR, which is also
+  /// used by constant folding.
+  #[default]
+  TruncTowardZero,
+  /// Rounds the quotient toward negative infinity, the remainder has the
+  /// sign of the divisor, like Python.
+  FloorDiv,
+  /// Euclidean division, the remainder is always non-negative.
+  Euclidean,
+}
+
+impl DivMode {
+  /// Returns the quotient and the remainder of `lhs` divided by `rhs`,
+  /// or `None` if `rhs` is zero.
+  fn div_rem(self, lhs: i32, rhs: i32) -> Option<(i32, i32)> {
+    if rhs == 0 {
+      return None;
+    }
+    let (q, r) = (lhs.wrapping_div(rhs), lhs.wrapping_rem(rhs));
+    Some(match self {
+      Self::TruncTowardZero => (q, r),
+      Self::FloorDiv if r != 0 && (r < 0) != (rhs < 0) => (q.wrapping_sub(1), r + rhs),
+      Self::FloorDiv => (q, r),
+      Self::Euclidean => (lhs.wrapping_div_euclid(rhs), lhs.wrapping_rem_euclid(rhs)),
+    })
   }
 }
 
+/// Evaluation mode of `undef` values.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UndefMode {
+  /// `undef` evaluates to an undefined value, which is an error to
+  /// compute with, branch on or return from `@main`.
+  #[default]
+  Undefined,
+  /// Each `undef` value is frozen to an arbitrary but fixed value when
+  /// it is first evaluated, so all reads of the same `undef` agree.
+  /// Integers are generated from the given seed, pointers are null.
+  ///
+  /// The frozen values only depend on the seed and the order in which
+  /// `undef`s are first evaluated, so runs with the same seed are
+  /// reproducible, and different seeds give different choices.
+  Freeze(u64),
+}
+
 impl<W: Write> Visitor<W> for Interpreter {
   type Output = i32;
 
   fn visit(&mut self, _: &mut W, _: &mut NameManager, program: &Program) -> Result<Self::Output> {
-    let ext_funcs = unsafe { ExternFuncs::new(&self.libs) }
-      .map_err(|e| new_error(&format!("invalid library: {}", e)))?;
-    let mut interpreter = InterpreterImpl::new(program, ext_funcs);
-    interpreter.interpret()
+    self.run_main(program, Vec::new())
   }
 }
 
 struct InterpreterImpl<'a> {
   program: &'a Program,
   global_allocs: Vec<Box<Val>>,
+  // addresses of all values in read-only global allocations
+  readonly: HashSet<*const Val>,
+  heap: Heap,
   vars: HashMap<*const ValueData, Val>,
   envs: Vec<Environment<'a>>,
   ext_funcs: ExternFuncs,
+  div_mode: DivMode,
+  undefs: Undefs,
+  step_limit: Option<u64>,
+  steps: u64,
+  compiled: HashMap<*const FunctionData, Rc<CompiledFunc<'a>>>,
+  ext_calls: Vec<ExtCall>,
 }
 
 macro_rules! func {
   ($self:ident) => {
-    $self.envs.last().unwrap().func
+    $self.envs.last().unwrap().func.func
   };
 }
 
@@ -58,30 +353,115 @@ macro_rules! value {
   };
 }
 
-macro_rules! bb_node {
-  ($self:ident, $bb:expr) => {
-    func!($self).layout().bbs().node(&$bb).unwrap()
-  };
-}
-
 impl<'a> InterpreterImpl<'a> {
   fn new(program: &'a Program, ext_funcs: ExternFuncs) -> Self {
     Self {
       program,
       global_allocs: Vec::new(),
+      readonly: HashSet::new(),
+      heap: Heap::default(),
       vars: HashMap::new(),
       envs: Vec::new(),
       ext_funcs,
+      div_mode: DivMode::default(),
+      undefs: Undefs::new(UndefMode::default()),
+      step_limit: None,
+      steps: 0,
+      compiled: HashMap::new(),
+      ext_calls: Vec::new(),
+    }
+  }
+
+  fn interpret(&mut self, name: &str, args: Vec<Val>) -> Result<i32> {
+    self.init()?;
+    self.eval_entry_int(name, args)
+  }
+
+  /// Checks the program and evaluates all global variables.
+  fn init(&mut self) -> Result<()> {
+    if !self.program.named_types().is_empty() {
+      return Err(new_error("named types are not supported"));
+    }
+    self.eval_globals()
+  }
+
+  /// Evaluates on the entry function with the given name and arguments,
+  /// which must return an integer.
+  fn eval_entry_int(&mut self, name: &str, args: Vec<Val>) -> Result<i32> {
+    match self.eval_entry(name, args)? {
+      Val::Int(i) => Ok(i),
+      _ => Err(new_error(&format!(
+        "function '{}' returned an invalid value",
+        name
+      ))),
+    }
+  }
+
+  /// Evaluates on the entry function with the given name and arguments.
+  fn eval_entry(&mut self, name: &str, args: Vec<Val>) -> Result<Val> {
+    let error = |msg: &str| new_error(&format!("function '{}' {}", name, msg));
+    let entry = self
+      .program
+      .funcs()
+      .values()
+      .find(|f| f.name() == name)
+      .ok_or_else(|| error("not found"))?;
+    if entry.layout().entry_bb().is_none() {
+      return Err(error("must have a body"));
+    }
+    let (params, ret) = match entry.ty().kind() {
+      TypeKind::Function(params, ret) => (params, ret),
+      _ => panic!("invalid function"),
+    };
+    if !ret.is_i32() {
+      return Err(error("must return an integer"));
+    }
+    if params.iter().any(|p| !p.is_i32()) {
+      return Err(new_error(&format!(
+        "parameters of function '{}' must be integers",
+        name
+      )));
+    }
+    if params.len() != args.len() {
+      return Err(error(&format!(
+        "takes {} argument(s), but {} given",
+        params.len(),
+        args.len()
+      )));
     }
+    self.steps = 0;
+    self.eval_func(entry, args)
   }
 
-  fn interpret(&mut self) -> Result<i32> {
-    // evaluate all global variables
-    for var in self.program.inst_layout() {
-      let value = self.program.borrow_value(*var);
+  /// Returns names and final values of all named global allocations,
+  /// integers are flattened in row-major order.
+  fn globals(&self) -> Vec<(String, Vec<Option<i32>>)> {
+    fn flatten(val: &Val, ints: &mut Vec<Option<i32>>) {
+      match val {
+        Val::Int(i) => ints.push(Some(*i)),
+        Val::Array(arr) => arr.iter().for_each(|v| flatten(v, ints)),
+        _ => ints.push(None),
+      }
+    }
+    (self.program.inst_layout().iter())
+      .zip(&self.global_allocs)
+      .filter_map(|(var, alloc)| {
+        let name = self.program.borrow_value(*var).name().clone()?;
+        let mut ints = Vec::new();
+        flatten(alloc, &mut ints);
+        Some((name, ints))
+      })
+      .collect()
+  }
+
+  /// Evaluates all global variables.
+  fn eval_globals(&mut self) -> Result<()> {
+    let program = self.program;
+    for var in program.inst_layout() {
+      let value = program.borrow_value(*var);
       match value.kind() {
         ValueKind::GlobalAlloc(ga) => {
-          let val = self.eval_global_const(&self.program.borrow_value(ga.init()));
+          let val = self.eval_global_const(&program.borrow_value(ga.init()))?;
           self.global_allocs.push(Box::new(val));
           self.vars.insert(
             &value as &ValueData,
@@ -91,47 +471,105 @@ impl<'a> InterpreterImpl<'a> {
         _ => panic!("invalid global variable"),
       }
     }
-    // evaluate on the main function
-    self
-      .program
-      .funcs()
-      .values()
-      .find(|f| f.name() == "@main")
-      .ok_or_else(|| new_error("function '@main' not found"))
-      .and_then(|f| self.eval_func(f, Vec::new()))
-      .and_then(|v| {
-        if let Val::Int(i) = v {
-          Ok(i)
-        } else {
-          Err(new_error("function '@main' must return an integer"))
+    self.update_readonly();
+    Ok(())
+  }
+
+  /// Updates addresses of all values in read-only global allocations.
+  fn update_readonly(&mut self) {
+    fn insert_addrs(val: &Val, addrs: &mut HashSet<*const Val>) {
+      addrs.insert(val);
+      if let Val::Array(arr) = val {
+        arr.iter().for_each(|v| insert_addrs(v, addrs));
+      }
+    }
+    self.readonly.clear();
+    for (var, alloc) in self.program.inst_layout().iter().zip(&self.global_allocs) {
+      if let ValueKind::GlobalAlloc(ga) = self.program.borrow_value(*var).kind() {
+        if ga.attrs().readonly {
+          insert_addrs(alloc, &mut self.readonly);
         }
-      })
+      }
+    }
   }
 
-  fn eval_global_const(&self, value: &ValueData) -> Val {
-    match value.kind() {
+  /// Takes a snapshot of the current state of the interpreter.
+  ///
+  /// See [`InterpState`] for the memory cost.
+  pub fn snapshot(&self) -> InterpState<'a> {
+    InterpState::copy_from(&self.global_allocs, &self.heap, &self.vars, &self.envs)
+  }
+
+  /// Restores the interpreter to the given snapshot.
+  pub fn restore(&mut self, state: InterpState<'a>) {
+    self.global_allocs = state.global_allocs;
+    self.heap = state.heap;
+    self.vars = state.vars;
+    self.envs = state.envs;
+    self.update_readonly();
+  }
+
+  fn eval_global_const(&mut self, value: &ValueData) -> Result<Val> {
+    let program = self.program;
+    Ok(match value.kind() {
       ValueKind::Integer(v) => Val::Int(v.value()),
       ValueKind::ZeroInit(_) => Self::new_zeroinit(value.ty()),
-      ValueKind::Undef(_) => Val::Undef,
+      ValueKind::Undef(_) => self.undefs.eval(value),
       ValueKind::Aggregate(v) => Val::Array(
         v.elems()
           .iter()
-          .map(|e| self.eval_global_const(&self.program.borrow_value(*e)))
-          .collect(),
+          .map(|e| self.eval_global_const(&program.borrow_value(*e)))
+          .collect::<Result<_>>()?,
       ),
+      // address of global allocation
+      ValueKind::GlobalAlloc(_) => self
+        .vars
+        .get(&(value as *const ValueData))
+        .cloned()
+        .ok_or_else(|| new_error("global variable referenced before definition"))?,
+      // constant pointer calculation
+      ValueKind::GetPtr(v) => Self::get_pointer(
+        self.eval_global_const(&program.borrow_value(v.src()))?,
+        self.eval_global_offset(v.index()),
+        Self::base_size(value.ty())?,
+      )?,
+      ValueKind::GetElemPtr(v) => Self::get_elem_pointer(
+        self.eval_global_const(&program.borrow_value(v.src()))?,
+        self.eval_global_offset(v.index()),
+        Self::base_size(value.ty())?,
+      )?,
       _ => panic!("invalid constant"),
+    })
+  }
+
+  fn eval_global_offset(&self, index: Value) -> isize {
+    match self.program.borrow_value(index).kind() {
+      ValueKind::Integer(i) => i.value() as isize,
+      _ => panic!("invalid index"),
+    }
+  }
+
+  fn base_size(ty: &Type) -> Result<usize> {
+    match ty.kind() {
+      TypeKind::Pointer(base) => Self::type_size(base),
+      _ => panic!("invalid pointer"),
     }
   }
 
-  fn eval_local_const(&self, value: &ValueData) -> Val {
+  fn type_size(ty: &Type) -> Result<usize> {
+    ty.try_size()
+      .ok_or_else(|| new_error(&format!("size of type '{}' is unknown", ty)))
+  }
+
+  fn eval_local_const(dfg: &DataFlowGraph, value: &ValueData, undefs: &mut Undefs) -> Val {
     match value.kind() {
       ValueKind::Integer(v) => Val::Int(v.value()),
       ValueKind::ZeroInit(_) => Self::new_zeroinit(value.ty()),
-      ValueKind::Undef(_) => Val::Undef,
+      ValueKind::Undef(_) => undefs.eval(value),
       ValueKind::Aggregate(v) => Val::Array(
         v.elems()
           .iter()
-          .map(|e| self.eval_local_const(func!(self).dfg().value(*e)))
+          .map(|e| Self::eval_local_const(dfg, dfg.value(*e), undefs))
           .collect(),
       ),
       _ => panic!("invalid constant"),
@@ -149,20 +587,23 @@ impl<'a> InterpreterImpl<'a> {
     }
   }
 
+  /// Performs `getptr`: advances `src` by `offset` objects of its own
+  /// pointee type, `base_size` is the size of that type.
   fn get_pointer(src: Val, offset: isize, base_size: usize) -> Result<Val> {
     match src {
       Val::Pointer { ptr, index, len } => {
-        let new_index = (index as isize + offset) as usize;
-        (new_index < len)
+        let new_index = index as isize + offset;
+        (0..len as isize)
+          .contains(&new_index)
           .then(|| Val::Pointer {
             ptr: ptr.map(|p| unsafe { NonNull::new_unchecked(p.as_ptr().offset(offset)) }),
-            index: new_index,
+            index: new_index as usize,
             len,
           })
           .ok_or_else(|| {
             new_error(&format!(
               "pointer calculation out of bounds with index {} and length {}",
-              index, len
+              new_index, len
             ))
           })
       }
@@ -173,7 +614,109 @@ impl<'a> InterpreterImpl<'a> {
     }
   }
 
+  /// Performs `getelemptr`: indexes the first dimension of the array
+  /// pointed to by `src`, `base_size` is the size of the array element.
+  fn get_elem_pointer(src: Val, offset: isize, base_size: usize) -> Result<Val> {
+    match src {
+      Val::Pointer { ptr, .. } => ptr
+        .map(|p| match unsafe { p.as_ref() } {
+          Val::Array(arr) => Self::get_pointer(Val::new_array_pointer(arr), offset, base_size),
+          _ => panic!("invalid array"),
+        })
+        .ok_or_else(|| new_error("accessing to null pointer"))?,
+      Val::UnsafePointer(ptr) => Ok(Val::UnsafePointer(ptr.map(|p| unsafe {
+        NonNull::new_unchecked((p.as_ptr() as isize + base_size as isize * offset) as *mut ())
+      }))),
+      _ => panic!("invalid pointer"),
+    }
+  }
+
   fn eval_func(&mut self, func: &'a FunctionData, args: Vec<Val>) -> Result<Val> {
+    // environments below the current depth belong to the callers
+    let depth = self.envs.len();
+    if let Some(ret) = self.enter_func(func, args)? {
+      return Ok(ret);
+    }
+    // evaluate instructions until the function returns,
+    // calls are evaluated on the environment stack instead of recursion
+    loop {
+      let (value, inst) = self.envs.last_mut().unwrap().next_inst();
+      self.step().map_err(|e| self.locate_error(value, e))?;
+      match inst.kind() {
+        ValueKind::Return(v) => {
+          let ret = self.eval_return(v);
+          self.envs.pop();
+          if self.envs.len() == depth {
+            return Ok(ret);
+          }
+          // pass the return value to the caller
+          let env = self.envs.last_mut().unwrap();
+          let call = env.call.take().unwrap();
+          env.vals[call] = ret;
+        }
+        _ => self
+          .eval_inst(inst)
+          .map_err(|e| self.locate_error(value, e))?,
+      }
+    }
+  }
+
+  /// Counts the evaluation of an instruction, returns an error if the
+  /// step limit is exceeded.
+  fn step(&mut self) -> Result<()> {
+    self.steps += 1;
+    match self.step_limit {
+      Some(limit) if self.steps > limit => {
+        Err(new_error(&format!("step limit of {} exceeded", limit)))
+      }
+      _ => Ok(()),
+    }
+  }
+
+  /// Evaluates the given non-return instruction.
+  fn eval_inst(&mut self, inst: &'a ValueData) -> Result<()> {
+    match inst.kind() {
+      ValueKind::Alloc(_) => self.eval_alloc(inst),
+      ValueKind::Load(v) => self.eval_load(inst, v)?,
+      ValueKind::Store(v) => self.eval_store(v)?,
+      ValueKind::GetPtr(v) => self.eval_getptr(inst, v)?,
+      ValueKind::GetElemPtr(v) => self.eval_getelemptr(inst, v)?,
+      ValueKind::ExtractValue(v) => self.eval_extract_value(v),
+      ValueKind::InsertValue(v) => self.eval_insert_value(v),
+      ValueKind::Copy(v) => self.eval_copy(v),
+      ValueKind::Binary(v) => self.eval_binary(v)?,
+      ValueKind::Unary(v) => self.eval_unary(v),
+      ValueKind::CheckedBinary(v) => self.eval_checked_binary(v),
+      ValueKind::Call(v) => self.eval_call(v)?,
+      ValueKind::Assume(v) => self.eval_assume(v)?,
+      ValueKind::Branch(v) => self.eval_branch(v),
+      ValueKind::Jump(v) => self.eval_jump(v),
+      ValueKind::Switch(v) => self.eval_switch(v),
+      ValueKind::Unreachable(_) => return Err(new_error("executed unreachable instruction")),
+      _ => panic!("invalid instruction"),
+    }
+    Ok(())
+  }
+
+  /// Adds the location of the given instruction to the given error.
+  ///
+  /// The location is the source span of the instruction if recorded,
+  /// otherwise the name or the handle of the instruction.
+  fn locate_error(&self, inst: Value, error: Error) -> Error {
+    let func = func!(self);
+    let location = match (func.span(inst), func.dfg().value(inst).name()) {
+      (Some(span), _) => span.to_string(),
+      (None, Some(name)) => format!("{} in {}", name, func.name()),
+      (None, None) => format!("{:?} in {}", inst, func.name()),
+    };
+    new_error(&format!("{}: {}", location, error))
+  }
+
+  /// Enters the given function with the given arguments.
+  ///
+  /// Returns the return value if the function is a function declaration,
+  /// otherwise pushes a new environment and returns `None`.
+  fn enter_func(&mut self, func: &'a FunctionData, args: Vec<Val>) -> Result<Option<Val>> {
     // check parameter count
     let param_len = match func.ty().kind() {
       TypeKind::Function(params, _) => params.len(),
@@ -181,46 +724,122 @@ impl<'a> InterpreterImpl<'a> {
     };
     assert_eq!(param_len, args.len(), "parameter count mismatch");
     // check if is a function declaration
-    if let Some(bb) = func.layout().bbs().front_node() {
+    if func.layout().entry_bb().is_some() {
       // setup the environment
-      self.envs.push(Environment::new(
-        func,
-        func
-          .params()
-          .iter()
-          .map(|p| func.dfg().value(*p) as *const ValueData)
-          .zip(args.into_iter())
-          .collect(),
-      ));
-      // evaluate the entry basic block
-      let ret = self.eval_bb(bb);
-      self.envs.pop();
-      ret
+      let func = self.compile(func);
+      self.envs.push(Environment::new(func, args));
+      Ok(None)
+    } else if let Some(ret) = self.call_intrinsic(func, &args) {
+      ret.map(Some)
     } else {
-      // call the external function
-      unsafe { self.ext_funcs.call(func, args) }
+      // call the external function, and record the call
+      let int = |v: &Val| match v {
+        Val::Int(i) => Some(*i),
+        _ => None,
+      };
+      let mut call = ExtCall {
+        name: func.name().into(),
+        args: args.iter().map(int).collect(),
+        ret: None,
+      };
+      let ret = unsafe { self.ext_funcs.call(func, args) };
+      call.ret = ret.as_ref().ok().and_then(int);
+      self.ext_calls.push(call);
+      ret.map(Some)
     }
   }
 
-  fn eval_bb(&mut self, bb: &BasicBlockNode) -> Result<Val> {
-    // evaluate on all instructions
-    for inst in bb.insts().keys() {
-      let inst = func!(self).dfg().value(*inst);
-      match inst.kind() {
-        ValueKind::Alloc(_) => self.eval_alloc(inst),
-        ValueKind::Load(v) => self.eval_load(inst, v)?,
-        ValueKind::Store(v) => self.eval_store(v)?,
-        ValueKind::GetPtr(v) => self.eval_getptr(inst, v)?,
-        ValueKind::GetElemPtr(v) => self.eval_getelemptr(inst, v)?,
-        ValueKind::Binary(v) => self.eval_binary(inst, v),
-        ValueKind::Call(v) => self.eval_call(inst, v)?,
-        ValueKind::Branch(v) => return self.eval_branch(v),
-        ValueKind::Jump(v) => return self.eval_jump(v),
-        ValueKind::Return(v) => return Ok(self.eval_return(v)),
-        _ => panic!("invalid instruction"),
+  /// Returns the compiled form of the given function definition, which
+  /// is compiled on the first call.
+  fn compile(&mut self, func: &'a FunctionData) -> Rc<CompiledFunc<'a>> {
+    let program = self.program;
+    let undefs = &mut self.undefs;
+    let compiled = self.compiled.entry(func);
+    compiled
+      .or_insert_with(|| Rc::new(CompiledFunc::new(program, func, undefs)))
+      .clone()
+  }
+
+  /// Calls the given function declaration if it is an intrinsic,
+  /// returns `None` if it is not.
+  ///
+  /// Intrinsics are `@malloc(i32): *T` and `@free(*T)`, which manage
+  /// memory owned by the interpreter rather than by external libraries.
+  fn call_intrinsic(&mut self, func: &FunctionData, args: &[Val]) -> Option<Result<Val>> {
+    let (params, ret) = match func.ty().kind() {
+      TypeKind::Function(params, ret) => (params, ret),
+      _ => panic!("invalid function"),
+    };
+    match (func.name(), params.as_slice(), ret.kind()) {
+      ("@malloc", [size], TypeKind::Pointer(base)) if size.is_i32() => {
+        Some(self.eval_malloc(&args[0], base))
+      }
+      ("@free", [ptr], TypeKind::Unit) if matches!(ptr.kind(), TypeKind::Pointer(_)) => {
+        Some(self.eval_free(&args[0]).map(|_| Val::Undef))
       }
+      _ => None,
     }
-    unreachable!()
+  }
+
+  /// Allocates `size` bytes of zero-initialized heap memory for objects
+  /// of type `base`, returns a pointer to the first object.
+  ///
+  /// Returns a null pointer if `size` is zero.
+  fn eval_malloc(&mut self, size: &Val, base: &Type) -> Result<Val> {
+    let size = match size {
+      Val::Int(i) if *i >= 0 => *i as usize,
+      Val::Int(i) => return Err(new_error(&format!("invalid allocation size {}", i))),
+      _ => return Err(new_error("invalid allocation size")),
+    };
+    if size == 0 {
+      return Ok(Val::new_val_pointer(None));
+    }
+    let base_size = Self::type_size(base)?;
+    if base_size == 0 {
+      return Err(new_error(&format!(
+        "can not allocate objects of zero-sized type '{}'",
+        base
+      )));
+    }
+    if size % base_size != 0 {
+      return Err(new_error(&format!(
+        "allocation size {} is not a multiple of the size of '{}'",
+        size, base
+      )));
+    }
+    Ok(self.heap.alloc(base, size / base_size))
+  }
+
+  /// Frees the heap memory pointed to by `ptr`, does nothing if `ptr`
+  /// is null.
+  ///
+  /// The memory is poisoned rather than released, so that any later
+  /// access through a dangling pointer can be reported until the block
+  /// is reused, see [`Heap`].
+  fn eval_free(&mut self, ptr: &Val) -> Result<()> {
+    let p = match ptr {
+      Val::Pointer { ptr: None, .. } | Val::UnsafePointer(None) => return Ok(()),
+      Val::Pointer {
+        ptr: Some(p),
+        index: 0,
+        ..
+      } => p.as_ptr() as *const Val,
+      _ => return Err(new_error("freeing a pointer not returned by '@malloc'")),
+    };
+    self.heap.free(p)
+  }
+
+  /// Enters the given basic block with the given arguments.
+  fn enter_bb(&mut self, bb: BasicBlock, args: &[Value]) {
+    // evaluate all arguments before updating any parameters
+    let args: Vec<_> = args.iter().map(|a| self.eval_value(*a)).collect();
+    let env = self.envs.last_mut().unwrap();
+    let index = env.cur_inst().target(bb);
+    let func = env.func.clone();
+    for (param, arg) in func.bbs[index].params.iter().zip(args) {
+      env.vals[*param] = arg;
+    }
+    env.enter_bb(index);
   }
 
   fn eval_alloc(&mut self, inst: &ValueData) {
@@ -230,9 +849,8 @@ impl<'a> InterpreterImpl<'a> {
     };
     let env = self.envs.last_mut().unwrap();
     env.allocs.push(Box::new(Self::new_zeroinit(base)));
-    env
-      .vals
-      .insert(inst, Val::new_val_pointer(env.allocs.last()));
+    let ptr = Val::new_val_pointer(env.allocs.last());
+    self.insert_val(ptr);
   }
 
   fn eval_load(&mut self, inst: &ValueData, load: &Load) -> Result<()> {
@@ -242,16 +860,27 @@ impl<'a> InterpreterImpl<'a> {
       _ => panic!("invalid pointer"),
     }
     .ok_or_else(|| new_error("accessing to null pointer"))?;
-    self.insert_val(inst, val);
+    if val.is_freed() {
+      return Err(new_error("use after free"));
+    }
+    self.insert_val(val);
     Ok(())
   }
 
   fn eval_store(&self, store: &Store) -> Result<()> {
     let val = self.eval_value(store.value());
     match self.eval_value(store.dest()) {
-      Val::Pointer { ptr, .. } => ptr
-        .map(|p| unsafe { *p.as_ptr() = val })
-        .ok_or_else(|| new_error("accessing to null pointer")),
+      Val::Pointer { ptr, .. } => {
+        let p = ptr.ok_or_else(|| new_error("accessing to null pointer"))?;
+        if unsafe { p.as_ref() }.is_freed() {
+          return Err(new_error("use after free"));
+        }
+        if self.readonly.contains(&(p.as_ptr() as *const Val)) {
+          return Err(new_error("writing to read-only global variable"));
+        }
+        unsafe { *p.as_ptr() = val };
+        Ok(())
+      }
       Val::UnsafePointer(ptr) => val.store_to_unsafe_ptr(ptr, value!(self, store.value()).ty()),
       _ => panic!("invalid pointer"),
     }
@@ -264,12 +893,9 @@ impl<'a> InterpreterImpl<'a> {
       _ => panic!("invalid index"),
     };
     // perform pointer calculation
-    let base_size = match inst.ty().kind() {
-      TypeKind::Pointer(base) => base.size(),
-      _ => panic!("invalid pointer"),
-    };
+    let base_size = Self::base_size(inst.ty())?;
     let ptr = Self::get_pointer(self.eval_value(gp.src()), offset, base_size)?;
-    self.insert_val(inst, ptr);
+    self.insert_val(ptr);
     Ok(())
   }
 
@@ -280,27 +906,37 @@ impl<'a> InterpreterImpl<'a> {
       _ => panic!("invalid index"),
     };
     // perform pointer calculation
-    let base_size = match inst.ty().kind() {
-      TypeKind::Pointer(base) => base.size(),
-      _ => panic!("invalid pointer"),
+    let base_size = Self::base_size(inst.ty())?;
+    let ptr = Self::get_elem_pointer(self.eval_value(gep.src()), offset, base_size)?;
+    self.insert_val(ptr);
+    Ok(())
+  }
+
+  fn eval_extract_value(&mut self, ev: &ExtractValue) {
+    let elem = match self.eval_value(ev.agg()) {
+      Val::Array(arr) => arr[ev.index()].clone(),
+      _ => panic!("invalid aggregate"),
     };
-    let ptr = match self.eval_value(gep.src()) {
-      Val::Pointer { ptr, .. } => ptr
-        .map(|p| match unsafe { p.as_ref() } {
-          Val::Array(arr) => Self::get_pointer(Val::new_array_pointer(arr), offset, base_size),
-          _ => panic!("invalid array"),
-        })
-        .ok_or_else(|| new_error("accessing to null pointer"))??,
-      Val::UnsafePointer(ptr) => Val::UnsafePointer(ptr.map(|p| unsafe {
-        NonNull::new_unchecked((p.as_ptr() as isize + base_size as isize * offset) as *mut ())
-      })),
-      _ => panic!("invalid pointer"),
+    self.insert_val(elem);
+  }
+
+  fn eval_insert_value(&mut self, iv: &InsertValue) {
+    let agg = match self.eval_value(iv.agg()) {
+      Val::Array(mut arr) => {
+        arr[iv.index()] = self.eval_value(iv.value());
+        Val::Array(arr)
+      }
+      _ => panic!("invalid aggregate"),
     };
-    self.insert_val(inst, ptr);
-    Ok(())
+    self.insert_val(agg);
   }
 
-  fn eval_binary(&mut self, inst: &ValueData, bin: &Binary) {
+  fn eval_copy(&mut self, copy: &CopyValue) {
+    let val = self.eval_value(copy.src());
+    self.insert_val(val);
+  }
+
+  fn eval_binary(&mut self, bin: &Binary) -> Result<()> {
     // evaluate lhs & rhs
     let lhs = self.eval_value(bin.lhs());
     let rhs = self.eval_value(bin.rhs());
@@ -316,11 +952,18 @@ impl<'a> InterpreterImpl<'a> {
       BinaryOp::Lt => (lv < rv) as i32,
       BinaryOp::Ge => (lv >= rv) as i32,
       BinaryOp::Le => (lv <= rv) as i32,
-      BinaryOp::Add => lv + rv,
-      BinaryOp::Sub => lv - rv,
-      BinaryOp::Mul => lv * rv,
-      BinaryOp::Div => lv / rv,
-      BinaryOp::Mod => lv % rv,
+      BinaryOp::Add => lv.wrapping_add(rv),
+      BinaryOp::Sub => lv.wrapping_sub(rv),
+      BinaryOp::Mul => lv.wrapping_mul(rv),
+      BinaryOp::Div | BinaryOp::Mod => {
+        let (q, r) =
+          (self.div_mode.div_rem(lv, rv)).ok_or_else(|| new_error("division by zero"))?;
+        if bin.op() == BinaryOp::Div {
+          q
+        } else {
+          r
+        }
+      }
       BinaryOp::And => lv & rv,
       BinaryOp::Or => lv | rv,
       BinaryOp::Xor => lv ^ rv,
@@ -328,90 +971,483 @@ impl<'a> InterpreterImpl<'a> {
       BinaryOp::Shr => ((lv as u32) >> rv) as i32,
       BinaryOp::Sar => lv >> rv,
     };
-    self.insert_val(inst, Val::Int(ans));
+    self.insert_val(Val::Int(ans));
+    Ok(())
   }
 
-  fn eval_call(&mut self, inst: &ValueData, call: &Call) -> Result<()> {
+  fn eval_unary(&mut self, unary: &Unary) {
+    let opr = match self.eval_value(unary.opr()) {
+      Val::Int(v) => v,
+      _ => panic!("invalid operand"),
+    };
+    self.insert_val(Val::Int(unary.eval(opr)));
+  }
+
+  fn eval_checked_binary(&mut self, bin: &CheckedBinary) {
+    // evaluate lhs & rhs
+    let lhs = self.eval_value(bin.lhs());
+    let rhs = self.eval_value(bin.rhs());
+    let (lv, rv) = match (lhs, rhs) {
+      (Val::Int(lv), Val::Int(rv)) => (lv, rv),
+      _ => panic!("invalid lhs or rhs"),
+    };
+    // perform the operation, produce the result and the overflow flag
+    let (ans, overflow) = bin.eval(lv, rv);
+    let arr = vec![Val::Int(ans), Val::Int(overflow as i32)].into_boxed_slice();
+    self.insert_val(Val::Array(arr));
+  }
+
+  fn eval_call(&mut self, call: &Call) -> Result<()> {
     // evaluate arguments
     let args = call.args().iter().map(|u| self.eval_value(*u)).collect();
     // perform function call
-    let ret = self.eval_func(self.program.func(call.callee()), args)?;
-    self.insert_val(inst, ret);
+    match self.enter_func(self.program.func(call.callee()), args)? {
+      Some(ret) => self.insert_val(ret),
+      // the return value will be inserted when the callee returns
+      None => {
+        let caller = self.envs.len() - 2;
+        let caller = &mut self.envs[caller];
+        caller.call = Some(caller.cur_inst().slot);
+      }
+    }
     Ok(())
   }
 
-  fn eval_branch(&mut self, br: &Branch) -> Result<Val> {
+  fn eval_assume(&self, assume: &Assume) -> Result<()> {
+    if self.eval_value(assume.cond()).as_bool() {
+      Ok(())
+    } else {
+      Err(new_error("assumption violated"))
+    }
+  }
+
+  fn eval_branch(&mut self, br: &Branch) {
     // evaluate on condition
     let cond = self.eval_value(br.cond());
     // perform branching
     if cond.as_bool() {
-      self.update_bb_params(br.true_bb(), br.true_args());
-      self.eval_bb(bb_node!(self, br.true_bb()))
+      self.enter_bb(br.true_bb(), br.true_args());
     } else {
-      self.update_bb_params(br.false_bb(), br.false_args());
-      self.eval_bb(bb_node!(self, br.false_bb()))
+      self.enter_bb(br.false_bb(), br.false_args());
     }
   }
 
-  fn eval_jump(&mut self, jump: &Jump) -> Result<Val> {
-    self.update_bb_params(jump.target(), jump.args());
-    self.eval_bb(bb_node!(self, jump.target()))
+  fn eval_jump(&mut self, jump: &Jump) {
+    self.enter_bb(jump.target(), jump.args());
+  }
+
+  fn eval_switch(&mut self, switch: &Switch) {
+    // evaluate on selector
+    let cond = match self.eval_value(switch.cond()) {
+      Val::Int(i) => i,
+      _ => panic!("invalid selector"),
+    };
+    // perform branching
+    let (target, args) = switch.target_of(cond);
+    self.enter_bb(target, args);
   }
 
   fn eval_return(&self, ret: &Return) -> Val {
     ret.value().map_or(Val::Undef, |v| self.eval_value(v))
   }
 
+  /// Evaluates the given operand of the current instruction.
   fn eval_value(&self, value: Value) -> Val {
-    if value.is_global() {
-      let value = self.program.borrow_value(value);
-      assert!(!value.kind().is_const());
-      let v: *const ValueData = &value as &ValueData;
-      self.vars.get(&v).unwrap().clone()
-    } else {
-      let value = value!(self, value);
-      if value.kind().is_const() {
-        self.eval_local_const(value)
-      } else {
-        let v: *const ValueData = value;
-        self
-          .vars
-          .get(&v)
-          .or_else(|| self.envs.last().unwrap().vals.get(&v))
-          .unwrap()
-          .clone()
+    let env = self.envs.last().unwrap();
+    match env.cur_inst().operand(value) {
+      Operand::Slot(slot) => env.vals[*slot].clone(),
+      Operand::Const(val) => val.clone(),
+      Operand::Global(v) => self.vars.get(v).unwrap().clone(),
+    }
+  }
+
+  /// Sets the result of the current instruction.
+  fn insert_val(&mut self, val: Val) {
+    let env = self.envs.last_mut().unwrap();
+    let slot = env.cur_inst().slot;
+    env.vals[slot] = val;
+  }
+}
+
+/// Values of `undef`s, evaluated by the given [`UndefMode`].
+struct Undefs {
+  mode: UndefMode,
+  // state of the random number generator
+  state: u64,
+  // frozen values of all evaluated `undef`s
+  frozen: HashMap<*const ValueData, Val>,
+}
+
+impl Undefs {
+  fn new(mode: UndefMode) -> Self {
+    let state = match mode {
+      UndefMode::Undefined => 0,
+      UndefMode::Freeze(seed) => seed,
+    };
+    Self {
+      mode,
+      state,
+      frozen: HashMap::new(),
+    }
+  }
+
+  /// Evaluates the given `undef`.
+  fn eval(&mut self, undef: &ValueData) -> Val {
+    if self.mode == UndefMode::Undefined {
+      return Val::Undef;
+    }
+    if let Some(val) = self.frozen.get(&(undef as *const ValueData)) {
+      return val.clone();
+    }
+    let val = self.freeze(undef.ty());
+    self.frozen.insert(undef, val.clone());
+    val
+  }
+
+  /// Generates a fixed value of the given type.
+  fn freeze(&mut self, ty: &Type) -> Val {
+    match ty.kind() {
+      TypeKind::Int32 => Val::Int(self.next() as i32),
+      TypeKind::Array(base, len) => Val::Array((0..*len).map(|_| self.freeze(base)).collect()),
+      TypeKind::Pointer(_) => Val::new_val_pointer(None),
+      _ => Val::Undef,
+    }
+  }
+
+  /// Returns the next random number (SplitMix64).
+  fn next(&mut self) -> u64 {
+    self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = self.state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+  }
+}
+
+/// Heap memory allocated by `@malloc`.
+///
+/// Blocks are keyed by the address of their first object. Freed blocks
+/// are poisoned and kept alive to detect use-after-free, and are reused
+/// by later allocations of the same type and length. So the heap only
+/// grows to the peak number of live blocks of each shape, but accesses
+/// through a dangling pointer are not reported after its block is
+/// reused.
+#[derive(Default)]
+struct Heap {
+  blocks: HashMap<*const Val, HeapBlock>,
+  // freed blocks, grouped by their object type and length
+  free: HashMap<(Type, usize), Vec<*const Val>>,
+}
+
+impl Heap {
+  /// Allocates a zero-initialized block of `len` objects of type `base`,
+  /// returns a pointer to the first object.
+  fn alloc(&mut self, base: &Type, len: usize) -> Val {
+    let block = match self.free.get_mut(&(base.clone(), len)).and_then(Vec::pop) {
+      Some(p) => {
+        let block = self.blocks.get_mut(&p).unwrap();
+        block.data.iter_mut().for_each(|v| v.zeroize(base));
+        block.freed = false;
+        block
+      }
+      None => {
+        let data: Box<[Val]> = (0..len)
+          .map(|_| InterpreterImpl::new_zeroinit(base))
+          .collect();
+        self.blocks.entry(data.as_ptr()).or_insert(HeapBlock {
+          data,
+          base: base.clone(),
+          freed: false,
+        })
       }
+    };
+    Val::new_array_pointer(&block.data)
+  }
+
+  /// Frees the block whose first object is at the given address.
+  fn free(&mut self, p: *const Val) -> Result<()> {
+    let block = self
+      .blocks
+      .get_mut(&p)
+      .ok_or_else(|| new_error("freeing a pointer not returned by '@malloc'"))?;
+    if block.freed {
+      return Err(new_error("double free"));
     }
+    block.freed = true;
+    block.data.iter_mut().for_each(Val::poison);
+    let key = (block.base.clone(), block.data.len());
+    self.free.entry(key).or_default().push(p);
+    Ok(())
   }
 
-  fn insert_val(&mut self, inst: &ValueData, val: Val) {
-    self.envs.last_mut().unwrap().vals.insert(inst, val);
+  /// Returns a deep copy of the heap, records the new address of each
+  /// value in the copy.
+  fn copy(&self, addrs: &mut HashMap<*const Val, NonNull<Val>>) -> Self {
+    let mut keys = HashMap::new();
+    let blocks = self
+      .blocks
+      .iter()
+      .map(|(p, block)| {
+        let copy = block.clone();
+        for (v, c) in block.data.iter().zip(copy.data.iter()) {
+          Val::map_addrs(v, c, addrs);
+        }
+        keys.insert(*p, copy.data.as_ptr());
+        (copy.data.as_ptr(), copy)
+      })
+      .collect();
+    let free = self
+      .free
+      .iter()
+      .map(|(k, ps)| (k.clone(), ps.iter().map(|p| keys[p]).collect()))
+      .collect();
+    Self { blocks, free }
   }
+}
 
-  fn update_bb_params(&mut self, bb: BasicBlock, args: &[Value]) {
-    let bb = func!(self).dfg().bb(bb);
-    for (param, arg) in bb.params().iter().zip(args.iter()) {
-      self.insert_val(func!(self).dfg().value(*param), self.eval_value(*arg));
+/// A block of heap memory allocated by `@malloc`.
+#[derive(Clone)]
+struct HeapBlock {
+  data: Box<[Val]>,
+  base: Type,
+  freed: bool,
+}
+
+/// A function definition compiled for evaluation.
+///
+/// Each value that holds a runtime value (function parameters, basic
+/// block parameters and instructions) is assigned a dense slot index,
+/// so values in an environment are stored in a `Vec` indexed by slots.
+/// Operands and target basic blocks of instructions are also resolved
+/// when compiling, so evaluating instructions does not look up handles
+/// in hash maps, except for global allocations.
+struct CompiledFunc<'a> {
+  func: &'a FunctionData,
+  /// Number of slots, function parameters take the first slots.
+  slots: usize,
+  /// Basic blocks in layout order, the entry basic block comes first.
+  bbs: Vec<CompiledBlock<'a>>,
+}
+
+struct CompiledBlock<'a> {
+  /// Slots of basic block parameters.
+  params: Vec<usize>,
+  insts: Vec<CompiledInst<'a>>,
+}
+
+struct CompiledInst<'a> {
+  value: Value,
+  data: &'a ValueData,
+  /// Slot of the result.
+  slot: usize,
+  /// Operands, which are usually only a few, so they are searched
+  /// linearly.
+  operands: Vec<(Value, Operand)>,
+  /// Target basic blocks and their indices in [`CompiledFunc::bbs`].
+  targets: Vec<(BasicBlock, usize)>,
+}
+
+enum Operand {
+  /// Value in the given slot.
+  Slot(usize),
+  /// Local constant.
+  Const(Val),
+  /// Global allocation.
+  Global(*const ValueData),
+}
+
+impl<'a> CompiledFunc<'a> {
+  fn new(program: &Program, func: &'a FunctionData, undefs: &mut Undefs) -> Self {
+    let dfg = func.dfg();
+    let layout = func.layout().bbs();
+    // assign slots
+    let mut slots = HashMap::new();
+    let values = layout.iter().flat_map(|(bb, node)| {
+      let params = dfg.bb(*bb).params().iter().copied();
+      params.chain(node.insts().keys().copied())
+    });
+    for value in func.params().iter().copied().chain(values) {
+      let slot = slots.len();
+      slots.insert(value, slot);
+    }
+    let indices: HashMap<_, _> = layout.keys().enumerate().map(|(i, bb)| (*bb, i)).collect();
+    // compile instructions
+    let mut operand = |v: Value| {
+      if v.is_global() {
+        Operand::Global(&*program.borrow_value(v) as *const ValueData)
+      } else {
+        let data = dfg.value(v);
+        if data.kind().is_const() {
+          Operand::Const(InterpreterImpl::eval_local_const(dfg, data, undefs))
+        } else {
+          Operand::Slot(slots[&v])
+        }
+      }
+    };
+    let bbs = layout
+      .iter()
+      .map(|(bb, node)| CompiledBlock {
+        params: dfg.bb(*bb).params().iter().map(|p| slots[p]).collect(),
+        insts: (node.insts().keys())
+          .map(|&inst| {
+            let data = dfg.value(inst);
+            CompiledInst {
+              value: inst,
+              data,
+              slot: slots[&inst],
+              operands: data.kind().value_uses().map(|v| (v, operand(v))).collect(),
+              targets: data.kind().bb_uses().map(|b| (b, indices[&b])).collect(),
+            }
+          })
+          .collect(),
+      })
+      .collect();
+    Self {
+      func,
+      slots: slots.len(),
+      bbs,
     }
   }
 }
 
+impl CompiledInst<'_> {
+  /// Returns the given operand.
+  fn operand(&self, value: Value) -> &Operand {
+    let operand = self.operands.iter().find(|(v, _)| *v == value);
+    &operand.expect("invalid operand").1
+  }
+
+  /// Returns the index of the given target basic block.
+  fn target(&self, bb: BasicBlock) -> usize {
+    let target = self.targets.iter().find(|(b, _)| *b == bb);
+    target.expect("invalid target").1
+  }
+}
+
+#[derive(Clone)]
 struct Environment<'a> {
-  func: &'a FunctionData,
+  func: Rc<CompiledFunc<'a>>,
   allocs: Vec<Box<Val>>,
-  vals: HashMap<*const ValueData, Val>,
+  vals: Vec<Val>,
+  // index of the current basic block
+  bb: usize,
+  // index of the next instruction to be evaluated in the current basic block
+  next: usize,
+  // slot of the call instruction that is waiting for the callee to return
+  call: Option<usize>,
 }
 
 impl<'a> Environment<'a> {
-  fn new(func: &'a FunctionData, vals: HashMap<*const ValueData, Val>) -> Self {
+  fn new(func: Rc<CompiledFunc<'a>>, args: Vec<Val>) -> Self {
+    let mut vals = vec![Val::Undef; func.slots];
+    for (val, arg) in vals.iter_mut().zip(args) {
+      *val = arg;
+    }
     Self {
       func,
       allocs: Vec::new(),
       vals,
+      bb: 0,
+      next: 0,
+      call: None,
+    }
+  }
+
+  /// Moves to the beginning of the given basic block.
+  fn enter_bb(&mut self, bb: usize) {
+    self.bb = bb;
+    self.next = 0;
+  }
+
+  /// Returns the next instruction, and moves to the instruction after it.
+  fn next_inst(&mut self) -> (Value, &'a ValueData) {
+    let inst = self.func.bbs[self.bb]
+      .insts
+      .get(self.next)
+      .expect("missing terminator");
+    self.next += 1;
+    (inst.value, inst.data)
+  }
+
+  /// Returns the instruction being evaluated.
+  fn cur_inst(&self) -> &CompiledInst<'a> {
+    &self.func.bbs[self.bb].insts[self.next - 1]
+  }
+}
+
+/// A snapshot of the mutable state of the interpreter, used by debuggers
+/// to roll back the execution.
+///
+/// The snapshot is a deep copy of all global and local allocations,
+/// values of instructions and parameters in all active functions, and
+/// the call stack. So it costs about as much memory as the state itself:
+/// one [`Val`] for each scalar and each array element in allocations
+/// (including heap blocks, freed or not),
+/// and one for each evaluated value of each active function. Pointers
+/// are relocated to the copied allocations. Memory owned by external
+/// functions is not part of the snapshot.
+pub struct InterpState<'a> {
+  global_allocs: Vec<Box<Val>>,
+  heap: Heap,
+  vars: HashMap<*const ValueData, Val>,
+  envs: Vec<Environment<'a>>,
+}
+
+impl<'a> InterpState<'a> {
+  fn copy_from(
+    global_allocs: &[Box<Val>],
+    heap: &Heap,
+    vars: &HashMap<*const ValueData, Val>,
+    envs: &[Environment<'a>],
+  ) -> Self {
+    // copy allocations, and record the new address of each value
+    let mut addrs = HashMap::new();
+    let mut copy_allocs = |allocs: &[Box<Val>]| -> Vec<Box<Val>> {
+      allocs
+        .iter()
+        .map(|alloc| {
+          let copy = alloc.clone();
+          Val::map_addrs(alloc, &copy, &mut addrs);
+          copy
+        })
+        .collect()
+    };
+    let mut global_allocs = copy_allocs(global_allocs);
+    let mut envs: Vec<_> = envs
+      .iter()
+      .map(|env| Environment {
+        allocs: copy_allocs(&env.allocs),
+        ..env.clone()
+      })
+      .collect();
+    let mut heap = heap.copy(&mut addrs);
+    let mut vars = vars.clone();
+    // relocate pointers to the copied allocations
+    let vals = global_allocs
+      .iter_mut()
+      .map(|a| a.as_mut())
+      .chain(heap.blocks.values_mut().flat_map(|b| b.data.iter_mut()))
+      .chain(vars.values_mut());
+    let vals = vals.chain(envs.iter_mut().flat_map(|env| {
+      let allocs = env.allocs.iter_mut().map(|a| a.as_mut());
+      allocs.chain(env.vals.iter_mut())
+    }));
+    vals.for_each(|v| v.relocate(&addrs));
+    Self {
+      global_allocs,
+      heap,
+      vars,
+      envs,
     }
   }
 }
 
+impl Clone for InterpState<'_> {
+  fn clone(&self) -> Self {
+    Self::copy_from(&self.global_allocs, &self.heap, &self.vars, &self.envs)
+  }
+}
+
 #[derive(Clone)]
 pub enum Val {
   Undef,
@@ -426,14 +1462,17 @@ pub enum Val {
     len: usize,
   },
   UnsafePointer(Option<NonNull<()>>),
+  // a scalar in heap memory that has been freed
+  Freed,
 }
 
 impl Val {
   fn new_val_pointer(parent: Option<&Box<Val>>) -> Self {
+    // a single object behaves like an array of length 1
     Self::Pointer {
       ptr: parent.map(|p| unsafe { NonNull::new_unchecked(p.as_ref() as *const Val as *mut Val) }),
       index: 0,
-      len: 0,
+      len: 1,
     }
   }
 
@@ -448,14 +1487,13 @@ impl Val {
   fn load_from_unsafe_ptr(ptr: Option<NonNull<()>>, ty: &Type) -> Option<Self> {
     ptr.map(|p| match ty.kind() {
       TypeKind::Int32 => Val::Int(unsafe { *(p.as_ptr() as *const i32) }),
-      TypeKind::Array(ty, len) => Val::Array(
+      TypeKind::Array(base, len) => Val::Array(
         (0..*len)
           .map(|i| {
+            let offset = ty.field_offset(i).unwrap();
             Val::load_from_unsafe_ptr(
-              Some(unsafe {
-                NonNull::new_unchecked((p.as_ptr() as usize + ty.size() * i) as *mut ())
-              }),
-              ty,
+              Some(unsafe { NonNull::new_unchecked((p.as_ptr() as usize + offset) as *mut ()) }),
+              base,
             )
             .unwrap()
           })
@@ -469,6 +1507,58 @@ impl Val {
     })
   }
 
+  /// Records the address of `copy` and its elements as the new address
+  /// of `val` and its elements.
+  fn map_addrs(val: &Val, copy: &Val, addrs: &mut HashMap<*const Val, NonNull<Val>>) {
+    addrs.insert(val, NonNull::from(copy));
+    if let (Val::Array(val), Val::Array(copy)) = (val, copy) {
+      for (v, c) in val.iter().zip(copy.iter()) {
+        Self::map_addrs(v, c, addrs);
+      }
+    }
+  }
+
+  /// Updates pointers in the current value by the given address map.
+  fn relocate(&mut self, addrs: &HashMap<*const Val, NonNull<Val>>) {
+    match self {
+      Val::Pointer { ptr: Some(p), .. } => {
+        if let Some(new) = addrs.get(&(p.as_ptr() as *const Val)) {
+          *p = *new;
+        }
+      }
+      Val::Array(arr) => arr.iter_mut().for_each(|v| v.relocate(addrs)),
+      _ => {}
+    }
+  }
+
+  /// Replaces all scalars in the current value with [`Val::Freed`],
+  /// keeps arrays so that pointers to their elements remain valid.
+  fn poison(&mut self) {
+    match self {
+      Val::Array(arr) => arr.iter_mut().for_each(Val::poison),
+      v => *v = Val::Freed,
+    }
+  }
+
+  /// Resets all scalars in the current value of the given type to zero,
+  /// keeps arrays so that pointers to their elements remain valid.
+  fn zeroize(&mut self, ty: &Type) {
+    match (self, ty.kind()) {
+      (Val::Array(arr), TypeKind::Array(base, _)) => arr.iter_mut().for_each(|v| v.zeroize(base)),
+      (v, TypeKind::Pointer(_)) => *v = Val::new_val_pointer(None),
+      (v, _) => *v = Val::Int(0),
+    }
+  }
+
+  /// Checks if the current value contains freed memory.
+  fn is_freed(&self) -> bool {
+    match self {
+      Val::Freed => true,
+      Val::Array(arr) => arr.iter().any(Val::is_freed),
+      _ => false,
+    }
+  }
+
   fn as_bool(&self) -> bool {
     matches!(self, Val::Int(i) if *i != 0)
   }
@@ -486,10 +1576,9 @@ impl Val {
             _ => panic!("invalid array type"),
           };
           arr.iter().enumerate().try_for_each(|(i, v)| {
+            let offset = ty.field_offset(i).unwrap();
             v.store_to_unsafe_ptr(
-              Some(unsafe {
-                NonNull::new_unchecked((p.as_ptr() as usize + base.size() * i) as *mut ())
-              }),
+              Some(unsafe { NonNull::new_unchecked((p.as_ptr() as usize + offset) as *mut ()) }),
               base,
             )
           })
@@ -503,3 +1592,776 @@ impl Val {
       .ok_or_else(|| new_error("accessing to null pointer"))?
   }
 }
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use koopa::back::Generator;
+  use koopa::front::Driver;
+  use koopa::ir::builder_traits::*;
+  use koopa::opt::{GepFolding, Linearize, Mem2Reg, Pass, PassManager};
+  use std::io::sink;
+
+  fn interpret(src: &str) -> Result<i32> {
+    let driver: Driver<_> = src.into();
+    let program = driver.generate_program().unwrap();
+    Generator::with_visitor(sink(), Interpreter::new(Vec::new())).generate_on(&program)
+  }
+
+  fn run_main(src: &str, args: &[i32]) -> Result<i32> {
+    let driver: Driver<_> = src.into();
+    let program = driver.generate_program().unwrap();
+    let args = args.iter().map(|a| Val::Int(*a)).collect();
+    Interpreter::new(Vec::new()).run_main(&program, args)
+  }
+
+  const MATRIX: &str = r#"
+global @a = alloc [[i32, 3], 4], {{0, 1, 2}, {3, 4, 5}, {6, 7, 8}, {9, 10, 11}}
+"#;
+
+  #[test]
+  fn getptr_and_getelemptr() {
+    // `getelemptr` narrows `*[[i32, 3], 4]` to `*[i32, 3]`,
+    // `getptr` then advances by whole rows
+    let src = format!(
+      r#"{MATRIX}
+fun @main(): i32 {{
+%entry:
+  %0 = getptr @a, 0
+  %row2 = getelemptr %0, 2
+  %row3 = getptr %row2, 1
+  %1 = getelemptr %row3, 1
+  %2 = load %1
+  %3 = getelemptr %row2, 0
+  %4 = getptr %3, 2
+  %5 = load %4
+  %6 = getptr %4, -2
+  %7 = load %6
+  %8 = mul %2, 100
+  %9 = mul %5, 10
+  %10 = add %8, %9
+  %11 = add %10, %7
+  ret %11
+}}
+"#
+    );
+    assert_eq!(interpret(&src).unwrap(), 1086);
+  }
+
+  #[test]
+  fn getptr_out_of_bounds() {
+    // the global allocation `@a` points to a single `[[i32, 3], 4]`,
+    // so `getptr @a, 1` points past its end
+    let src = format!(
+      r#"{MATRIX}
+fun @main(): i32 {{
+%entry:
+  %0 = getptr @a, 1
+  %1 = getelemptr %0, 0
+  %2 = getelemptr %1, 0
+  %3 = load %2
+  ret %3
+}}
+"#
+    );
+    assert!(interpret(&src).is_err());
+    let src = format!(
+      r#"{MATRIX}
+fun @main(): i32 {{
+%entry:
+  %0 = getelemptr @a, 3
+  %1 = getelemptr %0, 3
+  %2 = load %1
+  ret %2
+}}
+"#
+    );
+    assert!(interpret(&src).is_err());
+  }
+
+  #[test]
+  fn unary_and_bitwise() {
+    let src = r#"
+fun @main(): i32 {
+%entry:
+  %0 = not 5
+  %1 = neg %0
+  %2 = neg -2147483648
+  %3 = and %0, 12
+  %4 = or %3, 1
+  %5 = xor %4, %1
+  %6 = eq %2, -2147483648
+  %7 = add %5, %6
+  ret %7
+}
+"#;
+    // !5 = -6, -(-6) = 6, (-6 & 12 | 1) ^ 6 = 9 ^ 6 = 15,
+    // -(-2147483648) wraps to -2147483648, so the result is 15 + 1 = 16
+    assert_eq!(interpret(src).unwrap(), 16);
+  }
+
+  #[test]
+  fn wrapping_arithmetic() {
+    let src = r#"
+fun @main(): i32 {
+%entry:
+  %0 = add 2147483647, 1
+  %1 = mul %0, -1
+  %2 = sub %1, 1
+  %3 = eq %2, 2147483647
+  ret %3
+}
+"#;
+    assert_eq!(interpret(src).unwrap(), 1);
+  }
+
+  #[test]
+  fn assume() {
+    let src = r#"
+fun @main(): i32 {
+%entry:
+  %0 = lt 3, 10
+  assume %0
+  %1 = gt 3, 10
+  assume %1
+  ret 0
+}
+"#;
+    let err = interpret(src).unwrap_err();
+    assert!(err.to_string().contains("assumption violated"));
+  }
+
+  #[test]
+  fn snapshot_and_restore() {
+    let driver: Driver<_> = r#"
+global @n = alloc i32, zeroinit
+global @p = alloc *i32, @n
+
+fun @main(): i32 {
+%entry:
+  %p = load @p
+  %0 = load %p
+  %1 = add %0, 1
+  store %1, %p
+  %2 = load @n
+  ret %2
+}
+"#
+    .into();
+    let program = driver.generate_program().unwrap();
+    let ext_funcs = unsafe { ExternFuncs::new::<String>(&[]) }.unwrap();
+    let mut interp = InterpreterImpl::new(&program, ext_funcs);
+    interp.eval_globals().unwrap();
+    let state = interp.snapshot();
+    let run = |interp: &mut InterpreterImpl| match interp.eval_main(Vec::new()) {
+      Ok(Val::Int(i)) => i,
+      _ => panic!("invalid return value"),
+    };
+    assert_eq!(run(&mut interp), 1);
+    assert_eq!(run(&mut interp), 2);
+    // `@p` must point to the restored `@n`
+    interp.restore(state.clone());
+    assert_eq!(run(&mut interp), 1);
+    interp.restore(state);
+    assert_eq!(run(&mut interp), 1);
+    assert_eq!(run(&mut interp), 2);
+  }
+
+  #[test]
+  fn main_args_and_exit_code() {
+    let src = r#"
+fun @main(@argc: i32, @x: i32): i32 {
+%entry:
+  %0 = mul @argc, 10
+  %1 = add %0, @x
+  ret %1
+}
+"#;
+    assert_eq!(run_main(src, &[2, 7]).unwrap(), 27);
+    let err = run_main(src, &[1]).unwrap_err();
+    assert!(err.to_string().contains("takes 2 argument(s), but 1 given"));
+    let err = run_main("fun @f(): i32 {\n%entry:\n  ret 0\n}\n", &[]).unwrap_err();
+    assert!(err.to_string().contains("'@main' not found"));
+    let src = r#"
+fun @main() {
+%entry:
+  ret
+}
+"#;
+    let err = run_main(src, &[]).unwrap_err();
+    assert!(err.to_string().contains("must return an integer"));
+  }
+
+  #[test]
+  fn run_main_each() {
+    let driver: Driver<_> = r#"
+global @n = alloc i32, 10
+
+fun @main(@x: i32): i32 {
+%entry:
+  %0 = load @n
+  %1 = add %0, @x
+  store %1, @n
+  ret %1
+}
+"#
+    .into();
+    let program = driver.generate_program().unwrap();
+    let args = vec![vec![Val::Int(1)], vec![Val::Int(2)], vec![Val::Int(1)]];
+    let exits = Interpreter::new(Vec::new()).run_main_each(&program, args);
+    // each run starts from the initial value of `@n`
+    assert_eq!(exits.unwrap(), [11, 12, 11]);
+  }
+
+  #[test]
+  fn malloc_and_free() {
+    let src = r#"
+decl @malloc(i32): *[i32, 2]
+
+decl @free(*[i32, 2])
+
+fun @main(): i32 {
+%entry:
+  %p = call @malloc(24)
+  %q = getptr %p, 2
+  %0 = getelemptr %q, 1
+  store 40, %0
+  %1 = getelemptr %p, 1
+  store 2, %1
+  %2 = load %0
+  %3 = load %1
+  %4 = add %2, %3
+  call @free(%p)
+  ret %4
+}
+"#;
+    assert_eq!(interpret(src).unwrap(), 42);
+    // accesses out of the allocated block
+    let src = r#"
+decl @malloc(i32): *i32
+
+fun @main(): i32 {
+%entry:
+  %p = call @malloc(8)
+  %0 = getptr %p, 2
+  %1 = load %0
+  ret %1
+}
+"#;
+    assert!(interpret(src).is_err());
+    let src = r#"
+decl @malloc(i32): *i32
+
+fun @main(): i32 {
+%entry:
+  %p = call @malloc(6)
+  ret 0
+}
+"#;
+    let err = interpret(src).unwrap_err();
+    assert!(err.to_string().contains("not a multiple"));
+    let src = r#"
+decl @malloc(i32): *i32
+
+decl @free(*i32)
+
+fun @main(): i32 {
+%entry:
+  %p = call @malloc(8)
+  %q = getptr %p, 1
+  call @free(%q)
+  ret 0
+}
+"#;
+    let err = interpret(src).unwrap_err();
+    assert!(err.to_string().contains("not returned by '@malloc'"));
+  }
+
+  #[test]
+  fn use_after_free() {
+    let run = |body: &str| {
+      let src = format!(
+        r#"
+decl @malloc(i32): *[i32, 2]
+
+decl @free(*[i32, 2])
+
+fun @main(): i32 {{
+%entry:
+  %p = call @malloc(8)
+  %e = getelemptr %p, 1
+  call @free(%p)
+{body}
+}}
+"#
+      );
+      interpret(&src).unwrap_err().to_string()
+    };
+    assert!(run("  %0 = load %e\n  ret %0").contains("use after free"));
+    assert!(run("  store 1, %e\n  ret 0").contains("use after free"));
+    let err = run("  %0 = load %p\n  ret 0");
+    assert!(err.contains("use after free"));
+    assert!(run("  call @free(%p)\n  ret 0").contains("double free"));
+  }
+
+  #[test]
+  fn reuse_freed_blocks() {
+    let src = r#"
+decl @malloc(i32): *[i32, 2]
+
+decl @free(*[i32, 2])
+
+fun @main(): i32 {
+%entry:
+  %p = call @malloc(8)
+  %e = getelemptr %p, 1
+  store 5, %e
+  call @free(%p)
+  %q = call @malloc(8)
+  %f = getelemptr %q, 1
+  %0 = load %f
+  call @free(%q)
+  ret %0
+}
+"#;
+    // the freed block is reused and zero-initialized again
+    assert_eq!(interpret(src).unwrap(), 0);
+    let mut heap = Heap::default();
+    let ty = Type::get_array(Type::get_i32(), 2);
+    let ptr = |v: Val| match v {
+      Val::Pointer { ptr: Some(p), .. } => p.as_ptr() as *const Val,
+      _ => panic!("invalid pointer"),
+    };
+    let p = ptr(heap.alloc(&ty, 3));
+    heap.free(p).unwrap();
+    assert_eq!(ptr(heap.alloc(&ty, 3)), p);
+    assert_ne!(ptr(heap.alloc(&ty, 3)), p);
+    assert_eq!(heap.blocks.len(), 2);
+  }
+
+  #[test]
+  fn malloc_zero_sized() {
+    let mut program = Program::new();
+    let unit_ptr = Type::get_pointer(Type::get_unit());
+    let malloc = program.new_func(FunctionData::new_decl(
+      "@malloc".into(),
+      vec![Type::get_i32()],
+      unit_ptr,
+    ));
+    let main = program.new_func(FunctionData::new("@main".into(), vec![], Type::get_i32()));
+    let main = program.func_mut(main);
+    let entry = main.dfg_mut().new_bb().basic_block(Some("%entry".into()));
+    main.layout_mut().bbs_mut().push_key_back(entry).unwrap();
+    let size = main.dfg_mut().new_value().integer(4);
+    let call = main.dfg_mut().new_value().call(malloc, vec![size]);
+    let zero = main.dfg_mut().new_value().integer(0);
+    let ret = main.dfg_mut().new_value().ret(Some(zero));
+    main
+      .layout_mut()
+      .bb_mut(entry)
+      .insts_mut()
+      .extend([call, ret]);
+    let err = Interpreter::new(Vec::new())
+      .run_main(&program, vec![])
+      .unwrap_err();
+    assert!(err.to_string().contains("zero-sized type"));
+  }
+
+  #[test]
+  fn readonly_globals() {
+    let src = r#"
+global @r = alloc [i32, 2], {1, 2}, readonly
+global @p = alloc *[i32, 2], @r
+
+fun @main(): i32 {
+%entry:
+  %p = load @p
+  %e = getelemptr %p, 1
+  %0 = load %e
+  store 3, %e
+  ret %0
+}
+"#;
+    let err = interpret(src).unwrap_err();
+    assert!(err.to_string().contains("read-only"));
+    assert_eq!(interpret(&src.replace("  store 3, %e\n", "")).unwrap(), 2);
+  }
+
+  #[test]
+  fn div_modes() {
+    let src = r#"
+fun @main(@l: i32, @r: i32): i32 {
+%entry:
+  %0 = div @l, @r
+  %1 = mod @l, @r
+  %2 = mul %0, 10
+  %3 = add %2, %1
+  ret %3
+}
+
+fun @div(@l: i32, @r: i32): i32 {
+%entry:
+  %0 = div @l, @r
+  ret %0
+}
+"#;
+    let run = |mode, l, r| {
+      let driver: Driver<_> = src.into();
+      let program = driver.generate_program().unwrap();
+      let mut interpreter = Interpreter::new(Vec::new());
+      interpreter.set_div_mode(mode);
+      interpreter.run_main(&program, vec![Val::Int(l), Val::Int(r)])
+    };
+    let cases = [(7, 2), (-7, 2), (7, -2), (-7, -2), (6, -3)];
+    let expected = [
+      (DivMode::TruncTowardZero, [31, -31, -29, 29, -20]),
+      (DivMode::FloorDiv, [31, -39, -41, 29, -20]),
+      (DivMode::Euclidean, [31, -39, -29, 41, -20]),
+    ];
+    for (mode, results) in expected {
+      for ((l, r), ans) in cases.into_iter().zip(results) {
+        assert_eq!(run(mode, l, r).unwrap(), ans, "{mode:?}: {l} / {r}");
+      }
+      let err = run(mode, 1, 0).unwrap_err();
+      assert!(err.to_string().contains("division by zero"));
+    }
+    let src = src.replace("@main", "@unused").replace("@div", "@main");
+    for mode in [
+      DivMode::TruncTowardZero,
+      DivMode::FloorDiv,
+      DivMode::Euclidean,
+    ] {
+      let driver: Driver<_> = src.as_str().into();
+      let program = driver.generate_program().unwrap();
+      let mut interpreter = Interpreter::new(Vec::new());
+      interpreter.set_div_mode(mode);
+      let args = vec![Val::Int(i32::MIN), Val::Int(-1)];
+      assert_eq!(interpreter.run_main(&program, args).unwrap(), i32::MIN);
+    }
+  }
+
+  #[test]
+  fn block_params_and_recursion() {
+    // arguments are passed to basic block parameters in parallel
+    let src = r#"
+fun @fib(@n: i32): i32 {
+%entry:
+  jump %loop(0, 1, @n)
+
+%loop(%a: i32, %b: i32, %i: i32):
+  br %i, %body, %end
+
+%body:
+  %c = add %a, %b
+  %j = sub %i, 1
+  jump %swap(%b, %c, %j)
+
+%swap(%x: i32, %y: i32, %k: i32):
+  jump %loop(%x, %y, %k)
+
+%end:
+  ret %a
+}
+
+fun @sum(@n: i32): i32 {
+%entry:
+  br @n, %rec, %end
+
+%rec:
+  %0 = sub @n, 1
+  %1 = call @sum(%0)
+  %2 = call @fib(@n)
+  %3 = add %1, %2
+  ret %3
+
+%end:
+  ret 0
+}
+
+fun @main(@n: i32): i32 {
+%entry:
+  %0 = call @sum(@n)
+  ret %0
+}
+"#;
+    assert_eq!(run_main(src, &[10]).unwrap(), 143);
+    // recursion is only bounded by memory
+    let src = r#"
+fun @depth(@n: i32): i32 {
+%entry:
+  br @n, %rec, %end
+
+%rec:
+  %0 = sub @n, 1
+  %1 = call @depth(%0)
+  %2 = add %1, 1
+  ret %2
+
+%end:
+  ret 0
+}
+
+fun @main(@n: i32): i32 {
+%entry:
+  %0 = call @depth(@n)
+  ret %0
+}
+"#;
+    assert_eq!(run_main(src, &[100000]).unwrap(), 100000);
+    let src = r#"
+fun @main(@x: i32, @y: i32): i32 {
+%entry:
+  jump %swap(@x, @y, 2)
+
+%swap(%a: i32, %b: i32, %n: i32):
+  br %n, %again, %end
+
+%again:
+  %m = sub %n, 1
+  jump %swap(%b, %a, %m)
+
+%end:
+  %0 = mul %a, 10
+  %1 = add %0, %b
+  ret %1
+}
+"#;
+    assert_eq!(run_main(src, &[1, 2]).unwrap(), 12);
+    let src = src.replace("jump %swap(@x, @y, 2)", "jump %swap(@x, @y, 3)");
+    assert_eq!(run_main(&src, &[1, 2]).unwrap(), 21);
+  }
+
+  #[test]
+  fn frozen_undefs() {
+    let run = |src: &str, mode| {
+      let driver: Driver<_> = src.into();
+      let program = driver.generate_program().unwrap();
+      let mut interpreter = Interpreter::new(Vec::new());
+      interpreter.set_undef_mode(mode);
+      interpreter.run_main(&program, Vec::new())
+    };
+    let src = r#"
+global @g = alloc i32, undef
+
+fun @f(): i32 {
+%entry:
+  ret undef
+}
+
+fun @main(): i32 {
+%entry:
+  %0 = call @f()
+  %1 = call @f()
+  %2 = load @g
+  %3 = load @g
+  %4 = eq %0, %1
+  %5 = eq %2, %3
+  %6 = and %4, %5
+  ret %6
+}
+"#;
+    for seed in 0..8 {
+      assert_eq!(run(src, UndefMode::Freeze(seed)).unwrap(), 1);
+    }
+    let src = r#"
+fun @main(): i32 {
+%entry:
+  ret undef
+}
+"#;
+    let err = run(src, UndefMode::Undefined).unwrap_err();
+    assert!(err.to_string().contains("returned an invalid value"));
+    let vals: HashSet<_> = (0..8)
+      .map(|seed| run(src, UndefMode::Freeze(seed)).unwrap())
+      .collect();
+    assert!(vals.len() > 1);
+    for seed in 0..8 {
+      let mode = UndefMode::Freeze(seed);
+      assert_eq!(run(src, mode).unwrap(), run(src, mode).unwrap());
+    }
+  }
+
+  #[test]
+  fn step_limit() {
+    let src = r#"
+fun @main(@n: i32): i32 {
+%entry:
+  jump %loop(0)
+
+%loop(%i: i32):
+  %cond = lt %i, @n
+  br %cond, %body, %end
+
+%body:
+  %next = add %i, 1
+  jump %loop(%next)
+
+%end:
+  ret %i
+}
+"#;
+    let program = Driver::from(src).generate_program().unwrap();
+    let mut interpreter = Interpreter::new(Vec::new());
+    // each iteration evaluates 4 instructions, so a run with `@n` = 10
+    // evaluates 1 + 4 * 10 + 3 = 44 instructions, and steps are counted
+    // separately for each run
+    interpreter.set_step_limit(Some(44));
+    let args = vec![vec![Val::Int(10)], vec![Val::Int(10)]];
+    assert_eq!(interpreter.run_main_each(&program, args).unwrap(), [10, 10]);
+    let err = interpreter
+      .run_main(&program, vec![Val::Int(11)])
+      .unwrap_err();
+    assert!(err.to_string().contains("step limit of 44 exceeded"));
+    interpreter.set_step_limit(None);
+    assert_eq!(
+      interpreter.run_main(&program, vec![Val::Int(11)]).unwrap(),
+      11
+    );
+  }
+
+  #[test]
+  fn compare_runs() {
+    let compare = |lhs: &str, rhs: &str, name, args: &[i32]| {
+      let lhs = Driver::from(lhs).generate_program().unwrap();
+      let rhs = Driver::from(rhs).generate_program().unwrap();
+      let args = args.iter().map(|a| Val::Int(*a)).collect();
+      Interpreter::new(Vec::new()).compare(&lhs, &rhs, name, args)
+    };
+    let src = r#"
+global @g = alloc [i32, 2], zeroinit
+
+fun @f(@x: i32): i32 {
+%entry:
+  %0 = mul @x, 2
+  %1 = getelemptr @g, 1
+  store %0, %1
+  ret %0
+}
+"#;
+    let opt = src.replace("mul @x, 2", "add @x, @x");
+    assert_eq!(compare(src, &opt, "@f", &[21]), None);
+    let ret = src.replace("ret %0", "ret @x");
+    assert_eq!(
+      compare(src, &ret, "@f", &[21]),
+      Some(Divergence::Exit(Ok(42), Ok(21)))
+    );
+    let global = src.replace("@g, 1", "@g, 0");
+    assert_eq!(
+      compare(src, &global, "@f", &[21]),
+      Some(Divergence::Global(
+        "@g".into(),
+        vec![Some(0), Some(42)],
+        vec![Some(42), Some(0)]
+      ))
+    );
+    let error = src.replace("mul @x, 2", "div @x, 0");
+    assert!(matches!(
+      compare(src, &error, "@f", &[21]),
+      Some(Divergence::Exit(Ok(42), Err(_)))
+    ));
+    // no library is loaded, so external calls are recorded and then fail
+    let src = r#"
+decl @putint(i32)
+
+fun @main(): i32 {
+%entry:
+  call @putint(1)
+  ret 0
+}
+"#;
+    let call = |arg| ExtCall {
+      name: "@putint".into(),
+      args: vec![Some(arg)],
+      ret: None,
+    };
+    assert_eq!(
+      compare(src, &src.replace("@putint(1)", "@putint(2)"), "@main", &[]),
+      Some(Divergence::ExtCall(0, Some(call(1)), Some(call(2))))
+    );
+    let divergence = Divergence::ExtCall(1, Some(call(1)), None);
+    assert_eq!(
+      divergence.to_string(),
+      "external call 1 differs: @putint(1) vs no call"
+    );
+    let divergence = Divergence::Global("@g".into(), vec![Some(1), None], vec![Some(2), None]);
+    assert_eq!(
+      divergence.to_string(),
+      "final values of @g differ: [1, ?] vs [2, ?]"
+    );
+  }
+
+  /// Checks if running the given passes on the given program preserves
+  /// the results of the function with the given name on all inputs.
+  fn assert_semantics_preserved(src: &str, passes: Vec<Pass>, name: &str, inputs: &[&[i32]]) {
+    let program = Driver::from(src).generate_program().unwrap();
+    let mut opt = Driver::from(src).generate_program().unwrap();
+    let mut passman = PassManager::new();
+    passes.into_iter().for_each(|p| passman.register(p));
+    passman.run_passes(&mut opt);
+    assert_eq!(opt.verify(), Ok(()));
+    for input in inputs {
+      let args = input.iter().map(|a| Val::Int(*a)).collect();
+      let divergence = Interpreter::new(Vec::new()).compare(&program, &opt, name, args);
+      assert_eq!(divergence, None, "diverged on input {:?}", input);
+    }
+  }
+
+  #[test]
+  fn gep_folding() {
+    // `getelemptr (getptr @p, 1), 2` must not become `getelemptr @p, 6`
+    let src = r#"
+global @arr = alloc [[i32, 4], 3], {{0, 1, 2, 3}, {4, 5, 6, 7}, {8, 9, 10, 11}}
+
+fun @g(@p: *[i32, 4]): i32 {
+%entry:
+  %0 = getptr @p, 1
+  %1 = getelemptr %0, 2
+  %2 = load %1
+  ret %2
+}
+
+fun @main(): i32 {
+%entry:
+  %0 = getelemptr @arr, 0
+  %1 = getptr %0, 1
+  %2 = call @g(%1)
+  ret %2
+}
+"#;
+    assert_eq!(interpret(src).unwrap(), 10);
+    let passes = vec![Pass::Function(Box::new(GepFolding::new()))];
+    assert_semantics_preserved(src, passes, "@main", &[&[]]);
+  }
+
+  #[test]
+  fn ssa_round_trip() {
+    let src = r#"
+fun @f(@n: i32): i32 {
+%entry:
+  jump %loop(0, 0)
+
+%loop(%i: i32, %sum: i32):
+  %cond = lt %i, @n
+  br %cond, %body, %end(%sum)
+
+%body:
+  %odd = and %i, 1
+  br %odd, %next(%sum), %next(%i)
+
+%next(%x: i32):
+  %0 = add %sum, %x
+  %1 = add %i, 1
+  jump %loop(%1, %0)
+
+%end(%ans: i32):
+  ret %ans
+}
+"#;
+    let linearize = || Pass::Function(Box::new(Linearize::new()));
+    let mem2reg = || Pass::Function(Box::new(Mem2Reg::new()));
+    let inputs: &[&[i32]] = &[&[-1], &[0], &[1], &[5], &[10]];
+    // out of SSA form, and then back into SSA form
+    assert_semantics_preserved(src, vec![linearize()], "@f", inputs);
+    assert_semantics_preserved(src, vec![linearize(), mem2reg()], "@f", inputs);
+  }
+}
@@ -1,10 +1,11 @@
 mod ext_funcs;
 mod interpreter;
 
-use interpreter::Interpreter;
-use koopa::back::Generator;
+use interpreter::{DivMode, Interpreter, UndefMode, Val};
+use koopa::front::builder::BuildOptions;
 use koopa::front::Driver;
-use std::io::{sink, stdin, Error};
+use koopa::ir::Program;
+use std::io::{stdin, Error};
 use std::{env, fmt, process, result};
 
 fn main() {
@@ -16,21 +17,74 @@ fn main() {
 
 fn try_main() -> result::Result<i32, MainError> {
   // parse command line arguments
-  let CommandLineArgs { input, libs } = parse_cmd_args()?;
-  // parse the input file
-  let program = if let Some(file) = input {
+  let CommandLineArgs {
+    input,
+    libs,
+    div_mode,
+    undef_mode,
+    step_limit,
+    compare_with,
+    args,
+  } = parse_cmd_args()?;
+  let program = parse_program(input)?;
+  // interpret the program, the return value of `@main` is the exit code
+  let mut interpreter = Interpreter::new(libs);
+  interpreter.set_div_mode(div_mode);
+  interpreter.set_undef_mode(undef_mode);
+  interpreter.set_step_limit(step_limit);
+  if let Some(file) = compare_with {
+    // run `@main` in both programs once for each argument list,
+    // the exit code is the number of runs that diverge
+    let other = parse_program(Some(file))?;
+    let args = if args.is_empty() {
+      vec![Vec::new()]
+    } else {
+      args
+    };
+    let mut diverged = 0;
+    for (i, args) in args.into_iter().enumerate() {
+      match interpreter.compare(&program, &other, "@main", args) {
+        Some(divergence) => {
+          eprintln!("run {}: {}", i + 1, divergence);
+          diverged += 1;
+        }
+        None => eprintln!("run {}: same", i + 1),
+      }
+    }
+    return Ok(diverged);
+  }
+  if args.len() <= 1 {
+    let args = args.into_iter().next().unwrap_or_default();
+    return (interpreter.run_main(&program, args)).map_err(MainError::OtherError);
+  }
+  // run `@main` once for each argument list, the last return value is
+  // the exit code
+  let exits = (interpreter.run_main_each(&program, args)).map_err(MainError::OtherError)?;
+  for (i, exit) in exits.iter().enumerate() {
+    eprintln!("run {}: {}", i + 1, exit);
+  }
+  Ok(*exits.last().unwrap())
+}
+
+/// Parses the given input file, or the standard input if not provided.
+///
+/// Source spans are kept for runtime errors.
+fn parse_program(input: Option<String>) -> result::Result<Program, MainError> {
+  let options = BuildOptions {
+    keep_spans: true,
+    ..Default::default()
+  };
+  if let Some(file) = input {
     Driver::from_path(file)
       .map_err(MainError::InvalidFile)?
+      .with_options(options)
       .generate_program()
   } else {
-    Driver::from(stdin()).generate_program()
+    Driver::from(stdin())
+      .with_options(options)
+      .generate_program()
   }
-  .map_err(|_| MainError::ParseError)?;
-  // interpret the program
-  let interpreter = Interpreter::new(libs);
-  Generator::with_visitor(sink(), interpreter)
-    .generate_on(&program)
-    .map_err(MainError::OtherError)
+  .map_err(|_| MainError::ParseError)
 }
 
 enum MainError {
@@ -45,10 +99,17 @@ impl fmt::Display for MainError {
     match self {
       MainError::InvalidArgs => write!(
         f,
-        r#"Usage: interpreter [FILE] [-l DYN_LIB ...]
+        r#"Usage: interpreter [FILE] [-l DYN_LIB ...] [-d MODE] [-s STEPS] [-c FILE] [-- ARG ...]
 Options:
   FILE        use FILE as input instead of stdin
-  -l DYN_LIB  load dynamic library DYN_LIB"#
+  -l DYN_LIB  load dynamic library DYN_LIB
+  -d MODE     round 'div' and 'mod' by MODE: trunc (default), floor, euclid
+  -u SEED     freeze each 'undef' to a fixed value generated from SEED
+  -s STEPS    stop with an error after evaluating STEPS instructions
+  -c FILE     run '@main' in both the input and FILE, and report where
+              the runs diverge
+  -- ARG ...  pass integer arguments ARG to function '@main', repeat to
+              run '@main' again with other arguments from the same state"#
       ),
       MainError::InvalidFile(error) => write!(f, "invalid file operation: {}", error),
       MainError::ParseError => write!(f, "error occurred when parsing the input"),
@@ -61,23 +122,54 @@ Options:
 struct CommandLineArgs {
   input: Option<String>,
   libs: Vec<String>,
+  div_mode: DivMode,
+  undef_mode: UndefMode,
+  step_limit: Option<u64>,
+  compare_with: Option<String>,
+  args: Vec<Vec<Val>>,
 }
 
 fn parse_cmd_args() -> result::Result<CommandLineArgs, MainError> {
   let mut cmd_args = CommandLineArgs::default();
   let mut args = env::args();
   args.next();
-  loop {
-    match (args.next(), args.next()) {
-      (Some(file), Some(o)) if file != "-l" && o == "-l" => {
-        cmd_args.input = Some(file);
-        cmd_args
-          .libs
-          .push(args.next().ok_or(MainError::InvalidArgs)?);
+  // arguments after each `--` are passed to a run of `@main`
+  let mut args: Vec<_> = args.collect();
+  if let Some(i) = args.iter().position(|a| a == "--") {
+    let main_args: Vec<_> = args.drain(i..).collect();
+    cmd_args.args = main_args
+      .split(|a| a == "--")
+      .skip(1)
+      .map(|args| (args.iter().map(|a| a.parse().map(Val::Int))).collect::<result::Result<_, _>>())
+      .collect::<result::Result<_, _>>()
+      .map_err(|_| MainError::InvalidArgs)?;
+  }
+  let mut args = args.into_iter();
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "-l" => cmd_args
+        .libs
+        .push(args.next().ok_or(MainError::InvalidArgs)?),
+      "-d" => {
+        cmd_args.div_mode = match args.next().as_deref() {
+          Some("trunc") => DivMode::TruncTowardZero,
+          Some("floor") => DivMode::FloorDiv,
+          Some("euclid") => DivMode::Euclidean,
+          _ => return Err(MainError::InvalidArgs),
+        }
+      }
+      "-u" => {
+        let seed = args.next().and_then(|s| s.parse().ok());
+        cmd_args.undef_mode = UndefMode::Freeze(seed.ok_or(MainError::InvalidArgs)?);
+      }
+      "-s" => {
+        let steps = args.next().and_then(|s| s.parse().ok());
+        cmd_args.step_limit = Some(steps.ok_or(MainError::InvalidArgs)?);
+      }
+      "-c" if cmd_args.compare_with.is_none() => {
+        cmd_args.compare_with = Some(args.next().ok_or(MainError::InvalidArgs)?)
       }
-      (Some(file), _) if file != "-l" => cmd_args.input = Some(file),
-      (Some(o), Some(lib)) if o == "-l" => cmd_args.libs.push(lib),
-      (None, None) => break,
+      _ if cmd_args.input.is_none() => cmd_args.input = Some(arg),
       _ => return Err(MainError::InvalidArgs),
     }
   }
@@ -0,0 +1,38 @@
+use koopa::back::{NameManager, Visitor};
+use koopa::ir::Program;
+use std::io::{Result, Write};
+
+/// Generates control flow graphs of all function definitions
+/// in Graphviz DOT format.
+#[derive(Default)]
+pub struct DotVisitor;
+
+impl<W: Write> Visitor<W> for DotVisitor {
+  type Output = ();
+
+  fn visit(&mut self, w: &mut W, nm: &mut NameManager, program: &Program) -> Result<()> {
+    for func in program.func_layout() {
+      let func = program.func(*func);
+      // skip function declarations
+      if func.layout().entry_bb().is_none() {
+        continue;
+      }
+      writeln!(w, "digraph \"{}\" {{", nm.func_name(func))?;
+      nm.enter_func_scope();
+      for (bb, node) in func.layout().bbs() {
+        let name = nm.bb_name(func.dfg().bb(*bb));
+        writeln!(w, "  \"{}\";", name)?;
+        // generate edges from the terminator
+        if let Some(last) = node.insts().back_key() {
+          for target in func.dfg().value(*last).kind().bb_uses() {
+            let target = nm.bb_name(func.dfg().bb(target));
+            writeln!(w, "  \"{}\" -> \"{}\";", name, target)?;
+          }
+        }
+      }
+      nm.exit_func_scope();
+      writeln!(w, "}}")?;
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,222 @@
+#[path = "../opt/const_fold.rs"]
+mod const_fold;
+#[path = "../opt/dce.rs"]
+mod dce;
+mod dot;
+#[path = "../interpreter/ext_funcs.rs"]
+mod ext_funcs;
+// the CLI only runs `@main` without arguments, so most of the
+// interpreter API is unused
+#[allow(dead_code)]
+#[path = "../interpreter/interpreter.rs"]
+mod interpreter;
+
+use dot::DotVisitor;
+use interpreter::Interpreter;
+use koopa::back::{Generator, KoopaGenerator};
+use koopa::front::builder::BuildOptions;
+use koopa::front::Driver;
+use koopa::ir::Program;
+use koopa::opt::*;
+use std::fs::File;
+use std::io::{self, sink, stdin, stdout, Write};
+use std::{env, fmt, process};
+
+fn main() {
+  process::exit(try_main().unwrap_or_else(|e| {
+    eprintln!("{}", e);
+    -1
+  }));
+}
+
+fn try_main() -> Result<i32, Error> {
+  let CommandLineArgs {
+    command,
+    input,
+    libs,
+    passes,
+    output,
+  } = parse_cmd_args()?;
+  let mut program = parse_input(input)?;
+  match command {
+    Command::Interp => Generator::with_visitor(sink(), Interpreter::new(libs))
+      .generate_on(&program)
+      .map_err(Error::Io),
+    Command::Verify => match program.verify() {
+      Ok(()) => Ok(0),
+      Err(errors) => {
+        for error in &errors {
+          eprintln!("{}", error.display_in(&program));
+        }
+        Err(Error::Verify(errors.len()))
+      }
+    },
+    Command::Opt => {
+      let mut passman = PassManager::new();
+      for pass in passes.unwrap_or_else(|| "constfold,dce".into()).split(',') {
+        passman.register(new_pass(pass)?);
+      }
+      passman.run_passes(&mut program);
+      print_program(&program, output).map(|_| 0)
+    }
+    Command::Print => print_program(&program, output).map(|_| 0),
+    Command::Dot => Generator::with_visitor(open_output(output)?, DotVisitor)
+      .generate_on(&program)
+      .map(|_| 0)
+      .map_err(Error::Io),
+  }
+}
+
+/// Parses the given input file, or the standard input if not provided.
+///
+/// Source spans are kept for error messages.
+fn parse_input(input: Option<String>) -> Result<Program, Error> {
+  let options = BuildOptions {
+    keep_spans: true,
+    ..Default::default()
+  };
+  if let Some(file) = input {
+    Driver::from_path(file)
+      .map_err(Error::InvalidFile)?
+      .with_options(options)
+      .generate_program()
+  } else {
+    Driver::from(stdin())
+      .with_options(options)
+      .generate_program()
+  }
+  .map_err(|_| Error::Parse)
+}
+
+/// Opens the given output file, or the standard output if not provided.
+fn open_output(output: Option<String>) -> Result<Box<dyn Write>, Error> {
+  Ok(match output {
+    Some(file) => Box::new(File::create(file).map_err(Error::InvalidFile)?),
+    None => Box::new(stdout()),
+  })
+}
+
+/// Prints the given program in text form.
+fn print_program(program: &Program, output: Option<String>) -> Result<(), Error> {
+  KoopaGenerator::new(open_output(output)?)
+    .generate_on(program)
+    .map_err(Error::Io)
+}
+
+/// Creates a new pass by the given name.
+fn new_pass(name: &str) -> Result<Pass, Error> {
+  match name {
+    "deadarg" => return Ok(Pass::Module(Box::new(DeadArgElimination::new()))),
+    "constmerge" => return Ok(Pass::Module(Box::new(ConstantMerge::new()))),
+    "ipcp" => return Ok(Pass::Module(Box::new(IpConstProp::new()))),
+    "purity" => return Ok(Pass::Module(Box::new(InferPurity::new()))),
+    "localize" => return Ok(Pass::Module(Box::new(LocalizeMainGlobals::new()))),
+    _ => {}
+  }
+  Ok(Pass::Function(match name {
+    "brfold" => Box::new(BranchFolding::new()),
+    "constfold" => Box::new(const_fold::ConstantFolding::new()),
+    "dce" => Box::new(dce::DeadCodeElimination::new()),
+    "dse" => Box::new(DeadStoreElimination::new()),
+    "copyprop" => Box::new(CopyPropagation::new()),
+    "gepfold" => Box::new(GepFolding::new()),
+    "linearize" => Box::new(Linearize::new()),
+    "mem2reg" => Box::new(Mem2Reg::new()),
+    _ => return Err(Error::InvalidPass(name.into())),
+  }))
+}
+
+enum Error {
+  InvalidArgs,
+  InvalidFile(io::Error),
+  InvalidPass(String),
+  Parse,
+  Verify(usize),
+  Io(io::Error),
+}
+
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Error::InvalidArgs => write!(
+        f,
+        r#"Usage: koopa COMMAND [FILE] [OPTIONS]
+Commands:
+  interp [-l DYN_LIB ...]        interpret the program
+  verify                         verify the program
+  opt [-p PASS,...] [-o OUTPUT]  optimize the program
+  print [-o OUTPUT]              print the program in text form
+  dot [-o OUTPUT]                print control flow graphs in DOT format
+Options:
+  FILE       use FILE as input instead of stdin
+  -l DYN_LIB load dynamic library DYN_LIB
+  -p PASS    run passes (constfold, dce, dse, copyprop, gepfold,
+             linearize, mem2reg, brfold, deadarg, constmerge, ipcp,
+             purity, localize),
+             default to 'constfold,dce'
+  -o OUTPUT  use OUTPUT as output instead of stdout"#
+      ),
+      Error::InvalidFile(error) => write!(f, "invalid file operation: {}", error),
+      Error::InvalidPass(pass) => write!(f, "invalid pass: {}", pass),
+      Error::Parse => write!(f, "error occurred when parsing the input"),
+      Error::Verify(num) => write!(f, "{} error(s) found by the verifier", num),
+      Error::Io(error) => write!(f, "{}", error),
+    }
+  }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Command {
+  Interp,
+  Verify,
+  Opt,
+  Print,
+  Dot,
+}
+
+struct CommandLineArgs {
+  command: Command,
+  input: Option<String>,
+  libs: Vec<String>,
+  passes: Option<String>,
+  output: Option<String>,
+}
+
+fn parse_cmd_args() -> Result<CommandLineArgs, Error> {
+  let mut args = env::args();
+  args.next();
+  let command = match args.next().as_deref() {
+    Some("interp") => Command::Interp,
+    Some("verify") => Command::Verify,
+    Some("opt") => Command::Opt,
+    Some("print") => Command::Print,
+    Some("dot") => Command::Dot,
+    _ => return Err(Error::InvalidArgs),
+  };
+  let mut cmd_args = CommandLineArgs {
+    command,
+    input: None,
+    libs: Vec::new(),
+    passes: None,
+    output: None,
+  };
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "-l" if command == Command::Interp => {
+        cmd_args.libs.push(args.next().ok_or(Error::InvalidArgs)?)
+      }
+      "-p" if command == Command::Opt && cmd_args.passes.is_none() => {
+        cmd_args.passes = Some(args.next().ok_or(Error::InvalidArgs)?)
+      }
+      "-o"
+        if matches!(command, Command::Opt | Command::Print | Command::Dot)
+          && cmd_args.output.is_none() =>
+      {
+        cmd_args.output = Some(args.next().ok_or(Error::InvalidArgs)?)
+      }
+      _ if !arg.starts_with('-') && cmd_args.input.is_none() => cmd_args.input = Some(arg),
+      _ => return Err(Error::InvalidArgs),
+    }
+  }
+  Ok(cmd_args)
+}
@@ -1,4 +1,5 @@
-use koopa::ir::{builder_traits::*, BinaryOp, Function, FunctionData, Type, ValueKind};
+use koopa::ir::entities::ValueData;
+use koopa::ir::{builder_traits::*, BinaryOp, Function, FunctionData, Type, Value, ValueKind};
 use koopa::opt::FunctionPass;
 
 /// Performs constant folding.
@@ -6,8 +7,8 @@ pub struct ConstantFolding;
 
 impl FunctionPass for ConstantFolding {
   fn run_on(&mut self, _: Function, data: &mut FunctionData) {
-    // handle all binary instructions
-    while self.eval_const(data) {}
+    // handle all binary/unary instructions and aggregate operations
+    while self.eval_const(data) | self.eval_aggregate_ops(data) {}
     // handle all basic block parameters
     self.eval_bb_params(data);
   }
@@ -19,7 +20,7 @@ impl ConstantFolding {
   }
 
   fn eval_const(&self, data: &mut FunctionData) -> bool {
-    // find all evaluable binary instructions and evaluate
+    // find all evaluable binary/unary instructions and evaluate
     let mut evaluated = Vec::new();
     for (v, value) in data.dfg().values() {
       let ans = match value.kind() {
@@ -27,6 +28,12 @@ impl ConstantFolding {
           let lhs = data.dfg().value(bin.lhs()).kind();
           let rhs = data.dfg().value(bin.rhs()).kind();
           match (lhs, rhs) {
+            // flagged operations that break their promises are undefined
+            (ValueKind::Integer(l), ValueKind::Integer(r))
+              if bin.flags().is_violated(bin.op(), l.value(), r.value()) =>
+            {
+              None
+            }
             (ValueKind::Integer(l), ValueKind::Integer(r)) => match bin.op() {
               BinaryOp::NotEq => Some((l.value() != r.value()) as i32),
               BinaryOp::Eq => Some((l.value() == r.value()) as i32),
@@ -34,23 +41,37 @@ impl ConstantFolding {
               BinaryOp::Lt => Some((l.value() < r.value()) as i32),
               BinaryOp::Ge => Some((l.value() >= r.value()) as i32),
               BinaryOp::Le => Some((l.value() <= r.value()) as i32),
-              BinaryOp::Add => Some(l.value() + r.value()),
-              BinaryOp::Sub => Some(l.value() - r.value()),
-              BinaryOp::Mul => Some(l.value() * r.value()),
-              BinaryOp::Div => (r.value() != 0).then(|| l.value() / r.value()),
-              BinaryOp::Mod => (r.value() != 0).then(|| l.value() % r.value()),
+              // arithmetic operations wrap around on overflow
+              BinaryOp::Add => Some(l.value().wrapping_add(r.value())),
+              BinaryOp::Sub => Some(l.value().wrapping_sub(r.value())),
+              BinaryOp::Mul => Some(l.value().wrapping_mul(r.value())),
+              BinaryOp::Div => (r.value() != 0).then(|| l.value().wrapping_div(r.value())),
+              BinaryOp::Mod => (r.value() != 0).then(|| l.value().wrapping_rem(r.value())),
               BinaryOp::And => Some(l.value() & r.value()),
               BinaryOp::Or => Some(l.value() | r.value()),
               BinaryOp::Xor => Some(l.value() ^ r.value()),
-              BinaryOp::Shl => Some(l.value() << r.value()),
-              BinaryOp::Shr => Some((l.value() as u32 >> r.value()) as i32),
-              BinaryOp::Sar => Some(l.value() >> r.value()),
+              // shifts out of range are left untouched
+              BinaryOp::Shl | BinaryOp::Shr | BinaryOp::Sar => {
+                let (l, r) = match u32::try_from(r.value()) {
+                  Ok(r) if r < 32 => (l.value(), r),
+                  _ => continue,
+                };
+                Some(match bin.op() {
+                  BinaryOp::Shl => l << r,
+                  BinaryOp::Shr => ((l as u32) >> r) as i32,
+                  _ => l >> r,
+                })
+              }
             },
-            (ValueKind::Undef(_), _) => todo!(),
-            (_, ValueKind::Undef(_)) => todo!(),
+            // `x ^ x` is always zero
+            _ if bin.op() == BinaryOp::Xor && bin.lhs() == bin.rhs() => Some(0),
             _ => continue,
           }
         }
+        ValueKind::Unary(unary) => match data.dfg().value(unary.opr()).kind() {
+          ValueKind::Integer(i) => Some(unary.eval(i.value())),
+          _ => continue,
+        },
         _ => continue,
       };
       evaluated.push((*v, ans, data.layout().parent_bb(*v).unwrap()));
@@ -73,6 +94,47 @@ impl ConstantFolding {
     changed
   }
 
+  fn eval_aggregate_ops(&self, data: &mut FunctionData) -> bool {
+    // find all element extractions/insertions on constant aggregates
+    let mut evaluated = Vec::new();
+    for (v, value) in data.dfg().values() {
+      let ans = match value.kind() {
+        ValueKind::ExtractValue(ev) => match data.dfg().value(ev.agg()).kind() {
+          ValueKind::Aggregate(agg) => {
+            Folded::Raw(data.dfg().value(agg.elems()[ev.index()]).clone())
+          }
+          ValueKind::ZeroInit(_) if value.ty().is_i32() => Folded::Int(0),
+          ValueKind::ZeroInit(_) => Folded::ZeroInit(value.ty().clone()),
+          _ => continue,
+        },
+        ValueKind::InsertValue(iv) => match data.dfg().value(iv.agg()).kind() {
+          ValueKind::Aggregate(agg) if data.dfg().value(iv.value()).kind().is_const() => {
+            let mut elems = agg.elems().to_vec();
+            elems[iv.index()] = iv.value();
+            Folded::Aggregate(elems)
+          }
+          _ => continue,
+        },
+        _ => continue,
+      };
+      evaluated.push((*v, ans, data.layout().parent_bb(*v).unwrap()));
+    }
+    // updated values
+    let changed = !evaluated.is_empty();
+    // replace the evaluated instructions, and remove them from the layout
+    for (v, ans, bb) in evaluated {
+      let builder = data.dfg_mut().replace_value_with(v);
+      match ans {
+        Folded::Raw(data) => builder.raw(data),
+        Folded::Int(i) => builder.integer(i),
+        Folded::ZeroInit(ty) => builder.zero_init(ty),
+        Folded::Aggregate(elems) => builder.aggregate(elems),
+      };
+      data.layout_mut().bb_mut(bb).insts_mut().remove(&v);
+    }
+    changed
+  }
+
   fn eval_bb_params(&self, data: &mut FunctionData) {
     let mut bb_params = Vec::new();
     for (b, bb) in data.dfg().bbs() {
@@ -92,7 +154,15 @@ impl ConstantFolding {
               }
             }
             ValueKind::Jump(jump) => jump.args()[i],
-            _ => panic!("invalid branch/jump instruction"),
+            ValueKind::Switch(switch) => {
+              if switch.default_bb() == *b {
+                switch.default_args()[i]
+              } else {
+                let (_, _, args) = switch.cases().iter().find(|(_, t, _)| t == b).unwrap();
+                args[i]
+              }
+            }
+            _ => panic!("invalid branch/jump/switch instruction"),
           };
           // check if is constant
           let value = data.dfg().value(value);
@@ -120,3 +190,11 @@ impl ConstantFolding {
     }
   }
 }
+
+/// Result of constant folding on aggregate operations.
+enum Folded {
+  Raw(ValueData),
+  Int(i32),
+  ZeroInit(Type),
+  Aggregate(Vec<Value>),
+}
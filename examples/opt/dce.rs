@@ -104,30 +104,41 @@ impl DeadCodeElimination {
       users.into_iter().for_each(|user| {
         // get instruction from DFG
         let mut inst = data.dfg().value(user).clone();
-        // get argument list
-        let args = match inst.kind_mut() {
+        // update argument lists
+        let mut removed_args = HashSet::new();
+        let mut update_args = |args: &mut Vec<Value>| {
+          let mut index = 0;
+          args.retain(|a| {
+            index += 1;
+            let removed = !m.contains_key(&(index - 1));
+            // record removed values
+            if removed {
+              removed_args.insert(*a);
+            }
+            removed
+          });
+        };
+        match inst.kind_mut() {
           ValueKind::Branch(br) => {
             if br.true_bb() == b {
-              br.true_args_mut()
+              update_args(br.true_args_mut())
             } else {
-              br.false_args_mut()
+              update_args(br.false_args_mut())
             }
           }
-          ValueKind::Jump(jump) => jump.args_mut(),
-          _ => panic!("invalid branch/jump instruction"),
-        };
-        // update argument list
-        let mut removed_args = HashSet::new();
-        let mut index = 0;
-        args.retain(|a| {
-          index += 1;
-          let removed = !m.contains_key(&(index - 1));
-          // record removed values
-          if removed {
-            removed_args.insert(*a);
+          ValueKind::Jump(jump) => update_args(jump.args_mut()),
+          ValueKind::Switch(switch) => {
+            if switch.default_bb() == b {
+              update_args(switch.default_args_mut());
+            }
+            for (_, bb, args) in switch.cases_mut() {
+              if *bb == b {
+                update_args(args);
+              }
+            }
           }
-          removed
-        });
+          _ => panic!("invalid branch/jump/switch instruction"),
+        }
         // replace the existing instruction
         data.dfg_mut().replace_value_with(user).raw(inst);
         // remove unused values
@@ -142,13 +153,6 @@ impl DeadCodeElimination {
   }
 
   fn is_critical_inst(kind: &ValueKind) -> bool {
-    matches!(
-      kind,
-      ValueKind::Store(_)
-        | ValueKind::Call(_)
-        | ValueKind::Branch(_)
-        | ValueKind::Jump(_)
-        | ValueKind::Return(_)
-    )
+    kind.has_side_effects()
   }
 }
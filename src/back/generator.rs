@@ -1,5 +1,6 @@
-//! Koopa IR generator ([`Generator`]), name manager ([`NameManager`]) and
-//! Koopa IR visitor trait ([`Visitor`]) related implementations.
+//! Koopa IR generator ([`Generator`]), name manager ([`NameManager`]),
+//! naming policy trait ([`NamePolicy`]) and Koopa IR visitor trait
+//! ([`Visitor`]) related implementations.
 //!
 //! The Koopa IR generator converts in-memory Koopa IR programs into
 //! other forms by using IR visitors. IR visitors can use name manager
@@ -104,23 +105,40 @@ impl NameManager {
   ///
   /// Panics if the given value is a constant.
   pub fn value_name(&mut self, value: &ValueData) -> Rc<String> {
+    self.value_name_by(value, &mut NumberedNames)
+  }
+
+  /// Returns the name of the given value. Unnamed values are named by
+  /// the given naming policy when they are visited for the first time.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the given value is a constant.
+  pub fn value_name_by<P>(&mut self, value: &ValueData, policy: &mut P) -> Rc<String>
+  where
+    P: NamePolicy + ?Sized,
+  {
     assert!(!value.kind().is_const(), "can not name constants");
     if value.kind().is_global_alloc() {
-      self.value_name_impl(value, |s| &mut s.global_vars)
+      self.value_name_impl(value, policy, |s| &mut s.global_vars)
     } else {
-      self.value_name_impl(value, |s| &mut s.values)
+      self.value_name_impl(value, policy, |s| &mut s.values)
     }
   }
 
-  fn value_name_impl<F>(&mut self, value: &ValueData, value_set: F) -> Rc<String>
+  fn value_name_impl<P, F>(&mut self, value: &ValueData, policy: &mut P, value_set: F) -> Rc<String>
   where
+    P: NamePolicy + ?Sized,
     F: for<'a> Fn(&'a mut Self) -> &'a mut HashMap<*const ValueData, Rc<String>>,
   {
     let ptr: *const ValueData = value;
     if let Some(name) = value_set(self).get(&ptr) {
       name.clone()
     } else {
-      let name = self.next_name(value.name(), |s| &mut s.global_names);
+      let name = match value.name() {
+        Some(_) => self.next_name(value.name(), |s| &mut s.global_names),
+        None => self.next_name(&policy.name(value), |s| &mut s.global_names),
+      };
       let values = value_set(self);
       values.insert(ptr, name);
       values[&ptr].clone()
@@ -136,15 +154,21 @@ impl NameManager {
   /// and stores it to the given name set.
   fn next_name<F>(&mut self, name: &Option<String>, name_set: F) -> Rc<String>
   where
-    F: for<'a> FnOnce(&'a mut Self) -> &'a mut HashSet<StringRc>,
+    F: for<'a> Fn(&'a mut Self) -> &'a mut HashSet<StringRc>,
   {
     // check if there is a name
     if let Some(name) = name {
       self.next_name_str(name, name_set)
     } else {
-      // generate a temporary name
-      let name = self.prefix.temp_name(self.next_id);
-      self.next_id += 1;
+      // generate a temporary name,
+      // skip names that have already been used (like `%0`)
+      let name = loop {
+        let name = self.prefix.temp_name(self.next_id);
+        self.next_id += 1;
+        if !name_set(self).contains(&name) {
+          break name;
+        }
+      };
       let names = name_set(self);
       names.insert(name.clone().into());
       names.get(&name).unwrap().to_rc()
@@ -177,6 +201,32 @@ impl NameManager {
   }
 }
 
+/// Policy of naming unnamed values, used by the name manager.
+///
+/// Names returned by the policy are treated like names of named values,
+/// so they are prefixed according to [`Prefix`], and renamed with a
+/// suffix (like `%t_0`) if they are already taken. To keep the text form
+/// output parsable, names should be valid symbols, like `%t`.
+pub trait NamePolicy {
+  /// Returns the name of the given unnamed value, or `None` to use the
+  /// next numbered temporary name (like `%0`).
+  fn name(&mut self, value: &ValueData) -> Option<String>;
+
+  /// Called when a function is about to be generated.
+  fn enter_func(&mut self, _func: &FunctionData) {}
+}
+
+/// The default naming policy, which names all unnamed values with
+/// numbered temporary names, like `%0`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NumberedNames;
+
+impl NamePolicy for NumberedNames {
+  fn name(&mut self, _: &ValueData) -> Option<String> {
+    None
+  }
+}
+
 /// Kind of scope.
 #[derive(Clone, Copy, Default)]
 enum ScopeKind {
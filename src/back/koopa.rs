@@ -1,16 +1,44 @@
 //! Implementations of the visitor for the text form Koopa IR generator.
 
-use crate::back::{self, NameManager};
+use crate::back::{self, NameManager, NamePolicy, NumberedNames};
 use crate::ir::entities::{FunctionData, ValueData};
 use crate::ir::layout::BasicBlockNode;
 use crate::ir::values::*;
 use crate::ir::{BasicBlock, Program, Type, TypeKind, Value, ValueKind};
+use std::collections::HashMap;
 use std::io::{Result, Write};
+use std::rc::Rc;
 
 /// Visitor for generating the in-memeory form Koopa IR program into
 /// the text form.
-#[derive(Default)]
-pub struct Visitor;
+pub struct Visitor {
+  options: PrintOptions,
+  policy: Box<dyn NamePolicy>,
+}
+
+impl Visitor {
+  /// Creates a new visitor with the given print options.
+  pub fn with_options(options: PrintOptions) -> Self {
+    Self {
+      options,
+      policy: Box::new(NumberedNames),
+    }
+  }
+
+  /// Sets the naming policy of unnamed local values.
+  ///
+  /// The default policy is [`NumberedNames`]. The policy is not used if
+  /// [`PrintOptions::numbered_values`] is set.
+  pub fn set_name_policy<P: NamePolicy + 'static>(&mut self, policy: P) {
+    self.policy = Box::new(policy);
+  }
+}
+
+impl Default for Visitor {
+  fn default() -> Self {
+    Self::with_options(PrintOptions::default())
+  }
+}
 
 impl<W: Write> back::Visitor<W> for Visitor {
   type Output = ();
@@ -21,17 +49,60 @@ impl<W: Write> back::Visitor<W> for Visitor {
       nm,
       program,
       func: None,
+      options: &self.options,
+      policy: self.policy.as_mut(),
+      numbered: HashMap::new(),
     };
     visitor.visit()
   }
 }
 
+/// Options of the text form Koopa IR generator.
+///
+/// The default options produce the canonical text form, which can be
+/// parsed back by the frontend. Other options are for improving the
+/// readability, the output may not be parsed back.
+#[derive(Clone, Debug)]
+pub struct PrintOptions {
+  /// Width of the indentation of instructions.
+  pub indent: usize,
+  /// Prints the number of users of each instruction as comments.
+  pub used_by_comments: bool,
+  /// Prints local values with numbered names, ignoring their names.
+  pub numbered_values: bool,
+  /// Prints the type of each instruction after its name.
+  pub inline_types: bool,
+  /// Prints aggregates of characters as string literals, for example,
+  /// `{104, 105, 0}` is printed as `"hi\0"`.
+  ///
+  /// An aggregate is printed as a string literal if all its elements
+  /// are printable ASCII characters, `\n`, `\t` or `\r`, optionally
+  /// followed by `\0`s. Disabled by default, since string literals can
+  /// not be parsed in the classic dialect.
+  pub string_literals: bool,
+}
+
+impl Default for PrintOptions {
+  fn default() -> Self {
+    Self {
+      indent: 2,
+      used_by_comments: false,
+      numbered_values: false,
+      inline_types: false,
+      string_literals: false,
+    }
+  }
+}
+
 /// The implementation of text form Koopa IR generator.
 struct VisitorImpl<'a, W: Write> {
   w: &'a mut W,
   nm: &'a mut NameManager,
   program: &'a Program,
   func: Option<&'a FunctionData>,
+  options: &'a PrintOptions,
+  policy: &'a mut dyn NamePolicy,
+  numbered: HashMap<*const ValueData, Rc<String>>,
 }
 
 /// Returns a reference to the current function.
@@ -51,6 +122,12 @@ macro_rules! value {
 impl<'a, W: Write> VisitorImpl<'a, W> {
   /// Visits the program.
   fn visit(&mut self) -> Result<()> {
+    for (name, ty) in self.program.named_types().iter() {
+      writeln!(self.w, "type {} = {}", name, ty)?;
+    }
+    if !self.program.named_types().is_empty() {
+      writeln!(self.w)?;
+    }
     for inst in self.program.inst_layout() {
       self.visit_global_inst(&self.program.borrow_value(*inst))?;
     }
@@ -64,10 +141,15 @@ impl<'a, W: Write> VisitorImpl<'a, W> {
       let func = self.program.func(*func);
       self.func = Some(func);
       self.nm.enter_func_scope();
+      self.policy.enter_func(func);
+      self.numbered.clear();
       self.visit_func(func)?;
       self.nm.exit_func_scope();
+      // flush per function, so that large programs are not buffered
+      // entirely in the writer
+      self.w.flush()?;
     }
-    Ok(())
+    self.w.flush()
   }
 
   /// Generates the given function.
@@ -100,7 +182,8 @@ impl<'a, W: Write> VisitorImpl<'a, W> {
           write!(self.w, ", ")?;
         }
         let param = value!(self, *param);
-        write!(self.w, "{}: {}", self.nm.value_name(param), param.ty())?;
+        let name = self.local_name(param);
+        write!(self.w, "{}: {}", name, param.ty())?;
       }
     }
     write!(self.w, ")")?;
@@ -136,14 +219,15 @@ impl<'a, W: Write> VisitorImpl<'a, W> {
           write!(self.w, ", ")?;
         }
         let param = value!(self, *param);
-        write!(self.w, "{}: {}", self.nm.value_name(param), param.ty())?;
+        let name = self.local_name(param);
+        write!(self.w, "{}: {}", name, param.ty())?;
       }
       write!(self.w, ")")?;
     }
     writeln!(self.w, ":")?;
     // instrustions in basic block
     for inst in node.insts().keys() {
-      write!(self.w, "  ")?;
+      write!(self.w, "{:1$}", "", self.options.indent)?;
       self.visit_local_inst(value!(self, *inst))?;
     }
     Ok(())
@@ -163,6 +247,15 @@ impl<'a, W: Write> VisitorImpl<'a, W> {
       init.ty()
     )?;
     self.visit_global_const(&init)?;
+    if alloc.attrs().readonly {
+      write!(self.w, ", readonly")?;
+    }
+    if alloc.attrs().thread_local {
+      write!(self.w, ", threadlocal")?;
+    }
+    if let Some(align) = alloc.align() {
+      write!(self.w, ", align {}", align)?;
+    }
     writeln!(self.w)
   }
 
@@ -170,32 +263,68 @@ impl<'a, W: Write> VisitorImpl<'a, W> {
   fn visit_local_inst(&mut self, inst: &ValueData) -> Result<()> {
     // definition
     if !inst.ty().is_unit() {
-      write!(self.w, "{} = ", self.nm.value_name(inst))?;
+      let name = self.local_name(inst);
+      write!(self.w, "{}", name)?;
+      if self.options.inline_types {
+        write!(self.w, ": {}", inst.ty())?;
+      }
+      write!(self.w, " = ")?;
     }
     // content of instruction
     match inst.kind() {
-      ValueKind::Alloc(_) => self.visit_alloc(inst.ty()),
+      ValueKind::Alloc(v) => self.visit_alloc(inst.ty(), v),
       ValueKind::Load(v) => self.visit_load(v),
       ValueKind::Store(v) => self.visit_store(v),
       ValueKind::GetPtr(v) => self.visit_getptr(v),
       ValueKind::GetElemPtr(v) => self.visit_getelemptr(v),
+      ValueKind::ExtractValue(v) => self.visit_extract_value(v),
+      ValueKind::InsertValue(v) => self.visit_insert_value(v),
+      ValueKind::Copy(v) => self.visit_copy(v),
       ValueKind::Binary(v) => self.visit_binary(v),
+      ValueKind::Unary(v) => self.visit_unary(v),
+      ValueKind::CheckedBinary(v) => self.visit_checked_binary(v),
+      ValueKind::Assume(v) => self.visit_assume(v),
       ValueKind::Branch(v) => self.visit_branch(v),
       ValueKind::Jump(v) => self.visit_jump(v),
+      ValueKind::Switch(v) => self.visit_switch(v),
       ValueKind::Call(v) => self.visit_call(v),
       ValueKind::Return(v) => self.visit_return(v),
+      ValueKind::Unreachable(_) => write!(self.w, "unreachable"),
       _ => panic!("invalid local instruction"),
     }?;
+    // comments
+    if self.options.used_by_comments && !inst.ty().is_unit() {
+      write!(self.w, "  // used by {}", inst.used_by().len())?;
+    }
     writeln!(self.w)
   }
 
+  /// Returns the name of the given local value.
+  fn local_name(&mut self, value: &ValueData) -> Rc<String> {
+    if self.options.numbered_values {
+      let ptr: *const ValueData = value;
+      let nm = &mut self.nm;
+      self
+        .numbered
+        .entry(ptr)
+        .or_insert_with(|| nm.temp_value_name())
+        .clone()
+    } else {
+      self.nm.value_name_by(value, &mut *self.policy)
+    }
+  }
+
   /// Generates allocation.
-  fn visit_alloc(&mut self, ty: &Type) -> Result<()> {
+  fn visit_alloc(&mut self, ty: &Type, alloc: &Alloc) -> Result<()> {
     let base = match ty.kind() {
       TypeKind::Pointer(base) => base,
       _ => panic!("invalid pointer type"),
     };
-    write!(self.w, "alloc {}", base)
+    write!(self.w, "alloc {}", base)?;
+    if let Some(align) = alloc.align() {
+      write!(self.w, ", align {}", align)?;
+    }
+    Ok(())
   }
 
   /// Generates memory load.
@@ -228,9 +357,54 @@ impl<'a, W: Write> VisitorImpl<'a, W> {
     self.visit_value(gep.index())
   }
 
+  /// Generates element extraction.
+  fn visit_extract_value(&mut self, ev: &ExtractValue) -> Result<()> {
+    write!(self.w, "extractvalue ")?;
+    self.visit_value(ev.agg())?;
+    write!(self.w, ", {}", ev.index())
+  }
+
+  /// Generates element insertion.
+  fn visit_insert_value(&mut self, iv: &InsertValue) -> Result<()> {
+    write!(self.w, "insertvalue ")?;
+    self.visit_value(iv.agg())?;
+    write!(self.w, ", ")?;
+    self.visit_value(iv.value())?;
+    write!(self.w, ", {}", iv.index())
+  }
+
   /// Generates binary operation.
   fn visit_binary(&mut self, bin: &Binary) -> Result<()> {
     write!(self.w, "{} ", bin.op())?;
+    if !bin.flags().is_empty() {
+      write!(self.w, "{} ", bin.flags())?;
+    }
+    self.visit_value(bin.lhs())?;
+    write!(self.w, ", ")?;
+    self.visit_value(bin.rhs())
+  }
+
+  /// Generates unary operation.
+  fn visit_unary(&mut self, unary: &Unary) -> Result<()> {
+    write!(self.w, "{} ", unary.op())?;
+    self.visit_value(unary.opr())
+  }
+
+  /// Generates copy.
+  fn visit_copy(&mut self, copy: &CopyValue) -> Result<()> {
+    write!(self.w, "copy ")?;
+    self.visit_value(copy.src())
+  }
+
+  /// Generates assumption.
+  fn visit_assume(&mut self, assume: &Assume) -> Result<()> {
+    write!(self.w, "assume ")?;
+    self.visit_value(assume.cond())
+  }
+
+  /// Generates overflow-checked binary operation.
+  fn visit_checked_binary(&mut self, bin: &CheckedBinary) -> Result<()> {
+    write!(self.w, "checked {} ", bin.op())?;
     self.visit_value(bin.lhs())?;
     write!(self.w, ", ")?;
     self.visit_value(bin.rhs())
@@ -252,6 +426,23 @@ impl<'a, W: Write> VisitorImpl<'a, W> {
     self.visit_bb_target(jump.target(), jump.args())
   }
 
+  /// Generates multi-way branch.
+  fn visit_switch(&mut self, switch: &Switch) -> Result<()> {
+    write!(self.w, "switch ")?;
+    self.visit_value(switch.cond())?;
+    write!(self.w, ", ")?;
+    self.visit_bb_target(switch.default_bb(), switch.default_args())?;
+    write!(self.w, ", [")?;
+    for (i, (value, bb, args)) in switch.cases().iter().enumerate() {
+      if i != 0 {
+        write!(self.w, ", ")?;
+      }
+      write!(self.w, "{}: ", value)?;
+      self.visit_bb_target(*bb, args)?;
+    }
+    write!(self.w, "]")
+  }
+
   /// Generates function call.
   fn visit_call(&mut self, call: &Call) -> Result<()> {
     write!(
@@ -289,7 +480,8 @@ impl<'a, W: Write> VisitorImpl<'a, W> {
       if value.kind().is_const() {
         self.visit_local_const(value)
       } else {
-        write!(self.w, "{}", self.nm.value_name(value))
+        let name = self.local_name(value);
+        write!(self.w, "{}", name)
       }
     }
   }
@@ -301,6 +493,10 @@ impl<'a, W: Write> VisitorImpl<'a, W> {
       ValueKind::ZeroInit(_) => write!(self.w, "zeroinit"),
       ValueKind::Undef(_) => write!(self.w, "undef"),
       ValueKind::Aggregate(v) => {
+        let elems = v.elems().iter().map(|e| self.program.borrow_value(*e));
+        if let Some(s) = self.string_literal(elems.map(|e| integer_value(&e))) {
+          return write!(self.w, "{}", s);
+        }
         write!(self.w, "{{")?;
         for (i, elem) in v.elems().iter().enumerate() {
           if i != 0 {
@@ -310,10 +506,21 @@ impl<'a, W: Write> VisitorImpl<'a, W> {
         }
         write!(self.w, "}}")
       }
+      ValueKind::GlobalAlloc(_) => write!(self.w, "{}", self.nm.value_name(value)),
+      ValueKind::GetPtr(v) => self.visit_global_ptr("getptr", v.src(), v.index()),
+      ValueKind::GetElemPtr(v) => self.visit_global_ptr("getelemptr", v.src(), v.index()),
       _ => panic!("invalid constant"),
     }
   }
 
+  /// Generates the given global constant pointer calculation.
+  fn visit_global_ptr(&mut self, op: &str, src: Value, index: Value) -> Result<()> {
+    write!(self.w, "{} ", op)?;
+    self.visit_global_const(&self.program.borrow_value(src))?;
+    write!(self.w, ", ")?;
+    self.visit_global_const(&self.program.borrow_value(index))
+  }
+
   /// Generates the given local constant.
   fn visit_local_const(&mut self, value: &ValueData) -> Result<()> {
     match value.kind() {
@@ -321,6 +528,10 @@ impl<'a, W: Write> VisitorImpl<'a, W> {
       ValueKind::ZeroInit(_) => write!(self.w, "zeroinit"),
       ValueKind::Undef(_) => write!(self.w, "undef"),
       ValueKind::Aggregate(v) => {
+        let elems = v.elems().iter().map(|e| integer_value(value!(self, *e)));
+        if let Some(s) = self.string_literal(elems) {
+          return write!(self.w, "{}", s);
+        }
         write!(self.w, "{{")?;
         for (i, elem) in v.elems().iter().enumerate() {
           if i != 0 {
@@ -334,6 +545,39 @@ impl<'a, W: Write> VisitorImpl<'a, W> {
     }
   }
 
+  /// Returns the string literal form of the given elements of an
+  /// aggregate, or `None` if the aggregate can not be printed as a
+  /// string literal.
+  fn string_literal<I>(&self, elems: I) -> Option<String>
+  where
+    I: Iterator<Item = Option<i32>>,
+  {
+    if !self.options.string_literals {
+      return None;
+    }
+    let mut s = String::from('"');
+    let mut nul = false;
+    for elem in elems {
+      match elem? {
+        0 => {
+          nul = true;
+          s.push_str("\\0");
+        }
+        _ if nul => return None,
+        c @ 0x20..=0x7e => match c as u8 as char {
+          c @ ('"' | '\\') => s.extend(['\\', c]),
+          c => s.push(c),
+        },
+        0x0a => s.push_str("\\n"),
+        0x09 => s.push_str("\\t"),
+        0x0d => s.push_str("\\r"),
+        _ => return None,
+      }
+    }
+    // aggregates of only `\0`s are not printed as strings
+    (!s[1..].starts_with("\\0")).then(|| s + "\"")
+  }
+
   /// Generates the given basic block target.
   fn visit_bb_target(&mut self, bb: BasicBlock, params: &[Value]) -> Result<()> {
     write!(self.w, "{}", self.nm.bb_name(func!(self).dfg().bb(bb)))?;
@@ -351,10 +595,22 @@ impl<'a, W: Write> VisitorImpl<'a, W> {
   }
 }
 
+/// Returns the value of the given integer constant, or `None` if the
+/// given value is not an integer constant.
+fn integer_value(data: &ValueData) -> Option<i32> {
+  match data.kind() {
+    ValueKind::Integer(i) => Some(i.value()),
+    _ => None,
+  }
+}
+
 #[cfg(test)]
 mod test {
+  use super::*;
   use crate::back::KoopaGenerator;
   use crate::front::Driver;
+  use crate::ir::builder_traits::*;
+  use crate::ir::BinaryOp;
   use std::str;
 
   #[test]
@@ -409,6 +665,97 @@ fun @main(): i32 {
     assert_eq!(str::from_utf8(&gen.writer()).unwrap(), src);
   }
 
+  #[test]
+  fn dump_ir_switch() {
+    let src = r#"fun @test(@i: i32): i32 {
+%entry:
+  switch @i, %other, [0: %zero, -1: %end(1), 2: %end(@i)]
+
+%other:
+  switch @i, %end(-1), []
+
+%zero:
+  jump %end(0)
+
+%end(%ans: i32):
+  ret %ans
+}
+"#;
+    let driver: Driver<_> = src.into();
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen
+      .generate_on(&driver.generate_program().unwrap())
+      .unwrap();
+    assert_eq!(str::from_utf8(&gen.writer()).unwrap(), src);
+  }
+
+  #[test]
+  fn dump_ir_named_types() {
+    let src = r#"type @list = [*@list, 2]
+type @pair = [@list, 2]
+
+global @head = alloc *@list, zeroinit
+
+fun @first(): *@list {
+%entry:
+  %0 = load @head
+  ret %0
+}
+"#;
+    let driver: Driver<_> = src.into();
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen
+      .generate_on(&driver.generate_program().unwrap())
+      .unwrap();
+    assert_eq!(str::from_utf8(&gen.writer()).unwrap(), src);
+  }
+
+  #[test]
+  fn dump_ir_numeric_names() {
+    let src = r#"fun @test(): i32 {
+%entry:
+  %0 = add 1, 2
+  ret %0
+}
+"#;
+    let driver: Driver<_> = src.into();
+    let mut program = driver.generate_program().unwrap();
+    let func = *program.func_layout().first().unwrap();
+    let data = program.func_mut(func);
+    let entry = data.layout().entry_bb().unwrap();
+    let ret = data.terminator(entry).unwrap();
+    let value = data
+      .layout()
+      .bbs()
+      .node(&entry)
+      .unwrap()
+      .insts()
+      .front_key()
+      .copied()
+      .unwrap();
+    let two = data.dfg_mut().new_value().integer(2);
+    let mul = data.dfg_mut().new_value().binary(BinaryOp::Mul, value, two);
+    data
+      .layout_mut()
+      .bb_mut(entry)
+      .insts_mut()
+      .cursor_mut(ret)
+      .insert_key_before(mul)
+      .unwrap();
+    let new_ret = data.dfg_mut().new_value().ret(Some(mul));
+    data.set_terminator(entry, new_ret);
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen.generate_on(&program).unwrap();
+    let expected = r#"fun @test(): i32 {
+%entry:
+  %0 = add 1, 2
+  %1 = mul %0, 2
+  ret %1
+}
+"#;
+    assert_eq!(str::from_utf8(&gen.writer()).unwrap(), expected);
+  }
+
   #[test]
   fn dump_nested_loop() {
     let src = r#"decl @getint(): i32
@@ -451,4 +798,385 @@ fun @main(): i32 {
       .unwrap();
     assert_eq!(str::from_utf8(&gen.writer()).unwrap(), src);
   }
+
+  #[test]
+  fn dump_ir_with_options() {
+    let src = r#"fun @test(@i: i32): i32 {
+%entry:
+  %x = add @i, 1
+  %y = mul %x, %x
+  ret %y
+}
+"#;
+    let expected = r#"fun @test(%0: i32): i32 {
+%entry:
+    %1: i32 = add %0, 1  // used by 1
+    %2: i32 = mul %1, %1  // used by 1
+    ret %2
+}
+"#;
+    let driver: Driver<_> = src.into();
+    let options = PrintOptions {
+      indent: 4,
+      used_by_comments: true,
+      numbered_values: true,
+      inline_types: true,
+      string_literals: true,
+    };
+    let mut gen = KoopaGenerator::with_visitor(Vec::new(), Visitor::with_options(options));
+    gen
+      .generate_on(&driver.generate_program().unwrap())
+      .unwrap();
+    assert_eq!(str::from_utf8(&gen.writer()).unwrap(), expected);
+  }
+
+  #[test]
+  fn dump_ir_with_name_policy() {
+    #[derive(Default)]
+    struct KindNames {
+      loads: usize,
+      temps: usize,
+    }
+
+    impl NamePolicy for KindNames {
+      fn name(&mut self, value: &ValueData) -> Option<String> {
+        match value.kind() {
+          ValueKind::Load(_) => {
+            self.loads += 1;
+            Some(format!("%ld_{}", self.loads - 1))
+          }
+          ValueKind::Binary(_) => {
+            self.temps += 1;
+            Some(format!("%t_{}", self.temps - 1))
+          }
+          _ => None,
+        }
+      }
+
+      fn enter_func(&mut self, _: &FunctionData) {
+        *self = Self::default();
+      }
+    }
+
+    let src = r#"decl @getint(): i32
+
+fun @f(@p: *i32): i32 {
+%entry:
+  %0 = load @p
+  %1 = call @getint()
+  %x = add %0, %1
+  %2 = mul %x, %x
+  ret %2
+}
+
+fun @g(@p: *i32): i32 {
+%entry:
+  %0 = load @p
+  %1 = load @p
+  %2 = sub %0, %1
+  ret %2
+}
+"#;
+    let expected = r#"decl @getint(): i32
+
+fun @f(@p: *i32): i32 {
+%entry:
+  %ld_0 = load @p
+  %0 = call @getint()
+  %x = add %ld_0, %0
+  %t_0 = mul %x, %x
+  ret %t_0
+}
+
+fun @g(@p: *i32): i32 {
+%entry:
+  %ld_0 = load @p
+  %ld_1 = load @p
+  %t_0 = sub %ld_0, %ld_1
+  ret %t_0
+}
+"#;
+    let driver: Driver<_> = src.into();
+    let mut visitor = Visitor::default();
+    visitor.set_name_policy(KindNames::default());
+    let mut gen = KoopaGenerator::with_visitor(Vec::new(), visitor);
+    gen
+      .generate_on(&driver.generate_program().unwrap())
+      .unwrap();
+    assert_eq!(str::from_utf8(&gen.writer()).unwrap(), expected);
+  }
+
+  #[test]
+  fn write_to() {
+    struct Writer {
+      buf: Vec<u8>,
+      flushed: Vec<usize>,
+    }
+
+    impl Write for Writer {
+      fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.buf.write(buf)
+      }
+
+      fn flush(&mut self) -> Result<()> {
+        self.flushed.push(self.buf.len());
+        Ok(())
+      }
+    }
+
+    let src = r#"decl @getint(): i32
+
+fun @main(): i32 {
+%entry:
+  %0 = call @getint()
+  ret %0
+}
+"#;
+    let driver: Driver<_> = src.into();
+    let program = driver.generate_program().unwrap();
+    let mut w = Writer {
+      buf: Vec::new(),
+      flushed: Vec::new(),
+    };
+    back::write_to(&program, &mut w).unwrap();
+    assert_eq!(str::from_utf8(&w.buf).unwrap(), src);
+    let decl_len = "decl @getint(): i32\n".len();
+    assert_eq!(w.flushed, [decl_len, src.len(), src.len()]);
+  }
+
+  #[test]
+  fn dump_ir_checked_binary() {
+    let src = r#"fun @f(@a: i32, @b: i32): i32 {
+%entry:
+  %0 = checked mul @a, @b
+  %1 = alloc [i32, 2]
+  store %0, %1
+  %2 = getelemptr %1, 1
+  %3 = load %2
+  ret %3
+}
+"#;
+    let driver: Driver<_> = src.into();
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen
+      .generate_on(&driver.generate_program().unwrap())
+      .unwrap();
+    assert_eq!(str::from_utf8(&gen.writer()).unwrap(), src);
+  }
+
+  #[test]
+  fn dump_ir_unary() {
+    let src = r#"fun @f(@a: i32): i32 {
+%entry:
+  %0 = not @a
+  %1 = neg %0
+  ret %1
+}
+"#;
+    let driver: Driver<_> = src.into();
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen
+      .generate_on(&driver.generate_program().unwrap())
+      .unwrap();
+    assert_eq!(str::from_utf8(&gen.writer()).unwrap(), src);
+  }
+
+  #[test]
+  fn dump_ir_assume() {
+    let src = r#"fun @f(@a: i32): i32 {
+%entry:
+  %0 = lt @a, 10
+  assume %0
+  ret @a
+}
+"#;
+    let driver: Driver<_> = src.into();
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen
+      .generate_on(&driver.generate_program().unwrap())
+      .unwrap();
+    assert_eq!(str::from_utf8(&gen.writer()).unwrap(), src);
+  }
+
+  #[test]
+  fn dump_ir_copy() {
+    let src = r#"fun @f(@a: i32, @p: *i32): *i32 {
+%entry:
+  %0 = copy @a
+  %1 = copy @p
+  store %0, %1
+  ret %1
+}
+"#;
+    let driver: Driver<_> = src.into();
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen
+      .generate_on(&driver.generate_program().unwrap())
+      .unwrap();
+    assert_eq!(str::from_utf8(&gen.writer()).unwrap(), src);
+  }
+
+  #[test]
+  fn dump_ir_binary_flags() {
+    let src = r#"fun @f(@a: i32): i32 {
+%entry:
+  %0 = add nsw @a, 1
+  %1 = mul nsw nuw %0, 3
+  %2 = sar exact %1, 1
+  ret %2
+}
+"#;
+    let driver: Driver<_> = src.into();
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen
+      .generate_on(&driver.generate_program().unwrap())
+      .unwrap();
+    assert_eq!(str::from_utf8(&gen.writer()).unwrap(), src);
+  }
+
+  #[test]
+  fn dump_ir_align() {
+    let src = r#"global @x = alloc [i32, 4], zeroinit, align 16
+
+fun @f(): i32 {
+%entry:
+  %0 = alloc [i32, 4], align 16
+  %1 = alloc i32
+  %2 = getelemptr @x, 0
+  %3 = load %2
+  ret %3
+}
+"#;
+    let driver: Driver<_> = src.into();
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen
+      .generate_on(&driver.generate_program().unwrap())
+      .unwrap();
+    assert_eq!(str::from_utf8(&gen.writer()).unwrap(), src);
+    // alignments must be powers of two
+    let driver: Driver<_> = "fun @f() {\n%entry:\n  %0 = alloc i32, align 12\n  ret\n}\n".into();
+    assert!(driver.generate_program().is_err());
+  }
+
+  #[test]
+  fn dump_ir_global_attrs() {
+    let src = r#"global @x = alloc [i32, 2], {1, 2}, readonly
+global @y = alloc i32, zeroinit, threadlocal
+global @z = alloc i32, 0, readonly, threadlocal, align 8
+
+fun @f(): i32 {
+%entry:
+  %0 = load @z
+  ret %0
+}
+"#;
+    let driver: Driver<_> = src.into();
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen
+      .generate_on(&driver.generate_program().unwrap())
+      .unwrap();
+    assert_eq!(str::from_utf8(&gen.writer()).unwrap(), src);
+    // attributes are written before the alignment
+    let driver: Driver<_> = "global @x = alloc i32, 0, align 4, readonly\n".into();
+    assert!(driver.generate_program().is_err());
+  }
+
+  #[test]
+  fn dump_ir_aggregate_ops() {
+    let src = r#"fun @f(@p: *[i32, 2]): i32 {
+%entry:
+  %0 = load @p
+  %1 = insertvalue %0, 7, 0
+  %2 = extractvalue %1, 1
+  store %1, @p
+  ret %2
+}
+"#;
+    let driver: Driver<_> = src.into();
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen
+      .generate_on(&driver.generate_program().unwrap())
+      .unwrap();
+    assert_eq!(str::from_utf8(&gen.writer()).unwrap(), src);
+  }
+
+  #[test]
+  fn dump_ir_global_pointers() {
+    let src = r#"global @arr = alloc [[i32, 3], 2], zeroinit
+global @p = alloc *i32, getelemptr getelemptr @arr, 1, 2
+global @t = alloc [*[[i32, 3], 2], 2], {@arr, getptr @arr, 0}
+
+"#;
+    let driver: Driver<_> = src.into();
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen
+      .generate_on(&driver.generate_program().unwrap())
+      .unwrap();
+    assert_eq!(str::from_utf8(&gen.writer()).unwrap(), src);
+  }
+
+  #[test]
+  fn dump_ir_unreachable() {
+    let src = r#"fun @f(@a: i32): i32 {
+%entry:
+  br @a, %then, %else
+
+%then:
+  ret @a
+
+%else:
+  unreachable
+}
+"#;
+    let driver: Driver<_> = src.into();
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen
+      .generate_on(&driver.generate_program().unwrap())
+      .unwrap();
+    assert_eq!(str::from_utf8(&gen.writer()).unwrap(), src);
+  }
+
+  #[test]
+  fn dump_ir_strings() {
+    let src = r#"global @s = alloc [i32, 8], "hi \"x\"\n\0"
+global @t = alloc [[i32, 2], 2], {"ab", {1, 2}}
+global @z = alloc [i32, 2], {0, 0}
+
+fun @f() {
+%entry:
+  %0 = alloc [i32, 3]
+  store "\\\t\0", %0
+  ret
+}
+"#;
+    let driver: Driver<_> = src.into();
+    let mut program = driver.generate_program().unwrap();
+    let options = PrintOptions {
+      string_literals: true,
+      ..Default::default()
+    };
+    let mut gen = KoopaGenerator::with_visitor(Vec::new(), Visitor::with_options(options.clone()));
+    gen.generate_on(&program).unwrap();
+    assert_eq!(str::from_utf8(&gen.writer()).unwrap(), src);
+    // strings built by the builder, multi-byte characters are printed
+    // as bytes
+    let s = program.new_value().string_const("ok", true);
+    let g = program.new_value().global_alloc(s);
+    program.set_value_name(g, Some("@ok".into()));
+    let s = program.new_value().string_const("é", false);
+    let g = program.new_value().global_alloc(s);
+    program.set_value_name(g, Some("@e".into()));
+    let mut gen = KoopaGenerator::with_visitor(Vec::new(), Visitor::with_options(options));
+    gen.generate_on(&program).unwrap();
+    let expected = src.replace(
+      "{0, 0}\n",
+      "{0, 0}\nglobal @ok = alloc [i32, 3], \"ok\\0\"\nglobal @e = alloc [i32, 2], {195, 169}\n",
+    );
+    assert_eq!(str::from_utf8(&gen.writer()).unwrap(), expected);
+    // string literals are disabled by default
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen.generate_on(&program).unwrap();
+    let dump = String::from_utf8(gen.writer()).unwrap();
+    assert!(dump.starts_with("global @s = alloc [i32, 8], {104, 105, 32, 34, 120, 34, 10, 0}\n"));
+  }
 }
@@ -1,12 +1,14 @@
 //! Implementations of the visitor for the LLVM IR generator.
 
 use crate::back::{self, NameManager, Prefix};
-use crate::ir::entities::{FunctionData, ValueData};
+use crate::ir::entities::{CfgEdge, EdgeKind, FunctionData, ValueData};
 use crate::ir::layout::BasicBlockNode;
 use crate::ir::values::*;
 use crate::ir::{BasicBlock, Program, Type, TypeKind, Value, ValueKind};
-use std::io::{Result, Write};
+use std::collections::{BTreeSet, HashMap};
+use std::io::{Error, ErrorKind, Result, Write};
 use std::num::NonZeroUsize;
+use std::rc::Rc;
 
 /// Visitor for generating the in-memory form Koopa IR program into
 /// the text form LLVM IR program.
@@ -22,6 +24,10 @@ impl<W: Write> back::Visitor<W> for Visitor {
       nm,
       program,
       func: None,
+      bb: None,
+      edges: Vec::new(),
+      splits: HashMap::new(),
+      intrinsics: BTreeSet::new(),
     };
     visitor.visit()
   }
@@ -33,6 +39,15 @@ struct VisitorImpl<'a, W: Write> {
   nm: &'a mut NameManager,
   program: &'a Program,
   func: Option<&'a FunctionData>,
+  /// The current basic block.
+  bb: Option<BasicBlock>,
+  /// Control flow edges of the current function.
+  edges: Vec<CfgEdge>,
+  /// Labels of the blocks inserted on split edges, keyed by the source
+  /// basic block and the kind of the edge.
+  splits: HashMap<(BasicBlock, EdgeKind), Rc<String>>,
+  /// Declarations of used intrinsics.
+  intrinsics: BTreeSet<String>,
 }
 
 /// Returns a reference to the current function.
@@ -89,6 +104,13 @@ impl<'a, W: Write> VisitorImpl<'a, W> {
       self.visit_func(func)?;
       self.nm.exit_func_scope();
     }
+    // declarations of intrinsics
+    if !self.intrinsics.is_empty() {
+      writeln!(self.w)?;
+    }
+    for intrinsic in &self.intrinsics {
+      writeln!(self.w, "{}", intrinsic)?;
+    }
     Ok(())
   }
 
@@ -139,6 +161,7 @@ impl<'a, W: Write> VisitorImpl<'a, W> {
     // function body
     if !is_decl {
       writeln!(self.w, " {{")?;
+      self.split_edges(func);
       for (i, (bb, node)) in func.layout().bbs().iter().enumerate() {
         if i != 0 {
           writeln!(self.w)?;
@@ -158,43 +181,82 @@ impl<'a, W: Write> VisitorImpl<'a, W> {
     Ok(())
   }
 
+  /// Collects control flow edges of the given function, and assigns
+  /// labels to the edges that must be split.
+  ///
+  /// LLVM requires a phi function to have exactly one incoming value for
+  /// each predecessor, so if a terminator has multiple edges to the same
+  /// basic block with parameters, each of these edges goes through a new
+  /// block that only contains a `br`.
+  fn split_edges(&mut self, func: &FunctionData) {
+    self.edges = func.cfg_edges().collect();
+    self.splits.clear();
+    for edge in &self.edges {
+      let dup = self
+        .edges
+        .iter()
+        .filter(|e| e.from() == edge.from() && e.to() == edge.to())
+        .count()
+        > 1;
+      if dup && !func.dfg().bb(edge.to()).params().is_empty() {
+        let label = self.nm.temp_value_name();
+        self.splits.insert((edge.from(), edge.kind()), label);
+      }
+    }
+  }
+
   /// Generates the given basic block.
   fn visit_bb(&mut self, bb: BasicBlock, node: &BasicBlockNode) -> Result<()> {
+    self.bb = Some(bb);
     // basic block name
     let data = func!(self).dfg().bb(bb);
     writeln!(self.w, "{}:", &self.nm.bb_name(data)[1..])?;
     // basic block parameters (phi functions)
+    let edges: Vec<_> = self
+      .edges
+      .iter()
+      .filter(|e| e.to() == bb)
+      .cloned()
+      .collect();
     for (i, param) in data.params().iter().enumerate() {
       let param = value!(self, *param);
       write!(self.w, "  {} = phi ", self.nm.value_name(param))?;
       self.visit_type(param.ty())?;
       write!(self.w, " ")?;
-      for (k, user) in data.used_by().iter().enumerate() {
+      for (k, edge) in edges.iter().enumerate() {
         if k != 0 {
           write!(self.w, ", ")?;
         }
         write!(self.w, "[")?;
-        match value!(self, *user).kind() {
-          ValueKind::Branch(br) => {
-            if br.true_bb() == bb {
-              self.visit_value(false, br.true_args()[i])?;
-            } else {
-              self.visit_value(false, br.false_args()[i])?;
-            }
-          }
-          ValueKind::Jump(jump) => self.visit_value(false, jump.args()[i])?,
-          _ => panic!("invalid branch/jump instruction"),
-        }
+        self.visit_value(false, edge.args()[i])?;
         write!(self.w, ", ")?;
-        self.visit_bb_ref(func!(self).layout().parent_bb(*user).unwrap())?;
+        match self.splits.get(&(edge.from(), edge.kind())) {
+          Some(label) => write!(self.w, "{}", label)?,
+          None => self.visit_bb_ref(edge.from())?,
+        }
         write!(self.w, "]")?;
       }
       writeln!(self.w)?;
     }
     // instrustions in basic block
     for inst in node.insts().keys() {
+      let inst = value!(self, *inst);
+      // assumptions are only hints, drop them
+      if matches!(inst.kind(), ValueKind::Assume(_)) {
+        continue;
+      }
       write!(self.w, "  ")?;
-      self.visit_local_inst(value!(self, *inst))?;
+      self.visit_local_inst(inst)?;
+    }
+    // blocks on split edges
+    let splits: Vec<_> = (self.edges.iter().filter(|e| e.from() == bb))
+      .filter_map(|e| Some((self.splits.get(&(bb, e.kind()))?.clone(), e.to())))
+      .collect();
+    for (label, to) in splits {
+      writeln!(self.w, "\n{}:", &label[1..])?;
+      write!(self.w, "  br label ")?;
+      self.visit_bb_ref(to)?;
+      writeln!(self.w)?;
     }
     Ok(())
   }
@@ -206,42 +268,70 @@ impl<'a, W: Write> VisitorImpl<'a, W> {
       _ => panic!("invalid global instruction"),
     };
     let init = self.program.borrow_value(alloc.init());
-    write!(self.w, "{} = global ", self.nm.value_name(inst))?;
+    write!(self.w, "{} = ", self.nm.value_name(inst))?;
+    // read-only globals are placed in '.rodata', and thread-local
+    // globals are placed in '.tdata' or '.tbss'
+    if alloc.attrs().thread_local {
+      write!(self.w, "thread_local ")?;
+    }
+    if alloc.attrs().readonly {
+      write!(self.w, "constant ")?;
+    } else {
+      write!(self.w, "global ")?;
+    }
     self.visit_global_const(&init)?;
+    if let Some(align) = alloc.align() {
+      write!(self.w, ", align {}", align)?;
+    }
     writeln!(self.w)
   }
 
   /// Generates the given instruction.
   fn visit_local_inst(&mut self, inst: &ValueData) -> Result<()> {
     // definition
-    if !matches!(inst.kind(), ValueKind::Binary(_)) && !inst.ty().is_unit() {
+    if !matches!(
+      inst.kind(),
+      ValueKind::Binary(_) | ValueKind::CheckedBinary(_)
+    ) && !inst.ty().is_unit()
+    {
       write!(self.w, "{} = ", self.nm.value_name(inst))?;
     }
     // content of instruction
     match inst.kind() {
-      ValueKind::Alloc(_) => self.visit_alloc(inst.ty()),
+      ValueKind::Alloc(v) => self.visit_alloc(inst.ty(), v),
       ValueKind::Load(v) => self.visit_load(inst.ty(), v),
       ValueKind::Store(v) => self.visit_store(v),
       ValueKind::GetPtr(v) => self.visit_getptr(v),
       ValueKind::GetElemPtr(v) => self.visit_getelemptr(v),
+      ValueKind::ExtractValue(v) => self.visit_extract_value(v),
+      ValueKind::InsertValue(v) => self.visit_insert_value(v),
+      ValueKind::Copy(v) => self.visit_copy(v),
       ValueKind::Binary(v) => self.visit_binary(inst, v),
+      ValueKind::Unary(v) => self.visit_unary(v),
+      ValueKind::CheckedBinary(v) => self.visit_checked_binary(inst, v),
       ValueKind::Branch(v) => self.visit_branch(v),
       ValueKind::Jump(v) => self.visit_jump(v),
+      ValueKind::Switch(v) => self.visit_switch(v),
       ValueKind::Call(v) => self.visit_call(inst.ty(), v),
       ValueKind::Return(v) => self.visit_return(v),
+      ValueKind::Unreachable(_) => self.visit_unreachable(),
       _ => panic!("invalid instruction"),
     }?;
     writeln!(self.w)
   }
 
   /// Generates allocation.
-  fn visit_alloc(&mut self, ty: &Type) -> Result<()> {
+  fn visit_alloc(&mut self, ty: &Type, alloc: &Alloc) -> Result<()> {
     let base = match ty.kind() {
       TypeKind::Pointer(base) => base,
       _ => panic!("invalid pointer type"),
     };
     write!(self.w, "alloca ")?;
-    self.visit_type(base)
+    self.visit_type(base)?;
+    if let Some(align) = alloc.align() {
+      write!(self.w, ", align {}", align)?;
+    }
+    Ok(())
   }
 
   /// Generates memory load.
@@ -329,6 +419,103 @@ impl<'a, W: Write> VisitorImpl<'a, W> {
     Ok(())
   }
 
+  /// Generates unary operation.
+  fn visit_unary(&mut self, unary: &Unary) -> Result<()> {
+    match unary.op() {
+      UnaryOp::Not => {
+        write!(self.w, "xor i32 ")?;
+        self.visit_value(false, unary.opr())?;
+        write!(self.w, ", -1")
+      }
+      UnaryOp::Neg => {
+        write!(self.w, "sub i32 0, ")?;
+        self.visit_value(false, unary.opr())
+      }
+    }
+  }
+
+  /// Generates element extraction.
+  fn visit_extract_value(&mut self, ev: &ExtractValue) -> Result<()> {
+    write!(self.w, "extractvalue ")?;
+    self.visit_value(true, ev.agg())?;
+    write!(self.w, ", {}", ev.index())
+  }
+
+  /// Generates element insertion.
+  fn visit_insert_value(&mut self, iv: &InsertValue) -> Result<()> {
+    write!(self.w, "insertvalue ")?;
+    self.visit_value(true, iv.agg())?;
+    write!(self.w, ", ")?;
+    self.visit_value(true, iv.value())?;
+    write!(self.w, ", {}", iv.index())
+  }
+
+  /// Generates copy.
+  ///
+  /// LLVM IR has no copy instruction, so a `select` with a constant
+  /// condition is used, which will be folded by LLVM.
+  fn visit_copy(&mut self, copy: &CopyValue) -> Result<()> {
+    write!(self.w, "select i1 true, ")?;
+    self.visit_value(true, copy.src())?;
+    write!(self.w, ", ")?;
+    self.visit_value(true, copy.src())
+  }
+
+  /// Generates overflow-checked binary operation.
+  ///
+  /// The result of the overflow intrinsic is converted to `[2 x i32]`.
+  fn visit_checked_binary(&mut self, value: &ValueData, bin: &CheckedBinary) -> Result<()> {
+    let intrinsic = match bin.op() {
+      BinaryOp::Add => "sadd",
+      BinaryOp::Sub => "ssub",
+      BinaryOp::Mul => "smul",
+      _ => panic!("invalid checked binary operator"),
+    };
+    self.intrinsics.insert(format!(
+      "declare {{i32, i1}} @llvm.{}.with.overflow.i32(i32, i32)",
+      intrinsic
+    ));
+    // call the intrinsic
+    let pair = self.nm.temp_value_name();
+    write!(
+      self.w,
+      "{} = call {{i32, i1}} @llvm.{}.with.overflow.i32(i32 ",
+      pair, intrinsic
+    )?;
+    self.visit_value(false, bin.lhs())?;
+    write!(self.w, ", i32 ")?;
+    self.visit_value(false, bin.rhs())?;
+    writeln!(self.w, ")")?;
+    // build the result array
+    let result = self.nm.temp_value_name();
+    let overflow = self.nm.temp_value_name();
+    let flag = self.nm.temp_value_name();
+    let partial = self.nm.temp_value_name();
+    writeln!(
+      self.w,
+      "  {} = extractvalue {{i32, i1}} {}, 0",
+      result, pair
+    )?;
+    writeln!(
+      self.w,
+      "  {} = extractvalue {{i32, i1}} {}, 1",
+      overflow, pair
+    )?;
+    writeln!(self.w, "  {} = zext i1 {} to i32", flag, overflow)?;
+    writeln!(
+      self.w,
+      "  {} = insertvalue [2 x i32] undef, i32 {}, 0",
+      partial, result
+    )?;
+    write!(
+      self.w,
+      "  {} = insertvalue [2 x i32] {}, i32 {}, 1",
+      self.nm.value_name(value),
+      partial,
+      flag
+    )
+  }
+
   /// Generates branch.
   fn visit_branch(&mut self, br: &Branch) -> Result<()> {
     // generate condition
@@ -339,9 +526,9 @@ impl<'a, W: Write> VisitorImpl<'a, W> {
     // generate targets
     // ignore basic block parameters
     // because they are handled when generating basic blocks
-    self.visit_bb_ref(br.true_bb())?;
+    self.visit_edge_ref(EdgeKind::True, br.true_bb())?;
     write!(self.w, ", label ")?;
-    self.visit_bb_ref(br.false_bb())
+    self.visit_edge_ref(EdgeKind::False, br.false_bb())
   }
 
   /// Generates jump.
@@ -349,7 +536,23 @@ impl<'a, W: Write> VisitorImpl<'a, W> {
     write!(self.w, "br label ")?;
     // ignore basic block parameters
     // because they are handled when generating basic blocks
-    self.visit_bb_ref(jump.target())
+    self.visit_edge_ref(EdgeKind::Unconditional, jump.target())
+  }
+
+  /// Generates multi-way branch.
+  fn visit_switch(&mut self, switch: &Switch) -> Result<()> {
+    write!(self.w, "switch i32 ")?;
+    self.visit_value(false, switch.cond())?;
+    write!(self.w, ", label ")?;
+    // ignore basic block parameters
+    // because they are handled when generating basic blocks
+    self.visit_edge_ref(EdgeKind::SwitchDefault, switch.default_bb())?;
+    write!(self.w, " [")?;
+    for (value, bb, _) in switch.cases() {
+      write!(self.w, "\n    i32 {}, label ", value)?;
+      self.visit_edge_ref(EdgeKind::SwitchCase(*value), *bb)?;
+    }
+    write!(self.w, "\n  ]")
   }
 
   /// Generates function call.
@@ -380,6 +583,13 @@ impl<'a, W: Write> VisitorImpl<'a, W> {
     }
   }
 
+  /// Generates unreachable, which traps if executed.
+  fn visit_unreachable(&mut self) -> Result<()> {
+    self.intrinsics.insert("declare void @llvm.trap()".into());
+    writeln!(self.w, "call void @llvm.trap()")?;
+    write!(self.w, "  unreachable")
+  }
+
   /// Generates the given value.
   fn visit_value(&mut self, with_ty: bool, value: Value) -> Result<()> {
     if value.is_global() {
@@ -422,10 +632,31 @@ impl<'a, W: Write> VisitorImpl<'a, W> {
         }
         write!(self.w, "]")
       }
+      ValueKind::GlobalAlloc(_) => write!(self.w, "{}", self.nm.value_name(value)),
+      ValueKind::GetPtr(v) => self.visit_global_ptr(v.src(), v.index(), false),
+      ValueKind::GetElemPtr(v) => self.visit_global_ptr(v.src(), v.index(), true),
       _ => panic!("invalid constant"),
     }
   }
 
+  /// Generates the given global constant pointer calculation.
+  fn visit_global_ptr(&mut self, src: Value, index: Value, is_elem_ptr: bool) -> Result<()> {
+    let src = self.program.borrow_value(src);
+    write!(self.w, "getelementptr inbounds (")?;
+    self.visit_type(match src.ty().kind() {
+      TypeKind::Pointer(base) => base,
+      _ => panic!("invalid pointer type"),
+    })?;
+    write!(self.w, ", ")?;
+    self.visit_global_const(&src)?;
+    write!(self.w, ", ")?;
+    if is_elem_ptr {
+      write!(self.w, "i32 0, ")?;
+    }
+    self.visit_global_const(&self.program.borrow_value(index))?;
+    write!(self.w, ")")
+  }
+
   /// Generates the given local constant.
   fn visit_local_const(&mut self, with_ty: bool, value: &ValueData) -> Result<()> {
     if with_ty {
@@ -455,6 +686,14 @@ impl<'a, W: Write> VisitorImpl<'a, W> {
     write!(self.w, "{}", self.nm.bb_name(func!(self).dfg().bb(bb)))
   }
 
+  /// Generates the target of the given edge of the current basic block.
+  fn visit_edge_ref(&mut self, kind: EdgeKind, bb: BasicBlock) -> Result<()> {
+    match self.splits.get(&(self.bb.unwrap(), kind)) {
+      Some(label) => write!(self.w, "{}", label),
+      None => self.visit_bb_ref(bb),
+    }
+  }
+
   /// Generates the given type.
   fn visit_type(&mut self, ty: &Type) -> Result<()> {
     match ty.kind() {
@@ -481,6 +720,10 @@ impl<'a, W: Write> VisitorImpl<'a, W> {
         }
         write!(self.w, ")*")
       }
+      TypeKind::Named(name) => Err(Error::new(
+        ErrorKind::InvalidInput,
+        format!("named type '{}' is not supported by the LLVM backend", name),
+      )),
     }
   }
 }
@@ -745,6 +988,292 @@ $entry:
   store i32 %$6, i32* %_1
   ret i32 %$6
 }
+"#
+    );
+  }
+
+  #[test]
+  fn dump_unary() {
+    let driver: Driver<_> = r#"
+      fun @test(@a: i32): i32 {
+      %entry:
+        %0 = not @a
+        %1 = neg %0
+        ret %1
+      }
+    "#
+    .into();
+    let mut gen = LlvmGenerator::new(Vec::new());
+    gen
+      .generate_on(&driver.generate_program().unwrap())
+      .unwrap();
+    assert_eq!(
+      str::from_utf8(&gen.writer()).unwrap(),
+      r#"define i32 @test(i32 %a) {
+$entry:
+  %$0 = xor i32 %a, -1
+  %$1 = sub i32 0, %$0
+  ret i32 %$1
+}
+"#
+    );
+  }
+
+  #[test]
+  fn dump_align() {
+    let driver: Driver<_> = r#"
+      global @x = alloc [i32, 4], zeroinit, align 16
+
+      fun @test() {
+      %entry:
+        %0 = alloc [i32, 4], align 8
+        ret
+      }
+    "#
+    .into();
+    let mut gen = LlvmGenerator::new(Vec::new());
+    gen
+      .generate_on(&driver.generate_program().unwrap())
+      .unwrap();
+    assert_eq!(
+      str::from_utf8(&gen.writer()).unwrap(),
+      r#"@x = global [4 x i32] zeroinitializer, align 16
+
+define void @test() {
+$entry:
+  %$0 = alloca [4 x i32], align 8
+  ret void
+}
+"#
+    );
+  }
+
+  #[test]
+  fn dump_global_attrs() {
+    let driver: Driver<_> = r#"
+      global @x = alloc i32, 1, readonly
+      global @y = alloc i32, zeroinit, threadlocal
+      global @z = alloc i32, 2, readonly, threadlocal, align 8
+    "#
+    .into();
+    let mut gen = LlvmGenerator::new(Vec::new());
+    gen
+      .generate_on(&driver.generate_program().unwrap())
+      .unwrap();
+    assert_eq!(
+      str::from_utf8(&gen.writer()).unwrap(),
+      r#"@x = constant i32 1
+@y = thread_local global i32 zeroinitializer
+@z = thread_local constant i32 2, align 8
+
+"#
+    );
+  }
+
+  #[test]
+  fn dump_assume() {
+    let driver: Driver<_> = r#"
+      fun @test(@a: i32): i32 {
+      %entry:
+        %0 = lt @a, 10
+        assume %0
+        ret @a
+      }
+    "#
+    .into();
+    let mut gen = LlvmGenerator::new(Vec::new());
+    gen
+      .generate_on(&driver.generate_program().unwrap())
+      .unwrap();
+    assert_eq!(
+      str::from_utf8(&gen.writer()).unwrap(),
+      r#"define i32 @test(i32 %a) {
+$entry:
+  %$0 = icmp slt i32 %a, 10
+  %$1 = zext i1 %$0 to i32
+  ret i32 %a
+}
+"#
+    );
+  }
+
+  #[test]
+  fn dump_named_types() {
+    let driver: Driver<_> = r#"
+      type @list = [*@list, 2]
+      global @head = alloc *@list, zeroinit
+    "#
+    .into();
+    let mut gen = LlvmGenerator::new(Vec::new());
+    let error = gen
+      .generate_on(&driver.generate_program().unwrap())
+      .unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+  }
+
+  #[test]
+  fn dump_copy() {
+    let driver: Driver<_> = r#"
+      fun @test(@a: i32): i32 {
+      %entry:
+        %0 = copy @a
+        ret %0
+      }
+    "#
+    .into();
+    let mut gen = LlvmGenerator::new(Vec::new());
+    gen
+      .generate_on(&driver.generate_program().unwrap())
+      .unwrap();
+    assert_eq!(
+      str::from_utf8(&gen.writer()).unwrap(),
+      r#"define i32 @test(i32 %a) {
+$entry:
+  %$0 = select i1 true, i32 %a, i32 %a
+  ret i32 %$0
+}
+"#
+    );
+  }
+
+  #[test]
+  fn dump_switch_same_target() {
+    let driver: Driver<_> = r#"
+      fun @test(@a: i32): i32 {
+      %entry:
+        switch @a, %end(0), [1: %end(10), 2: %end(20), 3: %other]
+
+      %other:
+        jump %end(@a)
+
+      %end(%r: i32):
+        ret %r
+      }
+    "#
+    .into();
+    let mut gen = LlvmGenerator::new(Vec::new());
+    gen
+      .generate_on(&driver.generate_program().unwrap())
+      .unwrap();
+    assert_eq!(
+      str::from_utf8(&gen.writer()).unwrap(),
+      r#"define i32 @test(i32 %a) {
+$entry:
+  switch i32 %a, label %$0 [
+    i32 1, label %$1
+    i32 2, label %$2
+    i32 3, label %$other
+  ]
+
+$0:
+  br label %$end
+
+$1:
+  br label %$end
+
+$2:
+  br label %$end
+
+$other:
+  br label %$end
+
+$end:
+  %$r = phi i32 [0, %$0], [10, %$1], [20, %$2], [%a, %$other]
+  ret i32 %$r
+}
+"#
+    );
+  }
+
+  #[test]
+  fn dump_checked_binary() {
+    let driver: Driver<_> = r#"
+      fun @test(@a: i32, @p: *[i32, 2]): i32 {
+      %entry:
+        %r = checked add @a, 1
+        store %r, @p
+        ret @a
+      }
+    "#
+    .into();
+    let mut gen = LlvmGenerator::new(Vec::new());
+    gen
+      .generate_on(&driver.generate_program().unwrap())
+      .unwrap();
+    assert_eq!(
+      str::from_utf8(&gen.writer()).unwrap(),
+      r#"define i32 @test(i32 %a, [2 x i32]* %p) {
+$entry:
+  %$0 = call {i32, i1} @llvm.sadd.with.overflow.i32(i32 %a, i32 1)
+  %$1 = extractvalue {i32, i1} %$0, 0
+  %$2 = extractvalue {i32, i1} %$0, 1
+  %$3 = zext i1 %$2 to i32
+  %$4 = insertvalue [2 x i32] undef, i32 %$1, 0
+  %$r = insertvalue [2 x i32] %$4, i32 %$3, 1
+  store [2 x i32] %$r, [2 x i32]* %p
+  ret i32 %a
+}
+
+declare {i32, i1} @llvm.sadd.with.overflow.i32(i32, i32)
+"#
+    );
+  }
+
+  #[test]
+  fn dump_global_pointers() {
+    let driver: Driver<_> = r#"
+      global @arr = alloc [i32, 3], zeroinit
+      global @t = alloc [*i32, 2], {getelemptr @arr, 2, undef}
+      global @p = alloc *[i32, 3], getptr @arr, 0
+    "#
+    .into();
+    let mut gen = LlvmGenerator::new(Vec::new());
+    gen
+      .generate_on(&driver.generate_program().unwrap())
+      .unwrap();
+    assert_eq!(
+      str::from_utf8(&gen.writer()).unwrap(),
+      r#"@arr = global [3 x i32] zeroinitializer
+@t = global [2 x i32*] [i32* getelementptr inbounds ([3 x i32], [3 x i32]* @arr, i32 0, i32 2), i32* undef]
+@p = global [3 x i32]* getelementptr inbounds ([3 x i32], [3 x i32]* @arr, i32 0)
+
+"#
+    );
+  }
+
+  #[test]
+  fn dump_unreachable() {
+    let driver: Driver<_> = r#"
+      fun @test(@a: i32): i32 {
+      %entry:
+        br @a, %then, %else
+      %then:
+        ret @a
+      %else:
+        unreachable
+      }
+    "#
+    .into();
+    let mut gen = LlvmGenerator::new(Vec::new());
+    gen
+      .generate_on(&driver.generate_program().unwrap())
+      .unwrap();
+    assert_eq!(
+      str::from_utf8(&gen.writer()).unwrap(),
+      r#"define i32 @test(i32 %a) {
+$entry:
+  %$0 = icmp ne i32 %a, 0
+  br i1 %$0, label %$then, label %$else
+
+$then:
+  ret i32 %a
+
+$else:
+  call void @llvm.trap()
+  unreachable
+}
+
+declare void @llvm.trap()
 "#
     );
   }
@@ -3,10 +3,15 @@
 //! This modules provides generators for generating in-memory form
 //! Koopa IR to other forms, including:
 //!
-//! * The Koopa IR generator ([`Generator`]), name manager ([`NameManager`])
-//!   and the Koopa IR visitor trait ([`Visitor`]).
-//! * The text form Koopa IR generator ([`KoopaGenerator`]).
+//! * The Koopa IR generator ([`Generator`]), name manager ([`NameManager`]),
+//!   naming policies ([`NamePolicy`]) and the Koopa IR visitor trait
+//!   ([`Visitor`]).
+//! * The text form Koopa IR generator ([`KoopaGenerator`]), and
+//!   [`write_to`] for writing a program to any writer.
 //! * The LLVM IR generator ([`LlvmGenerator`]).
+//! * The register allocation interface ([`RegAlloc`]) for code generators,
+//!   with the default spill-all allocator ([`SpillAll`]) and a linear scan
+//!   allocator ([`LinearScan`]).
 //!
 //! # Examples
 //!
@@ -55,11 +60,28 @@
 pub mod generator;
 pub mod koopa;
 pub mod llvm;
+pub mod regalloc;
 
-pub use generator::{Generator, NameManager, Prefix, Visitor};
+pub use generator::{Generator, NameManager, NamePolicy, NumberedNames, Prefix, Visitor};
+pub use regalloc::{LinearScan, Location, RegAlloc, SpillAll};
+
+use crate::ir::Program;
+use std::io::{Result, Write};
 
 /// Generator for generating Koopa IR structures into text formatted Koopa IR.
+///
+/// Use [`Generator::with_visitor`] and [`koopa::Visitor::with_options`]
+/// to customize the output, see [`koopa::PrintOptions`]. Names of unnamed
+/// values can be customized by [`koopa::Visitor::set_name_policy`].
 pub type KoopaGenerator<W> = Generator<W, koopa::Visitor>;
 
 /// Generator for generating Koopa IR into LLVM IR.
 pub type LlvmGenerator<W> = Generator<W, llvm::Visitor>;
+
+/// Writes the given program in text form to the given writer.
+///
+/// The output is emitted incrementally and flushed after each function,
+/// without building the whole text form in memory.
+pub fn write_to<W: Write>(program: &Program, w: &mut W) -> Result<()> {
+  KoopaGenerator::new(w).generate_on(program)
+}
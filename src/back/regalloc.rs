@@ -0,0 +1,150 @@
+//! Register allocation interface for code generators.
+//!
+//! Allocation is decoupled from emission: a code generator computes the
+//! [`Liveness`] of a function, runs any [`RegAlloc`] implementation on it,
+//! and then emits code according to the returned [`Location`]s.
+
+use crate::ir::analysis::{LiveInterval, Liveness};
+use crate::ir::{FunctionData, Value};
+use std::collections::HashMap;
+
+/// Location of a variable after register allocation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Location {
+  /// The `n`th allocatable register of the target.
+  Reg(usize),
+  /// The `n`th stack slot of the function.
+  Stack(usize),
+}
+
+/// A register allocator.
+pub trait RegAlloc {
+  /// Assigns a location to every variable in the given liveness result
+  /// of the given function.
+  fn allocate(&mut self, func: &FunctionData, liveness: &Liveness) -> HashMap<Value, Location>;
+}
+
+/// Register allocator that puts all variables on the stack.
+///
+/// Each variable gets its own stack slot, in the order of the start
+/// points of live intervals.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SpillAll;
+
+impl RegAlloc for SpillAll {
+  fn allocate(&mut self, _: &FunctionData, liveness: &Liveness) -> HashMap<Value, Location> {
+    let intervals = liveness.intervals().iter().enumerate();
+    intervals
+      .map(|(i, interval)| (interval.value(), Location::Stack(i)))
+      .collect()
+  }
+}
+
+/// Linear scan register allocator over live intervals.
+///
+/// Implements the algorithm by Poletto and Sarkar: when all registers
+/// are occupied, the variable whose interval ends last is spilled.
+/// Spilled variables get their own stack slots.
+#[derive(Clone, Copy, Debug)]
+pub struct LinearScan {
+  regs: usize,
+}
+
+impl LinearScan {
+  /// Creates a new allocator with the given number of registers.
+  pub fn new(regs: usize) -> Self {
+    Self { regs }
+  }
+}
+
+impl RegAlloc for LinearScan {
+  fn allocate(&mut self, _: &FunctionData, liveness: &Liveness) -> HashMap<Value, Location> {
+    let mut locs = HashMap::new();
+    let mut free: Vec<_> = (0..self.regs).rev().collect();
+    // active intervals with registers, sorted by end point
+    let mut active: Vec<(LiveInterval, usize)> = Vec::new();
+    let mut slots = 0;
+    let mut spill = |locs: &mut HashMap<_, _>, value| {
+      locs.insert(value, Location::Stack(slots));
+      slots += 1;
+    };
+    for &interval in liveness.intervals() {
+      // expire old intervals
+      let expired = active.partition_point(|(i, _)| i.end() < interval.start());
+      free.extend(active.drain(..expired).map(|(_, r)| r));
+      // assign a register, or spill the interval that ends last
+      let reg = match free.pop() {
+        Some(reg) => reg,
+        None => match active.last() {
+          Some((last, _)) if last.end() > interval.end() => {
+            let (last, reg) = active.pop().unwrap();
+            spill(&mut locs, last.value());
+            reg
+          }
+          _ => {
+            spill(&mut locs, interval.value());
+            continue;
+          }
+        },
+      };
+      locs.insert(interval.value(), Location::Reg(reg));
+      let pos = active.partition_point(|(i, _)| i.end() <= interval.end());
+      active.insert(pos, (interval, reg));
+    }
+    locs
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::front::Driver;
+
+  fn check(func: &FunctionData, liveness: &Liveness, locs: &HashMap<Value, Location>) {
+    let intervals = liveness.intervals();
+    assert_eq!(locs.len(), intervals.len());
+    for (i, a) in intervals.iter().enumerate() {
+      for b in &intervals[i + 1..] {
+        let (la, lb) = (locs[&a.value()], locs[&b.value()]);
+        assert!(
+          la != lb || !a.overlaps(b),
+          "conflicting locations in `{}`",
+          func.name()
+        );
+      }
+    }
+  }
+
+  #[test]
+  fn allocate() {
+    let driver: Driver<_> = r#"
+fun @f(@a: i32, @b: i32): i32 {
+%entry:
+  %0 = add @a, @b
+  %1 = mul %0, @a
+  %2 = sub %1, @b
+  %3 = add %2, %0
+  %4 = mul %3, %1
+  ret %4
+}
+"#
+    .into();
+    let program = driver.generate_program().unwrap();
+    let func = program.funcs().values().next().unwrap();
+    let liveness = Liveness::compute(func);
+    let locs = SpillAll.allocate(func, &liveness);
+    check(func, &liveness, &locs);
+    assert!(locs.values().all(|l| matches!(l, Location::Stack(_))));
+    for regs in 0..6 {
+      let locs = LinearScan::new(regs).allocate(func, &liveness);
+      check(func, &liveness, &locs);
+      let used = locs.values().filter(|l| matches!(l, Location::Reg(_)));
+      assert!(used
+        .clone()
+        .all(|l| matches!(l, Location::Reg(r) if *r < regs)));
+      if regs >= 4 {
+        assert_eq!(used.count(), liveness.intervals().len());
+      }
+    }
+  }
+}
@@ -5,7 +5,7 @@
 //! ([`Parser`](crate::front::parser::Parser)) during the parsing process.
 
 use crate::front::span::Span;
-use crate::ir::BinaryOp;
+use crate::ir::{BinaryFlags, BinaryOp, GlobalAttrs, UnaryOp};
 use std::cmp::PartialEq;
 
 /// An abstract syntax tree (AST) of Koopa IR.
@@ -43,6 +43,8 @@ pub enum AstKind {
   PointerType(PointerType),
   /// Function type.
   FunType(FunType),
+  /// Named type.
+  NamedType(NamedType),
   /// Symbol refernce.
   SymbolRef(SymbolRef),
   /// Integer literal.
@@ -53,6 +55,8 @@ pub enum AstKind {
   Aggregate(Aggregate),
   /// Zero initializer.
   ZeroInit(ZeroInit),
+  /// Pointer calculation initializer.
+  PointerInit(PointerInit),
   /// Symbol definition.
   SymbolDef(SymbolDef),
   /// Global symbol definition.
@@ -69,22 +73,40 @@ pub enum AstKind {
   GetPointer(GetPointer),
   /// Element pointer calculation.
   GetElementPointer(GetElementPointer),
+  /// Element extraction.
+  ExtractValue(ExtractValue),
+  /// Element insertion.
+  InsertValue(InsertValue),
   /// Binary expression.
   BinaryExpr(BinaryExpr),
+  /// Unary expression.
+  UnaryExpr(UnaryExpr),
+  /// Overflow-checked binary expression.
+  CheckedBinaryExpr(CheckedBinaryExpr),
+  /// Copy.
+  Copy(Copy),
+  /// Assumption.
+  Assume(Assume),
   /// Branch.
   Branch(Branch),
   /// Jump.
   Jump(Jump),
+  /// Multi-way branch.
+  Switch(Switch),
   /// Function call.
   FunCall(FunCall),
   /// Return.
   Return(Return),
+  /// Unreachable.
+  Unreachable(Unreachable),
   /// Function definition.
   FunDef(FunDef),
   /// Basic block.
   Block(Block),
   /// Function declaration.
   FunDecl(FunDecl),
+  /// Named type definition.
+  TypeDef(TypeDef),
   /// End of file.
   End(End),
   /// Error.
@@ -143,6 +165,19 @@ impl FunType {
   }
 }
 
+/// Named type.
+#[derive(Debug, PartialEq, Eq)]
+pub struct NamedType {
+  pub name: String,
+}
+
+impl NamedType {
+  /// Creates a new boxed `NamedType` AST.
+  pub fn new_boxed(span: Span, name: String) -> AstBox {
+    Ast::new_boxed(span, AstKind::NamedType(Self { name }))
+  }
+}
+
 /// Symbol refernce.
 #[derive(Debug, PartialEq, Eq)]
 pub struct SymbolRef {
@@ -193,6 +228,31 @@ impl Aggregate {
   }
 }
 
+/// Pointer calculation initializer.
+///
+/// The source is a symbol reference, or another pointer calculation
+/// initializer.
+#[derive(Debug, PartialEq)]
+pub struct PointerInit {
+  pub is_elem_ptr: bool,
+  pub src: AstBox,
+  pub index: i32,
+}
+
+impl PointerInit {
+  /// Creates a new boxed `PointerInit` AST.
+  pub fn new_boxed(span: Span, is_elem_ptr: bool, src: AstBox, index: i32) -> AstBox {
+    Ast::new_boxed(
+      span,
+      AstKind::PointerInit(Self {
+        is_elem_ptr,
+        src,
+        index,
+      }),
+    )
+  }
+}
+
 /// Zero initializer.
 #[derive(Debug, PartialEq, Eq)]
 pub struct ZeroInit;
@@ -236,12 +296,13 @@ impl GlobalDef {
 #[derive(Debug, PartialEq)]
 pub struct MemDecl {
   pub ty: AstBox,
+  pub align: Option<usize>,
 }
 
 impl MemDecl {
   /// Creates a new boxed `MemDecl` AST.
-  pub fn new_boxed(span: Span, ty: AstBox) -> AstBox {
-    Ast::new_boxed(span, AstKind::MemDecl(Self { ty }))
+  pub fn new_boxed(span: Span, ty: AstBox, align: Option<usize>) -> AstBox {
+    Ast::new_boxed(span, AstKind::MemDecl(Self { ty, align }))
   }
 }
 
@@ -250,12 +311,28 @@ impl MemDecl {
 pub struct GlobalDecl {
   pub ty: AstBox,
   pub init: AstBox,
+  pub attrs: GlobalAttrs,
+  pub align: Option<usize>,
 }
 
 impl GlobalDecl {
   /// Creates a new boxed `GlobalDecl` AST.
-  pub fn new_boxed(span: Span, ty: AstBox, init: AstBox) -> AstBox {
-    Ast::new_boxed(span, AstKind::GlobalDecl(Self { ty, init }))
+  pub fn new_boxed(
+    span: Span,
+    ty: AstBox,
+    init: AstBox,
+    attrs: GlobalAttrs,
+    align: Option<usize>,
+  ) -> AstBox {
+    Ast::new_boxed(
+      span,
+      AstKind::GlobalDecl(Self {
+        ty,
+        init,
+        attrs,
+        align,
+      }),
+    )
   }
 }
 
@@ -314,18 +391,124 @@ impl GetElementPointer {
   }
 }
 
+/// Element extraction.
+#[derive(Debug, PartialEq)]
+pub struct ExtractValue {
+  pub symbol: String,
+  pub index: usize,
+}
+
+impl ExtractValue {
+  /// Creates a new boxed `ExtractValue` AST.
+  pub fn new_boxed(span: Span, symbol: String, index: usize) -> AstBox {
+    Ast::new_boxed(span, AstKind::ExtractValue(Self { symbol, index }))
+  }
+}
+
+/// Element insertion.
+#[derive(Debug, PartialEq)]
+pub struct InsertValue {
+  pub symbol: String,
+  pub value: AstBox,
+  pub index: usize,
+}
+
+impl InsertValue {
+  /// Creates a new boxed `InsertValue` AST.
+  pub fn new_boxed(span: Span, symbol: String, value: AstBox, index: usize) -> AstBox {
+    Ast::new_boxed(
+      span,
+      AstKind::InsertValue(Self {
+        symbol,
+        value,
+        index,
+      }),
+    )
+  }
+}
+
 /// Binary expression.
 #[derive(Debug, PartialEq)]
 pub struct BinaryExpr {
   pub op: BinaryOp,
+  pub flags: BinaryFlags,
   pub lhs: AstBox,
   pub rhs: AstBox,
 }
 
 impl BinaryExpr {
   /// Creates a new boxed `BinaryExpr` AST.
+  pub fn new_boxed(
+    span: Span,
+    op: BinaryOp,
+    flags: BinaryFlags,
+    lhs: AstBox,
+    rhs: AstBox,
+  ) -> AstBox {
+    Ast::new_boxed(
+      span,
+      AstKind::BinaryExpr(Self {
+        op,
+        flags,
+        lhs,
+        rhs,
+      }),
+    )
+  }
+}
+
+/// Unary expression.
+#[derive(Debug, PartialEq)]
+pub struct UnaryExpr {
+  pub op: UnaryOp,
+  pub opr: AstBox,
+}
+
+impl UnaryExpr {
+  /// Creates a new boxed `UnaryExpr` AST.
+  pub fn new_boxed(span: Span, op: UnaryOp, opr: AstBox) -> AstBox {
+    Ast::new_boxed(span, AstKind::UnaryExpr(Self { op, opr }))
+  }
+}
+
+/// Overflow-checked binary expression.
+#[derive(Debug, PartialEq)]
+pub struct CheckedBinaryExpr {
+  pub op: BinaryOp,
+  pub lhs: AstBox,
+  pub rhs: AstBox,
+}
+
+impl CheckedBinaryExpr {
+  /// Creates a new boxed `CheckedBinaryExpr` AST.
   pub fn new_boxed(span: Span, op: BinaryOp, lhs: AstBox, rhs: AstBox) -> AstBox {
-    Ast::new_boxed(span, AstKind::BinaryExpr(Self { op, lhs, rhs }))
+    Ast::new_boxed(span, AstKind::CheckedBinaryExpr(Self { op, lhs, rhs }))
+  }
+}
+
+/// Copy.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Copy {
+  pub symbol: String,
+}
+
+impl Copy {
+  /// Creates a new boxed `Copy` AST.
+  pub fn new_boxed(span: Span, symbol: String) -> AstBox {
+    Ast::new_boxed(span, AstKind::Copy(Self { symbol }))
+  }
+}
+
+/// Assumption.
+#[derive(Debug, PartialEq)]
+pub struct Assume {
+  pub cond: AstBox,
+}
+
+impl Assume {
+  /// Creates a new boxed `Assume` AST.
+  pub fn new_boxed(span: Span, cond: AstBox) -> AstBox {
+    Ast::new_boxed(span, AstKind::Assume(Self { cond }))
   }
 }
 
@@ -376,6 +559,36 @@ impl Jump {
   }
 }
 
+/// Multi-way branch.
+#[derive(Debug, PartialEq)]
+pub struct Switch {
+  pub cond: AstBox,
+  pub default: String,
+  pub default_args: Vec<AstBox>,
+  pub cases: Vec<(i32, String, Vec<AstBox>)>,
+}
+
+impl Switch {
+  /// Creates a new boxed `Switch` AST.
+  pub fn new_boxed(
+    span: Span,
+    cond: AstBox,
+    default: String,
+    default_args: Vec<AstBox>,
+    cases: Vec<(i32, String, Vec<AstBox>)>,
+  ) -> AstBox {
+    Ast::new_boxed(
+      span,
+      AstKind::Switch(Self {
+        cond,
+        default,
+        default_args,
+        cases,
+      }),
+    )
+  }
+}
+
 /// Function call.
 #[derive(Debug, PartialEq)]
 pub struct FunCall {
@@ -403,6 +616,17 @@ impl Return {
   }
 }
 
+/// Unreachable.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Unreachable;
+
+impl Unreachable {
+  /// Creates a new boxed `Unreachable` AST.
+  pub fn new_boxed(span: Span) -> AstBox {
+    Ast::new_boxed(span, AstKind::Unreachable(Self))
+  }
+}
+
 /// Function definition.
 #[derive(Debug, PartialEq)]
 pub struct FunDef {
@@ -475,6 +699,20 @@ impl FunDecl {
   }
 }
 
+/// Named type definition.
+#[derive(Debug, PartialEq)]
+pub struct TypeDef {
+  pub name: String,
+  pub ty: AstBox,
+}
+
+impl TypeDef {
+  /// Creates a new boxed `TypeDef` AST.
+  pub fn new_boxed(span: Span, name: String, ty: AstBox) -> AstBox {
+    Ast::new_boxed(span, AstKind::TypeDef(Self { name, ty }))
+  }
+}
+
 /// End of file.
 #[derive(Debug, PartialEq, Eq)]
 pub struct End;
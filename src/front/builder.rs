@@ -1,12 +1,19 @@
 //! Koopa IR builder ([`Builder`]) related implementations.
 
 use crate::front::ast::{self, AstBox, AstKind};
-use crate::front::span::{Error, Span};
+use crate::front::span::{Error, Pos, Span};
 use crate::ir::builder_traits::*;
 use crate::ir::dfg::DataFlowGraph;
-use crate::ir::{BasicBlock, Function, FunctionData, Program, Type, TypeKind, Value};
+use crate::ir::entities::DebugVar;
+use crate::ir::span::SourceSpan;
+use crate::ir::values::CheckedBinary;
+use crate::ir::ValueKind;
+use crate::ir::{
+  BasicBlock, BinaryOp, Function, FunctionData, Program, Type, TypeKind, UnaryOp, Value,
+};
 use crate::{log_error, log_warning, return_error};
 use std::collections::{HashMap, HashSet, VecDeque};
+use std::rc::Rc;
 
 /// Basic block information.
 struct BasicBlockInfo {
@@ -25,17 +32,64 @@ impl BasicBlockInfo {
   }
 }
 
+/// Options of the Koopa IR builder.
+#[derive(Clone, Debug)]
+pub struct BuildOptions {
+  /// Folds binary expressions whose operands are both integer constants
+  /// into integer constants while building.
+  ///
+  /// Only operations whose results are well-defined are folded, others
+  /// (e.g. division by zero or signed overflow) are left untouched.
+  pub fold_constants: bool,
+  /// Maximum total number of elements of array types, nested array
+  /// types are counted as a whole (e.g. `[[i32, 3], 2]` has 6 elements).
+  ///
+  /// Array types exceeding the limit are reported as errors.
+  pub max_array_elems: usize,
+  /// Records the source span of each global allocation and instruction
+  /// in the generated program, see [`Program::span`].
+  pub keep_spans: bool,
+  /// Records source variables of `alloc` instructions from metadata
+  /// comments in the form `//@var NAME [LINE]` on the same line, see
+  /// [`FunctionData::debug_var`]. `LINE` defaults to the line of the
+  /// instruction.
+  pub keep_debug_vars: bool,
+}
+
+impl Default for BuildOptions {
+  fn default() -> Self {
+    Self {
+      fold_constants: false,
+      max_array_elems: 1 << 24,
+      keep_spans: false,
+      keep_debug_vars: false,
+    }
+  }
+}
+
 /// Builder for building Koopa IR from ASTs.
 ///
 /// `Builder` performs semantic checks (e.g. type checking) on
 /// Koopa IR ASTs, and then builds the in-memory form Koopa IR.
 #[derive(Default)]
 pub struct Builder {
+  options: BuildOptions,
   program: Program,
   global_vars: HashMap<String, Value>,
   global_funcs: HashMap<String, Function>,
   local_bbs: HashMap<String, BasicBlockInfo>,
   local_symbols: HashSet<String>,
+  metadata: HashMap<u32, String>,
+  file: Option<Rc<str>>,
+}
+
+/// Returns the total number of elements of the given type, or `None`
+/// if the number overflows. Non-array types have one element.
+fn array_elems(ty: &Type) -> Option<usize> {
+  match ty.kind() {
+    TypeKind::Array(base, len) => array_elems(base)?.checked_mul(*len),
+    _ => Some(1),
+  }
 }
 
 /// Result returned by value generator methods in `Builder`.
@@ -87,6 +141,12 @@ macro_rules! generate_init {
         let elems = agg.elems.iter().map($agg_rec).collect::<Result<_, _>>()?;
         Ok($builder.aggregate(elems))
       }
+      AstKind::SymbolRef(_) | AstKind::PointerInit(_) => {
+        return_error!(
+          $ast.span,
+          "pointer initializers can only be used in global initializers"
+        )
+      }
       _ => panic!("invalid initializer AST"),
     }
   };
@@ -98,17 +158,36 @@ impl Builder {
     Self::default()
   }
 
+  /// Creates a new builder with the given build options.
+  pub fn with_options(options: BuildOptions) -> Self {
+    Self {
+      options,
+      ..Self::default()
+    }
+  }
+
   /// Builds the given AST into IR.
   pub fn build_on(&mut self, ast: &AstBox) {
     match &ast.kind {
       AstKind::GlobalDef(def) => self.build_on_global_def(&ast.span, def),
       AstKind::FunDef(def) => self.build_on_fun_def(&ast.span, def),
       AstKind::FunDecl(decl) => self.build_on_fun_decl(&ast.span, decl),
+      AstKind::TypeDef(def) => self.build_on_type_def(&ast.span, def),
       AstKind::Error(_) | AstKind::End(_) => { /* ignore errors and ends */ }
       _ => panic!("invalid AST input"),
     }
   }
 
+  /// Adds metadata comments for the following ASTs,
+  /// see [`Lexer::take_metadata`](crate::front::lexer::Lexer::take_metadata).
+  pub fn add_metadata(&mut self, metadata: Vec<(Pos, String)>) {
+    if self.options.keep_debug_vars {
+      self
+        .metadata
+        .extend(metadata.into_iter().map(|(pos, m)| (pos.line(), m)));
+    }
+  }
+
   /// Consumes the builder and get the generated program.
   ///
   /// Available only when no error has occurred.
@@ -144,12 +223,38 @@ impl Builder {
       .collect()
   }
 
+  /// Builds on named type definitions.
+  fn build_on_type_def(&mut self, span: &Span, ast: &ast::TypeDef) {
+    let ty = self.generate_type(&ast.ty);
+    if let Err(e) = self.program.named_types_mut().define(&ast.name, ty) {
+      log_error!(span, "{}", e);
+    }
+  }
+
+  /// Converts the given span into a source span in the current input file.
+  fn source_span(&mut self, span: &Span) -> SourceSpan {
+    let file = self.file.get_or_insert_with(|| Span::file_name().into());
+    span.to_source_span(file.clone())
+  }
+
   /// Builds on global symbol definitions.
   fn build_on_global_def(&mut self, span: &Span, ast: &ast::GlobalDef) {
     // create global allocation
     let decl = unwrap_ast!(ast.value, GlobalDecl);
-    if let Ok(init) = self.generate_global_init(&Self::generate_type(&decl.ty), &decl.init) {
-      let alloc = self.program.new_value().global_alloc(init);
+    let ty = self.generate_type(&decl.ty);
+    if !ty.is_allocatable() {
+      log_error!(decl.ty.span, "type '{}' can not be allocated", ty);
+      return;
+    }
+    if let Ok(init) = self.generate_global_init(&ty, &decl.init) {
+      let alloc = (self.program.new_value()).global_alloc_with_align(init, decl.align);
+      if !decl.attrs.is_empty() {
+        self.program.set_global_attrs(alloc, decl.attrs);
+      }
+      if self.options.keep_spans {
+        let span = self.source_span(span);
+        self.program.set_span(alloc, Some(span));
+      }
       // set name for the created value
       if !ast.name.is_temp() {
         self.program.set_value_name(alloc, Some(ast.name.clone()));
@@ -173,14 +278,14 @@ impl Builder {
     let ret_ty = ast
       .ret
       .as_ref()
-      .map_or_else(Type::get_unit, Self::generate_type);
+      .map_or_else(Type::get_unit, |t| self.generate_type(t));
     // create function definition
     let def = FunctionData::with_param_names(
       ast.name.clone(),
       ast
         .params
         .iter()
-        .map(|(n, a)| ((!n.is_temp()).then(|| n.clone()), Self::generate_type(a)))
+        .map(|(n, a)| ((!n.is_temp()).then(|| n.clone()), self.generate_type(a)))
         .collect(),
       ret_ty.clone(),
     );
@@ -220,11 +325,11 @@ impl Builder {
     // create function declaration
     let decl = FunctionData::new_decl(
       ast.name.clone(),
-      ast.params.iter().map(Self::generate_type).collect(),
+      ast.params.iter().map(|t| self.generate_type(t)).collect(),
       ast
         .ret
         .as_ref()
-        .map_or_else(Type::get_unit, Self::generate_type),
+        .map_or_else(Type::get_unit, |t| self.generate_type(t)),
     );
     // add to program
     let func = self.program.new_func(decl);
@@ -286,7 +391,11 @@ impl Builder {
             add_target(fbb);
           }
           AstKind::Jump(ast::Jump { target, .. }) => add_target(target),
-          AstKind::Return(_) | AstKind::Error(_) => {}
+          AstKind::Switch(ast::Switch { default, cases, .. }) => {
+            add_target(default);
+            cases.iter().for_each(|(_, bb, _)| add_target(bb));
+          }
+          AstKind::Return(_) | AstKind::Unreachable(_) | AstKind::Error(_) => {}
           _ => panic!("invalid end statement"),
         }
       }
@@ -309,7 +418,7 @@ impl Builder {
       let params = block
         .params
         .iter()
-        .map(|(n, a)| ((!n.is_temp()).then(|| n.clone()), Self::generate_type(a)))
+        .map(|(n, a)| ((!n.is_temp()).then(|| n.clone()), self.generate_type(a)))
         .collect();
       // create the current basic block
       let bb = self
@@ -362,6 +471,10 @@ impl Builder {
           add_pred(fbb);
         }
         AstKind::Jump(ast::Jump { target, .. }) => add_pred(target),
+        AstKind::Switch(ast::Switch { default, cases, .. }) => {
+          add_pred(default);
+          cases.iter().for_each(|(_, bb, _)| add_pred(bb));
+        }
         _ => {}
       }
     }
@@ -371,47 +484,154 @@ impl Builder {
   fn build_on_block(&mut self, func: Function, ret_ty: &Type, ast: &ast::Block) {
     // generate each statements
     for stmt in &ast.stmts {
-      if let Ok(stmt) = self.generate_stmt(func, &ast.name, ret_ty, stmt) {
+      if let Ok(inst) = self.generate_stmt(func, &ast.name, ret_ty, stmt) {
+        // skip folded constants
+        if self.program.func(func).dfg().value(inst).kind().is_const() {
+          continue;
+        }
+        let span = (self.options.keep_spans).then(|| self.source_span(&stmt.span));
         let info = self.local_bbs.get_mut(&ast.name).unwrap();
         // add statement to the current basic block
-        self
-          .program
-          .func_mut(func)
+        let data = self.program.func_mut(func);
+        data
           .layout_mut()
           .bb_mut(info.bb)
           .insts_mut()
-          .push_key_back(stmt)
+          .push_key_back(inst)
           .unwrap();
+        if span.is_some() {
+          data.set_span(inst, span);
+        }
+        if self.options.keep_debug_vars {
+          self.build_debug_var(func, inst, &stmt.span);
+        }
       }
     }
   }
 
+  /// Records the source variable of the given instruction if it is an
+  /// `alloc` with a variable metadata comment on the same line.
+  fn build_debug_var(&mut self, func: Function, inst: Value, span: &Span) {
+    let data = self.program.func_mut(func);
+    let ty = match (
+      data.dfg().value(inst).kind(),
+      data.dfg().value(inst).ty().kind(),
+    ) {
+      (ValueKind::Alloc(_), TypeKind::Pointer(base)) => base.clone(),
+      _ => return,
+    };
+    let line = span.start().line();
+    let metadata = match self.metadata.get(&line) {
+      Some(m) => m,
+      None => return,
+    };
+    let mut args = metadata.split_whitespace();
+    if args.next() != Some("var") {
+      return;
+    }
+    let var = match (args.next(), args.next().map(str::parse), args.next()) {
+      (Some(name), None, None) => Some(DebugVar::new(name.into(), ty, line)),
+      (Some(name), Some(Ok(line)), None) => Some(DebugVar::new(name.into(), ty, line)),
+      _ => None,
+    };
+    match var {
+      Some(var) => data.set_debug_var(inst, Some(var)),
+      None => log_warning!(span, "invalid variable metadata '{}', ignored", metadata),
+    }
+  }
+
   /// Generates the type by the given AST.
-  fn generate_type(ast: &AstBox) -> Type {
+  fn generate_type(&self, ast: &AstBox) -> Type {
     match &ast.kind {
       AstKind::IntType(_) => Type::get_i32(),
-      AstKind::ArrayType(ast) => Type::get_array(Self::generate_type(&ast.base), ast.len),
-      AstKind::PointerType(ast) => Type::get_pointer(Self::generate_type(&ast.base)),
+      AstKind::ArrayType(array) => {
+        let base = self.generate_type(&array.base);
+        let ty = Type::get_array(base.clone(), array.len);
+        // check the total number of elements, report only once
+        // for nested array types
+        let max = self.options.max_array_elems;
+        if array_elems(&ty).is_none_or(|n| n > max) && array_elems(&base).is_some_and(|n| n <= max)
+        {
+          log_error!(
+            ast.span,
+            "array type '{}' has too many elements, the limit is {}",
+            ty,
+            max
+          );
+        }
+        ty
+      }
+      AstKind::PointerType(ast) => Type::get_pointer(self.generate_type(&ast.base)),
       AstKind::FunType(ast) => Type::get_function(
-        ast.params.iter().map(Self::generate_type).collect(),
+        ast.params.iter().map(|t| self.generate_type(t)).collect(),
         ast
           .ret
           .as_ref()
-          .map_or(Type::get_unit(), Self::generate_type),
+          .map_or(Type::get_unit(), |t| self.generate_type(t)),
       ),
+      AstKind::NamedType(ast) => Type::get_named(ast.name.clone()),
       _ => panic!("invalid type AST"),
     }
   }
 
   /// Generates the global initializer by the given AST.
   fn generate_global_init(&mut self, ty: &Type, ast: &AstBox) -> ValueResult {
-    generate_init!(
-      ty,
-      ast,
-      self.program.new_value(),
-      |e| self.generate_global_init(elem_ty, e),
-      elem_ty
-    )
+    match &ast.kind {
+      AstKind::SymbolRef(_) | AstKind::PointerInit(_) => {
+        let value = self.generate_pointer_init(ast)?;
+        // check the type of the pointer
+        let value_ty = self.program.borrow_value(value).ty().clone();
+        if &value_ty == ty {
+          Ok(value)
+        } else {
+          return_error!(
+            ast.span,
+            "type mismatch, expected '{}', found '{}'",
+            ty,
+            value_ty
+          )
+        }
+      }
+      _ => generate_init!(
+        ty,
+        ast,
+        self.program.new_value(),
+        |e| self.generate_global_init(elem_ty, e),
+        elem_ty
+      ),
+    }
+  }
+
+  /// Generates the pointer initializer by the given AST.
+  fn generate_pointer_init(&mut self, ast: &AstBox) -> ValueResult {
+    match &ast.kind {
+      AstKind::SymbolRef(sym) => self
+        .global_vars
+        .get(&sym.symbol)
+        .copied()
+        .ok_or_else(|| log_error!(ast.span, "global variable '{}' not found", sym.symbol)),
+      AstKind::PointerInit(init) => {
+        let src = self.generate_pointer_init(&init.src)?;
+        // check the type of the source pointer
+        let src_ty = self.program.borrow_value(src).ty().clone();
+        let valid = match src_ty.kind() {
+          TypeKind::Pointer(base) => {
+            !init.is_elem_ptr || matches!(base.kind(), TypeKind::Array(..))
+          }
+          _ => false,
+        };
+        if !valid {
+          return_error!(init.src.span, "invalid source pointer type '{}'", src_ty);
+        }
+        let index = self.program.new_value().integer(init.index);
+        Ok(if init.is_elem_ptr {
+          self.program.new_value().get_elem_ptr(src, index)
+        } else {
+          self.program.new_value().get_ptr(src, index)
+        })
+      }
+      _ => panic!("invalid pointer initializer AST"),
+    }
   }
 
   /// Generates the local initializer by the given AST.
@@ -535,10 +755,16 @@ impl Builder {
   ) -> ValueResult {
     match &ast.kind {
       AstKind::Store(store) => self.generate_store(func, &ast.span, bb_name, store),
+      AstKind::Assume(assume) => {
+        let cond = self.generate_value(func, bb_name, &Type::get_i32(), &assume.cond)?;
+        Ok(self.dfg_mut(func).new_value().assume(cond))
+      }
       AstKind::Branch(br) => self.generate_branch(func, &ast.span, bb_name, br),
       AstKind::Jump(jump) => self.generate_jump(func, &ast.span, bb_name, jump),
+      AstKind::Switch(switch) => self.generate_switch(func, &ast.span, bb_name, switch),
       AstKind::FunCall(call) => self.generate_fun_call(func, &ast.span, bb_name, call),
       AstKind::Return(ret) => self.generate_return(func, &ast.span, bb_name, ret_ty, ret),
+      AstKind::Unreachable(_) => Ok(self.dfg_mut(func).new_value().unreachable()),
       AstKind::Error(_) => Error::default().into(),
       AstKind::SymbolDef(def) => {
         // check if has already been defined
@@ -557,7 +783,7 @@ impl Builder {
           );
         }
         // set value name
-        if !def.name.is_temp() {
+        if !def.name.is_temp() && !self.program.func(func).dfg().value(inst).kind().is_const() {
           self
             .dfg_mut(func)
             .set_value_name(inst, Some(def.name.clone()));
@@ -584,7 +810,14 @@ impl Builder {
       AstKind::GetElementPointer(gep) => {
         self.generate_get_element_pointer(func, &ast.span, bb_name, gep)
       }
-      AstKind::BinaryExpr(ast) => self.generate_binary_expr(func, bb_name, ast),
+      AstKind::ExtractValue(ev) => self.generate_extract_value(func, &ast.span, bb_name, ev),
+      AstKind::InsertValue(iv) => self.generate_insert_value(func, &ast.span, bb_name, iv),
+      AstKind::BinaryExpr(bin) => self.generate_binary_expr(func, &ast.span, bb_name, bin),
+      AstKind::UnaryExpr(ast) => self.generate_unary_expr(func, bb_name, ast),
+      AstKind::CheckedBinaryExpr(checked) => {
+        self.generate_checked_binary_expr(func, &ast.span, bb_name, checked)
+      }
+      AstKind::Copy(copy) => self.generate_copy(func, &ast.span, bb_name, copy),
       AstKind::FunCall(call) => self.generate_fun_call(func, &ast.span, bb_name, call),
       _ => panic!("invalid instruction"),
     }
@@ -592,8 +825,16 @@ impl Builder {
 
   /// Generates memory declarations.
   fn generate_mem_decl(&mut self, func: Function, ast: &ast::MemDecl) -> ValueResult {
-    let ty = Self::generate_type(&ast.ty);
-    Ok(self.dfg_mut(func).new_value().alloc(ty))
+    let ty = self.generate_type(&ast.ty);
+    if !ty.is_allocatable() {
+      return_error!(ast.ty.span, "type '{}' can not be allocated", ty);
+    }
+    Ok(
+      self
+        .dfg_mut(func)
+        .new_value()
+        .alloc_with_align(ty, ast.align),
+    )
   }
 
   /// Generates loads.
@@ -673,18 +914,220 @@ impl Builder {
     Ok(self.dfg_mut(func).new_value().get_elem_ptr(src, index))
   }
 
+  /// Generates the aggregate value of element extractions/insertions,
+  /// and checks the index. Returns the aggregate value and its type.
+  fn generate_agg(
+    &self,
+    func: Function,
+    span: &Span,
+    bb_name: &str,
+    symbol: &str,
+    index: usize,
+  ) -> Result<(Value, Type), Error> {
+    let agg = self.generate_symbol(span, bb_name, symbol)?;
+    let agg_ty = self.value_ty(func, agg);
+    match agg_ty.kind() {
+      TypeKind::Array(_, len) if index < *len => Ok((agg, agg_ty)),
+      TypeKind::Array(_, len) => return_error!(
+        span,
+        "index {} out of bounds, the array length is {}",
+        index,
+        len
+      ),
+      _ => return_error!(span, "expected array type, found '{}'", agg_ty),
+    }
+  }
+
+  /// Generates copies.
+  fn generate_copy(
+    &mut self,
+    func: Function,
+    span: &Span,
+    bb_name: &str,
+    ast: &ast::Copy,
+  ) -> ValueResult {
+    let src = self.generate_symbol(span, bb_name, &ast.symbol)?;
+    if self.value_ty(func, src).is_unit() {
+      return_error!(span, "can not copy a value of type 'unit'");
+    }
+    Ok(self.dfg_mut(func).new_value().copy(src))
+  }
+
+  /// Generates element extractions.
+  fn generate_extract_value(
+    &mut self,
+    func: Function,
+    span: &Span,
+    bb_name: &str,
+    ast: &ast::ExtractValue,
+  ) -> ValueResult {
+    let (agg, _) = self.generate_agg(func, span, bb_name, &ast.symbol, ast.index)?;
+    Ok(self.dfg_mut(func).new_value().extract_elem(agg, ast.index))
+  }
+
+  /// Generates element insertions.
+  fn generate_insert_value(
+    &mut self,
+    func: Function,
+    span: &Span,
+    bb_name: &str,
+    ast: &ast::InsertValue,
+  ) -> ValueResult {
+    let (agg, agg_ty) = self.generate_agg(func, span, bb_name, &ast.symbol, ast.index)?;
+    let base = match agg_ty.kind() {
+      TypeKind::Array(base, _) => base,
+      _ => unreachable!(),
+    };
+    let value = self.generate_value(func, bb_name, base, &ast.value)?;
+    Ok(
+      self
+        .dfg_mut(func)
+        .new_value()
+        .insert_elem(agg, value, ast.index),
+    )
+  }
+
   /// Generates binary expressions.
   fn generate_binary_expr(
     &mut self,
     func: Function,
+    span: &Span,
     bb_name: &str,
     ast: &ast::BinaryExpr,
   ) -> ValueResult {
+    if !ast.flags.is_supported(ast.op) {
+      return_error!(
+        span,
+        "binary operator '{}' does not support flags '{}'",
+        ast.op,
+        ast.flags
+      );
+    }
     let ty = Type::get_i32();
     // get lhs & rhs
     let lhs = self.generate_value(func, bb_name, &ty, &ast.lhs)?;
     let rhs = self.generate_value(func, bb_name, &ty, &ast.rhs)?;
-    Ok(self.dfg_mut(func).new_value().binary(ast.op, lhs, rhs))
+    if self.options.fold_constants {
+      if let Some(value) = self.fold_binary_expr(func, ast, lhs, rhs) {
+        return Ok(value);
+      }
+    }
+    Ok(
+      self
+        .dfg_mut(func)
+        .new_value()
+        .binary_with_flags(ast.op, ast.flags, lhs, rhs),
+    )
+  }
+
+  /// Generates unary expressions.
+  fn generate_unary_expr(
+    &mut self,
+    func: Function,
+    bb_name: &str,
+    ast: &ast::UnaryExpr,
+  ) -> ValueResult {
+    let opr = self.generate_value(func, bb_name, &Type::get_i32(), &ast.opr)?;
+    if self.options.fold_constants {
+      if let Some(value) = self.fold_unary_expr(func, ast, opr) {
+        return Ok(value);
+      }
+    }
+    Ok(self.dfg_mut(func).new_value().unary(ast.op, opr))
+  }
+
+  /// Generates overflow-checked binary expressions.
+  fn generate_checked_binary_expr(
+    &mut self,
+    func: Function,
+    span: &Span,
+    bb_name: &str,
+    ast: &ast::CheckedBinaryExpr,
+  ) -> ValueResult {
+    if !CheckedBinary::is_supported(ast.op) {
+      return_error!(span, "binary operator '{}' can not be checked", ast.op);
+    }
+    let ty = Type::get_i32();
+    // get lhs & rhs
+    let lhs = self.generate_value(func, bb_name, &ty, &ast.lhs)?;
+    let rhs = self.generate_value(func, bb_name, &ty, &ast.rhs)?;
+    Ok(
+      self
+        .dfg_mut(func)
+        .new_value()
+        .checked_binary(ast.op, lhs, rhs),
+    )
+  }
+
+  /// Folds the binary expression if both operands are integer constants
+  /// and the result is well-defined. Results that violate the flags are
+  /// folded to `undef`.
+  fn fold_binary_expr(
+    &mut self,
+    func: Function,
+    ast: &ast::BinaryExpr,
+    lhs: Value,
+    rhs: Value,
+  ) -> Option<Value> {
+    let dfg = self.program.func(func).dfg();
+    let int = |v: Value| match dfg.values().get(&v)?.kind() {
+      ValueKind::Integer(i) => Some(i.value()),
+      _ => None,
+    };
+    let (l, r) = (int(lhs)?, int(rhs)?);
+    let violated = ast.flags.is_violated(ast.op, l, r);
+    let value = match ast.op {
+      _ if violated => 0,
+      BinaryOp::NotEq => (l != r) as i32,
+      BinaryOp::Eq => (l == r) as i32,
+      BinaryOp::Gt => (l > r) as i32,
+      BinaryOp::Lt => (l < r) as i32,
+      BinaryOp::Ge => (l >= r) as i32,
+      BinaryOp::Le => (l <= r) as i32,
+      BinaryOp::Add => l.checked_add(r)?,
+      BinaryOp::Sub => l.checked_sub(r)?,
+      BinaryOp::Mul => l.checked_mul(r)?,
+      BinaryOp::Div => l.checked_div(r)?,
+      BinaryOp::Mod => l.checked_rem(r)?,
+      BinaryOp::And => l & r,
+      BinaryOp::Or => l | r,
+      BinaryOp::Xor => l ^ r,
+      BinaryOp::Shl => l.checked_shl(u32::try_from(r).ok()?)?,
+      BinaryOp::Shr => (l as u32).checked_shr(u32::try_from(r).ok()?)? as i32,
+      BinaryOp::Sar => l.checked_shr(u32::try_from(r).ok()?)?,
+    };
+    // remove operands created from integer literals,
+    // other operands may be referenced by symbols
+    let dfg = self.dfg_mut(func);
+    if matches!(ast.lhs.kind, AstKind::IntVal(_)) {
+      dfg.remove_value(lhs);
+    }
+    if matches!(ast.rhs.kind, AstKind::IntVal(_)) {
+      dfg.remove_value(rhs);
+    }
+    if violated {
+      Some(dfg.new_value().undef(Type::get_i32()))
+    } else {
+      Some(dfg.new_value().integer(value))
+    }
+  }
+
+  /// Folds the unary expression if the operand is an integer constant
+  /// and the result is well-defined.
+  fn fold_unary_expr(&mut self, func: Function, ast: &ast::UnaryExpr, opr: Value) -> Option<Value> {
+    let value = match self.program.func(func).dfg().values().get(&opr)?.kind() {
+      ValueKind::Integer(i) => match ast.op {
+        UnaryOp::Not => !i.value(),
+        UnaryOp::Neg => i.value().checked_neg()?,
+      },
+      _ => return None,
+    };
+    // remove the operand if it is created from an integer literal
+    let dfg = self.dfg_mut(func);
+    if matches!(ast.opr.kind, AstKind::IntVal(_)) {
+      dfg.remove_value(opr);
+    }
+    Some(dfg.new_value().integer(value))
   }
 
   /// Generates branchs.
@@ -729,6 +1172,40 @@ impl Builder {
     Ok(self.dfg_mut(func).new_value().jump_with_args(target, args))
   }
 
+  /// Generates multi-way branches.
+  fn generate_switch(
+    &mut self,
+    func: Function,
+    span: &Span,
+    bb_name: &str,
+    ast: &ast::Switch,
+  ) -> ValueResult {
+    // get selector
+    let cond = self.generate_value(func, bb_name, &Type::get_i32(), &ast.cond)?;
+    // get default target basic block and default arguments
+    let default = self.generate_bb(span, &ast.default)?;
+    let default_ty = self.bb_params_ty(func, default);
+    let default_args = self.generate_args(func, span, bb_name, &ast.default_args, &default_ty)?;
+    // get cases
+    let mut values = HashSet::new();
+    let mut cases = Vec::new();
+    for (value, bb, args) in &ast.cases {
+      if !values.insert(*value) {
+        return_error!(span, "duplicate case value {}", value);
+      }
+      let bb = self.generate_bb(span, bb)?;
+      let bb_ty = self.bb_params_ty(func, bb);
+      let args = self.generate_args(func, span, bb_name, args, &bb_ty)?;
+      cases.push((*value, bb, args));
+    }
+    Ok(
+      self
+        .dfg_mut(func)
+        .new_value()
+        .switch_with_args(cond, default, default_args, cases),
+    )
+  }
+
   /// Generates function calls.
   fn generate_fun_call(
     &mut self,
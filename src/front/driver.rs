@@ -1,9 +1,9 @@
 //! Koopa IR frontend driver ([`Driver`]) related implementations.
 
 use crate::front::ast::AstKind;
-use crate::front::builder::Builder;
+use crate::front::builder::{BuildOptions, Builder};
 use crate::front::lexer::Lexer;
-use crate::front::parser::Parser;
+use crate::front::parser::{ParseOptions, Parser};
 use crate::front::span::{Error, FileType, Span};
 use crate::ir::Program;
 use crate::log_raw_error;
@@ -32,6 +32,18 @@ impl<T: Read> Driver<T> {
     }
   }
 
+  /// Sets the build options of the current driver.
+  pub fn with_options(mut self, options: BuildOptions) -> Self {
+    self.builder = Builder::with_options(options);
+    self
+  }
+
+  /// Sets the parse options of the current driver.
+  pub fn with_parse_options(mut self, options: ParseOptions) -> Self {
+    self.parser = self.parser.map(|p| p.with_options(options));
+    self
+  }
+
   /// Consumes the current driver and generates Koopa IR program
   /// from the reader.
   pub fn generate_program(mut self) -> Result<Program, Error> {
@@ -44,6 +56,7 @@ impl<T: Read> Driver<T> {
         break;
       }
       // build on the current AST
+      self.builder.add_metadata(parser.take_metadata());
       self.builder.build_on(&ast);
       // exit if too many errors are generated
       if Span::error_num() > Self::MAX_ERR_NUM {
@@ -97,6 +110,9 @@ impl<'a> From<&'a str> for Driver<io::Cursor<&'a str>> {
 #[cfg(test)]
 mod test {
   use super::*;
+  use crate::back::KoopaGenerator;
+  use crate::front::parser::Dialect;
+  use crate::ir::entities::DebugVar;
   use crate::ir::{Type, ValueKind};
 
   #[test]
@@ -242,4 +258,267 @@ mod test {
     assert_eq!(Span::warning_num(), 0);
     assert_ne!(Span::error_num(), 0);
   }
+
+  #[test]
+  fn generate_numeric_names() {
+    let src = r#"fun @test(@i: i32): i32 {
+%entry:
+  %0 = add @i, 1
+  %x = mul %0, 2
+  br %x, %1, %end(%0)
+
+%1:
+  %2 = add %0, %x
+  jump %end(%2)
+
+%end(%3: i32):
+  ret %3
+}
+"#;
+    let driver: Driver<_> = src.into();
+    let program = driver.generate_program().unwrap();
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen.generate_on(&program).unwrap();
+    let dumped = std::str::from_utf8(&gen.writer()).unwrap().to_string();
+    assert_eq!(dumped, src);
+    // parse the output again
+    let driver: Driver<_> = dumped.as_str().into();
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen
+      .generate_on(&driver.generate_program().unwrap())
+      .unwrap();
+    assert_eq!(std::str::from_utf8(&gen.writer()).unwrap(), src);
+  }
+
+  #[test]
+  fn generate_fold_constants() {
+    let src = r#"fun @f(@a: i32): i32 {
+%entry:
+  %0 = add 2, 3
+  %1 = mul %0, %0
+  %2 = div %1, 0
+  %3 = add 2147483647, 1
+  %4 = shl 1, 32
+  %5 = sub @a, %1
+  %6 = add %2, %3
+  %7 = add %6, %4
+  %8 = lt %0, 1
+  %9 = add %7, %5
+  br %8, %end(%9), %end(%0)
+
+%end(%ans: i32):
+  ret %ans
+}
+"#;
+    let expected = r#"fun @f(@a: i32): i32 {
+%entry:
+  %0 = div 25, 0
+  %1 = add 2147483647, 1
+  %2 = shl 1, 32
+  %3 = sub @a, 25
+  %4 = add %0, %1
+  %5 = add %4, %2
+  %6 = add %5, %3
+  br 0, %end(%6), %end(5)
+
+%end(%ans: i32):
+  ret %ans
+}
+"#;
+    let driver: Driver<_> = src.into();
+    let program = driver
+      .with_options(BuildOptions {
+        fold_constants: true,
+        ..Default::default()
+      })
+      .generate_program()
+      .unwrap();
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen.generate_on(&program).unwrap();
+    assert_eq!(std::str::from_utf8(&gen.writer()).unwrap(), expected);
+  }
+
+  #[test]
+  fn generate_fold_flagged_constants() {
+    let src = r#"fun @f(): i32 {
+%entry:
+  %0 = add nsw 2147483647, 1
+  %1 = add nuw 2147483647, 1
+  %2 = div exact 7, 2
+  %3 = div exact 8, 2
+  %4 = add %0, %1
+  %5 = add %4, %2
+  %6 = add %5, %3
+  ret %6
+}
+"#;
+    let expected = r#"fun @f(): i32 {
+%entry:
+  %0 = add nuw 2147483647, 1
+  %1 = add undef, %0
+  %2 = add %1, undef
+  %3 = add %2, 4
+  ret %3
+}
+"#;
+    let driver: Driver<_> = src.into();
+    let program = driver
+      .with_options(BuildOptions {
+        fold_constants: true,
+        ..Default::default()
+      })
+      .generate_program()
+      .unwrap();
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen.generate_on(&program).unwrap();
+    assert_eq!(std::str::from_utf8(&gen.writer()).unwrap(), expected);
+    let driver: Driver<_> = "fun @f(): i32 {
+%entry:
+  %0 = add exact 1, 2
+  ret %0
+}
+"
+    .into();
+    assert!(driver.generate_program().is_err());
+  }
+
+  #[test]
+  fn parse_dialects() {
+    let classic = r#"global @x = alloc [i32, 2], {1, 2}
+
+fun @f(@a: i32): i32 {
+%entry:
+  %0 = getelemptr @x, 1
+  %1 = load %0
+  br @a, %end(%1), %end(0)
+
+%end(%r: i32):
+  ret %r
+}
+"#;
+    let options = ParseOptions {
+      dialect: Dialect::Classic,
+    };
+    let driver: Driver<_> = classic.into();
+    let driver = driver.with_parse_options(options.clone());
+    assert!(driver.generate_program().is_ok());
+    for src in [
+      "fun @f(@a: i32): i32 {\n%entry:\n  %0 = neg @a\n  ret %0\n}\n",
+      "fun @f(@a: i32): i32 {\n%entry:\n  %0 = add nsw @a, 1\n  ret %0\n}\n",
+      "fun @f(@a: i32) {\n%entry:\n  unreachable\n}\n",
+      "global @s = alloc [i32, 2], \"hi\"\n",
+    ] {
+      let driver: Driver<_> = src.into();
+      assert!(driver.generate_program().is_ok());
+      let driver: Driver<_> = src.into();
+      let driver = driver.with_parse_options(options.clone());
+      assert!(driver.generate_program().is_err());
+    }
+  }
+
+  #[test]
+  fn generate_max_array_elems() {
+    let options = BuildOptions {
+      max_array_elems: 10000,
+      ..Default::default()
+    };
+    let driver: Driver<_> = "global @x = alloc [[i32, 100], 100], zeroinit".into();
+    assert!(driver
+      .with_options(options.clone())
+      .generate_program()
+      .is_ok());
+    let driver: Driver<_> = "decl @f(*[[i32, 101], 100])".into();
+    assert!(driver.with_options(options).generate_program().is_err());
+  }
+
+  #[test]
+  fn generate_not_allocatable() {
+    for src in [
+      "global @f = alloc (i32): i32, zeroinit",
+      "fun @f() {\n%entry:\n  %0 = alloc [(), 2]\n  ret\n}\n",
+    ] {
+      let driver: Driver<_> = src.into();
+      assert!(driver.generate_program().is_err());
+    }
+    let driver: Driver<_> = "global @f = alloc [*(), 2], zeroinit".into();
+    assert!(driver.generate_program().is_ok());
+  }
+
+  #[test]
+  fn generate_int_literals() {
+    let src = r#"global @x = alloc [i32, 3], {0x7fffffff, 0xffffffff, -0b11}
+
+fun @f(@a: i32): i32 {
+%entry:
+  switch @a, %end, [4294967295: %end, -2147483648: %end]
+
+%end:
+  ret 0x10
+}
+"#;
+    let expected = r#"global @x = alloc [i32, 3], {2147483647, -1, -3}
+
+fun @f(@a: i32): i32 {
+%entry:
+  switch @a, %end, [-1: %end, -2147483648: %end]
+
+%end:
+  ret 16
+}
+"#;
+    let driver: Driver<_> = src.into();
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen
+      .generate_on(&driver.generate_program().unwrap())
+      .unwrap();
+    assert_eq!(std::str::from_utf8(&gen.writer()).unwrap(), expected);
+    for src in [
+      "global @x = alloc i32, 0x100000000",
+      "global @x = alloc i32, -2147483649",
+    ] {
+      let driver: Driver<_> = src.into();
+      assert!(driver.generate_program().is_err());
+    }
+  }
+
+  #[test]
+  fn generate_debug_vars() {
+    let src = r#"
+      fun @main(): i32 {
+      %entry:
+        %x = alloc i32      //@var x
+        %a = alloc [i32, 2] //@var arr 42
+        %y = alloc i32      //@ other metadata
+        //@var z
+        %z = alloc i32
+        ret 0
+      }
+    "#;
+    let options = BuildOptions {
+      keep_debug_vars: true,
+      ..Default::default()
+    };
+    let driver: Driver<_> = src.into();
+    let program = driver.with_options(options).generate_program().unwrap();
+    let func = program.funcs().values().next().unwrap();
+    let vars: Vec<_> = func.allocs().map(|a| func.debug_var(a).cloned()).collect();
+    assert_eq!(
+      vars,
+      [
+        Some(DebugVar::new("x".into(), Type::get_i32(), 4)),
+        Some(DebugVar::new(
+          "arr".into(),
+          Type::get_array(Type::get_i32(), 2),
+          42
+        )),
+        None,
+        None,
+      ]
+    );
+    // metadata comments are ignored by default
+    let driver: Driver<_> = src.into();
+    let program = driver.generate_program().unwrap();
+    let func = program.funcs().values().next().unwrap();
+    assert!(func.allocs().all(|a| func.debug_var(a).is_none()));
+  }
 }
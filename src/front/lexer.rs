@@ -2,7 +2,7 @@
 
 use crate::front::span::{Error, Pos, Span};
 use crate::front::token::{Keyword, Token, TokenKind};
-use crate::ir::BinaryOp;
+use crate::ir::{BinaryOp, UnaryOp};
 use crate::{log_raw_fatal_error, return_error};
 use std::collections::HashMap;
 use std::io::Read;
@@ -16,6 +16,7 @@ pub struct Lexer<T: Read> {
   pos: Pos,
   // `None` if EOF
   last_char: Option<char>,
+  metadata: Vec<(Pos, String)>,
 }
 
 /// Result that returned by [`Lexer`].
@@ -28,9 +29,19 @@ impl<T: Read> Lexer<T> {
       reader,
       pos: Pos::new(),
       last_char: Some(' '),
+      metadata: Vec::new(),
     }
   }
 
+  /// Takes all metadata comments read so far.
+  ///
+  /// Metadata comments are line comments starting with `//@`. Each one
+  /// is returned with the position of its first `/`, and its content
+  /// after `//@` with surrounding whitespaces trimmed.
+  pub fn take_metadata(&mut self) -> Vec<(Pos, String)> {
+    std::mem::take(&mut self.metadata)
+  }
+
   /// Returns the next token from file, or a lexer error.
   pub fn next_token(&mut self) -> Result {
     // skip spaces
@@ -51,6 +62,9 @@ impl<T: Read> Lexer<T> {
       } else if c.is_numeric() || c == '-' {
         // integer literals
         self.handle_integer()
+      } else if c == '"' {
+        // string literals
+        self.handle_string()
       } else {
         // other characters
         let pos = self.pos;
@@ -84,24 +98,108 @@ impl<T: Read> Lexer<T> {
   }
 
   /// Handles integer literals.
+  ///
+  /// Integer literals can be decimal, hexadecimal (with prefix `0x` or
+  /// `0X`) or binary (with prefix `0b` or `0B`), and can be negated by
+  /// a leading `-`, for example, `42`, `-0x1f` and `0b101`. The range
+  /// of integer values is checked by the parser.
   fn handle_integer(&mut self) -> Result {
     let mut span = Span::new(self.pos);
     // read to string
     let mut num = String::from(self.last_char.unwrap());
     self.next_char()?;
-    while self.last_char.map_or(false, |c| c.is_numeric()) {
+    while self.last_char.is_some_and(|c| c.is_ascii_alphanumeric()) {
       num.push(self.last_char.unwrap());
       span.update(self.pos);
       self.next_char()?;
     }
     // convert to integer
-    if let Ok(i) = num.parse() {
+    if let Some(i) = Self::parse_integer(&num) {
       Ok(Token::new(span, TokenKind::Int(i)))
     } else {
       self.log_err_and_skip(span, &format!("invalid integer literal '{}'", num))
     }
   }
 
+  /// Parses the given integer literal.
+  fn parse_integer(num: &str) -> Option<i64> {
+    let (neg, num) = match num.strip_prefix('-') {
+      Some(num) => (true, num),
+      None => (false, num),
+    };
+    let (radix, digits) = match num.get(..2) {
+      Some("0x" | "0X") => (16, &num[2..]),
+      Some("0b" | "0B") => (2, &num[2..]),
+      _ => (10, num),
+    };
+    // `from_str_radix` accepts signs, which are not allowed here
+    if !digits.starts_with(|c: char| c.is_ascii_alphanumeric()) {
+      return None;
+    }
+    let i = i64::from_str_radix(digits, radix).ok()?;
+    Some(if neg { -i } else { i })
+  }
+
+  /// Handles string literals.
+  ///
+  /// String literals are enclosed in double quotes, and can contain
+  /// escape sequences `\n`, `\t`, `\r`, `\0`, `\\`, `\"` and `\xHH`
+  /// (a byte in hexadecimal). Line breaks are not allowed in string
+  /// literals.
+  fn handle_string(&mut self) -> Result {
+    let mut span = Span::new(self.pos);
+    let mut string = Vec::new();
+    // eat '"'
+    self.next_char()?;
+    loop {
+      let c = match self.last_char {
+        Some('"') => break,
+        Some('\n') | None => return_error!(span, "unterminated string literal"),
+        Some(c) => c,
+      };
+      span.update(self.pos);
+      self.next_char()?;
+      if c != '\\' {
+        string.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+        continue;
+      }
+      // escape sequences
+      let escaped = match self.last_char {
+        Some('n') => b'\n',
+        Some('t') => b'\t',
+        Some('r') => b'\r',
+        Some('0') => b'\0',
+        Some('\\') => b'\\',
+        Some('"') => b'"',
+        Some('x') => {
+          let mut hex = String::new();
+          for _ in 0..2 {
+            span.update(self.pos);
+            self.next_char()?;
+            hex.extend(self.last_char);
+          }
+          match u8::from_str_radix(&hex, 16) {
+            Ok(b) if hex.starts_with(|c: char| c.is_ascii_hexdigit()) => b,
+            _ => {
+              return self.log_err_and_skip(span, &format!("invalid escape sequence '\\x{}'", hex))
+            }
+          }
+        }
+        c => {
+          let c = c.map_or(String::new(), String::from);
+          return self.log_err_and_skip(span, &format!("invalid escape sequence '\\{}'", c));
+        }
+      };
+      string.push(escaped);
+      span.update(self.pos);
+      self.next_char()?;
+    }
+    // eat '"'
+    span.update(self.pos);
+    self.next_char()?;
+    Ok(Token::new(span, TokenKind::Str(string)))
+  }
+
   /// Handles symbols.
   fn handle_symbol(&mut self) -> Result {
     let mut span = Span::new(self.pos);
@@ -162,6 +260,8 @@ impl<T: Read> Lexer<T> {
       Ok(Token::new(span, TokenKind::Keyword(keyword)))
     } else if let Some(op) = BINARY_OPS.with(|m| m.get(keyword.as_str()).copied()) {
       Ok(Token::new(span, TokenKind::BinaryOp(op)))
+    } else if let Some(op) = UNARY_OPS.with(|m| m.get(keyword.as_str()).copied()) {
+      Ok(Token::new(span, TokenKind::UnaryOp(op)))
     } else {
       self.log_err_and_skip(span, &format!("invalid keyword/operator '{}'", keyword))
     }
@@ -176,10 +276,20 @@ impl<T: Read> Lexer<T> {
     if self.last_char == Some('*') {
       self.handle_block_comment(span)
     } else if self.last_char == Some('/') {
-      // skip the current line
-      while self.last_char.map_or(false, |c| c != '\r' && c != '\n') {
+      // skip the current line, record it if is a metadata comment
+      self.next_char()?;
+      let is_metadata = self.last_char == Some('@');
+      let mut content = String::new();
+      while let Some(c) = self.last_char.filter(|c| *c != '\r' && *c != '\n') {
+        if is_metadata {
+          content.push(c);
+        }
         self.next_char()?;
       }
+      if is_metadata {
+        let pos = span.start();
+        self.metadata.push((pos, content[1..].trim().into()));
+      }
       // return the next token
       self.next_token()
     } else {
@@ -238,12 +348,26 @@ thread_local! {
     "store" => Keyword::Store,
     "getptr" => Keyword::GetPtr,
     "getelemptr" => Keyword::GetElemPtr,
+    "extractvalue" => Keyword::ExtractValue,
+    "insertvalue" => Keyword::InsertValue,
+    "checked" => Keyword::Checked,
+    "copy" => Keyword::Copy,
+    "assume" => Keyword::Assume,
     "br" => Keyword::Br,
     "jump" => Keyword::Jump,
+    "switch" => Keyword::Switch,
     "call" => Keyword::Call,
     "ret" => Keyword::Ret,
+    "unreachable" => Keyword::Unreachable,
     "fun" => Keyword::Fun,
     "decl" => Keyword::Decl,
+    "nsw" => Keyword::Nsw,
+    "nuw" => Keyword::Nuw,
+    "exact" => Keyword::Exact,
+    "align" => Keyword::Align,
+    "readonly" => Keyword::ReadOnly,
+    "threadlocal" => Keyword::ThreadLocal,
+    "type" => Keyword::Type,
   };
 
   /// All supported binary operators.
@@ -266,6 +390,12 @@ thread_local! {
     "shr" => BinaryOp::Shr,
     "sar" => BinaryOp::Sar,
   };
+
+  /// All supported unary operators.
+  static UNARY_OPS: HashMap<&'static str, UnaryOp> = hash_map! {
+    "not" => UnaryOp::Not,
+    "neg" => UnaryOp::Neg,
+  };
 }
 
 #[cfg(test)]
@@ -379,4 +509,38 @@ mod test {
     assert_eq!(lexer.next_token().unwrap().kind, TokenKind::End);
     assert_eq!(lexer.next_token().unwrap().kind, TokenKind::End);
   }
+
+  #[test]
+  fn read_integers() {
+    let buf = Cursor::new("0 -12 0x1F -0X10 0b101 0xffffffff 0x 12ab -0b 7");
+    let mut lexer = Lexer::new(buf);
+    for i in [0, -12, 31, -16, 5, 0xffffffff] {
+      assert_eq!(lexer.next_token().unwrap().kind, TokenKind::Int(i));
+    }
+    assert!(lexer.next_token().is_err());
+    assert!(lexer.next_token().is_err());
+    assert!(lexer.next_token().is_err());
+    assert_eq!(lexer.next_token().unwrap().kind, TokenKind::Int(7));
+  }
+
+  #[test]
+  fn read_strings() {
+    let buf = Cursor::new(r#""a\"b\\\n\x41\0" "\q" "ok" "é\xff" "\x4" "open"#);
+    let mut lexer = Lexer::new(buf);
+    assert_eq!(
+      lexer.next_token().unwrap().kind,
+      TokenKind::Str("a\"b\\\nA\0".into())
+    );
+    assert!(lexer.next_token().is_err());
+    assert_eq!(
+      lexer.next_token().unwrap().kind,
+      TokenKind::Str("ok".into())
+    );
+    assert_eq!(
+      lexer.next_token().unwrap().kind,
+      TokenKind::Str(vec![0xc3, 0xa9, 0xff])
+    );
+    assert!(lexer.next_token().is_err());
+    assert!(lexer.next_token().is_err());
+  }
 }
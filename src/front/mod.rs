@@ -7,6 +7,7 @@
 //! * Lexer ([`Lexer`](lexer::Lexer)), parser ([`Parser`](parser::Parser))
 //!   and analyzer ([`Builder`](builder::Builder)) of Koopa IR.
 //! * Koopa IR frontend driver ([`Driver`]).
+//! * Parsers of IR fragments ([`parse_type`] and [`parse_value`]).
 //!
 //! # Examples
 //!
@@ -57,3 +58,27 @@ pub mod span;
 pub mod token;
 
 pub use driver::Driver;
+
+use lexer::Lexer;
+use parser::Parser;
+use span::{FileType, Span};
+use std::io::Cursor;
+
+/// Parses the given string as a type, for example, `*[i32, 4]`.
+///
+/// Returns the AST of the type, or an error if the string is not
+/// exactly a type.
+pub fn parse_type(src: &str) -> parser::Result {
+  Span::reset(FileType::Buffer);
+  Parser::new(Lexer::new(Cursor::new(src)))?.parse_type_fragment()
+}
+
+/// Parses the given string as a value (like `%x`, `1` or `undef`), or
+/// an expression that produces a value (like `add 1, 2` or `load @p`).
+///
+/// Returns the AST of the value or the expression, or an error if the
+/// string is not exactly a value or an expression.
+pub fn parse_value(src: &str) -> parser::Result {
+  Span::reset(FileType::Buffer);
+  Parser::new(Lexer::new(Cursor::new(src)))?.parse_value_fragment()
+}
@@ -2,9 +2,11 @@
 
 use crate::front::ast::{self, AstBox};
 use crate::front::lexer::Lexer;
-use crate::front::span::{Error, Span};
+use crate::front::span::{Error, Pos, Span};
 use crate::front::token::{Keyword, Token, TokenKind};
+use crate::ir::{BinaryFlags, GlobalAttrs};
 use crate::return_error;
+use std::fmt;
 use std::io::Read;
 
 /// A parser for parsing the text form Koopa IR.
@@ -14,6 +16,73 @@ use std::io::Read;
 pub struct Parser<T: Read> {
   lexer: Lexer<T>,
   cur_token: Token,
+  options: ParseOptions,
+}
+
+/// Options of the Koopa IR parser.
+#[derive(Clone, Debug, Default)]
+pub struct ParseOptions {
+  /// The dialect of Koopa IR accepted by the parser, constructs not in
+  /// the dialect are reported as errors.
+  pub dialect: Dialect,
+}
+
+/// Dialects of the text form Koopa IR.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Dialect {
+  /// The classic Koopa IR, which supports global allocations, memory
+  /// operations (`alloc`, `load`, `store`, `getptr` and `getelemptr`),
+  /// binary operations, `br`, `jump`, `call`, `ret`, and basic block
+  /// parameters.
+  ///
+  /// Extensions, including `switch`, `unreachable`, `assume`, unary
+  /// operations, overflow-checked operations, aggregate operations,
+  /// binary operation flags, alignments and string literals, are
+  /// rejected.
+  Classic,
+  /// The latest dialect, which accepts all constructs supported by the
+  /// current version of Koopa IR.
+  #[default]
+  Latest,
+}
+
+impl Dialect {
+  /// Checks if the given token is supported by the current dialect.
+  fn supports(self, kind: &TokenKind) -> bool {
+    match self {
+      Self::Classic => !matches!(
+        kind,
+        TokenKind::Str(_)
+          | TokenKind::UnaryOp(_)
+          | TokenKind::Keyword(
+            Keyword::ExtractValue
+              | Keyword::InsertValue
+              | Keyword::Checked
+              | Keyword::Copy
+              | Keyword::Assume
+              | Keyword::Switch
+              | Keyword::Unreachable
+              | Keyword::Nsw
+              | Keyword::Nuw
+              | Keyword::Exact
+              | Keyword::Align
+              | Keyword::ReadOnly
+              | Keyword::ThreadLocal
+              | Keyword::Type
+          )
+      ),
+      Self::Latest => true,
+    }
+  }
+}
+
+impl fmt::Display for Dialect {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Classic => f.write_str("classic"),
+      Self::Latest => f.write_str("latest"),
+    }
+  }
 }
 
 /// Result that returned by [`Parser`].
@@ -73,11 +142,24 @@ impl<T: Read> Parser<T> {
     let mut parser = Self {
       lexer,
       cur_token: Token::default(),
+      options: ParseOptions::default(),
     };
     parser.next_token()?;
     Ok(parser)
   }
 
+  /// Sets the parse options of the current parser.
+  pub fn with_options(mut self, options: ParseOptions) -> Self {
+    self.options = options;
+    self
+  }
+
+  /// Takes all metadata comments read by the lexer so far,
+  /// see [`Lexer::take_metadata`].
+  pub fn take_metadata(&mut self) -> Vec<(Pos, String)> {
+    self.lexer.take_metadata()
+  }
+
   /// Parses the next AST and returns the box of paarsed AST.
   pub fn parse_next(&mut self) -> Result {
     match_token! {
@@ -86,13 +168,45 @@ impl<T: Read> Parser<T> {
       TokenKind::Keyword(Keyword::Global) => self.parse_global_def(),
       TokenKind::Keyword(Keyword::Fun) => self.parse_fun_def(),
       TokenKind::Keyword(Keyword::Decl) => self.parse_fun_decl(),
+      TokenKind::Keyword(Keyword::Type) => self.parse_type_def(),
       ? => return_error!(span, "expected global definition/declaration, found {}", kind),
     }
   }
 
+  /// Parses a type, and expects the end of input after it.
+  pub fn parse_type_fragment(&mut self) -> Result {
+    let ty = self.parse_type()?;
+    self.expect(TokenKind::End)?;
+    Ok(ty)
+  }
+
+  /// Parses a value (a symbol reference, an integer literal or
+  /// `undef`) or an expression (the right-hand side of a local symbol
+  /// definition, like `add 1, 2`), and expects the end of input after
+  /// it.
+  pub fn parse_value_fragment(&mut self) -> Result {
+    let value = match self.cur_token.kind {
+      TokenKind::Symbol(_) | TokenKind::Int(_) | TokenKind::Keyword(Keyword::Undef) => {
+        self.parse_value()?
+      }
+      _ => self.parse_expr()?,
+    };
+    self.expect(TokenKind::End)?;
+    Ok(value)
+  }
+
   /// Gets the next token.
   fn next_token(&mut self) -> std::result::Result<(), Error> {
     self.cur_token = self.lexer.next_token()?;
+    let Token { span, kind } = &self.cur_token;
+    if !self.options.dialect.supports(kind) {
+      return_error!(
+        span,
+        "{} is not supported in the {} dialect",
+        kind,
+        self.options.dialect
+      );
+    }
     Ok(())
   }
 
@@ -116,13 +230,19 @@ impl<T: Read> Parser<T> {
     // check & eat ','
     self.expect(TokenKind::Other(','))?;
     // get initializer
-    self.parse_init().map(|init| {
-      let span_last = init.span;
-      // create global memory declaration
-      let value = ast::GlobalDecl::new_boxed(span_alloc.into_updated_span(span_last), ty, init);
-      // create global symbol definition
-      ast::GlobalDef::new_boxed(span.into_updated_span(span_last), name, value)
-    })
+    let init = self.parse_init()?;
+    let mut span_last = init.span;
+    // get attributes and alignment
+    let (attrs, align) = self.parse_global_attrs(&mut span_last)?;
+    // create global memory declaration
+    let span_alloc = span_alloc.into_updated_span(span_last);
+    let value = ast::GlobalDecl::new_boxed(span_alloc, ty, init, attrs, align);
+    // create global symbol definition
+    Ok(ast::GlobalDef::new_boxed(
+      span.into_updated_span(span_last),
+      name,
+      value,
+    ))
   }
 
   /// Parses function definitions.
@@ -190,6 +310,28 @@ impl<T: Read> Parser<T> {
     Ok(ast::FunDecl::new_boxed(span, name, params, ret))
   }
 
+  /// Parses named type definitions.
+  fn parse_type_def(&mut self) -> Result {
+    let span = self.span();
+    // eat 'type'
+    self.next_token()?;
+    // get type name
+    let name_span = self.span();
+    let name = read!(self, TokenKind::Symbol, "type name")?;
+    if !name.starts_with('@') {
+      return_error!(name_span, "expected global type name, found '{}'", name);
+    }
+    // check & eat '='
+    self.expect(TokenKind::Other('='))?;
+    // get definition
+    let ty = self.parse_type()?;
+    Ok(ast::TypeDef::new_boxed(
+      span.into_updated_span(ty.span),
+      name,
+      ty,
+    ))
+  }
+
   /// Parses types.
   fn parse_type(&mut self) -> Result {
     let Token { span, kind } = &self.cur_token;
@@ -198,10 +340,18 @@ impl<T: Read> Parser<T> {
       TokenKind::Other('[') => self.parse_array_type(),
       TokenKind::Other('*') => self.parse_pointer_type(),
       TokenKind::Other('(') => self.parse_fun_type(),
+      TokenKind::Symbol(s) if s.starts_with('@') => self.parse_named_type(),
       _ => return_error!(span, "expected type, found {}", kind),
     }
   }
 
+  /// Parses named types.
+  fn parse_named_type(&mut self) -> Result {
+    let span = self.span();
+    let name = read!(self, TokenKind::Symbol, "type name")?;
+    Ok(ast::NamedType::new_boxed(span, name))
+  }
+
   /// Parses 32-bit integer types.
   fn parse_int_type(&mut self) -> Result {
     let span = self.span();
@@ -279,9 +429,12 @@ impl<T: Read> Parser<T> {
         TokenKind::Symbol(_) => self.parse_symbol_def(),
         TokenKind::Keyword(Keyword::Store) => self.parse_store(),
         TokenKind::Keyword(Keyword::Call) => self.parse_fun_call(),
+        TokenKind::Keyword(Keyword::Assume) => self.parse_assume(),
         TokenKind::Keyword(Keyword::Br) => { exit_flag = true; self.parse_branch() },
         TokenKind::Keyword(Keyword::Jump) => { exit_flag = true; self.parse_jump() },
+        TokenKind::Keyword(Keyword::Switch) => { exit_flag = true; self.parse_switch() },
         TokenKind::Keyword(Keyword::Ret) => { exit_flag = true; self.parse_return() },
+        TokenKind::Keyword(Keyword::Unreachable) => { exit_flag = true; self.parse_unreachable() },
         ? => return_error!(span, "expected statement, found {}", kind),
         break if TokenKind::Other('}') | TokenKind::End => { exit_flag = true; },
       }?);
@@ -303,17 +456,29 @@ impl<T: Read> Parser<T> {
     // check & eat '='
     self.expect(TokenKind::Other('='))?;
     // get value
-    let Token { span: sp, kind } = &self.cur_token;
+    self
+      .parse_expr()
+      .map(|value| ast::SymbolDef::new_boxed(span.into_updated_span(value.span), name, value))
+  }
+
+  /// Parses expressions, which are right-hand sides of local symbol
+  /// definitions.
+  fn parse_expr(&mut self) -> Result {
+    let Token { span, kind } = &self.cur_token;
     match kind {
       TokenKind::Keyword(Keyword::Alloc) => self.parse_mem_decl(),
       TokenKind::Keyword(Keyword::Load) => self.parse_load(),
       TokenKind::Keyword(Keyword::GetPtr) => self.parse_get_pointer(),
       TokenKind::Keyword(Keyword::GetElemPtr) => self.parse_get_element_pointer(),
+      TokenKind::Keyword(Keyword::ExtractValue) => self.parse_extract_value(),
+      TokenKind::Keyword(Keyword::InsertValue) => self.parse_insert_value(),
       TokenKind::BinaryOp(_) => self.parse_binary_expr(),
+      TokenKind::UnaryOp(_) => self.parse_unary_expr(),
+      TokenKind::Keyword(Keyword::Checked) => self.parse_checked_binary_expr(),
+      TokenKind::Keyword(Keyword::Copy) => self.parse_copy(),
       TokenKind::Keyword(Keyword::Call) => self.parse_fun_call(),
-      _ => return_error!(sp, "expected expression, found {}", kind),
+      _ => return_error!(span, "expected expression, found {}", kind),
     }
-    .map(|value| ast::SymbolDef::new_boxed(span.into_updated_span(value.span), name, value))
   }
 
   /// Parses memory declarations.
@@ -322,9 +487,61 @@ impl<T: Read> Parser<T> {
     // eat 'alloc'
     self.next_token()?;
     // get type
-    self
-      .parse_type()
-      .map(|ty| ast::MemDecl::new_boxed(span.into_updated_span(ty.span), ty))
+    let ty = self.parse_type()?;
+    let mut span = span.into_updated_span(ty.span);
+    // get alignment
+    let align = self.parse_align(&mut span)?;
+    Ok(ast::MemDecl::new_boxed(span, ty, align))
+  }
+
+  /// Parses optional attributes (like `, readonly`) and alignments of
+  /// global memory declarations, and updates the given span to the end
+  /// of them. The alignment must be the last one.
+  fn parse_global_attrs(
+    &mut self,
+    span: &mut Span,
+  ) -> std::result::Result<(GlobalAttrs, Option<usize>), Error> {
+    let mut attrs = GlobalAttrs::default();
+    while self.is_token(TokenKind::Other(',')) {
+      // eat ','
+      self.next_token()?;
+      // get attribute
+      let attr = match self.cur_token.kind {
+        TokenKind::Keyword(Keyword::ReadOnly) => &mut attrs.readonly,
+        TokenKind::Keyword(Keyword::ThreadLocal) => &mut attrs.thread_local,
+        _ => return Ok((attrs, Some(self.parse_align_value(span)?))),
+      };
+      *attr = true;
+      span.update_span(self.span());
+      self.next_token()?;
+    }
+    Ok((attrs, None))
+  }
+
+  /// Parses optional alignments of memory declarations (`, align N`),
+  /// and updates the given span to the end of the alignment.
+  fn parse_align(&mut self, span: &mut Span) -> std::result::Result<Option<usize>, Error> {
+    if !self.is_token(TokenKind::Other(',')) {
+      return Ok(None);
+    }
+    // eat ','
+    self.next_token()?;
+    self.parse_align_value(span).map(Some)
+  }
+
+  /// Parses alignments after the ',' (`align N`), and updates the given
+  /// span to the end of the alignment.
+  fn parse_align_value(&mut self, span: &mut Span) -> std::result::Result<usize, Error> {
+    // check & eat 'align'
+    self.expect(TokenKind::Keyword(Keyword::Align))?;
+    // get alignment
+    let span_align = self.span();
+    let align = read!(self, TokenKind::Int, "alignment")?;
+    if align <= 0 || align > u32::MAX as i64 || !(align as u64).is_power_of_two() {
+      return_error!(span_align, "alignment '{}' is not a power of two", align);
+    }
+    span.update_span(span_align);
+    Ok(align as usize)
   }
 
   /// Parses loads.
@@ -394,17 +611,114 @@ impl<T: Read> Parser<T> {
     Ok(ast::GetElementPointer::new_boxed(span, symbol, value))
   }
 
+  /// Parses element extractions.
+  fn parse_extract_value(&mut self) -> Result {
+    let mut span = self.span();
+    // eat 'extractvalue'
+    self.next_token()?;
+    // get symbol name
+    let symbol = read!(self, TokenKind::Symbol, "symbol")?;
+    // check & eat ','
+    self.expect(TokenKind::Other(','))?;
+    // get index
+    span.update_span(self.span());
+    let index = read!(self, TokenKind::Int, "index")? as usize;
+    Ok(ast::ExtractValue::new_boxed(span, symbol, index))
+  }
+
+  /// Parses element insertions.
+  fn parse_insert_value(&mut self) -> Result {
+    let mut span = self.span();
+    // eat 'insertvalue'
+    self.next_token()?;
+    // get symbol name
+    let symbol = read!(self, TokenKind::Symbol, "symbol")?;
+    // check & eat ','
+    self.expect(TokenKind::Other(','))?;
+    // get value
+    let value = self.parse_value()?;
+    // check & eat ','
+    self.expect(TokenKind::Other(','))?;
+    // get index
+    span.update_span(self.span());
+    let index = read!(self, TokenKind::Int, "index")? as usize;
+    Ok(ast::InsertValue::new_boxed(span, symbol, value, index))
+  }
+
   /// Parses binary expressions.
   fn parse_binary_expr(&mut self) -> Result {
     let span = self.span();
     // get operator
     let op = read!(self, TokenKind::BinaryOp, "binary operator")?;
+    // get flags
+    let mut flags = BinaryFlags::default();
+    loop {
+      let flag = match &self.cur_token.kind {
+        TokenKind::Keyword(Keyword::Nsw) => &mut flags.nsw,
+        TokenKind::Keyword(Keyword::Nuw) => &mut flags.nuw,
+        TokenKind::Keyword(Keyword::Exact) => &mut flags.exact,
+        _ => break,
+      };
+      if *flag {
+        return_error!(self.span(), "duplicate flag {}", self.cur_token.kind);
+      }
+      *flag = true;
+      self.next_token()?;
+    }
+    // get lhs & rhs
+    let lhs = self.parse_value()?;
+    self.expect(TokenKind::Other(','))?;
+    self.parse_value().map(|rhs| {
+      let span = span.into_updated_span(rhs.span);
+      ast::BinaryExpr::new_boxed(span, op, flags, lhs, rhs)
+    })
+  }
+
+  /// Parses unary expressions.
+  fn parse_unary_expr(&mut self) -> Result {
+    let span = self.span();
+    // get operator
+    let op = read!(self, TokenKind::UnaryOp, "unary operator")?;
+    // get operand
+    self
+      .parse_value()
+      .map(|opr| ast::UnaryExpr::new_boxed(span.into_updated_span(opr.span), op, opr))
+  }
+
+  /// Parses overflow-checked binary expressions.
+  fn parse_checked_binary_expr(&mut self) -> Result {
+    let span = self.span();
+    // eat 'checked'
+    self.next_token()?;
+    // get operator
+    let op = read!(self, TokenKind::BinaryOp, "binary operator")?;
     // get lhs & rhs
     let lhs = self.parse_value()?;
     self.expect(TokenKind::Other(','))?;
     self
       .parse_value()
-      .map(|rhs| ast::BinaryExpr::new_boxed(span.into_updated_span(rhs.span), op, lhs, rhs))
+      .map(|rhs| ast::CheckedBinaryExpr::new_boxed(span.into_updated_span(rhs.span), op, lhs, rhs))
+  }
+
+  /// Parses copies.
+  fn parse_copy(&mut self) -> Result {
+    let mut span = self.span();
+    // eat 'copy'
+    self.next_token()?;
+    // get symbol name
+    span.update_span(self.span());
+    read!(self, TokenKind::Symbol, "symbol").map(|symbol| ast::Copy::new_boxed(span, symbol))
+  }
+
+  /// Parses assumptions.
+  fn parse_assume(&mut self) -> Result {
+    let span = self.span();
+    // eat 'assume'
+    self.next_token()?;
+    // get condition
+    self
+      .parse_value()
+      .map(|cond| ast::Assume::new_boxed(span.into_updated_span(cond.span), cond))
   }
 
   /// Parses branches.
@@ -452,6 +766,53 @@ impl<T: Read> Parser<T> {
     ))
   }
 
+  /// Parses multi-way branches.
+  fn parse_switch(&mut self) -> Result {
+    let span = self.span();
+    // eat 'switch'
+    self.next_token()?;
+    // get selector
+    let cond = self.parse_value()?;
+    // check & eat ','
+    self.expect(TokenKind::Other(','))?;
+    // get default target basic block and its arguments
+    let default = read!(self, TokenKind::Symbol, "basic block name")?;
+    let (default_args, _) = self.parse_opt_list(|s| s.parse_value())?;
+    // check & eat ','
+    self.expect(TokenKind::Other(','))?;
+    // check & eat '['
+    self.expect(TokenKind::Other('['))?;
+    // get cases
+    let mut cases = Vec::new();
+    if !self.is_token(TokenKind::Other(']')) {
+      loop {
+        // get case value
+        let span = self.span();
+        let value = Self::int_value(&span, read!(self, TokenKind::Int, "integer")?)?;
+        // check & eat ':'
+        self.expect(TokenKind::Other(':'))?;
+        // get target basic block and its arguments
+        let bb = read!(self, TokenKind::Symbol, "basic block name")?;
+        let (args, _) = self.parse_opt_list(|s| s.parse_value())?;
+        cases.push((value, bb, args));
+        // eat ','
+        if !self.is_token(TokenKind::Other(',')) {
+          break;
+        }
+        self.next_token()?;
+      }
+    }
+    // check & eat ']'
+    let sp = self.expect(TokenKind::Other(']'))?;
+    Ok(ast::Switch::new_boxed(
+      span.into_updated_span(sp),
+      cond,
+      default,
+      default_args,
+      cases,
+    ))
+  }
+
   /// Parses function calls.
   fn parse_fun_call(&mut self) -> Result {
     let span = self.span();
@@ -485,6 +846,14 @@ impl<T: Read> Parser<T> {
     Ok(ast::Return::new_boxed(span, value))
   }
 
+  /// Parses unreachables.
+  fn parse_unreachable(&mut self) -> Result {
+    let span = self.span();
+    // eat 'unreachable'
+    self.next_token()?;
+    Ok(ast::Unreachable::new_boxed(span))
+  }
+
   /// Parses values.
   fn parse_value(&mut self) -> Result {
     let Token { span, kind } = &self.cur_token;
@@ -492,7 +861,7 @@ impl<T: Read> Parser<T> {
       // symbol reference
       TokenKind::Symbol(s) => ast::SymbolRef::new_boxed(*span, s.clone()),
       // integer literal
-      TokenKind::Int(i) => ast::IntVal::new_boxed(*span, *i as i32),
+      TokenKind::Int(i) => ast::IntVal::new_boxed(*span, Self::int_value(span, *i)?),
       // undefined value
       TokenKind::Keyword(Keyword::Undef) => ast::UndefVal::new_boxed(*span),
       // unknown
@@ -502,13 +871,27 @@ impl<T: Read> Parser<T> {
     Ok(ret)
   }
 
+  /// Converts the given integer literal to an `i32` value.
+  ///
+  /// Integer literals in range `[-2^31, 2^32)` are accepted. Literals
+  /// greater than `i32::MAX` are wrapped in two's complement, for
+  /// example, both `0xffffffff` and `4294967295` are `-1`. Literals
+  /// out of the range are reported as errors.
+  fn int_value(span: &Span, i: i64) -> std::result::Result<i32, Error> {
+    if (i32::MIN as i64..=u32::MAX as i64).contains(&i) {
+      Ok(i as i32)
+    } else {
+      return_error!(span, "integer literal '{}' is out of range", i)
+    }
+  }
+
   /// Parses initializers.
   fn parse_init(&mut self) -> Result {
     let Token { span, kind } = &self.cur_token;
     match kind {
       // integer literal
       TokenKind::Int(i) => {
-        let ast = ast::IntVal::new_boxed(*span, *i as i32);
+        let ast = ast::IntVal::new_boxed(*span, Self::int_value(span, *i)?);
         self.next_token()?;
         Ok(ast)
       }
@@ -526,11 +909,56 @@ impl<T: Read> Parser<T> {
       }
       // aggregate
       TokenKind::Other('{') => self.parse_aggregate(),
+      // string literal, which is an aggregate of characters
+      TokenKind::Str(string) => {
+        if string.is_empty() {
+          return_error!(span, "string literal can not be empty");
+        }
+        let elems = string
+          .iter()
+          .map(|b| ast::IntVal::new_boxed(*span, *b as i32))
+          .collect();
+        let ast = ast::Aggregate::new_boxed(*span, elems);
+        self.next_token()?;
+        Ok(ast)
+      }
+      // address of global allocation, or pointer calculation
+      TokenKind::Symbol(_)
+      | TokenKind::Keyword(Keyword::GetPtr)
+      | TokenKind::Keyword(Keyword::GetElemPtr) => self.parse_pointer_init(),
       // unknown
       _ => return_error!(span, "expected initializer, found {}", kind),
     }
   }
 
+  /// Parses pointer initializers.
+  fn parse_pointer_init(&mut self) -> Result {
+    let Token { span, kind } = &self.cur_token;
+    let mut span = *span;
+    let is_elem_ptr = match kind {
+      // symbol reference
+      TokenKind::Symbol(s) => {
+        let ast = ast::SymbolRef::new_boxed(span, s.clone());
+        self.next_token()?;
+        return Ok(ast);
+      }
+      TokenKind::Keyword(Keyword::GetPtr) => false,
+      TokenKind::Keyword(Keyword::GetElemPtr) => true,
+      _ => return_error!(span, "expected pointer initializer, found {}", kind),
+    };
+    // eat 'getptr' or 'getelemptr'
+    self.next_token()?;
+    // get source
+    let src = self.parse_pointer_init()?;
+    // check & eat ','
+    self.expect(TokenKind::Other(','))?;
+    // get index
+    let index_span = self.span();
+    span.update_span(index_span);
+    let index = Self::int_value(&index_span, read!(self, TokenKind::Int, "index")?)?;
+    Ok(ast::PointerInit::new_boxed(span, is_elem_ptr, src, index))
+  }
+
   /// Parses aggregates.
   fn parse_aggregate(&mut self) -> Result {
     let span = self.span();
@@ -646,6 +1074,8 @@ mod test {
           len: 10,
         }),
         init: new_ast!(ZeroInit),
+        attrs: GlobalAttrs::default(),
+        align: None,
       }),
     });
     assert_eq!(ast, expected);
@@ -693,6 +1123,7 @@ mod test {
             name: "%3".into(),
             value: new_ast!(BinaryExpr {
               op: BinaryOp::Mul,
+              flags: BinaryFlags::default(),
               lhs: new_ast!(SymbolRef {
                 symbol: "%2".into(),
               }),
@@ -780,4 +1211,38 @@ mod test {
     assert_eq!(parser.parse_next().unwrap(), new_ast!(End));
     assert_eq!(parser.parse_next().unwrap(), new_ast!(End));
   }
+
+  #[test]
+  fn parse_fragments() {
+    use crate::front::{parse_type, parse_value};
+    assert_eq!(
+      parse_type("*[i32, 4]").unwrap(),
+      new_ast!(PointerType {
+        base: new_ast!(ArrayType {
+          base: new_ast!(IntType),
+          len: 4,
+        }),
+      })
+    );
+    assert_eq!(
+      parse_value("%x").unwrap(),
+      new_ast!(SymbolRef {
+        symbol: "%x".into(),
+      })
+    );
+    assert_eq!(
+      parse_value("add 1, @y").unwrap(),
+      new_ast!(BinaryExpr {
+        op: BinaryOp::Add,
+        flags: BinaryFlags::default(),
+        lhs: new_ast!(IntVal { value: 1 }),
+        rhs: new_ast!(SymbolRef {
+          symbol: "@y".into(),
+        }),
+      })
+    );
+    assert!(parse_type("i32 i32").is_err());
+    assert!(parse_value("%x = add 1, 2").is_err());
+    assert!(parse_value("").is_err());
+  }
 }
@@ -1,8 +1,10 @@
 //! Span ([`Span`]) and error ([`Error`]) related implementations.
 
+use crate::ir::span::{SourcePos, SourceSpan};
 use std::cell::RefCell;
 use std::fmt::{self, Arguments};
 use std::path::PathBuf;
+use std::rc::Rc;
 
 #[cfg(not(feature = "no-front-logger"))]
 use colored::*;
@@ -118,11 +120,23 @@ impl Span {
     });
   }
 
+  /// A dummy span for synthesized nodes, which does not point at any
+  /// source code.
+  pub const EMPTY: Span = Span {
+    start: Pos { line: 0, col: 0 },
+    end: Pos { line: 0, col: 0 },
+  };
+
   /// Creates a new span from [`Pos`].
   pub fn new(start: Pos) -> Self {
     Self { start, end: start }
   }
 
+  /// Checks if the current span is the dummy span [`Span::EMPTY`].
+  pub fn is_empty(&self) -> bool {
+    self.start.line == 0
+  }
+
   /// Resets the global state in all spans.
   pub fn reset(file: FileType) {
     Self::STATE.with(|gs| {
@@ -226,6 +240,11 @@ impl Span {
     });
   }
 
+  /// Gets the name of the current input file set by [`Span::reset`].
+  pub fn file_name() -> String {
+    Self::STATE.with(|gs| gs.borrow().file.to_string())
+  }
+
   /// Gets the number of errors.
   pub fn error_num() -> usize {
     Self::STATE.with(|gs| gs.borrow().err_num)
@@ -307,6 +326,54 @@ impl Span {
     self.end = span.end;
   }
 
+  /// Returns a new span that covers both the current span and the given
+  /// span, and anything between them.
+  ///
+  /// The dummy span [`Span::EMPTY`] is ignored.
+  pub fn merge(&self, other: &Span) -> Span {
+    if self.is_empty() {
+      *other
+    } else if other.is_empty() {
+      *self
+    } else {
+      Self {
+        start: self.start.min(other.start),
+        end: self.end.max(other.end),
+      }
+    }
+  }
+
+  /// Returns a new span from the start of the current span to the end of
+  /// the given span, for example, from the first child of an AST node to
+  /// the last child.
+  ///
+  /// Returns the other span if either of the spans is [`Span::EMPTY`].
+  pub fn to(&self, end: &Span) -> Span {
+    if self.is_empty() {
+      *end
+    } else if end.is_empty() {
+      *self
+    } else {
+      self.into_updated_span(*end)
+    }
+  }
+
+  /// Returns the start position.
+  pub fn start(&self) -> Pos {
+    self.start
+  }
+
+  /// Returns the end position.
+  pub fn end(&self) -> Pos {
+    self.end
+  }
+
+  /// Converts the current span into a [`SourceSpan`] in the given file.
+  pub fn to_source_span(&self, file: Rc<str>) -> SourceSpan {
+    let pos = |p: Pos| SourcePos::new(p.line, p.col);
+    SourceSpan::new(file, pos(self.start), pos(self.end))
+  }
+
   /// Checks if the current span is in the same line as the given span.
   pub fn is_in_same_line_as(&self, span: &Span) -> bool {
     self.end.line == span.start.line
@@ -413,6 +480,14 @@ impl Default for Span {
   }
 }
 
+impl fmt::Display for Span {
+  /// Formats the span as `file:line:col`, where `file` is the current
+  /// input file set by [`Span::reset`].
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    Self::STATE.with(|gs| write!(f, "{}:{}", gs.borrow().file, self.start))
+  }
+}
+
 impl fmt::Debug for Span {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     write!(f, "{}-{}", self.start, self.end)
@@ -420,7 +495,9 @@ impl fmt::Debug for Span {
 }
 
 /// A line-column mark.
-#[derive(Clone, Copy)]
+///
+/// Marks are ordered by line number first, and then by column number.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Pos {
   line: u32,
   col: u32,
@@ -432,6 +509,16 @@ impl Pos {
     Self { line: 1, col: 0 }
   }
 
+  /// Returns the line number.
+  pub fn line(&self) -> u32 {
+    self.line
+  }
+
+  /// Returns the column number.
+  pub fn col(&self) -> u32 {
+    self.col
+  }
+
   /// Updates the line number ans column number based on the given character.
   pub fn update(&mut self, c: char) {
     match c {
@@ -580,4 +667,22 @@ mod test {
     assert_eq!(format!("{}", sp3.start), "1:1");
     assert_eq!(format!("{}", sp3.end), "10:15");
   }
+
+  #[test]
+  fn span_merge() {
+    let span = |l1, c1, l2, c2| Span {
+      start: Pos { line: l1, col: c1 },
+      end: Pos { line: l2, col: c2 },
+    };
+    let fmt = |s: Span| format!("{}-{}", s.start, s.end);
+    let (a, b) = (span(1, 4, 1, 8), span(3, 0, 3, 2));
+    assert_eq!(fmt(a.merge(&b)), "1:4-3:2");
+    assert_eq!(fmt(b.merge(&a)), "1:4-3:2");
+    assert_eq!(fmt(a.merge(&span(1, 6, 1, 7))), "1:4-1:8");
+    assert_eq!(fmt(a.to(&b)), "1:4-3:2");
+    assert_eq!(fmt(Span::EMPTY.merge(&b)), "3:0-3:2");
+    assert_eq!(fmt(a.to(&Span::EMPTY)), "1:4-1:8");
+    assert!(Span::EMPTY.merge(&Span::EMPTY).is_empty());
+    assert!(!a.is_empty());
+  }
 }
@@ -6,7 +6,7 @@
 //! the lexing process.
 
 use crate::front::span::Span;
-use crate::ir::BinaryOp;
+use crate::ir::{BinaryOp, UnaryOp};
 use std::fmt;
 
 /// Tokens that will be generated by the lexer.
@@ -37,12 +37,16 @@ impl Default for Token {
 pub enum TokenKind {
   /// Integer literal.
   Int(i64),
+  /// String literal, as UTF-8 bytes with escape sequences resolved.
+  Str(Vec<u8>),
   /// Symbol (identifier like `@id` or `%id`).
   Symbol(String),
   /// Keyword.
   Keyword(Keyword),
   /// Binary operator.
   BinaryOp(BinaryOp),
+  /// Unary operator.
+  UnaryOp(UnaryOp),
   /// Other characters.
   Other(char),
   /// End of file.
@@ -53,9 +57,11 @@ impl fmt::Display for TokenKind {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     match self {
       TokenKind::Int(v) => write!(f, "integer '{}'", v),
+      TokenKind::Str(v) => write!(f, "string \"{}\"", v.escape_ascii()),
       TokenKind::Symbol(v) => write!(f, "symbol '{}'", v),
       TokenKind::Keyword(v) => write!(f, "keyword '{}'", v),
       TokenKind::BinaryOp(v) => write!(f, "binary operator '{}'", v),
+      TokenKind::UnaryOp(v) => write!(f, "unary operator '{}'", v),
       TokenKind::Other(v) => write!(f, "character '{}'", v),
       TokenKind::End => write!(f, "end of file"),
     }
@@ -83,18 +89,46 @@ pub enum Keyword {
   GetPtr,
   /// Keyword `getelemptr`.
   GetElemPtr,
+  /// Keyword `extractvalue`.
+  ExtractValue,
+  /// Keyword `insertvalue`.
+  InsertValue,
+  /// Keyword `checked`.
+  Checked,
+  /// Keyword `copy`.
+  Copy,
+  /// Keyword `assume`.
+  Assume,
   /// Keyword `br`.
   Br,
   /// Keyword `jump`.
   Jump,
+  /// Keyword `switch`.
+  Switch,
   /// Keyword `call`.
   Call,
   /// Keyword `ret`.
   Ret,
+  /// Keyword `unreachable`.
+  Unreachable,
   /// Keyword `fun`.
   Fun,
   /// Keyword `decl`.
   Decl,
+  /// Keyword `nsw`.
+  Nsw,
+  /// Keyword `nuw`.
+  Nuw,
+  /// Keyword `exact`.
+  Exact,
+  /// Keyword `align`.
+  Align,
+  /// Keyword `readonly`.
+  ReadOnly,
+  /// Keyword `threadlocal`.
+  ThreadLocal,
+  /// Keyword `type`.
+  Type,
 }
 
 impl fmt::Display for Keyword {
@@ -109,12 +143,26 @@ impl fmt::Display for Keyword {
       Keyword::Store => f.write_str("store"),
       Keyword::GetPtr => f.write_str("getptr"),
       Keyword::GetElemPtr => f.write_str("getelemptr"),
+      Keyword::ExtractValue => f.write_str("extractvalue"),
+      Keyword::InsertValue => f.write_str("insertvalue"),
+      Keyword::Checked => f.write_str("checked"),
+      Keyword::Copy => f.write_str("copy"),
+      Keyword::Assume => f.write_str("assume"),
       Keyword::Br => f.write_str("br"),
       Keyword::Jump => f.write_str("jump"),
+      Keyword::Switch => f.write_str("switch"),
       Keyword::Call => f.write_str("call"),
       Keyword::Ret => f.write_str("ret"),
+      Keyword::Unreachable => f.write_str("unreachable"),
       Keyword::Fun => f.write_str("fun"),
       Keyword::Decl => f.write_str("decl"),
+      Keyword::Nsw => f.write_str("nsw"),
+      Keyword::Nuw => f.write_str("nuw"),
+      Keyword::Exact => f.write_str("exact"),
+      Keyword::Align => f.write_str("align"),
+      Keyword::ReadOnly => f.write_str("readonly"),
+      Keyword::ThreadLocal => f.write_str("threadlocal"),
+      Keyword::Type => f.write_str("type"),
     }
   }
 }
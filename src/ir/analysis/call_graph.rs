@@ -0,0 +1,225 @@
+//! Call graph analysis.
+
+use crate::ir::entities::{Function, Program, ValueKind};
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Call graph of all functions in a program.
+///
+/// Edges are taken from `call` instructions. Koopa IR has no function
+/// pointers, so every callee is known statically and there is no
+/// indirect call to be handled conservatively.
+pub struct CallGraph {
+  /// Callees of functions, in the order of first call sites.
+  callees: HashMap<Function, Vec<Function>>,
+  /// Callers of functions, in function layout order.
+  callers: HashMap<Function, Vec<Function>>,
+  /// Strongly connected components in reverse topological order.
+  sccs: Vec<Vec<Function>>,
+}
+
+impl CallGraph {
+  /// Computes the call graph of the given program.
+  pub fn compute(program: &Program) -> Self {
+    let mut callees: HashMap<_, Vec<_>> = HashMap::new();
+    let mut callers: HashMap<_, Vec<_>> = HashMap::new();
+    for &func in program.func_layout() {
+      callers.entry(func).or_default();
+      let data = program.func(func);
+      let succs = callees.entry(func).or_default();
+      for call in data.calls() {
+        if let ValueKind::Call(call) = data.dfg().value(call).kind() {
+          if !succs.contains(&call.callee()) {
+            succs.push(call.callee());
+            callers.entry(call.callee()).or_default().push(func);
+          }
+        }
+      }
+    }
+    let sccs = tarjan(program.func_layout(), &callees);
+    Self {
+      callees,
+      callers,
+      sccs,
+    }
+  }
+
+  /// Returns functions called by the given function, in the order of
+  /// their first call sites.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the given function is not in the program.
+  pub fn callees(&self, func: Function) -> &[Function] {
+    &self.callees[&func]
+  }
+
+  /// Returns functions that call the given function, in function
+  /// layout order.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the given function is not in the program.
+  pub fn callers(&self, func: Function) -> &[Function] {
+    &self.callers[&func]
+  }
+
+  /// Returns strongly connected components of the call graph in reverse
+  /// topological order, that is, callees come before their callers.
+  ///
+  /// Members of each component are mutually recursive, and are sorted
+  /// in function layout order.
+  pub fn sccs(&self) -> &[Vec<Function>] {
+    &self.sccs
+  }
+
+  /// Returns `true` if the given function is recursive, either directly
+  /// or through other functions.
+  pub fn is_recursive(&self, func: Function) -> bool {
+    self.callees(func).contains(&func)
+      || self
+        .sccs
+        .iter()
+        .any(|scc| scc.len() > 1 && scc.contains(&func))
+  }
+}
+
+/// Finds strongly connected components by Tarjan's algorithm.
+///
+/// Components are emitted in reverse topological order. Nodes are visited
+/// in the given order, so the result is deterministic. Members of each
+/// component are sorted in the given order.
+///
+/// All successors of the given nodes must be in the given nodes.
+pub(super) fn tarjan<T>(nodes: &[T], succs: &HashMap<T, Vec<T>>) -> Vec<Vec<T>>
+where
+  T: Copy + Eq + Hash,
+{
+  let pos: HashMap<_, _> = nodes.iter().enumerate().map(|(i, n)| (*n, i)).collect();
+  let mut index = HashMap::new();
+  let mut lowlink = HashMap::new();
+  let mut stack = Vec::new();
+  let mut on_stack = vec![false; nodes.len()];
+  let mut sccs = Vec::new();
+  for &root in nodes {
+    if index.contains_key(&root) {
+      continue;
+    }
+    // work list of (node, index of the next successor)
+    let mut work = vec![(root, 0)];
+    while let Some((node, next)) = work.pop() {
+      if next == 0 {
+        let i = index.len();
+        index.insert(node, i);
+        lowlink.insert(node, i);
+        stack.push(node);
+        on_stack[pos[&node]] = true;
+      }
+      let succs = &succs[&node];
+      if let Some(&succ) = succs.get(next) {
+        work.push((node, next + 1));
+        if !index.contains_key(&succ) {
+          work.push((succ, 0));
+        } else if on_stack[pos[&succ]] {
+          let low = lowlink[&node].min(index[&succ]);
+          lowlink.insert(node, low);
+        }
+        continue;
+      }
+      // all successors are visited, update the parent
+      if let Some(&(parent, _)) = work.last() {
+        let low = lowlink[&parent].min(lowlink[&node]);
+        lowlink.insert(parent, low);
+      }
+      if lowlink[&node] == index[&node] {
+        let mut scc = Vec::new();
+        loop {
+          let n = stack.pop().unwrap();
+          on_stack[pos[&n]] = false;
+          scc.push(n);
+          if n == node {
+            break;
+          }
+        }
+        scc.sort_by_key(|n| pos[n]);
+        sccs.push(scc);
+      }
+    }
+  }
+  sccs
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::front::Driver;
+  use crate::ir::builder_traits::*;
+
+  #[test]
+  fn sccs() {
+    let driver: Driver<_> = r#"
+decl @getint(): i32
+
+decl @placeholder(i32): i32
+
+fun @fact(@n: i32): i32 {
+%entry:
+  %0 = call @fact(@n)
+  ret %0
+}
+
+fun @even(@n: i32): i32 {
+%entry:
+  %0 = call @placeholder(@n)
+  ret %0
+}
+
+fun @odd(@n: i32): i32 {
+%entry:
+  %0 = call @even(@n)
+  %1 = call @fact(@n)
+  ret %0
+}
+
+fun @main(): i32 {
+%entry:
+  %0 = call @even(10)
+  %1 = call @fact(5)
+  %2 = call @getint()
+  ret %0
+}
+"#
+    .into();
+    let mut program = driver.generate_program().unwrap();
+    let funcs = program.func_layout().to_vec();
+    let (getint, placeholder) = (funcs[0], funcs[1]);
+    let (fact, even, odd, main) = (funcs[2], funcs[3], funcs[4], funcs[5]);
+    // make `@even` call `@odd`, which can not be written in the text form
+    let even_data = program.func_mut(even);
+    let call = even_data.calls().next().unwrap();
+    let n = even_data.params()[0];
+    even_data
+      .dfg_mut()
+      .replace_value_with(call)
+      .call(odd, vec![n]);
+    let cg = CallGraph::compute(&program);
+    assert_eq!(cg.callees(main), [even, fact, getint]);
+    assert_eq!(cg.callers(fact), [fact, odd, main]);
+    assert!(cg.callees(getint).is_empty());
+    assert!(cg.callers(placeholder).is_empty());
+    assert!(cg.is_recursive(fact) && cg.is_recursive(even));
+    assert!(!cg.is_recursive(main));
+    assert_eq!(
+      cg.sccs(),
+      [
+        vec![getint],
+        vec![placeholder],
+        vec![fact],
+        vec![even, odd],
+        vec![main]
+      ]
+    );
+    let order: Vec<_> = program.functions_in_topological_order().collect();
+    assert_eq!(order, [getint, placeholder, fact, even, odd, main]);
+  }
+}
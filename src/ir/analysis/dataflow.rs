@@ -0,0 +1,354 @@
+//! Generic data flow analysis framework.
+
+use crate::ir::entities::{BasicBlock, FunctionData, Value};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+
+/// A join-semilattice of data flow facts.
+pub trait Lattice: Clone + PartialEq {
+  /// Returns the bottom element, which is the identity of
+  /// [`merge`](Lattice::merge).
+  fn bottom() -> Self;
+
+  /// Merges (joins) the given fact into the current fact.
+  fn merge(&mut self, other: &Self);
+}
+
+/// Sets ordered by inclusion, merged by union.
+impl<T: Clone + Eq + Hash> Lattice for HashSet<T> {
+  fn bottom() -> Self {
+    HashSet::new()
+  }
+
+  fn merge(&mut self, other: &Self) {
+    self.extend(other.iter().cloned());
+  }
+}
+
+/// Direction of a data flow analysis.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+  /// Facts flow from the entry to the exits of basic blocks, and from
+  /// predecessors to successors.
+  Forward,
+  /// Facts flow from the exits to the entry of basic blocks, and from
+  /// successors to predecessors.
+  Backward,
+}
+
+/// A data flow analysis, which can be solved by [`DataFlow`].
+pub trait Analysis {
+  /// Type of data flow facts.
+  type Fact: Lattice;
+
+  /// Direction of the analysis.
+  const DIRECTION: Direction;
+
+  /// Returns the fact at the boundary, that is, at the entry of the
+  /// entry basic block for forward analyses, or at the exit of basic
+  /// blocks without successors for backward analyses.
+  ///
+  /// Defaults to [`Lattice::bottom`].
+  fn boundary(&self, _func: &FunctionData) -> Self::Fact {
+    Self::Fact::bottom()
+  }
+
+  /// Transfers the given fact over the given instruction.
+  fn transfer(&self, func: &FunctionData, inst: Value, fact: &mut Self::Fact);
+
+  /// Transfers the given fact over the parameters of the given basic
+  /// block, which are defined at the entry of the basic block.
+  ///
+  /// Defaults to doing nothing.
+  fn transfer_params(&self, _func: &FunctionData, _bb: BasicBlock, _fact: &mut Self::Fact) {}
+}
+
+/// References to analyses are also analyses, so an analysis can be
+/// solved without giving up its ownership.
+impl<A: Analysis> Analysis for &A {
+  type Fact = A::Fact;
+  const DIRECTION: Direction = A::DIRECTION;
+
+  fn boundary(&self, func: &FunctionData) -> Self::Fact {
+    (*self).boundary(func)
+  }
+
+  fn transfer(&self, func: &FunctionData, inst: Value, fact: &mut Self::Fact) {
+    (*self).transfer(func, inst, fact)
+  }
+
+  fn transfer_params(&self, func: &FunctionData, bb: BasicBlock, fact: &mut Self::Fact) {
+    (*self).transfer_params(func, bb, fact)
+  }
+}
+
+/// Solution of a data flow analysis on a function.
+///
+/// The solution is computed by a worklist algorithm, which iterates
+/// until a fixed point is reached. Basic blocks not in the layout have
+/// no facts, and basic blocks unreachable from the boundary may have
+/// bottom facts.
+pub struct DataFlow<A: Analysis> {
+  analysis: A,
+  block_in: HashMap<BasicBlock, A::Fact>,
+  block_out: HashMap<BasicBlock, A::Fact>,
+}
+
+impl<A: Analysis> DataFlow<A> {
+  /// Solves the given analysis on the given function.
+  pub fn solve(func: &FunctionData, analysis: A) -> Self {
+    let bbs: Vec<_> = func.layout().bbs().keys().copied().collect();
+    let succs: HashMap<_, HashSet<_>> = bbs
+      .iter()
+      .map(|&bb| {
+        let term = func.terminator(bb).into_iter();
+        let succs = term.flat_map(|t| func.dfg().value(t).kind().bb_uses());
+        (bb, succs.collect())
+      })
+      .collect();
+    let entry = func.layout().entry_bb();
+    let mut block_in: HashMap<_, _> = bbs.iter().map(|&bb| (bb, A::Fact::bottom())).collect();
+    let mut block_out = block_in.clone();
+    // process basic blocks in the flow order
+    let mut worklist: VecDeque<_> = match A::DIRECTION {
+      Direction::Forward => bbs.iter().copied().collect(),
+      Direction::Backward => bbs.iter().rev().copied().collect(),
+    };
+    let mut in_worklist: HashSet<_> = bbs.iter().copied().collect();
+    while let Some(bb) = worklist.pop_front() {
+      in_worklist.remove(&bb);
+      let (sources, dests, (input, output)) = match A::DIRECTION {
+        Direction::Forward => (
+          func.preds(bb).to_vec(),
+          succs[&bb].iter().copied().collect::<Vec<_>>(),
+          (&mut block_in, &mut block_out),
+        ),
+        Direction::Backward => (
+          succs[&bb].iter().copied().collect(),
+          func.preds(bb).to_vec(),
+          (&mut block_out, &mut block_in),
+        ),
+      };
+      // merge facts from the sources
+      let is_boundary = match A::DIRECTION {
+        Direction::Forward => Some(bb) == entry,
+        Direction::Backward => sources.is_empty(),
+      };
+      let mut fact = if is_boundary {
+        analysis.boundary(func)
+      } else {
+        A::Fact::bottom()
+      };
+      for src in &sources {
+        fact.merge(&output[src]);
+      }
+      input.insert(bb, fact.clone());
+      // transfer and propagate to the destinations
+      Self::transfer_block(&analysis, func, bb, &mut fact);
+      if output[&bb] != fact {
+        output.insert(bb, fact);
+        for dest in dests {
+          if in_worklist.insert(dest) {
+            worklist.push_back(dest);
+          }
+        }
+      }
+    }
+    Self {
+      analysis,
+      block_in,
+      block_out,
+    }
+  }
+
+  /// Transfers the given fact over the given basic block in the
+  /// direction of the analysis.
+  fn transfer_block(analysis: &A, func: &FunctionData, bb: BasicBlock, fact: &mut A::Fact) {
+    match A::DIRECTION {
+      Direction::Forward => {
+        analysis.transfer_params(func, bb, fact);
+        for inst in func.layout().block_insts(bb) {
+          analysis.transfer(func, inst, fact);
+        }
+      }
+      Direction::Backward => {
+        for inst in func.block_insts_rev(bb) {
+          analysis.transfer(func, inst, fact);
+        }
+        analysis.transfer_params(func, bb, fact);
+      }
+    }
+  }
+
+  /// Returns a reference to the analysis.
+  pub fn analysis(&self) -> &A {
+    &self.analysis
+  }
+
+  /// Returns the fact at the entry of the given basic block.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the basic block is not in the layout.
+  pub fn block_in(&self, bb: BasicBlock) -> &A::Fact {
+    &self.block_in[&bb]
+  }
+
+  /// Returns the fact at the exit of the given basic block.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the basic block is not in the layout.
+  pub fn block_out(&self, bb: BasicBlock) -> &A::Fact {
+    &self.block_out[&bb]
+  }
+
+  /// Returns the fact right before the given instruction, which is
+  /// recomputed from the facts of its basic block.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the instruction is not in the layout.
+  pub fn fact_before(&self, func: &FunctionData, inst: Value) -> A::Fact {
+    match A::DIRECTION {
+      Direction::Forward => self.replay(func, inst),
+      Direction::Backward => {
+        let mut fact = self.replay(func, inst);
+        self.analysis.transfer(func, inst, &mut fact);
+        fact
+      }
+    }
+  }
+
+  /// Returns the fact right after the given instruction, which is
+  /// recomputed from the facts of its basic block.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the instruction is not in the layout.
+  pub fn fact_after(&self, func: &FunctionData, inst: Value) -> A::Fact {
+    match A::DIRECTION {
+      Direction::Forward => {
+        let mut fact = self.replay(func, inst);
+        self.analysis.transfer(func, inst, &mut fact);
+        fact
+      }
+      Direction::Backward => self.replay(func, inst),
+    }
+  }
+
+  /// Transfers the fact from the start of the basic block of the given
+  /// instruction in the direction of the analysis, until reaching the
+  /// instruction.
+  fn replay(&self, func: &FunctionData, inst: Value) -> A::Fact {
+    let bb = func
+      .layout()
+      .parent_bb(inst)
+      .expect("`inst` is not in the layout");
+    match A::DIRECTION {
+      Direction::Forward => {
+        let mut fact = self.block_in[&bb].clone();
+        self.analysis.transfer_params(func, bb, &mut fact);
+        for i in func.layout().block_insts(bb).take_while(|i| *i != inst) {
+          self.analysis.transfer(func, i, &mut fact);
+        }
+        fact
+      }
+      Direction::Backward => {
+        let mut fact = self.block_out[&bb].clone();
+        for i in func.block_insts_rev(bb).take_while(|i| *i != inst) {
+          self.analysis.transfer(func, i, &mut fact);
+        }
+        fact
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::front::Driver;
+  use crate::ir::ValueKind;
+
+  /// Collects integers stored to memory on any path.
+  struct StoredInts;
+
+  impl Analysis for StoredInts {
+    type Fact = HashSet<i32>;
+    const DIRECTION: Direction = Direction::Forward;
+
+    fn boundary(&self, _: &FunctionData) -> Self::Fact {
+      HashSet::from([0])
+    }
+
+    fn transfer(&self, func: &FunctionData, inst: Value, fact: &mut Self::Fact) {
+      if let ValueKind::Store(s) = func.dfg().value(inst).kind() {
+        if let ValueKind::Integer(i) = func.dfg().value(s.value()).kind() {
+          fact.insert(i.value());
+        }
+      }
+    }
+  }
+
+  /// Collects integers stored to memory on any path to an exit.
+  struct StoredIntsRev;
+
+  impl Analysis for StoredIntsRev {
+    type Fact = HashSet<i32>;
+    const DIRECTION: Direction = Direction::Backward;
+
+    fn transfer(&self, func: &FunctionData, inst: Value, fact: &mut Self::Fact) {
+      StoredInts.transfer(func, inst, fact)
+    }
+  }
+
+  #[test]
+  fn solve_loop() {
+    let driver: Driver<_> = r#"fun @f(@p: *i32, @c: i32) {
+%entry:
+  store 1, @p
+  jump %loop
+
+%loop:
+  store 2, @p
+  br @c, %body, %exit
+
+%body:
+  store 3, @p
+  jump %loop
+
+%exit:
+  store 4, @p
+  ret
+}
+"#
+    .into();
+    let program = driver.generate_program().unwrap();
+    let func = program.funcs().values().next().unwrap();
+    let bbs: Vec<_> = func.layout().bbs().keys().copied().collect();
+    let (entry, head, body, exit) = (bbs[0], bbs[1], bbs[2], bbs[3]);
+    let stores: Vec<_> = func.stores().collect();
+
+    let fwd = DataFlow::solve(func, StoredInts);
+    assert_eq!(fwd.block_in(entry), &HashSet::from([0]));
+    assert_eq!(fwd.block_in(head), &HashSet::from([0, 1, 2, 3]));
+    assert_eq!(fwd.block_out(body), &HashSet::from([0, 1, 2, 3]));
+    assert_eq!(fwd.block_in(exit), &HashSet::from([0, 1, 2, 3]));
+    assert_eq!(fwd.block_out(exit), &HashSet::from([0, 1, 2, 3, 4]));
+    assert_eq!(fwd.fact_before(func, stores[0]), HashSet::from([0]));
+    assert_eq!(fwd.fact_after(func, stores[0]), HashSet::from([0, 1]));
+
+    let bwd = DataFlow::solve(func, StoredIntsRev);
+    assert_eq!(bwd.block_out(exit), &HashSet::new());
+    assert_eq!(bwd.block_in(exit), &HashSet::from([4]));
+    assert_eq!(bwd.block_in(head), &HashSet::from([2, 3, 4]));
+    assert_eq!(bwd.block_in(entry), &HashSet::from([1, 2, 3, 4]));
+    assert_eq!(bwd.fact_after(func, stores[1]), HashSet::from([2, 3, 4]));
+    assert_eq!(bwd.fact_before(func, stores[1]), HashSet::from([2, 3, 4]));
+    assert_eq!(
+      bwd.fact_before(func, stores[0]),
+      HashSet::from([1, 2, 3, 4])
+    );
+  }
+}
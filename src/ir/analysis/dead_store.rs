@@ -0,0 +1,129 @@
+//! Dead store analysis.
+
+use crate::ir::analysis::dataflow::{Analysis, DataFlow, Direction};
+use crate::ir::entities::{FunctionData, Value, ValueKind};
+use std::collections::HashSet;
+
+/// Returns all dead `store` instructions of the given function, in
+/// layout order.
+///
+/// A store is dead if the stored memory is overwritten by another store
+/// or the function returns on all paths, before it is loaded again.
+///
+/// Only stores to local allocations that are directly loaded from and
+/// stored to are checked. Allocations whose addresses are used in other
+/// ways (like pointer calculations, calls, or being stored to memory)
+/// may be accessed through aliases, so stores to them are never dead.
+pub fn dead_stores(func: &FunctionData) -> Vec<Value> {
+  let allocs: HashSet<_> = func.allocs().filter(|a| is_tracked(func, *a)).collect();
+  if allocs.is_empty() {
+    return Vec::new();
+  }
+  // a store is dead if the allocation is not live right after it
+  let flow = DataFlow::solve(func, LiveAllocs { allocs });
+  let mut dead = Vec::new();
+  for &bb in func.layout().bbs().keys() {
+    let mut live = flow.block_out(bb).clone();
+    let start = dead.len();
+    for inst in func.block_insts_rev(bb) {
+      if let ValueKind::Store(s) = func.dfg().value(inst).kind() {
+        if flow.analysis().allocs.contains(&s.dest()) && !live.contains(&s.dest()) {
+          dead.push(inst);
+        }
+      }
+      flow.analysis().transfer(func, inst, &mut live);
+    }
+    dead[start..].reverse();
+  }
+  dead
+}
+
+/// Checks if all users of the given allocation are loads from it or
+/// stores to it.
+fn is_tracked(func: &FunctionData, alloc: Value) -> bool {
+  let dfg = func.dfg();
+  dfg
+    .value(alloc)
+    .used_by()
+    .iter()
+    .all(|u| match dfg.value(*u).kind() {
+      ValueKind::Load(l) => l.src() == alloc,
+      ValueKind::Store(s) => s.dest() == alloc && s.value() != alloc,
+      _ => false,
+    })
+}
+
+/// Backward analysis of live tracked allocations, which may be loaded
+/// before being stored to again.
+struct LiveAllocs {
+  allocs: HashSet<Value>,
+}
+
+impl Analysis for LiveAllocs {
+  type Fact = HashSet<Value>;
+  const DIRECTION: Direction = Direction::Backward;
+
+  fn transfer(&self, func: &FunctionData, inst: Value, fact: &mut Self::Fact) {
+    match func.dfg().value(inst).kind() {
+      ValueKind::Load(l) if self.allocs.contains(&l.src()) => {
+        fact.insert(l.src());
+      }
+      ValueKind::Store(s) => {
+        fact.remove(&s.dest());
+      }
+      _ => {}
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::front::Driver;
+
+  #[test]
+  fn find_dead_stores() {
+    let driver: Driver<_> = r#"fun @f(@c: i32): i32 {
+%entry:
+  %x = alloc i32
+  %y = alloc [i32, 2]
+  %z = alloc i32
+  store 1, %x
+  store 2, %x
+  %0 = getelemptr %y, 0
+  store 3, %0
+  store 3, %0
+  store 4, %z
+  br @c, %then, %else
+
+%then:
+  %1 = load %z
+  store 5, %x
+  jump %end
+
+%else:
+  store 6, %z
+  jump %end
+
+%end:
+  %2 = load %x
+  store 7, %x
+  ret %2
+}
+"#
+    .into();
+    let program = driver.generate_program().unwrap();
+    let func = program.funcs().values().next().unwrap();
+    let values: Vec<_> = dead_stores(func)
+      .into_iter()
+      .map(|s| match func.dfg().value(s).kind() {
+        ValueKind::Store(s) => match func.dfg().value(s.value()).kind() {
+          ValueKind::Integer(i) => i.value(),
+          _ => unreachable!(),
+        },
+        _ => unreachable!(),
+      })
+      .collect();
+    assert_eq!(values, [1, 6, 7]);
+  }
+}
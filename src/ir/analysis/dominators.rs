@@ -0,0 +1,530 @@
+//! Dominator tree analysis.
+
+use crate::ir::entities::{BasicBlock, FunctionData};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Dominator tree of basic blocks in a function.
+///
+/// Only basic blocks reachable from the entry basic block are in
+/// the tree. Edges are taken from the terminators of basic blocks.
+///
+/// The tree keeps a copy of the control flow graph, and can be updated
+/// incrementally after inserting or deleting a single edge, see
+/// [`DominatorTree::insert_edge`] and [`DominatorTree::delete_edge`].
+/// Updates follow the algorithms by Georgiadis et al. (as used by LLVM),
+/// only the part of the tree that may change is visited.
+pub struct DominatorTree {
+  /// Successors of basic blocks.
+  succs: HashMap<BasicBlock, Vec<BasicBlock>>,
+  /// Predecessors of basic blocks.
+  preds: HashMap<BasicBlock, Vec<BasicBlock>>,
+  /// Immediate dominators, the entry basic block is its own
+  /// immediate dominator.
+  idoms: HashMap<BasicBlock, BasicBlock>,
+  /// Depths of reachable basic blocks in the tree, the depth of
+  /// the entry basic block is 0.
+  depths: HashMap<BasicBlock, usize>,
+  /// Children of reachable basic blocks in the tree.
+  children: HashMap<BasicBlock, Vec<BasicBlock>>,
+}
+
+impl DominatorTree {
+  /// Computes the dominator tree of the given function.
+  ///
+  /// Uses the iterative algorithm by Cooper, Harvey and Kennedy.
+  pub fn compute(func: &FunctionData) -> Self {
+    let mut succs = HashMap::new();
+    let mut preds: HashMap<_, Vec<_>> = HashMap::new();
+    for &bb in func.layout().bbs().keys() {
+      let targets: Vec<_> = func
+        .terminator(bb)
+        .map_or_else(Vec::new, |t| func.dfg().value(t).kind().bb_uses().collect());
+      for &target in &targets {
+        preds.entry(target).or_default().push(bb);
+      }
+      succs.insert(bb, targets);
+    }
+    Self::from_graph(func.layout().entry_bb(), succs, preds)
+  }
+
+  /// Computes the dominator tree of the given control flow graph.
+  fn from_graph(
+    entry: Option<BasicBlock>,
+    succs: HashMap<BasicBlock, Vec<BasicBlock>>,
+    preds: HashMap<BasicBlock, Vec<BasicBlock>>,
+  ) -> Self {
+    let mut dom = Self {
+      succs,
+      preds,
+      idoms: HashMap::new(),
+      depths: HashMap::new(),
+      children: HashMap::new(),
+    };
+    if let Some(entry) = entry {
+      dom.idoms.insert(entry, entry);
+      dom.depths.insert(entry, 0);
+      dom.rebuild(entry, |_, _| true);
+    }
+    dom
+  }
+
+  /// Returns `true` if the given basic block is reachable from
+  /// the entry basic block.
+  pub fn is_reachable(&self, bb: BasicBlock) -> bool {
+    self.depths.contains_key(&bb)
+  }
+
+  /// Returns the immediate dominator of the given basic block.
+  ///
+  /// Returns `None` if the basic block is the entry basic block,
+  /// or is unreachable.
+  pub fn idom(&self, bb: BasicBlock) -> Option<BasicBlock> {
+    self.idoms.get(&bb).copied().filter(|idom| *idom != bb)
+  }
+
+  /// Returns `true` if basic block `a` dominates basic block `b`.
+  ///
+  /// Every reachable basic block dominates itself. Unreachable basic
+  /// blocks neither dominate nor are dominated by any basic block.
+  pub fn dominates(&self, a: BasicBlock, mut b: BasicBlock) -> bool {
+    if !self.is_reachable(a) || !self.is_reachable(b) {
+      return false;
+    }
+    while self.depths[&b] > self.depths[&a] {
+      b = self.idoms[&b];
+    }
+    a == b
+  }
+
+  /// Updates the dominator tree after inserting an edge from basic
+  /// block `from` to basic block `to` into the control flow graph.
+  ///
+  /// If `to` was reachable, only the basic blocks whose immediate
+  /// dominators change, and the deeper basic blocks on the paths to
+  /// them, are visited. Otherwise, the basic blocks that become
+  /// reachable are also computed from scratch.
+  pub fn insert_edge(&mut self, from: BasicBlock, to: BasicBlock) {
+    self.succs.entry(from).or_default().push(to);
+    self.preds.entry(to).or_default().push(from);
+    if !self.is_reachable(from) {
+      return;
+    }
+    if self.is_reachable(to) {
+      self.insert_reachable(from, to);
+      return;
+    }
+    // basic blocks that become reachable can only be entered through
+    // the new edge, so `to` is the root of their subtree
+    self.idoms.insert(to, from);
+    self.depths.insert(to, self.depths[&from] + 1);
+    self.children.entry(from).or_default().push(to);
+    let region = self.rebuild(to, |dom, bb| !dom.is_reachable(bb));
+    // then insert edges from the new subtree to the rest of the tree
+    let in_region: HashSet<_> = region.iter().copied().collect();
+    let edges: Vec<_> = region
+      .iter()
+      .flat_map(|&bb| self.succs(bb).map(move |succ| (bb, succ)))
+      .filter(|(_, succ)| !in_region.contains(succ))
+      .collect();
+    for (from, to) in edges {
+      self.insert_reachable(from, to);
+    }
+  }
+
+  /// Updates the dominator tree after inserting an edge between two
+  /// reachable basic blocks.
+  ///
+  /// Affected basic blocks are found by a depth-based search from `to`,
+  /// and their new immediate dominator is the nearest common dominator
+  /// of `from` and `to`.
+  fn insert_reachable(&mut self, from: BasicBlock, to: BasicBlock) {
+    let ncd = self.nca(from, to);
+    if ncd == to || ncd == self.idoms[&to] {
+      return;
+    }
+    let min_depth = self.depths[&ncd] + 1;
+    // visit affected basic blocks in decreasing order of depth
+    let mut buckets = BTreeMap::from([(self.depths[&to], vec![to])]);
+    let mut visited = HashSet::from([to]);
+    let mut affected = Vec::new();
+    while let Some(mut bucket) = buckets.last_entry() {
+      let depth = *bucket.key();
+      let bb = bucket.get_mut().pop().unwrap();
+      if bucket.get().is_empty() {
+        bucket.remove();
+      }
+      affected.push(bb);
+      // deeper basic blocks are not affected, but may lead to other
+      // affected basic blocks
+      let mut stack = vec![bb];
+      while let Some(bb) = stack.pop() {
+        for succ in self.succs(bb) {
+          match self.depths.get(&succ) {
+            Some(&d) if d > min_depth && visited.insert(succ) => {
+              if d > depth {
+                stack.push(succ);
+              } else {
+                buckets.entry(d).or_default().push(succ);
+              }
+            }
+            _ => {}
+          }
+        }
+      }
+    }
+    for &bb in &affected {
+      self.set_idom(bb, ncd);
+    }
+    for bb in affected {
+      self.update_depths(bb);
+    }
+  }
+
+  /// Updates the dominator tree after deleting an edge from basic
+  /// block `from` to basic block `to` from the control flow graph.
+  ///
+  /// If `to` is still reachable, only the subtree of the nearest
+  /// common dominator of `from` and `to` is recomputed. Otherwise,
+  /// the subtree of `to` is removed, and the smallest subtree that
+  /// contains all basic blocks entered from it is recomputed.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the edge does not exist.
+  pub fn delete_edge(&mut self, from: BasicBlock, to: BasicBlock) {
+    let succs = self.succs.get_mut(&from).expect("edge does not exist");
+    let index = succs.iter().position(|bb| *bb == to);
+    succs.remove(index.expect("edge does not exist"));
+    let preds = self.preds.get_mut(&to).unwrap();
+    preds.remove(preds.iter().position(|bb| *bb == from).unwrap());
+    if !self.is_reachable(from) || self.succs[&from].contains(&to) {
+      return;
+    }
+    // nothing changes if `to` dominates `from`
+    let ncd = self.nca(from, to);
+    if ncd == to {
+      return;
+    }
+    // `to` is still reachable if it has a predecessor that it does not
+    // dominate
+    let supported = self.preds[&to]
+      .iter()
+      .any(|&p| self.is_reachable(p) && self.nca(to, p) != to);
+    if supported {
+      self.rebuild_subtree(ncd);
+    } else {
+      self.delete_unreachable(to);
+    }
+  }
+
+  /// Removes the subtree of the given basic block, which becomes
+  /// unreachable, and recomputes the affected part of the tree.
+  fn delete_unreachable(&mut self, to: BasicBlock) {
+    // the subtree contains basic blocks reachable from `to` through
+    // deeper basic blocks, other basic blocks entered from the subtree
+    // may change their immediate dominators
+    let depth = self.depths[&to];
+    let mut subtree = HashSet::from([to]);
+    let mut worklist = vec![to];
+    let mut entered = Vec::new();
+    while let Some(bb) = worklist.pop() {
+      for succ in self.succs(bb) {
+        match self.depths.get(&succ) {
+          Some(&d) if d > depth => {
+            if subtree.insert(succ) {
+              worklist.push(succ);
+            }
+          }
+          Some(_) => entered.push(succ),
+          None => {}
+        }
+      }
+    }
+    let mut root = to;
+    for bb in entered {
+      let ncd = self.nca(bb, to);
+      if ncd != bb && self.depths[&ncd] < self.depths[&root] {
+        root = ncd;
+      }
+    }
+    // remove the subtree
+    let parent = self.idoms[&to];
+    self
+      .children
+      .get_mut(&parent)
+      .unwrap()
+      .retain(|bb| *bb != to);
+    for bb in subtree {
+      self.idoms.remove(&bb);
+      self.depths.remove(&bb);
+      self.children.remove(&bb);
+    }
+    if root != to {
+      self.rebuild_subtree(root);
+    }
+  }
+
+  /// Recomputes the subtree of the given reachable basic block.
+  fn rebuild_subtree(&mut self, root: BasicBlock) {
+    let depth = self.depths[&root];
+    self.rebuild(root, |dom, bb| {
+      dom.depths.get(&bb).is_some_and(|d| *d > depth)
+    });
+  }
+
+  /// Recomputes the subtree of the given reachable basic block, which
+  /// contains all basic blocks reachable from the root through basic
+  /// blocks accepted by `in_region`. Returns basic blocks in the subtree.
+  ///
+  /// Basic blocks in the subtree other than the root can only be
+  /// entered from the subtree, so their immediate dominators can be
+  /// computed locally by the iterative algorithm.
+  fn rebuild<F>(&mut self, root: BasicBlock, in_region: F) -> Vec<BasicBlock>
+  where
+    F: Fn(&Self, BasicBlock) -> bool,
+  {
+    let order = self.region_order(root, in_region);
+    let rpo: HashMap<_, _> = order.iter().enumerate().map(|(i, bb)| (*bb, i)).collect();
+    // compute immediate dominators
+    let mut idoms = HashMap::from([(root, root)]);
+    let mut changed = true;
+    while changed {
+      changed = false;
+      for &bb in &order[1..] {
+        let new_idom = self.preds[&bb]
+          .iter()
+          .filter(|p| idoms.contains_key(*p))
+          .copied()
+          .reduce(|a, b| intersect(&idoms, &rpo, a, b))
+          .unwrap();
+        if idoms.insert(bb, new_idom) != Some(new_idom) {
+          changed = true;
+        }
+      }
+    }
+    // update the tree
+    for &bb in &order {
+      self.children.insert(bb, Vec::new());
+    }
+    for &bb in &order[1..] {
+      self.idoms.insert(bb, idoms[&bb]);
+      self.children.get_mut(&idoms[&bb]).unwrap().push(bb);
+    }
+    self.update_depths(root);
+    order
+  }
+
+  /// Returns the reverse post-order of basic blocks reachable from
+  /// the given basic block through basic blocks accepted by `in_region`.
+  fn region_order<F>(&self, root: BasicBlock, in_region: F) -> Vec<BasicBlock>
+  where
+    F: Fn(&Self, BasicBlock) -> bool,
+  {
+    let mut order = Vec::new();
+    let mut visited = HashSet::from([root]);
+    let mut stack = vec![(root, self.succs(root))];
+    while let Some((bb, iter)) = stack.last_mut() {
+      match iter.next() {
+        Some(succ) if in_region(self, succ) && visited.insert(succ) => {
+          stack.push((succ, self.succs(succ)))
+        }
+        Some(_) => {}
+        None => {
+          order.push(*bb);
+          stack.pop();
+        }
+      }
+    }
+    order.reverse();
+    order
+  }
+
+  /// Returns successors of the given basic block.
+  fn succs(&self, bb: BasicBlock) -> impl Iterator<Item = BasicBlock> + '_ {
+    self.succs.get(&bb).into_iter().flatten().copied()
+  }
+
+  /// Returns the nearest common dominator of the given reachable
+  /// basic blocks.
+  fn nca(&self, mut a: BasicBlock, mut b: BasicBlock) -> BasicBlock {
+    while a != b {
+      if self.depths[&a] < self.depths[&b] {
+        b = self.idoms[&b];
+      } else {
+        a = self.idoms[&a];
+      }
+    }
+    a
+  }
+
+  /// Sets the immediate dominator of the given reachable basic block.
+  fn set_idom(&mut self, bb: BasicBlock, idom: BasicBlock) {
+    let old = self.idoms.insert(bb, idom).unwrap();
+    self.children.get_mut(&old).unwrap().retain(|c| *c != bb);
+    self.children.entry(idom).or_default().push(bb);
+  }
+
+  /// Updates depths of basic blocks in the subtree of the given
+  /// basic block, from the depth of its immediate dominator.
+  fn update_depths(&mut self, bb: BasicBlock) {
+    let idom = self.idoms[&bb];
+    let depth = if idom == bb {
+      0
+    } else {
+      self.depths[&idom] + 1
+    };
+    let mut worklist = vec![(bb, depth)];
+    while let Some((bb, depth)) = worklist.pop() {
+      self.depths.insert(bb, depth);
+      let children = self.children.get(&bb).into_iter().flatten();
+      worklist.extend(children.map(|c| (*c, depth + 1)));
+    }
+  }
+}
+
+/// Returns the nearest common dominator of the given basic blocks.
+fn intersect(
+  idoms: &HashMap<BasicBlock, BasicBlock>,
+  rpo: &HashMap<BasicBlock, usize>,
+  mut a: BasicBlock,
+  mut b: BasicBlock,
+) -> BasicBlock {
+  while a != b {
+    while rpo[&a] > rpo[&b] {
+      a = idoms[&a];
+    }
+    while rpo[&b] > rpo[&a] {
+      b = idoms[&b];
+    }
+  }
+  a
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::front::Driver;
+  use crate::ir::builder_traits::*;
+  use crate::ir::{Program, Type};
+
+  #[test]
+  fn diamond_and_loop() {
+    let driver: Driver<_> = r#"fun @f(@a: i32) {
+%entry:
+  br @a, %then, %else
+
+%then:
+  jump %loop
+
+%else:
+  jump %loop
+
+%loop:
+  br @a, %loop, %end
+
+%end:
+  ret
+}
+"#
+    .into();
+    let mut program = driver.generate_program().unwrap();
+    let func = program.funcs_mut().values_mut().next().unwrap();
+    let bbs: Vec<_> = func.layout().bbs().keys().copied().collect();
+    let (entry, then, els, lp, end) = (bbs[0], bbs[1], bbs[2], bbs[3], bbs[4]);
+    // add an unreachable basic block
+    let dead = func.dfg_mut().new_bb().basic_block(None);
+    let jump = func.dfg_mut().new_value().jump(end);
+    func.layout_mut().bbs_mut().push_key_back(dead).unwrap();
+    func
+      .layout_mut()
+      .bb_mut(dead)
+      .insts_mut()
+      .push_key_back(jump)
+      .unwrap();
+    let dom = DominatorTree::compute(func);
+    assert_eq!(dom.idom(entry), None);
+    assert_eq!(dom.idom(then), Some(entry));
+    assert_eq!(dom.idom(els), Some(entry));
+    assert_eq!(dom.idom(lp), Some(entry));
+    assert_eq!(dom.idom(end), Some(lp));
+    assert_eq!(dom.idom(dead), None);
+    assert!(dom.dominates(entry, end));
+    assert!(dom.dominates(lp, lp));
+    assert!(!dom.dominates(then, lp));
+    assert!(!dom.dominates(dead, end));
+    assert!(!dom.is_reachable(dead));
+  }
+
+  #[test]
+  fn incremental_updates() {
+    let mut program = Program::new();
+    let func = FunctionData::new("@f".into(), vec![Type::get_i32()], Type::get_unit());
+    let func = program.new_func(func);
+    let func = program.func_mut(func);
+    let cond = func.params()[0];
+    let bbs: Vec<_> = (0..6)
+      .map(|_| func.dfg_mut().new_bb().basic_block(None))
+      .collect();
+    func.layout_mut().bbs_mut().extend(bbs.iter().copied());
+    let mut succs = vec![Vec::new(); bbs.len()];
+    // sets successors of the given basic block by changing its terminator
+    let set_succs = |func: &mut FunctionData, i: usize, targets: Vec<usize>| {
+      let targets: Vec<_> = targets.into_iter().map(|t| bbs[t]).collect();
+      let term = match targets.as_slice() {
+        [] => func.dfg_mut().new_value().ret(None),
+        [t] => func.dfg_mut().new_value().jump(*t),
+        [t, f] => func.dfg_mut().new_value().branch(cond, *t, *f),
+        _ => {
+          let cases = targets[1..].iter().enumerate();
+          let cases = cases.map(|(i, bb)| (i as i32, *bb)).collect();
+          func.dfg_mut().new_value().switch(cond, targets[0], cases)
+        }
+      };
+      func.set_terminator(bbs[i], term);
+    };
+    let check = |func: &FunctionData, dom: &DominatorTree| {
+      let expected = DominatorTree::compute(func);
+      for &bb in func.layout().bbs().keys() {
+        assert_eq!(dom.is_reachable(bb), expected.is_reachable(bb));
+        assert_eq!(dom.idom(bb), expected.idom(bb));
+      }
+    };
+    // initial graph: 0 -> 1 -> 2 -> 3, 1 -> 4 -> 3
+    for (i, targets) in [(0, vec![1]), (1, vec![2, 4]), (2, vec![3]), (4, vec![3])] {
+      succs[i] = targets.clone();
+      set_succs(func, i, targets);
+    }
+    for i in [3, 5] {
+      set_succs(func, i, Vec::new());
+    }
+    let mut dom = DominatorTree::compute(func);
+    // (insert, from, to)
+    let edits = [
+      (true, 3, 1),
+      (true, 0, 3),
+      (true, 2, 5),
+      (true, 5, 4),
+      (false, 1, 4),
+      (true, 3, 3),
+      (false, 0, 1),
+      (true, 4, 2),
+      (false, 2, 5),
+      (true, 0, 5),
+      (false, 0, 3),
+      (false, 0, 5),
+      (true, 0, 1),
+    ];
+    for (insert, from, to) in edits {
+      if insert {
+        succs[from].push(to);
+        dom.insert_edge(bbs[from], bbs[to]);
+      } else {
+        let index = succs[from].iter().position(|t| *t == to).unwrap();
+        succs[from].remove(index);
+        dom.delete_edge(bbs[from], bbs[to]);
+      }
+      set_succs(func, from, succs[from].clone());
+      check(func, &dom);
+    }
+  }
+}
@@ -0,0 +1,143 @@
+//! Detection of trivially infinite loops.
+
+use crate::ir::analysis::call_graph::tarjan;
+use crate::ir::analysis::DominatorTree;
+use crate::ir::entities::{BasicBlock, FunctionData, ValueKind};
+use std::collections::{HashMap, HashSet};
+
+/// Returns headers of loops in the given function that can never be
+/// exited normally, in layout order.
+///
+/// A loop is a strongly connected component of basic blocks reachable
+/// from the entry basic block, with at least one edge. It can be exited
+/// normally if any of its basic blocks returns, or if it has an edge to
+/// a basic block outside the loop from which a `ret` or another loop is
+/// reachable. So an exit that only leads to `unreachable` does not count.
+/// Nested loops are merged into the outermost one, which is reported as
+/// a whole.
+///
+/// The header of a loop is the basic block that is entered from outside
+/// the loop. If there are multiple such basic blocks (the loop is
+/// irreducible), the first one in layout order is reported.
+///
+/// The detection is purely based on the control flow graph, so it does
+/// not look at branch conditions, and assumes that calls always return.
+pub fn find_infinite_loops(func: &FunctionData) -> Vec<BasicBlock> {
+  let dom = DominatorTree::compute(func);
+  let bbs: Vec<_> = (func.layout().bbs().keys())
+    .copied()
+    .filter(|bb| dom.is_reachable(*bb))
+    .collect();
+  let succs: HashMap<_, Vec<_>> = bbs
+    .iter()
+    .map(|&bb| {
+      let mut targets = Vec::new();
+      if let Some(t) = func.terminator(bb) {
+        for target in func.dfg().value(t).kind().bb_uses() {
+          if !targets.contains(&target) {
+            targets.push(target);
+          }
+        }
+      }
+      (bb, targets)
+    })
+    .collect();
+  let returns = |bb: &BasicBlock| {
+    let kind = func.terminator(*bb).map(|t| func.dfg().value(t).kind());
+    matches!(kind, Some(ValueKind::Return(_)))
+  };
+  // components are in reverse topological order, so successors
+  // are visited before their predecessors
+  let mut escapes = HashSet::new();
+  let mut headers = Vec::new();
+  for scc in tarjan(&bbs, &succs) {
+    let is_loop = scc.len() > 1 || succs[&scc[0]].contains(&scc[0]);
+    let exits = scc.iter().any(returns)
+      || (scc.iter())
+        .flat_map(|bb| &succs[bb])
+        .any(|s| !scc.contains(s) && escapes.contains(s));
+    if is_loop && !exits {
+      let header = scc.iter().find(|bb| {
+        Some(**bb) == func.layout().entry_bb() || func.preds(**bb).iter().any(|p| !scc.contains(p))
+      });
+      headers.push(*header.unwrap());
+    }
+    if is_loop || exits {
+      escapes.extend(scc);
+    }
+  }
+  let pos: HashMap<_, _> = bbs.iter().enumerate().map(|(i, bb)| (*bb, i)).collect();
+  headers.sort_by_key(|bb| pos[bb]);
+  headers
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::front::Driver;
+
+  #[test]
+  fn find_loops() {
+    let driver: Driver<_> = r#"fun @f(@c: i32): i32 {
+%entry:
+  br @c, %self, %outer
+
+%self:
+  jump %self
+
+%outer:
+  br @c, %inner, %trap
+
+%inner:
+  br @c, %inner, %outer
+
+%trap:
+  unreachable
+
+%exit:
+  jump %exit
+}
+
+fun @g(@c: i32): i32 {
+%entry:
+  jump %first
+
+%first:
+  br @c, %first, %second
+
+%second:
+  br @c, %second, %end
+
+%end:
+  ret 0
+}
+
+fun @h(@c: i32): i32 {
+%entry:
+  br @c, %a, %b
+
+%a:
+  jump %b
+
+%b:
+  jump %a
+}
+"#
+    .into();
+    let program = driver.generate_program().unwrap();
+    let loops = |name: &str| {
+      let func = (program.funcs().values())
+        .find(|f| f.name() == name)
+        .unwrap();
+      find_infinite_loops(func)
+        .into_iter()
+        .map(|bb| func.dfg().bb(bb).name().clone().unwrap())
+        .collect::<Vec<_>>()
+    };
+    // `%exit` is unreachable, `%outer` only exits to a trap
+    assert_eq!(loops("@f"), ["%self", "%outer"]);
+    assert!(loops("@g").is_empty());
+    // irreducible loop entered from both `%a` and `%b`
+    assert_eq!(loops("@h"), ["%a"]);
+  }
+}
@@ -0,0 +1,320 @@
+//! Liveness analysis.
+
+use crate::ir::analysis::dataflow::{Analysis, DataFlow, Direction};
+use crate::ir::entities::{BasicBlock, FunctionData, Value, ValueKind};
+use std::collections::{HashMap, HashSet};
+
+/// Live variable analysis of a function.
+///
+/// Variables are function parameters, basic block parameters and
+/// instructions that produce a non-unit value. Constants and global
+/// values are never live.
+///
+/// Instructions are numbered in layout order. Each basic block takes
+/// one extra number before its first instruction, at which its
+/// parameters are defined, and function parameters are defined at `0`.
+/// Arguments passed to a basic block are used by the terminator of
+/// the predecessor.
+pub struct Liveness {
+  /// Variables live at the entry of basic blocks.
+  live_in: HashMap<BasicBlock, HashSet<Value>>,
+  /// Variables live at the exit of basic blocks.
+  live_out: HashMap<BasicBlock, HashSet<Value>>,
+  /// Numbers of instructions.
+  indices: HashMap<Value, usize>,
+  /// Live intervals, sorted by start point.
+  intervals: Vec<LiveInterval>,
+}
+
+impl Liveness {
+  /// Computes the liveness of variables in the given function.
+  pub fn compute(func: &FunctionData) -> Self {
+    let is_var = |v: Value| is_var(func, v);
+    // number instructions
+    let mut indices = HashMap::new();
+    let mut ranges = HashMap::new();
+    let mut index = 1;
+    for (&bb, node) in func.layout().bbs() {
+      let start = index;
+      index += 1;
+      for &inst in node.insts().keys() {
+        indices.insert(inst, index);
+        index += 1;
+      }
+      ranges.insert(bb, (start, index - 1));
+    }
+    // solve the data flow equations
+    let bbs: Vec<_> = func.layout().bbs().keys().copied().collect();
+    let flow = DataFlow::solve(func, LiveVars);
+    let live_in: HashMap<_, _> = bbs
+      .iter()
+      .map(|&bb| (bb, flow.block_in(bb).clone()))
+      .collect();
+    let live_out: HashMap<_, _> = (bbs.iter())
+      .map(|&bb| (bb, flow.block_out(bb).clone()))
+      .collect();
+    // build live intervals
+    let mut points: HashMap<Value, (usize, usize)> = HashMap::new();
+    let mut extend = |v: Value, i: usize| {
+      let (lo, hi) = points.entry(v).or_insert((i, i));
+      *lo = (*lo).min(i);
+      *hi = (*hi).max(i);
+    };
+    for &p in func.params() {
+      extend(p, 0);
+    }
+    for &bb in &bbs {
+      let (start, end) = ranges[&bb];
+      for &p in func.dfg().bb(bb).params() {
+        extend(p, start);
+      }
+      for &v in &live_in[&bb] {
+        extend(v, start);
+      }
+      for &v in &live_out[&bb] {
+        extend(v, end);
+      }
+      for &inst in func.layout().bbs().node(&bb).unwrap().insts().keys() {
+        let i = indices[&inst];
+        if is_var(inst) {
+          extend(inst, i);
+        }
+        for v in func.dfg().value(inst).kind().value_uses() {
+          if is_var(v) {
+            extend(v, i);
+          }
+        }
+      }
+    }
+    let mut intervals: Vec<_> = points
+      .into_iter()
+      .map(|(value, (start, end))| LiveInterval { value, start, end })
+      .collect();
+    intervals.sort_by_key(|i| (i.start, i.end));
+    Self {
+      live_in,
+      live_out,
+      indices,
+      intervals,
+    }
+  }
+
+  /// Returns variables live at the entry of the given basic block.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the basic block is not in the layout.
+  pub fn live_in(&self, bb: BasicBlock) -> &HashSet<Value> {
+    &self.live_in[&bb]
+  }
+
+  /// Returns variables live at the exit of the given basic block.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the basic block is not in the layout.
+  pub fn live_out(&self, bb: BasicBlock) -> &HashSet<Value> {
+    &self.live_out[&bb]
+  }
+
+  /// Returns the number of the given instruction.
+  pub fn index(&self, inst: Value) -> Option<usize> {
+    self.indices.get(&inst).copied()
+  }
+
+  /// Returns live intervals of all variables, sorted by start point.
+  pub fn intervals(&self) -> &[LiveInterval] {
+    &self.intervals
+  }
+
+  /// Returns the live interval of the given variable.
+  pub fn interval(&self, value: Value) -> Option<&LiveInterval> {
+    self.intervals.iter().find(|i| i.value == value)
+  }
+
+  /// Returns variables live across the given `call` instruction, that
+  /// is, live both right before and right after the call, in the order
+  /// of [`intervals`](Liveness::intervals).
+  ///
+  /// The result of the call is defined by the call, and arguments only
+  /// used by the call die at the call, so neither is live across it.
+  /// The liveness must be computed on the given function.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the given value is not a `call` instruction in the
+  /// layout of the function.
+  pub fn live_across(&self, func: &FunctionData, call: Value) -> Vec<Value> {
+    assert!(
+      matches!(func.dfg().value(call).kind(), ValueKind::Call(_)),
+      "`call` is not a call instruction"
+    );
+    let bb = (func.layout().parent_bb(call)).expect("`call` is not in the layout");
+    let mut live = self.live_out[&bb].clone();
+    for inst in func.block_insts_rev(bb).take_while(|i| *i != call) {
+      LiveVars.transfer(func, inst, &mut live);
+    }
+    // variables live after the call are also live before it, except
+    // the result of the call
+    live.remove(&call);
+    (self.intervals.iter())
+      .map(|i| i.value)
+      .filter(|v| live.contains(v))
+      .collect()
+  }
+}
+
+/// Checks if the given value is a variable.
+fn is_var(func: &FunctionData, v: Value) -> bool {
+  !v.is_global() && {
+    let data = func.dfg().value(v);
+    match data.kind() {
+      ValueKind::FuncArgRef(_) | ValueKind::BlockArgRef(_) => true,
+      kind => kind.is_local_inst() && !data.ty().is_unit(),
+    }
+  }
+}
+
+/// Backward analysis of live variables.
+struct LiveVars;
+
+impl Analysis for LiveVars {
+  type Fact = HashSet<Value>;
+  const DIRECTION: Direction = Direction::Backward;
+
+  fn transfer(&self, func: &FunctionData, inst: Value, fact: &mut Self::Fact) {
+    fact.remove(&inst);
+    let uses = func.dfg().value(inst).kind().value_uses();
+    fact.extend(uses.filter(|v| is_var(func, *v)));
+  }
+
+  fn transfer_params(&self, func: &FunctionData, bb: BasicBlock, fact: &mut Self::Fact) {
+    for p in func.dfg().bb(bb).params() {
+      fact.remove(p);
+    }
+  }
+}
+
+/// A live interval `[start, end]` of a variable.
+///
+/// The interval covers all points where the variable is live, and may
+/// also cover points where it is not (lifetime holes are not tracked).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LiveInterval {
+  value: Value,
+  start: usize,
+  end: usize,
+}
+
+impl LiveInterval {
+  /// Returns the variable.
+  pub fn value(&self) -> Value {
+    self.value
+  }
+
+  /// Returns the start point.
+  pub fn start(&self) -> usize {
+    self.start
+  }
+
+  /// Returns the end point.
+  pub fn end(&self) -> usize {
+    self.end
+  }
+
+  /// Returns `true` if the interval overlaps the given interval.
+  pub fn overlaps(&self, other: &LiveInterval) -> bool {
+    self.start <= other.end && other.start <= self.end
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::front::Driver;
+
+  #[test]
+  fn loop_liveness() {
+    let driver: Driver<_> = r#"
+fun @f(@n: i32): i32 {
+%entry:
+  %x = add @n, 1
+  jump %loop(0)
+
+%loop(%i: i32):
+  %c = lt %i, @n
+  br %c, %body, %exit
+
+%body:
+  %j = add %i, %x
+  jump %loop(%j)
+
+%exit:
+  ret %i
+}
+"#
+    .into();
+    let program = driver.generate_program().unwrap();
+    let func = program.funcs().values().next().unwrap();
+    let liveness = Liveness::compute(func);
+    let bbs: Vec<_> = func.layout().bbs().keys().copied().collect();
+    let (entry, head, body, exit) = (bbs[0], bbs[1], bbs[2], bbs[3]);
+    let insts: Vec<_> = func
+      .layout()
+      .bbs()
+      .iter()
+      .flat_map(|(_, n)| n.insts().keys().copied())
+      .collect();
+    let (n, i) = (func.params()[0], func.dfg().bb(head).params()[0]);
+    let (x, c, j) = (insts[0], insts[2], insts[4]);
+    assert!(liveness.live_in(entry).contains(&n));
+    assert_eq!(liveness.live_in(head), &HashSet::from([n, x]));
+    assert_eq!(liveness.live_out(head), &HashSet::from([n, x, i]));
+    assert_eq!(liveness.live_out(body), &HashSet::from([n, x]));
+    assert!(!liveness.live_in(body).contains(&j));
+    assert_eq!(liveness.live_in(exit), &HashSet::from([i]));
+    assert!(liveness.live_out(exit).is_empty());
+    // `@n` and `%x` live until the back edge
+    let back_edge = liveness.index(insts[5]).unwrap();
+    assert_eq!(liveness.interval(n).unwrap().end(), back_edge);
+    assert_eq!(liveness.interval(x).unwrap().end(), back_edge);
+    assert_eq!(
+      liveness.interval(c).unwrap().end(),
+      liveness.index(insts[3]).unwrap()
+    );
+    assert!(liveness
+      .interval(i)
+      .unwrap()
+      .overlaps(liveness.interval(j).unwrap()));
+    let starts: Vec<_> = liveness.intervals().iter().map(|i| i.start()).collect();
+    assert!(starts.windows(2).all(|w| w[0] <= w[1]));
+    assert_eq!(liveness.intervals().len(), 5);
+  }
+
+  #[test]
+  fn live_across_calls() {
+    let driver: Driver<_> = r#"decl @g(i32): i32
+
+fun @f(@a: i32, @b: i32): i32 {
+%entry:
+  %x = add @a, 1
+  %0 = call @g(@b)
+  %1 = call @g(%0)
+  %2 = add %1, %x
+  %3 = add %2, %0
+  ret %3
+}
+"#
+    .into();
+    let program = driver.generate_program().unwrap();
+    let func = program.func(program.func_layout()[1]);
+    let calls: Vec<_> = func.calls().collect();
+    let entry = func.layout().entry_bb().unwrap();
+    let x = func.layout().block_insts(entry).next().unwrap();
+    let liveness = Liveness::compute(func);
+    // `@b` dies at the first call, and `%0` is defined by it
+    assert_eq!(liveness.live_across(func, calls[0]), vec![x]);
+    // `%0` is used by the second call, and also used after it
+    assert_eq!(func.values_live_across(calls[1]), vec![x, calls[0]]);
+  }
+}
@@ -0,0 +1,37 @@
+//! Analyses of Koopa IR.
+//!
+//! This module provides analyses on in-memory form Koopa IR functions,
+//! including:
+//!
+//! * Call graph analysis ([`CallGraph`]).
+//! * Generic data flow analysis framework ([`DataFlow`]).
+//! * Dead store analysis ([`dead_stores`]).
+//! * Dominator tree analysis ([`DominatorTree`]).
+//! * Detection of trivially infinite loops ([`find_infinite_loops`]).
+//! * Integer range analysis ([`IntRange`]).
+//! * Liveness analysis ([`Liveness`]).
+//! * Reaching definitions analysis ([`ReachingDefs`]).
+//! * Relocation evaluation of global constant pointers ([`Relocation`]).
+//! * Loop trip count estimation ([`trip_count`]).
+
+mod call_graph;
+pub mod dataflow;
+mod dead_store;
+mod dominators;
+mod infinite_loop;
+mod liveness;
+mod range;
+mod reaching_defs;
+mod reloc;
+mod trip_count;
+
+pub use call_graph::CallGraph;
+pub use dataflow::DataFlow;
+pub use dead_store::dead_stores;
+pub use dominators::DominatorTree;
+pub use infinite_loop::find_infinite_loops;
+pub use liveness::{LiveInterval, Liveness};
+pub use range::{IntRange, Interval};
+pub use reaching_defs::ReachingDefs;
+pub use reloc::Relocation;
+pub use trip_count::{trip_count, TripCount};
@@ -0,0 +1,669 @@
+//! Integer range analysis.
+
+use crate::ir::entities::{BasicBlock, FunctionData, Value, ValueKind};
+use crate::ir::types::Type;
+use crate::ir::values::BinaryOp;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+
+/// A closed interval `[lo, hi]` of `i32` values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Interval {
+  lo: i32,
+  hi: i32,
+}
+
+impl Interval {
+  /// Creates a new interval `[lo, hi]`.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `lo` is greater than `hi`.
+  pub fn new(lo: i32, hi: i32) -> Self {
+    assert!(lo <= hi, "`lo` must not be greater than `hi`");
+    Self { lo, hi }
+  }
+
+  /// Creates a new interval that contains all `i32` values.
+  pub fn full() -> Self {
+    Self {
+      lo: i32::MIN,
+      hi: i32::MAX,
+    }
+  }
+
+  /// Creates a new interval that contains only the given value.
+  pub fn constant(value: i32) -> Self {
+    Self {
+      lo: value,
+      hi: value,
+    }
+  }
+
+  /// Returns the lower bound of the interval.
+  pub fn lo(&self) -> i32 {
+    self.lo
+  }
+
+  /// Returns the upper bound of the interval.
+  pub fn hi(&self) -> i32 {
+    self.hi
+  }
+
+  /// Returns `true` if the interval contains all `i32` values.
+  pub fn is_full(&self) -> bool {
+    self.lo == i32::MIN && self.hi == i32::MAX
+  }
+
+  /// Returns the value if the interval contains only one value.
+  pub fn as_constant(&self) -> Option<i32> {
+    (self.lo == self.hi).then_some(self.lo)
+  }
+
+  /// Returns `true` if the interval contains the given value.
+  pub fn contains(&self, value: i32) -> bool {
+    self.lo <= value && value <= self.hi
+  }
+
+  /// Returns the smallest interval that contains both intervals.
+  pub fn union(&self, other: &Self) -> Self {
+    Self {
+      lo: self.lo.min(other.lo),
+      hi: self.hi.max(other.hi),
+    }
+  }
+
+  /// Returns the intersection of both intervals, or `None` if the
+  /// intersection is empty.
+  pub fn intersect(&self, other: &Self) -> Option<Self> {
+    let (lo, hi) = (self.lo.max(other.lo), self.hi.min(other.hi));
+    (lo <= hi).then_some(Self { lo, hi })
+  }
+
+  /// Creates a new interval by the given 64-bit bounds. Returns a full
+  /// interval if the bounds exceed the range of `i32`, since the result
+  /// may wrap around.
+  fn from_i64(lo: i64, hi: i64) -> Self {
+    match (i32::try_from(lo), i32::try_from(hi)) {
+      (Ok(lo), Ok(hi)) => Self { lo, hi },
+      _ => Self::full(),
+    }
+  }
+
+  /// Creates a new interval that contains all the given 64-bit values.
+  fn from_i64s(values: [i64; 4]) -> Self {
+    let lo = values.iter().copied().min().unwrap();
+    let hi = values.iter().copied().max().unwrap();
+    Self::from_i64(lo, hi)
+  }
+}
+
+impl fmt::Display for Interval {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "[{}, {}]", self.lo, self.hi)
+  }
+}
+
+/// Integer range analysis.
+///
+/// Computes the possible range of all `i32` values in a function by
+/// propagating intervals through binary operations and basic block
+/// arguments. Ranges are refined along branch edges, for example, after
+/// `br (lt %x, 10), %then, %else`, `%x` is less than 10 in `%then`.
+/// Assumptions refine ranges in the same way for the rest of the basic
+/// block, and code after an assumption that never holds is unreachable.
+///
+/// Arithmetic operations wrap around on overflow, in this case the
+/// result is treated as unknown (a full interval). Loops are handled
+/// by widening the ranges that keep changing.
+pub struct IntRange {
+  ranges: HashMap<Value, Interval>,
+  bb_ranges: HashMap<BasicBlock, State>,
+}
+
+/// Ranges of values at a program point.
+type State = HashMap<Value, Interval>;
+
+/// Number of updates of a loop header before widening its ranges.
+const WIDEN_THRESHOLD: usize = 3;
+
+impl IntRange {
+  /// Computes ranges of all `i32` values in the given function.
+  pub fn compute(func: &FunctionData) -> Self {
+    let mut range = Self {
+      ranges: HashMap::new(),
+      bb_ranges: HashMap::new(),
+    };
+    for (v, data) in func.dfg().values() {
+      if let ValueKind::Integer(i) = data.kind() {
+        range.ranges.insert(*v, Interval::constant(i.value()));
+      }
+    }
+    let entry = match func.layout().entry_bb() {
+      Some(entry) => entry,
+      None => return range,
+    };
+    range.bb_ranges.insert(entry, State::new());
+    let headers = loop_headers(func, entry);
+    let mut updates = HashMap::new();
+    let mut worklist = VecDeque::from([entry]);
+    while let Some(bb) = worklist.pop_front() {
+      for (target, state) in range.visit_bb(func, bb) {
+        let count = updates.entry(target).or_insert(0);
+        *count += 1;
+        let widen = headers.contains(&target) && *count > WIDEN_THRESHOLD;
+        if range.merge(target, state, widen) && !worklist.contains(&target) {
+          worklist.push_back(target);
+        }
+      }
+    }
+    range
+  }
+
+  /// Returns the range of the given value in the whole function.
+  ///
+  /// Returns a full interval if the given value is not an `i32` value,
+  /// or it is in an unreachable basic block.
+  pub fn range_of(&self, value: Value) -> Interval {
+    self
+      .ranges
+      .get(&value)
+      .copied()
+      .unwrap_or_else(Interval::full)
+  }
+
+  /// Returns the range of the given value at the beginning of the given
+  /// basic block, which considers refinements along branch edges.
+  pub fn range_at(&self, bb: BasicBlock, value: Value) -> Interval {
+    self
+      .bb_ranges
+      .get(&bb)
+      .and_then(|s| s.get(&value))
+      .copied()
+      .unwrap_or_else(|| self.range_of(value))
+  }
+
+  /// Evaluates all instructions in the given basic block, returns the
+  /// states on all feasible outgoing edges.
+  fn visit_bb(&mut self, func: &FunctionData, bb: BasicBlock) -> Vec<(BasicBlock, State)> {
+    let mut state = self.bb_ranges[&bb].clone();
+    for p in func.dfg().bb(bb).params() {
+      if let Some(r) = state.get(p) {
+        self.update(*p, *r);
+      }
+    }
+    let node = func.layout().bbs().node(&bb).unwrap();
+    let mut edges = Vec::new();
+    for &inst in node.insts().keys() {
+      let data = func.dfg().value(inst);
+      match data.kind() {
+        ValueKind::Binary(bin) if data.ty() == &Type::get_i32() => {
+          let lhs = self.lookup(func, &state, bin.lhs());
+          let rhs = self.lookup(func, &state, bin.rhs());
+          let r = binary(bin.op(), lhs, rhs);
+          state.insert(inst, r);
+          self.update(inst, r);
+        }
+        ValueKind::Copy(copy) if data.ty() == &Type::get_i32() => {
+          let r = self.lookup(func, &state, copy.src());
+          state.insert(inst, r);
+          self.update(inst, r);
+        }
+        ValueKind::Assume(assume) if !self.refine_cond(func, &mut state, assume.cond(), true) => {
+          return edges;
+        }
+        _ if data.ty() == &Type::get_i32() => {
+          state.insert(inst, Interval::full());
+          self.update(inst, Interval::full());
+        }
+        ValueKind::Branch(br) => {
+          for (taken, target, args) in [
+            (true, br.true_bb(), br.true_args()),
+            (false, br.false_bb(), br.false_args()),
+          ] {
+            let mut out = state.clone();
+            if self.refine_cond(func, &mut out, br.cond(), taken) {
+              edges.push(self.edge_state(func, out, target, args));
+            }
+          }
+        }
+        ValueKind::Jump(jump) => {
+          edges.push(self.edge_state(func, state.clone(), jump.target(), jump.args()));
+        }
+        ValueKind::Switch(switch) => {
+          let out = state.clone();
+          edges.push(self.edge_state(func, out, switch.default_bb(), switch.default_args()));
+          for (value, target, args) in switch.cases() {
+            let mut out = state.clone();
+            if self.refine(func, &mut out, switch.cond(), Interval::constant(*value)) {
+              edges.push(self.edge_state(func, out, *target, args));
+            }
+          }
+        }
+        _ => {}
+      }
+    }
+    edges
+  }
+
+  /// Returns the target basic block and the state on the given edge.
+  fn edge_state(
+    &self,
+    func: &FunctionData,
+    mut state: State,
+    target: BasicBlock,
+    args: &[Value],
+  ) -> (BasicBlock, State) {
+    for (param, arg) in func.dfg().bb(target).params().iter().zip(args) {
+      if func.dfg().value(*param).ty() == &Type::get_i32() {
+        let r = self.lookup(func, &state, *arg);
+        state.insert(*param, r);
+      }
+    }
+    (target, state)
+  }
+
+  /// Merges the given state into the state of the given basic block.
+  /// Returns `true` if the state of the basic block changed.
+  fn merge(&mut self, bb: BasicBlock, state: State, widen: bool) -> bool {
+    let old = match self.bb_ranges.get(&bb) {
+      Some(old) => old,
+      None => {
+        self.bb_ranges.insert(bb, state);
+        return true;
+      }
+    };
+    // only keep values that are available on all incoming edges
+    let new: State = old
+      .iter()
+      .filter_map(|(v, o)| {
+        let r = o.union(state.get(v)?);
+        if widen {
+          let lo = if r.lo < o.lo { i32::MIN } else { r.lo };
+          let hi = if r.hi > o.hi { i32::MAX } else { r.hi };
+          Some((*v, Interval::new(lo, hi)))
+        } else {
+          Some((*v, r))
+        }
+      })
+      .collect();
+    let changed = &new != old;
+    self.bb_ranges.insert(bb, new);
+    changed
+  }
+
+  /// Updates the range of the given value in the whole function.
+  fn update(&mut self, value: Value, range: Interval) {
+    self
+      .ranges
+      .entry(value)
+      .and_modify(|r| *r = r.union(&range))
+      .or_insert(range);
+  }
+
+  /// Returns the range of the given value in the given state.
+  fn lookup(&self, func: &FunctionData, state: &State, value: Value) -> Interval {
+    match func.dfg().values().get(&value).map(|d| d.kind()) {
+      Some(ValueKind::Integer(i)) => Interval::constant(i.value()),
+      _ => state.get(&value).copied().unwrap_or_else(Interval::full),
+    }
+  }
+
+  /// Refines the range of the given value in the given state.
+  /// Returns `false` if the refined range is empty.
+  fn refine(&self, func: &FunctionData, state: &mut State, value: Value, range: Interval) -> bool {
+    match self.lookup(func, state, value).intersect(&range) {
+      Some(r) => {
+        if !func
+          .dfg()
+          .values()
+          .get(&value)
+          .is_some_and(|d| d.kind().is_const())
+        {
+          state.insert(value, r);
+        }
+        true
+      }
+      None => false,
+    }
+  }
+
+  /// Refines ranges in the given state by assuming the given condition
+  /// is `taken` (non-zero) or not. Returns `false` if the assumption
+  /// is impossible.
+  fn refine_cond(&self, func: &FunctionData, state: &mut State, cond: Value, taken: bool) -> bool {
+    // refine the condition itself
+    let c = self.lookup(func, state, cond);
+    let c = if taken {
+      if c.lo == 0 && c.hi == 0 {
+        return false;
+      } else if c.lo == 0 {
+        Interval::new(1, c.hi)
+      } else if c.hi == 0 {
+        Interval::new(c.lo, -1)
+      } else {
+        c
+      }
+    } else {
+      Interval::constant(0)
+    };
+    if !self.refine(func, state, cond, c) {
+      return false;
+    }
+    // refine operands of the comparison
+    let bin = match func.dfg().values().get(&cond).map(|d| d.kind()) {
+      Some(ValueKind::Binary(bin)) => bin,
+      _ => return true,
+    };
+    let op = match (bin.op(), taken) {
+      (op, true) => op,
+      (BinaryOp::Lt, false) => BinaryOp::Ge,
+      (BinaryOp::Le, false) => BinaryOp::Gt,
+      (BinaryOp::Gt, false) => BinaryOp::Le,
+      (BinaryOp::Ge, false) => BinaryOp::Lt,
+      (BinaryOp::Eq, false) => BinaryOp::NotEq,
+      (BinaryOp::NotEq, false) => BinaryOp::Eq,
+      _ => return true,
+    };
+    self.refine_cmp(func, state, op, bin.lhs(), bin.rhs())
+  }
+
+  /// Refines ranges of operands in the given state by assuming the
+  /// comparison `lhs op rhs` is true. Returns `false` if the assumption
+  /// is impossible.
+  fn refine_cmp(
+    &self,
+    func: &FunctionData,
+    state: &mut State,
+    op: BinaryOp,
+    lhs: Value,
+    rhs: Value,
+  ) -> bool {
+    let a = self.lookup(func, state, lhs);
+    let b = self.lookup(func, state, rhs);
+    match op {
+      BinaryOp::Lt => {
+        b.hi != i32::MIN
+          && a.lo != i32::MAX
+          && self.refine(func, state, lhs, Interval::new(i32::MIN, b.hi - 1))
+          && self.refine(func, state, rhs, Interval::new(a.lo + 1, i32::MAX))
+      }
+      BinaryOp::Le => {
+        self.refine(func, state, lhs, Interval::new(i32::MIN, b.hi))
+          && self.refine(func, state, rhs, Interval::new(a.lo, i32::MAX))
+      }
+      BinaryOp::Gt => self.refine_cmp(func, state, BinaryOp::Lt, rhs, lhs),
+      BinaryOp::Ge => self.refine_cmp(func, state, BinaryOp::Le, rhs, lhs),
+      BinaryOp::Eq => self.refine(func, state, lhs, b) && self.refine(func, state, rhs, a),
+      BinaryOp::NotEq => match (a.as_constant(), b.as_constant()) {
+        (Some(x), Some(y)) => x != y,
+        (_, Some(k)) => self.refine_not_eq(func, state, lhs, a, k),
+        (Some(k), _) => self.refine_not_eq(func, state, rhs, b, k),
+        _ => true,
+      },
+      _ => true,
+    }
+  }
+
+  /// Refines the range of the given value by excluding the given
+  /// constant from one of the bounds.
+  fn refine_not_eq(
+    &self,
+    func: &FunctionData,
+    state: &mut State,
+    value: Value,
+    range: Interval,
+    k: i32,
+  ) -> bool {
+    if range.lo == k {
+      self.refine(func, state, value, Interval::new(k + 1, range.hi))
+    } else if range.hi == k {
+      self.refine(func, state, value, Interval::new(range.lo, k - 1))
+    } else {
+      true
+    }
+  }
+}
+
+/// Returns all targets of back edges (loop headers) in the given function.
+fn loop_headers(func: &FunctionData, entry: BasicBlock) -> HashSet<BasicBlock> {
+  let succs = |bb: BasicBlock| -> Vec<BasicBlock> {
+    func
+      .terminator(bb)
+      .map_or_else(Vec::new, |t| func.dfg().value(t).kind().bb_uses().collect())
+  };
+  let mut headers = HashSet::new();
+  let mut visited = HashSet::from([entry]);
+  let mut on_stack = HashSet::from([entry]);
+  let mut stack = vec![(entry, succs(entry).into_iter())];
+  while let Some((bb, iter)) = stack.last_mut() {
+    match iter.next() {
+      Some(succ) if on_stack.contains(&succ) => {
+        headers.insert(succ);
+      }
+      Some(succ) if visited.insert(succ) => {
+        on_stack.insert(succ);
+        stack.push((succ, succs(succ).into_iter()));
+      }
+      Some(_) => {}
+      None => {
+        on_stack.remove(bb);
+        stack.pop();
+      }
+    }
+  }
+  headers
+}
+
+/// Returns the range of the result of the given binary operation.
+fn binary(op: BinaryOp, a: Interval, b: Interval) -> Interval {
+  let (al, ah, bl, bh) = (a.lo as i64, a.hi as i64, b.lo as i64, b.hi as i64);
+  let cmp = |always: bool, never: bool| {
+    if always {
+      Interval::constant(1)
+    } else if never {
+      Interval::constant(0)
+    } else {
+      Interval::new(0, 1)
+    }
+  };
+  match op {
+    BinaryOp::NotEq => cmp(a.intersect(&b).is_none(), a == b && a.lo == a.hi),
+    BinaryOp::Eq => cmp(a == b && a.lo == a.hi, a.intersect(&b).is_none()),
+    BinaryOp::Gt => cmp(al > bh, ah <= bl),
+    BinaryOp::Lt => cmp(ah < bl, al >= bh),
+    BinaryOp::Ge => cmp(al >= bh, ah < bl),
+    BinaryOp::Le => cmp(ah <= bl, al > bh),
+    BinaryOp::Add => Interval::from_i64(al + bl, ah + bh),
+    BinaryOp::Sub => Interval::from_i64(al - bh, ah - bl),
+    BinaryOp::Mul => Interval::from_i64s([al * bl, al * bh, ah * bl, ah * bh]),
+    BinaryOp::Div if !b.contains(0) => Interval::from_i64s([al / bl, al / bh, ah / bl, ah / bh]),
+    BinaryOp::Mod if !b.contains(0) => {
+      // the result has the same sign as the dividend,
+      // and its absolute value is less than the divisor's
+      let m = bl.abs().max(bh.abs()) - 1;
+      Interval::from_i64(al.max(-m).min(0), ah.min(m).max(0))
+    }
+    BinaryOp::And if al >= 0 || bl >= 0 => {
+      let hi = match (al >= 0, bl >= 0) {
+        (true, true) => ah.min(bh),
+        (true, false) => ah,
+        _ => bh,
+      };
+      Interval::from_i64(0, hi)
+    }
+    BinaryOp::Or | BinaryOp::Xor if al >= 0 && bl >= 0 => {
+      // all bits of the result are below the highest bit of operands
+      let bits = 32 - (ah.max(bh) as u32).leading_zeros();
+      Interval::from_i64(0, (1 << bits) - 1)
+    }
+    BinaryOp::Shl if bl >= 0 && bh < 32 => {
+      Interval::from_i64s([al << bl, al << bh, ah << bl, ah << bh])
+    }
+    BinaryOp::Shr | BinaryOp::Sar if bl >= 0 && bh < 32 && (al >= 0 || op == BinaryOp::Sar) => {
+      Interval::from_i64s([al >> bl, al >> bh, ah >> bl, ah >> bh])
+    }
+    _ => Interval::full(),
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::front::Driver;
+  use crate::ir::Program;
+
+  fn compute(src: &str) -> (Program, IntRange) {
+    let driver: Driver<_> = src.into();
+    let program = driver.generate_program().unwrap();
+    let range = IntRange::compute(program.func(program.func_layout()[0]));
+    (program, range)
+  }
+
+  fn value(program: &Program, name: &str) -> Value {
+    let func = program.func(program.func_layout()[0]);
+    let (value, _) = func
+      .dfg()
+      .values()
+      .iter()
+      .find(|(_, d)| d.name().as_deref() == Some(name))
+      .unwrap();
+    *value
+  }
+
+  fn bb(program: &Program, name: &str) -> BasicBlock {
+    let func = program.func(program.func_layout()[0]);
+    let (bb, _) = func
+      .dfg()
+      .bbs()
+      .iter()
+      .find(|(_, d)| d.name().as_deref() == Some(name))
+      .unwrap();
+    *bb
+  }
+
+  #[test]
+  fn interval() {
+    let a = Interval::new(-3, 5);
+    let b = Interval::new(4, 10);
+    assert_eq!(a.union(&b), Interval::new(-3, 10));
+    assert_eq!(a.intersect(&b), Some(Interval::new(4, 5)));
+    assert_eq!(a.intersect(&Interval::constant(6)), None);
+    assert!(Interval::full().is_full());
+    let (add, mul) = (BinaryOp::Add, BinaryOp::Mul);
+    assert_eq!(binary(add, a, b), Interval::new(1, 15));
+    assert_eq!(binary(mul, a, b), Interval::new(-30, 50));
+    assert_eq!(binary(BinaryOp::Sub, a, b), Interval::new(-13, 1));
+    assert_eq!(
+      binary(BinaryOp::Div, b, Interval::new(-2, -1)),
+      Interval::new(-10, -2)
+    );
+    assert_eq!(
+      binary(BinaryOp::Mod, a, Interval::constant(4)),
+      Interval::new(-3, 3)
+    );
+    assert_eq!(
+      binary(BinaryOp::Lt, a, Interval::constant(6)),
+      Interval::constant(1)
+    );
+    assert_eq!(
+      binary(BinaryOp::Or, b, Interval::constant(1)),
+      Interval::new(0, 15)
+    );
+    // overflow
+    let max = Interval::constant(i32::MAX);
+    assert!(binary(add, max, Interval::constant(1)).is_full());
+    assert!(binary(mul, max, Interval::constant(2)).is_full());
+    assert!(binary(BinaryOp::Div, a, a).is_full());
+  }
+
+  #[test]
+  fn loop_with_refinement() {
+    let (program, range) = compute(
+      r#"fun @f(@n: i32): i32 {
+%entry:
+  jump %loop(0)
+
+%loop(%i: i32):
+  %cond = lt %i, 10
+  br %cond, %body, %end
+
+%body:
+  %next = add %i, 1
+  %rem = mod @n, 4
+  jump %loop(%next)
+
+%end:
+  ret %i
+}
+"#,
+    );
+    let i = value(&program, "%i");
+    assert_eq!(range.range_of(i), Interval::new(0, i32::MAX));
+    assert_eq!(
+      range.range_at(bb(&program, "%body"), i),
+      Interval::new(0, 9)
+    );
+    assert_eq!(
+      range.range_at(bb(&program, "%end"), i),
+      Interval::new(10, i32::MAX)
+    );
+    let next = value(&program, "%next");
+    assert_eq!(range.range_of(next), Interval::new(1, 10));
+    let rem = value(&program, "%rem");
+    assert_eq!(range.range_of(rem), Interval::new(-3, 3));
+    let cond = value(&program, "%cond");
+    assert_eq!(range.range_of(cond), Interval::new(0, 1));
+  }
+
+  #[test]
+  fn infeasible_edge() {
+    let (program, range) = compute(
+      r#"fun @f(@n: i32): i32 {
+%entry:
+  %x = mod @n, 8
+  %cond = gt %x, 10
+  br %cond, %then, %end(%x)
+
+%then:
+  jump %end(100)
+
+%end(%y: i32):
+  ret %y
+}
+"#,
+    );
+    let y = value(&program, "%y");
+    assert_eq!(range.range_of(y), Interval::new(-7, 7));
+    let cond = value(&program, "%cond");
+    assert_eq!(range.range_of(cond), Interval::constant(0));
+  }
+
+  #[test]
+  fn assume() {
+    let (program, range) = compute(
+      r#"fun @f(@n: i32): i32 {
+%entry:
+  %lt = lt @n, 10
+  assume %lt
+  %ge = ge @n, 0
+  assume %ge
+  %x = add @n, 1
+  %gt = gt %x, 5
+  br %gt, %then, %end(%x)
+
+%then:
+  %big = gt %x, 20
+  assume %big
+  jump %end(100)
+
+%end(%y: i32):
+  ret %y
+}
+"#,
+    );
+    assert_eq!(range.range_of(value(&program, "%x")), Interval::new(1, 10));
+    // the jump after an assumption that never holds is unreachable
+    assert_eq!(range.range_of(value(&program, "%y")), Interval::new(1, 5));
+  }
+}
@@ -0,0 +1,241 @@
+//! Reaching definitions analysis.
+
+use crate::ir::analysis::dataflow::{Analysis, DataFlow, Direction};
+use crate::ir::dfg::DataFlowGraph;
+use crate::ir::entities::{FunctionData, Value, ValueKind};
+use std::collections::{HashMap, HashSet};
+
+/// Reaching definitions of local allocations in a function.
+///
+/// A definition of an allocation is an instruction that may write to
+/// the allocated memory, including:
+///
+/// * The `alloc` instruction itself, which defines the memory as
+///   uninitialized.
+/// * `store` instructions to the allocation, or to pointers derived
+///   from it by `getptr` and `getelemptr`.
+/// * If the address of the allocation escapes (for example, being
+///   passed to a function, stored to memory or passed to a basic
+///   block), `store` instructions to unknown pointers and `call`
+///   instructions.
+///
+/// Only stores directly to the allocation kill other definitions, so
+/// stores through derived pointers, which may only write a part of
+/// the memory, never kill definitions.
+pub struct ReachingDefs {
+  defs: DefKinds,
+  /// Definitions reaching the point right before each instruction.
+  before: HashMap<Value, HashSet<Value>>,
+}
+
+impl ReachingDefs {
+  /// Computes the reaching definitions of the given function.
+  pub fn compute(func: &FunctionData) -> Self {
+    let defs = DefKinds::new(func);
+    let flow = DataFlow::solve(func, &defs);
+    let mut before = HashMap::new();
+    for &bb in func.layout().bbs().keys() {
+      let mut fact = flow.block_in(bb).clone();
+      for inst in func.layout().block_insts(bb) {
+        before.insert(inst, fact.clone());
+        defs.transfer(func, inst, &mut fact);
+      }
+    }
+    Self { defs, before }
+  }
+
+  /// Returns definitions of the given allocation which reach the point
+  /// right before the given instruction.
+  ///
+  /// Returns an empty set if the instruction is not in the layout, or
+  /// the value is not a local allocation.
+  pub fn defs_reaching(&self, point: Value, alloc: Value) -> HashSet<Value> {
+    (self.before.get(&point).into_iter())
+      .flatten()
+      .copied()
+      .filter(|d| self.defs.may_define(*d, alloc))
+      .collect()
+  }
+
+  /// Checks if the address of the given allocation escapes.
+  pub fn is_escaped(&self, alloc: Value) -> bool {
+    self.defs.escaped.contains(&alloc)
+  }
+}
+
+/// Kind of a definition.
+#[derive(Clone, Copy)]
+enum DefKind {
+  /// Allocation.
+  Alloc,
+  /// Store directly to the given allocation.
+  StoreTo(Value),
+  /// Store to a pointer derived from the given allocation.
+  StoreToPart(Value),
+  /// Store to an unknown pointer, or function call.
+  Unknown,
+}
+
+/// Definitions of allocations, and the forward analysis of them.
+struct DefKinds {
+  kinds: HashMap<Value, DefKind>,
+  escaped: HashSet<Value>,
+}
+
+impl DefKinds {
+  fn new(func: &FunctionData) -> Self {
+    let dfg = func.dfg();
+    let mut kinds = HashMap::new();
+    let mut escaped = HashSet::new();
+    for (_, node) in func.layout().bbs() {
+      for &inst in node.insts().keys() {
+        let kind = match dfg.value(inst).kind() {
+          ValueKind::Alloc(_) => {
+            if Self::escapes(dfg, inst) {
+              escaped.insert(inst);
+            }
+            DefKind::Alloc
+          }
+          ValueKind::Store(s) => match Self::root(dfg, s.dest()) {
+            Some(a) if a == s.dest() => DefKind::StoreTo(a),
+            Some(a) => DefKind::StoreToPart(a),
+            None => DefKind::Unknown,
+          },
+          ValueKind::Call(_) => DefKind::Unknown,
+          _ => continue,
+        };
+        kinds.insert(inst, kind);
+      }
+    }
+    Self { kinds, escaped }
+  }
+
+  /// Returns the allocation from which the given pointer is derived.
+  fn root(dfg: &DataFlowGraph, mut ptr: Value) -> Option<Value> {
+    loop {
+      if ptr.is_global() {
+        return None;
+      }
+      ptr = match dfg.value(ptr).kind() {
+        ValueKind::Alloc(_) => return Some(ptr),
+        ValueKind::GetPtr(gp) => gp.src(),
+        ValueKind::GetElemPtr(gep) => gep.src(),
+        _ => return None,
+      };
+    }
+  }
+
+  /// Checks if the address of the given allocation, or any pointer
+  /// derived from it, is used other than loading and storing.
+  fn escapes(dfg: &DataFlowGraph, alloc: Value) -> bool {
+    let mut ptrs = vec![alloc];
+    while let Some(ptr) = ptrs.pop() {
+      for &user in dfg.value(ptr).used_by() {
+        match dfg.value(user).kind() {
+          ValueKind::Load(_) => {}
+          ValueKind::Store(s) if s.value() != ptr => {}
+          ValueKind::GetPtr(_) | ValueKind::GetElemPtr(_) => ptrs.push(user),
+          _ => return true,
+        }
+      }
+    }
+    false
+  }
+
+  /// Checks if the given definition may define the given allocation.
+  fn may_define(&self, def: Value, alloc: Value) -> bool {
+    match self.kinds.get(&def) {
+      Some(DefKind::Alloc) => def == alloc,
+      Some(DefKind::StoreTo(a) | DefKind::StoreToPart(a)) => *a == alloc,
+      Some(DefKind::Unknown) => self.escaped.contains(&alloc),
+      None => false,
+    }
+  }
+}
+
+impl Analysis for DefKinds {
+  type Fact = HashSet<Value>;
+  const DIRECTION: Direction = Direction::Forward;
+
+  fn transfer(&self, _: &FunctionData, inst: Value, fact: &mut Self::Fact) {
+    let killed = match self.kinds.get(&inst) {
+      Some(DefKind::Alloc) => inst,
+      Some(DefKind::StoreTo(a)) => *a,
+      Some(_) => {
+        fact.insert(inst);
+        return;
+      }
+      None => return,
+    };
+    fact.retain(|d| match self.kinds[d] {
+      DefKind::Alloc => *d != killed,
+      DefKind::StoreTo(a) | DefKind::StoreToPart(a) => a != killed,
+      DefKind::Unknown => true,
+    });
+    fact.insert(inst);
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::front::Driver;
+
+  #[test]
+  fn reaching_defs() {
+    let driver: Driver<_> = r#"decl @use(*i32)
+
+fun @f(@c: i32): i32 {
+%entry:
+  %x = alloc i32
+  %a = alloc [i32, 2]
+  %e = alloc i32
+  store 0, %x
+  store {1, 2}, %a
+  %0 = getelemptr %a, 1
+  store 3, %0
+  jump %loop
+
+%loop:
+  %1 = load %x
+  br @c, %body, %exit
+
+%body:
+  store 4, %x
+  store 5, %e
+  call @use(%e)
+  jump %loop
+
+%exit:
+  %2 = load %0
+  %3 = load %e
+  store 6, %x
+  %4 = load %x
+  ret %4
+}
+"#
+    .into();
+    let program = driver.generate_program().unwrap();
+    let func = program.func(program.func_layout()[1]);
+    let insts: Vec<_> = (func.layout().bbs().nodes())
+      .flat_map(|n| n.insts().keys().copied())
+      .collect();
+    let (x, a, e) = (insts[0], insts[1], insts[2]);
+    let (st0, st12, st3) = (insts[3], insts[4], insts[6]);
+    let (ld_x, st4, st5, call) = (insts[8], insts[10], insts[11], insts[12]);
+    let (ld_part, ld_e, st6, ld_x2) = (insts[14], insts[15], insts[16], insts[17]);
+    let defs = ReachingDefs::compute(func);
+    // uninitialized memory
+    assert_eq!(defs.defs_reaching(st0, x), HashSet::from([x]));
+    // back edge
+    assert_eq!(defs.defs_reaching(ld_x, x), HashSet::from([st0, st4]));
+    // stores to a part do not kill the whole allocation
+    assert_eq!(defs.defs_reaching(ld_part, a), HashSet::from([st12, st3]));
+    // calls may define escaped allocations
+    assert!(defs.is_escaped(e) && !defs.is_escaped(x) && !defs.is_escaped(a));
+    assert_eq!(defs.defs_reaching(ld_e, e), HashSet::from([e, st5, call]));
+    assert_eq!(defs.defs_reaching(st6, x), HashSet::from([st0, st4]));
+    assert_eq!(defs.defs_reaching(ld_x2, x), HashSet::from([st6]));
+    assert!(defs.defs_reaching(ld_x2, ld_x).is_empty());
+  }
+}
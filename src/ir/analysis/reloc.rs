@@ -0,0 +1,96 @@
+//! Relocation evaluation of global constant pointers.
+
+use crate::ir::entities::{Program, Value, ValueKind};
+use crate::ir::types::TypeKind;
+
+/// A relocation entry, which refers to the address of a global
+/// allocation plus a byte offset.
+///
+/// Global initializers may contain the address of a global allocation,
+/// or pointer calculations (`getptr`/`getelemptr`) on it. These values
+/// can not be evaluated until link time, but can be folded to a symbol
+/// and an offset, for example, `.word sym+8` in assembly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Relocation {
+  symbol: Value,
+  offset: i64,
+}
+
+impl Relocation {
+  /// Evaluates the given global value to a relocation entry.
+  ///
+  /// Returns `None` if the given value is not a global allocation,
+  /// or a pointer calculation on a global constant pointer with an
+  /// integer constant index, or the size of the pointee type is unknown
+  /// (it contains undefined named types).
+  ///
+  /// # Panics
+  ///
+  /// Panics if the given value does not exist.
+  pub fn eval(program: &Program, value: Value) -> Option<Self> {
+    let data = program.borrow_value(value);
+    let (src, index) = match data.kind() {
+      ValueKind::GlobalAlloc(_) => {
+        return Some(Self {
+          symbol: value,
+          offset: 0,
+        })
+      }
+      ValueKind::GetPtr(gp) => (gp.src(), gp.index()),
+      ValueKind::GetElemPtr(gep) => (gep.src(), gep.index()),
+      _ => return None,
+    };
+    let index = match program.borrow_value(index).kind() {
+      ValueKind::Integer(i) => i.value() as i64,
+      _ => return None,
+    };
+    let base_size = match data.ty().kind() {
+      TypeKind::Pointer(base) => program.named_types().size_of(base)? as i64,
+      _ => return None,
+    };
+    Self::eval(program, src).map(|r| Self {
+      symbol: r.symbol,
+      offset: r.offset + index * base_size,
+    })
+  }
+
+  /// Returns the global allocation that the relocation refers to.
+  pub fn symbol(&self) -> Value {
+    self.symbol
+  }
+
+  /// Returns the offset in bytes.
+  pub fn offset(&self) -> i64 {
+    self.offset
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::front::Driver;
+
+  #[test]
+  fn eval_relocations() {
+    let driver: Driver<_> = r#"global @arr = alloc [[i32, 3], 2], zeroinit
+global @p = alloc *i32, getelemptr getelemptr @arr, 1, 2
+global @q = alloc *[[i32, 3], 2], getptr @arr, 1
+global @t = alloc [*[[i32, 3], 2], 2], {@arr, @arr}
+"#
+    .into();
+    let program = driver.generate_program().unwrap();
+    let globals = program.inst_layout();
+    let init = |g: Value| match program.borrow_value(g).kind() {
+      ValueKind::GlobalAlloc(ga) => ga.init(),
+      _ => unreachable!(),
+    };
+    let arr = globals[0];
+    let reloc = Relocation::eval(&program, init(globals[1])).unwrap();
+    assert_eq!(reloc.symbol(), arr);
+    assert_eq!(reloc.offset(), 20);
+    let reloc = Relocation::eval(&program, init(globals[2])).unwrap();
+    assert_eq!((reloc.symbol(), reloc.offset()), (arr, 24));
+    assert_eq!(Relocation::eval(&program, arr).unwrap().offset(), 0);
+    assert_eq!(Relocation::eval(&program, init(globals[3])), None);
+  }
+}
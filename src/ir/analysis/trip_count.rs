@@ -0,0 +1,359 @@
+//! Loop trip count estimation.
+
+use crate::ir::analysis::{DominatorTree, IntRange, Interval};
+use crate::ir::entities::{BasicBlock, FunctionData, Value, ValueKind};
+use crate::ir::values::BinaryOp;
+use std::collections::HashSet;
+
+/// Trip count of a loop, returned by [`trip_count`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TripCount {
+  /// The loop body runs exactly the given number of times.
+  Exact(u64),
+  /// The loop body runs at most the given number of times.
+  Bounded(u64),
+  /// The trip count is unknown.
+  Unknown,
+}
+
+/// Returns the trip count of the loop with the given header, that is,
+/// the number of times the header branches into the loop body.
+///
+/// The loop is the natural loop of the header, formed by back edges from
+/// basic blocks dominated by the header. Only counted loops like the
+/// following are recognized:
+///
+/// ```koopa
+/// %header(%i: i32):
+///   %cond = lt %i, 10
+///   br %cond, %body, %exit
+///
+/// %body:
+///   %next = add %i, 1
+///   jump %header(%next)
+/// ```
+///
+/// That is:
+///
+/// * The header ends with a `br` on a comparison (`lt`, `le`, `gt`, `ge`,
+///   `eq` or `ne`) between a parameter of the header and a constant
+///   bound, in either order. One target of the `br` is in the loop, and
+///   the other one is outside.
+/// * All back edges pass the parameter plus (`add`) or minus (`sub`) the
+///   same non-zero constant step.
+/// * Edges entering the loop pass initial values of the parameter, whose
+///   range is given by [`IntRange`]. Constant bounds and steps are also
+///   recognized by range analysis.
+///
+/// Returns [`TripCount::Bounded`] if the initial value is not a constant,
+/// or the loop has other exits, like `ret`s or branches out of the loop
+/// body. Returns [`TripCount::Unknown`] if the loop is not recognized, or
+/// the parameter may wrap around before the loop exits.
+pub fn trip_count(func: &FunctionData, header: BasicBlock) -> TripCount {
+  count(func, header).unwrap_or(TripCount::Unknown)
+}
+
+/// Returns the trip count of the loop with the given header, or `None`
+/// if it is unknown.
+fn count(func: &FunctionData, header: BasicBlock) -> Option<TripCount> {
+  let dom = DominatorTree::compute(func);
+  if !dom.is_reachable(header) {
+    return None;
+  }
+  let body = loop_body(func, &dom, header)?;
+  let br = match func.dfg().value(func.terminator(header)?).kind() {
+    ValueKind::Branch(br) => br,
+    _ => return None,
+  };
+  // get the condition for staying in the loop
+  let negate = match (body.contains(&br.true_bb()), body.contains(&br.false_bb())) {
+    (true, false) => false,
+    (false, true) => true,
+    _ => return None,
+  };
+  let (param, op, bound) = compare(func, header, br.cond())?;
+  let op = if negate { negate_op(op) } else { op };
+  let index = (func.dfg().bb(header).params().iter()).position(|p| *p == param)?;
+  let range = IntRange::compute(func);
+  let bound = range.range_of(bound).as_constant()?;
+  // get initial values and the step
+  let mut init: Option<Interval> = None;
+  let mut step = None;
+  for edge in func.cfg_edges().filter(|e| e.to() == header) {
+    if !dom.is_reachable(edge.from()) {
+      continue;
+    }
+    let arg = edge.args()[index];
+    if body.contains(&edge.from()) {
+      let s = step_of(func, &range, param, arg)?;
+      if step.replace(s).is_some_and(|old| old != s) {
+        return None;
+      }
+    } else {
+      let r = range.range_of(arg);
+      init = Some(init.map_or(r, |i| i.union(&r)));
+    }
+  }
+  let (init, step) = (init?, step?);
+  // the trip count decreases as the initial value moves towards the bound
+  let start = match op {
+    BinaryOp::Lt | BinaryOp::Le => init.lo(),
+    BinaryOp::Gt | BinaryOp::Ge => init.hi(),
+    _ => init.as_constant()?,
+  };
+  let n = iterations(op, start.into(), bound.into(), step)?;
+  let other_exits = body.iter().any(|&bb| {
+    bb != header
+      && func.terminator(bb).is_some_and(|t| {
+        let kind = func.dfg().value(t).kind();
+        matches!(kind, ValueKind::Return(_) | ValueKind::Unreachable(_))
+          || kind.bb_uses().any(|s| !body.contains(&s))
+      })
+  });
+  Some(if init.as_constant().is_some() && !other_exits {
+    TripCount::Exact(n)
+  } else {
+    TripCount::Bounded(n)
+  })
+}
+
+/// Returns basic blocks of the natural loop with the given header, or
+/// `None` if the header has no back edges.
+fn loop_body(
+  func: &FunctionData,
+  dom: &DominatorTree,
+  header: BasicBlock,
+) -> Option<HashSet<BasicBlock>> {
+  let mut stack: Vec<_> = (func.preds(header).iter())
+    .copied()
+    .filter(|p| dom.is_reachable(*p) && dom.dominates(header, *p))
+    .collect();
+  if stack.is_empty() {
+    return None;
+  }
+  let mut body = HashSet::from([header]);
+  while let Some(bb) = stack.pop() {
+    if body.insert(bb) {
+      stack.extend(func.preds(bb).iter().filter(|p| dom.is_reachable(**p)));
+    }
+  }
+  Some(body)
+}
+
+/// Returns the parameter of the given header, the comparison operator
+/// and the bound if the given condition compares them. The operator is
+/// normalized as if the parameter is the left-hand side.
+fn compare(
+  func: &FunctionData,
+  header: BasicBlock,
+  cond: Value,
+) -> Option<(Value, BinaryOp, Value)> {
+  let bin = match func.dfg().values().get(&cond)?.kind() {
+    ValueKind::Binary(bin) => bin,
+    _ => return None,
+  };
+  let swapped = swap_op(bin.op())?;
+  let params = func.dfg().bb(header).params();
+  if params.contains(&bin.lhs()) {
+    Some((bin.lhs(), bin.op(), bin.rhs()))
+  } else if params.contains(&bin.rhs()) {
+    Some((bin.rhs(), swapped, bin.lhs()))
+  } else {
+    None
+  }
+}
+
+/// Returns the comparison operator with swapped operands, or `None` if
+/// the given operator is not a comparison.
+fn swap_op(op: BinaryOp) -> Option<BinaryOp> {
+  Some(match op {
+    BinaryOp::Lt => BinaryOp::Gt,
+    BinaryOp::Gt => BinaryOp::Lt,
+    BinaryOp::Le => BinaryOp::Ge,
+    BinaryOp::Ge => BinaryOp::Le,
+    BinaryOp::Eq | BinaryOp::NotEq => op,
+    _ => return None,
+  })
+}
+
+/// Returns the negation of the given comparison operator.
+fn negate_op(op: BinaryOp) -> BinaryOp {
+  match op {
+    BinaryOp::Lt => BinaryOp::Ge,
+    BinaryOp::Ge => BinaryOp::Lt,
+    BinaryOp::Gt => BinaryOp::Le,
+    BinaryOp::Le => BinaryOp::Gt,
+    BinaryOp::Eq => BinaryOp::NotEq,
+    BinaryOp::NotEq => BinaryOp::Eq,
+    _ => panic!("expected a comparison operator"),
+  }
+}
+
+/// Returns the step if the given value is the given parameter plus or
+/// minus a non-zero constant.
+fn step_of(func: &FunctionData, range: &IntRange, param: Value, value: Value) -> Option<i64> {
+  let bin = match func.dfg().values().get(&value)?.kind() {
+    ValueKind::Binary(bin) => bin,
+    _ => return None,
+  };
+  let constant = |v| range.range_of(v).as_constant().map(i64::from);
+  let step = match bin.op() {
+    BinaryOp::Add if bin.lhs() == param => constant(bin.rhs())?,
+    BinaryOp::Add if bin.rhs() == param => constant(bin.lhs())?,
+    BinaryOp::Sub if bin.lhs() == param => -constant(bin.rhs())?,
+    _ => return None,
+  };
+  (step != 0).then_some(step)
+}
+
+/// Returns the number of iterations of `i = init; while (i op bound)
+/// i += step;`, or `None` if the loop never exits, or `i` may wrap
+/// around before the loop exits.
+fn iterations(op: BinaryOp, init: i64, bound: i64, step: i64) -> Option<u64> {
+  let n = match op {
+    BinaryOp::Lt | BinaryOp::Le => {
+      let bound = if op == BinaryOp::Le { bound + 1 } else { bound };
+      if init >= bound {
+        return Some(0);
+      }
+      // the last value of `i` is in `[bound, bound + step)`
+      if step < 0 || bound - 1 + step > i32::MAX as i64 {
+        return None;
+      }
+      (bound - init + step - 1) / step
+    }
+    BinaryOp::Gt | BinaryOp::Ge => {
+      let bound = if op == BinaryOp::Ge { bound - 1 } else { bound };
+      if init <= bound {
+        return Some(0);
+      }
+      // the last value of `i` is in `(bound + step, bound]`
+      if step > 0 || bound + 1 + step < i32::MIN as i64 {
+        return None;
+      }
+      (init - bound - step - 1) / -step
+    }
+    BinaryOp::NotEq => {
+      let dist = bound - init;
+      if dist % step != 0 || dist / step < 0 {
+        return None;
+      }
+      dist / step
+    }
+    BinaryOp::Eq => (init == bound) as i64,
+    _ => panic!("expected a comparison operator"),
+  };
+  Some(n as u64)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::front::Driver;
+
+  #[test]
+  fn counted_loops() {
+    let driver: Driver<_> = r#"fun @up(): i32 {
+%entry:
+  jump %header(0)
+
+%header(%i: i32):
+  %c = lt %i, 10
+  br %c, %body, %exit
+
+%body:
+  %next = add %i, 1
+  jump %header(%next)
+
+%exit:
+  ret %i
+}
+
+fun @down(): i32 {
+%entry:
+  jump %header(10)
+
+%header(%i: i32):
+  %c = lt 0, %i
+  br %c, %body, %exit
+
+%body:
+  %next = sub %i, 3
+  jump %header(%next)
+
+%exit:
+  ret %i
+}
+
+fun @early_exit(@x: i32): i32 {
+%entry:
+  jump %header(0)
+
+%header(%i: i32):
+  %c = ge %i, 100
+  br %c, %exit, %body
+
+%body:
+  br @x, %exit, %latch
+
+%latch:
+  %next = add %i, 1
+  jump %header(%next)
+
+%exit:
+  ret 0
+}
+
+fun @skip(): i32 {
+%entry:
+  jump %header(0)
+
+%header(%i: i32):
+  %c = ne %i, 7
+  br %c, %body, %exit
+
+%body:
+  %next = add %i, 2
+  jump %header(%next)
+
+%exit:
+  ret 0
+}
+
+fun @wrap(): i32 {
+%entry:
+  jump %header(0)
+
+%header(%i: i32):
+  %c = le %i, 2147483647
+  br %c, %body, %exit
+
+%body:
+  %next = add %i, 1
+  jump %header(%next)
+
+%exit:
+  ret 0
+}
+"#
+    .into();
+    let program = driver.generate_program().unwrap();
+    let count = |name: &str| {
+      let func = (program.funcs().values())
+        .find(|f| f.name() == name)
+        .unwrap();
+      let header = (func.layout().bbs().keys())
+        .copied()
+        .find(|bb| func.dfg().bb(*bb).name().as_deref() == Some("%header"))
+        .unwrap();
+      trip_count(func, header)
+    };
+    assert_eq!(count("@up"), TripCount::Exact(10));
+    // 10, 7, 4, 1
+    assert_eq!(count("@down"), TripCount::Exact(4));
+    assert_eq!(count("@early_exit"), TripCount::Bounded(100));
+    // `%i` skips over 7
+    assert_eq!(count("@skip"), TripCount::Unknown);
+    assert_eq!(count("@wrap"), TripCount::Unknown);
+  }
+}
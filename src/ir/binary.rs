@@ -0,0 +1,922 @@
+//! Compact binary serialization of Koopa IR programs.
+//!
+//! Programs written by [`write_program`] can be read back by
+//! [`read_program`] much faster than parsing the text form, which is
+//! useful for caching programs between invocations of tools.
+//!
+//! The binary form preserves global values, functions, basic blocks and
+//! values (including names, and values that are not in the layout), so
+//! the `used_by` sets of values and basic blocks are rebuilt exactly.
+//! Handles of entities are renumbered, and source spans are not kept.
+//! Named types are stored by name, and their definitions
+//! ([`Program::named_types`]) are stored before global values.
+//!
+//! Blobs start with a magic number and a format version, blobs written
+//! by other versions of this module are rejected by [`read_program`].
+
+use crate::ir::entities::{BasicBlockData, FunctionData, ValueData};
+use crate::ir::types::is_named_type_name;
+use crate::ir::values::*;
+use crate::ir::{BasicBlock, Function, Program, Type, TypeKind, Value, ValueKind};
+use std::collections::{HashMap, HashSet};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::{error, fmt};
+
+/// Magic number at the beginning of binary blobs.
+const MAGIC: &[u8; 8] = b"KOOPABIN";
+
+/// Version of the binary format.
+///
+/// Must be updated whenever the format changes.
+pub const VERSION: u32 = 6;
+
+/// Error returned by [`read_program`].
+#[derive(Debug)]
+pub enum ReadError {
+  /// I/O error.
+  Io(io::Error),
+  /// The input is not a binary blob of Koopa IR.
+  InvalidMagic,
+  /// The blob is written by an unsupported version of format.
+  UnsupportedVersion(u32),
+  /// The blob is corrupted.
+  Malformed(&'static str),
+}
+
+impl fmt::Display for ReadError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Io(error) => write!(f, "{}", error),
+      Self::InvalidMagic => write!(f, "not a binary Koopa IR program"),
+      Self::UnsupportedVersion(v) => {
+        write!(f, "unsupported format version {}, expected {}", v, VERSION)
+      }
+      Self::Malformed(msg) => write!(f, "malformed binary program: {}", msg),
+    }
+  }
+}
+
+impl error::Error for ReadError {}
+
+impl From<io::Error> for ReadError {
+  fn from(error: io::Error) -> Self {
+    Self::Io(error)
+  }
+}
+
+/// Writes the given program to the given writer in binary form.
+///
+/// # Panics
+///
+/// Panics if an instruction uses a function parameter or a basic block
+/// parameter that does not belong to its function or basic block.
+pub fn write_program(program: &Program, w: impl Write) -> io::Result<()> {
+  let mut w = Writer {
+    w: BufWriter::new(w),
+  };
+  w.bytes(MAGIC)?;
+  w.bytes(&VERSION.to_le_bytes())?;
+  // named type definitions
+  w.uint(program.named_types().len())?;
+  for (name, ty) in program.named_types().iter() {
+    w.str(name)?;
+    w.ty(ty)?;
+  }
+  // global values, operands always come before users
+  let values = program.borrow_values();
+  let mut globals = Vec::new();
+  let mut visited = HashSet::new();
+  let mut roots: Vec<_> = values.keys().copied().collect();
+  roots.sort_unstable_by_key(|v| v.0);
+  for v in roots {
+    sort_globals(&values, v, &mut visited, &mut globals);
+  }
+  let global_index: HashMap<_, _> = globals.iter().enumerate().map(|(i, v)| (*v, i)).collect();
+  let func_index: HashMap<_, _> = (program.func_layout().iter())
+    .enumerate()
+    .map(|(i, f)| (*f, i))
+    .collect();
+  let refs = Refs {
+    globals: &global_index,
+    locals: &HashMap::new(),
+    bbs: &HashMap::new(),
+    funcs: &func_index,
+  };
+  w.uint(globals.len())?;
+  for v in &globals {
+    w.value_data(&refs, &values[v])?;
+  }
+  w.uint(program.inst_layout().len())?;
+  for v in program.inst_layout() {
+    w.uint(global_index[v])?;
+  }
+  drop(values);
+  // function signatures
+  w.uint(program.func_layout().len())?;
+  for f in program.func_layout() {
+    let data = program.func(*f);
+    w.str(data.name())?;
+    w.ty(data.ty())?;
+    w.bool(data.layout().entry_bb().is_some())?;
+    for p in data.params() {
+      w.opt_str(data.dfg().value(*p).name())?;
+    }
+  }
+  // function bodies
+  for f in program.func_layout() {
+    let data = program.func(*f);
+    if data.layout().entry_bb().is_some() {
+      write_func(&mut w, &refs, data)?;
+    }
+  }
+  w.w.flush()
+}
+
+/// Sorts global values in post-order of operands.
+fn sort_globals(
+  values: &HashMap<Value, ValueData>,
+  value: Value,
+  visited: &mut HashSet<Value>,
+  globals: &mut Vec<Value>,
+) {
+  if visited.insert(value) {
+    for v in values[&value].kind().value_uses() {
+      sort_globals(values, v, visited, globals);
+    }
+    globals.push(value);
+  }
+}
+
+/// Writes the body of the given function.
+fn write_func(w: &mut Writer<impl Write>, refs: &Refs, func: &FunctionData) -> io::Result<()> {
+  let dfg = func.dfg();
+  // basic blocks, those in the layout come first
+  let mut bbs: Vec<_> = func.layout().bbs().keys().copied().collect();
+  let mut others: Vec<_> = (dfg.bbs().keys())
+    .filter(|bb| func.layout().bbs().node(bb).is_none())
+    .copied()
+    .collect();
+  others.sort_unstable_by_key(|bb| bb.0);
+  bbs.extend(others);
+  // local values, parameters come first
+  let mut locals = func.params().to_vec();
+  locals.extend(
+    bbs
+      .iter()
+      .flat_map(|bb| dfg.bb(*bb).params().iter().copied()),
+  );
+  let mut others: Vec<_> = (dfg.values().iter())
+    .filter(|(_, d)| {
+      !matches!(
+        d.kind(),
+        ValueKind::FuncArgRef(_) | ValueKind::BlockArgRef(_)
+      )
+    })
+    .map(|(v, _)| *v)
+    .collect();
+  others.sort_unstable_by_key(|v| v.0);
+  let params_len = locals.len();
+  locals.extend(others);
+  let local_index = locals.iter().enumerate().map(|(i, v)| (*v, i)).collect();
+  let bb_index = bbs.iter().enumerate().map(|(i, bb)| (*bb, i)).collect();
+  let refs = Refs {
+    locals: &local_index,
+    bbs: &bb_index,
+    ..*refs
+  };
+  w.uint(bbs.len())?;
+  for bb in &bbs {
+    let data = dfg.bb(*bb);
+    w.opt_str(data.name())?;
+    w.uint(data.params().len())?;
+    for p in data.params() {
+      let p = dfg.value(*p);
+      w.opt_str(p.name())?;
+      w.ty(p.ty())?;
+    }
+  }
+  // names and types of other values first, then their contents
+  w.uint(locals.len() - params_len)?;
+  for v in &locals[params_len..] {
+    let data = dfg.value(*v);
+    w.opt_str(data.name())?;
+    w.ty(data.ty())?;
+  }
+  for v in &locals[params_len..] {
+    w.kind(&refs, dfg.value(*v).kind())?;
+  }
+  // layout
+  w.uint(func.layout().bbs().len())?;
+  for (_, node) in func.layout().bbs() {
+    w.uint(node.insts().len())?;
+    for inst in node.insts().keys() {
+      w.uint(local_index[inst])?;
+    }
+  }
+  Ok(())
+}
+
+/// Indices of referenced entities.
+#[derive(Clone, Copy)]
+struct Refs<'a> {
+  globals: &'a HashMap<Value, usize>,
+  locals: &'a HashMap<Value, usize>,
+  bbs: &'a HashMap<BasicBlock, usize>,
+  funcs: &'a HashMap<Function, usize>,
+}
+
+/// Writer of primitives.
+struct Writer<W: Write> {
+  w: BufWriter<W>,
+}
+
+impl<W: Write> Writer<W> {
+  fn bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+    self.w.write_all(bytes)
+  }
+
+  /// Writes an unsigned integer in LEB128.
+  fn uint(&mut self, value: usize) -> io::Result<()> {
+    let mut value = value as u64;
+    loop {
+      let byte = (value & 0x7f) as u8;
+      value >>= 7;
+      if value == 0 {
+        return self.bytes(&[byte]);
+      }
+      self.bytes(&[byte | 0x80])?;
+    }
+  }
+
+  /// Writes a signed integer in zigzag-encoded LEB128.
+  fn int(&mut self, value: i32) -> io::Result<()> {
+    self.uint(((value << 1) ^ (value >> 31)) as u32 as usize)
+  }
+
+  fn bool(&mut self, value: bool) -> io::Result<()> {
+    self.bytes(&[value as u8])
+  }
+
+  fn str(&mut self, s: &str) -> io::Result<()> {
+    self.uint(s.len())?;
+    self.bytes(s.as_bytes())
+  }
+
+  fn opt_str(&mut self, s: &Option<String>) -> io::Result<()> {
+    match s {
+      Some(s) => {
+        self.bool(true)?;
+        self.str(s)
+      }
+      None => self.bool(false),
+    }
+  }
+
+  fn ty(&mut self, ty: &Type) -> io::Result<()> {
+    match ty.kind() {
+      TypeKind::Int32 => self.bytes(&[0]),
+      TypeKind::Unit => self.bytes(&[1]),
+      TypeKind::Array(base, len) => {
+        self.bytes(&[2])?;
+        self.ty(base)?;
+        self.uint(*len)
+      }
+      TypeKind::Pointer(base) => {
+        self.bytes(&[3])?;
+        self.ty(base)
+      }
+      TypeKind::Function(params, ret) => {
+        self.bytes(&[4])?;
+        self.uint(params.len())?;
+        for p in params {
+          self.ty(p)?;
+        }
+        self.ty(ret)
+      }
+      TypeKind::Named(name) => {
+        self.bytes(&[5])?;
+        self.str(name)
+      }
+    }
+  }
+
+  /// Writes a reference to a value, the lowest bit indicates if the
+  /// value is a local value.
+  fn value(&mut self, refs: &Refs, value: Value) -> io::Result<()> {
+    let (index, local) = match refs.globals.get(&value) {
+      Some(i) => (*i, 0),
+      None => (refs.locals[&value], 1),
+    };
+    self.uint(index << 1 | local)
+  }
+
+  fn values(&mut self, refs: &Refs, values: &[Value]) -> io::Result<()> {
+    self.uint(values.len())?;
+    values.iter().try_for_each(|v| self.value(refs, *v))
+  }
+
+  fn bb(&mut self, refs: &Refs, bb: BasicBlock) -> io::Result<()> {
+    self.uint(refs.bbs[&bb])
+  }
+
+  fn value_data(&mut self, refs: &Refs, data: &ValueData) -> io::Result<()> {
+    self.opt_str(data.name())?;
+    self.ty(data.ty())?;
+    self.kind(refs, data.kind())
+  }
+
+  fn kind(&mut self, refs: &Refs, kind: &ValueKind) -> io::Result<()> {
+    match kind {
+      ValueKind::Integer(v) => {
+        self.bytes(&[0])?;
+        self.int(v.value())
+      }
+      ValueKind::ZeroInit(_) => self.bytes(&[1]),
+      ValueKind::Undef(_) => self.bytes(&[2]),
+      ValueKind::Aggregate(v) => {
+        self.bytes(&[3])?;
+        self.values(refs, v.elems())
+      }
+      ValueKind::FuncArgRef(_) | ValueKind::BlockArgRef(_) => {
+        panic!("parameters are written with functions or basic blocks")
+      }
+      ValueKind::Alloc(v) => {
+        self.bytes(&[4])?;
+        self.uint(v.align().unwrap_or(0))
+      }
+      ValueKind::GlobalAlloc(v) => {
+        self.bytes(&[5])?;
+        self.value(refs, v.init())?;
+        self.uint(v.align().unwrap_or(0))?;
+        let attrs = v.attrs();
+        self.bytes(&[attrs.readonly as u8 | (attrs.thread_local as u8) << 1])
+      }
+      ValueKind::Load(v) => {
+        self.bytes(&[6])?;
+        self.value(refs, v.src())
+      }
+      ValueKind::Store(v) => {
+        self.bytes(&[7])?;
+        self.value(refs, v.value())?;
+        self.value(refs, v.dest())
+      }
+      ValueKind::GetPtr(v) => {
+        self.bytes(&[8])?;
+        self.value(refs, v.src())?;
+        self.value(refs, v.index())
+      }
+      ValueKind::GetElemPtr(v) => {
+        self.bytes(&[9])?;
+        self.value(refs, v.src())?;
+        self.value(refs, v.index())
+      }
+      ValueKind::Binary(v) => {
+        let flags = v.flags();
+        let flags = flags.nsw as u8 | (flags.nuw as u8) << 1 | (flags.exact as u8) << 2;
+        self.bytes(&[10, binary_op_code(v.op()), flags])?;
+        self.value(refs, v.lhs())?;
+        self.value(refs, v.rhs())
+      }
+      ValueKind::Unary(v) => {
+        self.bytes(&[11, v.op() as u8])?;
+        self.value(refs, v.opr())
+      }
+      ValueKind::CheckedBinary(v) => {
+        self.bytes(&[12, binary_op_code(v.op())])?;
+        self.value(refs, v.lhs())?;
+        self.value(refs, v.rhs())
+      }
+      ValueKind::ExtractValue(v) => {
+        self.bytes(&[13])?;
+        self.value(refs, v.agg())?;
+        self.uint(v.index())
+      }
+      ValueKind::InsertValue(v) => {
+        self.bytes(&[14])?;
+        self.value(refs, v.agg())?;
+        self.value(refs, v.value())?;
+        self.uint(v.index())
+      }
+      ValueKind::Assume(v) => {
+        self.bytes(&[15])?;
+        self.value(refs, v.cond())
+      }
+      ValueKind::Branch(v) => {
+        self.bytes(&[16])?;
+        self.value(refs, v.cond())?;
+        self.bb(refs, v.true_bb())?;
+        self.values(refs, v.true_args())?;
+        self.bb(refs, v.false_bb())?;
+        self.values(refs, v.false_args())
+      }
+      ValueKind::Jump(v) => {
+        self.bytes(&[17])?;
+        self.bb(refs, v.target())?;
+        self.values(refs, v.args())
+      }
+      ValueKind::Switch(v) => {
+        self.bytes(&[18])?;
+        self.value(refs, v.cond())?;
+        self.bb(refs, v.default_bb())?;
+        self.values(refs, v.default_args())?;
+        self.uint(v.cases().len())?;
+        for (value, bb, args) in v.cases() {
+          self.int(*value)?;
+          self.bb(refs, *bb)?;
+          self.values(refs, args)?;
+        }
+        Ok(())
+      }
+      ValueKind::Call(v) => {
+        self.bytes(&[19])?;
+        self.uint(refs.funcs[&v.callee()])?;
+        self.values(refs, v.args())
+      }
+      ValueKind::Return(v) => match v.value() {
+        Some(value) => {
+          self.bytes(&[20])?;
+          self.value(refs, value)
+        }
+        None => self.bytes(&[21]),
+      },
+      ValueKind::Unreachable(_) => self.bytes(&[22]),
+      ValueKind::Copy(v) => {
+        self.bytes(&[23])?;
+        self.value(refs, v.src())
+      }
+    }
+  }
+}
+
+/// All binary operators, indexed by their codes.
+const BINARY_OPS: [BinaryOp; 17] = [
+  BinaryOp::NotEq,
+  BinaryOp::Eq,
+  BinaryOp::Gt,
+  BinaryOp::Lt,
+  BinaryOp::Ge,
+  BinaryOp::Le,
+  BinaryOp::Add,
+  BinaryOp::Sub,
+  BinaryOp::Mul,
+  BinaryOp::Div,
+  BinaryOp::Mod,
+  BinaryOp::And,
+  BinaryOp::Or,
+  BinaryOp::Xor,
+  BinaryOp::Shl,
+  BinaryOp::Shr,
+  BinaryOp::Sar,
+];
+
+/// Returns the code of the given binary operator.
+fn binary_op_code(op: BinaryOp) -> u8 {
+  BINARY_OPS.iter().position(|o| *o == op).unwrap() as u8
+}
+
+/// Reads a program in binary form from the given reader.
+///
+/// The blob is expected to be written by [`write_program`]. Corrupted
+/// blobs are rejected if they are detected, but operand types are not
+/// checked again, use [`Program::verify`] if the blob is untrusted.
+pub fn read_program(r: impl Read) -> Result<Program, ReadError> {
+  let mut r = Reader {
+    r: BufReader::new(r),
+  };
+  let mut magic = [0; 8];
+  r.r.read_exact(&mut magic)?;
+  if &magic != MAGIC {
+    return Err(ReadError::InvalidMagic);
+  }
+  let mut version = [0; 4];
+  r.r.read_exact(&mut version)?;
+  let version = u32::from_le_bytes(version);
+  if version != VERSION {
+    return Err(ReadError::UnsupportedVersion(version));
+  }
+  let mut program = Program::new();
+  // named type definitions
+  let len = r.uint()?;
+  for _ in 0..len {
+    let name = r.str()?;
+    let ty = r.ty()?;
+    (program.named_types_mut().define(&name, ty)).map_err(|_| malformed("named type"))?;
+  }
+  // global values
+  let len = r.uint()?;
+  let mut globals = Vec::new();
+  for _ in 0..len {
+    let name = r.opt_str()?;
+    let ty = r.ty()?;
+    let refs = ReadRefs {
+      globals: &globals,
+      locals: &[],
+      bbs: &[],
+      funcs: &[],
+    };
+    let mut data = r.kind(&refs, ty)?;
+    data.set_name(name);
+    globals.push(program.new_value_data(data));
+  }
+  let len = r.uint()?;
+  for _ in 0..len {
+    let alloc = *globals.get(r.uint()?).ok_or(malformed("global"))?;
+    if !program.borrow_value(alloc).kind().is_global_alloc() {
+      return Err(malformed("global allocation"));
+    }
+    program.inst_layout.push(alloc);
+  }
+  // function signatures
+  let len = r.uint()?;
+  let mut funcs = Vec::new();
+  let mut defs = Vec::new();
+  for _ in 0..len {
+    let name = r.str()?;
+    if !(name.len() > 1 && name.starts_with('@')) {
+      return Err(malformed("function name"));
+    }
+    let (params, ret) = match r.ty()?.kind() {
+      TypeKind::Function(params, ret) => (params.clone(), ret.clone()),
+      _ => return Err(malformed("function type")),
+    };
+    if params.iter().any(Type::is_unit) {
+      return Err(malformed("parameter type"));
+    }
+    let func = if r.bool()? {
+      let params = (params.into_iter())
+        .map(|ty| r.opt_str().map(|n| (n, ty)))
+        .collect::<Result<_, _>>()?;
+      let func = program.new_func(FunctionData::with_param_names(name, params, ret));
+      defs.push(func);
+      func
+    } else {
+      program.new_func(FunctionData::new_decl(name, params, ret))
+    };
+    funcs.push(func);
+  }
+  // function bodies
+  for func in defs {
+    read_func(&mut r, &globals, &funcs, program.func_mut(func))?;
+  }
+  Ok(program)
+}
+
+/// Reads the body of the given function.
+fn read_func(
+  r: &mut Reader<impl Read>,
+  globals: &[Value],
+  funcs: &[Function],
+  func: &mut FunctionData,
+) -> Result<(), ReadError> {
+  let mut locals = func.params().to_vec();
+  // basic blocks
+  let len = r.uint()?;
+  let mut bbs = Vec::new();
+  for _ in 0..len {
+    let name = r.opt_str()?;
+    let params_len = r.uint()?;
+    let mut params = Vec::new();
+    for i in 0..params_len {
+      let name = r.opt_str()?;
+      let mut data = BlockArgRef::new_data(i, r.ty()?);
+      data.set_name(name);
+      params.push(func.dfg_mut().new_value_data(data));
+    }
+    locals.extend(params.iter().copied());
+    let data = BasicBlockData::with_params(name, params);
+    bbs.push(func.dfg_mut().new_bb_data(data));
+  }
+  // other values are created as placeholders first,
+  // since they may refer to each other
+  let len = r.uint()?;
+  let start = locals.len();
+  for _ in 0..len {
+    let name = r.opt_str()?;
+    let mut data = Undef::new_data(r.ty()?);
+    data.set_name(name);
+    locals.push(func.dfg_mut().new_value_data(data));
+  }
+  let refs = ReadRefs {
+    globals,
+    locals: &locals,
+    bbs: &bbs,
+    funcs,
+  };
+  for &v in &locals[start..] {
+    let ty = func.dfg().value(v).ty().clone();
+    let mut data = r.kind(&refs, ty)?;
+    data.set_name(func.dfg().value(v).name().clone());
+    func.dfg_mut().replace_value_with_data(v, data);
+  }
+  // layout
+  let len = r.uint()?;
+  for &bb in bbs.get(..len).ok_or(malformed("layout"))? {
+    func.layout_mut().bbs_mut().push_key_back(bb).unwrap();
+    let insts_len = r.uint()?;
+    for _ in 0..insts_len {
+      let inst = *locals.get(r.uint()?).ok_or(malformed("instruction"))?;
+      let insts = func.layout_mut().bb_mut(bb).insts_mut();
+      insts
+        .push_key_back(inst)
+        .map_err(|_| malformed("instruction"))?;
+    }
+  }
+  Ok(())
+}
+
+/// Returns a malformed error.
+fn malformed(msg: &'static str) -> ReadError {
+  ReadError::Malformed(msg)
+}
+
+/// Referenced entities by indices.
+struct ReadRefs<'a> {
+  globals: &'a [Value],
+  locals: &'a [Value],
+  bbs: &'a [BasicBlock],
+  funcs: &'a [Function],
+}
+
+/// Reader of primitives.
+struct Reader<R: Read> {
+  r: BufReader<R>,
+}
+
+impl<R: Read> Reader<R> {
+  fn byte(&mut self) -> Result<u8, ReadError> {
+    let mut byte = [0];
+    self.r.read_exact(&mut byte)?;
+    Ok(byte[0])
+  }
+
+  fn uint(&mut self) -> Result<usize, ReadError> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+      let byte = self.byte()?;
+      value |= ((byte & 0x7f) as u64) << shift;
+      if byte & 0x80 == 0 {
+        return usize::try_from(value).map_err(|_| malformed("integer"));
+      }
+    }
+    Err(malformed("integer"))
+  }
+
+  fn int(&mut self) -> Result<i32, ReadError> {
+    let value = u32::try_from(self.uint()?).map_err(|_| malformed("integer"))?;
+    Ok((value >> 1) as i32 ^ -((value & 1) as i32))
+  }
+
+  fn bool(&mut self) -> Result<bool, ReadError> {
+    match self.byte()? {
+      0 => Ok(false),
+      1 => Ok(true),
+      _ => Err(malformed("boolean")),
+    }
+  }
+
+  fn str(&mut self) -> Result<String, ReadError> {
+    let len = self.uint()?;
+    let mut bytes = Vec::new();
+    (&mut self.r).take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != len {
+      return Err(malformed("string"));
+    }
+    String::from_utf8(bytes).map_err(|_| malformed("string"))
+  }
+
+  fn opt_str(&mut self) -> Result<Option<String>, ReadError> {
+    let s = if self.bool()? {
+      Some(self.str()?)
+    } else {
+      None
+    };
+    match &s {
+      Some(n) if !(n.len() > 1 && (n.starts_with('%') || n.starts_with('@'))) => {
+        Err(malformed("name"))
+      }
+      _ => Ok(s),
+    }
+  }
+
+  fn ty(&mut self) -> Result<Type, ReadError> {
+    Ok(match self.byte()? {
+      0 => Type::get_i32(),
+      1 => Type::get_unit(),
+      2 => {
+        let base = self.ty()?;
+        match self.uint()? {
+          0 => return Err(malformed("array length")),
+          len => Type::get_array(base, len),
+        }
+      }
+      3 => Type::get_pointer(self.ty()?),
+      4 => {
+        let len = self.uint()?;
+        let params = (0..len).map(|_| self.ty()).collect::<Result<_, _>>()?;
+        Type::get_function(params, self.ty()?)
+      }
+      5 => match self.str()? {
+        name if is_named_type_name(&name) => Type::get_named(name),
+        _ => return Err(malformed("named type name")),
+      },
+      _ => return Err(malformed("type")),
+    })
+  }
+
+  fn value(&mut self, refs: &ReadRefs) -> Result<Value, ReadError> {
+    let index = self.uint()?;
+    let values = if index & 1 == 0 {
+      refs.globals
+    } else {
+      refs.locals
+    };
+    values
+      .get(index >> 1)
+      .copied()
+      .ok_or(malformed("value reference"))
+  }
+
+  fn values(&mut self, refs: &ReadRefs) -> Result<Vec<Value>, ReadError> {
+    let len = self.uint()?;
+    (0..len).map(|_| self.value(refs)).collect()
+  }
+
+  fn bb(&mut self, refs: &ReadRefs) -> Result<BasicBlock, ReadError> {
+    let index = self.uint()?;
+    refs
+      .bbs
+      .get(index)
+      .copied()
+      .ok_or(malformed("basic block reference"))
+  }
+
+  fn binary_op(&mut self) -> Result<BinaryOp, ReadError> {
+    let code = self.byte()?;
+    BINARY_OPS
+      .get(code as usize)
+      .copied()
+      .ok_or(malformed("binary operator"))
+  }
+
+  /// Reads the alignment of a memory allocation, `0` for unspecified.
+  fn align(&mut self) -> Result<Option<usize>, ReadError> {
+    match self.uint()? {
+      0 => Ok(None),
+      a if a.is_power_of_two() => Ok(Some(a)),
+      _ => Err(malformed("alignment")),
+    }
+  }
+
+  /// Reads the kind of a value, returns the value data with the given
+  /// type.
+  fn kind(&mut self, refs: &ReadRefs, ty: Type) -> Result<ValueData, ReadError> {
+    Ok(match self.byte()? {
+      0 => Integer::new_data(self.int()?),
+      1 => ZeroInit::new_data(ty),
+      2 => Undef::new_data(ty),
+      3 => Aggregate::new_data(self.values(refs)?, ty),
+      4 => Alloc::new_data(ty, self.align()?),
+      5 => {
+        let (init, align) = (self.value(refs)?, self.align()?);
+        let attrs = match self.byte()? {
+          bits if bits < 4 => GlobalAttrs {
+            readonly: bits & 1 != 0,
+            thread_local: bits & 2 != 0,
+          },
+          _ => return Err(malformed("global attributes")),
+        };
+        GlobalAlloc::new_data_with_attrs(init, align, attrs, ty)
+      }
+      6 => Load::new_data(self.value(refs)?, ty),
+      7 => Store::new_data(self.value(refs)?, self.value(refs)?),
+      8 => GetPtr::new_data(self.value(refs)?, self.value(refs)?, ty),
+      9 => GetElemPtr::new_data(self.value(refs)?, self.value(refs)?, ty),
+      10 => {
+        let op = self.binary_op()?;
+        let flags = match self.byte()? {
+          bits @ 0..=7 => BinaryFlags {
+            nsw: bits & 1 != 0,
+            nuw: bits & 2 != 0,
+            exact: bits & 4 != 0,
+          },
+          _ => return Err(malformed("binary flags")),
+        };
+        Binary::new_data_with_flags(op, flags, self.value(refs)?, self.value(refs)?, ty)
+      }
+      11 => {
+        let op = match self.byte()? {
+          0 => UnaryOp::Not,
+          1 => UnaryOp::Neg,
+          _ => return Err(malformed("unary operator")),
+        };
+        Unary::new_data(op, self.value(refs)?, ty)
+      }
+      12 => CheckedBinary::new_data(self.binary_op()?, self.value(refs)?, self.value(refs)?, ty),
+      13 => ExtractValue::new_data(self.value(refs)?, self.uint()?, ty),
+      14 => InsertValue::new_data(self.value(refs)?, self.value(refs)?, self.uint()?, ty),
+      15 => Assume::new_data(self.value(refs)?),
+      16 => {
+        let cond = self.value(refs)?;
+        let (true_bb, true_args) = (self.bb(refs)?, self.values(refs)?);
+        let (false_bb, false_args) = (self.bb(refs)?, self.values(refs)?);
+        Branch::with_args(cond, true_bb, false_bb, true_args, false_args)
+      }
+      17 => Jump::with_args(self.bb(refs)?, self.values(refs)?),
+      18 => {
+        let cond = self.value(refs)?;
+        let (default, default_args) = (self.bb(refs)?, self.values(refs)?);
+        let len = self.uint()?;
+        let cases = (0..len)
+          .map(|_| Ok((self.int()?, self.bb(refs)?, self.values(refs)?)))
+          .collect::<Result<_, ReadError>>()?;
+        Switch::new_data(cond, default, default_args, cases)
+      }
+      19 => {
+        let index = self.uint()?;
+        let callee = *refs.funcs.get(index).ok_or(malformed("function"))?;
+        Call::new_data(callee, self.values(refs)?, ty)
+      }
+      20 => Return::new_data(Some(self.value(refs)?)),
+      21 => Return::new_data(None),
+      22 => Unreachable::new_data(),
+      23 => CopyValue::new_data(self.value(refs)?, ty),
+      _ => return Err(malformed("value kind")),
+    })
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::back::KoopaGenerator;
+  use crate::front::Driver;
+
+  fn dump(program: &Program) -> String {
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen.generate_on(program).unwrap();
+    String::from_utf8(gen.writer()).unwrap()
+  }
+
+  #[test]
+  fn round_trip() {
+    let driver: Driver<_> = r#"type @list = [*@list, 2]
+
+global @x = alloc [i32, 3], {1, 2, 3}
+global @p = alloc *[i32, 3], @x, readonly, threadlocal, align 16
+global @l = alloc @list, zeroinit
+
+decl @getint(): i32
+
+fun @f(@n: i32): i32 {
+%entry:
+  %a = alloc i32, align 8
+  %0 = getelemptr @x, 1
+  %1 = load %0
+  %c = checked add @n, %1
+  %2 = extractvalue %c, 1
+  br %2, %ovf, %loop(0)
+
+%ovf:
+  unreachable
+
+%loop(%i: i32):
+  %3 = lt %i, -5
+  assume %3
+  %4 = neg %i
+  %5 = shl nsw nuw %4, 1
+  %6 = div exact %5, 2
+  switch %6, %loop(%i), [1: %end, 2: %end]
+
+%end:
+  %7 = call @getint()
+  ret %7
+}
+"#
+    .into();
+    let program = driver.generate_program().unwrap();
+    let mut blob = Vec::new();
+    write_program(&program, &mut blob).unwrap();
+    let read = read_program(blob.as_slice()).unwrap();
+    assert_eq!(read.verify(), Ok(()));
+    assert_eq!(read.audit_use_lists(), Ok(()));
+    assert_eq!(dump(&read), dump(&program));
+    assert_eq!(read.content_hash(), program.content_hash());
+    // corrupted blobs
+    assert!(matches!(
+      read_program(&b"KOOPAIR"[..]),
+      Err(ReadError::Io(_))
+    ));
+    let mut old = blob.clone();
+    old[8] = 0;
+    assert!(matches!(
+      read_program(old.as_slice()),
+      Err(ReadError::UnsupportedVersion(0))
+    ));
+    old[0] = b'X';
+    assert!(matches!(
+      read_program(old.as_slice()),
+      Err(ReadError::InvalidMagic)
+    ));
+    assert!(read_program(&blob[..blob.len() - 1]).is_err());
+  }
+}
@@ -6,9 +6,11 @@
 //! provided by the builder traits.
 
 use crate::ir::dfg::DataFlowGraph;
-use crate::ir::entities::{BasicBlock, BasicBlockData, Function, Program, Value, ValueData};
-use crate::ir::types::{Type, TypeKind};
-use crate::ir::values::*;
+use crate::ir::entities::{
+  BasicBlock, BasicBlockData, Function, Program, Value, ValueData, ValueKind,
+};
+use crate::ir::types::Type;
+use crate::ir::values::{self, BinaryFlags, BinaryOp, BlockArgRef, SwitchCase, UnaryOp};
 
 /// A trait that provides methods for querying entity information.
 pub trait EntityInfoQuerier {
@@ -50,6 +52,10 @@ pub trait ValueInserter {
 }
 
 /// A builder trait that provides method for building value data.
+///
+/// Value data are created by constructor functions in module
+/// [`values`](crate::ir::values), see these functions for the type of
+/// the created value data.
 pub trait ValueBuilder: Sized + EntityInfoQuerier + ValueInserter {
   /// Create a new value by the given value data.
   fn raw(mut self, data: ValueData) -> Value {
@@ -58,7 +64,7 @@ pub trait ValueBuilder: Sized + EntityInfoQuerier + ValueInserter {
 
   /// Create a new integer constant.
   fn integer(mut self, value: i32) -> Value {
-    self.insert_value(Integer::new_data(value))
+    self.insert_value(values::integer(value))
   }
 
   /// Create a new zero initializer.
@@ -67,8 +73,7 @@ pub trait ValueBuilder: Sized + EntityInfoQuerier + ValueInserter {
   ///
   /// Panics if the given type is a unit type.
   fn zero_init(mut self, ty: Type) -> Value {
-    assert!(!ty.is_unit(), "`ty` can not be unit");
-    self.insert_value(ZeroInit::new_data(ty))
+    self.insert_value(values::zero_init(ty))
   }
 
   /// Create a new undefined value.
@@ -77,8 +82,7 @@ pub trait ValueBuilder: Sized + EntityInfoQuerier + ValueInserter {
   ///
   /// Panics if the given type is a unit type.
   fn undef(mut self, ty: Type) -> Value {
-    assert!(!ty.is_unit(), "`ty` can not be unit");
-    self.insert_value(Undef::new_data(ty))
+    self.insert_value(values::undef(ty))
   }
 
   /// Creates an aggregate constant with elements `elems`.
@@ -91,26 +95,26 @@ pub trait ValueBuilder: Sized + EntityInfoQuerier + ValueInserter {
   /// * Presence of non-constant elements or unit type elements.
   /// * Elements have different types.
   fn aggregate(mut self, elems: Vec<Value>) -> Value {
-    // element list should not be empty
-    assert!(!elems.is_empty(), "`elems` must not be empty");
-    // check if all elements are constant
-    assert!(
-      elems.iter().all(|e| self.is_const(*e)),
-      "`elems` must all be constants"
-    );
-    // check if all elements have the same type
-    assert!(
-      elems
-        .windows(2)
-        .all(|e| self.value_type(e[0]) == self.value_type(e[1])),
-      "type mismatch in `elems`"
-    );
-    // check base type
-    let base = self.value_type(elems[0]);
-    assert!(!base.is_unit(), "base type must not be `unit`");
-    // create array type
-    let ty = Type::get_array(base, elems.len());
-    self.insert_value(Aggregate::new_data(elems, ty))
+    let data = values::aggregate(&self, elems);
+    self.insert_value(data)
+  }
+
+  /// Creates an aggregate constant of bytes in the given string, with
+  /// a trailing `0` if `nul_terminated` is `true`.
+  ///
+  /// The result type is `[i32, N]`, where `N` is the number of bytes.
+  /// Each byte of the UTF-8 encoded string is an `i32` element.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the string is empty and `nul_terminated` is `false`.
+  fn string_const(mut self, s: &str, nul_terminated: bool) -> Value {
+    let bytes = s.bytes().chain(nul_terminated.then_some(0));
+    let elems: Vec<_> = bytes
+      .map(|b| self.insert_value(values::integer(b as i32)))
+      .collect();
+    let data = values::aggregate(&self, elems);
+    self.insert_value(data)
   }
 }
 
@@ -120,12 +124,57 @@ pub trait GlobalInstBuilder: ValueBuilder {
   ///
   /// # Panics
   ///
-  /// Panics if the type of the initialize is a unit type.
+  /// Panics if the type of the initializer can not be allocated, see
+  /// [`Type::is_allocatable`].
   fn global_alloc(mut self, init: Value) -> Value {
-    let init_ty = self.value_type(init);
-    assert!(!init_ty.is_unit(), "the type of `init` must not be unit");
-    let ty = Type::get_pointer(init_ty);
-    self.insert_value(GlobalAlloc::new_data(init, ty))
+    let data = values::global_alloc(&self, init);
+    self.insert_value(data)
+  }
+
+  /// Creates a global memory allocation with the given alignment in
+  /// bytes. The alignment of the initializer type is used if `align`
+  /// is `None`.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the type of the initializer can not be allocated (see
+  /// [`Type::is_allocatable`]), or the given alignment is not a power
+  /// of two.
+  fn global_alloc_with_align(mut self, init: Value, align: Option<usize>) -> Value {
+    let data = values::global_alloc_with_align(&self, init, align);
+    self.insert_value(data)
+  }
+
+  /// Creates a constant pointer calculation with the given source pointer
+  /// and index, which can be used in global initializers.
+  ///
+  /// The source pointer should be a global memory allocation or another
+  /// constant pointer calculation, and the index should be an integer
+  /// constant, see [`Relocation`](crate::ir::analysis::Relocation).
+  ///
+  /// # Panics
+  ///
+  /// Panics if the source type is not a pointer type, or the index type is
+  /// not an integer type.
+  fn get_ptr(mut self, src: Value, index: Value) -> Value {
+    let data = values::get_ptr(&self, src, index);
+    self.insert_value(data)
+  }
+
+  /// Creates a constant element pointer calculation with the given source
+  /// pointer and index, which can be used in global initializers.
+  ///
+  /// The source pointer should be a global memory allocation or another
+  /// constant pointer calculation, and the index should be an integer
+  /// constant, see [`Relocation`](crate::ir::analysis::Relocation).
+  ///
+  /// # Panics
+  ///
+  /// Panics if the source type is not a pointer type of an array, or the
+  /// index type is not an integer type.
+  fn get_elem_ptr(mut self, src: Value, index: Value) -> Value {
+    let data = values::get_elem_ptr(&self, src, index);
+    self.insert_value(data)
   }
 }
 
@@ -135,10 +184,23 @@ pub trait LocalInstBuilder: ValueBuilder {
   ///
   /// # Panics
   ///
-  /// Panics if the given type is a unit type.
+  /// Panics if the given type can not be allocated, see
+  /// [`Type::is_allocatable`].
   fn alloc(mut self, ty: Type) -> Value {
-    assert!(!ty.is_unit(), "`ty` can not be unit");
-    self.insert_value(Alloc::new_data(Type::get_pointer(ty)))
+    self.insert_value(values::alloc(ty))
+  }
+
+  /// Creates a local memory allocation with the given alignment in
+  /// bytes. The alignment of the given type is used if `align` is
+  /// `None`.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the given type can not be allocated (see
+  /// [`Type::is_allocatable`]), or the given alignment is not a power
+  /// of two.
+  fn alloc_with_align(mut self, ty: Type, align: Option<usize>) -> Value {
+    self.insert_value(values::alloc_with_align(ty, align))
   }
 
   /// Creates a memory load with the given source.
@@ -147,12 +209,8 @@ pub trait LocalInstBuilder: ValueBuilder {
   ///
   /// Panics if the type of the source value is not a pointer type.
   fn load(mut self, src: Value) -> Value {
-    let src_ty = self.value_type(src);
-    let ty = match src_ty.kind() {
-      TypeKind::Pointer(ty) => ty.clone(),
-      _ => panic!("expected a pointer type"),
-    };
-    self.insert_value(Load::new_data(src, ty))
+    let data = values::load(&self, src);
+    self.insert_value(data)
   }
 
   /// Creates a memory store with the given value and destination.
@@ -161,11 +219,8 @@ pub trait LocalInstBuilder: ValueBuilder {
   ///
   /// Panics if the dest type is not a pointer of the value type.
   fn store(mut self, value: Value, dest: Value) -> Value {
-    assert!(
-      Type::get_pointer(self.value_type(value)) == self.value_type(dest),
-      "the type of `dest` must be the pointer of `value`'s type"
-    );
-    self.insert_value(Store::new_data(value, dest))
+    let data = values::store(&self, value, dest);
+    self.insert_value(data)
   }
 
   /// Creates a pointer calculation with the given source pointer and index.
@@ -175,16 +230,8 @@ pub trait LocalInstBuilder: ValueBuilder {
   /// Panics if the source type is not a pointer type, or the index type is
   /// not an integer type.
   fn get_ptr(mut self, src: Value, index: Value) -> Value {
-    let src_ty = self.value_type(src);
-    assert!(
-      matches!(src_ty.kind(), TypeKind::Pointer(..)),
-      "`src` must be a pointer"
-    );
-    assert!(
-      self.value_type(index).is_i32(),
-      "`index` must be an integer"
-    );
-    self.insert_value(GetPtr::new_data(src, index, src_ty))
+    let data = values::get_ptr(&self, src, index);
+    self.insert_value(data)
   }
 
   /// Creates a element pointer calculation with the given source pointer
@@ -195,18 +242,8 @@ pub trait LocalInstBuilder: ValueBuilder {
   /// Panics if the source type is not a pointer type of an array, or the
   /// index type is not an integer type.
   fn get_elem_ptr(mut self, src: Value, index: Value) -> Value {
-    assert!(
-      self.value_type(index).is_i32(),
-      "`index` must be an integer"
-    );
-    let ty = match self.value_type(src).kind() {
-      TypeKind::Pointer(ty) => match ty.kind() {
-        TypeKind::Array(base, _) => Type::get_pointer(base.clone()),
-        _ => panic!("`src` must be a pointer of array"),
-      },
-      _ => panic!("`src` must be a pointer of array"),
-    };
-    self.insert_value(GetElemPtr::new_data(src, index, ty))
+    let data = values::get_elem_ptr(&self, src, index);
+    self.insert_value(data)
   }
 
   /// Creates a binary operation.
@@ -215,13 +252,94 @@ pub trait LocalInstBuilder: ValueBuilder {
   ///
   /// Panics if the lhs/rhs type is not an integer type.
   fn binary(mut self, op: BinaryOp, lhs: Value, rhs: Value) -> Value {
-    let lhs_ty = self.value_type(lhs);
-    let rhs_ty = self.value_type(rhs);
-    assert!(
-      lhs_ty.is_i32() && lhs_ty == rhs_ty,
-      "both `lhs` and `rhs` must be integer"
-    );
-    self.insert_value(Binary::new_data(op, lhs, rhs, lhs_ty))
+    let data = values::binary(&self, op, lhs, rhs);
+    self.insert_value(data)
+  }
+
+  /// Creates a binary operation with the given flags.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the lhs/rhs type is not an integer type, or the flags are
+  /// not supported by the operator.
+  fn binary_with_flags(
+    mut self,
+    op: BinaryOp,
+    flags: BinaryFlags,
+    lhs: Value,
+    rhs: Value,
+  ) -> Value {
+    let data = values::binary_with_flags(&self, op, flags, lhs, rhs);
+    self.insert_value(data)
+  }
+
+  /// Creates a unary operation.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the operand type is not an integer type.
+  fn unary(mut self, op: UnaryOp, opr: Value) -> Value {
+    let data = values::unary(&self, op, opr);
+    self.insert_value(data)
+  }
+
+  /// Creates an overflow-checked binary operation with the given
+  /// operator, lhs and rhs. The result type is `[i32, 2]`, see
+  /// [`CheckedBinary`](crate::ir::values::CheckedBinary) for details.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the operator is not supported, or the lhs/rhs type is
+  /// not an integer type.
+  fn checked_binary(mut self, op: BinaryOp, lhs: Value, rhs: Value) -> Value {
+    let data = values::checked_binary(&self, op, lhs, rhs);
+    self.insert_value(data)
+  }
+
+  /// Creates an element extraction with the given aggregate value and
+  /// index. The result type is the base type of the aggregate type.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the aggregate type is not an array type, or the index
+  /// is out of bounds.
+  fn extract_elem(mut self, agg: Value, index: usize) -> Value {
+    let data = values::extract_value(&self, agg, index);
+    self.insert_value(data)
+  }
+
+  /// Creates an element insertion with the given aggregate value,
+  /// inserted value and index. The result type is the aggregate type.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the aggregate type is not an array type, the index is
+  /// out of bounds, or the value type does not match the base type of
+  /// the aggregate type.
+  fn insert_elem(mut self, agg: Value, value: Value, index: usize) -> Value {
+    let data = values::insert_value(&self, agg, value, index);
+    self.insert_value(data)
+  }
+
+  /// Creates a copy of the given value. The result type is the type of
+  /// the source.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the source type is `unit`.
+  fn copy(mut self, src: Value) -> Value {
+    let data = values::copy(&self, src);
+    self.insert_value(data)
+  }
+
+  /// Creates an assumption with the given condition.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the condition type is not an integer type.
+  fn assume(mut self, cond: Value) -> Value {
+    let data = values::assume(&self, cond);
+    self.insert_value(data)
   }
 
   /// Creates a conditional branch with the given condition and targets.
@@ -231,16 +349,8 @@ pub trait LocalInstBuilder: ValueBuilder {
   /// Panics if the condition type is not an integer type, or the true/false
   /// basic block has parameters.
   fn branch(mut self, cond: Value, true_bb: BasicBlock, false_bb: BasicBlock) -> Value {
-    assert!(self.value_type(cond).is_i32(), "`cond` must be integer");
-    assert!(
-      self.bb_params(true_bb).is_empty(),
-      "`true_bb` must not have parameters"
-    );
-    assert!(
-      self.bb_params(false_bb).is_empty(),
-      "`false_bb` must not have parameters"
-    );
-    self.insert_value(Branch::new_data(cond, true_bb, false_bb))
+    let data = values::branch(&self, cond, true_bb, false_bb);
+    self.insert_value(data)
   }
 
   /// Creates a conditional branch with the given condition, targets
@@ -258,12 +368,8 @@ pub trait LocalInstBuilder: ValueBuilder {
     true_args: Vec<Value>,
     false_args: Vec<Value>,
   ) -> Value {
-    assert!(self.value_type(cond).is_i32(), "`cond` must be integer");
-    check_bb_arg_types(&self, self.bb_params(true_bb), &true_args);
-    check_bb_arg_types(&self, self.bb_params(false_bb), &false_args);
-    self.insert_value(Branch::with_args(
-      cond, true_bb, false_bb, true_args, false_args,
-    ))
+    let data = values::branch_with_args(&self, cond, true_bb, false_bb, true_args, false_args);
+    self.insert_value(data)
   }
 
   /// Creates a unconditional jump with the given target.
@@ -272,11 +378,8 @@ pub trait LocalInstBuilder: ValueBuilder {
   ///
   /// Panics if the target basic block has parameters.
   fn jump(mut self, target: BasicBlock) -> Value {
-    assert!(
-      self.bb_params(target).is_empty(),
-      "`target` must not have parameters"
-    );
-    self.insert_value(Jump::new_data(target))
+    let data = values::jump(&self, target);
+    self.insert_value(data)
   }
 
   /// Creates a unconditional jump with the given target and arguments.
@@ -285,8 +388,40 @@ pub trait LocalInstBuilder: ValueBuilder {
   ///
   /// Panics if the argument types of the target basic block do not match.
   fn jump_with_args(mut self, target: BasicBlock, args: Vec<Value>) -> Value {
-    check_bb_arg_types(&self, self.bb_params(target), &args);
-    self.insert_value(Jump::with_args(target, args))
+    let data = values::jump_with_args(&self, target, args);
+    self.insert_value(data)
+  }
+
+  /// Creates a multi-way branch with the given selector, default target
+  /// and cases (case value and target basic block).
+  ///
+  /// # Panics
+  ///
+  /// Panics if the selector type is not an integer type, the default/case
+  /// basic block has parameters, or there are duplicate case values.
+  fn switch(mut self, cond: Value, default: BasicBlock, cases: Vec<(i32, BasicBlock)>) -> Value {
+    let data = values::switch(&self, cond, default, cases);
+    self.insert_value(data)
+  }
+
+  /// Creates a multi-way branch with the given selector, default target,
+  /// default arguments and cases (case value, target basic block and
+  /// arguments).
+  ///
+  /// # Panics
+  ///
+  /// Panics if the selector type is not an integer type, the argument
+  /// types of the default/case basic block do not match, or there are
+  /// duplicate case values.
+  fn switch_with_args(
+    mut self,
+    cond: Value,
+    default: BasicBlock,
+    default_args: Vec<Value>,
+    cases: Vec<SwitchCase>,
+  ) -> Value {
+    let data = values::switch_with_args(&self, cond, default, default_args, cases);
+    self.insert_value(data)
   }
 
   /// Creates a function call.
@@ -295,20 +430,8 @@ pub trait LocalInstBuilder: ValueBuilder {
   ///
   /// Panics if the argument types of the callee do not match.
   fn call(mut self, callee: Function, args: Vec<Value>) -> Value {
-    let ty = match self.func_type(callee).kind() {
-      TypeKind::Function(params, ret) => {
-        assert!(
-          params
-            .iter()
-            .zip(args.iter())
-            .all(|(ty, a)| ty == &self.value_type(*a)),
-          "argument type mismatch"
-        );
-        ret.clone()
-      }
-      _ => panic!("expected a function type"),
-    };
-    self.insert_value(Call::new_data(callee, args, ty))
+    let data = values::call(&self, callee, args);
+    self.insert_value(data)
   }
 
   /// Creates a new return instruction.
@@ -317,11 +440,13 @@ pub trait LocalInstBuilder: ValueBuilder {
   ///
   /// Panics if the value type (if value is not `None`) is a unit type.
   fn ret(mut self, value: Option<Value>) -> Value {
-    assert!(
-      value.map_or(true, |v| !self.value_type(v).is_unit()),
-      "the type of `value` must not be `unit`"
-    );
-    self.insert_value(Return::new_data(value))
+    let data = values::ret(&self, value);
+    self.insert_value(data)
+  }
+
+  /// Creates a new unreachable instruction.
+  fn unreachable(mut self) -> Value {
+    self.insert_value(values::unreachable())
   }
 }
 
@@ -390,24 +515,6 @@ pub trait BasicBlockBuilder: Sized + ValueInserter {
   }
 }
 
-/// Checks if the parameter types of the given basic block type matches
-/// the given argument types.
-///
-/// # Panics
-///
-/// Panics if the parameter types of the given basic block type does not
-/// match the given argument types.
-fn check_bb_arg_types(querier: &impl EntityInfoQuerier, params: &[Value], args: &[Value]) {
-  assert!(
-    params.len() == args.len()
-      && params
-        .iter()
-        .zip(args.iter())
-        .all(|(p, a)| querier.value_type(*p) == querier.value_type(*a)),
-    "arguments type of basic block mismatch"
-  );
-}
-
 /// Checks if the given name is a valid basic block name.
 ///
 /// # Panics
@@ -415,8 +522,9 @@ fn check_bb_arg_types(querier: &impl EntityInfoQuerier, params: &[Value], args:
 /// Panics if the given name (if exists) not starts with `%` or `@`.
 fn check_bb_name(name: &Option<String>) {
   assert!(
-    name.as_ref().map_or(true, |n| n.len() > 1
-      && (n.starts_with('%') || n.starts_with('@'))),
+    name
+      .as_ref()
+      .is_none_or(|n| n.len() > 1 && (n.starts_with('%') || n.starts_with('@'))),
     "invalid basic block name"
   );
 }
@@ -426,6 +534,12 @@ pub trait DfgBasedInfoQuerier {
   fn dfg(&self) -> &DataFlowGraph;
 }
 
+impl DfgBasedInfoQuerier for DataFlowGraph {
+  fn dfg(&self) -> &DataFlowGraph {
+    self
+  }
+}
+
 impl<T: DfgBasedInfoQuerier> EntityInfoQuerier for T {
   fn value_type(&self, value: Value) -> Type {
     self
@@ -566,14 +680,14 @@ impl<'a> EntityInfoQuerier for GlobalBuilder<'a> {
   }
 
   fn is_const(&self, value: Value) -> bool {
-    self
-      .program
-      .values
-      .borrow()
-      .get(&value)
-      .expect("value does not exist")
-      .kind()
-      .is_const()
+    // addresses of global allocations are also constants in global scope
+    let values = self.program.values.borrow();
+    let kind = values.get(&value).expect("value does not exist").kind();
+    kind.is_const()
+      || matches!(
+        kind,
+        ValueKind::GlobalAlloc(_) | ValueKind::GetPtr(_) | ValueKind::GetElemPtr(_)
+      )
   }
 
   fn bb_params(&self, _: BasicBlock) -> &[Value] {
@@ -81,6 +81,27 @@ impl DataFlowGraph {
     value
   }
 
+  /// Creates a copy of the given local value, and returns the handle of
+  /// the copy.
+  ///
+  /// Operands of the copy are replaced according to the given map,
+  /// operands not in the map are kept unchanged. The name of the value
+  /// is not copied, and the copy is not added to the layout.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the given value does not exist.
+  pub fn clone_value(&mut self, value: Value, map: &HashMap<Value, Value>) -> Value {
+    let mut data = self.value(value).clone();
+    data.set_name(None);
+    for v in data.kind_mut().value_uses_mut() {
+      if let Some(new) = map.get(v) {
+        *v = *new;
+      }
+    }
+    self.new_value_data(data)
+  }
+
   /// Replaces the given value with a new value.
   /// Returns a [`ReplaceBuilder`] for building the new value.
   ///
@@ -94,11 +115,16 @@ impl DataFlowGraph {
   /// Replaces the given value with a new value data.
   /// This method will be called by [`ReplaceBuilder`].
   ///
+  /// Users of the given value are kept, since they still refer to the
+  /// value after the replacement. The `used_by` set of the given data
+  /// is ignored.
+  ///
   /// # Panics
   ///
   /// Panics if the given value does not exist.
-  pub(in crate::ir) fn replace_value_with_data(&mut self, value: Value, data: ValueData) {
-    let old = self.values.remove(&value).unwrap();
+  pub(in crate::ir) fn replace_value_with_data(&mut self, value: Value, mut data: ValueData) {
+    let mut old = self.values.remove(&value).unwrap();
+    data.used_by = std::mem::take(&mut old.used_by);
     for v in old.kind().value_uses() {
       data_mut!(self, v).used_by.remove(&value);
     }
@@ -114,6 +140,25 @@ impl DataFlowGraph {
     self.values.insert(value, data);
   }
 
+  /// Replaces all uses of the given value with the new value.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the given values do not exist, or any user of the given
+  /// value is not a local value.
+  pub(crate) fn replace_uses_with(&mut self, value: Value, new: Value) {
+    let users: Vec<_> = data!(self, value).used_by.iter().copied().collect();
+    for user in users {
+      let mut data = self.value(user).clone();
+      for v in data.kind_mut().value_uses_mut() {
+        if *v == value {
+          *v = new;
+        }
+      }
+      self.replace_value_with_data(user, data);
+    }
+  }
+
   /// Removes the given value. Returns the corresponding value data.
   ///
   /// # Panics
@@ -155,6 +200,12 @@ impl DataFlowGraph {
     self.values.get(&value).expect("`value` does not exist")
   }
 
+  /// Returns a reference to the given local value, or `None` if the
+  /// value does not exist, for example, it has been removed.
+  pub fn try_value(&self, value: Value) -> Option<&ValueData> {
+    self.values.get(&value)
+  }
+
   /// Returns a reference to the value map.
   pub fn values(&self) -> &HashMap<Value, ValueData> {
     &self.values
@@ -183,13 +234,21 @@ impl DataFlowGraph {
       (Aggregate(l), Aggregate(r)) => return_if!(l.elems().len() != r.elems().len()),
       (FuncArgRef(l), FuncArgRef(r)) => return_if!(l.index() != r.index()),
       (BlockArgRef(l), BlockArgRef(r)) => return_if!(l.index() != r.index()),
-      (Alloc(_), Alloc(_)) => return true,
-      (GlobalAlloc(_), GlobalAlloc(_)) => (),
+      (Alloc(l), Alloc(r)) => return l.align() == r.align(),
+      (GlobalAlloc(l), GlobalAlloc(r)) => {
+        return_if!(l.align() != r.align() || l.attrs() != r.attrs())
+      }
       (Load(_), Load(_)) => (),
       (Store(_), Store(_)) => (),
       (GetPtr(_), GetPtr(_)) => (),
       (GetElemPtr(_), GetElemPtr(_)) => (),
-      (Binary(l), Binary(r)) => return_if!(l.op() != r.op()),
+      (Binary(l), Binary(r)) => return_if!(l.op() != r.op() || l.flags() != r.flags()),
+      (Unary(l), Unary(r)) => return_if!(l.op() != r.op()),
+      (CheckedBinary(l), CheckedBinary(r)) => return_if!(l.op() != r.op()),
+      (ExtractValue(l), ExtractValue(r)) => return_if!(l.index() != r.index()),
+      (InsertValue(l), InsertValue(r)) => return_if!(l.index() != r.index()),
+      (Copy(_), Copy(_)) => (),
+      (Assume(_), Assume(_)) => (),
       (Branch(l), Branch(r)) => {
         return_if!(
           l.true_bb() != r.true_bb()
@@ -205,6 +264,7 @@ impl DataFlowGraph {
         return_if!(l.callee() != r.callee() || l.args().len() != r.args().len())
       }
       (Return(l), Return(r)) => return_if!(l.value().xor(r.value()).is_some()),
+      (Unreachable(_), Unreachable(_)) => return true,
       _ => return false,
     }
     for (lu, ru) in lhs.kind().value_uses().zip(rhs.kind().value_uses()) {
@@ -286,7 +346,7 @@ impl DataFlowGraph {
 #[cfg(test)]
 mod test {
   use crate::ir::builder_traits::*;
-  use crate::ir::{BinaryOp, FunctionData, Program, Type};
+  use crate::ir::{values, BinaryOp, FunctionData, Program, Type};
 
   #[test]
   fn value_eq() {
@@ -314,4 +374,53 @@ mod test {
     let sub2 = func.dfg_mut().new_value().binary(BinaryOp::Sub, add2, int2);
     assert!(!func.dfg().value_eq(sub1, sub2));
   }
+
+  #[test]
+  fn value_constructors() {
+    let mut program = Program::new();
+    let func = program.new_func(FunctionData::new("@test".into(), vec![], Type::get_unit()));
+    let func = program.func_mut(func);
+    let data = values::integer(1);
+    assert_eq!(data.ty(), &Type::get_i32());
+    let one = func.dfg_mut().new_value().raw(data);
+    let data = values::alloc(Type::get_i32());
+    assert_eq!(data.ty(), &Type::get_pointer(Type::get_i32()));
+    let ptr = func.dfg_mut().new_value().raw(data);
+    let data = values::load(func.dfg(), ptr);
+    assert_eq!(data.ty(), &Type::get_i32());
+    let data = values::binary(func.dfg(), BinaryOp::Add, one, one);
+    assert_eq!(data.ty(), &Type::get_i32());
+    let add = func.dfg_mut().new_value().raw(data);
+    assert_eq!(values::store(func.dfg(), add, ptr).ty(), &Type::get_unit());
+    let data = values::checked_binary(func.dfg(), BinaryOp::Mul, add, one);
+    assert_eq!(data.ty(), &Type::get_array(Type::get_i32(), 2));
+    let checked = func.dfg_mut().new_value().raw(data);
+    let data = values::extract_value(func.dfg(), checked, 1);
+    assert_eq!(data.ty(), &Type::get_i32());
+    assert!(values::ret(func.dfg(), Some(add)).ty().is_unit());
+  }
+
+  #[test]
+  fn replace_keeps_users() {
+    let mut program = Program::new();
+    let func = program.new_func(FunctionData::new(
+      "@test".into(),
+      vec![Type::get_i32()],
+      Type::get_unit(),
+    ));
+    let func = program.func_mut(func);
+    let a = func.params()[0];
+    let add = func.dfg_mut().new_value().binary(BinaryOp::Add, a, a);
+    let mul = func.dfg_mut().new_value().binary(BinaryOp::Mul, add, add);
+    // the new data has no users, but `%mul` still uses the value
+    func
+      .dfg_mut()
+      .replace_value_with(add)
+      .binary(BinaryOp::Sub, a, a);
+    assert!(func.dfg().value(add).used_by().contains(&mul));
+    // so uses of the value can still be found and replaced
+    func.dfg_mut().replace_uses_with(add, a);
+    assert!(func.dfg().value(add).used_by().is_empty());
+    assert!(func.dfg().value(a).used_by().contains(&mul));
+  }
 }
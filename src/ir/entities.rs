@@ -2,15 +2,23 @@
 //! ([`Function`], [`FunctionData`]), basic blocks ([`BasicBlock`],
 //! [`BasicBlockData`]) and values ([`Value`], [`ValueData`]).
 
-use crate::ir::builder::GlobalBuilder;
+use crate::ir::analysis::Liveness;
+use crate::ir::builder::{EntityInfoQuerier, GlobalBuilder, LocalBuilder};
+use crate::ir::builder_traits::{BasicBlockBuilder, LocalInstBuilder};
 use crate::ir::dfg::DataFlowGraph;
+use crate::ir::hash;
 use crate::ir::idman::{is_global_id, next_func_id, next_global_value_id};
 use crate::ir::idman::{BasicBlockId, FunctionId, ValueId};
-use crate::ir::layout::Layout;
-use crate::ir::types::Type;
+use crate::ir::layout::{BlockInsts, Layout};
+use crate::ir::span::SourceSpan;
+use crate::ir::stats::Stats;
+use crate::ir::types::{NamedTypes, Type, TypeKind};
 use crate::ir::values;
-use std::cell::{Ref, RefCell};
+use crate::ir::verifier::{self, UseListError, VerifyError, VerifyReport};
+use std::cell::{Cell, OnceCell, Ref, RefCell};
 use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::iter::Rev;
 use std::rc::{Rc, Weak};
 
 /// A Koopa IR program.
@@ -23,6 +31,8 @@ pub struct Program {
   funcs: HashMap<Function, FunctionData>,
   func_tys: Rc<RefCell<HashMap<Function, Type>>>,
   func_layout: Vec<Function>,
+  named_types: NamedTypes,
+  spans: HashMap<Value, SourceSpan>,
 }
 
 /// Returns a mutable reference to the global value data by the given
@@ -89,6 +99,71 @@ impl Program {
     data
   }
 
+  /// Removes all global values that are not used by any other values
+  /// (including values in functions), except global allocations.
+  ///
+  /// Values will be removed repeatedly until no more values can be removed.
+  pub fn gc_values(&mut self) {
+    loop {
+      let unused: Vec<_> = self
+        .values
+        .borrow()
+        .iter()
+        .filter(|(_, d)| d.used_by.is_empty() && !d.kind().is_global_alloc())
+        .map(|(v, _)| *v)
+        .collect();
+      if unused.is_empty() {
+        break;
+      }
+      for value in unused {
+        self.remove_value(value);
+      }
+    }
+  }
+
+  /// Replaces all uses of the given global value with the new global
+  /// value, including uses by values in functions.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the given values do not exist.
+  pub fn replace_value_uses(&mut self, value: Value, new: Value) {
+    assert!(
+      self.values.borrow().contains_key(&new),
+      "`new` does not exist"
+    );
+    let users = std::mem::take(&mut data_mut!(self, value).used_by);
+    for user in users {
+      let replace = |data: &mut ValueData| {
+        for v in data.kind_mut().value_uses_mut() {
+          if *v == value {
+            *v = new;
+          }
+        }
+      };
+      let global_user = self
+        .values
+        .borrow_mut()
+        .get_mut(&user)
+        .map(replace)
+        .is_some();
+      if global_user {
+        data_mut!(self, new).used_by.insert(user);
+        continue;
+      }
+      let func = self
+        .funcs
+        .values_mut()
+        .find(|f| f.dfg().values().contains_key(&user))
+        .expect("user of `value` does not exist");
+      let mut data = func.dfg().value(user).clone();
+      replace(&mut data);
+      // the old uses have been taken, add them back for the replacement
+      data_mut!(self, value).used_by.insert(user);
+      func.dfg_mut().replace_value_with_data(user, data);
+    }
+  }
+
   /// Sets the name of the given global value.
   ///
   /// # Panics
@@ -104,6 +179,146 @@ impl Program {
       .set_name(name);
   }
 
+  /// Sets the attributes of the given global allocation.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the given value does not exist, or it is not a global
+  /// allocation.
+  pub fn set_global_attrs(&mut self, value: Value, attrs: values::GlobalAttrs) {
+    match data_mut!(self, value).kind_mut() {
+      ValueKind::GlobalAlloc(ga) => ga.set_attrs(attrs),
+      _ => panic!("`value` is not a global allocation"),
+    }
+  }
+
+  /// Prepends the given prefix to the names of all named global values
+  /// and all function definitions, for example, `@x` will be renamed
+  /// to `@mod_x` with prefix `mod_`. Function declarations are not
+  /// renamed.
+  ///
+  /// Returns the first new name that collides with another name as
+  /// error, in this case nothing will be renamed. New names are checked
+  /// against each other, against the names of function declarations,
+  /// and against the names of all local values and basic blocks.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the given prefix contains characters other than
+  /// alphanumerics and `_`.
+  pub fn prefix_names(&mut self, prefix: &str) -> Result<(), String> {
+    let mut values = self.values.borrow_mut();
+    let new_values: Vec<_> = values
+      .iter()
+      .filter_map(|(v, d)| d.name().as_ref().map(|n| (*v, add_name_prefix(n, prefix))))
+      .collect();
+    let new_funcs: Vec<_> = self
+      .funcs
+      .iter()
+      .filter(|(_, d)| d.layout().entry_bb().is_some())
+      .map(|(f, d)| (*f, add_name_prefix(d.name(), prefix)))
+      .collect();
+    // check for collisions
+    let mut taken: HashSet<&str> = self
+      .funcs
+      .values()
+      .filter(|d| d.layout().entry_bb().is_none())
+      .map(|d| d.name())
+      .collect();
+    for data in self.funcs.values() {
+      let dfg = data.dfg();
+      taken.extend(dfg.values().values().filter_map(|d| d.name().as_deref()));
+      taken.extend(dfg.bbs().values().filter_map(|d| d.name().as_deref()));
+    }
+    let new_names = new_values
+      .iter()
+      .map(|(_, n)| n)
+      .chain(new_funcs.iter().map(|(_, n)| n));
+    if let Some(name) = find_name_collision(new_names, taken) {
+      return Err(name);
+    }
+    // perform renaming
+    for (value, name) in new_values {
+      values.get_mut(&value).unwrap().set_name(Some(name));
+    }
+    drop(values);
+    for (func, name) in new_funcs {
+      self.func_mut(func).set_name(name);
+    }
+    Ok(())
+  }
+
+  /// Renames the given function.
+  ///
+  /// Calls refer to functions by handles, so all references to the
+  /// function are updated automatically, and generators will print the
+  /// new name everywhere.
+  ///
+  /// Returns an error if the new name is not a valid global name, or it
+  /// has already been used by another function or value, in this case
+  /// nothing will be renamed.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the given function does not exist.
+  pub fn rename_function(&mut self, func: Function, new_name: &str) -> Result<(), RenameError> {
+    if self.func(func).name() != new_name {
+      self.check_new_name(new_name)?;
+      self.func_mut(func).set_name(new_name.into());
+    }
+    Ok(())
+  }
+
+  /// Renames the given global value.
+  ///
+  /// Returns an error if the new name is not a valid global name, or it
+  /// has already been used by another function or value, in this case
+  /// nothing will be renamed.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the given value does not exist.
+  pub fn rename_global(&mut self, value: Value, new_name: &str) -> Result<(), RenameError> {
+    if self.borrow_value(value).name().as_deref() != Some(new_name) {
+      self.check_new_name(new_name)?;
+      data_mut!(self, value).set_name(Some(new_name.into()));
+    }
+    Ok(())
+  }
+
+  /// Checks if the given name can be used as a new name of a function
+  /// or a global value.
+  ///
+  /// The name must be a valid global symbol, that is, `@` followed by
+  /// one or more letters, digits or underscores.
+  fn check_new_name(&self, name: &str) -> Result<(), RenameError> {
+    let valid = name
+      .strip_prefix('@')
+      .is_some_and(|s| !s.is_empty() && s.chars().all(|c| c.is_alphanumeric() || c == '_'));
+    if !valid {
+      return Err(RenameError::InvalidName(name.into()));
+    }
+    let used_by_global = (self.values.borrow().values()).any(|d| d.name().as_deref() == Some(name));
+    let used_by_func = self.funcs.values().any(|d| {
+      d.name() == name || (d.dfg().values().values()).any(|d| d.name().as_deref() == Some(name))
+    });
+    if used_by_global || used_by_func {
+      Err(RenameError::NameCollision(name.into()))
+    } else {
+      Ok(())
+    }
+  }
+
+  /// Returns a reference to the definitions of named types.
+  pub fn named_types(&self) -> &NamedTypes {
+    &self.named_types
+  }
+
+  /// Returns a mutable reference to the definitions of named types.
+  pub fn named_types_mut(&mut self) -> &mut NamedTypes {
+    &mut self.named_types
+  }
+
   /// Immutably borrows the global value map.
   pub fn borrow_values(&self) -> Ref<HashMap<Value, ValueData>> {
     self.values.borrow()
@@ -125,6 +340,39 @@ impl Program {
     })
   }
 
+  /// Immutably borrows the global value data by the given value handle,
+  /// returns `None` if the value does not exist, for example, it has
+  /// been removed.
+  pub fn try_value(&self, value: Value) -> Option<Ref<'_, ValueData>> {
+    Ref::filter_map(self.values.borrow(), |m| m.get(&value)).ok()
+  }
+
+  /// Returns the source span of the given value.
+  ///
+  /// Global values are looked up in the current program, and local
+  /// values are looked up in all functions. Returns `None` if no span
+  /// is recorded for the value.
+  pub fn span(&self, value: Value) -> Option<&SourceSpan> {
+    if value.is_global() {
+      self.spans.get(&value)
+    } else {
+      self.funcs.values().find_map(|f| f.span(value))
+    }
+  }
+
+  /// Sets the source span of the given global value.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the given value is not a global value.
+  pub fn set_span(&mut self, value: Value, span: Option<SourceSpan>) {
+    assert!(value.is_global(), "`value` is not a global value");
+    match span {
+      Some(span) => self.spans.insert(value, span),
+      None => self.spans.remove(&value),
+    };
+  }
+
   /// Creates a new function in the current program.
   pub fn new_func(&mut self, mut data: FunctionData) -> Function {
     let func = Function(next_func_id());
@@ -147,6 +395,55 @@ impl Program {
     self.funcs.remove(&func)
   }
 
+  /// Removes parameters at the given indices from the given function,
+  /// and the corresponding arguments from all calls to the function.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the given function does not exist, or any of the removed
+  /// parameters is still used.
+  pub fn remove_params(&mut self, func: Function, indices: &[usize]) {
+    let data = self.funcs.get_mut(&func).expect("`func` does not exist");
+    let (params_ty, ret_ty) = match data.ty.kind() {
+      TypeKind::Function(params_ty, ret_ty) => (params_ty, ret_ty.clone()),
+      _ => unreachable!(),
+    };
+    let params_ty = params_ty.iter().enumerate();
+    let params_ty = params_ty.filter(|(i, _)| !indices.contains(i));
+    data.ty = Type::get_function(params_ty.map(|(_, ty)| ty.clone()).collect(), ret_ty);
+    self.func_tys.borrow_mut().insert(func, data.ty.clone());
+    // update parameters, function declarations have no parameter values
+    let params = std::mem::take(&mut data.params);
+    for (i, param) in params.into_iter().enumerate() {
+      if indices.contains(&i) {
+        data.dfg_mut().remove_value(param);
+        data.spans.remove(&param);
+      } else {
+        let mut arg = data.dfg.value(param).clone();
+        if let ValueKind::FuncArgRef(arg) = arg.kind_mut() {
+          *arg.index_mut() = data.params.len();
+        }
+        data.dfg_mut().replace_value_with_data(param, arg);
+        data.params.push(param);
+      }
+    }
+    // update call sites
+    for data in self.funcs.values_mut() {
+      let calls: Vec<_> = data
+        .values_of_kind(|k| matches!(k, ValueKind::Call(c) if c.callee() == func))
+        .collect();
+      for call in calls {
+        let mut call_data = data.dfg.value(call).clone();
+        if let ValueKind::Call(c) = call_data.kind_mut() {
+          let args = std::mem::take(c.args_mut()).into_iter().enumerate();
+          let args = args.filter(|(i, _)| !indices.contains(i));
+          *c.args_mut() = args.map(|(_, arg)| arg).collect();
+        }
+        data.dfg_mut().replace_value_with_data(call, call_data);
+      }
+    }
+  }
+
   /// Returns a reference to the function map.
   pub fn funcs(&self) -> &HashMap<Function, FunctionData> {
     &self.funcs
@@ -162,6 +459,16 @@ impl Program {
     &self.func_layout
   }
 
+  /// Returns all functions in reverse topological order of the call
+  /// graph, so that callees come before their callers.
+  ///
+  /// Mutually recursive functions are emitted adjacently, in function
+  /// layout order. See [`CallGraph`](crate::ir::analysis::CallGraph).
+  pub fn functions_in_topological_order(&self) -> impl Iterator<Item = Function> {
+    let cg = crate::ir::analysis::CallGraph::compute(self);
+    cg.sccs().concat().into_iter()
+  }
+
   /// Returns a reference to the function data by
   /// the given function handle.
   ///
@@ -181,6 +488,76 @@ impl Program {
   pub fn func_mut(&mut self, func: Function) -> &mut FunctionData {
     self.funcs.get_mut(&func).expect("`func` does not exist")
   }
+
+  /// Verifies all global values and functions in the current program.
+  ///
+  /// Returns all found errors if the program is not well-formed.
+  pub fn verify(&self) -> Result<(), Vec<VerifyError>> {
+    verifier::verify_program(self)
+  }
+
+  /// Verifies all global values and functions in the current program,
+  /// and also checks for warnings, see [`verifier::report_program`].
+  pub fn verify_report(&self) -> VerifyReport {
+    verifier::report_program(self)
+  }
+
+  /// Checks if the `used_by` sets of all values and basic blocks in the
+  /// current program match the operands of all values.
+  ///
+  /// This is a debugging tool for passes that update values directly,
+  /// see [`verifier::audit_use_lists`].
+  pub fn audit_use_lists(&self) -> Result<(), Vec<UseListError>> {
+    verifier::audit_use_lists(self)
+  }
+
+  /// Returns `true` if the given instruction of the given function has
+  /// side effects.
+  ///
+  /// Same as [`ValueKind::has_side_effects`], except that calls to pure
+  /// functions (see [`FunctionData::is_pure`]) have no side effects.
+  /// This only means that unused calls to pure functions can be removed,
+  /// the calls still read memory (see [`ValueKind::may_read_memory`]).
+  pub fn has_side_effects(&self, func: Function, inst: Value) -> bool {
+    match self.func(func).dfg().value(inst).kind() {
+      ValueKind::Call(call) => !self.func(call.callee()).is_pure(),
+      kind => kind.has_side_effects(),
+    }
+  }
+
+  /// Returns `true` if the given instruction of the given function may
+  /// write to memory.
+  ///
+  /// Same as [`ValueKind::may_write_memory`], except that calls to pure
+  /// functions (see [`FunctionData::is_pure`]) do not write to memory.
+  pub fn may_write_memory(&self, func: Function, inst: Value) -> bool {
+    match self.func(func).dfg().value(inst).kind() {
+      ValueKind::Call(call) => !self.func(call.callee()).is_pure(),
+      kind => kind.may_write_memory(),
+    }
+  }
+
+  /// Returns the content hash of the current program.
+  ///
+  /// The hash covers the structural content of the program, including
+  /// named type definitions, names of global allocations and functions,
+  /// function signatures, the order of basic blocks, and kinds, types
+  /// and operands of values.
+  /// Handle ids and names of local values and basic blocks are not
+  /// hashed, so two programs with the same content hash equal, even if
+  /// they are built separately.
+  ///
+  /// This is a content hash for caching, not a cryptographic hash.
+  pub fn content_hash(&self) -> u64 {
+    hash::hash_program(self)
+  }
+
+  /// Returns statistics of the current program, including numbers of
+  /// functions, basic blocks and instructions, and the total size of
+  /// global allocations.
+  pub fn statistics(&self) -> Stats {
+    Stats::compute(self)
+  }
 }
 
 /// Weak pointer for the `RefCell` of global value map.
@@ -193,6 +570,73 @@ pub(in crate::ir) type GlobalValueMapCell = Weak<RefCell<HashMap<Value, ValueDat
 /// For [`DataFlowGraph`]s in function.
 pub(in crate::ir) type FuncTypeMapCell = Weak<RefCell<HashMap<Function, Type>>>;
 
+/// Error returned by [`Program::rename_function`] and
+/// [`Program::rename_global`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RenameError {
+  /// The new name is not `@` followed by one or more letters, digits
+  /// or underscores.
+  InvalidName(String),
+  /// The new name has already been used by another function or value.
+  NameCollision(String),
+}
+
+impl fmt::Display for RenameError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::InvalidName(name) => write!(f, "invalid global name '{}'", name),
+      Self::NameCollision(name) => write!(f, "name '{}' has already been used", name),
+    }
+  }
+}
+
+/// Inserts the given prefix into the given name after the sigil.
+///
+/// # Panics
+///
+/// Panics if the given prefix contains characters other than
+/// alphanumerics and `_`.
+fn add_name_prefix(name: &str, prefix: &str) -> String {
+  assert!(
+    prefix.chars().all(|c| c.is_alphanumeric() || c == '_'),
+    "invalid name prefix"
+  );
+  format!("{}{}{}", &name[..1], prefix, &name[1..])
+}
+
+/// Returns the first new name that is already taken, or that appears
+/// more than once in the new names.
+fn find_name_collision<'a>(
+  new_names: impl IntoIterator<Item = &'a String>,
+  mut taken: HashSet<&'a str>,
+) -> Option<String> {
+  new_names
+    .into_iter()
+    .find(|n| !taken.insert(n.as_str()))
+    .cloned()
+}
+
+/// Returns new names for entities whose names are already taken by
+/// earlier entities in the given list.
+fn dedup_names<T>(named: Vec<(T, String)>) -> Vec<(T, String)> {
+  let mut taken: HashSet<_> = named.iter().map(|(_, n)| n.clone()).collect();
+  let mut seen = HashSet::new();
+  let mut renamed = Vec::new();
+  for (entity, name) in named {
+    if seen.insert(name.clone()) {
+      continue;
+    }
+    let new_name = (1..)
+      .map(|i| format!("{}_{}", name, i))
+      .find(|n| !taken.contains(n))
+      .unwrap();
+    taken.insert(new_name.clone());
+    seen.insert(new_name.clone());
+    renamed.push((entity, new_name));
+  }
+  renamed
+}
+
 /// A handle of Koopa IR function.
 ///
 /// You can fetch [`FunctionData`] from [`Program`] by using this handle.
@@ -202,12 +646,57 @@ pub struct Function(FunctionId);
 /// Data of Koopa IR function.
 ///
 /// Functions can hold basic blocks.
+///
+/// # Caching
+///
+/// Functions keep a dirty flag, which is cleared by a successful
+/// [`FunctionData::verify`], so that [`FunctionData::verify_cached`]
+/// can skip verifying functions that are not changed since then.
+/// Functions also cache predecessors of basic blocks, which are
+/// computed on the first call of [`FunctionData::preds`], and basic
+/// blocks of basic block parameters, which are computed on the first
+/// call of [`FunctionData::value_bb`].
+///
+/// The following operations mark the function dirty, and invalidate
+/// the cached predecessors and basic blocks of parameters:
+///
+/// * [`FunctionData::dfg_mut`], so all value/basic block builders,
+///   replacers and removals through the data flow graph.
+/// * [`FunctionData::layout_mut`], so all insertions, removals and
+///   reorderings of basic blocks and instructions.
+/// * Other methods that edit the control flow graph, such as
+///   [`FunctionData::split_block`].
+///
+/// Recomputing the predecessors visits all basic blocks and their
+/// terminators. Edits that can not change the control flow graph mark
+/// the function dirty but keep the cached predecessors, and edits of
+/// a single terminator update them in place, without a recomputation:
+///
+/// * [`FunctionData::new_value`], which only creates values.
+/// * [`FunctionData::set_terminator`], which updates predecessors of
+///   the old and the new targets.
+/// * [`FunctionData::replace_inst`], which updates predecessors if
+///   a terminator is replaced.
+///
+/// So passes that query predecessors while editing terminators should
+/// build new values by [`FunctionData::new_value`] rather than through
+/// [`FunctionData::dfg_mut`].
+///
+/// Renaming the function or its values does not affect verification
+/// results, and changes to global values or other functions in the
+/// program are not tracked.
 pub struct FunctionData {
   ty: Type,
   name: String,
   params: Vec<Value>,
   dfg: DataFlowGraph,
   layout: Layout,
+  dirty: Cell<bool>,
+  preds: OnceCell<HashMap<BasicBlock, Vec<BasicBlock>>>,
+  param_bbs: OnceCell<HashMap<Value, BasicBlock>>,
+  spans: HashMap<Value, SourceSpan>,
+  debug_vars: HashMap<Value, DebugVar>,
+  pure: bool,
 }
 
 impl FunctionData {
@@ -233,115 +722,1055 @@ impl FunctionData {
       params,
       dfg,
       layout: Layout::new(),
+      dirty: Cell::new(true),
+      preds: OnceCell::new(),
+      param_bbs: OnceCell::new(),
+      spans: HashMap::new(),
+      debug_vars: HashMap::new(),
+      pure: false,
+    }
+  }
+
+  /// Creates a new function definition with parameter names.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the given name not starts with `%` or `@`, or the given
+  /// type can not construct a valid function type.
+  pub fn with_param_names(name: String, params: Vec<(Option<String>, Type)>, ret_ty: Type) -> Self {
+    use crate::ir::values::FuncArgRef;
+    Self::check_sanity(&name, params.iter().map(|(_, ty)| ty));
+    // create function argument references
+    let mut dfg = DataFlowGraph::new();
+    let (params, params_ty) = params
+      .into_iter()
+      .enumerate()
+      .map(|(i, (n, ty))| {
+        let v = dfg.new_value_data(FuncArgRef::new_data(i, ty.clone()));
+        dfg.set_value_name(v, n);
+        (v, ty)
+      })
+      .unzip();
+    Self {
+      ty: Type::get_function(params_ty, ret_ty),
+      name,
+      params,
+      dfg,
+      layout: Layout::new(),
+      dirty: Cell::new(true),
+      preds: OnceCell::new(),
+      param_bbs: OnceCell::new(),
+      spans: HashMap::new(),
+      debug_vars: HashMap::new(),
+      pure: false,
+    }
+  }
+
+  /// Creates a new function declaration.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the given name not starts with `%` or `@`, or the given
+  /// type can not construct a valid function type.
+  pub fn new_decl(name: String, params_ty: Vec<Type>, ret_ty: Type) -> Self {
+    Self::check_sanity(&name, params_ty.iter());
+    Self {
+      ty: Type::get_function(params_ty, ret_ty),
+      name,
+      params: Vec::new(),
+      dfg: DataFlowGraph::new(),
+      layout: Layout::new(),
+      dirty: Cell::new(true),
+      preds: OnceCell::new(),
+      param_bbs: OnceCell::new(),
+      spans: HashMap::new(),
+      debug_vars: HashMap::new(),
+      pure: false,
+    }
+  }
+
+  /// Checks if the given name and type is valid.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the given name and type is invalid.
+  fn check_sanity<'a, T>(name: &str, mut params: T)
+  where
+    T: Iterator<Item = &'a Type>,
+  {
+    assert!(
+      name.len() > 1 && (name.starts_with('%') || name.starts_with('@')),
+      "invalid function name"
+    );
+    assert!(
+      params.all(|p| !p.is_unit()),
+      "parameter type must not be `unit`!"
+    );
+  }
+
+  /// Returns a reference to the function's type.
+  pub fn ty(&self) -> &Type {
+    &self.ty
+  }
+
+  /// Returns a reference to the function's name.
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+
+  /// Sets the function's name.
+  pub fn set_name(&mut self, name: String) {
+    self.name = name;
+  }
+
+  /// Returns `true` if the function is marked as pure.
+  ///
+  /// A pure function does not write to memory other than its own local
+  /// allocations, does not perform I/O, only calls pure functions, and
+  /// always returns to its caller, i.e. it does not loop forever, recurse
+  /// without bound or trap. Functions are not pure by default.
+  ///
+  /// Calls to pure functions may still read memory, so an unused call to
+  /// a pure function can be removed (see [`Program::has_side_effects`]),
+  /// and memory operations can be moved across it as long as they do not
+  /// write to memory the call may read (see [`Program::may_write_memory`]).
+  /// Two calls with the same arguments can not be merged, and a call can
+  /// not be moved across stores, since the results may differ.
+  pub fn is_pure(&self) -> bool {
+    self.pure
+  }
+
+  /// Marks the function as pure or not, see [`FunctionData::is_pure`].
+  ///
+  /// Function declarations can also be marked as pure, for example,
+  /// external functions like `@abs`.
+  ///
+  /// The mark is kept in memory only. Neither the text form nor the
+  /// binary form of Koopa IR records it, so it is lost after a program
+  /// is dumped and parsed again, and passes like
+  /// [`InferPurity`](crate::opt::InferPurity) must be run again.
+  pub fn set_pure(&mut self, pure: bool) {
+    self.pure = pure;
+  }
+
+  /// Returns a reference to the function parameters.
+  pub fn params(&self) -> &[Value] {
+    &self.params
+  }
+
+  /// Returns indices of function parameters that are never used.
+  ///
+  /// Returns an empty vector for function declarations.
+  pub fn unused_params(&self) -> Vec<usize> {
+    let params = self.params.iter().enumerate();
+    let unused = params.filter(|(_, p)| self.dfg.value(**p).used_by().is_empty());
+    unused.map(|(i, _)| i).collect()
+  }
+
+  /// Returns a reference to the data flow graph.
+  pub fn dfg(&self) -> &DataFlowGraph {
+    &self.dfg
+  }
+
+  /// Returns a mutable reference to the data flow graph.
+  ///
+  /// Marks the function dirty.
+  pub fn dfg_mut(&mut self) -> &mut DataFlowGraph {
+    self.invalidate();
+    &mut self.dfg
+  }
+
+  /// Returns a [`LocalBuilder`] for building a new local value in the
+  /// data flow graph.
+  ///
+  /// Marks the function dirty, but unlike [`FunctionData::dfg_mut`],
+  /// keeps the cached predecessors.
+  pub fn new_value(&mut self) -> LocalBuilder<'_> {
+    self.dirty.set(true);
+    self.dfg.new_value()
+  }
+
+  /// Returns a reference to the layout.
+  pub fn layout(&self) -> &Layout {
+    &self.layout
+  }
+
+  /// Returns a mutable reference to the layout.
+  ///
+  /// Marks the function dirty.
+  pub fn layout_mut(&mut self) -> &mut Layout {
+    self.invalidate();
+    &mut self.layout
+  }
+
+  /// Returns the source span of the given value in the current
+  /// function, or `None` if no span is recorded.
+  ///
+  /// Spans are recorded by the front end if
+  /// [`BuildOptions::keep_spans`](crate::front::builder::BuildOptions::keep_spans)
+  /// is set, and are not updated when values are removed or replaced.
+  pub fn span(&self, value: Value) -> Option<&SourceSpan> {
+    self.spans.get(&value)
+  }
+
+  /// Sets the source span of the given value in the current function.
+  pub fn set_span(&mut self, value: Value, span: Option<SourceSpan>) {
+    match span {
+      Some(span) => self.spans.insert(value, span),
+      None => self.spans.remove(&value),
+    };
+  }
+
+  /// Returns the source variable of the given `alloc` instruction in
+  /// the current function, or `None` if no variable is recorded.
+  ///
+  /// Variables are recorded by the front end from `//@var` metadata
+  /// comments if
+  /// [`BuildOptions::keep_debug_vars`](crate::front::builder::BuildOptions::keep_debug_vars)
+  /// is set, and are not updated when values are removed or replaced.
+  pub fn debug_var(&self, value: Value) -> Option<&DebugVar> {
+    self.debug_vars.get(&value)
+  }
+
+  /// Sets the source variable of the given `alloc` instruction in the
+  /// current function.
+  pub fn set_debug_var(&mut self, value: Value, var: Option<DebugVar>) {
+    match var {
+      Some(var) => self.debug_vars.insert(value, var),
+      None => self.debug_vars.remove(&value),
+    };
+  }
+
+  /// Verifies the current function.
+  ///
+  /// Returns all found errors if the function is not well-formed.
+  /// Clears the dirty flag if the function is well-formed.
+  pub fn verify(&self) -> Result<(), Vec<VerifyError>> {
+    let result = verifier::verify_func(self);
+    if result.is_ok() {
+      self.dirty.set(false);
+    }
+    result
+  }
+
+  /// Verifies the current function, and also checks for warnings,
+  /// see [`verifier::report_func`].
+  pub fn verify_report(&self) -> VerifyReport {
+    verifier::report_func(self)
+  }
+
+  /// Verifies the current function if it is changed since the last
+  /// successful verification, otherwise returns `Ok(())` immediately.
+  ///
+  /// See [`FunctionData`] for operations that mark the function dirty.
+  pub fn verify_cached(&self) -> Result<(), Vec<VerifyError>> {
+    if self.dirty.get() {
+      self.verify()
+    } else {
+      Ok(())
+    }
+  }
+
+  /// Returns `true` if the function may be changed since the last
+  /// successful verification.
+  pub fn is_dirty(&self) -> bool {
+    self.dirty.get()
+  }
+
+  /// Marks the function dirty, and invalidates cached predecessors and
+  /// basic blocks of parameters.
+  fn invalidate(&mut self) {
+    self.dirty.set(true);
+    self.preds.take();
+    self.param_bbs.take();
+  }
+
+  /// Returns predecessors of the given basic block in layout order.
+  ///
+  /// Predecessors are basic blocks in the layout whose terminators
+  /// target the given basic block, each predecessor appears only once.
+  /// The result is cached until the function is changed, see
+  /// [`FunctionData`] for operations that invalidate the cache.
+  pub fn preds(&self, bb: BasicBlock) -> &[BasicBlock] {
+    let preds = self.preds.get_or_init(|| {
+      let mut preds: HashMap<_, Vec<_>> = HashMap::new();
+      for &pred in self.layout.bbs().keys() {
+        for target in self.targets(pred) {
+          preds.entry(target).or_default().push(pred);
+        }
+      }
+      preds
+    });
+    preds.get(&bb).map_or(&[], |p| p.as_slice())
+  }
+
+  /// Returns targets of the terminator of the given basic block.
+  fn targets(&self, bb: BasicBlock) -> HashSet<BasicBlock> {
+    (self.terminator(bb).into_iter())
+      .flat_map(|t| self.dfg.value(t).kind().bb_uses())
+      .collect()
+  }
+
+  /// Updates the cached predecessors (if any) after the terminator of
+  /// the given basic block is changed, `old` is the targets of the old
+  /// terminator.
+  fn update_preds(&mut self, bb: BasicBlock, old: HashSet<BasicBlock>) {
+    let new = self.targets(bb);
+    let preds = match self.preds.get_mut() {
+      Some(preds) => preds,
+      None => return,
+    };
+    for target in old.difference(&new) {
+      if let Some(preds) = preds.get_mut(target) {
+        preds.retain(|p| *p != bb);
+      }
+    }
+    for &target in new.difference(&old) {
+      // keep predecessors in layout order
+      let target_preds = preds.entry(target).or_default();
+      let index = (self.layout.bbs().keys())
+        .take_while(|b| **b != bb)
+        .filter(|b| target_preds.contains(b))
+        .count();
+      target_preds.insert(index, bb);
+    }
+  }
+
+  /// Verifies if all incoming edges of basic blocks in the current
+  /// function pass arguments which match the basic block parameters.
+  pub fn verify_block_params(&self) -> Result<(), Vec<VerifyError>> {
+    verifier::verify_block_params(self)
+  }
+
+  /// Prepends the given prefix to the names of all named values and
+  /// basic blocks in the current function, for example, `%x` will be
+  /// renamed to `%inl_x` with prefix `inl_`. The name of the function
+  /// itself is not changed.
+  ///
+  /// Returns the first new name that collides with another name as
+  /// error, in this case nothing will be renamed. New value names are
+  /// checked against each other and against the names of global values,
+  /// new basic block names are checked against each other. Function
+  /// names are in a separate namespace, so they can not collide with
+  /// local names.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the given prefix contains characters other than
+  /// alphanumerics and `_`.
+  pub fn prefix_names(&mut self, prefix: &str) -> Result<(), String> {
+    let new_values: Vec<_> = self
+      .dfg
+      .values()
+      .iter()
+      .filter_map(|(v, d)| d.name().as_ref().map(|n| (*v, add_name_prefix(n, prefix))))
+      .collect();
+    let new_bbs: Vec<_> = self
+      .dfg
+      .bbs()
+      .iter()
+      .filter_map(|(b, d)| d.name().as_ref().map(|n| (*b, add_name_prefix(n, prefix))))
+      .collect();
+    // check for collisions of values
+    let globals = self.dfg.globals.upgrade();
+    let globals = globals.as_ref().map(|g| g.borrow());
+    let taken = globals
+      .iter()
+      .flat_map(|g| g.values().filter_map(|d| d.name().as_deref()))
+      .collect();
+    if let Some(name) = find_name_collision(new_values.iter().map(|(_, n)| n), taken) {
+      return Err(name);
+    }
+    drop(globals);
+    // check for collisions of basic blocks
+    let new_names = new_bbs.iter().map(|(_, n)| n);
+    if let Some(name) = find_name_collision(new_names, HashSet::new()) {
+      return Err(name);
+    }
+    // perform renaming
+    for (value, name) in new_values {
+      self.dfg.set_value_name(value, Some(name));
+    }
+    for (bb, name) in new_bbs {
+      self.dfg.bb_mut(bb).set_name(Some(name));
+    }
+    Ok(())
+  }
+
+  /// Renames values and basic blocks in the current function whose
+  /// names are already taken by earlier ones, by appending the first
+  /// suffix `_1`, `_2`, ... that makes the name unique.
+  ///
+  /// Values and basic blocks have separate namespaces. Function
+  /// parameters come first, then basic block parameters and
+  /// instructions in layout order, then values and basic blocks not
+  /// in the layout. After this, [`FunctionData::verify_unique_names`]
+  /// always succeeds.
+  pub fn dedup_names(&mut self) {
+    let (values, bbs) = self.named_in_order();
+    let values: Vec<_> = values
+      .into_iter()
+      .map(|v| (v, self.dfg.value(v).name().clone().unwrap()))
+      .collect();
+    let bbs: Vec<_> = bbs
+      .into_iter()
+      .map(|b| (b, self.dfg.bb(b).name().clone().unwrap()))
+      .collect();
+    for (value, name) in dedup_names(values) {
+      self.dfg.set_value_name(value, Some(name));
+    }
+    for (bb, name) in dedup_names(bbs) {
+      self.dfg.bb_mut(bb).set_name(Some(name));
+    }
+  }
+
+  /// Verifies if all named values and all named basic blocks in the
+  /// current function have distinct names.
+  ///
+  /// This is not checked by [`FunctionData::verify`], since duplicate
+  /// names are renamed by generators. Use [`FunctionData::dedup_names`]
+  /// to fix the reported errors.
+  pub fn verify_unique_names(&self) -> Result<(), Vec<VerifyError>> {
+    verifier::verify_unique_names(self)
+  }
+
+  /// Returns named values and named basic blocks in the current
+  /// function, in the order described in [`FunctionData::dedup_names`].
+  pub(in crate::ir) fn named_in_order(&self) -> (Vec<Value>, Vec<BasicBlock>) {
+    let mut values = self.params.clone();
+    let mut bbs = Vec::new();
+    for &bb in self.layout.bbs().keys() {
+      bbs.push(bb);
+      values.extend(self.dfg.bb(bb).params());
+      values.extend(self.layout.block_insts(bb));
+    }
+    // values and basic blocks not in the layout
+    let in_layout: HashSet<_> = values.iter().copied().collect();
+    let mut rest: Vec<_> = (self.dfg.values().keys())
+      .filter(|v| !in_layout.contains(v))
+      .copied()
+      .collect();
+    rest.sort_unstable_by_key(|v| v.0);
+    values.extend(rest);
+    let mut rest: Vec<_> = (self.dfg.bbs().keys())
+      .filter(|b| self.layout.bbs().node(b).is_none())
+      .copied()
+      .collect();
+    rest.sort_unstable_by_key(|b| b.0);
+    bbs.extend(rest);
+    values.retain(|v| self.dfg.value(*v).name().is_some());
+    bbs.retain(|b| self.dfg.bb(*b).name().is_some());
+    (values, bbs)
+  }
+
+  /// Returns an iterator of all instructions in the layout whose kind
+  /// satisfies the given predicate, in layout order.
+  pub fn values_of_kind<'a, P>(&'a self, pred: P) -> impl Iterator<Item = Value> + 'a
+  where
+    P: Fn(&ValueKind) -> bool + 'a,
+  {
+    self
+      .layout
+      .bbs()
+      .nodes()
+      .flat_map(|node| node.insts().keys())
+      .copied()
+      .filter(move |v| pred(self.dfg.value(*v).kind()))
+  }
+
+  /// Returns an iterator of all `alloc` instructions in the layout.
+  pub fn allocs(&self) -> impl Iterator<Item = Value> + '_ {
+    self.values_of_kind(|k| matches!(k, ValueKind::Alloc(_)))
+  }
+
+  /// Returns values live across the given `call` instruction, which
+  /// are live both right before and right after the call, see
+  /// [`Liveness::live_across`].
+  ///
+  /// The liveness of the function is computed on every call of this
+  /// method, use [`Liveness`] directly to query multiple calls.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the given value is not a `call` instruction in the
+  /// layout of the current function.
+  pub fn values_live_across(&self, call: Value) -> Vec<Value> {
+    Liveness::compute(self).live_across(self, call)
+  }
+
+  /// Returns an iterator of all `call` instructions in the layout.
+  pub fn calls(&self) -> impl Iterator<Item = Value> + '_ {
+    self.values_of_kind(|k| matches!(k, ValueKind::Call(_)))
+  }
+
+  /// Returns an iterator of all `store` instructions in the layout.
+  pub fn stores(&self) -> impl Iterator<Item = Value> + '_ {
+    self.values_of_kind(|k| matches!(k, ValueKind::Store(_)))
+  }
+
+  /// Returns an iterator over the instructions in the given basic block
+  /// in reverse order, from the terminator to the first instruction.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the given basic block is not in the layout.
+  pub fn block_insts_rev(&self, bb: BasicBlock) -> Rev<BlockInsts<'_>> {
+    self.layout.block_insts(bb).rev()
+  }
+
+  /// Returns the basic block in the layout where the given value is
+  /// defined.
+  ///
+  /// For instructions, returns the basic block that contains the
+  /// instruction. For basic block parameters, returns the basic block
+  /// that holds the parameter. Returns `None` for other values (like
+  /// constants and function parameters), and values not in the layout.
+  ///
+  /// Basic blocks of parameters are cached until the function is
+  /// changed, see [`FunctionData`] for operations that invalidate the
+  /// cache.
+  pub fn value_bb(&self, value: Value) -> Option<BasicBlock> {
+    match self.dfg.values().get(&value)?.kind() {
+      ValueKind::BlockArgRef(_) => {
+        let param_bbs = self.param_bbs.get_or_init(|| {
+          let bbs = self.layout.bbs().keys();
+          bbs
+            .flat_map(|&bb| self.dfg.bb(bb).params().iter().map(move |p| (*p, bb)))
+            .collect()
+        });
+        param_bbs.get(&value).copied()
+      }
+      _ => self.layout.parent_bb(value),
+    }
+  }
+
+  /// Returns the terminator of the given basic block.
+  ///
+  /// Returns `None` if the basic block is not in the layout, or the last
+  /// instruction of the basic block is not a terminator.
+  pub fn terminator(&self, bb: BasicBlock) -> Option<Value> {
+    let last = *self.layout.bbs().node(&bb)?.insts().back_key()?;
+    self.dfg.value(last).kind().is_terminator().then_some(last)
+  }
+
+  /// Sets the terminator of the given basic block.
+  ///
+  /// The old terminator (if exists) will be removed from both the layout
+  /// and the data flow graph, and the given terminator will be appended
+  /// to the end of the basic block.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the given basic block is not in the layout, the given
+  /// value is not a terminator or is already in the layout, or the old
+  /// terminator is still used by other values.
+  pub fn set_terminator(&mut self, bb: BasicBlock, inst: Value) {
+    assert!(
+      self.dfg.value(inst).kind().is_terminator(),
+      "`inst` is not a terminator"
+    );
+    self.dirty.set(true);
+    let targets = self.targets(bb);
+    if let Some(old) = self.terminator(bb) {
+      self.layout.bb_mut(bb).insts_mut().remove(&old);
+      self.dfg.remove_value(old);
+    }
+    self
+      .layout
+      .bb_mut(bb)
+      .insts_mut()
+      .push_key_back(inst)
+      .expect("`inst` is already in the layout");
+    self.update_preds(bb, targets);
+  }
+
+  /// Replaces the given instruction in the given basic block with the
+  /// new instruction, which takes the position of the old one in the
+  /// layout.
+  ///
+  /// All uses of the old instruction are redirected to the new one, and
+  /// then the old instruction is removed from both the layout and the
+  /// data flow graph.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the old instruction is not in the given basic block, the
+  /// new instruction is already in the layout or uses the old one, or
+  /// their types are not compatible (see
+  /// [`Type::structurally_compatible`]).
+  pub fn replace_inst(&mut self, bb: BasicBlock, old: Value, new: Value) {
+    assert_eq!(
+      self.layout.parent_bb(old),
+      Some(bb),
+      "`old` is not in basic block `bb`"
+    );
+    assert!(
+      self.layout.parent_bb(new).is_none(),
+      "`new` is already in the layout"
+    );
+    let old_data = self.dfg.value(old);
+    assert!(!old_data.used_by().contains(&new), "`new` uses `old`");
+    assert!(
+      old_data
+        .ty()
+        .structurally_compatible(self.dfg.value(new).ty()),
+      "type mismatch between `old` and `new`"
+    );
+    self.dirty.set(true);
+    let targets = self.targets(bb);
+    let insts = self.layout.bb_mut(bb).insts_mut();
+    insts.cursor_mut(old).insert_key_before(new).unwrap();
+    insts.remove(&old);
+    self.dfg.replace_uses_with(old, new);
+    self.dfg.remove_value(old);
+    self.update_preds(bb, targets);
+  }
+
+  /// Removes the given unused instruction from both the layout and the
+  /// data flow graph, and then removes its constant operands that are
+  /// no longer used. Returns the data of the removed instruction.
+  ///
+  /// Marks the function dirty.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the given instruction is not in the layout, or is still
+  /// used by other values.
+  pub fn remove_inst(&mut self, inst: Value) -> ValueData {
+    let bb = self
+      .layout
+      .parent_bb(inst)
+      .expect("`inst` is not in the layout");
+    self.invalidate();
+    self.layout.bb_mut(bb).insts_mut().remove(&inst);
+    let removed = self.dfg.remove_value(inst);
+    for v in removed.kind().value_uses() {
+      if (self.dfg.values().get(&v)).is_some_and(|v| v.kind().is_const() && v.used_by().is_empty())
+      {
+        self.dfg.remove_value(v);
+      }
+    }
+    removed
+  }
+
+  /// Splits the given basic block into two at the given instruction.
+  ///
+  /// The given instruction and all instructions after it (including the
+  /// terminator) are moved to a new basic block, which is placed after
+  /// the given basic block in the layout. A `jump` to the new basic
+  /// block is then appended to the given basic block. Returns the new
+  /// basic block.
+  ///
+  /// Successors of the given basic block become successors of the new
+  /// basic block, arguments passed to them are not changed.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the given instruction is not in the given basic block.
+  pub fn split_block(&mut self, bb: BasicBlock, at: Value) -> BasicBlock {
+    assert_eq!(
+      self.layout.parent_bb(at),
+      Some(bb),
+      "`at` is not in basic block `bb`"
+    );
+    self.invalidate();
+    let tail = self.dfg.new_bb().basic_block(None);
+    let bbs = self.layout.bbs_mut();
+    bbs.cursor_mut(bb).insert_key_after(tail).unwrap();
+    // move instructions to the new basic block
+    let insts = self.layout.bbs().node(&bb).unwrap().insts();
+    let moved: Vec<_> = insts.keys().skip_while(|i| **i != at).copied().collect();
+    for inst in moved {
+      self.layout.bb_mut(bb).insts_mut().remove(&inst);
+      self
+        .layout
+        .bb_mut(tail)
+        .insts_mut()
+        .push_key_back(inst)
+        .unwrap();
+    }
+    let jump = self.dfg.new_value().jump(tail);
+    self
+      .layout
+      .bb_mut(bb)
+      .insts_mut()
+      .push_key_back(jump)
+      .unwrap();
+    tail
+  }
+
+  /// Calls the given closure on every instruction in the layout, in
+  /// layout order, with a mutable reference to the data flow graph, the
+  /// basic block of the instruction and the instruction itself.
+  ///
+  /// The layout order is snapshotted before the first call, so the
+  /// closure is not called on instructions created during iteration.
+  /// The closure can change instructions in place, for example by
+  /// [`DataFlowGraph::replace_value_with`].
+  ///
+  /// If the closure removes an instruction from the data flow graph by
+  /// [`DataFlowGraph::remove_value`], including the current one, the
+  /// instruction is also removed from the layout after the closure
+  /// returns, and the closure will not be called on it later.
+  ///
+  /// Marks the function dirty.
+  pub fn rewrite_insts<F>(&mut self, mut f: F)
+  where
+    F: FnMut(&mut DataFlowGraph, BasicBlock, Value),
+  {
+    let insts: Vec<_> = (self.layout.bbs().iter())
+      .flat_map(|(&bb, node)| node.insts().keys().map(move |&inst| (bb, inst)))
+      .collect();
+    self.invalidate();
+    for &(bb, inst) in &insts {
+      if self.dfg.values().contains_key(&inst) {
+        f(&mut self.dfg, bb, inst);
+      }
+    }
+    // remove instructions that are removed from the data flow graph
+    for (bb, inst) in insts {
+      if !self.dfg.values().contains_key(&inst) {
+        self.layout.bb_mut(bb).insts_mut().remove(&inst);
+      }
+    }
+  }
+
+  /// Replaces kinds of instructions in the current function in place,
+  /// in layout order.
+  ///
+  /// The closure is called on the kind of each instruction, and returns
+  /// the new kind, or `None` to keep the instruction unchanged. Replaced
+  /// instructions keep their handles, types, names and positions in the
+  /// layout, and use lists are updated if operands are changed. Operand
+  /// types of new kinds are not checked, see [`FunctionData::verify`].
+  ///
+  /// Marks the function dirty.
+  ///
+  /// # Panics
+  ///
+  /// Panics if a new kind is not a local instruction, or its result type
+  /// does not match the type of the instruction.
+  pub fn map_kinds<F>(&mut self, mut f: F)
+  where
+    F: FnMut(&ValueKind) -> Option<ValueKind>,
+  {
+    let insts: Vec<_> = (self.layout.bbs().values())
+      .flat_map(|node| node.insts().keys().copied())
+      .collect();
+    self.invalidate();
+    for inst in insts {
+      let data = self.dfg.value(inst);
+      let kind = match f(data.kind()) {
+        Some(kind) => kind,
+        None => continue,
+      };
+      assert!(kind.is_local_inst(), "expected a local instruction");
+      match self.kind_ty(&kind) {
+        Some(ty) => assert!(
+          ty.structurally_compatible(data.ty()),
+          "result type of the new kind does not match"
+        ),
+        None => assert!(
+          matches!(data.ty().kind(), TypeKind::Pointer(..)),
+          "result type of the new kind does not match"
+        ),
+      }
+      let mut data = data.clone();
+      *data.kind_mut() = kind;
+      self.dfg.replace_value_with_data(inst, data);
     }
   }
 
-  /// Creates a new function definition with parameter names.
+  /// Returns the result type of the given local instruction kind, or
+  /// `None` for local allocations, whose types can not be inferred from
+  /// their kinds.
+  fn kind_ty(&self, kind: &ValueKind) -> Option<Type> {
+    let ty = |value| self.dfg.value_type(value);
+    let base = |ty: Type| match ty.kind() {
+      TypeKind::Pointer(base) | TypeKind::Array(base, _) => base.clone(),
+      _ => panic!("invalid operand type"),
+    };
+    Some(match kind {
+      ValueKind::Alloc(_) => return None,
+      ValueKind::Load(v) => base(ty(v.src())),
+      ValueKind::GetPtr(v) => ty(v.src()),
+      ValueKind::GetElemPtr(v) => Type::get_pointer(base(base(ty(v.src())))),
+      ValueKind::Binary(v) => ty(v.lhs()),
+      ValueKind::Unary(v) => v
+        .op()
+        .result_ty(&ty(v.opr()))
+        .expect("invalid operand type"),
+      ValueKind::CheckedBinary(v) => Type::get_array(ty(v.lhs()), 2),
+      ValueKind::ExtractValue(v) => base(ty(v.agg())),
+      ValueKind::InsertValue(v) => ty(v.agg()),
+      ValueKind::Copy(v) => ty(v.src()),
+      ValueKind::Call(v) => match self.dfg.func_type(v.callee()).kind() {
+        TypeKind::Function(_, ret) => ret.clone(),
+        _ => panic!("invalid function type"),
+      },
+      // stores, assumptions and terminators
+      _ => Type::get_unit(),
+    })
+  }
+
+  /// Duplicates the given basic block, including its parameters and
+  /// instructions, and places the copy after it in the layout. Returns
+  /// the new basic block.
+  ///
+  /// Instructions are copied by [`DataFlowGraph::clone_value`] in order.
+  /// Mappings from parameters and instructions of the given basic block
+  /// to their copies are inserted into `value_map`, and are used to
+  /// replace operands of later copies, so operands defined in other
+  /// basic blocks can be remapped by populating `value_map` in advance,
+  /// or patched by the caller afterwards. Targets of the terminator are
+  /// not changed. Names are not copied.
   ///
   /// # Panics
   ///
-  /// Panics if the given name not starts with `%` or `@`, or the given
-  /// type can not construct a valid function type.
-  pub fn with_param_names(name: String, params: Vec<(Option<String>, Type)>, ret_ty: Type) -> Self {
-    use crate::ir::values::FuncArgRef;
-    Self::check_sanity(&name, params.iter().map(|(_, ty)| ty));
-    // create function argument references
-    let mut dfg = DataFlowGraph::new();
-    let (params, params_ty) = params
-      .into_iter()
-      .enumerate()
-      .map(|(i, (n, ty))| {
-        let v = dfg.new_value_data(FuncArgRef::new_data(i, ty.clone()));
-        dfg.set_value_name(v, n);
-        (v, ty)
-      })
-      .unzip();
-    Self {
-      ty: Type::get_function(params_ty, ret_ty),
-      name,
-      params,
-      dfg,
-      layout: Layout::new(),
+  /// Panics if the given basic block is not in the layout.
+  pub fn clone_block(
+    &mut self,
+    bb: BasicBlock,
+    value_map: &mut HashMap<Value, Value>,
+  ) -> BasicBlock {
+    let insts: Vec<_> = self.layout.block_insts(bb).collect();
+    self.invalidate();
+    // create the new basic block with parameters
+    let params = self.dfg.bb(bb).params().to_vec();
+    let tys = (params.iter())
+      .map(|p| self.dfg.value(*p).ty().clone())
+      .collect();
+    let new_bb = self.dfg.new_bb().basic_block_with_params(None, tys);
+    let new_params = self.dfg.bb(new_bb).params().to_vec();
+    value_map.extend(params.into_iter().zip(new_params));
+    let bbs = self.layout.bbs_mut();
+    bbs.cursor_mut(bb).insert_key_after(new_bb).unwrap();
+    // copy instructions
+    for inst in insts {
+      let new = self.dfg.clone_value(inst, value_map);
+      value_map.insert(inst, new);
+      let insts = self.layout.bb_mut(new_bb).insts_mut();
+      insts.push_key_back(new).unwrap();
     }
+    new_bb
   }
 
-  /// Creates a new function declaration.
+  /// Returns all edges of the control flow graph, in layout order of
+  /// their source basic blocks.
+  ///
+  /// Edges of the same terminator are returned in the order they appear
+  /// in the terminator. A terminator may have multiple edges to the same
+  /// target basic block, for example `br %0, %a(1), %a(2)`.
+  pub fn cfg_edges(&self) -> impl Iterator<Item = CfgEdge> + '_ {
+    self.layout.bbs().keys().flat_map(move |&from| {
+      let kind = self.terminator(from).map(|t| self.dfg.value(t).kind());
+      let edges = match kind {
+        Some(ValueKind::Branch(br)) => vec![
+          (EdgeKind::True, br.true_bb(), br.true_args()),
+          (EdgeKind::False, br.false_bb(), br.false_args()),
+        ],
+        Some(ValueKind::Jump(jump)) => {
+          vec![(EdgeKind::Unconditional, jump.target(), jump.args())]
+        }
+        Some(ValueKind::Switch(switch)) => {
+          let default = (
+            EdgeKind::SwitchDefault,
+            switch.default_bb(),
+            switch.default_args(),
+          );
+          let cases = (switch.cases().iter())
+            .map(|(v, bb, args)| (EdgeKind::SwitchCase(*v), *bb, args.as_slice()));
+          std::iter::once(default).chain(cases).collect()
+        }
+        _ => vec![],
+      };
+      edges.into_iter().map(move |(kind, to, args)| CfgEdge {
+        from,
+        to,
+        args: args.to_vec(),
+        kind,
+      })
+    })
+  }
+
+  /// Splits the given edge by inserting a new basic block on it.
+  ///
+  /// The new basic block is placed after the source basic block in the
+  /// layout, it has no parameters and contains only a `jump` to the
+  /// target basic block, which passes the arguments of the edge. The
+  /// edge in the terminator of the source basic block is redirected to
+  /// the new basic block without arguments. Returns the new basic block.
   ///
   /// # Panics
   ///
-  /// Panics if the given name not starts with `%` or `@`, or the given
-  /// type can not construct a valid function type.
-  pub fn new_decl(name: String, params_ty: Vec<Type>, ret_ty: Type) -> Self {
-    Self::check_sanity(&name, params_ty.iter());
-    Self {
-      ty: Type::get_function(params_ty, ret_ty),
-      name,
-      params: Vec::new(),
-      dfg: DataFlowGraph::new(),
-      layout: Layout::new(),
-    }
+  /// Panics if the terminator of the source basic block does not have
+  /// the given edge.
+  pub fn split_edge(&mut self, edge: &CfgEdge) -> BasicBlock {
+    let term = self
+      .terminator(edge.from)
+      .expect("source basic block has no terminator");
+    let mut data = self.dfg.value(term).clone();
+    let mid = self.dfg.new_bb().basic_block(None);
+    let slot = match (data.kind_mut(), edge.kind) {
+      (ValueKind::Branch(br), EdgeKind::True) if br.true_bb() == edge.to => {
+        br.true_args_mut().clear();
+        br.true_bb_mut()
+      }
+      (ValueKind::Branch(br), EdgeKind::False) if br.false_bb() == edge.to => {
+        br.false_args_mut().clear();
+        br.false_bb_mut()
+      }
+      (ValueKind::Jump(jump), EdgeKind::Unconditional) if jump.target() == edge.to => {
+        jump.args_mut().clear();
+        jump.target_mut()
+      }
+      (ValueKind::Switch(switch), EdgeKind::SwitchDefault) if switch.default_bb() == edge.to => {
+        switch.default_args_mut().clear();
+        switch.default_bb_mut()
+      }
+      (ValueKind::Switch(switch), EdgeKind::SwitchCase(value)) => {
+        let case = (switch.cases_mut().iter_mut())
+          .find(|(v, bb, _)| *v == value && *bb == edge.to)
+          .expect("terminator does not have the given edge");
+        case.2.clear();
+        &mut case.1
+      }
+      _ => panic!("terminator does not have the given edge"),
+    };
+    *slot = mid;
+    self.invalidate();
+    self.dfg.replace_value_with_data(term, data);
+    let jump = (self.dfg.new_value()).jump_with_args(edge.to, edge.args.clone());
+    let bbs = self.layout.bbs_mut();
+    bbs.cursor_mut(edge.from).insert_key_after(mid).unwrap();
+    self
+      .layout
+      .bb_mut(mid)
+      .insts_mut()
+      .push_key_back(jump)
+      .unwrap();
+    mid
   }
 
-  /// Checks if the given name and type is valid.
+  /// Checks if edges from basic block `from` to basic block `to` are
+  /// critical, that is, `from` has multiple outgoing edges and `to` has
+  /// multiple incoming edges.
   ///
-  /// # Panics
+  /// Edges are counted as in [`FunctionData::cfg_edges`], so a terminator
+  /// like `br %0, %a(1), %a(2)` has two edges to `%a`. Returns `false`
+  /// if there is no edge from `from` to `to`.
+  pub fn is_critical_edge(&self, from: BasicBlock, to: BasicBlock) -> bool {
+    let (mut outs, mut ins, mut found) = (0, 0, false);
+    for edge in self.cfg_edges() {
+      outs += (edge.from == from) as usize;
+      ins += (edge.to == to) as usize;
+      found |= edge.from == from && edge.to == to;
+    }
+    found && outs > 1 && ins > 1
+  }
+
+  /// Splits all critical edges (see [`FunctionData::is_critical_edge`])
+  /// by [`FunctionData::split_edge`].
   ///
-  /// Panics if the given name and type is invalid.
-  fn check_sanity<'a, T>(name: &str, mut params: T)
-  where
-    T: Iterator<Item = &'a Type>,
-  {
-    assert!(
-      name.len() > 1 && (name.starts_with('%') || name.starts_with('@')),
-      "invalid function name"
-    );
-    assert!(
-      params.all(|p| !p.is_unit()),
-      "parameter type must not be `unit`!"
-    );
+  /// Arguments of each critical edge are moved to the `jump` in the new
+  /// basic block, so after splitting, basic blocks with parameters only
+  /// have incoming edges from basic blocks with a single successor.
+  pub fn split_critical_edges(&mut self) {
+    let edges: Vec<_> = self.cfg_edges().collect();
+    let mut outs: HashMap<_, usize> = HashMap::new();
+    let mut ins: HashMap<_, usize> = HashMap::new();
+    for edge in &edges {
+      *outs.entry(edge.from).or_default() += 1;
+      *ins.entry(edge.to).or_default() += 1;
+    }
+    for edge in edges {
+      if outs[&edge.from] > 1 && ins[&edge.to] > 1 {
+        self.split_edge(&edge);
+      }
+    }
   }
+}
 
-  /// Returns a reference to the function's type.
-  pub fn ty(&self) -> &Type {
-    &self.ty
+/// Debug information of a source-level variable, which is stored in
+/// an `alloc` instruction, see [`FunctionData::debug_var`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DebugVar {
+  name: String,
+  ty: Type,
+  line: u32,
+}
+
+impl DebugVar {
+  /// Creates a new source variable with the given name, type, and the
+  /// line number where it is declared.
+  pub fn new(name: String, ty: Type, line: u32) -> Self {
+    Self { name, ty, line }
   }
 
-  /// Returns a reference to the function's name.
+  /// Returns the name of the variable in the source language.
   pub fn name(&self) -> &str {
     &self.name
   }
 
-  /// Sets the function's name.
-  pub fn set_name(&mut self, name: String) {
-    self.name = name;
+  /// Returns the type of the variable.
+  pub fn ty(&self) -> &Type {
+    &self.ty
   }
 
-  /// Returns a reference to the function parameters.
-  pub fn params(&self) -> &[Value] {
-    &self.params
+  /// Returns the line number where the variable is declared.
+  pub fn line(&self) -> u32 {
+    self.line
   }
+}
 
-  /// Returns a reference to the data flow graph.
-  pub fn dfg(&self) -> &DataFlowGraph {
-    &self.dfg
+/// An edge of the control flow graph, see [`FunctionData::cfg_edges`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CfgEdge {
+  from: BasicBlock,
+  to: BasicBlock,
+  args: Vec<Value>,
+  kind: EdgeKind,
+}
+
+impl CfgEdge {
+  /// Returns the source basic block of the edge.
+  pub fn from(&self) -> BasicBlock {
+    self.from
   }
 
-  /// Returns a mutable reference to the data flow graph.
-  pub fn dfg_mut(&mut self) -> &mut DataFlowGraph {
-    &mut self.dfg
+  /// Returns the target basic block of the edge.
+  pub fn to(&self) -> BasicBlock {
+    self.to
   }
 
-  /// Returns a reference to the layout.
-  pub fn layout(&self) -> &Layout {
-    &self.layout
+  /// Returns the arguments passed to the target basic block along
+  /// the edge.
+  pub fn args(&self) -> &[Value] {
+    &self.args
   }
 
-  /// Returns a mutable reference to the layout.
-  pub fn layout_mut(&mut self) -> &mut Layout {
-    &mut self.layout
+  /// Returns the kind of the edge.
+  pub fn kind(&self) -> EdgeKind {
+    self.kind
   }
 }
 
+/// Kind of control flow edges, which indicates the part of the
+/// terminator that the edge comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EdgeKind {
+  /// The true target of `br`.
+  True,
+  /// The false target of `br`.
+  False,
+  /// The target of `jump`.
+  Unconditional,
+  /// The default target of `switch`.
+  SwitchDefault,
+  /// The target of a case of `switch` with the given value.
+  SwitchCase(i32),
+}
+
 /// A handle of Koopa IR basic block.
 ///
 /// You can fetch [`BasicBlockData`] from [`DataFlowGraph`] in
@@ -460,8 +1889,9 @@ impl ValueData {
   /// Panics if the given name (if exists) not starts with `%` or `@`.
   pub(in crate::ir) fn set_name(&mut self, name: Option<String>) {
     assert!(
-      name.as_ref().map_or(true, |n| n.len() > 1
-        && (n.starts_with('%') || n.starts_with('@'))),
+      name
+        .as_ref()
+        .is_none_or(|n| n.len() > 1 && (n.starts_with('%') || n.starts_with('@'))),
       "invalid value name"
     );
     self.name = name;
@@ -524,14 +1954,30 @@ pub enum ValueKind {
   GetElemPtr(values::GetElemPtr),
   /// Binary operation.
   Binary(values::Binary),
+  /// Unary operation.
+  Unary(values::Unary),
+  /// Overflow-checked binary operation.
+  CheckedBinary(values::CheckedBinary),
+  /// Element extraction from an aggregate value.
+  ExtractValue(values::ExtractValue),
+  /// Element insertion into an aggregate value.
+  InsertValue(values::InsertValue),
+  /// Copy of a value.
+  Copy(values::CopyValue),
+  /// Assumption.
+  Assume(values::Assume),
   /// Conditional branch.
   Branch(values::Branch),
   /// Unconditional jump.
   Jump(values::Jump),
+  /// Multi-way branch.
+  Switch(values::Switch),
   /// Function call.
   Call(values::Call),
   /// Function return.
   Return(values::Return),
+  /// Unreachable.
+  Unreachable(values::Unreachable),
 }
 
 impl ValueKind {
@@ -551,6 +1997,31 @@ impl ValueKind {
     }
   }
 
+  /// Returns mutable references to all values that used by the `ValueKind`.
+  pub(crate) fn value_uses_mut(&mut self) -> Vec<&mut Value> {
+    match self {
+      ValueKind::Aggregate(v) => v.elems_mut().iter_mut().collect(),
+      ValueKind::GlobalAlloc(v) => vec![v.init_mut()],
+      ValueKind::Load(v) => vec![v.src_mut()],
+      ValueKind::Store(v) => v.uses_mut(),
+      ValueKind::GetPtr(v) => v.uses_mut(),
+      ValueKind::GetElemPtr(v) => v.uses_mut(),
+      ValueKind::Binary(v) => v.uses_mut(),
+      ValueKind::Unary(v) => vec![v.opr_mut()],
+      ValueKind::CheckedBinary(v) => v.uses_mut(),
+      ValueKind::ExtractValue(v) => v.uses_mut(),
+      ValueKind::InsertValue(v) => v.uses_mut(),
+      ValueKind::Copy(v) => vec![v.src_mut()],
+      ValueKind::Assume(v) => vec![v.cond_mut()],
+      ValueKind::Branch(v) => v.uses_mut(),
+      ValueKind::Switch(v) => v.uses_mut(),
+      ValueKind::Jump(v) => v.args_mut().iter_mut().collect(),
+      ValueKind::Call(v) => v.args_mut().iter_mut().collect(),
+      ValueKind::Return(v) => v.value_mut().iter_mut().collect(),
+      _ => Vec::new(),
+    }
+  }
+
   /// Returns `true` if the `ValueKind` represents a constant value.
   pub fn is_const(&self) -> bool {
     matches!(
@@ -567,6 +2038,46 @@ impl ValueKind {
     matches!(self, ValueKind::GlobalAlloc(..))
   }
 
+  /// Returns `true` if the `ValueKind` represents a terminator, i.e. an
+  /// instruction that must be placed at the end of a basic block.
+  pub fn is_terminator(&self) -> bool {
+    matches!(
+      self,
+      ValueKind::Branch(..)
+        | ValueKind::Jump(..)
+        | ValueKind::Switch(..)
+        | ValueKind::Return(..)
+        | ValueKind::Unreachable(..)
+    )
+  }
+
+  /// Returns `true` if the `ValueKind` represents an instruction with
+  /// side effects, which can not be removed even if its result is unused.
+  ///
+  /// Stores, calls, assumptions and terminators have side effects.
+  /// Calls are treated conservatively, since the callee is unknown here,
+  /// see [`Program::has_side_effects`] for calls to pure functions.
+  pub fn has_side_effects(&self) -> bool {
+    matches!(
+      self,
+      ValueKind::Store(..) | ValueKind::Call(..) | ValueKind::Assume(..)
+    ) || self.is_terminator()
+  }
+
+  /// Returns `true` if the `ValueKind` represents an instruction that
+  /// may read memory, i.e. loads and calls.
+  pub fn may_read_memory(&self) -> bool {
+    matches!(self, ValueKind::Load(..) | ValueKind::Call(..))
+  }
+
+  /// Returns `true` if the `ValueKind` represents an instruction that
+  /// may write to memory, i.e. stores and calls.
+  ///
+  /// See [`Program::may_write_memory`] for calls to pure functions.
+  pub fn may_write_memory(&self) -> bool {
+    matches!(self, ValueKind::Store(..) | ValueKind::Call(..))
+  }
+
   /// Returns `true` if the `ValueKind` represents a local instruction.
   pub fn is_local_inst(&self) -> bool {
     matches!(
@@ -577,10 +2088,18 @@ impl ValueKind {
         | ValueKind::GetPtr(..)
         | ValueKind::GetElemPtr(..)
         | ValueKind::Binary(..)
+        | ValueKind::Unary(..)
+        | ValueKind::CheckedBinary(..)
+        | ValueKind::ExtractValue(..)
+        | ValueKind::InsertValue(..)
+        | ValueKind::Copy(..)
+        | ValueKind::Assume(..)
         | ValueKind::Branch(..)
         | ValueKind::Jump(..)
+        | ValueKind::Switch(..)
         | ValueKind::Call(..)
         | ValueKind::Return(..)
+        | ValueKind::Unreachable(..)
     )
   }
 }
@@ -629,6 +2148,12 @@ impl<'a> Iterator for ValueUses<'a> {
       ValueKind::GetPtr(v) => field_use!(v.src(), v.index()),
       ValueKind::GetElemPtr(v) => field_use!(v.src(), v.index()),
       ValueKind::Binary(v) => field_use!(v.lhs(), v.rhs()),
+      ValueKind::Unary(v) => field_use!(v.opr()),
+      ValueKind::CheckedBinary(v) => field_use!(v.lhs(), v.rhs()),
+      ValueKind::ExtractValue(v) => field_use!(v.agg()),
+      ValueKind::InsertValue(v) => field_use!(v.agg(), v.value()),
+      ValueKind::Copy(v) => field_use!(v.src()),
+      ValueKind::Assume(v) => field_use!(v.cond()),
       ValueKind::Branch(v) => {
         let tlen = v.true_args().len();
         if cur == 0 {
@@ -642,6 +2167,20 @@ impl<'a> Iterator for ValueUses<'a> {
         }
       }
       ValueKind::Jump(v) => vec_use!(v.args()),
+      ValueKind::Switch(v) => {
+        if cur == 0 {
+          return Some(v.cond());
+        }
+        let mut index = cur - 1;
+        let args = std::iter::once(v.default_args()).chain(v.cases().iter().map(|c| &c.2[..]));
+        for args in args {
+          if index < args.len() {
+            return Some(args[index]);
+          }
+          index -= args.len();
+        }
+        None
+      }
       ValueKind::Call(v) => vec_use!(v.args()),
       ValueKind::Return(v) => match cur {
         0 => v.value(),
@@ -674,7 +2213,918 @@ impl<'a> Iterator for BasicBlockUses<'a> {
         0 => Some(jump.target()),
         _ => None,
       },
+      ValueKind::Switch(switch) => match cur {
+        0 => Some(switch.default_bb()),
+        _ => switch.cases().get(cur - 1).map(|c| c.1),
+      },
       _ => None,
     }
   }
 }
+
+#[cfg(test)]
+mod test {
+  use crate::back::KoopaGenerator;
+  use crate::front::Driver;
+  use crate::ir::builder_traits::*;
+  use crate::ir::entities::{EdgeKind, RenameError};
+  use crate::ir::{BinaryOp, FunctionData, Program, Type, ValueKind};
+  use std::collections::HashMap;
+
+  #[test]
+  fn gc_values() {
+    let mut program = Program::new();
+    // used by a global allocation
+    let init = program.new_value().integer(1);
+    let alloc = program.new_value().global_alloc(init);
+    // used by a local instruction only
+    let local_used = program.new_value().integer(2);
+    let func = program.new_func(FunctionData::new("@f".into(), vec![], Type::get_unit()));
+    let func = program.func_mut(func);
+    func.dfg_mut().new_value().store(local_used, alloc);
+    // unused chain
+    let elem = program.new_value().integer(3);
+    let agg = program.new_value().aggregate(vec![elem, elem]);
+    program.gc_values();
+    let values = program.borrow_values();
+    assert!(values.contains_key(&init));
+    assert!(values.contains_key(&alloc));
+    assert!(values.contains_key(&local_used));
+    assert!(!values.contains_key(&elem));
+    assert!(!values.contains_key(&agg));
+  }
+
+  #[test]
+  fn try_value() {
+    let mut program = Program::new();
+    let one = program.new_value().integer(1);
+    let g = program.new_value().global_alloc(one);
+    let func = program.new_func(FunctionData::new("@f".into(), vec![], Type::get_unit()));
+    let dfg = program.func_mut(func).dfg_mut();
+    let two = dfg.new_value().integer(2);
+    assert!(dfg.try_value(two).is_some_and(|d| d.ty().is_i32()));
+    dfg.remove_value(two);
+    assert!(dfg.try_value(two).is_none());
+    // global values are not in the data flow graph of functions
+    assert!(dfg.try_value(g).is_none());
+    assert!(program.try_value(g).is_some());
+    program.remove_value(g);
+    assert!(program.try_value(g).is_none());
+  }
+
+  #[test]
+  fn values_of_kind() {
+    let mut program = Program::new();
+    let func = program.new_func(FunctionData::new("@f".into(), vec![], Type::get_unit()));
+    let data = program.func_mut(func);
+    let entry = data.dfg_mut().new_bb().basic_block(None);
+    let other = data.dfg_mut().new_bb().basic_block(None);
+    data.layout_mut().bbs_mut().extend([entry, other]);
+    let alloc1 = data.dfg_mut().new_value().alloc(Type::get_i32());
+    let zero = data.dfg_mut().new_value().integer(0);
+    let store = data.dfg_mut().new_value().store(zero, alloc1);
+    let call = data.dfg_mut().new_value().call(func, vec![]);
+    let jump = data.dfg_mut().new_value().jump(other);
+    let insts = data.layout_mut().bb_mut(entry).insts_mut();
+    insts.extend([alloc1, store, call, jump]);
+    let alloc2 = data.dfg_mut().new_value().alloc(Type::get_i32());
+    let ret = data.dfg_mut().new_value().ret(None);
+    data
+      .layout_mut()
+      .bb_mut(other)
+      .insts_mut()
+      .extend([alloc2, ret]);
+    assert_eq!(data.allocs().collect::<Vec<_>>(), [alloc1, alloc2]);
+    assert_eq!(data.calls().collect::<Vec<_>>(), [call]);
+    assert_eq!(data.stores().collect::<Vec<_>>(), [store]);
+  }
+
+  #[test]
+  fn terminator() {
+    let mut program = Program::new();
+    let func = program.new_func(FunctionData::new("@f".into(), vec![], Type::get_unit()));
+    let data = program.func_mut(func);
+    let entry = data.dfg_mut().new_bb().basic_block(None);
+    let body = data
+      .dfg_mut()
+      .new_bb()
+      .basic_block_with_params(None, vec![Type::get_i32()]);
+    data.layout_mut().bbs_mut().extend([entry, body]);
+    let alloc = data.dfg_mut().new_value().alloc(Type::get_i32());
+    data
+      .layout_mut()
+      .bb_mut(entry)
+      .insts_mut()
+      .push_key_back(alloc)
+      .unwrap();
+    assert_eq!(data.terminator(entry), None);
+    // set a new terminator
+    let zero = data.dfg_mut().new_value().integer(0);
+    let jump = data.dfg_mut().new_value().jump_with_args(body, vec![zero]);
+    data.set_terminator(entry, jump);
+    assert_eq!(data.terminator(entry), Some(jump));
+    assert!(data.dfg().bb(body).used_by().contains(&jump));
+    // replace the existing terminator
+    let ret = data.dfg_mut().new_value().ret(None);
+    data.set_terminator(entry, ret);
+    assert_eq!(data.terminator(entry), Some(ret));
+    assert!(data.dfg().bb(body).used_by().is_empty());
+    assert!(data.dfg().value(zero).used_by().is_empty());
+    let insts: Vec<_> = data
+      .layout()
+      .bbs()
+      .node(&entry)
+      .unwrap()
+      .insts()
+      .keys()
+      .copied()
+      .collect();
+    assert_eq!(insts, [alloc, ret]);
+  }
+
+  #[test]
+  fn rename_symbols() {
+    let src = r#"global @x = alloc i32, zeroinit
+
+decl @getint(): i32
+
+fun @foo(@a: i32): i32 {
+%entry:
+  %v = load @x
+  %w = add %v, @a
+  ret %w
+}
+
+fun @main(): i32 {
+%entry:
+  %0 = call @getint()
+  %1 = call @foo(%0)
+  ret %1
+}
+"#;
+    let driver: Driver<_> = src.into();
+    let mut program = driver.generate_program().unwrap();
+    let foo = program.func_layout()[1];
+    let x = *program.inst_layout().first().unwrap();
+    assert_eq!(
+      program.rename_function(foo, "@main"),
+      Err(RenameError::NameCollision("@main".into()))
+    );
+    assert_eq!(
+      program.rename_function(foo, "@x"),
+      Err(RenameError::NameCollision("@x".into()))
+    );
+    assert_eq!(
+      program.rename_function(foo, "@a"),
+      Err(RenameError::NameCollision("@a".into()))
+    );
+    assert_eq!(
+      program.rename_function(foo, "bar"),
+      Err(RenameError::InvalidName("bar".into()))
+    );
+    for name in ["@", "@a b", "@a-b", "@%x", "@x\n"] {
+      assert_eq!(
+        program.rename_function(foo, name),
+        Err(RenameError::InvalidName(name.into()))
+      );
+    }
+    assert_eq!(
+      program.rename_global(x, "@getint"),
+      Err(RenameError::NameCollision("@getint".into()))
+    );
+    assert_eq!(program.rename_function(foo, "@foo"), Ok(()));
+    assert_eq!(program.rename_function(foo, "@bar"), Ok(()));
+    assert_eq!(program.rename_global(x, "@y"), Ok(()));
+    assert_eq!(program.verify(), Ok(()));
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen.generate_on(&program).unwrap();
+    let expected = r#"global @y = alloc i32, zeroinit
+
+decl @getint(): i32
+
+fun @bar(@a: i32): i32 {
+%entry:
+  %v = load @y
+  %w = add %v, @a
+  ret %w
+}
+
+fun @main(): i32 {
+%entry:
+  %0 = call @getint()
+  %1 = call @bar(%0)
+  ret %1
+}
+"#;
+    assert_eq!(std::str::from_utf8(&gen.writer()).unwrap(), expected);
+  }
+
+  #[test]
+  fn prefix_names() {
+    let src = r#"global @x = alloc i32, zeroinit
+
+decl @getint(): i32
+
+fun @f(@a: i32): i32 {
+%entry:
+  %v = add @a, 1
+  jump %end(%v)
+
+%end(%ans: i32):
+  ret %ans
+}
+"#;
+    let driver: Driver<_> = src.into();
+    let mut program = driver.generate_program().unwrap();
+    let func = *program.func_layout().last().unwrap();
+    assert_eq!(program.func_mut(func).prefix_names("inl_"), Ok(()));
+    assert_eq!(program.prefix_names("mod_"), Ok(()));
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen.generate_on(&program).unwrap();
+    let expected = r#"global @mod_x = alloc i32, zeroinit
+
+decl @getint(): i32
+
+fun @mod_f(@inl_a: i32): i32 {
+%inl_entry:
+  %inl_v = add @inl_a, 1
+  jump %inl_end(%inl_v)
+
+%inl_end(%inl_ans: i32):
+  ret %inl_ans
+}
+"#;
+    assert_eq!(std::str::from_utf8(&gen.writer()).unwrap(), expected);
+    // collisions
+    let src = r#"global @inl_a = alloc i32, zeroinit
+
+fun @f(@a: i32, @mod_inl_a: i32) {
+%entry:
+  ret
+}
+"#;
+    let driver: Driver<_> = src.into();
+    let mut program = driver.generate_program().unwrap();
+    let func = *program.func_layout().last().unwrap();
+    assert_eq!(
+      program.func_mut(func).prefix_names("inl_"),
+      Err("@inl_a".into())
+    );
+    assert_eq!(program.prefix_names("mod_"), Err("@mod_inl_a".into()));
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen.generate_on(&program).unwrap();
+    assert_eq!(std::str::from_utf8(&gen.writer()).unwrap(), src);
+    // collisions with function declarations
+    let src = r#"decl @mod_f()
+
+fun @f() {
+%entry:
+  ret
+}
+"#;
+    let driver: Driver<_> = src.into();
+    let mut program = driver.generate_program().unwrap();
+    assert_eq!(program.prefix_names("mod_"), Err("@mod_f".into()));
+    // collisions between basic blocks
+    let mut program = Program::new();
+    let func = program.new_func(FunctionData::new("@f".into(), vec![], Type::get_unit()));
+    let data = program.func_mut(func);
+    let bb1 = data.dfg_mut().new_bb().basic_block(Some("%a".into()));
+    let bb2 = data.dfg_mut().new_bb().basic_block(Some("%a".into()));
+    data.layout_mut().bbs_mut().extend([bb1, bb2]);
+    assert_eq!(data.prefix_names("inl_"), Err("%inl_a".into()));
+    assert_eq!(data.dfg().bb(bb1).name().as_deref(), Some("%a"));
+  }
+
+  #[test]
+  fn value_bb() {
+    let src = r#"fun @f(@a: i32): i32 {
+%entry:
+  %v = add @a, 1
+  jump %end(%v)
+
+%end(%ans: i32):
+  ret %ans
+}
+"#;
+    let driver: Driver<_> = src.into();
+    let mut program = driver.generate_program().unwrap();
+    let data = program.func_mut(*program.func_layout().last().unwrap());
+    let entry = data.layout().entry_bb().unwrap();
+    let end = data.layout().bbs().back_key().copied().unwrap();
+    let jump = data.terminator(entry).unwrap();
+    let v = data
+      .layout()
+      .bbs()
+      .node(&entry)
+      .unwrap()
+      .insts()
+      .front_key()
+      .copied()
+      .unwrap();
+    let ans = data.dfg().bb(end).params()[0];
+    let one = match data.dfg().value(v).kind() {
+      ValueKind::Binary(bin) => bin.rhs(),
+      _ => unreachable!(),
+    };
+    assert_eq!(data.value_bb(v), Some(entry));
+    assert_eq!(data.value_bb(jump), Some(entry));
+    assert_eq!(data.value_bb(ans), Some(end));
+    assert_eq!(data.value_bb(data.params()[0]), None);
+    assert_eq!(data.value_bb(one), None);
+    // cached basic blocks of parameters are invalidated by layout edits
+    data.layout_mut().bbs_mut().remove(&end);
+    assert_eq!(data.value_bb(ans), None);
+  }
+
+  #[test]
+  fn content_hash() {
+    let src = r#"global @g = alloc [i32, 2], {1, 2}
+
+fun @f(@a: i32): i32 {
+%entry:
+  %0 = getelemptr @g, 1
+  %1 = load %0
+  %2 = add @a, %1
+  br %2, %then, %end
+
+%then:
+  jump %end
+
+%end:
+  ret %2
+}
+"#;
+    let hash = |src: &str| {
+      let driver: Driver<_> = src.into();
+      driver.generate_program().unwrap().content_hash()
+    };
+    // handle ids and local names are not hashed
+    assert_eq!(hash(src), hash(src));
+    assert_eq!(hash(src), hash(&src.replace("%then", "%t")));
+    // content changes
+    assert_ne!(hash(src), hash(&src.replace("add", "sub")));
+    assert_ne!(hash(src), hash(&src.replace("{1, 2}", "{1, 3}")));
+    assert_ne!(hash(src), hash(&src.replace("@g, 1", "@g, 0")));
+    assert_ne!(hash(src), hash(&src.replace("ret %2", "ret %1")));
+    assert_ne!(hash(src), hash(&src.replace("@f", "@h")));
+  }
+
+  #[test]
+  fn verify_cached() {
+    let mut program = Program::new();
+    let func = program.new_func(FunctionData::new("@f".into(), vec![], Type::get_unit()));
+    let data = program.func_mut(func);
+    assert!(data.is_dirty());
+    let entry = data.dfg_mut().new_bb().basic_block(None);
+    let ret = data.dfg_mut().new_value().ret(None);
+    data.layout_mut().bbs_mut().push_key_back(entry).unwrap();
+    data.set_terminator(entry, ret);
+    assert_eq!(data.verify_cached(), Ok(()));
+    assert!(!data.is_dirty());
+    // read-only accesses and renaming keep the cache
+    assert_eq!(data.terminator(entry), Some(ret));
+    data.set_name("@g".into());
+    assert!(!data.is_dirty());
+    // mutations mark the function dirty
+    let jump = data.dfg_mut().new_value().jump(entry);
+    assert!(data.is_dirty());
+    assert_eq!(data.verify_cached(), Ok(()));
+    data.set_terminator(entry, jump);
+    assert!(data.is_dirty());
+    assert_eq!(data.verify_cached(), Ok(()));
+    data.layout_mut().bb_mut(entry).insts_mut().clear();
+    assert!(data.verify_cached().is_err());
+    assert!(data.is_dirty());
+  }
+
+  #[test]
+  fn preds() {
+    let driver: Driver<_> = r#"fun @f(@a: i32) {
+%entry:
+  br @a, %then, %end
+
+%then:
+  br @a, %end, %end
+
+%end:
+  ret
+}
+"#
+    .into();
+    let mut program = driver.generate_program().unwrap();
+    let data = program.funcs_mut().values_mut().next().unwrap();
+    let bbs: Vec<_> = data.layout().bbs().keys().copied().collect();
+    let (entry, then, end) = (bbs[0], bbs[1], bbs[2]);
+    assert_eq!(data.preds(entry), &[]);
+    assert_eq!(data.preds(then), &[entry]);
+    assert_eq!(data.preds(end), &[entry, then]);
+    // retarget the terminator of `%then`, predecessors are updated
+    // rather than recomputed
+    let jump = data.new_value().jump(then);
+    data.set_terminator(then, jump);
+    assert!(data.preds.get().is_some());
+    assert_eq!(data.preds(then), &[entry, then]);
+    assert_eq!(data.preds(end), &[entry]);
+    let cond = data.params()[0];
+    let br = data.new_value().branch(cond, end, then);
+    let jump = data.terminator(entry).unwrap();
+    data.replace_inst(entry, jump, br);
+    assert!(data.preds.get().is_some());
+    assert_eq!(data.preds(then), &[entry, then]);
+    assert_eq!(data.preds(end), &[entry]);
+    let jump = data.new_value().jump(end);
+    data.set_terminator(then, jump);
+    assert_eq!(data.preds(then), &[entry]);
+    assert_eq!(data.preds(end), &[entry, then]);
+    // remove `%then` from the layout
+    data.layout_mut().bbs_mut().remove(&then);
+    assert_eq!(data.preds(then), &[entry]);
+  }
+
+  #[test]
+  fn side_effects() {
+    let src = r#"decl @abs(i32): i32
+decl @putint(i32)
+
+fun @f(@p: *i32): i32 {
+%entry:
+  %0 = load @p
+  %1 = add %0, 1
+  store %1, @p
+  %2 = call @abs(%1)
+  call @putint(%2)
+  %3 = getptr @p, 1
+  ret %2
+}
+"#;
+    let driver: Driver<_> = src.into();
+    let mut program = driver.generate_program().unwrap();
+    let funcs = program.func_layout().to_vec();
+    let (abs, func) = (funcs[0], funcs[2]);
+    let layout = program.func(func).layout();
+    let insts: Vec<_> = layout.block_insts(layout.entry_bb().unwrap()).collect();
+    let kinds: Vec<_> = insts
+      .iter()
+      .map(|i| {
+        let kind = program.func(func).dfg().value(*i).kind();
+        (
+          kind.has_side_effects(),
+          kind.may_read_memory(),
+          kind.may_write_memory(),
+        )
+      })
+      .collect();
+    assert_eq!(
+      kinds,
+      [
+        (false, true, false),
+        (false, false, false),
+        (true, false, true),
+        (true, true, true),
+        (true, true, true),
+        (false, false, false),
+        (true, false, false),
+      ]
+    );
+    assert!(program.has_side_effects(func, insts[3]));
+    assert!(program.may_write_memory(func, insts[3]));
+    program.func_mut(abs).set_pure(true);
+    assert!(!program.has_side_effects(func, insts[3]));
+    assert!(!program.may_write_memory(func, insts[3]));
+    assert!(program.has_side_effects(func, insts[4]));
+    assert!(program.has_side_effects(func, insts[2]));
+    assert!(!program.has_side_effects(func, insts[1]));
+  }
+
+  #[test]
+  fn clone_block() {
+    let src = r#"fun @f(@n: i32): i32 {
+%entry:
+  jump %loop(0)
+
+%loop(%i: i32):
+  %0 = add %i, 1
+  %1 = mul %0, @n
+  %2 = lt %1, 100
+  br %2, %loop(%1), %end
+
+%end:
+  ret @n
+}
+"#;
+    let driver: Driver<_> = src.into();
+    let mut program = driver.generate_program().unwrap();
+    let data = program.funcs_mut().values_mut().next().unwrap();
+    let bbs: Vec<_> = data.layout().bbs().keys().copied().collect();
+    let (entry, body) = (bbs[0], bbs[1]);
+    let mut map = HashMap::new();
+    let copy = data.clone_block(body, &mut map);
+    assert_eq!(map.len(), 5);
+    let i = data.dfg().bb(body).params()[0];
+    assert_eq!(map[&i], data.dfg().bb(copy).params()[0]);
+    // unroll the loop once: `%loop` -> copy -> `%loop`
+    let term = data.terminator(body).unwrap();
+    let edges: Vec<_> = data.cfg_edges().filter(|e| e.from() == body).collect();
+    let mid = data.split_edge(&edges[0]);
+    let jump = data.terminator(mid).unwrap();
+    let args = match data.dfg().value(jump).kind() {
+      ValueKind::Jump(j) => j.args().to_vec(),
+      _ => unreachable!(),
+    };
+    let new_jump = data.dfg_mut().new_value().jump_with_args(copy, args);
+    data.set_terminator(mid, new_jump);
+    assert_eq!(data.terminator(body), Some(term));
+    assert_eq!(data.preds(copy), &[mid]);
+    assert_eq!(data.preds(body), &[entry, copy]);
+    assert_eq!(data.verify(), Ok(()));
+    assert_eq!(program.audit_use_lists(), Ok(()));
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen.generate_on(&program).unwrap();
+    assert_eq!(
+      std::str::from_utf8(&gen.writer()).unwrap(),
+      r#"fun @f(@n: i32): i32 {
+%entry:
+  jump %loop(0)
+
+%loop(%i: i32):
+  %0 = add %i, 1
+  %1 = mul %0, @n
+  %2 = lt %1, 100
+  br %2, %3, %end
+
+%3:
+  jump %4(%1)
+
+%4(%5: i32):
+  %6 = add %5, 1
+  %7 = mul %6, @n
+  %8 = lt %7, 100
+  br %8, %loop(%7), %end
+
+%end:
+  ret @n
+}
+"#
+    );
+  }
+
+  #[test]
+  fn cfg_edges() {
+    let src = r#"fun @f(@a: i32): i32 {
+%entry:
+  br @a, %end(1), %end(2)
+
+%end(%x: i32):
+  switch %x, %exit(0), [1: %end(%x), 2: %exit(%x)]
+
+%exit(%y: i32):
+  ret %y
+}
+"#;
+    let driver: Driver<_> = src.into();
+    let mut program = driver.generate_program().unwrap();
+    let data = program.funcs_mut().values_mut().next().unwrap();
+    let bbs: Vec<_> = data.layout().bbs().keys().copied().collect();
+    let (entry, end, exit) = (bbs[0], bbs[1], bbs[2]);
+    let edges: Vec<_> = data.cfg_edges().collect();
+    let kinds: Vec<_> = edges.iter().map(|e| (e.from(), e.to(), e.kind())).collect();
+    assert_eq!(
+      kinds,
+      [
+        (entry, end, EdgeKind::True),
+        (entry, end, EdgeKind::False),
+        (end, exit, EdgeKind::SwitchDefault),
+        (end, end, EdgeKind::SwitchCase(1)),
+        (end, exit, EdgeKind::SwitchCase(2)),
+      ]
+    );
+    let x = data.dfg().bb(end).params()[0];
+    assert_eq!(edges[1].args().len(), 1);
+    assert_eq!(edges[4].args(), &[x]);
+    // split the false edge and the last case
+    let mid1 = data.split_edge(&edges[1]);
+    let mid2 = data.split_edge(&edges[4]);
+    assert_eq!(data.verify(), Ok(()));
+    assert_eq!(program.audit_use_lists(), Ok(()));
+    let data = program.funcs().values().next().unwrap();
+    assert_eq!(data.preds(end), &[entry, mid1, end]);
+    assert_eq!(data.preds(exit), &[end, mid2]);
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen.generate_on(&program).unwrap();
+    assert_eq!(
+      std::str::from_utf8(&gen.writer()).unwrap(),
+      r#"fun @f(@a: i32): i32 {
+%entry:
+  br @a, %end(1), %0
+
+%0:
+  jump %end(2)
+
+%end(%x: i32):
+  switch %x, %exit(0), [1: %end(%x), 2: %1]
+
+%1:
+  jump %exit(%x)
+
+%exit(%y: i32):
+  ret %y
+}
+"#
+    );
+  }
+
+  #[test]
+  fn split_critical_edges() {
+    let src = r#"fun @f(@a: i32): i32 {
+%entry:
+  br @a, %then, %end(0)
+
+%then:
+  jump %end(1)
+
+%end(%x: i32):
+  ret %x
+}
+"#;
+    let driver: Driver<_> = src.into();
+    let mut program = driver.generate_program().unwrap();
+    let data = program.funcs_mut().values_mut().next().unwrap();
+    let bbs: Vec<_> = data.layout().bbs().keys().copied().collect();
+    let (entry, then, end) = (bbs[0], bbs[1], bbs[2]);
+    assert!(data.is_critical_edge(entry, end));
+    assert!(!data.is_critical_edge(entry, then));
+    assert!(!data.is_critical_edge(then, end));
+    assert!(!data.is_critical_edge(then, entry));
+    data.split_critical_edges();
+    assert_eq!(data.verify(), Ok(()));
+    assert!(data
+      .cfg_edges()
+      .all(|e| !data.is_critical_edge(e.from(), e.to())));
+    assert_eq!(program.audit_use_lists(), Ok(()));
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen.generate_on(&program).unwrap();
+    assert_eq!(
+      std::str::from_utf8(&gen.writer()).unwrap(),
+      r#"fun @f(@a: i32): i32 {
+%entry:
+  br @a, %then, %0
+
+%0:
+  jump %end(0)
+
+%then:
+  jump %end(1)
+
+%end(%x: i32):
+  ret %x
+}
+"#
+    );
+  }
+
+  #[test]
+  fn map_kinds() {
+    let driver: Driver<_> = r#"fun @f(@a: i32, @b: i32): i32 {
+%entry:
+  %0 = add @a, @b
+  %1 = mul %0, @b
+  ret %1
+}
+"#
+    .into();
+    let mut program = driver.generate_program().unwrap();
+    let data = program.funcs_mut().values_mut().next().unwrap();
+    let entry = data.layout().entry_bb().unwrap();
+    let insts: Vec<_> = data.layout().block_insts(entry).collect();
+    data.map_kinds(|kind| match kind {
+      ValueKind::Binary(bin) => {
+        let mut bin = bin.clone();
+        match bin.op() {
+          BinaryOp::Add => *bin.op_mut() = BinaryOp::Sub,
+          _ => *bin.lhs_mut() = bin.rhs(),
+        }
+        Some(ValueKind::Binary(bin))
+      }
+      _ => None,
+    });
+    assert_eq!(data.verify(), Ok(()));
+    assert!(data.dfg().value(insts[0]).used_by().is_empty());
+    assert_eq!(data.dfg().value(data.params()[1]).used_by().len(), 2);
+    assert_eq!(program.audit_use_lists(), Ok(()));
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen.generate_on(&program).unwrap();
+    assert_eq!(
+      std::str::from_utf8(&gen.writer()).unwrap(),
+      r#"fun @f(@a: i32, @b: i32): i32 {
+%entry:
+  %0 = sub @a, @b
+  %1 = mul @b, @b
+  ret %1
+}
+"#
+    );
+  }
+
+  #[test]
+  fn rewrite_insts() {
+    let driver: Driver<_> = r#"fun @f(@a: i32): i32 {
+%entry:
+  %0 = add @a, 0
+  %1 = mul @a, 2
+  %2 = sub @a, 1
+  ret %1
+}
+"#
+    .into();
+    let mut program = driver.generate_program().unwrap();
+    let data = program.funcs_mut().values_mut().next().unwrap();
+    let mut visited = 0;
+    data.rewrite_insts(|dfg, _, inst| {
+      visited += 1;
+      let (op, lhs, rhs) = match dfg.value(inst).kind() {
+        ValueKind::Binary(b) => (b.op(), b.lhs(), b.rhs()),
+        _ => return,
+      };
+      if dfg.value(inst).used_by().is_empty() {
+        // remove dead instructions, including the current one
+        dfg.remove_value(inst);
+      } else if op == BinaryOp::Mul {
+        // the new operand is not visited
+        let one = dfg.new_value().integer(1);
+        dfg.replace_value_with(inst).binary(BinaryOp::Shl, lhs, one);
+      } else {
+        return;
+      }
+      // remove the old constant operand
+      if dfg.value(rhs).used_by().is_empty() {
+        dfg.remove_value(rhs);
+      }
+    });
+    assert_eq!(visited, 4);
+    assert_eq!(data.verify(), Ok(()));
+    let mut values = data.dfg().values().values();
+    assert!(values.all(|v| !v.kind().is_const() || !v.used_by().is_empty()));
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen.generate_on(&program).unwrap();
+    assert_eq!(
+      std::str::from_utf8(&gen.writer()).unwrap(),
+      r#"fun @f(@a: i32): i32 {
+%entry:
+  %0 = shl @a, 1
+  ret %0
+}
+"#
+    );
+  }
+
+  #[test]
+  fn replace_inst() {
+    let src = r#"fun @f(@a: i32): i32 {
+%entry:
+  %0 = mul @a, 2
+  %1 = add %0, %0
+  ret %1
+}
+"#;
+    let driver: Driver<_> = src.into();
+    let mut program = driver.generate_program().unwrap();
+    let data = program.funcs_mut().values_mut().next().unwrap();
+    let entry = data.layout().entry_bb().unwrap();
+    let insts: Vec<_> = data.layout().block_insts(entry).collect();
+    let a = data.params()[0];
+    let one = data.dfg_mut().new_value().integer(1);
+    let shl = data.dfg_mut().new_value().binary(BinaryOp::Shl, a, one);
+    data.replace_inst(entry, insts[0], shl);
+    assert!(data.dfg().values().get(&insts[0]).is_none());
+    assert_eq!(data.dfg().value(shl).used_by().len(), 1);
+    assert_eq!(data.value_bb(shl), Some(entry));
+    assert_eq!(data.verify(), Ok(()));
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen.generate_on(&program).unwrap();
+    assert_eq!(
+      std::str::from_utf8(&gen.writer()).unwrap(),
+      r#"fun @f(@a: i32): i32 {
+%entry:
+  %0 = shl @a, 1
+  %1 = add %0, %0
+  ret %1
+}
+"#
+    );
+  }
+
+  #[test]
+  fn remove_inst() {
+    let src = r#"fun @f(@a: i32): i32 {
+%entry:
+  %0 = mul @a, 2
+  %1 = add @a, 2
+  ret %1
+}
+"#;
+    let driver: Driver<_> = src.into();
+    let mut program = driver.generate_program().unwrap();
+    let data = program.funcs_mut().values_mut().next().unwrap();
+    let entry = data.layout().entry_bb().unwrap();
+    let insts: Vec<_> = data.layout().block_insts(entry).collect();
+    let num_values = data.dfg().values().len();
+    // constant `2` of `%0` is removed with it, since it is a separate
+    // value from the one of `%1`
+    let removed = data.remove_inst(insts[0]);
+    assert!(matches!(removed.kind(), ValueKind::Binary(_)));
+    assert_eq!(data.dfg().values().len(), num_values - 2);
+    assert_eq!(data.layout().block_insts(entry).count(), 2);
+    assert_eq!(data.verify(), Ok(()));
+  }
+
+  #[test]
+  fn split_block() {
+    let src = r#"fun @f(@a: i32): i32 {
+%entry:
+  jump %loop
+
+%loop:
+  %0 = add @a, 1
+  %1 = mul %0, 2
+  br %1, %loop, %end
+
+%end:
+  ret %1
+}
+"#;
+    let driver: Driver<_> = src.into();
+    let mut program = driver.generate_program().unwrap();
+    let data = program.funcs_mut().values_mut().next().unwrap();
+    let bbs: Vec<_> = data.layout().bbs().keys().copied().collect();
+    let (entry, head, end) = (bbs[0], bbs[1], bbs[2]);
+    let insts: Vec<_> = data
+      .layout()
+      .bbs()
+      .node(&head)
+      .unwrap()
+      .insts()
+      .keys()
+      .copied()
+      .collect();
+    let tail = data.split_block(head, insts[1]);
+    assert_eq!(data.verify(), Ok(()));
+    assert_eq!(data.preds(tail), &[head]);
+    assert_eq!(data.preds(end), &[tail]);
+    assert_eq!(data.preds(head), &[entry, tail]);
+    assert_eq!(data.value_bb(insts[2]), Some(tail));
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen.generate_on(&program).unwrap();
+    assert_eq!(
+      std::str::from_utf8(&gen.writer()).unwrap(),
+      r#"fun @f(@a: i32): i32 {
+%entry:
+  jump %loop
+
+%loop:
+  %0 = add @a, 1
+  jump %1
+
+%1:
+  %2 = mul %0, 2
+  br %2, %loop, %end
+
+%end:
+  ret %2
+}
+"#
+    );
+  }
+
+  #[test]
+  fn block_insts() {
+    let driver: Driver<_> = r#"fun @f(@a: i32): i32 {
+%entry:
+  %0 = add @a, 1
+  %1 = mul %0, 2
+  %2 = sub %1, 3
+  ret %2
+}
+"#
+    .into();
+    let program = driver.generate_program().unwrap();
+    let data = program.funcs().values().next().unwrap();
+    let bb = data.layout().entry_bb().unwrap();
+    let node = data.layout().bbs().node(&bb).unwrap();
+    let insts: Vec<_> = node.insts().keys().copied().collect();
+    let mut rev = insts.clone();
+    rev.reverse();
+    assert_eq!(data.layout().block_insts(bb).collect::<Vec<_>>(), insts);
+    assert_eq!(data.block_insts_rev(bb).collect::<Vec<_>>(), rev);
+    // iterate from both ends until they meet
+    let mut iter = data.layout().block_insts(bb);
+    assert_eq!(iter.len(), 4);
+    assert_eq!(iter.next_back(), Some(insts[3]));
+    assert_eq!(iter.next(), Some(insts[0]));
+    assert_eq!(iter.next_back(), Some(insts[2]));
+    assert_eq!(iter.len(), 1);
+    assert_eq!(iter.next(), Some(insts[1]));
+    assert_eq!(iter.next(), None);
+    assert_eq!(iter.next_back(), None);
+  }
+}
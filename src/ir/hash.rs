@@ -0,0 +1,268 @@
+//! Content hashing of Koopa IR programs.
+
+use crate::ir::entities::{
+  BasicBlock, Function, FunctionData, Program, Value, ValueData, ValueKind,
+};
+use std::collections::HashMap;
+
+/// Computes the content hash of the given program.
+pub(in crate::ir) fn hash_program(program: &Program) -> u64 {
+  let mut hasher = ContentHasher::new(program);
+  hasher.hash_program();
+  hasher.state
+}
+
+/// Hasher that hashes the structural content of a program.
+///
+/// Handles are replaced by their indices in layouts, so the hash
+/// does not depend on handle ids.
+struct ContentHasher<'a> {
+  program: &'a Program,
+  /// FNV-1a state.
+  state: u64,
+  /// Indices of global allocations.
+  globals: HashMap<Value, usize>,
+  /// Indices of functions.
+  funcs: HashMap<Function, usize>,
+  /// The function currently being hashed.
+  func: Option<&'a FunctionData>,
+  /// Indices of basic blocks in the current function.
+  bbs: HashMap<BasicBlock, usize>,
+  /// Indices of non-constant local values in the current function.
+  locals: HashMap<Value, usize>,
+}
+
+impl<'a> ContentHasher<'a> {
+  const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+  const FNV_PRIME: u64 = 0x100000001b3;
+
+  fn new(program: &'a Program) -> Self {
+    Self {
+      program,
+      state: Self::FNV_OFFSET,
+      globals: program
+        .inst_layout()
+        .iter()
+        .copied()
+        .enumerate()
+        .map(|(i, v)| (v, i))
+        .collect(),
+      funcs: program
+        .func_layout()
+        .iter()
+        .copied()
+        .enumerate()
+        .map(|(i, v)| (v, i))
+        .collect(),
+      func: None,
+      bbs: HashMap::new(),
+      locals: HashMap::new(),
+    }
+  }
+
+  fn write(&mut self, bytes: &[u8]) {
+    for b in bytes {
+      self.state ^= *b as u64;
+      self.state = self.state.wrapping_mul(Self::FNV_PRIME);
+    }
+  }
+
+  fn write_int(&mut self, i: i64) {
+    self.write(&i.to_le_bytes());
+  }
+
+  fn write_str(&mut self, s: &str) {
+    self.write_int(s.len() as i64);
+    self.write(s.as_bytes());
+  }
+
+  fn hash_program(&mut self) {
+    let program = self.program;
+    // named type definitions
+    self.write_int(program.named_types().len() as i64);
+    for (name, ty) in program.named_types().iter() {
+      self.write_str(name);
+      self.write_str(&ty.to_string());
+    }
+    // global allocations, with their names
+    self.write_int(program.inst_layout().len() as i64);
+    for alloc in program.inst_layout() {
+      let data = program.borrow_value(*alloc);
+      self.write_str(data.name().as_deref().unwrap_or(""));
+      self.hash_data(&data);
+    }
+    // functions
+    self.write_int(program.func_layout().len() as i64);
+    for func in program.func_layout() {
+      self.hash_func(program.func(*func));
+    }
+  }
+
+  fn hash_func(&mut self, func: &'a FunctionData) {
+    self.write_str(func.name());
+    self.write_str(&func.ty().to_string());
+    // number basic blocks and local values in layout order
+    self.func = Some(func);
+    self.bbs.clear();
+    self.locals.clear();
+    for (i, bb) in func.layout().bbs().keys().enumerate() {
+      self.bbs.insert(*bb, i);
+    }
+    let defs = func
+      .params()
+      .iter()
+      .chain(func.layout().bbs().iter().flat_map(|(bb, node)| {
+        func
+          .dfg()
+          .bb(*bb)
+          .params()
+          .iter()
+          .chain(node.insts().keys())
+      }));
+    for (i, v) in defs.enumerate() {
+      self.locals.insert(*v, i);
+    }
+    // basic blocks and instructions
+    self.write_int(func.layout().bbs().len() as i64);
+    for (bb, node) in func.layout().bbs() {
+      for p in func.dfg().bb(*bb).params() {
+        self.write_str(&func.dfg().value(*p).ty().to_string());
+      }
+      self.write_int(node.insts().len() as i64);
+      for inst in node.insts().keys() {
+        self.hash_data(func.dfg().value(*inst));
+      }
+    }
+    self.func = None;
+  }
+
+  /// Hashes a reference to the given value.
+  fn hash_value(&mut self, value: Value) {
+    if value.is_global() {
+      if let Some(i) = self.globals.get(&value).copied() {
+        self.write(b"g");
+        self.write_int(i as i64);
+      } else {
+        // constants, or constant pointers
+        let data = self.program.borrow_value(value);
+        self.hash_data(&data);
+      }
+    } else if let Some(i) = self.locals.get(&value).copied() {
+      self.write(b"l");
+      self.write_int(i as i64);
+    } else {
+      match self.func.and_then(|f| f.dfg().values().get(&value)) {
+        Some(data) => self.hash_data(data),
+        None => self.write(b"?"),
+      }
+    }
+  }
+
+  /// Hashes the given value data.
+  fn hash_data(&mut self, data: &ValueData) {
+    self.write_str(&data.ty().to_string());
+    let tag = match data.kind() {
+      ValueKind::Integer(v) => {
+        self.write_int(v.value() as i64);
+        "integer"
+      }
+      ValueKind::ZeroInit(_) => "zeroinit",
+      ValueKind::Undef(_) => "undef",
+      ValueKind::Aggregate(v) => {
+        self.write_int(v.elems().len() as i64);
+        "aggregate"
+      }
+      ValueKind::FuncArgRef(v) => {
+        self.write_int(v.index() as i64);
+        "funcarg"
+      }
+      ValueKind::BlockArgRef(v) => {
+        self.write_int(v.index() as i64);
+        "blockarg"
+      }
+      ValueKind::Alloc(v) => {
+        if let Some(align) = v.align() {
+          self.write_int(align as i64);
+        }
+        "alloc"
+      }
+      ValueKind::GlobalAlloc(v) => {
+        if let Some(align) = v.align() {
+          self.write_int(align as i64);
+        }
+        if v.attrs().readonly {
+          self.write_str("readonly");
+        }
+        if v.attrs().thread_local {
+          self.write_str("threadlocal");
+        }
+        "globalalloc"
+      }
+      ValueKind::Load(_) => "load",
+      ValueKind::Store(_) => "store",
+      ValueKind::GetPtr(_) => "getptr",
+      ValueKind::GetElemPtr(_) => "getelemptr",
+      ValueKind::Binary(v) => {
+        self.write_str(&v.op().to_string());
+        if !v.flags().is_empty() {
+          self.write_str(&v.flags().to_string());
+        }
+        "binary"
+      }
+      ValueKind::Unary(v) => {
+        self.write_str(&v.op().to_string());
+        "unary"
+      }
+      ValueKind::CheckedBinary(v) => {
+        self.write_str(&v.op().to_string());
+        "checked"
+      }
+      ValueKind::ExtractValue(v) => {
+        self.write_int(v.index() as i64);
+        "extractvalue"
+      }
+      ValueKind::InsertValue(v) => {
+        self.write_int(v.index() as i64);
+        "insertvalue"
+      }
+      ValueKind::Branch(v) => {
+        self.write_int(v.true_args().len() as i64);
+        "br"
+      }
+      ValueKind::Jump(_) => "jump",
+      ValueKind::Switch(v) => {
+        self.write_int(v.default_args().len() as i64);
+        for (value, _, args) in v.cases() {
+          self.write_int(*value as i64);
+          self.write_int(args.len() as i64);
+        }
+        "switch"
+      }
+      ValueKind::Call(v) => {
+        let callee = self.funcs.get(&v.callee()).map_or(-1, |i| *i as i64);
+        self.write_int(callee);
+        "call"
+      }
+      ValueKind::Return(v) => {
+        self.write_int(v.value().is_some() as i64);
+        "ret"
+      }
+      ValueKind::Copy(_) => "copy",
+      ValueKind::Assume(_) => "assume",
+      ValueKind::Unreachable(_) => "unreachable",
+    };
+    self.write_str(tag);
+    // operands
+    let uses: Vec<_> = data.kind().value_uses().collect();
+    self.write_int(uses.len() as i64);
+    for value in uses {
+      self.hash_value(value);
+    }
+    let bbs: Vec<_> = data.kind().bb_uses().collect();
+    self.write_int(bbs.len() as i64);
+    for bb in bbs {
+      let index = self.bbs.get(&bb).map_or(-1, |i| *i as i64);
+      self.write_int(index);
+    }
+  }
+}
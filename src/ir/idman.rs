@@ -77,3 +77,61 @@ pub(in crate::ir) fn next_bb_id() -> BasicBlockId {
 pub(in crate::ir) fn next_func_id() -> FunctionId {
   NEXT_FUNC_ID.with(|id| id.replace(unsafe { NonZeroU32::new_unchecked(id.get().get() + 1) }))
 }
+
+/// Resets all ID counters of the current thread to their initial values.
+///
+/// After calling this function, programs built in the same way will get
+/// identical value/basic block/function handles, which is useful for
+/// producing reproducible output (e.g. golden tests).
+///
+/// ID counters are thread local, so this function only affects the
+/// current thread, and it's safe to be called in multi-threaded
+/// environment (e.g. parallel tests).
+///
+/// # Notes
+///
+/// Handles are only guaranteed to be unique between resets. Do not mix
+/// programs (and handles) created before the reset with those created
+/// after the reset, or different entities may share the same handle.
+pub fn reset_id_counters() {
+  NEXT_GLOBAL_VALUE_ID.with(|id| id.set(GLOBAL_VALUE_ID_STARTS_FROM));
+  NEXT_LOCAL_VALUE_ID.with(|id| id.set(LOCAL_VALUE_ID_STARTS_FROM));
+  NEXT_BB_ID.with(|id| id.set(BB_ID_STARTS_FROM));
+  NEXT_FUNC_ID.with(|id| id.set(FUNC_ID_STARTS_FROM));
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::ir::builder_traits::*;
+  use crate::ir::{FunctionData, Program, Type};
+
+  fn build_program() -> (Program, Vec<String>) {
+    let mut program = Program::new();
+    let zero = program.new_value().zero_init(Type::get_i32());
+    let alloc = program.new_value().global_alloc(zero);
+    let func = program.new_func(FunctionData::new("@f".into(), vec![], Type::get_unit()));
+    let data = program.func_mut(func);
+    let entry = data.dfg_mut().new_bb().basic_block(None);
+    let ret = data.dfg_mut().new_value().ret(None);
+    let handles = vec![
+      format!("{:?}", zero),
+      format!("{:?}", alloc),
+      format!("{:?}", func),
+      format!("{:?}", entry),
+      format!("{:?}", ret),
+    ];
+    (program, handles)
+  }
+
+  #[test]
+  fn reset_counters() {
+    reset_id_counters();
+    let (_, handles1) = build_program();
+    reset_id_counters();
+    let (_, handles2) = build_program();
+    assert_eq!(handles1, handles2);
+    let (_, handles3) = build_program();
+    assert_ne!(handles1, handles3);
+  }
+}
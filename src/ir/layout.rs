@@ -2,7 +2,7 @@
 //! related implementations.
 
 use crate::ir::entities::{BasicBlock, Value};
-use key_node_list::{impl_node, KeyNodeList, Map};
+use key_node_list::{impl_node, KeyNodeList, Map, Node};
 use std::borrow::Borrow;
 use std::cell::RefCell;
 use std::collections::{hash_map::Entry, HashMap};
@@ -55,8 +55,68 @@ impl Layout {
   pub fn parent_bb(&self, inst: Value) -> Option<BasicBlock> {
     self.inst_bb.as_ref().borrow().get(&inst).copied()
   }
+
+  /// Returns a double-ended iterator over the instructions in the given
+  /// basic block, without allocation.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the given basic block does not exist.
+  pub fn block_insts(&self, bb: BasicBlock) -> BlockInsts<'_> {
+    let insts = self.bbs.node(&bb).expect("`bb` does not exist").insts();
+    BlockInsts {
+      insts,
+      front: insts.front_key().copied(),
+      back: insts.back_key().copied(),
+      len: insts.len(),
+    }
+  }
+}
+
+/// A double-ended iterator over the instructions in a basic block,
+/// returned by [`Layout::block_insts`].
+///
+/// The iterator walks the links of the [`InstList`] from both ends.
+#[derive(Clone)]
+pub struct BlockInsts<'a> {
+  insts: &'a InstList,
+  front: Option<Value>,
+  back: Option<Value>,
+  len: usize,
+}
+
+impl Iterator for BlockInsts<'_> {
+  type Item = Value;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.len == 0 {
+      return None;
+    }
+    let cur = self.front?;
+    self.front = self.insts.node(&cur).unwrap().next().copied();
+    self.len -= 1;
+    Some(cur)
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    (self.len, Some(self.len))
+  }
 }
 
+impl DoubleEndedIterator for BlockInsts<'_> {
+  fn next_back(&mut self) -> Option<Self::Item> {
+    if self.len == 0 {
+      return None;
+    }
+    let cur = self.back?;
+    self.back = self.insts.node(&cur).unwrap().prev().copied();
+    self.len -= 1;
+    Some(cur)
+  }
+}
+
+impl ExactSizeIterator for BlockInsts<'_> {}
+
 impl Default for Layout {
   fn default() -> Self {
     let inst_bb = Rc::new(RefCell::new(HashMap::new()));
@@ -9,6 +9,28 @@
 //!   [`ValueData`](entities::ValueData)).
 //! * Types of IR values ([`Type`]).
 //! * IR builders and IR builder traits ([`builder`]).
+//! * Analyses of IR ([`analysis`]).
+//!
+//! # SSA Form
+//!
+//! Koopa IR is in SSA form, but it has no phi functions. Instead, basic
+//! blocks can have parameters ([`ValueKind::BlockArgRef`]), and branch,
+//! jump and switch instructions pass arguments to their targets. A phi
+//! function `%x = phi [%a, %bb1], [%b, %bb2]` in the target basic block
+//! corresponds to a basic block parameter `%x`, with `%bb1` passing `%a`
+//! and `%bb2` passing `%b`. The verifier checks if the arguments match
+//! the parameters ([`verifier::verify_block_params`]).
+//!
+//! ```koopa
+//! fun @max(@a: i32, @b: i32): i32 {
+//! %entry:
+//!   %cond = gt @a, @b
+//!   br %cond, %end(@a), %end(@b)
+//!
+//! %end(%ans: i32):
+//!   ret %ans
+//! }
+//! ```
 //!
 //! # Example
 //!
@@ -76,13 +98,20 @@
 //! fib_data.layout_mut().bb_mut(else_bb).insts_mut().extend([sub1, call1, sub2, call2, ans, ret]);
 //! ```
 
+pub mod analysis;
+#[cfg(feature = "binary")]
+pub mod binary;
 pub mod builder;
 pub mod dfg;
 pub mod entities;
 pub mod layout;
+pub mod span;
+pub mod stats;
 pub mod types;
 pub mod values;
+pub mod verifier;
 
+mod hash;
 mod idman;
 
 pub mod builder_traits {
@@ -103,5 +132,6 @@ pub mod builder_traits {
 }
 
 pub use entities::{BasicBlock, Function, FunctionData, Program, Value, ValueKind};
+pub use idman::reset_id_counters;
 pub use types::{Type, TypeKind};
-pub use values::BinaryOp;
+pub use values::{BinaryFlags, BinaryOp, GlobalAttrs, UnaryOp};
@@ -0,0 +1,91 @@
+//! Source spans ([`SourceSpan`]) of values in Koopa IR programs.
+//!
+//! Unlike spans in the front end ([`crate::front::span::Span`]), source
+//! spans carry the name of their source file, and can be displayed
+//! without any global state.
+
+use std::fmt;
+use std::rc::Rc;
+
+/// A span that records the source code location of a value.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SourceSpan {
+  file: Rc<str>,
+  start: SourcePos,
+  end: SourcePos,
+}
+
+impl SourceSpan {
+  /// Creates a new source span in the given file.
+  pub fn new(file: Rc<str>, start: SourcePos, end: SourcePos) -> Self {
+    Self { file, start, end }
+  }
+
+  /// Returns the name of the source file.
+  pub fn file(&self) -> &str {
+    &self.file
+  }
+
+  /// Returns the start position.
+  pub fn start(&self) -> SourcePos {
+    self.start
+  }
+
+  /// Returns the end position.
+  pub fn end(&self) -> SourcePos {
+    self.end
+  }
+}
+
+impl fmt::Display for SourceSpan {
+  /// Formats the span as `file:line:col`.
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}:{}", self.file, self.start)
+  }
+}
+
+/// A line-column position in a source file.
+///
+/// Positions are ordered by line number first, and then by column number.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SourcePos {
+  line: u32,
+  col: u32,
+}
+
+impl SourcePos {
+  /// Creates a new position.
+  pub fn new(line: u32, col: u32) -> Self {
+    Self { line, col }
+  }
+
+  /// Returns the line number.
+  pub fn line(&self) -> u32 {
+    self.line
+  }
+
+  /// Returns the column number.
+  pub fn col(&self) -> u32 {
+    self.col
+  }
+}
+
+impl fmt::Display for SourcePos {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}:{}", self.line, self.col)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn display() {
+    let start = SourcePos::new(3, 5);
+    let span = SourceSpan::new("a.koopa".into(), start, SourcePos::new(3, 9));
+    assert_eq!(span.to_string(), "a.koopa:3:5");
+    assert_eq!(span.file(), "a.koopa");
+    assert!(span.start() < span.end());
+  }
+}
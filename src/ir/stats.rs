@@ -0,0 +1,197 @@
+//! Statistics of Koopa IR programs, see [`Program::statistics`].
+
+use crate::ir::{FunctionData, Program, TypeKind, ValueKind};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+/// Statistics of a Koopa IR program, returned by [`Program::statistics`].
+///
+/// The [`Display`](fmt::Display) implementation prints a compact report,
+/// one line for the whole program and one line for each function, which
+/// can be compared before and after running a pass.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+  /// Number of function declarations.
+  pub decls: usize,
+  /// Number of global allocations.
+  pub globals: usize,
+  /// Total size of global allocations in bytes. Allocations of
+  /// undefined named types are not counted.
+  pub global_bytes: usize,
+  /// Summary of all function definitions.
+  pub total: FuncStats,
+  /// Names and statistics of function definitions, in layout order.
+  pub funcs: Vec<(String, FuncStats)>,
+}
+
+impl Stats {
+  /// Computes statistics of the given program.
+  pub(in crate::ir) fn compute(program: &Program) -> Self {
+    let mut stats = Self::default();
+    for &func in program.func_layout() {
+      let data = program.func(func);
+      if data.layout().entry_bb().is_none() {
+        stats.decls += 1;
+        continue;
+      }
+      let func_stats = FuncStats::compute(data);
+      stats.total.merge(&func_stats);
+      stats.funcs.push((data.name().into(), func_stats));
+    }
+    for &alloc in program.inst_layout() {
+      if let TypeKind::Pointer(base) = program.borrow_value(alloc).ty().kind() {
+        stats.global_bytes += program.named_types().size_of(base).unwrap_or(0);
+      }
+      stats.globals += 1;
+    }
+    stats
+  }
+}
+
+impl fmt::Display for Stats {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    writeln!(
+      f,
+      "functions: {}, declarations: {}, globals: {} ({} bytes)",
+      self.funcs.len(),
+      self.decls,
+      self.globals,
+      self.global_bytes
+    )?;
+    writeln!(f, "total: {}", self.total)?;
+    for (name, stats) in &self.funcs {
+      writeln!(f, "{}: {}", name, stats)?;
+    }
+    Ok(())
+  }
+}
+
+/// Statistics of a function definition, or summary of multiple function
+/// definitions.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FuncStats {
+  /// Number of basic blocks.
+  pub bbs: usize,
+  /// Number of instructions.
+  pub insts: usize,
+  /// Number of instructions of each kind, like `add` or `load`.
+  pub inst_kinds: BTreeMap<String, usize>,
+  /// Maximum number of predecessors of a basic block.
+  pub max_fan_in: usize,
+  /// Maximum number of distinct successors of a basic block.
+  pub max_fan_out: usize,
+}
+
+impl FuncStats {
+  /// Computes statistics of the given function.
+  fn compute(func: &FunctionData) -> Self {
+    let mut stats = Self::default();
+    for (&bb, node) in func.layout().bbs() {
+      stats.bbs += 1;
+      for &inst in node.insts().keys() {
+        stats.insts += 1;
+        let kind = kind_name(func.dfg().value(inst).kind());
+        *stats.inst_kinds.entry(kind).or_default() += 1;
+      }
+      let succs: HashSet<_> = (func.terminator(bb).into_iter())
+        .flat_map(|t| func.dfg().value(t).kind().bb_uses())
+        .collect();
+      stats.max_fan_out = stats.max_fan_out.max(succs.len());
+      stats.max_fan_in = stats.max_fan_in.max(func.preds(bb).len());
+    }
+    stats
+  }
+
+  /// Adds the given statistics to the current statistics.
+  fn merge(&mut self, other: &Self) {
+    self.bbs += other.bbs;
+    self.insts += other.insts;
+    for (kind, count) in &other.inst_kinds {
+      *self.inst_kinds.entry(kind.clone()).or_default() += count;
+    }
+    self.max_fan_in = self.max_fan_in.max(other.max_fan_in);
+    self.max_fan_out = self.max_fan_out.max(other.max_fan_out);
+  }
+}
+
+impl fmt::Display for FuncStats {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{} blocks, {} instructions", self.bbs, self.insts)?;
+    if !self.inst_kinds.is_empty() {
+      let kinds: Vec<_> = (self.inst_kinds.iter())
+        .map(|(kind, count)| format!("{} {}", kind, count))
+        .collect();
+      write!(f, " ({})", kinds.join(", "))?;
+    }
+    write!(
+      f,
+      ", max fan-in {}, max fan-out {}",
+      self.max_fan_in, self.max_fan_out
+    )
+  }
+}
+
+/// Returns the name of the given instruction kind in the text form.
+fn kind_name(kind: &ValueKind) -> String {
+  match kind {
+    ValueKind::Alloc(_) => "alloc".into(),
+    ValueKind::Load(_) => "load".into(),
+    ValueKind::Store(_) => "store".into(),
+    ValueKind::GetPtr(_) => "getptr".into(),
+    ValueKind::GetElemPtr(_) => "getelemptr".into(),
+    ValueKind::Binary(v) => v.op().to_string(),
+    ValueKind::Unary(v) => v.op().to_string(),
+    ValueKind::CheckedBinary(v) => format!("checked {}", v.op()),
+    ValueKind::ExtractValue(_) => "extractvalue".into(),
+    ValueKind::InsertValue(_) => "insertvalue".into(),
+    ValueKind::Copy(_) => "copy".into(),
+    ValueKind::Assume(_) => "assume".into(),
+    ValueKind::Branch(_) => "br".into(),
+    ValueKind::Jump(_) => "jump".into(),
+    ValueKind::Switch(_) => "switch".into(),
+    ValueKind::Call(_) => "call".into(),
+    ValueKind::Return(_) => "ret".into(),
+    ValueKind::Unreachable(_) => "unreachable".into(),
+    _ => panic!("invalid local instruction"),
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use crate::front::Driver;
+
+  #[test]
+  fn statistics() {
+    let driver: Driver<_> = r#"decl @putint(i32)
+
+global @g = alloc [i32, 3], zeroinit
+global @h = alloc i32, 1
+
+fun @main(): i32 {
+%entry:
+  %0 = load @h
+  br %0, %then, %end
+
+%then:
+  call @putint(%0)
+  jump %end
+
+%end:
+  %1 = add %0, 1
+  ret %1
+}
+"#
+    .into();
+    let program = driver.generate_program().unwrap();
+    let stats = program.statistics();
+    assert_eq!(stats.global_bytes, 16);
+    assert_eq!(stats.total.inst_kinds["load"], 1);
+    assert_eq!(
+      stats.to_string(),
+      r#"functions: 1, declarations: 1, globals: 2 (16 bytes)
+total: 3 blocks, 6 instructions (add 1, br 1, call 1, jump 1, load 1, ret 1), max fan-in 2, max fan-out 2
+@main: 3 blocks, 6 instructions (add 1, br 1, call 1, jump 1, load 1, ret 1), max fan-in 2, max fan-out 2
+"#
+    );
+  }
+}
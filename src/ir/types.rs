@@ -2,10 +2,14 @@
 //!
 //! Each Koopa IR value and function should have a type. A type can be
 //! a 32-bit integer type, a unit type, an array type, a pointer type,
-//! or a function type.
+//! a function type, or a named type.
+//!
+//! Named types are opaque placeholders that are defined by programs
+//! (see [`NamedTypes`]), which allows recursive types like linked lists
+//! (for example, `@list` defined as `[*@list, 2]`).
 
 use std::cell::{Cell, RefCell};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 use std::{cmp, fmt, hash, mem};
 
@@ -22,6 +26,8 @@ pub enum TypeKind {
   Pointer(Type),
   /// Function (with parameter types and return type).
   Function(Vec<Type>, Type),
+  /// Named type (with name), which is defined by programs.
+  Named(String),
 }
 
 impl fmt::Display for TypeKind {
@@ -31,6 +37,7 @@ impl fmt::Display for TypeKind {
       TypeKind::Unit => write!(f, "unit"),
       TypeKind::Array(t, len) => write!(f, "[{}, {}]", t, len),
       TypeKind::Pointer(t) => write!(f, "*{}", t),
+      TypeKind::Named(name) => write!(f, "{}", name),
       TypeKind::Function(params, ret) => {
         write!(f, "(")?;
         let mut first = true;
@@ -76,6 +83,13 @@ impl Type {
     })
   }
 
+  /// Returns a type by the given [`TypeKind`] without interning it,
+  /// which simulates types created by other type pools.
+  #[cfg(test)]
+  pub(in crate::ir) fn get_uninterned(type_data: TypeKind) -> Type {
+    Self(Rc::new(type_data))
+  }
+
   /// Returns an `i32` type.
   pub fn get_i32() -> Type {
     Type::get(TypeKind::Int32)
@@ -102,6 +116,56 @@ impl Type {
     Type::get(TypeKind::Function(params, ret))
   }
 
+  /// Returns a named type. The named type is defined by programs,
+  /// see [`NamedTypes`].
+  ///
+  /// # Panics
+  ///
+  /// Panics if the given name not starts with `@`.
+  pub fn get_named(name: String) -> Type {
+    assert!(
+      is_named_type_name(&name),
+      "invalid named type name '{}'",
+      name
+    );
+    Type::get(TypeKind::Named(name))
+  }
+
+  /// Rebuilds the current type by recursively applying the given function.
+  ///
+  /// If the function returns `Some` for a type, the type is replaced by
+  /// the returned type, which is not visited further. Otherwise, base
+  /// types of arrays and pointers, and parameter and return types of
+  /// functions are visited. Named types are opaque and never expanded,
+  /// but can still be replaced by the function.
+  ///
+  /// For example, `ty.map(|t| t.is_i32().then(Type::get_unit))` replaces
+  /// all `i32` types in `ty` with `unit`.
+  pub fn map<F>(&self, f: F) -> Type
+  where
+    F: Fn(&Type) -> Option<Type>,
+  {
+    self.map_with(&f)
+  }
+
+  fn map_with<F>(&self, f: &F) -> Type
+  where
+    F: Fn(&Type) -> Option<Type>,
+  {
+    if let Some(ty) = f(self) {
+      return ty;
+    }
+    match self.kind() {
+      TypeKind::Array(base, len) => Type::get_array(base.map_with(f), *len),
+      TypeKind::Pointer(base) => Type::get_pointer(base.map_with(f)),
+      TypeKind::Function(params, ret) => Type::get_function(
+        params.iter().map(|p| p.map_with(f)).collect(),
+        ret.map_with(f),
+      ),
+      TypeKind::Int32 | TypeKind::Unit | TypeKind::Named(_) => self.clone(),
+    }
+  }
+
   /// Sets the size of pointers.
   pub fn set_ptr_size(size: usize) {
     Self::PTR_SIZE.with(|ptr_size| ptr_size.set(size));
@@ -112,6 +176,31 @@ impl Type {
     &self.0
   }
 
+  /// Checks if the current type is structurally compatible with the
+  /// given type, that is, they have the same structure, regardless of
+  /// whether they are the same interned type.
+  ///
+  /// [`PartialEq`] of types compares the identity of types, which is
+  /// the same as structural compatibility for types from the same type
+  /// pool. Named types are opaque, and are compared by their names.
+  pub fn structurally_compatible(&self, other: &Type) -> bool {
+    if Rc::ptr_eq(&self.0, &other.0) {
+      return true;
+    }
+    match (self.kind(), other.kind()) {
+      (TypeKind::Int32, TypeKind::Int32) | (TypeKind::Unit, TypeKind::Unit) => true,
+      (TypeKind::Array(l, ll), TypeKind::Array(r, rl)) => ll == rl && l.structurally_compatible(r),
+      (TypeKind::Pointer(l), TypeKind::Pointer(r)) => l.structurally_compatible(r),
+      (TypeKind::Function(lp, lr), TypeKind::Function(rp, rr)) => {
+        lp.len() == rp.len()
+          && lr.structurally_compatible(rr)
+          && lp.iter().zip(rp).all(|(l, r)| l.structurally_compatible(r))
+      }
+      (TypeKind::Named(l), TypeKind::Named(r)) => l == r,
+      _ => false,
+    }
+  }
+
   /// Checks if the current type is an integer type.
   pub fn is_i32(&self) -> bool {
     matches!(self.0.as_ref(), TypeKind::Int32)
@@ -122,13 +211,244 @@ impl Type {
     matches!(self.0.as_ref(), TypeKind::Unit)
   }
 
+  /// Checks if the current type can be allocated by `alloc` and
+  /// `global alloc`.
+  ///
+  /// Unit types, function types and zero-sized arrays (arrays of types
+  /// that can not be allocated) can not be allocated. Named types are
+  /// assumed to be allocatable, use [`NamedTypes::is_allocatable`] to
+  /// check their definitions.
+  pub fn is_allocatable(&self) -> bool {
+    self.is_allocatable_in(None)
+  }
+
+  fn is_allocatable_in(&self, named: Option<&NamedTypes>) -> bool {
+    match self.kind() {
+      TypeKind::Int32 | TypeKind::Pointer(_) => true,
+      TypeKind::Unit | TypeKind::Function(..) => false,
+      TypeKind::Array(base, len) => *len != 0 && base.is_allocatable_in(named),
+      TypeKind::Named(name) => {
+        named.is_none_or(|n| n.get(name).is_some_and(|b| b.is_allocatable_in(named)))
+      }
+    }
+  }
+
   /// Returns the size of the current type in bytes.
+  ///
+  /// The size is always a multiple of the alignment (see
+  /// [`Type::align_of`]), so the size of an aggregate includes the
+  /// padding after its last element, see [`Type::field_offset`].
+  ///
+  /// # Panics
+  ///
+  /// Panics if the current type contains named types by value, whose
+  /// sizes depend on their definitions. Use [`Type::try_size`] or
+  /// [`NamedTypes::size_of`] for such types.
   pub fn size(&self) -> usize {
+    self
+      .try_size()
+      .expect("size of named types depends on their definitions")
+  }
+
+  /// Returns the size of the current type in bytes like [`Type::size`],
+  /// or `None` if the current type contains named types by value.
+  pub fn try_size(&self) -> Option<usize> {
+    self.size_in(None)
+  }
+
+  fn size_in(&self, named: Option<&NamedTypes>) -> Option<usize> {
+    match self.kind() {
+      TypeKind::Int32 => Some(4),
+      TypeKind::Unit => Some(0),
+      TypeKind::Array(ty, len) => Some(ty.stride_in(named)? * len),
+      TypeKind::Pointer(..) | TypeKind::Function(..) => Some(Self::PTR_SIZE.with(|s| s.get())),
+      TypeKind::Named(name) => named?.get(name)?.size_in(named),
+    }
+  }
+
+  /// Returns the alignment of the current type in bytes, or `None` if
+  /// the current type contains named types by value (see
+  /// [`NamedTypes::align_of`]).
+  ///
+  /// This is the default alignment of memory allocations of the type,
+  /// see [`Alloc::align`](crate::ir::values::Alloc::align).
+  pub fn align_of(&self) -> Option<usize> {
+    self.align_in(None)
+  }
+
+  fn align_in(&self, named: Option<&NamedTypes>) -> Option<usize> {
+    match self.kind() {
+      TypeKind::Int32 => Some(4),
+      TypeKind::Unit => Some(1),
+      TypeKind::Array(ty, _) => ty.align_in(named),
+      TypeKind::Pointer(..) | TypeKind::Function(..) => Some(Self::PTR_SIZE.with(|s| s.get())),
+      TypeKind::Named(name) => named?.get(name)?.align_in(named),
+    }
+  }
+
+  /// Returns the byte offset of the element at the given index in the
+  /// current aggregate type, or `None` if the current type is not an
+  /// aggregate type, the index is out of bounds, or the element type
+  /// contains named types by value (see [`NamedTypes::field_offset`]).
+  ///
+  /// Elements are laid out in order, each at the smallest offset after
+  /// the previous element that is a multiple of its alignment (see
+  /// [`Type::align_of`]). The size of an aggregate is the end of its
+  /// last element rounded up to the alignment of the aggregate, which
+  /// is the largest alignment of its elements. So elements of an array
+  /// are placed every [`Type::size`] of the element type, which is
+  /// already padded to a multiple of its alignment.
+  ///
+  /// The interpreter and the backends use these rules for `getelemptr`
+  /// and for accessing aggregates in memory.
+  pub fn field_offset(&self, index: usize) -> Option<usize> {
+    self.field_offset_in(None, index)
+  }
+
+  fn field_offset_in(&self, named: Option<&NamedTypes>, index: usize) -> Option<usize> {
+    match self.kind() {
+      TypeKind::Array(ty, len) if index < *len => Some(ty.stride_in(named)? * index),
+      TypeKind::Named(name) => named?.get(name)?.field_offset_in(named, index),
+      _ => None,
+    }
+  }
+
+  /// Returns the distance in bytes between adjacent elements of the
+  /// current type in an array, that is, the size rounded up to the
+  /// alignment.
+  fn stride_in(&self, named: Option<&NamedTypes>) -> Option<usize> {
+    let align = self.align_in(named)?;
+    Some(self.size_in(named)?.div_ceil(align) * align)
+  }
+
+  /// Checks if the current type contains the named type with the given
+  /// name by value, i.e. not through pointers.
+  fn contains_by_value(
+    &self,
+    named: &NamedTypes,
+    name: &str,
+    visited: &mut HashSet<String>,
+  ) -> bool {
     match self.kind() {
-      TypeKind::Int32 => 4,
-      TypeKind::Unit => 0,
-      TypeKind::Array(ty, len) => ty.size() * len,
-      TypeKind::Pointer(..) | TypeKind::Function(..) => Self::PTR_SIZE.with(|s| s.get()),
+      TypeKind::Array(base, _) => base.contains_by_value(named, name, visited),
+      TypeKind::Named(n) if n == name => true,
+      TypeKind::Named(n) => {
+        visited.insert(n.clone())
+          && named
+            .get(n)
+            .is_some_and(|body| body.contains_by_value(named, name, visited))
+      }
+      _ => false,
+    }
+  }
+}
+
+/// Checks if the given string is a valid name of named types.
+pub(in crate::ir) fn is_named_type_name(name: &str) -> bool {
+  name.len() > 1 && name.starts_with('@')
+}
+
+/// Definitions of named types of a program, in definition order.
+///
+/// Named types are opaque, and are compared by their names. Their
+/// sizes, alignments and field offsets are taken from their definitions.
+/// See [`Program::named_types`](crate::ir::Program::named_types).
+#[derive(Clone, Default)]
+pub struct NamedTypes {
+  defs: HashMap<String, Type>,
+  names: Vec<String>,
+}
+
+impl NamedTypes {
+  /// Defines the named type with the given name as the given type.
+  ///
+  /// Named types can reference themselves through pointers, but a named
+  /// type that contains itself by value (which has an infinite size) is
+  /// rejected. Each named type can only be defined once.
+  pub fn define(&mut self, name: &str, body: Type) -> Result<(), NamedTypeError> {
+    if !is_named_type_name(name) {
+      return Err(NamedTypeError::InvalidName(name.into()));
+    }
+    if self.defs.contains_key(name) {
+      return Err(NamedTypeError::Redefinition(name.into()));
+    }
+    if body.contains_by_value(self, name, &mut HashSet::new()) {
+      return Err(NamedTypeError::InfiniteSize(name.into()));
+    }
+    self.defs.insert(name.into(), body);
+    self.names.push(name.into());
+    Ok(())
+  }
+
+  /// Returns the definition of the named type with the given name,
+  /// or `None` if the named type is not defined.
+  pub fn get(&self, name: &str) -> Option<&Type> {
+    self.defs.get(name)
+  }
+
+  /// Returns an iterator of all named types and their definitions,
+  /// in definition order.
+  pub fn iter(&self) -> impl Iterator<Item = (&str, &Type)> {
+    (self.names.iter()).map(|name| (name.as_str(), &self.defs[name]))
+  }
+
+  /// Returns the number of defined named types.
+  pub fn len(&self) -> usize {
+    self.names.len()
+  }
+
+  /// Returns `true` if no named types are defined.
+  pub fn is_empty(&self) -> bool {
+    self.names.is_empty()
+  }
+
+  /// Checks if the given type can be allocated, like
+  /// [`Type::is_allocatable`], but named types must be defined as
+  /// types that can be allocated.
+  pub fn is_allocatable(&self, ty: &Type) -> bool {
+    ty.is_allocatable_in(Some(self))
+  }
+
+  /// Returns the size of the given type in bytes, like [`Type::try_size`],
+  /// but named types are replaced by their definitions. Returns `None`
+  /// if the given type contains undefined named types by value.
+  pub fn size_of(&self, ty: &Type) -> Option<usize> {
+    ty.size_in(Some(self))
+  }
+
+  /// Returns the alignment of the given type in bytes, like
+  /// [`Type::align_of`], but named types are replaced by their
+  /// definitions. Returns `None` if the given type contains undefined
+  /// named types by value.
+  pub fn align_of(&self, ty: &Type) -> Option<usize> {
+    ty.align_in(Some(self))
+  }
+
+  /// Returns the byte offset of the element at the given index in the
+  /// given aggregate type, like [`Type::field_offset`], but named types
+  /// are replaced by their definitions.
+  pub fn field_offset(&self, ty: &Type, index: usize) -> Option<usize> {
+    ty.field_offset_in(Some(self), index)
+  }
+}
+
+/// Error returned by [`NamedTypes::define`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NamedTypeError {
+  /// The name does not start with `@`.
+  InvalidName(String),
+  /// The named type has already been defined.
+  Redefinition(String),
+  /// The named type contains itself by value.
+  InfiniteSize(String),
+}
+
+impl fmt::Display for NamedTypeError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::InvalidName(name) => write!(f, "invalid named type name '{}'", name),
+      Self::Redefinition(name) => write!(f, "named type '{}' has already been defined", name),
+      Self::InfiniteSize(name) => write!(f, "named type '{}' has an infinite size", name),
     }
   }
 }
@@ -240,4 +560,154 @@ mod test {
       4 * 5
     );
   }
+
+  #[test]
+  fn type_align() {
+    assert_eq!(Type::get_i32().align_of(), Some(4));
+    assert_eq!(Type::get_unit().align_of(), Some(1));
+    assert_eq!(Type::get_array(Type::get_i32(), 5).align_of(), Some(4));
+    assert_eq!(
+      Type::get_pointer(Type::get_i32()).align_of(),
+      Some(mem::size_of::<usize>())
+    );
+    Type::set_ptr_size(4);
+    assert_eq!(
+      Type::get_array(Type::get_pointer(Type::get_i32()), 5).align_of(),
+      Some(4)
+    );
+  }
+
+  #[test]
+  fn field_offset() {
+    let i32 = Type::get_i32();
+    let arr = Type::get_array(i32.clone(), 3);
+    assert_eq!(arr.field_offset(0), Some(0));
+    assert_eq!(arr.field_offset(2), Some(8));
+    assert_eq!(arr.field_offset(3), None);
+    assert_eq!(i32.field_offset(0), None);
+    let mat = Type::get_array(arr.clone(), 2);
+    assert_eq!(mat.field_offset(1), Some(arr.size()));
+    assert_eq!(mat.size(), 2 * arr.size());
+    Type::set_ptr_size(8);
+    let ptrs = Type::get_array(Type::get_pointer(i32), 2);
+    assert_eq!(ptrs.field_offset(1), Some(8));
+    let pair = Type::get_named("@pair".into());
+    let mut named = NamedTypes::default();
+    assert_eq!(pair.field_offset(1), None);
+    assert_eq!(named.field_offset(&pair, 1), None);
+    named.define("@pair", ptrs).unwrap();
+    assert_eq!(pair.field_offset(1), None);
+    assert_eq!(named.field_offset(&pair, 1), Some(8));
+    Type::set_ptr_size(mem::size_of::<*const ()>());
+  }
+
+  #[test]
+  fn type_allocatable() {
+    let i32 = Type::get_i32();
+    let unit = Type::get_unit();
+    let func = Type::get_function(vec![i32.clone()], unit.clone());
+    assert!(i32.is_allocatable());
+    assert!(Type::get_array(Type::get_pointer(func.clone()), 2).is_allocatable());
+    assert!(!unit.is_allocatable());
+    assert!(!func.is_allocatable());
+    assert!(!Type::get_array(unit.clone(), 2).is_allocatable());
+    assert!(!Type::get_array(Type::get_array(func, 3), 2).is_allocatable());
+    let pair = Type::get_named("@pair".into());
+    let void = Type::get_named("@void".into());
+    let mut named = NamedTypes::default();
+    assert!(pair.is_allocatable() && void.is_allocatable());
+    assert!(!named.is_allocatable(&pair));
+    named.define("@pair", Type::get_array(i32, 2)).unwrap();
+    named.define("@void", unit).unwrap();
+    assert!(named.is_allocatable(&pair));
+    assert!(!named.is_allocatable(&void));
+  }
+
+  #[test]
+  fn named_type() {
+    let mut named = NamedTypes::default();
+    let list = Type::get_named("@list".into());
+    assert_eq!(format!("{}", list), "@list");
+    assert_eq!(list.try_size(), None);
+    assert_eq!(named.get("@list"), None);
+    assert_eq!(named.size_of(&list), None);
+    let body = Type::get_array(Type::get_pointer(list.clone()), 2);
+    assert_eq!(named.define("@list", body.clone()), Ok(()));
+    assert_eq!(named.get("@list"), Some(&body));
+    assert_eq!(named.size_of(&list), Some(body.size()));
+    assert_eq!(named.align_of(&list), body.align_of());
+    assert_eq!(
+      named.define("@list", Type::get_i32()),
+      Err(NamedTypeError::Redefinition("@list".into()))
+    );
+    assert_eq!(
+      named.define("list", Type::get_i32()),
+      Err(NamedTypeError::InvalidName("list".into()))
+    );
+    // infinite size
+    let node = Type::get_named("@node".into());
+    assert_eq!(
+      named.define("@node", Type::get_array(node, 2)),
+      Err(NamedTypeError::InfiniteSize("@node".into()))
+    );
+    let a = Type::get_named("@a".into());
+    let b = Type::get_named("@b".into());
+    assert_eq!(named.define("@a", Type::get_array(b, 2)), Ok(()));
+    assert_eq!(
+      named.define("@b", Type::get_array(a.clone(), 2)),
+      Err(NamedTypeError::InfiniteSize("@b".into()))
+    );
+    assert_eq!(named.define("@b", Type::get_pointer(a)), Ok(()));
+    let names: Vec<_> = named.iter().map(|(name, _)| name).collect();
+    assert_eq!(names, ["@list", "@a", "@b"]);
+    // definitions are not shared between registries
+    assert_eq!(NamedTypes::default().size_of(&list), None);
+  }
+
+  #[test]
+  fn map_type() {
+    let i32 = Type::get_i32();
+    let ptr = Type::get_pointer(Type::get_array(i32.clone(), 4));
+    let func = Type::get_function(vec![ptr.clone(), i32.clone()], i32.clone());
+    let named = Type::get_named("@map_type_named".into());
+    let to_unit = |t: &Type| t.is_i32().then(Type::get_unit);
+    assert_eq!(i32.map(to_unit), Type::get_unit());
+    assert_eq!(format!("{}", ptr.map(to_unit)), "*[unit, 4]");
+    assert_eq!(format!("{}", func.map(to_unit)), "(*[unit, 4], unit)");
+    assert_eq!(named.map(to_unit), named);
+    // replaced types are not visited again
+    let flatten = |t: &Type| match t.kind() {
+      TypeKind::Array(base, _) => Some(Type::get_pointer(base.clone())),
+      _ => None,
+    };
+    let nested = Type::get_array(Type::get_array(i32.clone(), 2), 3);
+    assert_eq!(format!("{}", nested.map(flatten)), "*[i32, 2]");
+    // unchanged types are the same interned type
+    let id = |_: &Type| None;
+    assert!(Rc::ptr_eq(&func.map(id).0, &func.0));
+  }
+
+  #[test]
+  fn structural_compatibility() {
+    let i32 = Type::get_i32();
+    let arr = Type::get_array(i32.clone(), 2);
+    let func = Type::get_function(vec![Type::get_pointer(arr.clone())], Type::get_unit());
+    // types from another type pool
+    let arr2 = Type::get_uninterned(TypeKind::Array(Type::get_uninterned(TypeKind::Int32), 2));
+    let ptr2 = Type::get_uninterned(TypeKind::Pointer(arr2.clone()));
+    let func2 = Type::get_uninterned(TypeKind::Function(
+      vec![ptr2.clone()],
+      Type::get_uninterned(TypeKind::Unit),
+    ));
+    assert_ne!(arr, arr2);
+    assert!(arr.structurally_compatible(&arr2));
+    assert_ne!(func, func2);
+    assert!(func.structurally_compatible(&func2));
+    assert!(func2.structurally_compatible(&func));
+    assert!(!arr.structurally_compatible(&Type::get_array(i32.clone(), 3)));
+    assert!(!ptr2.structurally_compatible(&Type::get_pointer(i32.clone())));
+    assert!(!func.structurally_compatible(&Type::get_function(vec![], Type::get_unit())));
+    assert!(Type::get_named("@t".into()).structurally_compatible(&Type::get_named("@t".into())));
+    assert!(!Type::get_named("@t".into()).structurally_compatible(&i32));
+  }
 }
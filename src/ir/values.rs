@@ -1,7 +1,18 @@
 //! Definitions of Koopa IR values, including constants and instructions.
-
+//!
+//! This module also provides a constructor function for each kind of
+//! value, such as [`integer`], [`binary`] or [`load`]. These functions
+//! compute the type of the value data, and check the types of operands
+//! by using the given [`EntityInfoQuerier`], for example, a
+//! [`DataFlowGraph`](crate::ir::dfg::DataFlowGraph). The created value
+//! data can be inserted by [`ValueBuilder::raw`].
+//!
+//! [`ValueBuilder::raw`]: crate::ir::builder::ValueBuilder::raw
+
+use crate::ir::builder::EntityInfoQuerier;
 use crate::ir::entities::{BasicBlock, Function, Value, ValueData, ValueKind};
-use crate::ir::types::Type;
+use crate::ir::types::{Type, TypeKind};
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 
 /// Integer constant.
@@ -66,6 +77,60 @@ impl Aggregate {
   pub fn elems_mut(&mut self) -> &mut Vec<Value> {
     &mut self.elems
   }
+
+  /// Returns an iterator of the leaf scalar elements in row-major order,
+  /// descending into nested aggregates. Each item is an element and its
+  /// type. Element data are looked up in the given value map, for
+  /// example, [`DataFlowGraph::values`] for local aggregates, or
+  /// [`Program::borrow_values`] for global ones.
+  ///
+  /// Zero initializers and undefined values of array types are expanded
+  /// recursively to one item per scalar element. Each of these items is
+  /// the zero initializer or undefined value itself, with the type of
+  /// the scalar element.
+  ///
+  /// [`DataFlowGraph::values`]: crate::ir::dfg::DataFlowGraph::values
+  /// [`Program::borrow_values`]: crate::ir::Program::borrow_values
+  ///
+  /// # Panics
+  ///
+  /// Panics if any element does not exist in the given value map.
+  pub fn flatten<'a>(
+    &'a self,
+    values: &'a HashMap<Value, ValueData>,
+  ) -> impl Iterator<Item = (Value, Type)> + 'a {
+    // elements to be visited, with the types of expanded elements and
+    // the remaining numbers of repetitions
+    let mut stack: Vec<(Value, Option<Type>, usize)> =
+      self.elems.iter().rev().map(|e| (*e, None, 1)).collect();
+    std::iter::from_fn(move || loop {
+      let (value, ty, count) = stack.last_mut()?;
+      let (value, ty) = (*value, ty.clone());
+      if *count > 1 {
+        *count -= 1;
+      } else {
+        stack.pop();
+      }
+      let data = values.get(&value).expect("element does not exist");
+      let ty = ty.unwrap_or_else(|| data.ty().clone());
+      match data.kind() {
+        ValueKind::Aggregate(agg) => {
+          stack.extend(agg.elems().iter().rev().map(|e| (*e, None, 1)));
+          continue;
+        }
+        ValueKind::ZeroInit(_) | ValueKind::Undef(_) => {
+          if let TypeKind::Array(base, len) = ty.kind() {
+            if *len > 0 {
+              stack.push((value, Some(base.clone()), *len));
+            }
+            continue;
+          }
+        }
+        _ => {}
+      }
+      return Some((value, ty));
+    })
+  }
 }
 
 /// Function argument reference.
@@ -114,12 +179,33 @@ impl BlockArgRef {
 
 /// Local memory allocation.
 #[derive(Clone, Debug)]
-pub struct Alloc;
+pub struct Alloc {
+  align: Option<usize>,
+}
 
 impl Alloc {
-  pub(in crate::ir) fn new_data(ty: Type) -> ValueData {
+  pub(in crate::ir) fn new_data(ty: Type, align: Option<usize>) -> ValueData {
     assert!(!ty.is_unit(), "`ty` can not be unit");
-    ValueData::new(ty, ValueKind::Alloc(Self))
+    ValueData::new(ty, ValueKind::Alloc(Self { align }))
+  }
+
+  /// Returns the alignment of the allocation in bytes.
+  ///
+  /// Returns `None` if the alignment is not specified, in this case
+  /// the allocation is aligned to [`Type::align_of`] of the allocated
+  /// type.
+  pub fn align(&self) -> Option<usize> {
+    self.align
+  }
+
+  /// Sets the alignment of the allocation in bytes.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the given alignment is not a power of two.
+  pub fn set_align(&mut self, align: Option<usize>) {
+    assert_align(align);
+    self.align = align;
   }
 }
 
@@ -127,11 +213,22 @@ impl Alloc {
 #[derive(Clone, Debug)]
 pub struct GlobalAlloc {
   init: Value,
+  align: Option<usize>,
+  attrs: GlobalAttrs,
 }
 
 impl GlobalAlloc {
-  pub(in crate::ir) fn new_data(init: Value, ty: Type) -> ValueData {
-    ValueData::new(ty, ValueKind::GlobalAlloc(Self { init }))
+  pub(in crate::ir) fn new_data(init: Value, align: Option<usize>, ty: Type) -> ValueData {
+    Self::new_data_with_attrs(init, align, GlobalAttrs::default(), ty)
+  }
+
+  pub(in crate::ir) fn new_data_with_attrs(
+    init: Value,
+    align: Option<usize>,
+    attrs: GlobalAttrs,
+    ty: Type,
+  ) -> ValueData {
+    ValueData::new(ty, ValueKind::GlobalAlloc(Self { init, align, attrs }))
   }
 
   /// Returns the initializer.
@@ -143,6 +240,66 @@ impl GlobalAlloc {
   pub fn init_mut(&mut self) -> &mut Value {
     &mut self.init
   }
+
+  /// Returns the alignment of the allocation in bytes.
+  ///
+  /// Returns `None` if the alignment is not specified, in this case
+  /// the allocation is aligned to [`Type::align_of`] of the type of
+  /// the initializer.
+  pub fn align(&self) -> Option<usize> {
+    self.align
+  }
+
+  /// Sets the alignment of the allocation in bytes.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the given alignment is not a power of two.
+  pub fn set_align(&mut self, align: Option<usize>) {
+    assert_align(align);
+    self.align = align;
+  }
+
+  /// Returns the attributes of the allocation.
+  pub fn attrs(&self) -> GlobalAttrs {
+    self.attrs
+  }
+
+  /// Sets the attributes of the allocation.
+  pub fn set_attrs(&mut self, attrs: GlobalAttrs) {
+    self.attrs = attrs;
+  }
+}
+
+/// Attributes of global memory allocations.
+///
+/// In the text form, attributes are written after the initializer and
+/// before the alignment, for example
+/// `global @x = alloc i32, 1, readonly, threadlocal, align 4`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct GlobalAttrs {
+  /// Read-only, the allocated memory is never written after being
+  /// initialized, and can be placed in a read-only section. Storing to
+  /// the memory is an error.
+  pub readonly: bool,
+  /// Thread-local, each thread has its own copy of the allocated
+  /// memory, which is placed in a thread-local section.
+  pub thread_local: bool,
+}
+
+impl GlobalAttrs {
+  /// Returns `true` if no attribute is set.
+  pub fn is_empty(&self) -> bool {
+    !self.readonly && !self.thread_local
+  }
+}
+
+/// Asserts that the given alignment is a power of two.
+fn assert_align(align: Option<usize>) {
+  assert!(
+    align.is_none_or(|a| a.is_power_of_two()),
+    "alignment must be a power of two"
+  );
 }
 
 /// Memory load.
@@ -179,6 +336,10 @@ impl Store {
     ValueData::new(Type::get_unit(), ValueKind::Store(Self { value, dest }))
   }
 
+  pub(in crate::ir) fn uses_mut(&mut self) -> Vec<&mut Value> {
+    vec![&mut self.value, &mut self.dest]
+  }
+
   /// Returns the value of the memory store.
   pub fn value(&self) -> Value {
     self.value
@@ -212,6 +373,10 @@ impl GetPtr {
     ValueData::new(ty, ValueKind::GetPtr(Self { src, index }))
   }
 
+  pub(in crate::ir) fn uses_mut(&mut self) -> Vec<&mut Value> {
+    vec![&mut self.src, &mut self.index]
+  }
+
   /// Returns the source memory location.
   pub fn src(&self) -> Value {
     self.src
@@ -245,6 +410,10 @@ impl GetElemPtr {
     ValueData::new(ty, ValueKind::GetElemPtr(Self { src, index }))
   }
 
+  pub(in crate::ir) fn uses_mut(&mut self) -> Vec<&mut Value> {
+    vec![&mut self.src, &mut self.index]
+  }
+
   /// Returns the source memory location.
   pub fn src(&self) -> Value {
     self.src
@@ -267,16 +436,42 @@ impl GetElemPtr {
 }
 
 /// Binary operation.
+///
+/// The operation may carry [`BinaryFlags`], which make some results
+/// undefined, see [`BinaryFlags`] for details.
 #[derive(Clone, Debug)]
 pub struct Binary {
   op: BinaryOp,
+  flags: BinaryFlags,
   lhs: Value,
   rhs: Value,
 }
 
 impl Binary {
   pub(in crate::ir) fn new_data(op: BinaryOp, lhs: Value, rhs: Value, ty: Type) -> ValueData {
-    ValueData::new(ty, ValueKind::Binary(Self { op, lhs, rhs }))
+    Self::new_data_with_flags(op, BinaryFlags::default(), lhs, rhs, ty)
+  }
+
+  pub(in crate::ir) fn new_data_with_flags(
+    op: BinaryOp,
+    flags: BinaryFlags,
+    lhs: Value,
+    rhs: Value,
+    ty: Type,
+  ) -> ValueData {
+    ValueData::new(
+      ty,
+      ValueKind::Binary(Self {
+        op,
+        flags,
+        lhs,
+        rhs,
+      }),
+    )
+  }
+
+  pub(in crate::ir) fn uses_mut(&mut self) -> Vec<&mut Value> {
+    vec![&mut self.lhs, &mut self.rhs]
   }
 
   /// Returns the binary operator.
@@ -289,6 +484,16 @@ impl Binary {
     &mut self.op
   }
 
+  /// Returns the flags of the operation.
+  pub fn flags(&self) -> BinaryFlags {
+    self.flags
+  }
+
+  /// Returns a mutable reference to the flags of the operation.
+  pub fn flags_mut(&mut self) -> &mut BinaryFlags {
+    &mut self.flags
+  }
+
   /// Returns the left-hand side use.
   pub fn lhs(&self) -> Value {
     self.lhs
@@ -310,7 +515,306 @@ impl Binary {
   }
 }
 
+/// Flags of binary operations, which promise that the result of the
+/// operation is exact. Otherwise the result is undefined, and may be
+/// folded to `undef` by optimizers.
+///
+/// In the text form, flags are written after the operator in the order
+/// of fields, for example `add nsw nuw %0, 1` and `div exact %0, 4`.
+/// Backends ignore the flags.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct BinaryFlags {
+  /// No signed wrap, supported by `add`, `sub`, `mul` and `shl`:
+  /// the result does not overflow as signed integers.
+  pub nsw: bool,
+  /// No unsigned wrap, supported by `add`, `sub`, `mul` and `shl`:
+  /// the result does not overflow as unsigned integers.
+  pub nuw: bool,
+  /// Exact, supported by `div`, `shr` and `sar`: no non-zero bits
+  /// are discarded, that is, the remainder is zero.
+  pub exact: bool,
+}
+
+impl BinaryFlags {
+  /// Returns `true` if no flag is set.
+  pub fn is_empty(&self) -> bool {
+    !self.nsw && !self.nuw && !self.exact
+  }
+
+  /// Returns `true` if all set flags are supported by the given binary
+  /// operator.
+  pub fn is_supported(&self, op: BinaryOp) -> bool {
+    use BinaryOp::*;
+    let wrap = matches!(op, Add | Sub | Mul | Shl);
+    let exact = matches!(op, Div | Shr | Sar);
+    (wrap || !self.nsw && !self.nuw) && (exact || !self.exact)
+  }
+
+  /// Returns `true` if the given binary operation on the given operands
+  /// breaks the promise of the flags, in which case the result is
+  /// undefined.
+  ///
+  /// Shift amounts are taken modulo 32.
+  pub fn is_violated(&self, op: BinaryOp, lhs: i32, rhs: i32) -> bool {
+    let (ul, ur) = (lhs as u32, rhs as u32);
+    let shift = ur % 32;
+    let nsw = match op {
+      BinaryOp::Add => lhs.checked_add(rhs).is_none(),
+      BinaryOp::Sub => lhs.checked_sub(rhs).is_none(),
+      BinaryOp::Mul => lhs.checked_mul(rhs).is_none(),
+      BinaryOp::Shl => (lhs << shift) >> shift != lhs,
+      _ => false,
+    };
+    let nuw = match op {
+      BinaryOp::Add => ul.checked_add(ur).is_none(),
+      BinaryOp::Sub => ul.checked_sub(ur).is_none(),
+      BinaryOp::Mul => ul.checked_mul(ur).is_none(),
+      BinaryOp::Shl => (ul << shift) >> shift != ul,
+      _ => false,
+    };
+    let exact = match op {
+      BinaryOp::Div => rhs != 0 && lhs.wrapping_rem(rhs) != 0,
+      BinaryOp::Shr | BinaryOp::Sar => ul & ((1u64 << shift) - 1) as u32 != 0,
+      _ => false,
+    };
+    self.nsw && nsw || self.nuw && nuw || self.exact && exact
+  }
+}
+
+impl fmt::Display for BinaryFlags {
+  /// Writes the set flags separated by spaces.
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let flags = [(self.nsw, "nsw"), (self.nuw, "nuw"), (self.exact, "exact")];
+    let mut flags = flags.iter().filter(|(set, _)| *set).map(|(_, name)| name);
+    if let Some(flag) = flags.next() {
+      f.write_str(flag)?;
+      for flag in flags {
+        write!(f, " {}", flag)?;
+      }
+    }
+    Ok(())
+  }
+}
+
+/// Unary operation.
+///
+/// The operand and the result are both `i32`.
+#[derive(Clone, Debug)]
+pub struct Unary {
+  op: UnaryOp,
+  opr: Value,
+}
+
+impl Unary {
+  pub(in crate::ir) fn new_data(op: UnaryOp, opr: Value, ty: Type) -> ValueData {
+    ValueData::new(ty, ValueKind::Unary(Self { op, opr }))
+  }
+
+  /// Returns the unary operator.
+  pub fn op(&self) -> UnaryOp {
+    self.op
+  }
+
+  /// Returns a mutable reference to the unary operator.
+  pub fn op_mut(&mut self) -> &mut UnaryOp {
+    &mut self.op
+  }
+
+  /// Returns the operand.
+  pub fn opr(&self) -> Value {
+    self.opr
+  }
+
+  /// Returns a mutable reference to the operand.
+  pub fn opr_mut(&mut self) -> &mut Value {
+    &mut self.opr
+  }
+
+  /// Evaluates the operation on the given operand.
+  pub fn eval(&self, opr: i32) -> i32 {
+    self.op.eval(opr)
+  }
+}
+
+/// Element extraction from an aggregate value.
+///
+/// The aggregate value must be of an array type, and the result is
+/// the element at the given index.
+#[derive(Clone, Debug)]
+pub struct ExtractValue {
+  agg: Value,
+  index: usize,
+}
+
+impl ExtractValue {
+  pub(in crate::ir) fn new_data(agg: Value, index: usize, ty: Type) -> ValueData {
+    ValueData::new(ty, ValueKind::ExtractValue(Self { agg, index }))
+  }
+
+  pub(in crate::ir) fn uses_mut(&mut self) -> Vec<&mut Value> {
+    vec![&mut self.agg]
+  }
+
+  /// Returns the aggregate value.
+  pub fn agg(&self) -> Value {
+    self.agg
+  }
+
+  /// Returns a mutable reference to the aggregate value.
+  pub fn agg_mut(&mut self) -> &mut Value {
+    &mut self.agg
+  }
+
+  /// Returns the index of the element.
+  pub fn index(&self) -> usize {
+    self.index
+  }
+
+  /// Returns a mutable reference to the index of the element.
+  pub fn index_mut(&mut self) -> &mut usize {
+    &mut self.index
+  }
+}
+
+/// Element insertion into an aggregate value.
+///
+/// The aggregate value must be of an array type, and the result is
+/// a copy of the aggregate value, with the element at the given index
+/// replaced by the given value.
+#[derive(Clone, Debug)]
+pub struct InsertValue {
+  agg: Value,
+  value: Value,
+  index: usize,
+}
+
+impl InsertValue {
+  pub(in crate::ir) fn new_data(agg: Value, value: Value, index: usize, ty: Type) -> ValueData {
+    ValueData::new(ty, ValueKind::InsertValue(Self { agg, value, index }))
+  }
+
+  pub(in crate::ir) fn uses_mut(&mut self) -> Vec<&mut Value> {
+    vec![&mut self.agg, &mut self.value]
+  }
+
+  /// Returns the aggregate value.
+  pub fn agg(&self) -> Value {
+    self.agg
+  }
+
+  /// Returns a mutable reference to the aggregate value.
+  pub fn agg_mut(&mut self) -> &mut Value {
+    &mut self.agg
+  }
+
+  /// Returns the inserted value.
+  pub fn value(&self) -> Value {
+    self.value
+  }
+
+  /// Returns a mutable reference to the inserted value.
+  pub fn value_mut(&mut self) -> &mut Value {
+    &mut self.value
+  }
+
+  /// Returns the index of the element.
+  pub fn index(&self) -> usize {
+    self.index
+  }
+
+  /// Returns a mutable reference to the index of the element.
+  pub fn index_mut(&mut self) -> &mut usize {
+    &mut self.index
+  }
+}
+
+/// Overflow-checked binary operation.
+///
+/// The result is an array of type `[i32, 2]`. Element 0 is the result
+/// of the operation, wrapped to 32 bits (two's complement). Element 1 is
+/// `1` if the operation overflows as a signed operation, otherwise `0`.
+///
+/// Only [`BinaryOp::Add`], [`BinaryOp::Sub`] and [`BinaryOp::Mul`] are
+/// supported.
+#[derive(Clone, Debug)]
+pub struct CheckedBinary {
+  op: BinaryOp,
+  lhs: Value,
+  rhs: Value,
+}
+
+impl CheckedBinary {
+  pub(in crate::ir) fn new_data(op: BinaryOp, lhs: Value, rhs: Value, ty: Type) -> ValueData {
+    ValueData::new(ty, ValueKind::CheckedBinary(Self { op, lhs, rhs }))
+  }
+
+  pub(in crate::ir) fn uses_mut(&mut self) -> Vec<&mut Value> {
+    vec![&mut self.lhs, &mut self.rhs]
+  }
+
+  /// Returns `true` if the given binary operator is supported by
+  /// overflow-checked binary operations.
+  pub fn is_supported(op: BinaryOp) -> bool {
+    matches!(op, BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul)
+  }
+
+  /// Returns the binary operator.
+  pub fn op(&self) -> BinaryOp {
+    self.op
+  }
+
+  /// Returns a mutable reference to the binary operator.
+  pub fn op_mut(&mut self) -> &mut BinaryOp {
+    &mut self.op
+  }
+
+  /// Returns the left-hand side use.
+  pub fn lhs(&self) -> Value {
+    self.lhs
+  }
+
+  /// Returns a mutable reference to the left-hand side use.
+  pub fn lhs_mut(&mut self) -> &mut Value {
+    &mut self.lhs
+  }
+
+  /// Returns the right-hand side use.
+  pub fn rhs(&self) -> Value {
+    self.rhs
+  }
+
+  /// Returns a mutable reference to the right-hand side use.
+  pub fn rhs_mut(&mut self) -> &mut Value {
+    &mut self.rhs
+  }
+
+  /// Evaluates the operation on the given operands, returns the wrapped
+  /// result and whether the operation overflows.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the operator is not supported.
+  pub fn eval(&self, lhs: i32, rhs: i32) -> (i32, bool) {
+    match self.op {
+      BinaryOp::Add => lhs.overflowing_add(rhs),
+      BinaryOp::Sub => lhs.overflowing_sub(rhs),
+      BinaryOp::Mul => lhs.overflowing_mul(rhs),
+      op => panic!("unsupported checked binary operator `{}`", op),
+    }
+  }
+}
+
 /// Supported binary operators.
+///
+/// All operators take `i32` operands and produce an `i32` result.
+/// Comparisons produce `1` if the comparison holds, otherwise `0`.
+/// Arithmetic operations wrap around on overflow (two's complement).
+///
+/// [`And`](BinaryOp::And), [`Or`](BinaryOp::Or) and
+/// [`Xor`](BinaryOp::Xor) are bitwise operations, not logical ones.
+/// Since comparisons produce `0` or `1`, they also work as logical
+/// operations on comparison results, but without short-circuit
+/// evaluation, which must be expressed with control flow.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum BinaryOp {
   /// Not equal to.
@@ -331,9 +835,13 @@ pub enum BinaryOp {
   Sub,
   /// Multiplication.
   Mul,
-  /// Division.
+  /// Signed division, rounds toward zero.
+  ///
+  /// Constant folding in the front end and optimization passes follows
+  /// this rounding, and never folds a division by zero.
   Div,
-  /// Modulo.
+  /// Signed remainder of [`BinaryOp::Div`], has the same sign as the
+  /// dividend.
   Mod,
   /// Bitwise AND.
   And,
@@ -373,6 +881,100 @@ impl fmt::Display for BinaryOp {
   }
 }
 
+/// Supported unary operators.
+///
+/// All operators take an `i32` operand and produce an `i32` result.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum UnaryOp {
+  /// Bitwise NOT (complement).
+  Not,
+  /// Negation, wraps around on overflow.
+  Neg,
+}
+
+impl UnaryOp {
+  /// Evaluates the operator on the given operand.
+  pub fn eval(self, opr: i32) -> i32 {
+    match self {
+      UnaryOp::Not => !opr,
+      UnaryOp::Neg => opr.wrapping_neg(),
+    }
+  }
+
+  /// Returns the result type of the operator on an operand of the given
+  /// type, or `None` if the operator does not accept the type.
+  ///
+  /// `not` and `neg` accept integers, and preserve the operand type.
+  pub fn result_ty(self, opr: &Type) -> Option<Type> {
+    match self {
+      UnaryOp::Not | UnaryOp::Neg => opr.is_i32().then(|| opr.clone()),
+    }
+  }
+}
+
+impl fmt::Display for UnaryOp {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      UnaryOp::Not => f.write_str("not"),
+      UnaryOp::Neg => f.write_str("neg"),
+    }
+  }
+}
+
+/// Copy of a value.
+///
+/// The result is the source value, and has the same type. Copies are
+/// used to lower basic block arguments to moves, they can be removed by
+/// [`CopyPropagation`](crate::opt::CopyPropagation), and code generators
+/// may emit register moves or elide them.
+#[derive(Clone, Debug)]
+pub struct CopyValue {
+  src: Value,
+}
+
+impl CopyValue {
+  pub(in crate::ir) fn new_data(src: Value, ty: Type) -> ValueData {
+    ValueData::new(ty, ValueKind::Copy(Self { src }))
+  }
+
+  /// Returns the source value.
+  pub fn src(&self) -> Value {
+    self.src
+  }
+
+  /// Returns a mutable reference to the source value.
+  pub fn src_mut(&mut self) -> &mut Value {
+    &mut self.src
+  }
+}
+
+/// Assumption, an optimization hint that the condition is non-zero.
+///
+/// Analyses may refine ranges of values after the assumption, see
+/// [`IntRange`](crate::ir::analysis::IntRange). Executing an assumption
+/// whose condition is zero is undefined behavior, interpreters may
+/// report it as an error, and code generators may drop assumptions.
+#[derive(Clone, Debug)]
+pub struct Assume {
+  cond: Value,
+}
+
+impl Assume {
+  pub(in crate::ir) fn new_data(cond: Value) -> ValueData {
+    ValueData::new(Type::get_unit(), ValueKind::Assume(Self { cond }))
+  }
+
+  /// Returns the condition.
+  pub fn cond(&self) -> Value {
+    self.cond
+  }
+
+  /// Returns a mutable reference to the condition.
+  pub fn cond_mut(&mut self) -> &mut Value {
+    &mut self.cond
+  }
+}
+
 /// Conditional branch.
 #[derive(Clone, Debug)]
 pub struct Branch {
@@ -420,6 +1022,13 @@ impl Branch {
     )
   }
 
+  pub(in crate::ir) fn uses_mut(&mut self) -> Vec<&mut Value> {
+    let mut uses = vec![&mut self.cond];
+    uses.extend(self.true_args.iter_mut());
+    uses.extend(self.false_args.iter_mut());
+    uses
+  }
+
   /// Returns the branch condition.
   pub fn cond(&self) -> Value {
     self.cond
@@ -518,6 +1127,101 @@ impl Jump {
   }
 }
 
+/// Multi-way branch.
+#[derive(Clone, Debug)]
+pub struct Switch {
+  cond: Value,
+  default: BasicBlock,
+  default_args: Vec<Value>,
+  cases: Vec<SwitchCase>,
+}
+
+/// A case of multi-way branch, including the case value, the target
+/// basic block and the arguments passed to the target basic block.
+pub type SwitchCase = (i32, BasicBlock, Vec<Value>);
+
+impl Switch {
+  pub(in crate::ir) fn new_data(
+    cond: Value,
+    default: BasicBlock,
+    default_args: Vec<Value>,
+    cases: Vec<SwitchCase>,
+  ) -> ValueData {
+    ValueData::new(
+      Type::get_unit(),
+      ValueKind::Switch(Self {
+        cond,
+        default,
+        default_args,
+        cases,
+      }),
+    )
+  }
+
+  pub(in crate::ir) fn uses_mut(&mut self) -> Vec<&mut Value> {
+    let mut uses = vec![&mut self.cond];
+    uses.extend(self.default_args.iter_mut());
+    for (_, _, args) in &mut self.cases {
+      uses.extend(args.iter_mut());
+    }
+    uses
+  }
+
+  /// Returns the selector of the multi-way branch.
+  pub fn cond(&self) -> Value {
+    self.cond
+  }
+
+  /// Returns a mutable reference to the selector.
+  pub fn cond_mut(&mut self) -> &mut Value {
+    &mut self.cond
+  }
+
+  /// Returns the default target basic block.
+  pub fn default_bb(&self) -> BasicBlock {
+    self.default
+  }
+
+  /// Returns a mutable reference to the default target basic block.
+  pub fn default_bb_mut(&mut self) -> &mut BasicBlock {
+    &mut self.default
+  }
+
+  /// Returns a reference to the arguments passed to
+  /// the default target basic block.
+  pub fn default_args(&self) -> &[Value] {
+    &self.default_args
+  }
+
+  /// Returns a mutable reference to the arguments passed to
+  /// the default target basic block.
+  pub fn default_args_mut(&mut self) -> &mut Vec<Value> {
+    &mut self.default_args
+  }
+
+  /// Returns a reference to the cases.
+  pub fn cases(&self) -> &[SwitchCase] {
+    &self.cases
+  }
+
+  /// Returns a mutable reference to the cases.
+  pub fn cases_mut(&mut self) -> &mut Vec<SwitchCase> {
+    &mut self.cases
+  }
+
+  /// Returns the target basic block and arguments of the given
+  /// selector value.
+  pub fn target_of(&self, value: i32) -> (BasicBlock, &[Value]) {
+    self
+      .cases
+      .iter()
+      .find(|(v, _, _)| *v == value)
+      .map_or((self.default, &self.default_args), |(_, bb, args)| {
+        (*bb, args)
+      })
+  }
+}
+
 /// Function call.
 #[derive(Clone, Debug)]
 pub struct Call {
@@ -572,3 +1276,649 @@ impl Return {
     &mut self.value
   }
 }
+
+/// Unreachable.
+///
+/// A terminator that marks the end of code that can never be executed.
+#[derive(Clone, Debug)]
+pub struct Unreachable;
+
+impl Unreachable {
+  pub(in crate::ir) fn new_data() -> ValueData {
+    ValueData::new(Type::get_unit(), ValueKind::Unreachable(Self))
+  }
+}
+
+/// Creates an integer constant.
+///
+/// The result type is `i32`.
+pub fn integer(value: i32) -> ValueData {
+  Integer::new_data(value)
+}
+
+/// Creates a zero initializer.
+///
+/// The result type is the given type.
+///
+/// # Panics
+///
+/// Panics if the given type is a unit type.
+pub fn zero_init(ty: Type) -> ValueData {
+  assert!(!ty.is_unit(), "`ty` can not be unit");
+  ZeroInit::new_data(ty)
+}
+
+/// Creates an undefined value.
+///
+/// The result type is the given type.
+///
+/// # Panics
+///
+/// Panics if the given type is a unit type.
+pub fn undef(ty: Type) -> ValueData {
+  assert!(!ty.is_unit(), "`ty` can not be unit");
+  Undef::new_data(ty)
+}
+
+/// Creates an aggregate constant with elements `elems`.
+///
+/// The result type is `[T, N]`, where `T` is the type of elements,
+/// and `N` is the number of elements.
+///
+/// # Panics
+///
+/// Panics if:
+///
+/// * No elements are provided.
+/// * Presence of non-constant elements or unit type elements.
+/// * Elements have different types.
+pub fn aggregate(q: &impl EntityInfoQuerier, elems: Vec<Value>) -> ValueData {
+  // element list should not be empty
+  assert!(!elems.is_empty(), "`elems` must not be empty");
+  // check if all elements are constant
+  assert!(
+    elems.iter().all(|e| q.is_const(*e)),
+    "`elems` must all be constants"
+  );
+  // check if all elements have the same type
+  assert!(
+    elems
+      .windows(2)
+      .all(|e| q.value_type(e[0]) == q.value_type(e[1])),
+    "type mismatch in `elems`"
+  );
+  // check base type
+  let base = q.value_type(elems[0]);
+  assert!(!base.is_unit(), "base type must not be `unit`");
+  // create array type
+  let ty = Type::get_array(base, elems.len());
+  Aggregate::new_data(elems, ty)
+}
+
+/// Creates a reference to the basic block parameter at the given index.
+///
+/// The result type is the given type. The created value should be
+/// added to the parameter list of the basic block, see
+/// [`BasicBlockData::params_mut`](crate::ir::entities::BasicBlockData::params_mut).
+///
+/// # Panics
+///
+/// Panics if the given type is a unit type.
+pub fn block_arg_ref(index: usize, ty: Type) -> ValueData {
+  assert!(!ty.is_unit(), "`ty` can not be unit");
+  BlockArgRef::new_data(index, ty)
+}
+
+/// Creates a global memory allocation.
+///
+/// The result type is `*T`, where `T` is the type of the initializer.
+///
+/// # Panics
+///
+/// Panics if the type of the initializer can not be allocated, see
+/// [`Type::is_allocatable`].
+pub fn global_alloc(q: &impl EntityInfoQuerier, init: Value) -> ValueData {
+  global_alloc_with_align(q, init, None)
+}
+
+/// Creates a global memory allocation with the given alignment in bytes.
+///
+/// The result type is `*T`, where `T` is the type of the initializer.
+///
+/// # Panics
+///
+/// Panics if the type of the initializer can not be allocated (see
+/// [`Type::is_allocatable`]), or the given alignment is not a power of
+/// two.
+pub fn global_alloc_with_align(
+  q: &impl EntityInfoQuerier,
+  init: Value,
+  align: Option<usize>,
+) -> ValueData {
+  let init_ty = q.value_type(init);
+  assert!(
+    init_ty.is_allocatable(),
+    "the type of `init` ('{}') can not be allocated",
+    init_ty
+  );
+  assert_align(align);
+  GlobalAlloc::new_data(init, align, Type::get_pointer(init_ty))
+}
+
+/// Creates a local memory allocation.
+///
+/// The result type is `*T`, where `T` is the given type.
+///
+/// # Panics
+///
+/// Panics if the given type can not be allocated, see
+/// [`Type::is_allocatable`].
+pub fn alloc(ty: Type) -> ValueData {
+  alloc_with_align(ty, None)
+}
+
+/// Creates a local memory allocation with the given alignment in bytes.
+///
+/// The result type is `*T`, where `T` is the given type.
+///
+/// # Panics
+///
+/// Panics if the given type can not be allocated (see
+/// [`Type::is_allocatable`]), or the given alignment is not a power of
+/// two.
+pub fn alloc_with_align(ty: Type, align: Option<usize>) -> ValueData {
+  assert!(ty.is_allocatable(), "`ty` ('{}') can not be allocated", ty);
+  assert_align(align);
+  Alloc::new_data(Type::get_pointer(ty), align)
+}
+
+/// Creates a memory load with the given source.
+///
+/// The result type is `T`, where `*T` is the type of the source.
+///
+/// # Panics
+///
+/// Panics if the type of the source value is not a pointer type.
+pub fn load(q: &impl EntityInfoQuerier, src: Value) -> ValueData {
+  let ty = match q.value_type(src).kind() {
+    TypeKind::Pointer(ty) => ty.clone(),
+    _ => panic!("expected a pointer type"),
+  };
+  Load::new_data(src, ty)
+}
+
+/// Creates a memory store with the given value and destination.
+///
+/// The result type is `unit`.
+///
+/// # Panics
+///
+/// Panics if the dest type is not a pointer of the value type.
+pub fn store(q: &impl EntityInfoQuerier, value: Value, dest: Value) -> ValueData {
+  assert!(
+    Type::get_pointer(q.value_type(value)).structurally_compatible(&q.value_type(dest)),
+    "the type of `dest` must be the pointer of `value`'s type"
+  );
+  Store::new_data(value, dest)
+}
+
+/// Creates a pointer calculation with the given source pointer and index.
+///
+/// The result type is the type of the source.
+///
+/// # Panics
+///
+/// Panics if the source type is not a pointer type, or the index type is
+/// not an integer type.
+pub fn get_ptr(q: &impl EntityInfoQuerier, src: Value, index: Value) -> ValueData {
+  let src_ty = q.value_type(src);
+  assert!(
+    matches!(src_ty.kind(), TypeKind::Pointer(..)),
+    "`src` must be a pointer"
+  );
+  assert!(q.value_type(index).is_i32(), "`index` must be an integer");
+  GetPtr::new_data(src, index, src_ty)
+}
+
+/// Creates a element pointer calculation with the given source pointer
+/// and index.
+///
+/// The result type is `*T`, where `*[T, N]` is the type of the source.
+///
+/// # Panics
+///
+/// Panics if the source type is not a pointer type of an array, or the
+/// index type is not an integer type.
+pub fn get_elem_ptr(q: &impl EntityInfoQuerier, src: Value, index: Value) -> ValueData {
+  assert!(q.value_type(index).is_i32(), "`index` must be an integer");
+  let ty = match q.value_type(src).kind() {
+    TypeKind::Pointer(ty) => match ty.kind() {
+      TypeKind::Array(base, _) => Type::get_pointer(base.clone()),
+      _ => panic!("`src` must be a pointer of array"),
+    },
+    _ => panic!("`src` must be a pointer of array"),
+  };
+  GetElemPtr::new_data(src, index, ty)
+}
+
+/// Creates a binary operation.
+///
+/// The result type is `i32`.
+///
+/// # Panics
+///
+/// Panics if the lhs/rhs type is not an integer type.
+pub fn binary(q: &impl EntityInfoQuerier, op: BinaryOp, lhs: Value, rhs: Value) -> ValueData {
+  let lhs_ty = q.value_type(lhs);
+  let rhs_ty = q.value_type(rhs);
+  assert!(
+    lhs_ty.is_i32() && lhs_ty == rhs_ty,
+    "both `lhs` and `rhs` must be integer"
+  );
+  Binary::new_data(op, lhs, rhs, lhs_ty)
+}
+
+/// Creates a binary operation with the given flags.
+///
+/// The result type is `i32`.
+///
+/// # Panics
+///
+/// Panics if the lhs/rhs type is not an integer type, or the flags are
+/// not supported by the operator.
+pub fn binary_with_flags(
+  q: &impl EntityInfoQuerier,
+  op: BinaryOp,
+  flags: BinaryFlags,
+  lhs: Value,
+  rhs: Value,
+) -> ValueData {
+  assert!(flags.is_supported(op), "`flags` are not supported by `op`");
+  let mut data = binary(q, op, lhs, rhs);
+  if let ValueKind::Binary(bin) = data.kind_mut() {
+    bin.flags = flags;
+  }
+  data
+}
+
+/// Creates a unary operation.
+///
+/// The result type is `i32`.
+///
+/// # Panics
+///
+/// Panics if the operand type is not an integer type.
+pub fn unary(q: &impl EntityInfoQuerier, op: UnaryOp, opr: Value) -> ValueData {
+  let ty = op
+    .result_ty(&q.value_type(opr))
+    .expect("`opr` must be integer");
+  Unary::new_data(op, opr, ty)
+}
+
+/// Creates an overflow-checked binary operation with the given
+/// operator, lhs and rhs.
+///
+/// The result type is `[i32, 2]`, see [`CheckedBinary`] for details.
+///
+/// # Panics
+///
+/// Panics if the operator is not supported, or the lhs/rhs type is
+/// not an integer type.
+pub fn checked_binary(
+  q: &impl EntityInfoQuerier,
+  op: BinaryOp,
+  lhs: Value,
+  rhs: Value,
+) -> ValueData {
+  assert!(
+    CheckedBinary::is_supported(op),
+    "`op` must be `add`, `sub` or `mul`"
+  );
+  let lhs_ty = q.value_type(lhs);
+  let rhs_ty = q.value_type(rhs);
+  assert!(
+    lhs_ty.is_i32() && lhs_ty == rhs_ty,
+    "both `lhs` and `rhs` must be integer"
+  );
+  CheckedBinary::new_data(op, lhs, rhs, Type::get_array(lhs_ty, 2))
+}
+
+/// Creates an element extraction with the given aggregate value and
+/// index.
+///
+/// The result type is `T`, where `[T, N]` is the type of the aggregate.
+///
+/// # Panics
+///
+/// Panics if the aggregate type is not an array type, or the index
+/// is out of bounds.
+pub fn extract_value(q: &impl EntityInfoQuerier, agg: Value, index: usize) -> ValueData {
+  let ty = match q.value_type(agg).kind() {
+    TypeKind::Array(base, len) => {
+      assert!(index < *len, "`index` out of bounds");
+      base.clone()
+    }
+    _ => panic!("`agg` must be an array"),
+  };
+  ExtractValue::new_data(agg, index, ty)
+}
+
+/// Creates an element insertion with the given aggregate value,
+/// inserted value and index.
+///
+/// The result type is the type of the aggregate.
+///
+/// # Panics
+///
+/// Panics if the aggregate type is not an array type, the index is
+/// out of bounds, or the value type does not match the base type of
+/// the aggregate type.
+pub fn insert_value(
+  q: &impl EntityInfoQuerier,
+  agg: Value,
+  value: Value,
+  index: usize,
+) -> ValueData {
+  let ty = q.value_type(agg);
+  match ty.kind() {
+    TypeKind::Array(base, len) => {
+      assert!(index < *len, "`index` out of bounds");
+      assert!(
+        &q.value_type(value) == base,
+        "the type of `value` must match the base type of `agg`"
+      );
+    }
+    _ => panic!("`agg` must be an array"),
+  }
+  InsertValue::new_data(agg, value, index, ty)
+}
+
+/// Creates a copy of the given value.
+///
+/// The result type is the type of the source.
+///
+/// # Panics
+///
+/// Panics if the source type is `unit`.
+pub fn copy(q: &impl EntityInfoQuerier, src: Value) -> ValueData {
+  let ty = q.value_type(src);
+  assert!(!ty.is_unit(), "`src` must not be `unit`");
+  CopyValue::new_data(src, ty)
+}
+
+/// Creates an assumption with the given condition.
+///
+/// The result type is `unit`.
+///
+/// # Panics
+///
+/// Panics if the condition type is not an integer type.
+pub fn assume(q: &impl EntityInfoQuerier, cond: Value) -> ValueData {
+  assert!(q.value_type(cond).is_i32(), "`cond` must be integer");
+  Assume::new_data(cond)
+}
+
+/// Creates a conditional branch with the given condition and targets.
+///
+/// The result type is `unit`.
+///
+/// # Panics
+///
+/// Panics if the condition type is not an integer type, or the true/false
+/// basic block has parameters.
+pub fn branch(
+  q: &impl EntityInfoQuerier,
+  cond: Value,
+  true_bb: BasicBlock,
+  false_bb: BasicBlock,
+) -> ValueData {
+  assert!(q.value_type(cond).is_i32(), "`cond` must be integer");
+  assert!(
+    q.bb_params(true_bb).is_empty(),
+    "`true_bb` must not have parameters"
+  );
+  assert!(
+    q.bb_params(false_bb).is_empty(),
+    "`false_bb` must not have parameters"
+  );
+  Branch::new_data(cond, true_bb, false_bb)
+}
+
+/// Creates a conditional branch with the given condition, targets
+/// and arguments.
+///
+/// The result type is `unit`.
+///
+/// # Panics
+///
+/// Panics if the condition type is not an integer type, or the argument
+/// types of the true/false basic block do not match.
+pub fn branch_with_args(
+  q: &impl EntityInfoQuerier,
+  cond: Value,
+  true_bb: BasicBlock,
+  false_bb: BasicBlock,
+  true_args: Vec<Value>,
+  false_args: Vec<Value>,
+) -> ValueData {
+  assert!(q.value_type(cond).is_i32(), "`cond` must be integer");
+  check_bb_arg_types(q, true_bb, &true_args);
+  check_bb_arg_types(q, false_bb, &false_args);
+  Branch::with_args(cond, true_bb, false_bb, true_args, false_args)
+}
+
+/// Creates a unconditional jump with the given target.
+///
+/// The result type is `unit`.
+///
+/// # Panics
+///
+/// Panics if the target basic block has parameters.
+pub fn jump(q: &impl EntityInfoQuerier, target: BasicBlock) -> ValueData {
+  assert!(
+    q.bb_params(target).is_empty(),
+    "`target` must not have parameters"
+  );
+  Jump::new_data(target)
+}
+
+/// Creates a unconditional jump with the given target and arguments.
+///
+/// The result type is `unit`.
+///
+/// # Panics
+///
+/// Panics if the argument types of the target basic block do not match.
+pub fn jump_with_args(
+  q: &impl EntityInfoQuerier,
+  target: BasicBlock,
+  args: Vec<Value>,
+) -> ValueData {
+  check_bb_arg_types(q, target, &args);
+  Jump::with_args(target, args)
+}
+
+/// Creates a multi-way branch with the given selector, default target
+/// and cases (case value and target basic block).
+///
+/// The result type is `unit`.
+///
+/// # Panics
+///
+/// Panics if the selector type is not an integer type, the default/case
+/// basic block has parameters, or there are duplicate case values.
+pub fn switch(
+  q: &impl EntityInfoQuerier,
+  cond: Value,
+  default: BasicBlock,
+  cases: Vec<(i32, BasicBlock)>,
+) -> ValueData {
+  let cases = cases
+    .into_iter()
+    .map(|(v, bb)| (v, bb, Vec::new()))
+    .collect();
+  switch_with_args(q, cond, default, Vec::new(), cases)
+}
+
+/// Creates a multi-way branch with the given selector, default target,
+/// default arguments and cases (case value, target basic block and
+/// arguments).
+///
+/// The result type is `unit`.
+///
+/// # Panics
+///
+/// Panics if the selector type is not an integer type, the argument
+/// types of the default/case basic block do not match, or there are
+/// duplicate case values.
+pub fn switch_with_args(
+  q: &impl EntityInfoQuerier,
+  cond: Value,
+  default: BasicBlock,
+  default_args: Vec<Value>,
+  cases: Vec<SwitchCase>,
+) -> ValueData {
+  assert!(q.value_type(cond).is_i32(), "`cond` must be integer");
+  check_bb_arg_types(q, default, &default_args);
+  let mut values = HashSet::new();
+  for (v, bb, args) in &cases {
+    assert!(values.insert(*v), "duplicate case value {}", v);
+    check_bb_arg_types(q, *bb, args);
+  }
+  Switch::new_data(cond, default, default_args, cases)
+}
+
+/// Creates a function call.
+///
+/// The result type is the return type of the callee.
+///
+/// # Panics
+///
+/// Panics if the argument types of the callee do not match.
+pub fn call(q: &impl EntityInfoQuerier, callee: Function, args: Vec<Value>) -> ValueData {
+  let ty = match q.func_type(callee).kind() {
+    TypeKind::Function(params, ret) => {
+      assert!(
+        params
+          .iter()
+          .zip(args.iter())
+          .all(|(ty, a)| ty.structurally_compatible(&q.value_type(*a))),
+        "argument type mismatch"
+      );
+      ret.clone()
+    }
+    _ => panic!("expected a function type"),
+  };
+  Call::new_data(callee, args, ty)
+}
+
+/// Creates a new return instruction.
+///
+/// The result type is `unit`.
+///
+/// # Panics
+///
+/// Panics if the value type (if value is not `None`) is a unit type.
+pub fn ret(q: &impl EntityInfoQuerier, value: Option<Value>) -> ValueData {
+  assert!(
+    value.is_none_or(|v| !q.value_type(v).is_unit()),
+    "the type of `value` must not be `unit`"
+  );
+  Return::new_data(value)
+}
+
+/// Creates a new unreachable instruction.
+///
+/// The result type is `unit`.
+pub fn unreachable() -> ValueData {
+  Unreachable::new_data()
+}
+
+/// Checks if the parameter types of the given basic block matches
+/// the given argument types.
+///
+/// # Panics
+///
+/// Panics if the parameter types of the given basic block does not
+/// match the given argument types.
+fn check_bb_arg_types(q: &impl EntityInfoQuerier, bb: BasicBlock, args: &[Value]) {
+  let params = q.bb_params(bb);
+  assert!(
+    params.len() == args.len()
+      && params
+        .iter()
+        .zip(args.iter())
+        .all(|(p, a)| q.value_type(*p).structurally_compatible(&q.value_type(*a))),
+    "arguments type of basic block mismatch"
+  );
+}
+
+#[cfg(test)]
+mod test {
+  use crate::ir::builder_traits::*;
+  use crate::ir::{Program, Type, ValueKind};
+
+  #[test]
+  fn flatten_aggregate() {
+    let mut program = Program::new();
+    let ints: Vec<_> = (1..=4).map(|i| program.new_value().integer(i)).collect();
+    let row1 = program.new_value().aggregate(vec![ints[0], ints[1]]);
+    let row2 = program.new_value().aggregate(vec![ints[2], ints[3]]);
+    let zero = program
+      .new_value()
+      .zero_init(Type::get_array(Type::get_i32(), 2));
+    let agg = program.new_value().aggregate(vec![row1, zero, row2]);
+    // zero initializers of nested arrays are expanded recursively
+    let mat = Type::get_array(Type::get_array(Type::get_i32(), 2), 3);
+    let zeros = program.new_value().zero_init(mat);
+    let agg = program.new_value().aggregate(vec![agg, zeros]);
+    let values = program.borrow_values();
+    let elems: Vec<_> = match values[&agg].kind() {
+      ValueKind::Aggregate(agg) => agg.flatten(&values).collect(),
+      _ => unreachable!(),
+    };
+    assert!(elems.iter().all(|(_, ty)| ty.is_i32()));
+    let elems: Vec<_> = elems.into_iter().map(|(e, _)| e).collect();
+    let mut expected = vec![ints[0], ints[1], zero, zero, ints[2], ints[3]];
+    expected.extend([zeros; 6]);
+    assert_eq!(elems, expected);
+  }
+
+  #[test]
+  fn binary_flags() {
+    use super::{BinaryFlags, BinaryOp};
+    let nsw = BinaryFlags {
+      nsw: true,
+      ..Default::default()
+    };
+    let nuw = BinaryFlags {
+      nuw: true,
+      ..Default::default()
+    };
+    let exact = BinaryFlags {
+      exact: true,
+      ..Default::default()
+    };
+    assert!(nsw.is_supported(BinaryOp::Shl) && !nsw.is_supported(BinaryOp::Div));
+    assert!(exact.is_supported(BinaryOp::Sar) && !exact.is_supported(BinaryOp::Add));
+    assert!(BinaryFlags::default().is_supported(BinaryOp::Eq));
+    assert!(nsw.is_violated(BinaryOp::Add, i32::MAX, 1));
+    assert!(!nuw.is_violated(BinaryOp::Add, i32::MAX, 1));
+    assert!(nuw.is_violated(BinaryOp::Sub, 0, 1));
+    assert!(!nsw.is_violated(BinaryOp::Sub, 0, 1));
+    assert!(nsw.is_violated(BinaryOp::Shl, 1 << 30, 1));
+    assert!(!nuw.is_violated(BinaryOp::Shl, 1 << 30, 1));
+    assert!(nuw.is_violated(BinaryOp::Shl, -1, 1));
+    assert!(exact.is_violated(BinaryOp::Div, 7, 2));
+    assert!(!exact.is_violated(BinaryOp::Div, -8, 2));
+    assert!(exact.is_violated(BinaryOp::Sar, 6, 2));
+    assert!(!exact.is_violated(BinaryOp::Shr, 8, 3));
+    let all = BinaryFlags {
+      nsw: true,
+      nuw: true,
+      exact: false,
+    };
+    assert_eq!(all.to_string(), "nsw nuw");
+    assert_eq!(BinaryFlags::default().to_string(), "");
+  }
+}
@@ -0,0 +1,2512 @@
+//! Verifier of Koopa IR.
+//!
+//! The verifier checks if functions or programs are well-formed, and
+//! reports all found problems as [`VerifyError`]s. It can also audit
+//! the use lists of values and basic blocks ([`audit_use_lists`]).
+//!
+//! [`report_program`] and [`report_func`] also check for suspicious but
+//! well-formed code, and report errors and [`VerifyWarning`]s separately
+//! in a [`VerifyReport`]. Each [`Diagnostic`] has a severity and a
+//! stable code, like `K001`.
+
+use crate::ir::analysis::{dead_stores, DominatorTree, Relocation};
+use crate::ir::entities::{BasicBlock, FunctionData, Program, Value, ValueData, ValueKind};
+use crate::ir::span::SourceSpan;
+use crate::ir::types::{NamedTypes, Type, TypeKind};
+use crate::ir::values::{BinaryFlags, BinaryOp, CheckedBinary, UnaryOp};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// Error reported by the verifier.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyError {
+  /// The number of arguments passed to a basic block does not match
+  /// the number of its parameters.
+  BlockArgNum {
+    /// The predecessor basic block.
+    pred: BasicBlock,
+    /// The branch/jump instruction in the predecessor.
+    inst: Value,
+    /// The target basic block.
+    bb: BasicBlock,
+    /// The number of parameters of the target basic block.
+    expected: usize,
+    /// The number of arguments.
+    found: usize,
+  },
+  /// The type of an argument passed to a basic block does not match
+  /// the type of the corresponding parameter.
+  BlockArgType {
+    /// The predecessor basic block.
+    pred: BasicBlock,
+    /// The branch/jump instruction in the predecessor.
+    inst: Value,
+    /// The target basic block.
+    bb: BasicBlock,
+    /// Index of the argument.
+    index: usize,
+    /// Type of the parameter.
+    expected: Type,
+    /// Type of the argument.
+    found: Type,
+  },
+  /// A reachable basic block does not end with a terminator.
+  MissingTerminator {
+    /// The basic block.
+    bb: BasicBlock,
+  },
+  /// A `ret` instruction returns no value, but the function returns
+  /// a non-unit type.
+  MissingReturnValue {
+    /// The basic block that contains the return instruction.
+    bb: BasicBlock,
+    /// The return instruction.
+    inst: Value,
+    /// Return type of the function.
+    expected: Type,
+  },
+  /// The number of elements of an aggregate does not match the length
+  /// of its array type.
+  AggregateLen {
+    /// The aggregate value.
+    value: Value,
+    /// Length of the array type.
+    expected: usize,
+    /// The number of elements.
+    found: usize,
+  },
+  /// The type of an element of an aggregate does not match the base
+  /// type of its array type.
+  AggregateElemType {
+    /// The aggregate value.
+    value: Value,
+    /// Index of the first mismatching element.
+    index: usize,
+    /// Base type of the array type.
+    expected: Type,
+    /// Type of the element.
+    found: Type,
+  },
+  /// The index of an element extraction/insertion is out of bounds.
+  AggregateIndex {
+    /// The element extraction/insertion.
+    inst: Value,
+    /// The index.
+    index: usize,
+    /// Length of the array type of the aggregate.
+    len: usize,
+  },
+  /// An overflow-checked binary operation has an unsupported operator.
+  CheckedBinaryOp {
+    /// The overflow-checked binary operation.
+    inst: Value,
+    /// The operator.
+    op: BinaryOp,
+  },
+  /// A binary operation has flags that are not supported by its
+  /// operator, see [`BinaryFlags::is_supported`].
+  BinaryFlags {
+    /// The binary operation.
+    inst: Value,
+    /// The operator.
+    op: BinaryOp,
+    /// The flags.
+    flags: BinaryFlags,
+  },
+  /// The operand type of a unary operation is not accepted by the
+  /// operator, or the result type does not match the operand type,
+  /// see [`UnaryOp::result_ty`].
+  UnaryType {
+    /// The unary operation.
+    inst: Value,
+    /// The operator.
+    op: UnaryOp,
+    /// Type of the operand.
+    opr: Type,
+    /// Type of the result.
+    found: Type,
+  },
+  /// An instruction uses a value whose definition does not dominate it.
+  UndominatedUse {
+    /// The basic block that contains the instruction.
+    bb: BasicBlock,
+    /// The instruction.
+    inst: Value,
+    /// The used value.
+    value: Value,
+  },
+  /// The initializer of a global allocation contains a value that can
+  /// not be folded to a constant or a relocation.
+  NonConstantInit {
+    /// The global allocation.
+    alloc: Value,
+    /// The value in the initializer.
+    value: Value,
+  },
+  /// A local or global allocation allocates a type that can not be
+  /// allocated, see [`Type::is_allocatable`]. Named types must be
+  /// defined as types that can be allocated, which is only checked when
+  /// verifying programs, see [`NamedTypes::is_allocatable`].
+  NotAllocatable {
+    /// The allocation.
+    alloc: Value,
+    /// The allocated type.
+    ty: Type,
+  },
+  /// A value has the same name as an earlier value in the function.
+  DuplicateValueName {
+    /// The later value.
+    value: Value,
+    /// The duplicate name.
+    name: String,
+  },
+  /// A basic block has the same name as an earlier basic block in the
+  /// function.
+  DuplicateBlockName {
+    /// The later basic block.
+    bb: BasicBlock,
+    /// The duplicate name.
+    name: String,
+  },
+  /// A `store` instruction writes to a read-only global allocation, or
+  /// to a pointer derived from it.
+  StoreToReadOnly {
+    /// The store instruction.
+    inst: Value,
+    /// The global allocation.
+    global: Value,
+  },
+  /// The number of arguments passed to a function does not match the
+  /// number of its parameters.
+  CallArgNum {
+    /// The call instruction.
+    inst: Value,
+    /// The number of parameters of the callee.
+    expected: usize,
+    /// The number of arguments.
+    found: usize,
+  },
+  /// The type of an argument passed to a function is not compatible
+  /// with the type of the corresponding parameter.
+  CallArgType {
+    /// The call instruction.
+    inst: Value,
+    /// Index of the argument.
+    index: usize,
+    /// Type of the parameter.
+    expected: Type,
+    /// Type of the argument.
+    found: Type,
+  },
+  /// The type of the destination of a `store` instruction is not
+  /// compatible with the pointer of the type of the stored value.
+  StoreType {
+    /// The store instruction.
+    inst: Value,
+    /// Pointer of the type of the stored value.
+    expected: Type,
+    /// Type of the destination.
+    found: Type,
+  },
+  /// The type of a call instruction is not compatible with the return
+  /// type of the callee.
+  CallRetType {
+    /// The call instruction.
+    inst: Value,
+    /// Return type of the callee.
+    expected: Type,
+    /// Type of the call instruction.
+    found: Type,
+  },
+  /// The result of a call to a function that returns unit is used.
+  UnitCallResultUsed {
+    /// The call instruction.
+    inst: Value,
+    /// The first user of the result.
+    user: Value,
+  },
+  /// A `ret` instruction returns a value whose type is not compatible
+  /// with the return type of the function, or returns a value while the
+  /// function returns unit.
+  ReturnType {
+    /// The basic block that contains the return instruction.
+    bb: BasicBlock,
+    /// The return instruction.
+    inst: Value,
+    /// Return type of the function.
+    expected: Type,
+    /// Type of the returned value.
+    found: Type,
+  },
+  /// The type of a `copy` instruction is not compatible with the type of
+  /// its source.
+  CopyType {
+    /// The copy instruction.
+    inst: Value,
+    /// Type of the source.
+    expected: Type,
+    /// Type of the copy instruction.
+    found: Type,
+  },
+}
+
+impl fmt::Display for VerifyError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::BlockArgNum {
+        pred,
+        inst,
+        bb,
+        expected,
+        found,
+      } => write!(
+        f,
+        "{:?} (in predecessor {:?}) passes {} argument(s) to {:?}, expected {}",
+        inst, pred, found, bb, expected
+      ),
+      Self::BlockArgType {
+        pred,
+        inst,
+        bb,
+        index,
+        expected,
+        found,
+      } => write!(
+        f,
+        "{:?} (in predecessor {:?}) passes argument #{} of type {} to {:?}, expected {}",
+        inst, pred, index, found, bb, expected
+      ),
+      Self::MissingTerminator { bb } => {
+        write!(f, "basic block {:?} does not end with a terminator", bb)
+      }
+      Self::MissingReturnValue { bb, inst, expected } => write!(
+        f,
+        "{:?} (in basic block {:?}) returns no value, expected a value of type {}",
+        inst, bb, expected
+      ),
+      Self::AggregateLen {
+        value,
+        expected,
+        found,
+      } => write!(
+        f,
+        "aggregate {:?} has {} element(s), expected {}",
+        value, found, expected
+      ),
+      Self::AggregateElemType {
+        value,
+        index,
+        expected,
+        found,
+      } => write!(
+        f,
+        "element #{} of aggregate {:?} has type {}, expected {}",
+        index, value, found, expected
+      ),
+      Self::AggregateIndex { inst, index, len } => write!(
+        f,
+        "{:?} accesses element #{} of an aggregate of length {}",
+        inst, index, len
+      ),
+      Self::CheckedBinaryOp { inst, op } => write!(
+        f,
+        "{:?} performs checked operation '{}', expected 'add', 'sub' or 'mul'",
+        inst, op
+      ),
+      Self::BinaryFlags { inst, op, flags } => write!(
+        f,
+        "{:?} performs '{}' with unsupported flags '{}'",
+        inst, op, flags
+      ),
+      Self::UnaryType {
+        inst,
+        op,
+        opr,
+        found,
+      } => match op.result_ty(opr) {
+        Some(expected) => write!(
+          f,
+          "{:?} performs '{}' on type {} and produces type {}, expected {}",
+          inst, op, opr, found, expected
+        ),
+        None => write!(
+          f,
+          "{:?} performs '{}' on unsupported type {}",
+          inst, op, opr
+        ),
+      },
+      Self::UndominatedUse { bb, inst, value } => write!(
+        f,
+        "{:?} (in basic block {:?}) uses {:?}, whose definition does not dominate the use",
+        inst, bb, value
+      ),
+      Self::NonConstantInit { alloc, value } => write!(
+        f,
+        "initializer of {:?} contains {:?}, which is not constant-foldable",
+        alloc, value
+      ),
+      Self::NotAllocatable { alloc, ty } => write!(
+        f,
+        "{:?} allocates type {}, which can not be allocated",
+        alloc, ty
+      ),
+      Self::DuplicateValueName { value, name } => {
+        write!(f, "value {:?} reuses name '{}'", value, name)
+      }
+      Self::DuplicateBlockName { bb, name } => {
+        write!(f, "basic block {:?} reuses name '{}'", bb, name)
+      }
+      Self::StoreToReadOnly { inst, global } => {
+        write!(f, "{:?} writes to read-only global {:?}", inst, global)
+      }
+      Self::CallArgNum {
+        inst,
+        expected,
+        found,
+      } => write!(
+        f,
+        "{:?} passes {} argument(s) to the callee, expected {}",
+        inst, found, expected
+      ),
+      Self::CallArgType {
+        inst,
+        index,
+        expected,
+        found,
+      } => write!(
+        f,
+        "{:?} passes argument #{} of type {} to the callee, expected {}",
+        inst, index, found, expected
+      ),
+      Self::StoreType {
+        inst,
+        expected,
+        found,
+      } => write!(
+        f,
+        "{:?} stores to destination of type {}, expected {}",
+        inst, found, expected
+      ),
+      Self::CallRetType {
+        inst,
+        expected,
+        found,
+      } => write!(
+        f,
+        "{:?} has type {}, expected return type {} of the callee",
+        inst, found, expected
+      ),
+      Self::UnitCallResultUsed { inst, user } => write!(
+        f,
+        "{:?} uses the result of {:?}, whose callee returns unit",
+        user, inst
+      ),
+      Self::ReturnType {
+        bb,
+        inst,
+        expected,
+        found,
+      } => write!(
+        f,
+        "{:?} (in basic block {:?}) returns a value of type {}, expected {}",
+        inst, bb, found, expected
+      ),
+      Self::CopyType {
+        inst,
+        expected,
+        found,
+      } => write!(
+        f,
+        "{:?} has type {}, expected type {} of the source",
+        inst, found, expected
+      ),
+    }
+  }
+}
+
+impl VerifyError {
+  /// Returns the stable code of the error, like `K001`.
+  pub fn code(&self) -> &'static str {
+    match self {
+      Self::BlockArgNum { .. } => "K001",
+      Self::BlockArgType { .. } => "K002",
+      Self::MissingTerminator { .. } => "K003",
+      Self::MissingReturnValue { .. } => "K004",
+      Self::AggregateLen { .. } => "K005",
+      Self::AggregateElemType { .. } => "K006",
+      Self::AggregateIndex { .. } => "K007",
+      Self::CheckedBinaryOp { .. } => "K008",
+      Self::BinaryFlags { .. } => "K009",
+      Self::UnaryType { .. } => "K010",
+      Self::UndominatedUse { .. } => "K011",
+      Self::NonConstantInit { .. } => "K012",
+      Self::NotAllocatable { .. } => "K013",
+      Self::DuplicateValueName { .. } => "K014",
+      Self::DuplicateBlockName { .. } => "K015",
+      Self::StoreToReadOnly { .. } => "K016",
+      Self::CallArgNum { .. } => "K017",
+      Self::CallArgType { .. } => "K018",
+      Self::StoreType { .. } => "K019",
+      Self::CallRetType { .. } => "K020",
+      Self::UnitCallResultUsed { .. } => "K021",
+      Self::ReturnType { .. } => "K022",
+      Self::CopyType { .. } => "K023",
+    }
+  }
+
+  /// Returns the value that the error is reported on, or `None` if
+  /// the error is reported on a basic block.
+  pub fn value(&self) -> Option<Value> {
+    match self {
+      Self::BlockArgNum { inst, .. }
+      | Self::BlockArgType { inst, .. }
+      | Self::MissingReturnValue { inst, .. }
+      | Self::AggregateIndex { inst, .. }
+      | Self::CheckedBinaryOp { inst, .. }
+      | Self::BinaryFlags { inst, .. }
+      | Self::UnaryType { inst, .. }
+      | Self::UndominatedUse { inst, .. }
+      | Self::StoreToReadOnly { inst, .. }
+      | Self::CallArgNum { inst, .. }
+      | Self::CallArgType { inst, .. }
+      | Self::StoreType { inst, .. }
+      | Self::CallRetType { inst, .. }
+      | Self::UnitCallResultUsed { inst, .. }
+      | Self::ReturnType { inst, .. }
+      | Self::CopyType { inst, .. } => Some(*inst),
+      Self::AggregateLen { value, .. } | Self::AggregateElemType { value, .. } => Some(*value),
+      Self::NonConstantInit { alloc, .. } | Self::NotAllocatable { alloc, .. } => Some(*alloc),
+      Self::DuplicateValueName { value, .. } => Some(*value),
+      Self::MissingTerminator { .. } | Self::DuplicateBlockName { .. } => None,
+    }
+  }
+
+  /// Returns the source span of the value that the error is reported
+  /// on, or `None` if no span is recorded, see [`Program::span`].
+  pub fn span<'a>(&self, program: &'a Program) -> Option<&'a SourceSpan> {
+    self.value().and_then(|v| program.span(v))
+  }
+
+  /// Returns a displayable form of the error with its location in
+  /// the given program.
+  ///
+  /// The location is `file:line:col` if the source span is available,
+  /// otherwise the name of the value or the basic block that the error
+  /// is reported on.
+  pub fn display_in<'a>(&'a self, program: &'a Program) -> LocatedVerifyError<'a> {
+    LocatedVerifyError {
+      diag: Diagnostic::Error(self),
+      message: self,
+      program,
+    }
+  }
+}
+
+/// Warning reported by the verifier.
+///
+/// Warnings are reported on well-formed but suspicious code, so they
+/// do not prevent the program from being compiled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyWarning {
+  /// A basic block is unreachable from the entry basic block.
+  UnreachableBlock {
+    /// The basic block.
+    bb: BasicBlock,
+  },
+  /// A `store` instruction is dead, see [`dead_stores`].
+  DeadStore {
+    /// The store instruction.
+    inst: Value,
+  },
+  /// A function parameter is never used.
+  UnusedParam {
+    /// The parameter.
+    param: Value,
+    /// Index of the parameter.
+    index: usize,
+  },
+}
+
+impl fmt::Display for VerifyWarning {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::UnreachableBlock { bb } => write!(f, "basic block {:?} is unreachable", bb),
+      Self::DeadStore { inst } => write!(f, "{:?} stores a value that is never loaded", inst),
+      Self::UnusedParam { param, index } => {
+        write!(f, "parameter #{} ({:?}) is never used", index, param)
+      }
+    }
+  }
+}
+
+impl VerifyWarning {
+  /// Returns the stable code of the warning, like `K101`.
+  pub fn code(&self) -> &'static str {
+    match self {
+      Self::UnreachableBlock { .. } => "K101",
+      Self::DeadStore { .. } => "K102",
+      Self::UnusedParam { .. } => "K103",
+    }
+  }
+
+  /// Returns the value that the warning is reported on, or `None` if
+  /// the warning is reported on a basic block.
+  pub fn value(&self) -> Option<Value> {
+    match self {
+      Self::DeadStore { inst } => Some(*inst),
+      Self::UnusedParam { param, .. } => Some(*param),
+      Self::UnreachableBlock { .. } => None,
+    }
+  }
+}
+
+/// Severity of a [`Diagnostic`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+  /// Valid but suspicious code.
+  Warning,
+  /// Malformed code, which can not be compiled.
+  Error,
+}
+
+impl fmt::Display for Severity {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Warning => write!(f, "warning"),
+      Self::Error => write!(f, "error"),
+    }
+  }
+}
+
+/// A diagnostic reported by the verifier, which is either an error or
+/// a warning.
+///
+/// Diagnostics are displayed as `severity[code]: message`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Diagnostic<'a> {
+  /// An error.
+  Error(&'a VerifyError),
+  /// A warning.
+  Warning(&'a VerifyWarning),
+}
+
+impl<'a> Diagnostic<'a> {
+  /// Returns the severity of the diagnostic.
+  pub fn severity(&self) -> Severity {
+    match self {
+      Self::Error(_) => Severity::Error,
+      Self::Warning(_) => Severity::Warning,
+    }
+  }
+
+  /// Returns the stable code of the diagnostic.
+  pub fn code(&self) -> &'static str {
+    match self {
+      Self::Error(e) => e.code(),
+      Self::Warning(w) => w.code(),
+    }
+  }
+
+  /// Returns the value that the diagnostic is reported on, or `None`
+  /// if the diagnostic is reported on a basic block.
+  pub fn value(&self) -> Option<Value> {
+    match self {
+      Self::Error(e) => e.value(),
+      Self::Warning(w) => w.value(),
+    }
+  }
+
+  /// Returns the basic block that the diagnostic is reported on, or
+  /// `None` if the diagnostic is reported on a value.
+  fn bb(&self) -> Option<BasicBlock> {
+    match self {
+      Self::Error(VerifyError::MissingTerminator { bb })
+      | Self::Error(VerifyError::DuplicateBlockName { bb, .. })
+      | Self::Warning(VerifyWarning::UnreachableBlock { bb }) => Some(*bb),
+      _ => None,
+    }
+  }
+
+  /// Returns the source span of the value that the diagnostic is
+  /// reported on, or `None` if no span is recorded.
+  pub fn span<'p>(&self, program: &'p Program) -> Option<&'p SourceSpan> {
+    self.value().and_then(|v| program.span(v))
+  }
+
+  /// Returns a displayable form of the diagnostic with its location in
+  /// the given program, see [`VerifyError::display_in`].
+  pub fn display_in(&'a self, program: &'a Program) -> LocatedVerifyError<'a> {
+    LocatedVerifyError {
+      diag: *self,
+      message: self,
+      program,
+    }
+  }
+}
+
+impl fmt::Display for Diagnostic<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}[{}]: ", self.severity(), self.code())?;
+    match self {
+      Self::Error(e) => write!(f, "{}", e),
+      Self::Warning(w) => write!(f, "{}", w),
+    }
+  }
+}
+
+/// A [`VerifyError`] or a [`Diagnostic`] with its location in a program,
+/// returned by [`VerifyError::display_in`] and [`Diagnostic::display_in`].
+pub struct LocatedVerifyError<'a> {
+  diag: Diagnostic<'a>,
+  message: &'a dyn fmt::Display,
+  program: &'a Program,
+}
+
+impl LocatedVerifyError<'_> {
+  /// Returns the name of the value or the basic block that the
+  /// diagnostic is reported on.
+  fn name(&self) -> Option<String> {
+    let program = self.program;
+    match (self.diag.value(), self.diag.bb()) {
+      (Some(v), _) if v.is_global() => program.borrow_values().get(&v)?.name().clone(),
+      (Some(v), _) => program
+        .funcs()
+        .values()
+        .find_map(|f| f.dfg().values().get(&v))?
+        .name()
+        .clone(),
+      (None, Some(bb)) => program
+        .funcs()
+        .values()
+        .find_map(|f| f.dfg().bbs().get(&bb))?
+        .name()
+        .clone(),
+      _ => None,
+    }
+  }
+}
+
+impl fmt::Display for LocatedVerifyError<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    if let Some(span) = self.diag.span(self.program) {
+      write!(f, "{}: {}", span, self.message)
+    } else if let Some(name) = self.name() {
+      write!(f, "{}: {}", name, self.message)
+    } else {
+      write!(f, "{}", self.message)
+    }
+  }
+}
+
+/// Errors and warnings reported by [`report_program`] and
+/// [`report_func`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VerifyReport {
+  /// Errors, on malformed code.
+  pub errors: Vec<VerifyError>,
+  /// Warnings, on valid but suspicious code.
+  pub warnings: Vec<VerifyWarning>,
+}
+
+impl VerifyReport {
+  /// Returns `true` if the report contains any error.
+  pub fn has_errors(&self) -> bool {
+    !self.errors.is_empty()
+  }
+
+  /// Returns `true` if the report contains neither errors nor warnings.
+  pub fn is_clean(&self) -> bool {
+    self.errors.is_empty() && self.warnings.is_empty()
+  }
+
+  /// Returns an iterator of all diagnostics in the report, errors
+  /// first.
+  pub fn diagnostics(&self) -> impl Iterator<Item = Diagnostic<'_>> {
+    let errors = self.errors.iter().map(Diagnostic::Error);
+    errors.chain(self.warnings.iter().map(Diagnostic::Warning))
+  }
+
+  /// Converts the report to the result of [`verify_program`] or
+  /// [`verify_func`], dropping all warnings.
+  pub fn into_result(self) -> Result<(), Vec<VerifyError>> {
+    to_result(self.errors)
+  }
+}
+
+/// Error reported by [`audit_use_lists`], which means that a `used_by`
+/// set does not match the operands in the program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UseListError {
+  /// A value uses another value, but is not in its `used_by` set.
+  MissingUse {
+    /// The used value.
+    value: Value,
+    /// The user.
+    user: Value,
+  },
+  /// A value is in the `used_by` set of another value, but does not
+  /// use it, or does not exist.
+  SpuriousUse {
+    /// The used value.
+    value: Value,
+    /// The user.
+    user: Value,
+  },
+  /// A value uses a basic block, but is not in its `used_by` set.
+  MissingBlockUse {
+    /// The used basic block.
+    bb: BasicBlock,
+    /// The user.
+    user: Value,
+  },
+  /// A value is in the `used_by` set of a basic block, but does not
+  /// use it, or does not exist.
+  SpuriousBlockUse {
+    /// The used basic block.
+    bb: BasicBlock,
+    /// The user.
+    user: Value,
+  },
+}
+
+impl fmt::Display for UseListError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::MissingUse { value, user } => {
+        write!(f, "{:?} uses {:?}, but is not in its use list", user, value)
+      }
+      Self::SpuriousUse { value, user } => write!(
+        f,
+        "{:?} is in the use list of {:?}, but does not use it",
+        user, value
+      ),
+      Self::MissingBlockUse { bb, user } => write!(
+        f,
+        "{:?} uses basic block {:?}, but is not in its use list",
+        user, bb
+      ),
+      Self::SpuriousBlockUse { bb, user } => write!(
+        f,
+        "{:?} is in the use list of basic block {:?}, but does not use it",
+        user, bb
+      ),
+    }
+  }
+}
+
+/// Checks if the `used_by` sets of all values and basic blocks in the
+/// given program match the operands of all values.
+///
+/// The expected `used_by` sets are recomputed by scanning operands of
+/// all global values and all values in data flow graphs of functions,
+/// including values that are not in the layout.
+pub fn audit_use_lists(program: &Program) -> Result<(), Vec<UseListError>> {
+  let mut uses: HashMap<Value, HashSet<Value>> = HashMap::new();
+  let mut bb_uses: HashMap<BasicBlock, HashSet<Value>> = HashMap::new();
+  let values = program.borrow_values();
+  let mut scan = |user: Value, data: &ValueData| {
+    for v in data.kind().value_uses() {
+      uses.entry(v).or_default().insert(user);
+    }
+    for bb in data.kind().bb_uses() {
+      bb_uses.entry(bb).or_default().insert(user);
+    }
+  };
+  for (v, data) in values.iter() {
+    scan(*v, data);
+  }
+  for f in program.func_layout() {
+    for (v, data) in program.func(*f).dfg().values() {
+      scan(*v, data);
+    }
+  }
+  // compare with the stored `used_by` sets
+  let mut errors = Vec::new();
+  let mut globals: Vec<_> = values.iter().collect();
+  globals.sort_unstable_by_key(|(v, _)| v.0);
+  let mut all_values: Vec<_> = globals.into_iter().map(|(v, d)| (*v, d)).collect();
+  for f in program.func_layout() {
+    let mut locals: Vec<_> = program.func(*f).dfg().values().iter().collect();
+    locals.sort_unstable_by_key(|(v, _)| v.0);
+    all_values.extend(locals.into_iter().map(|(v, d)| (*v, d)));
+  }
+  for (value, data) in all_values {
+    let (missing, spurious) = diff_uses(&uses.remove(&value).unwrap_or_default(), data.used_by());
+    errors.extend(missing.map(|user| UseListError::MissingUse { value, user }));
+    errors.extend(spurious.map(|user| UseListError::SpuriousUse { value, user }));
+  }
+  for f in program.func_layout() {
+    let mut bbs: Vec<_> = program.func(*f).dfg().bbs().iter().collect();
+    bbs.sort_unstable_by_key(|(bb, _)| bb.0);
+    for (&bb, data) in bbs {
+      let (missing, spurious) = diff_uses(&bb_uses.remove(&bb).unwrap_or_default(), data.used_by());
+      errors.extend(missing.map(|user| UseListError::MissingBlockUse { bb, user }));
+      errors.extend(spurious.map(|user| UseListError::SpuriousBlockUse { bb, user }));
+    }
+  }
+  if errors.is_empty() {
+    Ok(())
+  } else {
+    Err(errors)
+  }
+}
+
+/// Compares the expected and the found `used_by` sets, returns missing
+/// and spurious users, sorted by their handles.
+fn diff_uses(
+  expected: &HashSet<Value>,
+  found: &HashSet<Value>,
+) -> (impl Iterator<Item = Value>, impl Iterator<Item = Value>) {
+  let mut missing: Vec<_> = expected.difference(found).copied().collect();
+  let mut spurious: Vec<_> = found.difference(expected).copied().collect();
+  missing.sort_unstable_by_key(|v| v.0);
+  spurious.sort_unstable_by_key(|v| v.0);
+  (missing.into_iter(), spurious.into_iter())
+}
+
+/// Verifies all global values and functions in the given program.
+pub fn verify_program(program: &Program) -> Result<(), Vec<VerifyError>> {
+  // check global aggregates
+  let values = program.borrow_values();
+  let mut aggregates: Vec<_> = values
+    .iter()
+    .filter(|(_, data)| matches!(data.kind(), ValueKind::Aggregate(_)))
+    .collect();
+  aggregates.sort_unstable_by_key(|(v, _)| v.0);
+  let mut errors: Vec<_> = aggregates
+    .into_iter()
+    .filter_map(|(v, data)| check_aggregate(*v, data, |e| values.get(&e).map(|d| d.ty().clone())))
+    .collect();
+  drop(values);
+  // check global allocations and initializers
+  for &alloc in program.inst_layout() {
+    let data = program.borrow_value(alloc);
+    if let Some(error) = check_allocatable(alloc, &data, Some(program.named_types())) {
+      errors.push(error);
+    } else if let ValueKind::GlobalAlloc(ga) = data.kind() {
+      errors.extend(check_global_init(program, alloc, ga.init()));
+    }
+  }
+  // check functions
+  errors.extend(
+    program
+      .func_layout()
+      .iter()
+      .filter_map(|f| verify_func(program.func(*f)).err())
+      .flatten(),
+  );
+  // check named types in local allocations, which are assumed to be
+  // allocatable by `verify_func`
+  for &func in program.func_layout() {
+    let data = program.func(func);
+    errors.extend(data.allocs().filter_map(|alloc| {
+      let value = data.dfg().value(alloc);
+      check_allocatable(alloc, value, None)
+        .is_none()
+        .then(|| check_allocatable(alloc, value, Some(program.named_types())))
+        .flatten()
+    }));
+  }
+  to_result(errors)
+}
+
+/// Verifies the given function.
+pub fn verify_func(func: &FunctionData) -> Result<(), Vec<VerifyError>> {
+  let mut verifier = Verifier::new(func);
+  verifier.verify_block_params();
+  verifier.verify_terminators();
+  verifier.verify_allocs();
+  verifier.verify_readonly_stores();
+  verifier.verify_operand_types();
+  verifier.verify_aggregates();
+  verifier.verify_aggregate_indices();
+  verifier.verify_checked_binaries();
+  verifier.verify_binary_flags();
+  verifier.verify_unaries();
+  verifier.verify_ssa_dominance();
+  verifier.finish()
+}
+
+/// Verifies all global values and functions in the given program, and
+/// also checks for warnings in functions without errors.
+pub fn report_program(program: &Program) -> VerifyReport {
+  let mut report = VerifyReport::default();
+  if let Err(errors) = verify_program(program) {
+    report.errors = errors;
+  }
+  for &func in program.func_layout() {
+    let data = program.func(func);
+    if verify_func(data).is_ok() {
+      report.warnings.extend(check_warnings(data));
+    }
+  }
+  report
+}
+
+/// Verifies the given function, and also checks for warnings if the
+/// function has no errors.
+///
+/// Warnings are not checked on functions with errors, since the
+/// analyses for warnings require well-formed functions.
+pub fn report_func(func: &FunctionData) -> VerifyReport {
+  match verify_func(func) {
+    Ok(()) => VerifyReport {
+      errors: Vec::new(),
+      warnings: check_warnings(func),
+    },
+    Err(errors) => VerifyReport {
+      errors,
+      warnings: Vec::new(),
+    },
+  }
+}
+
+/// Verifies if all incoming edges of basic blocks pass arguments which
+/// match the basic block parameters.
+pub fn verify_block_params(func: &FunctionData) -> Result<(), Vec<VerifyError>> {
+  let mut verifier = Verifier::new(func);
+  verifier.verify_block_params();
+  verifier.finish()
+}
+
+/// Verifies if all uses of local values in reachable basic blocks are
+/// dominated by their definitions.
+///
+/// Basic block parameters are treated as defined at the entry of
+/// their basic blocks.
+pub fn verify_ssa_dominance(func: &FunctionData) -> Result<(), Vec<VerifyError>> {
+  let mut verifier = Verifier::new(func);
+  verifier.verify_ssa_dominance();
+  verifier.finish()
+}
+
+/// Verifies if all named values and all named basic blocks in the given
+/// function have distinct names.
+///
+/// This is not part of [`verify_func`], see
+/// [`FunctionData::verify_unique_names`].
+pub fn verify_unique_names(func: &FunctionData) -> Result<(), Vec<VerifyError>> {
+  let mut verifier = Verifier::new(func);
+  verifier.verify_unique_names();
+  verifier.finish()
+}
+
+/// Checks for warnings in the given well-formed function.
+fn check_warnings(func: &FunctionData) -> Vec<VerifyWarning> {
+  if func.layout().entry_bb().is_none() {
+    return Vec::new();
+  }
+  let dom = DominatorTree::compute(func);
+  let unreachable = (func.layout().bbs().keys())
+    .filter(|bb| !dom.is_reachable(**bb))
+    .map(|&bb| VerifyWarning::UnreachableBlock { bb });
+  let mut warnings: Vec<_> = unreachable.collect();
+  warnings.extend(
+    dead_stores(func)
+      .into_iter()
+      .map(|inst| VerifyWarning::DeadStore { inst }),
+  );
+  warnings.extend(
+    (func.params().iter().enumerate())
+      .filter(|(_, p)| func.dfg().value(**p).used_by().is_empty())
+      .map(|(index, &param)| VerifyWarning::UnusedParam { param, index }),
+  );
+  warnings
+}
+
+/// Checks if the given global initializer can be folded to constants
+/// and relocations.
+fn check_global_init(program: &Program, alloc: Value, value: Value) -> Option<VerifyError> {
+  match program.borrow_value(value).kind() {
+    ValueKind::Integer(_) | ValueKind::ZeroInit(_) | ValueKind::Undef(_) => None,
+    ValueKind::Aggregate(agg) => agg
+      .elems()
+      .iter()
+      .find_map(|e| check_global_init(program, alloc, *e)),
+    _ if Relocation::eval(program, value).is_some() => None,
+    _ => Some(VerifyError::NonConstantInit { alloc, value }),
+  }
+}
+
+/// Checks if the given local or global allocation allocates a type
+/// that can be allocated.
+///
+/// Named types are checked against the given definitions, or assumed
+/// to be allocatable if no definitions are given.
+fn check_allocatable(
+  alloc: Value,
+  data: &ValueData,
+  named: Option<&NamedTypes>,
+) -> Option<VerifyError> {
+  match (data.kind(), data.ty().kind()) {
+    (ValueKind::Alloc(_) | ValueKind::GlobalAlloc(_), TypeKind::Pointer(ty))
+      if !named.map_or(ty.is_allocatable(), |n| n.is_allocatable(ty)) =>
+    {
+      Some(VerifyError::NotAllocatable {
+        alloc,
+        ty: ty.clone(),
+      })
+    }
+    _ => None,
+  }
+}
+
+/// Converts the given error list to a result.
+fn to_result(errors: Vec<VerifyError>) -> Result<(), Vec<VerifyError>> {
+  if errors.is_empty() {
+    Ok(())
+  } else {
+    Err(errors)
+  }
+}
+
+/// Checks if the number and types of elements of the given aggregate
+/// match its array type. Returns the first found error.
+///
+/// Nested aggregates are values themselves, so they are checked
+/// separately.
+fn check_aggregate<F>(value: Value, data: &ValueData, elem_ty: F) -> Option<VerifyError>
+where
+  F: Fn(Value) -> Option<Type>,
+{
+  let agg = match data.kind() {
+    ValueKind::Aggregate(agg) => agg,
+    _ => return None,
+  };
+  let (base, len) = match data.ty().kind() {
+    TypeKind::Array(base, len) => (base, *len),
+    _ => return None,
+  };
+  if agg.elems().len() != len {
+    return Some(VerifyError::AggregateLen {
+      value,
+      expected: len,
+      found: agg.elems().len(),
+    });
+  }
+  agg.elems().iter().enumerate().find_map(|(index, e)| {
+    elem_ty(*e)
+      .filter(|found| !found.structurally_compatible(base))
+      .map(|found| VerifyError::AggregateElemType {
+        value,
+        index,
+        expected: base.clone(),
+        found,
+      })
+  })
+}
+
+/// Verifier of a single function.
+struct Verifier<'a> {
+  func: &'a FunctionData,
+  errors: Vec<VerifyError>,
+}
+
+impl<'a> Verifier<'a> {
+  fn new(func: &'a FunctionData) -> Self {
+    Self {
+      func,
+      errors: Vec::new(),
+    }
+  }
+
+  fn finish(self) -> Result<(), Vec<VerifyError>> {
+    to_result(self.errors)
+  }
+
+  /// Returns the type of the given value, or `None` if the value
+  /// does not exist.
+  fn value_ty(&self, value: Value) -> Option<Type> {
+    let dfg = self.func.dfg();
+    dfg
+      .values()
+      .get(&value)
+      .map(|d| d.ty().clone())
+      .or_else(|| {
+        dfg
+          .globals
+          .upgrade()
+          .and_then(|g| g.borrow().get(&value).map(|d| d.ty().clone()))
+      })
+  }
+
+  /// Returns all outgoing edges (instruction, target, arguments) of
+  /// the given basic block.
+  fn edges(&self, bb: BasicBlock) -> Vec<(Value, BasicBlock, &'a [Value])> {
+    let func = self.func;
+    let node = func.layout().bbs().node(&bb).unwrap();
+    let mut edges = Vec::new();
+    for &inst in node.insts().keys() {
+      match func.dfg().value(inst).kind() {
+        ValueKind::Branch(br) => {
+          edges.push((inst, br.true_bb(), br.true_args()));
+          edges.push((inst, br.false_bb(), br.false_args()));
+        }
+        ValueKind::Jump(jump) => edges.push((inst, jump.target(), jump.args())),
+        ValueKind::Switch(switch) => {
+          edges.push((inst, switch.default_bb(), switch.default_args()));
+          for (_, bb, args) in switch.cases() {
+            edges.push((inst, *bb, args));
+          }
+        }
+        _ => {}
+      }
+    }
+    edges
+  }
+
+  fn verify_block_params(&mut self) {
+    let func = self.func;
+    for &pred in func.layout().bbs().keys() {
+      for (inst, bb, args) in self.edges(pred) {
+        let params = match func.dfg().bbs().get(&bb) {
+          Some(data) => data.params(),
+          None => continue,
+        };
+        if params.len() != args.len() {
+          self.errors.push(VerifyError::BlockArgNum {
+            pred,
+            inst,
+            bb,
+            expected: params.len(),
+            found: args.len(),
+          });
+          continue;
+        }
+        for (index, (param, arg)) in params.iter().zip(args).enumerate() {
+          let expected = func.dfg().value(*param).ty().clone();
+          match self.value_ty(*arg) {
+            Some(found) if !found.structurally_compatible(&expected) => {
+              self.errors.push(VerifyError::BlockArgType {
+                pred,
+                inst,
+                bb,
+                index,
+                expected,
+                found,
+              })
+            }
+            _ => {}
+          }
+        }
+      }
+    }
+  }
+
+  /// Verifies if the number and types of elements of all local
+  /// aggregates match their array types.
+  fn verify_aggregates(&mut self) {
+    let dfg = self.func.dfg();
+    let mut aggregates: Vec<_> = dfg
+      .values()
+      .iter()
+      .filter(|(_, data)| matches!(data.kind(), ValueKind::Aggregate(_)))
+      .collect();
+    aggregates.sort_unstable_by_key(|(v, _)| v.0);
+    for (value, data) in aggregates {
+      if let Some(error) = check_aggregate(*value, data, |e| self.value_ty(e)) {
+        self.errors.push(error);
+      }
+    }
+  }
+
+  /// Verifies if all local allocations allocate types that can be
+  /// allocated.
+  fn verify_allocs(&mut self) {
+    let func = self.func;
+    for alloc in func.allocs() {
+      self
+        .errors
+        .extend(check_allocatable(alloc, func.dfg().value(alloc), None));
+    }
+  }
+
+  /// Verifies if the types of arguments and results of all calls and
+  /// the types of all stores are compatible with the callees and the
+  /// destinations, and results of calls that return unit are unused.
+  fn verify_operand_types(&mut self) {
+    let func = self.func;
+    let func_tys = func.dfg().func_tys.upgrade();
+    for node in func.layout().bbs().nodes() {
+      for &inst in node.insts().keys() {
+        match func.dfg().value(inst).kind() {
+          ValueKind::Call(call) => {
+            let callee_ty = func_tys
+              .as_ref()
+              .and_then(|t| t.borrow().get(&call.callee()).cloned());
+            let (params, ret) = match callee_ty.as_ref().map(|t| t.kind()) {
+              Some(TypeKind::Function(params, ret)) => (params.clone(), ret),
+              _ => continue,
+            };
+            let data = func.dfg().value(inst);
+            if ret.is_unit() {
+              let user = data.used_by().iter().min_by_key(|u| u.0);
+              if let Some(&user) = user {
+                self
+                  .errors
+                  .push(VerifyError::UnitCallResultUsed { inst, user });
+              }
+            } else if !data.ty().structurally_compatible(ret) {
+              self.errors.push(VerifyError::CallRetType {
+                inst,
+                expected: ret.clone(),
+                found: data.ty().clone(),
+              });
+            }
+            if params.len() != call.args().len() {
+              self.errors.push(VerifyError::CallArgNum {
+                inst,
+                expected: params.len(),
+                found: call.args().len(),
+              });
+              continue;
+            }
+            for (index, (expected, arg)) in params.into_iter().zip(call.args()).enumerate() {
+              match self.value_ty(*arg) {
+                Some(found) if !found.structurally_compatible(&expected) => {
+                  self.errors.push(VerifyError::CallArgType {
+                    inst,
+                    index,
+                    expected,
+                    found,
+                  })
+                }
+                _ => {}
+              }
+            }
+          }
+          ValueKind::Store(store) => {
+            let (value, dest) = (self.value_ty(store.value()), self.value_ty(store.dest()));
+            if let (Some(value), Some(found)) = (value, dest) {
+              let expected = Type::get_pointer(value);
+              if !found.structurally_compatible(&expected) {
+                self.errors.push(VerifyError::StoreType {
+                  inst,
+                  expected,
+                  found,
+                });
+              }
+            }
+          }
+          ValueKind::Copy(copy) => {
+            let found = func.dfg().value(inst).ty();
+            match self.value_ty(copy.src()) {
+              Some(expected) if !found.structurally_compatible(&expected) => {
+                self.errors.push(VerifyError::CopyType {
+                  inst,
+                  expected,
+                  found: found.clone(),
+                })
+              }
+              _ => {}
+            }
+          }
+          _ => {}
+        }
+      }
+    }
+  }
+
+  /// Verifies if no store writes to read-only global allocations.
+  fn verify_readonly_stores(&mut self) {
+    let func = self.func;
+    let globals = match func.dfg().globals.upgrade() {
+      Some(globals) => globals,
+      None => return,
+    };
+    let globals = globals.borrow();
+    for inst in func.stores() {
+      let dest = match func.dfg().value(inst).kind() {
+        ValueKind::Store(s) => s.dest(),
+        _ => unreachable!(),
+      };
+      // find the allocation from which the destination is derived
+      let mut ptr = dest;
+      let data = loop {
+        let data = match func.dfg().values().get(&ptr).or_else(|| globals.get(&ptr)) {
+          Some(data) => data,
+          None => break None,
+        };
+        ptr = match data.kind() {
+          ValueKind::GetPtr(gp) => gp.src(),
+          ValueKind::GetElemPtr(gep) => gep.src(),
+          _ => break Some(data),
+        };
+      };
+      if let Some(ValueKind::GlobalAlloc(ga)) = data.map(|d| d.kind()) {
+        if ga.attrs().readonly {
+          self
+            .errors
+            .push(VerifyError::StoreToReadOnly { inst, global: ptr });
+        }
+      }
+    }
+  }
+
+  /// Verifies if the indices of all element extractions/insertions
+  /// are in bounds.
+  fn verify_aggregate_indices(&mut self) {
+    let func = self.func;
+    for node in func.layout().bbs().nodes() {
+      for &inst in node.insts().keys() {
+        let (agg, index) = match func.dfg().value(inst).kind() {
+          ValueKind::ExtractValue(ev) => (ev.agg(), ev.index()),
+          ValueKind::InsertValue(iv) => (iv.agg(), iv.index()),
+          _ => continue,
+        };
+        if let Some(TypeKind::Array(_, len)) = self.value_ty(agg).as_ref().map(Type::kind) {
+          if index >= *len {
+            self.errors.push(VerifyError::AggregateIndex {
+              inst,
+              index,
+              len: *len,
+            });
+          }
+        }
+      }
+    }
+  }
+
+  /// Verifies if all overflow-checked binary operations have
+  /// supported operators.
+  fn verify_checked_binaries(&mut self) {
+    let func = self.func;
+    for node in func.layout().bbs().nodes() {
+      for &inst in node.insts().keys() {
+        if let ValueKind::CheckedBinary(bin) = func.dfg().value(inst).kind() {
+          if !CheckedBinary::is_supported(bin.op()) {
+            self
+              .errors
+              .push(VerifyError::CheckedBinaryOp { inst, op: bin.op() });
+          }
+        }
+      }
+    }
+  }
+
+  /// Verifies if flags of all binary operations are supported by their
+  /// operators.
+  fn verify_binary_flags(&mut self) {
+    let func = self.func;
+    for node in func.layout().bbs().nodes() {
+      for &inst in node.insts().keys() {
+        if let ValueKind::Binary(bin) = func.dfg().value(inst).kind() {
+          if !bin.flags().is_supported(bin.op()) {
+            self.errors.push(VerifyError::BinaryFlags {
+              inst,
+              op: bin.op(),
+              flags: bin.flags(),
+            });
+          }
+        }
+      }
+    }
+  }
+
+  /// Verifies if types of all unary operations match their operators.
+  fn verify_unaries(&mut self) {
+    let func = self.func;
+    for node in func.layout().bbs().nodes() {
+      for &inst in node.insts().keys() {
+        let data = func.dfg().value(inst);
+        if let ValueKind::Unary(unary) = data.kind() {
+          // missing operands are reported by other checks
+          let opr = match self.value_ty(unary.opr()) {
+            Some(opr) => opr,
+            None => continue,
+          };
+          let result = unary.op().result_ty(&opr);
+          if !result.is_some_and(|ty| ty.structurally_compatible(data.ty())) {
+            self.errors.push(VerifyError::UnaryType {
+              inst,
+              op: unary.op(),
+              opr,
+              found: data.ty().clone(),
+            });
+          }
+        }
+      }
+    }
+  }
+
+  /// Verifies if all uses of local values in reachable basic blocks
+  /// are dominated by their definitions.
+  fn verify_ssa_dominance(&mut self) {
+    let func = self.func;
+    let dom = DominatorTree::compute(func);
+    for (&bb, node) in func.layout().bbs() {
+      if !dom.is_reachable(bb) {
+        continue;
+      }
+      let mut defined = HashSet::new();
+      for &inst in node.insts().keys() {
+        for value in func.dfg().value(inst).kind().value_uses() {
+          // skip global values and local constants
+          let data = match func.dfg().values().get(&value) {
+            Some(data) if !data.kind().is_const() => data,
+            _ => continue,
+          };
+          let dominated = match (data.kind(), func.value_bb(value)) {
+            (ValueKind::FuncArgRef(_), _) => true,
+            (ValueKind::BlockArgRef(_), Some(def_bb)) => dom.dominates(def_bb, bb),
+            (_, Some(def_bb)) if def_bb == bb => defined.contains(&value),
+            (_, Some(def_bb)) => dom.dominates(def_bb, bb),
+            (_, None) => false,
+          };
+          if !dominated {
+            self
+              .errors
+              .push(VerifyError::UndominatedUse { bb, inst, value });
+          }
+        }
+        defined.insert(inst);
+      }
+    }
+  }
+
+  /// Verifies if names of values and basic blocks are unique.
+  fn verify_unique_names(&mut self) {
+    let (values, bbs) = self.func.named_in_order();
+    let mut names = HashSet::new();
+    for value in values {
+      let name = self.func.dfg().value(value).name().clone().unwrap();
+      if !names.insert(name.clone()) {
+        self
+          .errors
+          .push(VerifyError::DuplicateValueName { value, name });
+      }
+    }
+    let mut names = HashSet::new();
+    for bb in bbs {
+      let name = self.func.dfg().bb(bb).name().clone().unwrap();
+      if !names.insert(name.clone()) {
+        self
+          .errors
+          .push(VerifyError::DuplicateBlockName { bb, name });
+      }
+    }
+  }
+
+  /// Verifies if all reachable basic blocks end with terminators, and
+  /// all returns match the return type of the function.
+  fn verify_terminators(&mut self) {
+    let func = self.func;
+    let ret_ty = match func.ty().kind() {
+      TypeKind::Function(_, ret) => ret.clone(),
+      _ => panic!("invalid function type"),
+    };
+    // find all reachable basic blocks from the entry
+    let mut reachable = HashSet::new();
+    let mut worklist: Vec<_> = func.layout().entry_bb().into_iter().collect();
+    while let Some(bb) = worklist.pop() {
+      if func.layout().bbs().contains_key(&bb) && reachable.insert(bb) {
+        if let Some(inst) = func.terminator(bb) {
+          worklist.extend(func.dfg().value(inst).kind().bb_uses());
+        }
+      }
+    }
+    // check reachable basic blocks in layout order
+    for &bb in func.layout().bbs().keys() {
+      if !reachable.contains(&bb) {
+        continue;
+      }
+      match func.terminator(bb) {
+        Some(inst) => match func.dfg().value(inst).kind() {
+          ValueKind::Return(ret) => match ret.value().map(|v| self.value_ty(v)) {
+            None if !ret_ty.is_unit() => self.errors.push(VerifyError::MissingReturnValue {
+              bb,
+              inst,
+              expected: ret_ty.clone(),
+            }),
+            Some(Some(ty)) if ret_ty.is_unit() || !ty.structurally_compatible(&ret_ty) => {
+              self.errors.push(VerifyError::ReturnType {
+                bb,
+                inst,
+                expected: ret_ty.clone(),
+                found: ty,
+              })
+            }
+            _ => {}
+          },
+          _ => {}
+        },
+        None => self.errors.push(VerifyError::MissingTerminator { bb }),
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::front::builder::BuildOptions;
+  use crate::front::Driver;
+  use crate::ir::builder_traits::*;
+  use crate::ir::values::{
+    Aggregate, Alloc, Binary, Call, CopyValue, ExtractValue, Jump, Store, Unary,
+  };
+
+  #[test]
+  fn block_params() {
+    let mut program = Program::new();
+    let func = program.new_func(FunctionData::new("@f".into(), vec![], Type::get_unit()));
+    let func = program.func_mut(func);
+    let entry = func.dfg_mut().new_bb().basic_block(None);
+    let body = func
+      .dfg_mut()
+      .new_bb()
+      .basic_block_with_params(None, vec![Type::get_i32()]);
+    func.layout_mut().bbs_mut().extend([entry, body]);
+    let zero = func.dfg_mut().new_value().integer(0);
+    let jump = func.dfg_mut().new_value().jump_with_args(body, vec![zero]);
+    func
+      .layout_mut()
+      .bb_mut(entry)
+      .insts_mut()
+      .push_key_back(jump)
+      .unwrap();
+    let ret = func.dfg_mut().new_value().ret(None);
+    func
+      .layout_mut()
+      .bb_mut(body)
+      .insts_mut()
+      .push_key_back(ret)
+      .unwrap();
+    assert_eq!(verify_block_params(func), Ok(()));
+    // wrong number of arguments
+    func
+      .dfg_mut()
+      .replace_value_with_data(jump, Jump::new_data(body));
+    assert_eq!(
+      verify_block_params(func),
+      Err(vec![VerifyError::BlockArgNum {
+        pred: entry,
+        inst: jump,
+        bb: body,
+        expected: 1,
+        found: 0,
+      }])
+    );
+    // wrong argument type
+    let alloc = func.dfg_mut().new_value().alloc(Type::get_i32());
+    func
+      .dfg_mut()
+      .replace_value_with_data(jump, Jump::with_args(body, vec![alloc]));
+    let errors = verify_block_params(func).unwrap_err();
+    assert_eq!(
+      errors,
+      [VerifyError::BlockArgType {
+        pred: entry,
+        inst: jump,
+        bb: body,
+        index: 0,
+        expected: Type::get_i32(),
+        found: Type::get_pointer(Type::get_i32()),
+      }]
+    );
+  }
+
+  #[test]
+  fn terminators() {
+    let mut program = Program::new();
+    let func = program.new_func(FunctionData::new("@f".into(), vec![], Type::get_i32()));
+    let func = program.func_mut(func);
+    let entry = func.dfg_mut().new_bb().basic_block(None);
+    let body = func.dfg_mut().new_bb().basic_block(None);
+    let end = func.dfg_mut().new_bb().basic_block(None);
+    let unreachable = func.dfg_mut().new_bb().basic_block(None);
+    func
+      .layout_mut()
+      .bbs_mut()
+      .extend([entry, body, end, unreachable]);
+    let jump = func.dfg_mut().new_value().jump(body);
+    func.set_terminator(entry, jump);
+    let zero = func.dfg_mut().new_value().integer(0);
+    let br = func.dfg_mut().new_value().branch(zero, body, end);
+    func.set_terminator(body, br);
+    let ret = func.dfg_mut().new_value().ret(None);
+    func.set_terminator(end, ret);
+    assert_eq!(
+      verify_func(func),
+      Err(vec![VerifyError::MissingReturnValue {
+        bb: end,
+        inst: ret,
+        expected: Type::get_i32(),
+      }])
+    );
+    // missing terminator
+    let ret = func.dfg_mut().new_value().ret(Some(zero));
+    func.set_terminator(end, ret);
+    assert_eq!(verify_func(func), Ok(()));
+    func.layout_mut().bb_mut(body).insts_mut().remove(&br);
+    assert_eq!(
+      verify_func(func),
+      Err(vec![VerifyError::MissingTerminator { bb: body }])
+    );
+  }
+
+  #[test]
+  fn return_types() {
+    let mut program = Program::new();
+    let func = program.new_func(FunctionData::new("@f".into(), vec![], Type::get_unit()));
+    let func = program.func_mut(func);
+    let entry = func.dfg_mut().new_bb().basic_block(None);
+    func.layout_mut().bbs_mut().extend([entry]);
+    let zero = func.dfg_mut().new_value().integer(0);
+    let ret = func.dfg_mut().new_value().ret(Some(zero));
+    func.set_terminator(entry, ret);
+    let errors = verify_func(func).unwrap_err();
+    assert_eq!(
+      errors,
+      vec![VerifyError::ReturnType {
+        bb: entry,
+        inst: ret,
+        expected: Type::get_unit(),
+        found: Type::get_i32(),
+      }]
+    );
+    assert_eq!(
+      errors[0].to_string(),
+      format!(
+        "{:?} (in basic block {:?}) returns a value of type i32, expected unit",
+        ret, entry
+      )
+    );
+    // returns a value of mismatched type
+    let ptr_ty = Type::get_pointer(Type::get_i32());
+    let func = program.new_func(FunctionData::new(
+      "@g".into(),
+      vec![ptr_ty.clone()],
+      Type::get_i32(),
+    ));
+    let func = program.func_mut(func);
+    let p = func.params()[0];
+    let entry = func.dfg_mut().new_bb().basic_block(None);
+    func.layout_mut().bbs_mut().extend([entry]);
+    let ret = func.dfg_mut().new_value().ret(Some(p));
+    func.set_terminator(entry, ret);
+    assert_eq!(
+      verify_func(func),
+      Err(vec![VerifyError::ReturnType {
+        bb: entry,
+        inst: ret,
+        expected: Type::get_i32(),
+        found: ptr_ty,
+      }])
+    );
+    let zero = func.dfg_mut().new_value().integer(0);
+    let ret = func.dfg_mut().new_value().ret(Some(zero));
+    func.set_terminator(entry, ret);
+    assert_eq!(verify_func(func), Ok(()));
+  }
+
+  #[test]
+  fn aggregates() {
+    let mut program = Program::new();
+    let one = program.new_value().integer(1);
+    let arr = program.new_value().aggregate(vec![one, one]);
+    let agg = program.new_value().aggregate(vec![arr, arr]);
+    program.new_value().global_alloc(agg);
+    let func = program.new_func(FunctionData::new("@f".into(), vec![], Type::get_unit()));
+    let func = program.func_mut(func);
+    let entry = func.dfg_mut().new_bb().basic_block(None);
+    func.layout_mut().bbs_mut().push_key_back(entry).unwrap();
+    let zero = func.dfg_mut().new_value().integer(0);
+    let local = func.dfg_mut().new_value().aggregate(vec![zero, zero]);
+    let ret = func.dfg_mut().new_value().ret(None);
+    func.set_terminator(entry, ret);
+    assert_eq!(program.verify(), Ok(()));
+    // wrong number of elements
+    let func = program.func_layout()[0];
+    let dfg = program.func_mut(func).dfg_mut();
+    let ty = Type::get_array(Type::get_i32(), 3);
+    dfg.replace_value_with_data(local, Aggregate::new_data(vec![zero, zero], ty));
+    assert_eq!(
+      program.verify(),
+      Err(vec![VerifyError::AggregateLen {
+        value: local,
+        expected: 3,
+        found: 2,
+      }])
+    );
+    // wrong element type
+    let dfg = program.func_mut(func).dfg_mut();
+    let ty = Type::get_array(Type::get_array(Type::get_i32(), 2), 2);
+    dfg.replace_value_with_data(local, Aggregate::new_data(vec![arr, zero], ty));
+    assert_eq!(
+      program.verify(),
+      Err(vec![VerifyError::AggregateElemType {
+        value: local,
+        index: 1,
+        expected: Type::get_array(Type::get_i32(), 2),
+        found: Type::get_i32(),
+      }])
+    );
+  }
+
+  #[test]
+  fn ssa_dominance() {
+    let driver: Driver<_> = r#"fun @f(@a: i32): i32 {
+%entry:
+  br @a, %then, %else
+
+%then:
+  %x = add @a, 1
+  jump %end
+
+%else:
+  jump %end
+
+%end:
+  %y = add %x, 2
+  ret %y
+}
+"#
+    .into();
+    let program = driver.generate_program().unwrap();
+    let func = program.funcs().values().next().unwrap();
+    let end = *func.layout().bbs().back_key().unwrap();
+    let insts: Vec<_> = func
+      .layout()
+      .bbs()
+      .nodes()
+      .flat_map(|n| n.insts().keys())
+      .collect();
+    assert_eq!(
+      verify_ssa_dominance(func),
+      Err(vec![VerifyError::UndominatedUse {
+        bb: end,
+        inst: *insts[4],
+        value: *insts[1],
+      }])
+    );
+    // reorder instructions in the same basic block
+    let driver: Driver<_> = r#"fun @g(@a: i32): i32 {
+%entry:
+  jump %body(@a)
+
+%body(%b: i32):
+  %0 = add %b, 1
+  %1 = mul %0, 2
+  ret %1
+}
+"#
+    .into();
+    let mut program = driver.generate_program().unwrap();
+    let func = program.funcs_mut().values_mut().next().unwrap();
+    assert_eq!(verify_ssa_dominance(func), Ok(()));
+    let body = *func.layout().bbs().back_key().unwrap();
+    let insts = func.layout().bbs().node(&body).unwrap().insts();
+    let (add, mul) = (*insts.front_key().unwrap(), *insts.keys().nth(1).unwrap());
+    let insts = func.layout_mut().bb_mut(body).insts_mut();
+    insts.remove(&mul);
+    insts.push_key_front(mul).unwrap();
+    assert_eq!(
+      verify_ssa_dominance(func),
+      Err(vec![VerifyError::UndominatedUse {
+        bb: body,
+        inst: mul,
+        value: add,
+      }])
+    );
+  }
+
+  #[test]
+  fn checked_binaries() {
+    let mut program = Program::new();
+    let func = program.new_func(FunctionData::new(
+      "@f".into(),
+      vec![Type::get_i32()],
+      Type::get_unit(),
+    ));
+    let func = program.func_mut(func);
+    let a = func.params()[0];
+    let entry = func.dfg_mut().new_bb().basic_block(None);
+    func.layout_mut().bbs_mut().push_key_back(entry).unwrap();
+    let bin = func
+      .dfg_mut()
+      .new_value()
+      .checked_binary(BinaryOp::Sub, a, a);
+    let ret = func.dfg_mut().new_value().ret(None);
+    func
+      .layout_mut()
+      .bb_mut(entry)
+      .insts_mut()
+      .extend([bin, ret]);
+    assert_eq!(verify_func(func), Ok(()));
+    let ty = Type::get_array(Type::get_i32(), 2);
+    func
+      .dfg_mut()
+      .replace_value_with_data(bin, CheckedBinary::new_data(BinaryOp::Div, a, a, ty));
+    assert_eq!(
+      verify_func(func),
+      Err(vec![VerifyError::CheckedBinaryOp {
+        inst: bin,
+        op: BinaryOp::Div,
+      }])
+    );
+  }
+
+  #[test]
+  fn unaries() {
+    let mut program = Program::new();
+    let func = program.new_func(FunctionData::new(
+      "@f".into(),
+      vec![Type::get_i32()],
+      Type::get_unit(),
+    ));
+    let func = program.func_mut(func);
+    let a = func.params()[0];
+    let entry = func.dfg_mut().new_bb().basic_block(None);
+    func.layout_mut().bbs_mut().push_key_back(entry).unwrap();
+    let neg = func.dfg_mut().new_value().unary(UnaryOp::Neg, a);
+    let not = func.dfg_mut().new_value().unary(UnaryOp::Not, neg);
+    let ret = func.dfg_mut().new_value().ret(None);
+    func
+      .layout_mut()
+      .bb_mut(entry)
+      .insts_mut()
+      .extend([neg, not, ret]);
+    assert_eq!(verify_func(func), Ok(()));
+    let ptr = Type::get_pointer(Type::get_i32());
+    let i32 = Type::get_i32();
+    func
+      .dfg_mut()
+      .replace_value_with_data(neg, Unary::new_data(UnaryOp::Neg, a, ptr.clone()));
+    let errors = verify_func(func).unwrap_err();
+    let expected = vec![
+      VerifyError::UnaryType {
+        inst: neg,
+        op: UnaryOp::Neg,
+        opr: i32.clone(),
+        found: ptr.clone(),
+      },
+      VerifyError::UnaryType {
+        inst: not,
+        op: UnaryOp::Not,
+        opr: ptr.clone(),
+        found: i32,
+      },
+    ];
+    assert_eq!(errors, expected);
+    assert_eq!(
+      errors[0].to_string(),
+      format!(
+        "{:?} performs 'neg' on type i32 and produces type *i32, expected i32",
+        neg
+      )
+    );
+    assert_eq!(
+      errors[1].to_string(),
+      format!("{:?} performs 'not' on unsupported type *i32", not)
+    );
+  }
+
+  #[test]
+  fn binary_flags() {
+    let mut program = Program::new();
+    let func = program.new_func(FunctionData::new(
+      "@f".into(),
+      vec![Type::get_i32()],
+      Type::get_unit(),
+    ));
+    let func = program.func_mut(func);
+    let a = func.params()[0];
+    let entry = func.dfg_mut().new_bb().basic_block(None);
+    func.layout_mut().bbs_mut().push_key_back(entry).unwrap();
+    let flags = BinaryFlags {
+      nsw: true,
+      ..Default::default()
+    };
+    let add = (func.dfg_mut().new_value()).binary_with_flags(BinaryOp::Add, flags, a, a);
+    let ret = func.dfg_mut().new_value().ret(None);
+    func
+      .layout_mut()
+      .bb_mut(entry)
+      .insts_mut()
+      .extend([add, ret]);
+    assert_eq!(verify_func(func), Ok(()));
+    let flags = BinaryFlags {
+      exact: true,
+      ..Default::default()
+    };
+    let data = Binary::new_data_with_flags(BinaryOp::Add, flags, a, a, Type::get_i32());
+    func.dfg_mut().replace_value_with_data(add, data);
+    let errors = verify_func(func).unwrap_err();
+    assert_eq!(
+      errors,
+      vec![VerifyError::BinaryFlags {
+        inst: add,
+        op: BinaryOp::Add,
+        flags,
+      }]
+    );
+    assert_eq!(
+      errors[0].to_string(),
+      format!("{:?} performs 'add' with unsupported flags 'exact'", add)
+    );
+  }
+
+  #[test]
+  fn not_allocatable() {
+    let mut program = Program::new();
+    let func = program.new_func(FunctionData::new("@f".into(), vec![], Type::get_unit()));
+    let func = program.func_mut(func);
+    let entry = func.dfg_mut().new_bb().basic_block(None);
+    func.layout_mut().bbs_mut().push_key_back(entry).unwrap();
+    let alloc = func.dfg_mut().new_value().alloc(Type::get_i32());
+    let ret = func.dfg_mut().new_value().ret(None);
+    func
+      .layout_mut()
+      .bb_mut(entry)
+      .insts_mut()
+      .extend([alloc, ret]);
+    assert_eq!(verify_func(func), Ok(()));
+    let ty = Type::get_function(vec![], Type::get_unit());
+    let data = Alloc::new_data(Type::get_pointer(ty.clone()), None);
+    func.dfg_mut().replace_value_with_data(alloc, data);
+    let errors = verify_func(func).unwrap_err();
+    assert_eq!(
+      errors,
+      vec![VerifyError::NotAllocatable {
+        alloc,
+        ty: ty.clone()
+      }]
+    );
+    assert_eq!(
+      errors[0].to_string(),
+      format!("{:?} allocates type (), which can not be allocated", alloc)
+    );
+  }
+
+  #[test]
+  fn named_types() {
+    let driver: Driver<_> = r#"type @list = [*@list, 2]
+
+global @l = alloc @list, zeroinit
+global @n = alloc @node, zeroinit
+global @p = alloc *@node, zeroinit
+
+fun @f() {
+%entry:
+  %a = alloc @list
+  %b = alloc @node
+  ret
+}
+"#
+    .into();
+    let program = driver.generate_program().unwrap();
+    let global = program.inst_layout()[1];
+    let func = program.funcs().values().next().unwrap();
+    let entry = func.layout().entry_bb().unwrap();
+    let local = *func
+      .layout()
+      .bbs()
+      .node(&entry)
+      .unwrap()
+      .insts()
+      .keys()
+      .nth(1)
+      .unwrap();
+    // undefined named types are only rejected when verifying programs
+    assert_eq!(verify_func(func), Ok(()));
+    let node = Type::get_named("@node".into());
+    assert_eq!(
+      program.verify(),
+      Err(vec![
+        VerifyError::NotAllocatable {
+          alloc: global,
+          ty: node.clone(),
+        },
+        VerifyError::NotAllocatable {
+          alloc: local,
+          ty: node,
+        },
+      ])
+    );
+  }
+
+  #[test]
+  fn operand_types() {
+    let driver: Driver<_> = r#"decl @g(*[i32, 2]): i32
+
+fun @f(@p: *[i32, 2]): i32 {
+%entry:
+  %0 = call @g(@p)
+  ret %0
+}
+"#
+    .into();
+    let mut program = driver.generate_program().unwrap();
+    // a declaration from another source, whose types are structurally
+    // the same but not interned in the same pool
+    let arr = Type::get_uninterned(TypeKind::Array(Type::get_i32(), 2));
+    let ptr = Type::get_uninterned(TypeKind::Pointer(arr));
+    assert_ne!(ptr, Type::get_pointer(Type::get_array(Type::get_i32(), 2)));
+    let h = program.new_func(FunctionData::new_decl(
+      "@h".into(),
+      vec![ptr.clone()],
+      Type::get_i32(),
+    ));
+    let f = program.func_layout()[1];
+    let func = program.func_mut(f);
+    let p = func.params()[0];
+    let entry = func.layout().entry_bb().unwrap();
+    let call = func.layout().block_insts(entry).next().unwrap();
+    func.dfg_mut().replace_value_with(call).call(h, vec![p]);
+    assert_eq!(program.verify(), Ok(()));
+    // incompatible types
+    let func = program.func_mut(f);
+    let zero = func.dfg_mut().new_value().integer(0);
+    let data = Call::new_data(h, vec![zero], Type::get_i32());
+    func.dfg_mut().replace_value_with_data(call, data);
+    let store = func.dfg_mut().new_value().raw(Store::new_data(zero, p));
+    func
+      .layout_mut()
+      .bb_mut(entry)
+      .insts_mut()
+      .push_key_front(store)
+      .unwrap();
+    assert_eq!(
+      program.verify(),
+      Err(vec![
+        VerifyError::StoreType {
+          inst: store,
+          expected: Type::get_pointer(Type::get_i32()),
+          found: Type::get_pointer(Type::get_array(Type::get_i32(), 2)),
+        },
+        VerifyError::CallArgType {
+          inst: call,
+          index: 0,
+          expected: ptr,
+          found: Type::get_i32(),
+        },
+      ])
+    );
+  }
+
+  #[test]
+  fn copy_types() {
+    let mut program = Program::new();
+    let func = program.new_func(FunctionData::new(
+      "@f".into(),
+      vec![Type::get_i32()],
+      Type::get_i32(),
+    ));
+    let func = program.func_mut(func);
+    let a = func.params()[0];
+    let entry = func.dfg_mut().new_bb().basic_block(None);
+    func.layout_mut().bbs_mut().push_key_back(entry).unwrap();
+    let copy = func.dfg_mut().new_value().copy(a);
+    let ret = func.dfg_mut().new_value().ret(Some(a));
+    func
+      .layout_mut()
+      .bb_mut(entry)
+      .insts_mut()
+      .extend([copy, ret]);
+    assert_eq!(verify_func(func), Ok(()));
+    let ptr = Type::get_pointer(Type::get_i32());
+    func
+      .dfg_mut()
+      .replace_value_with_data(copy, CopyValue::new_data(a, ptr.clone()));
+    let errors = verify_func(func).unwrap_err();
+    assert_eq!(
+      errors,
+      vec![VerifyError::CopyType {
+        inst: copy,
+        expected: Type::get_i32(),
+        found: ptr,
+      }]
+    );
+    assert_eq!(errors[0].code(), "K023");
+    assert_eq!(
+      errors[0].to_string(),
+      format!("{:?} has type *i32, expected type i32 of the source", copy)
+    );
+  }
+
+  #[test]
+  fn call_results() {
+    let driver: Driver<_> = r#"decl @v()
+
+decl @g(): i32
+
+fun @f(@p: *i32): i32 {
+%entry:
+  call @v()
+  %0 = call @g()
+  ret %0
+}
+"#
+    .into();
+    let mut program = driver.generate_program().unwrap();
+    assert_eq!(program.verify(), Ok(()));
+    let g = program.func_layout()[1];
+    let f = program.func_layout()[2];
+    let func = program.func_mut(f);
+    let p = func.params()[0];
+    let entry = func.layout().entry_bb().unwrap();
+    let insts: Vec<_> = func.layout().block_insts(entry).collect();
+    let (vcall, gcall) = (insts[0], insts[1]);
+    let data = Call::new_data(g, vec![], Type::get_unit());
+    func.dfg_mut().replace_value_with_data(gcall, data);
+    let store = func.dfg_mut().new_value().raw(Store::new_data(vcall, p));
+    func
+      .layout_mut()
+      .bb_mut(entry)
+      .insts_mut()
+      .cursor_mut(gcall)
+      .insert_key_after(store)
+      .unwrap();
+    let errors = program.verify().unwrap_err();
+    assert_eq!(
+      errors,
+      vec![
+        VerifyError::UnitCallResultUsed {
+          inst: vcall,
+          user: store
+        },
+        VerifyError::CallRetType {
+          inst: gcall,
+          expected: Type::get_i32(),
+          found: Type::get_unit(),
+        },
+        VerifyError::StoreType {
+          inst: store,
+          expected: Type::get_pointer(Type::get_unit()),
+          found: Type::get_pointer(Type::get_i32()),
+        },
+      ]
+    );
+    assert_eq!(
+      errors[0].to_string(),
+      format!(
+        "{:?} uses the result of {:?}, whose callee returns unit",
+        store, vcall
+      )
+    );
+    assert_eq!(errors[1].code(), "K020");
+  }
+
+  #[test]
+  fn aggregate_indices() {
+    let driver: Driver<_> = r#"fun @f(): i32 {
+%entry:
+  %a = alloc [i32, 2]
+  %0 = load %a
+  %1 = insertvalue %0, 5, 1
+  %2 = extractvalue %1, 1
+  ret %2
+}
+"#
+    .into();
+    let mut program = driver.generate_program().unwrap();
+    let func = program.funcs_mut().values_mut().next().unwrap();
+    assert_eq!(verify_func(func), Ok(()));
+    let entry = func.layout().entry_bb().unwrap();
+    let insts: Vec<_> = func
+      .layout()
+      .bbs()
+      .node(&entry)
+      .unwrap()
+      .insts()
+      .keys()
+      .copied()
+      .collect();
+    let (agg, ev) = (insts[2], insts[3]);
+    func
+      .dfg_mut()
+      .replace_value_with_data(ev, ExtractValue::new_data(agg, 2, Type::get_i32()));
+    assert_eq!(
+      verify_func(func),
+      Err(vec![VerifyError::AggregateIndex {
+        inst: ev,
+        index: 2,
+        len: 2,
+      }])
+    );
+  }
+
+  #[test]
+  fn readonly_stores() {
+    let driver: Driver<_> = r#"global @r = alloc [i32, 2], {1, 2}, readonly
+global @w = alloc i32, 0
+
+fun @f(@p: *i32) {
+%entry:
+  %0 = getelemptr @r, 1
+  store 3, %0
+  store 4, @w
+  store 5, @p
+  ret
+}
+"#
+    .into();
+    let program = driver.generate_program().unwrap();
+    let r = program.inst_layout()[0];
+    let func = program.func(program.func_layout()[0]);
+    let store = func.stores().next().unwrap();
+    let error = VerifyError::StoreToReadOnly {
+      inst: store,
+      global: r,
+    };
+    assert_eq!(error.code(), "K016");
+    assert_eq!(program.verify(), Err(vec![error]));
+  }
+
+  #[test]
+  fn global_inits() {
+    let mut program = Program::new();
+    let ty = Type::get_array(Type::get_i32(), 2);
+    let zero = program.new_value().zero_init(ty);
+    let arr = program.new_value().global_alloc(zero);
+    let one = program.new_value().integer(1);
+    let ptr = program.new_value().get_elem_ptr(arr, one);
+    program.new_value().global_alloc(ptr);
+    assert_eq!(verify_program(&program), Ok(()));
+    let undef = program.new_value().undef(Type::get_i32());
+    let ptr = program.new_value().get_elem_ptr(arr, undef);
+    let alloc = program.new_value().global_alloc(ptr);
+    assert_eq!(
+      verify_program(&program),
+      Err(vec![VerifyError::NonConstantInit { alloc, value: ptr }])
+    );
+  }
+
+  #[test]
+  fn error_locations() {
+    let driver: Driver<_> = r#"global @g = alloc i32, zeroinit
+
+fun @f(): i32 {
+%entry:
+  %a = alloc [i32, 2]
+  %0 = load %a
+  %1 = extractvalue %0, 1
+  ret %1
+}
+"#
+    .into();
+    let options = BuildOptions {
+      keep_spans: true,
+      ..Default::default()
+    };
+    let mut program = driver.with_options(options).generate_program().unwrap();
+    let func = program.funcs_mut().values_mut().next().unwrap();
+    let entry = func.layout().entry_bb().unwrap();
+    let insts: Vec<_> = func
+      .layout()
+      .bbs()
+      .node(&entry)
+      .unwrap()
+      .insts()
+      .keys()
+      .copied()
+      .collect();
+    let (load, ev) = (insts[1], insts[2]);
+    func
+      .dfg_mut()
+      .replace_value_with_data(ev, ExtractValue::new_data(load, 2, Type::get_i32()));
+    let error = VerifyError::AggregateIndex {
+      inst: ev,
+      index: 2,
+      len: 2,
+    };
+    assert_eq!(program.verify(), Err(vec![error.clone()]));
+    // spans are rendered as `file:line:col`
+    assert_eq!(error.value(), Some(ev));
+    assert_eq!(error.span(&program).unwrap().start().line(), 7);
+    assert_eq!(
+      error.display_in(&program).to_string(),
+      format!("<buffer>:7:3: {}", error)
+    );
+    // falls back to value names, or basic block names
+    let func = program.funcs_mut().values_mut().next().unwrap();
+    func.set_span(ev, None);
+    func.dfg_mut().set_value_name(ev, Some("%e".into()));
+    assert_eq!(
+      error.display_in(&program).to_string(),
+      format!("%e: {}", error)
+    );
+    let error = VerifyError::MissingTerminator { bb: entry };
+    assert_eq!(
+      error.display_in(&program).to_string(),
+      format!("%entry: {}", error)
+    );
+    let g = program.inst_layout()[0];
+    assert_eq!(program.span(g).unwrap().start().line(), 1);
+  }
+
+  #[test]
+  fn use_lists() {
+    let driver: Driver<_> = r#"
+global @g = alloc i32, 1
+
+fun @f(): i32 {
+%entry:
+  %0 = load @g
+  jump %end(%0)
+
+%end(%x: i32):
+  ret %x
+}
+"#
+    .into();
+    let mut program = driver.generate_program().unwrap();
+    assert_eq!(program.audit_use_lists(), Ok(()));
+    let g = program.inst_layout()[0];
+    let func = program.func_layout()[0];
+    let data = program.func(func);
+    let load = data
+      .values_of_kind(|k| matches!(k, ValueKind::Load(_)))
+      .next();
+    let jump = data
+      .values_of_kind(|k| matches!(k, ValueKind::Jump(_)))
+      .next();
+    let (load, jump) = (load.unwrap(), jump.unwrap());
+    let end = data.layout().bbs().keys().nth(1).copied().unwrap();
+    let entry = data.layout().entry_bb().unwrap();
+    // corrupt use lists
+    program
+      .values
+      .borrow_mut()
+      .get_mut(&g)
+      .unwrap()
+      .used_by
+      .clear();
+    let bb_data = program.func_mut(func).dfg_mut().bb_mut(end);
+    bb_data.used_by.clear();
+    program
+      .func_mut(func)
+      .dfg_mut()
+      .bb_mut(entry)
+      .used_by
+      .insert(load);
+    assert_eq!(
+      program.audit_use_lists(),
+      Err(vec![
+        UseListError::MissingUse {
+          value: g,
+          user: load
+        },
+        UseListError::SpuriousBlockUse {
+          bb: entry,
+          user: load
+        },
+        UseListError::MissingBlockUse {
+          bb: end,
+          user: jump
+        },
+      ])
+    );
+  }
+
+  #[test]
+  fn unique_names() {
+    let driver: Driver<_> = r#"
+fun @f(@x: i32): i32 {
+%entry:
+  %x_1 = add @x, 1
+  %y = add %x_1, 2
+  jump %end(%y)
+
+%end(%z: i32):
+  ret %z
+}
+"#
+    .into();
+    let mut program = driver.generate_program().unwrap();
+    let func = program.func_mut(program.func_layout()[0]);
+    assert_eq!(func.verify_unique_names(), Ok(()));
+    // rename `%y` and `%z` to `%x`, and `%end` to `%entry`
+    let entry = func.layout().entry_bb().unwrap();
+    let end = *func.layout().bbs().back_key().unwrap();
+    let y = func.layout().block_insts(entry).nth(1).unwrap();
+    let z = func.dfg().bb(end).params()[0];
+    func.dfg_mut().set_value_name(y, Some("%x".into()));
+    func.dfg_mut().set_value_name(z, Some("%x".into()));
+    func.dfg_mut().bb_mut(end).set_name(Some("%entry".into()));
+    // `@x` and `%x` are different names
+    assert_eq!(
+      func.verify_unique_names(),
+      Err(vec![
+        VerifyError::DuplicateValueName {
+          value: z,
+          name: "%x".into()
+        },
+        VerifyError::DuplicateBlockName {
+          bb: end,
+          name: "%entry".into()
+        },
+      ])
+    );
+    // `%x_1` is taken by an earlier value
+    func.dedup_names();
+    assert_eq!(func.verify_unique_names(), Ok(()));
+    assert_eq!(func.dfg().value(y).name().as_deref(), Some("%x"));
+    assert_eq!(func.dfg().value(z).name().as_deref(), Some("%x_2"));
+    assert_eq!(func.dfg().bb(end).name().as_deref(), Some("%entry_1"));
+    assert_eq!(program.verify(), Ok(()));
+  }
+
+  #[test]
+  fn warnings() {
+    let driver: Driver<_> = r#"fun @f(@a: i32, @b: i32): i32 {
+%entry:
+  %x = alloc i32
+  store @a, %x
+  store 1, %x
+  %0 = load %x
+  ret %0
+}
+"#
+    .into();
+    let mut program = driver.generate_program().unwrap();
+    let f = program.func_layout()[0];
+    let func = program.func_mut(f);
+    let unreachable = func.dfg_mut().new_bb().basic_block(Some("%dead".into()));
+    func.layout_mut().bbs_mut().extend([unreachable]);
+    let zero = func.dfg_mut().new_value().integer(0);
+    let ret = func.dfg_mut().new_value().ret(Some(zero));
+    func
+      .layout_mut()
+      .bb_mut(unreachable)
+      .insts_mut()
+      .extend([ret]);
+    let entry = func.layout().entry_bb().unwrap();
+    let store = func.layout().block_insts(entry).nth(1).unwrap();
+    let b = func.params()[1];
+    // warnings are reported on well-formed functions
+    let report = program.verify_report();
+    assert!(!report.has_errors() && !report.is_clean());
+    assert_eq!(
+      report.warnings,
+      vec![
+        VerifyWarning::UnreachableBlock { bb: unreachable },
+        VerifyWarning::DeadStore { inst: store },
+        VerifyWarning::UnusedParam { param: b, index: 1 },
+      ]
+    );
+    let codes: Vec<_> = report.diagnostics().map(|d| d.code()).collect();
+    assert_eq!(codes, ["K101", "K102", "K103"]);
+    let diag = report.diagnostics().next().unwrap();
+    assert_eq!(diag.severity(), Severity::Warning);
+    assert_eq!(
+      diag.display_in(&program).to_string(),
+      format!("%dead: warning[K101]: {}", report.warnings[0])
+    );
+    assert_eq!(report.into_result(), Ok(()));
+    // but not on functions with errors
+    let func = program.func_mut(f);
+    let ret = func.terminator(entry).unwrap();
+    func.dfg_mut().replace_value_with(ret).ret(None);
+    let report = program.verify_report();
+    assert!(report.has_errors() && report.warnings.is_empty());
+    assert_eq!(report.errors[0].code(), "K004");
+    let diag = report.diagnostics().next().unwrap();
+    assert_eq!(diag.severity(), Severity::Error);
+    assert_eq!(
+      diag.to_string(),
+      format!("error[K004]: {}", report.errors[0])
+    );
+  }
+}
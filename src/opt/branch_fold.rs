@@ -0,0 +1,181 @@
+//! Branch folding pass.
+
+use crate::ir::analysis::DominatorTree;
+use crate::ir::builder_traits::*;
+use crate::ir::{BasicBlock, Function, FunctionData, Value, ValueKind};
+use crate::opt::FunctionPass;
+
+/// Folds branches on constant conditions, and removes basic blocks
+/// that are unreachable from the entry basic block.
+///
+/// `br 1, %a(..), %b(..)` (or any non-zero integer) is replaced with
+/// `jump %a(..)`, and `br 0, %a(..), %b(..)` is replaced with
+/// `jump %b(..)`. Arguments passed along the dropped edge are no longer
+/// used by the branch, constant arguments are removed if they have no
+/// other users.
+///
+/// Uses of values defined in removed basic blocks can only appear in
+/// other removed basic blocks, they are replaced with `undef` before
+/// removal, and the `undef`s are removed with the basic blocks.
+#[derive(Default)]
+pub struct BranchFolding;
+
+impl FunctionPass for BranchFolding {
+  fn run_on(&mut self, _: Function, data: &mut FunctionData) {
+    if data.layout().entry_bb().is_none() {
+      return;
+    }
+    let bbs: Vec<_> = data.layout().bbs().keys().copied().collect();
+    for bb in bbs {
+      self.fold_branch(data, bb);
+    }
+    self.remove_unreachable_bbs(data);
+  }
+}
+
+impl BranchFolding {
+  /// Creates a new branch folding pass.
+  pub fn new() -> Self {
+    Self
+  }
+
+  /// Replaces the terminator of the given basic block with a `jump`
+  /// if it is a branch on an integer constant.
+  fn fold_branch(&self, data: &mut FunctionData, bb: BasicBlock) {
+    let (target, args, uses) = match data.terminator(bb).map(|t| data.dfg().value(t).kind()) {
+      Some(kind @ ValueKind::Branch(br)) => {
+        let (target, args) = match data.dfg().value(br.cond()).kind() {
+          ValueKind::Integer(i) if i.value() != 0 => (br.true_bb(), br.true_args()),
+          ValueKind::Integer(_) => (br.false_bb(), br.false_args()),
+          _ => return,
+        };
+        (target, args.to_vec(), kind.value_uses().collect::<Vec<_>>())
+      }
+      _ => return,
+    };
+    let jump = data.dfg_mut().new_value().jump_with_args(target, args);
+    data.set_terminator(bb, jump);
+    // the condition and arguments on the dropped edge may be unused now
+    for value in uses {
+      self.remove_unused_const(data, value);
+    }
+  }
+
+  /// Removes all basic blocks in the layout that are unreachable from
+  /// the entry basic block.
+  fn remove_unreachable_bbs(&self, data: &mut FunctionData) {
+    let dom = DominatorTree::compute(data);
+    let dead: Vec<_> = (data.layout().bbs().keys())
+      .copied()
+      .filter(|bb| !dom.is_reachable(*bb))
+      .collect();
+    // cut all uses between values in dead basic blocks
+    let mut undefs = Vec::new();
+    for &bb in &dead {
+      let insts: Vec<_> = data.layout().block_insts(bb).collect();
+      let defs = data.dfg().bb(bb).params().iter().copied().chain(insts);
+      for value in defs.collect::<Vec<_>>() {
+        if !data.dfg().value(value).used_by().is_empty() {
+          let ty = data.dfg().value(value).ty().clone();
+          let undef = data.dfg_mut().new_value().undef(ty);
+          data.dfg_mut().replace_uses_with(value, undef);
+          undefs.push(undef);
+        }
+      }
+    }
+    // remove instructions, then basic blocks
+    for &bb in &dead {
+      let insts: Vec<_> = data.layout().block_insts(bb).collect();
+      for inst in insts {
+        data.layout_mut().bb_mut(bb).insts_mut().remove(&inst);
+        let removed = data.dfg_mut().remove_value(inst);
+        for v in removed.kind().value_uses() {
+          self.remove_unused_const(data, v);
+        }
+      }
+    }
+    for bb in dead {
+      data.layout_mut().bbs_mut().remove(&bb);
+      data.dfg_mut().remove_bb(bb);
+    }
+    for undef in undefs {
+      self.remove_unused_const(data, undef);
+    }
+  }
+
+  /// Removes the given value if it is an unused constant, and also
+  /// removes elements of the removed aggregate that become unused.
+  fn remove_unused_const(&self, data: &mut FunctionData, value: Value) {
+    if data
+      .dfg()
+      .values()
+      .get(&value)
+      .is_some_and(|v| v.kind().is_const() && v.used_by().is_empty())
+    {
+      let removed = data.dfg_mut().remove_value(value);
+      for v in removed.kind().value_uses() {
+        self.remove_unused_const(data, v);
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::back::KoopaGenerator;
+  use crate::front::Driver;
+  use crate::opt::{Pass, PassManager};
+  use std::str;
+
+  fn fold(src: &str) -> String {
+    let driver: Driver<_> = src.into();
+    let mut program = driver.generate_program().unwrap();
+    let mut passman = PassManager::new();
+    passman.register(Pass::Function(Box::new(BranchFolding::new())));
+    passman.run_passes(&mut program);
+    assert_eq!(program.verify(), Ok(()));
+    assert_eq!(program.audit_use_lists(), Ok(()));
+    for data in program.funcs().values() {
+      let mut values = data.dfg().values().values();
+      assert!(values.all(|v| !v.kind().is_const() || !v.used_by().is_empty()));
+    }
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen.generate_on(&program).unwrap();
+    str::from_utf8(&gen.writer()).unwrap().into()
+  }
+
+  #[test]
+  fn fold_constant_branches() {
+    let src = r#"fun @f(@n: i32): i32 {
+%entry:
+  br 0, %loop(0), %end(@n)
+
+%loop(%i: i32):
+  %0 = add %i, 1
+  %1 = lt %0, @n
+  br %1, %loop(%0), %end(%0)
+
+%end(%x: i32):
+  br 2, %exit(%x), %loop(%x)
+
+%exit(%y: i32):
+  ret %y
+}
+"#;
+    assert_eq!(
+      fold(src),
+      r#"fun @f(@n: i32): i32 {
+%entry:
+  jump %end(@n)
+
+%end(%x: i32):
+  jump %exit(%x)
+
+%exit(%y: i32):
+  ret %y
+}
+"#
+    );
+  }
+}
@@ -0,0 +1,150 @@
+//! Constant merging pass.
+
+use crate::ir::entities::ValueData;
+use crate::ir::{Program, Value, ValueKind};
+use crate::opt::ModulePass;
+use std::collections::HashMap;
+
+/// Merges global allocations that are never written and have
+/// structurally equal initializers.
+///
+/// A global allocation is considered read-only if all of its users are
+/// `load` instructions that load from it directly, so its address never
+/// escapes. The first one in the global layout is kept, uses of others
+/// are redirected to it, and then they are removed from the program.
+#[derive(Default)]
+pub struct ConstantMerge;
+
+impl ModulePass for ConstantMerge {
+  fn run_on(&mut self, program: &mut Program) {
+    let mut canonical: Vec<Value> = Vec::new();
+    let mut merged = Vec::new();
+    for &alloc in program.inst_layout() {
+      if !Self::is_read_only(program, alloc) {
+        continue;
+      }
+      let values = program.borrow_values();
+      let same = canonical
+        .iter()
+        .find(|c| Self::alloc_eq(&values, **c, alloc));
+      match same {
+        Some(c) => merged.push((alloc, *c)),
+        None => canonical.push(alloc),
+      }
+    }
+    for (alloc, c) in merged {
+      program.replace_value_uses(alloc, c);
+      program.remove_value(alloc);
+    }
+    program.gc_values();
+  }
+}
+
+impl ConstantMerge {
+  /// Creates a new constant merging pass.
+  pub fn new() -> Self {
+    Self
+  }
+
+  /// Checks if the given global allocation is only loaded from.
+  fn is_read_only(program: &Program, alloc: Value) -> bool {
+    let users = program.borrow_value(alloc).used_by().clone();
+    users.into_iter().all(|user| {
+      let data = program
+        .funcs()
+        .values()
+        .find_map(|f| f.dfg().values().get(&user));
+      matches!(data.map(|d| d.kind()), Some(ValueKind::Load(l)) if l.src() == alloc)
+    })
+  }
+
+  /// Checks if the two given global allocations have the same type and
+  /// attributes, and structurally equal initializers.
+  fn alloc_eq(values: &HashMap<Value, ValueData>, lhs: Value, rhs: Value) -> bool {
+    match (values[&lhs].kind(), values[&rhs].kind()) {
+      (ValueKind::GlobalAlloc(l), ValueKind::GlobalAlloc(r)) => {
+        values[&lhs].ty() == values[&rhs].ty()
+          && l.attrs() == r.attrs()
+          && Self::const_eq(values, l.init(), r.init())
+      }
+      _ => false,
+    }
+  }
+
+  /// Checks if the two given constants are structurally equal.
+  fn const_eq(values: &HashMap<Value, ValueData>, lhs: Value, rhs: Value) -> bool {
+    let (l, r) = (&values[&lhs], &values[&rhs]);
+    l.ty() == r.ty()
+      && match (l.kind(), r.kind()) {
+        (ValueKind::Integer(l), ValueKind::Integer(r)) => l.value() == r.value(),
+        (ValueKind::ZeroInit(_), ValueKind::ZeroInit(_)) => true,
+        (ValueKind::Aggregate(l), ValueKind::Aggregate(r)) => {
+          l.elems().len() == r.elems().len()
+            && (l.elems().iter())
+              .zip(r.elems())
+              .all(|(l, r)| Self::const_eq(values, *l, *r))
+        }
+        // `undef` may be refined to different values
+        _ => false,
+      }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::back::KoopaGenerator;
+  use crate::front::Driver;
+
+  #[test]
+  fn merge_constants() {
+    let driver: Driver<_> = r#"global @a = alloc [i32, 2], {1, 2}
+global @b = alloc [i32, 2], {1, 2}
+global @c = alloc [i32, 2], {1, 2}
+global @d = alloc [i32, 2], {1, 3}
+global @e = alloc i32, zeroinit
+global @f = alloc i32, zeroinit
+global @g = alloc i32, zeroinit
+global @p = alloc *i32, @e
+
+fun @main(): i32 {
+%entry:
+  %0 = load @a
+  %1 = load @b
+  store %1, @c
+  %2 = load @d
+  %3 = load @f
+  %4 = load @g
+  ret %4
+}
+"#
+    .into();
+    let mut program = driver.generate_program().unwrap();
+    ConstantMerge::new().run_on(&mut program);
+    assert_eq!(program.verify(), Ok(()));
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen.generate_on(&program).unwrap();
+    // `@c` is written, `@e` escapes through `@p`
+    assert_eq!(
+      std::str::from_utf8(&gen.writer()).unwrap(),
+      r#"global @a = alloc [i32, 2], {1, 2}
+global @c = alloc [i32, 2], {1, 2}
+global @d = alloc [i32, 2], {1, 3}
+global @e = alloc i32, zeroinit
+global @f = alloc i32, zeroinit
+global @p = alloc *i32, @e
+
+fun @main(): i32 {
+%entry:
+  %0 = load @a
+  %1 = load @a
+  store %1, @c
+  %2 = load @d
+  %3 = load @f
+  %4 = load @f
+  ret %4
+}
+"#
+    );
+  }
+}
@@ -0,0 +1,252 @@
+//! Copy propagation pass.
+
+use crate::ir::values::Binary;
+use crate::ir::{BinaryOp, Function, FunctionData, Value, ValueKind};
+use crate::opt::FunctionPass;
+
+/// Replaces all uses of copy instructions with their sources, and
+/// removes the copy instructions.
+///
+/// An instruction is treated as a copy of `%x` if it is `copy %x`, the
+/// canonical form of copies, or a binary operation with an identity
+/// constant operand, for example:
+///
+/// * `add %x, 0`, `add 0, %x`, `sub %x, 0`.
+/// * `mul %x, 1`, `mul 1, %x`, `div %x, 1`.
+/// * `or %x, 0`, `or 0, %x`, `xor %x, 0`, `xor 0, %x`.
+/// * `shl %x, 0`, `shr %x, 0`, `sar %x, 0`.
+/// * `and %x, %x`, `or %x, %x`.
+///
+/// Sources of `copy` instructions can be constants, for example, uses
+/// of `copy 1` are replaced with `1`. Binary operations whose source is
+/// a constant, like `add 0, 1`, are left to constant folding.
+///
+/// The pass runs until there are no more copies.
+#[derive(Default)]
+pub struct CopyPropagation;
+
+impl FunctionPass for CopyPropagation {
+  fn run_on(&mut self, _: Function, data: &mut FunctionData) {
+    while self.propagate(data) {}
+  }
+}
+
+impl CopyPropagation {
+  /// Creates a new copy propagation pass.
+  pub fn new() -> Self {
+    Self
+  }
+
+  /// Performs one round of propagation. Returns `true` if changed.
+  fn propagate(&self, data: &mut FunctionData) -> bool {
+    let insts: Vec<_> = data
+      .layout()
+      .bbs()
+      .nodes()
+      .flat_map(|node| node.insts().keys())
+      .copied()
+      .collect();
+    let mut changed = false;
+    for inst in insts {
+      if let Some(src) = self.copy_src(data, inst) {
+        data.dfg_mut().replace_uses_with(inst, src);
+        data.remove_inst(inst);
+        changed = true;
+      }
+    }
+    changed
+  }
+
+  /// Returns the source value if the given instruction is a copy.
+  ///
+  /// Binary operations are copies only if their sources are not constants.
+  fn copy_src(&self, data: &FunctionData, inst: Value) -> Option<Value> {
+    match data.dfg().value(inst).kind() {
+      ValueKind::Copy(copy) => Some(copy.src()),
+      ValueKind::Binary(bin) => {
+        let src = Self::binary_src(data, bin)?;
+        match data.dfg().values().get(&src) {
+          Some(v) if v.kind().is_const() => None,
+          _ => Some(src),
+        }
+      }
+      _ => None,
+    }
+  }
+
+  /// Returns the source value if the given binary operation has an
+  /// identity constant operand.
+  fn binary_src(data: &FunctionData, bin: &Binary) -> Option<Value> {
+    let int = |v: Value| match data.dfg().values().get(&v)?.kind() {
+      ValueKind::Integer(i) => Some(i.value()),
+      _ => None,
+    };
+    let (lhs, rhs) = (bin.lhs(), bin.rhs());
+    Some(match (bin.op(), int(lhs), int(rhs)) {
+      (BinaryOp::And | BinaryOp::Or, _, _) if lhs == rhs => lhs,
+      (BinaryOp::Add | BinaryOp::Or | BinaryOp::Xor, Some(0), _) | (BinaryOp::Mul, Some(1), _) => {
+        rhs
+      }
+      (
+        BinaryOp::Add
+        | BinaryOp::Sub
+        | BinaryOp::Or
+        | BinaryOp::Xor
+        | BinaryOp::Shl
+        | BinaryOp::Shr
+        | BinaryOp::Sar,
+        _,
+        Some(0),
+      )
+      | (BinaryOp::Mul | BinaryOp::Div, _, Some(1)) => lhs,
+      _ => return None,
+    })
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::back::KoopaGenerator;
+  use crate::front::Driver;
+  use crate::ir::builder_traits::*;
+  use crate::ir::Program;
+  use crate::opt::{Pass, PassManager};
+  use std::str;
+
+  fn propagate(src: &str) -> String {
+    let driver: Driver<_> = src.into();
+    run(driver.generate_program().unwrap())
+  }
+
+  fn run(mut program: Program) -> String {
+    let mut passman = PassManager::new();
+    passman.register(Pass::Function(Box::new(CopyPropagation::new())));
+    passman.run_passes(&mut program);
+    assert_eq!(program.verify(), Ok(()));
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen.generate_on(&program).unwrap();
+    str::from_utf8(&gen.writer()).unwrap().into()
+  }
+
+  #[test]
+  fn copy_chain() {
+    let src = r#"fun @f(@a: i32, @b: i32): i32 {
+%entry:
+  %0 = add @a, 0
+  %1 = mul 1, %0
+  %2 = sub %1, 0
+  %3 = add %2, @b
+  %4 = shl %3, 0
+  br %4, %end(%2), %end(%4)
+
+%end(%ans: i32):
+  ret %ans
+}
+"#;
+    let expected = r#"fun @f(@a: i32, @b: i32): i32 {
+%entry:
+  %0 = add @a, @b
+  br %0, %end(@a), %end(%0)
+
+%end(%ans: i32):
+  ret %ans
+}
+"#;
+    assert_eq!(propagate(src), expected);
+  }
+
+  #[test]
+  fn explicit_copies() {
+    let src = r#"fun @f(@a: i32, @p: *i32): i32 {
+%entry:
+  %0 = copy @a
+  %1 = copy %0
+  %2 = copy @p
+  store %1, %2
+  %3 = add %1, 1
+  ret %3
+}
+"#;
+    let expected = r#"fun @f(@a: i32, @p: *i32): i32 {
+%entry:
+  store @a, @p
+  %0 = add @a, 1
+  ret %0
+}
+"#;
+    assert_eq!(propagate(src), expected);
+  }
+
+  #[test]
+  fn constant_copies() {
+    let driver: Driver<_> = r#"fun @f(@a: i32): i32 {
+%entry:
+  %0 = add @a, @a
+  ret %0
+}
+"#
+    .into();
+    let mut program = driver.generate_program().unwrap();
+    // copies of constants can not be parsed, build them instead
+    let data = program.funcs_mut().values_mut().next().unwrap();
+    let entry = data.layout().entry_bb().unwrap();
+    let insts = data.layout().bbs().node(&entry).unwrap().insts();
+    let add = insts.front_key().copied().unwrap();
+    let a = data.params()[0];
+    let one = data.dfg_mut().new_value().integer(1);
+    let copy1 = data.dfg_mut().new_value().copy(one);
+    let copy2 = data.dfg_mut().new_value().copy(copy1);
+    (data.dfg_mut().replace_value_with(add)).binary(BinaryOp::Add, a, copy2);
+    let insts = data.layout_mut().bb_mut(entry).insts_mut();
+    insts.push_key_front(copy2).unwrap();
+    insts.push_key_front(copy1).unwrap();
+    let expected = r#"fun @f(@a: i32): i32 {
+%entry:
+  %0 = add @a, 1
+  ret %0
+}
+"#;
+    assert_eq!(run(program), expected);
+  }
+
+  #[test]
+  fn bitwise_copies() {
+    let src = r#"fun @f(@a: i32, @b: i32): i32 {
+%entry:
+  %0 = and @a, @a
+  %1 = or 0, %0
+  %2 = xor %1, 0
+  %3 = or %2, %2
+  %4 = and %3, @b
+  %5 = xor %4, %4
+  ret %5
+}
+"#;
+    let expected = r#"fun @f(@a: i32, @b: i32): i32 {
+%entry:
+  %0 = and @a, @b
+  %1 = xor %0, %0
+  ret %1
+}
+"#;
+    assert_eq!(propagate(src), expected);
+  }
+
+  #[test]
+  fn not_copies() {
+    let src = r#"fun @f(@a: i32): i32 {
+%entry:
+  %0 = add 0, 0
+  %1 = sub 0, @a
+  %2 = mul @a, 0
+  %3 = div 1, @a
+  %4 = add %0, %1
+  %5 = add %2, %3
+  %6 = add %4, %5
+  ret %6
+}
+"#;
+    assert_eq!(propagate(src), src);
+  }
+}
@@ -0,0 +1,119 @@
+//! Dead argument elimination pass.
+
+use crate::ir::Program;
+use crate::opt::ModulePass;
+
+/// Removes unused parameters from functions, and the corresponding
+/// arguments from all call sites.
+///
+/// Function declarations and `@main` are skipped, since their signatures
+/// are visible outside the program. Koopa IR can not take addresses of
+/// functions, so all callers of a function are `call` instructions.
+///
+/// The pass runs until there are no more unused parameters, since
+/// removing arguments may make parameters of callers unused. Other
+/// values passed to removed arguments are left in place, run dead code
+/// elimination after this pass to remove them.
+#[derive(Default)]
+pub struct DeadArgElimination;
+
+impl ModulePass for DeadArgElimination {
+  fn run_on(&mut self, program: &mut Program) {
+    while self.eliminate(program) {}
+  }
+}
+
+impl DeadArgElimination {
+  /// Creates a new dead argument elimination pass.
+  pub fn new() -> Self {
+    Self
+  }
+
+  /// Performs one round of elimination. Returns `true` if changed.
+  fn eliminate(&self, program: &mut Program) -> bool {
+    let funcs: Vec<_> = program
+      .funcs()
+      .iter()
+      .filter(|(_, data)| data.layout().entry_bb().is_some() && data.name() != "@main")
+      .map(|(func, data)| (*func, data.unused_params()))
+      .filter(|(_, unused)| !unused.is_empty())
+      .collect();
+    let changed = !funcs.is_empty();
+    for (func, unused) in funcs {
+      program.remove_params(func, &unused);
+    }
+    changed
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::back::KoopaGenerator;
+  use crate::front::Driver;
+
+  #[test]
+  fn remove_unused_params() {
+    let driver: Driver<_> = r#"decl @getint(i32): i32
+
+fun @f(@a: i32, @b: i32, @c: i32): i32 {
+%entry:
+  %0 = add @a, @c
+  %1 = call @getint(@a)
+  ret %0
+}
+
+fun @g(@x: i32): i32 {
+%entry:
+  %0 = call @f(1, @x, 3)
+  %1 = call @f(%0, 5, 6)
+  ret %1
+}
+
+fun @main(@unused: i32): i32 {
+%entry:
+  %0 = call @g(7)
+  ret %0
+}
+"#
+    .into();
+    let mut program = driver.generate_program().unwrap();
+    let unused: Vec<_> = program
+      .funcs()
+      .values()
+      .map(|f| (f.name(), f.unused_params()))
+      .collect();
+    assert!(unused.contains(&("@f", vec![1])));
+    assert!(unused.contains(&("@g", vec![])));
+    assert!(unused.contains(&("@getint", vec![])));
+    DeadArgElimination::new().run_on(&mut program);
+    assert_eq!(program.verify(), Ok(()));
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen.generate_on(&program).unwrap();
+    assert_eq!(
+      std::str::from_utf8(&gen.writer()).unwrap(),
+      r#"decl @getint(i32): i32
+
+fun @f(@a: i32, @c: i32): i32 {
+%entry:
+  %0 = add @a, @c
+  %1 = call @getint(@a)
+  ret %0
+}
+
+fun @g(): i32 {
+%entry:
+  %2 = call @f(1, 3)
+  %3 = call @f(%2, 6)
+  ret %3
+}
+
+fun @main(@unused: i32): i32 {
+%entry:
+  %4 = call @g()
+  ret %4
+}
+"#
+    );
+  }
+}
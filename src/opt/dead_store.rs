@@ -0,0 +1,74 @@
+//! Dead store elimination pass.
+
+use crate::ir::analysis::dead_stores;
+use crate::ir::{Function, FunctionData};
+use crate::opt::FunctionPass;
+
+/// Removes dead `store` instructions found by [`dead_stores`].
+///
+/// Stores to local allocations whose addresses may escape or be used
+/// in pointer calculations are kept.
+#[derive(Default)]
+pub struct DeadStoreElimination;
+
+impl FunctionPass for DeadStoreElimination {
+  fn run_on(&mut self, _: Function, data: &mut FunctionData) {
+    for store in dead_stores(data) {
+      data.remove_inst(store);
+    }
+  }
+}
+
+impl DeadStoreElimination {
+  /// Creates a new dead store elimination pass.
+  pub fn new() -> Self {
+    Self
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::back::KoopaGenerator;
+  use crate::front::Driver;
+  use crate::opt::{Pass, PassManager};
+  use std::str;
+
+  #[test]
+  fn eliminate_dead_stores() {
+    let driver: Driver<_> = r#"fun @f(@p: *i32): i32 {
+%entry:
+  %x = alloc i32
+  store 1, %x
+  store 2, @p
+  store 2, @p
+  store 3, %x
+  %0 = load %x
+  store %0, %x
+  ret %0
+}
+"#
+    .into();
+    let mut program = driver.generate_program().unwrap();
+    let mut passman = PassManager::new();
+    passman.register(Pass::Function(Box::new(DeadStoreElimination::new())));
+    passman.run_passes(&mut program);
+    assert_eq!(program.verify(), Ok(()));
+    assert_eq!(program.audit_use_lists(), Ok(()));
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen.generate_on(&program).unwrap();
+    assert_eq!(
+      str::from_utf8(&gen.writer()).unwrap(),
+      r#"fun @f(@p: *i32): i32 {
+%entry:
+  %x = alloc i32
+  store 2, @p
+  store 2, @p
+  store 3, %x
+  %0 = load %x
+  ret %0
+}
+"#
+    );
+  }
+}
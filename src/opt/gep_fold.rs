@@ -0,0 +1,192 @@
+//! Constant-index pointer calculation folding pass.
+
+use crate::ir::builder_traits::*;
+use crate::ir::{Function, FunctionData, Value, ValueKind};
+use crate::opt::FunctionPass;
+
+/// Folds consecutive pointer calculations (`getptr`/`getelemptr`) with
+/// constant indices into a single pointer calculation.
+///
+/// The following patterns are folded, where `a` and `b` are constants:
+///
+/// * `getptr (getptr %p, a), b` into `getptr %p, a + b`.
+/// * `getptr (getelemptr %p, a), b` into `getelemptr %p, a + b`.
+///
+/// `getelemptr (getptr %p, a), b` is not folded, since element indices
+/// must be in the bounds of the array, and `getelemptr %p, a * len + b`
+/// is out of bounds if `a` is not zero.
+///
+/// The intermediate pointer is folded only if it has no other users.
+/// Pointer calculations like `getptr %p, 0` are replaced with `%p`.
+#[derive(Default)]
+pub struct GepFolding;
+
+impl FunctionPass for GepFolding {
+  fn run_on(&mut self, _: Function, data: &mut FunctionData) {
+    while self.fold(data) {}
+  }
+}
+
+/// Kind of pointer calculation.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PtrCalc {
+  GetPtr,
+  GetElemPtr,
+}
+
+impl GepFolding {
+  /// Creates a new pointer calculation folding pass.
+  pub fn new() -> Self {
+    Self
+  }
+
+  /// Performs one round of folding. Returns `true` if changed.
+  fn fold(&self, data: &mut FunctionData) -> bool {
+    let insts: Vec<_> = data
+      .layout()
+      .bbs()
+      .nodes()
+      .flat_map(|node| node.insts().keys())
+      .copied()
+      .collect();
+    let mut changed = false;
+    for inst in insts {
+      // skip removed instructions
+      if !data.dfg().values().contains_key(&inst) {
+        continue;
+      }
+      changed |= self.fold_zero(data, inst) || self.fold_inst(data, inst);
+    }
+    changed
+  }
+
+  /// Replaces `getptr %p, 0` with `%p`.
+  fn fold_zero(&self, data: &mut FunctionData, inst: Value) -> bool {
+    match self.ptr_calc(data, inst) {
+      Some((PtrCalc::GetPtr, src, 0)) => {
+        data.dfg_mut().replace_uses_with(inst, src);
+        data.remove_inst(inst);
+        true
+      }
+      _ => false,
+    }
+  }
+
+  /// Folds the given instruction with its source pointer calculation.
+  fn fold_inst(&self, data: &mut FunctionData, inst: Value) -> bool {
+    let (outer, src, b) = match self.ptr_calc(data, inst) {
+      Some(calc) => calc,
+      None => return false,
+    };
+    let (inner, p, a) = match self.ptr_calc(data, src) {
+      Some((kind, p, a)) if data.dfg().value(src).used_by().len() == 1 => (kind, p, a),
+      _ => return false,
+    };
+    let (kind, index) = match (outer, inner) {
+      (PtrCalc::GetPtr, PtrCalc::GetPtr) => (PtrCalc::GetPtr, a.checked_add(b)),
+      (PtrCalc::GetPtr, PtrCalc::GetElemPtr) => (PtrCalc::GetElemPtr, a.checked_add(b)),
+      (PtrCalc::GetElemPtr, _) => return false,
+    };
+    let index = match index {
+      Some(index) => data.dfg_mut().new_value().integer(index),
+      None => return false,
+    };
+    let builder = data.dfg_mut().replace_value_with(inst);
+    match kind {
+      PtrCalc::GetPtr => builder.get_ptr(p, index),
+      PtrCalc::GetElemPtr => builder.get_elem_ptr(p, index),
+    };
+    data.remove_inst(src);
+    true
+  }
+
+  /// Returns the kind, source pointer and constant index of the given
+  /// pointer calculation.
+  fn ptr_calc(&self, data: &FunctionData, inst: Value) -> Option<(PtrCalc, Value, i32)> {
+    let (kind, src, index) = match data.dfg().values().get(&inst)?.kind() {
+      ValueKind::GetPtr(gp) => (PtrCalc::GetPtr, gp.src(), gp.index()),
+      ValueKind::GetElemPtr(gep) => (PtrCalc::GetElemPtr, gep.src(), gep.index()),
+      _ => return None,
+    };
+    match data.dfg().values().get(&index)?.kind() {
+      ValueKind::Integer(i) => Some((kind, src, i.value())),
+      _ => None,
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::back::KoopaGenerator;
+  use crate::front::Driver;
+  use crate::opt::{Pass, PassManager};
+  use std::str;
+
+  fn fold(src: &str) -> String {
+    let driver: Driver<_> = src.into();
+    let mut program = driver.generate_program().unwrap();
+    let mut passman = PassManager::new();
+    passman.register(Pass::Function(Box::new(GepFolding::new())));
+    passman.run_passes(&mut program);
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen.generate_on(&program).unwrap();
+    str::from_utf8(&gen.writer()).unwrap().into()
+  }
+
+  #[test]
+  fn fold_chain() {
+    let src = r#"global @arr = alloc [[i32, 4], 3], zeroinit
+
+fun @f(): i32 {
+%entry:
+  %0 = getelemptr @arr, 1
+  %1 = getptr %0, 1
+  %2 = getelemptr %1, 2
+  %3 = getptr %2, 0
+  %4 = getptr %3, 1
+  %5 = load %4
+  ret %5
+}
+"#;
+    let expected = r#"global @arr = alloc [[i32, 4], 3], zeroinit
+
+fun @f(): i32 {
+%entry:
+  %0 = getelemptr @arr, 2
+  %1 = getelemptr %0, 3
+  %2 = load %1
+  ret %2
+}
+"#;
+    assert_eq!(fold(src), expected);
+  }
+
+  #[test]
+  fn elem_ptr_of_ptr() {
+    let src = r#"fun @f(@p: *[i32, 4]): i32 {
+%entry:
+  %0 = getptr @p, 1
+  %1 = getelemptr %0, 2
+  %2 = load %1
+  ret %2
+}
+"#;
+    assert_eq!(fold(src), src);
+  }
+
+  #[test]
+  fn multiple_uses() {
+    let src = r#"fun @f(@p: *i32): i32 {
+%entry:
+  %0 = getptr @p, 1
+  %1 = getptr %0, 2
+  %2 = load %0
+  %3 = load %1
+  %4 = add %2, %3
+  ret %4
+}
+"#;
+    assert_eq!(fold(src), src);
+  }
+}
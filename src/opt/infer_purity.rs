@@ -0,0 +1,412 @@
+//! Function purity inference pass.
+
+use crate::ir::analysis::CallGraph;
+use crate::ir::dfg::DataFlowGraph;
+use crate::ir::{
+  BasicBlock, BinaryOp, Function, FunctionData, Program, Type, TypeKind, Value, ValueKind,
+};
+use crate::opt::ModulePass;
+use std::collections::HashSet;
+
+/// Infers purity of function definitions, and marks them with
+/// [`FunctionData::set_pure`].
+///
+/// A function definition is pure if it does not store to memory other
+/// than its own local allocations, only calls pure functions, and always
+/// returns. Stores to pointers derived from `alloc` by `getptr` and
+/// `getelemptr` are local, stores to other pointers (global allocations,
+/// parameters, loaded pointers and basic block parameters) are not.
+///
+/// Whether a function returns is decided conservatively. Functions with
+/// loops in their control flow graphs, recursive functions, and functions
+/// with instructions that may trap (`div` and `mod` by a value that is
+/// not a non-zero constant, `assume`, `unreachable`, and pointer
+/// calculations that may go out of bounds) are impure. A `getptr` is in
+/// bounds only if its index is constant zero, and a `getelemptr` only if
+/// its index is a constant within the length of the array.
+///
+/// Pointers passed in by parameters, loaded from memory or stored in
+/// global allocations are assumed to be valid, so loads through them and
+/// in-bounds calculations on them are not considered trapping.
+///
+/// Function declarations are not changed, since their bodies are
+/// unknown. They are pure only if they are already marked as pure, so
+/// calls to I/O functions like `@putint` make the caller impure.
+#[derive(Default)]
+pub struct InferPurity;
+
+impl ModulePass for InferPurity {
+  fn run_on(&mut self, program: &mut Program) {
+    let defs: Vec<_> = (program.func_layout().iter())
+      .copied()
+      .filter(|f| program.func(*f).layout().entry_bb().is_some())
+      .collect();
+    let cg = CallGraph::compute(program);
+    for &func in &defs {
+      let data = program.func(func);
+      let returns =
+        !cg.is_recursive(func) && !self.has_cycle(data) && !self.may_trap(program, data);
+      program.func_mut(func).set_pure(returns);
+    }
+    let mut changed = true;
+    while changed {
+      changed = false;
+      for &func in &defs {
+        if program.func(func).is_pure() && !self.is_pure(program, func) {
+          program.func_mut(func).set_pure(false);
+          changed = true;
+        }
+      }
+    }
+  }
+}
+
+impl InferPurity {
+  /// Creates a new purity inference pass.
+  pub fn new() -> Self {
+    Self
+  }
+
+  /// Checks if the given function is pure under the current purity
+  /// marks of other functions.
+  fn is_pure(&self, program: &Program, func: Function) -> bool {
+    let data = program.func(func);
+    let calls_pure = data
+      .calls()
+      .all(|call| match data.dfg().value(call).kind() {
+        ValueKind::Call(c) => program.func(c.callee()).is_pure(),
+        _ => unreachable!(),
+      });
+    calls_pure && !self.has_nonlocal_store(data)
+  }
+
+  /// Checks if the given function stores to non-local memory.
+  fn has_nonlocal_store(&self, data: &FunctionData) -> bool {
+    data
+      .stores()
+      .any(|store| match data.dfg().value(store).kind() {
+        ValueKind::Store(s) => !self.is_local_ptr(data.dfg(), s.dest()),
+        _ => unreachable!(),
+      })
+  }
+
+  /// Checks if the control flow graph of the given function has cycles
+  /// reachable from the entry basic block.
+  fn has_cycle(&self, data: &FunctionData) -> bool {
+    let entry = data.layout().entry_bb().unwrap();
+    let succs = |bb: BasicBlock| -> Vec<BasicBlock> {
+      (data.terminator(bb).into_iter())
+        .flat_map(|t| data.dfg().value(t).kind().bb_uses())
+        .collect()
+    };
+    // depth-first search, basic blocks on the stack are on the current path
+    let mut visited = HashSet::from([entry]);
+    let mut on_path = HashSet::from([entry]);
+    let mut stack = vec![(entry, succs(entry))];
+    while let Some((bb, targets)) = stack.last_mut() {
+      if let Some(target) = targets.pop() {
+        if on_path.contains(&target) {
+          return true;
+        }
+        if visited.insert(target) {
+          on_path.insert(target);
+          stack.push((target, succs(target)));
+        }
+      } else {
+        on_path.remove(bb);
+        stack.pop();
+      }
+    }
+    false
+  }
+
+  /// Checks if the given function has instructions that may trap.
+  fn may_trap(&self, program: &Program, data: &FunctionData) -> bool {
+    let dfg = data.dfg();
+    let int_of = |v| match dfg.value(v).kind() {
+      ValueKind::Integer(i) => Some(i.value()),
+      _ => None,
+    };
+    let ty_of = |v: Value| -> Type {
+      if v.is_global() {
+        program.borrow_value(v).ty().clone()
+      } else {
+        dfg.value(v).ty().clone()
+      }
+    };
+    let mut insts = data.values_of_kind(|kind| match kind {
+      ValueKind::Binary(bin) => matches!(bin.op(), BinaryOp::Div | BinaryOp::Mod),
+      kind => matches!(
+        kind,
+        ValueKind::GetPtr(_)
+          | ValueKind::GetElemPtr(_)
+          | ValueKind::Assume(_)
+          | ValueKind::Unreachable(_)
+      ),
+    });
+    insts.any(|inst| match dfg.value(inst).kind() {
+      ValueKind::Binary(bin) => int_of(bin.rhs()).is_none_or(|i| i == 0),
+      ValueKind::GetPtr(gp) => int_of(gp.index()) != Some(0),
+      ValueKind::GetElemPtr(gep) => {
+        let len = match ty_of(gep.src()).kind() {
+          TypeKind::Pointer(base) => match base.kind() {
+            TypeKind::Array(_, len) => *len,
+            _ => return true,
+          },
+          _ => return true,
+        };
+        int_of(gep.index()).is_none_or(|i| i < 0 || i as usize >= len)
+      }
+      _ => true,
+    })
+  }
+
+  /// Checks if the given pointer is derived from a local allocation.
+  fn is_local_ptr(&self, dfg: &DataFlowGraph, mut ptr: Value) -> bool {
+    loop {
+      if ptr.is_global() {
+        return false;
+      }
+      ptr = match dfg.value(ptr).kind() {
+        ValueKind::Alloc(_) => return true,
+        ValueKind::GetPtr(gp) => gp.src(),
+        ValueKind::GetElemPtr(gep) => gep.src(),
+        _ => return false,
+      };
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::front::Driver;
+  use crate::ir::builder_traits::*;
+
+  #[test]
+  fn infer_purity() {
+    let driver: Driver<_> = r#"global @g = alloc i32, 0
+
+decl @abs(i32): i32
+decl @putint(i32)
+
+fun @sq(@x: i32): i32 {
+%entry:
+  %0 = alloc [i32, 2]
+  %1 = getelemptr %0, 1
+  store @x, %1
+  %2 = load %1
+  %3 = mul %2, %2
+  %4 = load @g
+  %5 = add %3, %4
+  ret %5
+}
+
+fun @fact(@n: i32): i32 {
+%entry:
+  br @n, %rec, %end
+
+%rec:
+  %0 = sub @n, 1
+  %1 = call @fact(%0)
+  %2 = mul @n, %1
+  ret %2
+
+%end:
+  ret 1
+}
+
+fun @set(@x: i32) {
+%entry:
+  store @x, @g
+  ret
+}
+
+fun @count(@n: i32) {
+%entry:
+  br @n, %rec, %end
+
+%rec:
+  %0 = sub @n, 1
+  call @count(%0)
+  call @set(@n)
+  ret
+
+%end:
+  ret
+}
+
+fun @store_param(@p: *i32) {
+%entry:
+  store 0, @p
+  ret
+}
+
+fun @print_abs(@x: i32) {
+%entry:
+  %0 = call @abs(@x)
+  call @putint(%0)
+  ret
+}
+
+fun @use_abs(@x: i32): i32 {
+%entry:
+  %0 = call @abs(@x)
+  ret %0
+}
+"#
+    .into();
+    let mut program = driver.generate_program().unwrap();
+    let abs = program.func_layout()[0];
+    program.func_mut(abs).set_pure(true);
+    InferPurity::new().run_on(&mut program);
+    let pure: Vec<_> = (program.func_layout().iter())
+      .map(|f| program.func(*f))
+      .filter(|data| data.is_pure())
+      .map(|data| data.name())
+      .collect();
+    assert_eq!(pure, ["@abs", "@sq", "@use_abs"]);
+  }
+
+  #[test]
+  fn infer_termination() {
+    let driver: Driver<_> = r#"decl @placeholder(i32): i32
+
+fun @sum(@n: i32): i32 {
+%entry:
+  jump %loop(0, 0)
+
+%loop(%i: i32, %s: i32):
+  %0 = lt %i, @n
+  br %0, %body, %end
+
+%body:
+  %1 = add %s, %i
+  %2 = add %i, 1
+  jump %loop(%2, %1)
+
+%end:
+  ret %s
+}
+
+fun @even(@n: i32): i32 {
+%entry:
+  br @n, %rec, %end
+
+%rec:
+  %0 = sub @n, 1
+  %1 = call @placeholder(%0)
+  ret %1
+
+%end:
+  ret 1
+}
+
+fun @odd(@n: i32): i32 {
+%entry:
+  br @n, %rec, %end
+
+%rec:
+  %0 = sub @n, 1
+  %1 = call @even(%0)
+  ret %1
+
+%end:
+  ret 0
+}
+
+fun @div(@x: i32, @y: i32): i32 {
+%entry:
+  %0 = div @x, @y
+  ret %0
+}
+
+fun @half(@x: i32): i32 {
+%entry:
+  %0 = div @x, 2
+  %1 = mod @x, 2
+  %2 = add %0, %1
+  ret %2
+}
+
+fun @check(@x: i32): i32 {
+%entry:
+  br @x, %then, %else
+
+%then:
+  ret @x
+
+%else:
+  unreachable
+}
+
+fun @positive(@x: i32): i32 {
+%entry:
+  %0 = gt @x, 0
+  assume %0
+  ret @x
+}
+
+fun @call_sum(@n: i32): i32 {
+%entry:
+  %0 = call @sum(@n)
+  %1 = call @half(%0)
+  ret %1
+}
+
+fun @elem(@i: i32): i32 {
+%entry:
+  %0 = alloc [i32, 2]
+  %1 = getelemptr %0, @i
+  %2 = load %1
+  ret %2
+}
+
+fun @past(): i32 {
+%entry:
+  %0 = alloc [i32, 2]
+  %1 = getelemptr %0, 2
+  %2 = load %1
+  ret %2
+}
+
+fun @next(): i32 {
+%entry:
+  %0 = alloc [i32, 2]
+  %1 = getelemptr %0, 0
+  %2 = getptr %1, 1
+  %3 = load %2
+  ret %3
+}
+
+fun @last(): i32 {
+%entry:
+  %0 = alloc [i32, 2]
+  %1 = getelemptr %0, 1
+  %2 = getptr %1, 0
+  store 1, %2
+  %3 = load %1
+  ret %3
+}
+"#
+    .into();
+    let mut program = driver.generate_program().unwrap();
+    let funcs = program.func_layout().to_vec();
+    let (even, odd) = (funcs[2], funcs[3]);
+    // make `@even` call `@odd`, which can not be written in the text form
+    let even_data = program.func_mut(even);
+    let call = even_data.calls().next().unwrap();
+    let args = match even_data.dfg().value(call).kind() {
+      ValueKind::Call(call) => call.args().to_vec(),
+      _ => unreachable!(),
+    };
+    even_data.dfg_mut().replace_value_with(call).call(odd, args);
+    InferPurity::new().run_on(&mut program);
+    let pure: Vec<_> = (program.func_layout().iter())
+      .map(|f| program.func(*f))
+      .filter(|data| data.is_pure())
+      .map(|data| data.name())
+      .collect();
+    assert_eq!(pure, ["@half", "@last"]);
+  }
+}
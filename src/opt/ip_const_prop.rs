@@ -0,0 +1,266 @@
+//! Interprocedural constant propagation pass.
+
+use crate::ir::analysis::CallGraph;
+use crate::ir::builder_traits::*;
+use crate::ir::{Function, Program, Value, ValueKind};
+use crate::opt::ModulePass;
+
+/// Propagates integer constants passed to parameters into callees.
+///
+/// If all call sites of a function pass the same integer constant to
+/// a parameter, uses of the parameter in the function are replaced with
+/// the constant, and then the parameter is removed from the function
+/// and all call sites. A recursive call that passes the parameter to
+/// itself at the same position does not prevent the propagation.
+///
+/// Function declarations and `@main` are skipped, since they may be
+/// called from outside the program. Koopa IR can not take addresses of
+/// functions, so all callers of a function are known from the call
+/// graph, and functions without callers are left unchanged.
+///
+/// The pass runs until there are no more constant parameters, since
+/// propagated constants may be passed to other functions.
+#[derive(Default)]
+pub struct IpConstProp;
+
+impl ModulePass for IpConstProp {
+  fn run_on(&mut self, program: &mut Program) {
+    while self.propagate(program) {}
+  }
+}
+
+impl IpConstProp {
+  /// Creates a new interprocedural constant propagation pass.
+  pub fn new() -> Self {
+    Self
+  }
+
+  /// Performs one round of propagation. Returns `true` if changed.
+  fn propagate(&self, program: &mut Program) -> bool {
+    let cg = CallGraph::compute(program);
+    let mut changed = false;
+    for func in program.func_layout().to_vec() {
+      let data = program.func(func);
+      if data.layout().entry_bb().is_none() || data.name() == "@main" {
+        continue;
+      }
+      let consts = self.const_params(program, &cg, func);
+      if consts.is_empty() {
+        continue;
+      }
+      let data = program.func_mut(func);
+      for &(index, value) in &consts {
+        let param = data.params()[index];
+        if !data.dfg().value(param).used_by().is_empty() {
+          let int = data.dfg_mut().new_value().integer(value);
+          data.dfg_mut().replace_uses_with(param, int);
+        }
+      }
+      let indices: Vec<_> = consts.into_iter().map(|(i, _)| i).collect();
+      let args = self.args_of_params(program, &cg, func, &indices);
+      program.remove_params(func, &indices);
+      // constants passed to the removed parameters may be unused now
+      for (caller, arg) in args {
+        let data = program.func_mut(caller);
+        if (data.dfg().values().get(&arg))
+          .is_some_and(|v| v.kind().is_const() && v.used_by().is_empty())
+        {
+          data.dfg_mut().remove_value(arg);
+        }
+      }
+      changed = true;
+    }
+    changed
+  }
+
+  /// Returns indices of parameters of the given function that receive
+  /// the same integer constant at all call sites, and the constants.
+  fn const_params(&self, program: &Program, cg: &CallGraph, func: Function) -> Vec<(usize, i32)> {
+    let params = program.func(func).params();
+    let mut consts: Vec<Option<Option<i32>>> = vec![None; params.len()];
+    for &caller in cg.callers(func) {
+      let data = program.func(caller);
+      for call in data.calls() {
+        let args = match data.dfg().value(call).kind() {
+          ValueKind::Call(c) if c.callee() == func => c.args(),
+          _ => continue,
+        };
+        for (i, arg) in args.iter().enumerate() {
+          if caller == func && *arg == params[i] {
+            continue;
+          }
+          let value = match data.dfg().value(*arg).kind() {
+            ValueKind::Integer(int) => Some(int.value()),
+            _ => None,
+          };
+          consts[i] = match consts[i] {
+            None => Some(value),
+            Some(c) if c == value => Some(c),
+            Some(_) => Some(None),
+          };
+        }
+      }
+    }
+    consts
+      .into_iter()
+      .enumerate()
+      .filter_map(|(i, c)| Some((i, c??)))
+      .collect()
+  }
+
+  /// Returns arguments passed to parameters at the given indices of the
+  /// given function at all call sites, and the callers.
+  fn args_of_params(
+    &self,
+    program: &Program,
+    cg: &CallGraph,
+    func: Function,
+    indices: &[usize],
+  ) -> Vec<(Function, Value)> {
+    let mut args = Vec::new();
+    for &caller in cg.callers(func) {
+      let data = program.func(caller);
+      for call in data.calls() {
+        if let ValueKind::Call(c) = data.dfg().value(call).kind() {
+          if c.callee() == func {
+            args.extend(indices.iter().map(|i| (caller, c.args()[*i])));
+          }
+        }
+      }
+    }
+    args
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::back::KoopaGenerator;
+  use crate::front::Driver;
+
+  fn assert_no_unused_consts(program: &Program) {
+    for data in program.funcs().values() {
+      let mut values = data.dfg().values().values();
+      assert!(values.all(|v| !v.kind().is_const() || !v.used_by().is_empty()));
+    }
+  }
+
+  #[test]
+  fn propagate_constants() {
+    let driver: Driver<_> = r#"decl @putint(i32)
+
+fun @sum(@n: i32, @step: i32, @acc: i32): i32 {
+%entry:
+  %0 = le @n, 0
+  br %0, %ret, %rec
+
+%ret:
+  ret @acc
+
+%rec:
+  %1 = sub @n, @step
+  %2 = add @acc, @n
+  %3 = call @sum(%1, @step, %2)
+  ret %3
+}
+
+fun @twice(@x: i32, @y: i32): i32 {
+%entry:
+  %0 = call @sum(@x, 1, 0)
+  %1 = call @sum(@y, 1, 0)
+  %2 = add %0, %1
+  ret %2
+}
+
+fun @main(@a: i32): i32 {
+%entry:
+  %0 = call @twice(10, @a)
+  %1 = call @twice(10, 20)
+  call @putint(5)
+  ret %1
+}
+"#
+    .into();
+    let mut program = driver.generate_program().unwrap();
+    IpConstProp::new().run_on(&mut program);
+    assert_eq!(program.verify(), Ok(()));
+    assert_eq!(program.audit_use_lists(), Ok(()));
+    assert_no_unused_consts(&program);
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen.generate_on(&program).unwrap();
+    // `@acc` varies in the recursive call, `@y` varies across callers
+    assert_eq!(
+      std::str::from_utf8(&gen.writer()).unwrap(),
+      r#"decl @putint(i32)
+
+fun @sum(@n: i32, @acc: i32): i32 {
+%entry:
+  %0 = le @n, 0
+  br %0, %ret, %rec
+
+%ret:
+  ret @acc
+
+%rec:
+  %1 = sub @n, 1
+  %2 = add @acc, @n
+  %3 = call @sum(%1, %2)
+  ret %3
+}
+
+fun @twice(@y: i32): i32 {
+%entry:
+  %4 = call @sum(10, 0)
+  %5 = call @sum(@y, 0)
+  %6 = add %4, %5
+  ret %6
+}
+
+fun @main(@a: i32): i32 {
+%entry:
+  %7 = call @twice(@a)
+  %8 = call @twice(20)
+  call @putint(5)
+  ret %8
+}
+"#
+    );
+  }
+
+  #[test]
+  fn unused_const_params() {
+    let driver: Driver<_> = r#"fun @f(@x: i32, @y: i32): i32 {
+%entry:
+  ret @y
+}
+
+fun @main(@a: i32): i32 {
+%entry:
+  %0 = call @f(1, @a)
+  ret %0
+}
+"#
+    .into();
+    let mut program = driver.generate_program().unwrap();
+    IpConstProp::new().run_on(&mut program);
+    assert_eq!(program.verify(), Ok(()));
+    assert_eq!(program.audit_use_lists(), Ok(()));
+    assert_no_unused_consts(&program);
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen.generate_on(&program).unwrap();
+    assert_eq!(
+      std::str::from_utf8(&gen.writer()).unwrap(),
+      r#"fun @f(@y: i32): i32 {
+%entry:
+  ret @y
+}
+
+fun @main(@a: i32): i32 {
+%entry:
+  %0 = call @f(@a)
+  ret %0
+}
+"#
+    );
+  }
+}
@@ -0,0 +1,320 @@
+//! SSA destruction pass that removes all basic block parameters.
+
+use crate::ir::builder_traits::*;
+use crate::ir::{BasicBlock, Function, FunctionData, Value, ValueKind};
+use crate::opt::FunctionPass;
+
+/// Removes all basic block parameters, and all arguments passed by
+/// branch/jump/switch instructions.
+///
+/// Each basic block parameter is replaced with a stack slot (`alloc`)
+/// in the entry basic block. Arguments are stored to the slots right
+/// before the branch/jump instruction in predecessors, and loaded at
+/// the beginning of the basic block.
+///
+/// All arguments are SSA values that have been computed before any of
+/// the stores, so copies on the same edge behave like parallel copies,
+/// and swapping parameters (the "swap problem") needs no extra
+/// temporaries. Since slots are only read at the beginning of the
+/// corresponding basic block, stores on one edge do not affect the
+/// values seen along other edges (the "lost copy problem"). The only
+/// exception is a branch or switch that targets the same basic block
+/// more than once, in this case all these edges are split.
+#[derive(Default)]
+pub struct Linearize;
+
+impl FunctionPass for Linearize {
+  fn run_on(&mut self, _: Function, data: &mut FunctionData) {
+    let entry = match data.layout().entry_bb() {
+      Some(entry) => entry,
+      None => return,
+    };
+    let bbs: Vec<_> = data
+      .layout()
+      .bbs()
+      .keys()
+      .copied()
+      .filter(|bb| !data.dfg().bb(*bb).params().is_empty())
+      .collect();
+    for bb in bbs {
+      self.linearize_bb(data, entry, bb);
+    }
+  }
+}
+
+impl Linearize {
+  /// Creates a new linearization pass.
+  pub fn new() -> Self {
+    Self
+  }
+
+  fn linearize_bb(&self, data: &mut FunctionData, entry: BasicBlock, bb: BasicBlock) {
+    // replace parameters with loads from stack slots
+    let params = std::mem::take(data.dfg_mut().bb_mut(bb).params_mut());
+    let mut slots = Vec::new();
+    let mut loads = Vec::new();
+    for p in params {
+      let ty = data.dfg().value(p).ty().clone();
+      let slot = data.dfg_mut().new_value().alloc(ty);
+      let load = data.dfg_mut().new_value().load(slot);
+      data.dfg_mut().replace_uses_with(p, load);
+      data.dfg_mut().remove_value(p);
+      slots.push(slot);
+      loads.push(load);
+    }
+    let insts = data.layout_mut().bb_mut(bb).insts_mut();
+    for load in loads.into_iter().rev() {
+      insts.push_key_front(load).unwrap();
+    }
+    let insts = data.layout_mut().bb_mut(entry).insts_mut();
+    for slot in slots.iter().rev() {
+      insts.push_key_front(*slot).unwrap();
+    }
+    // store arguments in predecessors, in layout order
+    let used_by = data.dfg().bb(bb).used_by().clone();
+    let users: Vec<_> = data
+      .layout()
+      .bbs()
+      .nodes()
+      .flat_map(|node| node.insts().keys())
+      .copied()
+      .filter(|inst| used_by.contains(inst))
+      .collect();
+    for user in users {
+      match data.dfg().value(user).kind().clone() {
+        ValueKind::Jump(jump) => {
+          self.insert_stores(data, user, jump.args(), &slots);
+          data.dfg_mut().replace_value_with(user).jump(bb);
+        }
+        ValueKind::Branch(br) => {
+          let (mut true_bb, mut false_bb) = (br.true_bb(), br.false_bb());
+          let mut true_args = br.true_args().to_vec();
+          let mut false_args = br.false_args().to_vec();
+          if true_bb == bb && false_bb == bb {
+            let pred = data.layout().parent_bb(user).unwrap();
+            true_bb = self.split_edge(data, pred, bb, &true_args, &slots);
+            false_bb = self.split_edge(data, true_bb, bb, &false_args, &slots);
+            true_args.clear();
+            false_args.clear();
+          } else if true_bb == bb {
+            self.insert_stores(data, user, &true_args, &slots);
+            true_args.clear();
+          } else {
+            self.insert_stores(data, user, &false_args, &slots);
+            false_args.clear();
+          }
+          data.dfg_mut().replace_value_with(user).branch_with_args(
+            br.cond(),
+            true_bb,
+            false_bb,
+            true_args,
+            false_args,
+          );
+        }
+        ValueKind::Switch(switch) => {
+          // the first target is the default target
+          let mut targets = vec![(switch.default_bb(), switch.default_args().to_vec())];
+          targets.extend(switch.cases().iter().map(|(_, t, a)| (*t, a.clone())));
+          if targets.iter().filter(|(t, _)| *t == bb).count() > 1 {
+            let mut after = data.layout().parent_bb(user).unwrap();
+            for (target, args) in targets.iter_mut().filter(|(t, _)| *t == bb) {
+              after = self.split_edge(data, after, bb, args, &slots);
+              *target = after;
+              args.clear();
+            }
+          } else {
+            let (_, args) = targets.iter_mut().find(|(t, _)| *t == bb).unwrap();
+            self.insert_stores(data, user, args, &slots);
+            args.clear();
+          }
+          let mut targets = targets.into_iter();
+          let (default, default_args) = targets.next().unwrap();
+          let cases = switch
+            .cases()
+            .iter()
+            .zip(targets)
+            .map(|((value, _, _), (t, a))| (*value, t, a))
+            .collect();
+          data.dfg_mut().replace_value_with(user).switch_with_args(
+            switch.cond(),
+            default,
+            default_args,
+            cases,
+          );
+        }
+        _ => panic!("invalid branch/jump/switch instruction"),
+      }
+    }
+  }
+
+  /// Inserts stores of the given arguments before the given instruction.
+  fn insert_stores(&self, data: &mut FunctionData, inst: Value, args: &[Value], slots: &[Value]) {
+    let bb = data.layout().parent_bb(inst).unwrap();
+    for (arg, slot) in args.iter().zip(slots) {
+      let store = data.dfg_mut().new_value().store(*arg, *slot);
+      let insts = data.layout_mut().bb_mut(bb).insts_mut();
+      insts.cursor_mut(inst).insert_key_before(store).unwrap();
+    }
+  }
+
+  /// Creates a new basic block that stores the given arguments and
+  /// jumps to the target basic block, and places it after the given
+  /// basic block. Returns the new basic block.
+  fn split_edge(
+    &self,
+    data: &mut FunctionData,
+    after: BasicBlock,
+    target: BasicBlock,
+    args: &[Value],
+    slots: &[Value],
+  ) -> BasicBlock {
+    let bb = data.dfg_mut().new_bb().basic_block(None);
+    let jump = data.dfg_mut().new_value().jump(target);
+    let bbs = data.layout_mut().bbs_mut();
+    bbs.cursor_mut(after).insert_key_after(bb).unwrap();
+    data
+      .layout_mut()
+      .bb_mut(bb)
+      .insts_mut()
+      .push_key_back(jump)
+      .unwrap();
+    self.insert_stores(data, jump, args, slots);
+    bb
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::back::KoopaGenerator;
+  use crate::front::Driver;
+  use crate::opt::{Pass, PassManager};
+  use std::str;
+
+  fn linearize(src: &str) -> String {
+    let driver: Driver<_> = src.into();
+    let mut program = driver.generate_program().unwrap();
+    let mut passman = PassManager::new();
+    passman.register(Pass::Function(Box::new(Linearize::new())));
+    passman.run_passes(&mut program);
+    assert_eq!(program.verify(), Ok(()));
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen.generate_on(&program).unwrap();
+    str::from_utf8(&gen.writer()).unwrap().into()
+  }
+
+  #[test]
+  fn swap() {
+    let src = r#"fun @swap(@a: i32, @b: i32): i32 {
+%entry:
+  jump %loop(@a, @b, 0)
+
+%loop(%x: i32, %y: i32, %i: i32):
+  %cond = lt %i, 3
+  %next = add %i, 1
+  br %cond, %loop(%y, %x, %next), %end(%x)
+
+%end(%ans: i32):
+  ret %ans
+}
+"#;
+    let expected = r#"fun @swap(@a: i32, @b: i32): i32 {
+%entry:
+  %0 = alloc i32
+  %1 = alloc i32
+  %2 = alloc i32
+  %3 = alloc i32
+  store @a, %1
+  store @b, %2
+  store 0, %3
+  jump %loop
+
+%loop:
+  %4 = load %1
+  %5 = load %2
+  %6 = load %3
+  %cond = lt %6, 3
+  %next = add %6, 1
+  store %5, %1
+  store %4, %2
+  store %next, %3
+  store %4, %0
+  br %cond, %loop, %end
+
+%end:
+  %7 = load %0
+  ret %7
+}
+"#;
+    assert_eq!(linearize(src), expected);
+  }
+
+  #[test]
+  fn same_target() {
+    let src = r#"fun @f(@a: i32, @b: i32): i32 {
+%entry:
+  br @a, %end(@a), %end(@b)
+
+%end(%ans: i32):
+  ret %ans
+}
+"#;
+    let expected = r#"fun @f(@a: i32, @b: i32): i32 {
+%entry:
+  %0 = alloc i32
+  br @a, %1, %2
+
+%1:
+  store @a, %0
+  jump %end
+
+%2:
+  store @b, %0
+  jump %end
+
+%end:
+  %3 = load %0
+  ret %3
+}
+"#;
+    assert_eq!(linearize(src), expected);
+  }
+
+  #[test]
+  fn switch() {
+    let src = r#"fun @f(@a: i32): i32 {
+%entry:
+  switch @a, %other, [1: %end(@a), 2: %end(0)]
+
+%other:
+  switch @a, %end(2), []
+
+%end(%ans: i32):
+  ret %ans
+}
+"#;
+    let expected = r#"fun @f(@a: i32): i32 {
+%entry:
+  %0 = alloc i32
+  switch @a, %other, [1: %1, 2: %2]
+
+%1:
+  store @a, %0
+  jump %end
+
+%2:
+  store 0, %0
+  jump %end
+
+%other:
+  store 2, %0
+  switch @a, %end, []
+
+%end:
+  %3 = load %0
+  ret %3
+}
+"#;
+    assert_eq!(linearize(src), expected);
+  }
+}
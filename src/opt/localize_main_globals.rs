@@ -0,0 +1,310 @@
+//! Localization pass of global allocations used by `@main`.
+
+use crate::ir::analysis::CallGraph;
+use crate::ir::builder_traits::*;
+use crate::ir::dfg::DataFlowGraph;
+use crate::ir::{Function, FunctionData, Program, Type, TypeKind, Value, ValueKind};
+use crate::opt::ModulePass;
+use std::collections::HashSet;
+
+/// Promotes global allocations that are only used by `@main` to local
+/// allocations in `@main`.
+///
+/// A global allocation keeps its value across calls, so it can only be
+/// promoted into a function that runs at most once. This pass assumes
+/// that `@main` is only called as the entry of the program, and skips
+/// all global allocations if `@main` is called by any function. After
+/// inlining, most globals are only used by `@main`.
+///
+/// A global allocation is promoted if:
+///
+/// * All of its users are instructions in `@main`, so it is not used by
+///   other functions or by initializers of other global allocations.
+/// * Its address does not escape. That is, it and pointers calculated
+///   from it by `getptr`/`getelemptr` are only loaded from or stored to.
+/// * Its initializer is an integer, `zeroinit`, `undef`, an aggregate of
+///   them, or the address of another global allocation.
+///
+/// The local allocation is placed at the beginning of the entry basic
+/// block, followed by a store of the initializer (unless the initializer
+/// is `undef`). It keeps the alignment of the global, and is named after
+/// the global, like `%x` for `@x`, with a suffix like `%x_1` if the name
+/// is taken in `@main`.
+#[derive(Default)]
+pub struct LocalizeMainGlobals;
+
+impl ModulePass for LocalizeMainGlobals {
+  fn run_on(&mut self, program: &mut Program) {
+    let main = match Self::main_func(program) {
+      Some(main) => main,
+      None => return,
+    };
+    let mut insts = Vec::new();
+    for alloc in program.inst_layout().to_vec() {
+      if let Some(init) = Self::promotable_init(program, main, alloc) {
+        insts.extend(Self::localize(program, main, alloc, init));
+      }
+    }
+    // insert local allocations in the order of global allocations
+    let data = program.func_mut(main);
+    let entry = data.layout().entry_bb().unwrap();
+    let bb_insts = data.layout_mut().bb_mut(entry).insts_mut();
+    for inst in insts.into_iter().rev() {
+      bb_insts.push_key_front(inst).unwrap();
+    }
+    program.gc_values();
+  }
+}
+
+/// Initializer of a global allocation, copied into a function.
+enum Init {
+  Integer(i32),
+  ZeroInit(Type),
+  Undef(Type),
+  Aggregate(Vec<Init>),
+  Global(Value),
+}
+
+impl LocalizeMainGlobals {
+  /// Creates a new localization pass of global allocations.
+  pub fn new() -> Self {
+    Self
+  }
+
+  /// Returns `@main` if it is defined and not called by any function.
+  fn main_func(program: &Program) -> Option<Function> {
+    let main = (program.funcs().iter())
+      .find(|(_, f)| f.name() == "@main" && f.layout().entry_bb().is_some())
+      .map(|(f, _)| *f)?;
+    CallGraph::compute(program)
+      .callers(main)
+      .is_empty()
+      .then_some(main)
+  }
+
+  /// Returns the initializer of the given global allocation if it can be
+  /// promoted into the given function.
+  fn promotable_init(program: &Program, func: Function, alloc: Value) -> Option<Init> {
+    let data = program.borrow_value(alloc);
+    let init = match data.kind() {
+      ValueKind::GlobalAlloc(ga) => ga.init(),
+      _ => panic!("invalid global allocation"),
+    };
+    let dfg = program.func(func).dfg();
+    let users = data.used_by().clone();
+    drop(data);
+    if users.is_empty()
+      || !users.iter().all(|u| dfg.values().contains_key(u))
+      || !Self::is_local_ptr(dfg, alloc, users)
+    {
+      return None;
+    }
+    Self::init(program, init, true)
+  }
+
+  /// Checks if the given pointer and pointers calculated from it are only
+  /// loaded from or stored to.
+  ///
+  /// `users` are users of the pointer, which are all local values.
+  fn is_local_ptr(dfg: &DataFlowGraph, ptr: Value, users: HashSet<Value>) -> bool {
+    let is_local = |user: Value| Self::is_local_ptr(dfg, user, dfg.value(user).used_by().clone());
+    users.into_iter().all(|user| match dfg.value(user).kind() {
+      ValueKind::Load(_) => true,
+      ValueKind::Store(s) => s.value() != ptr,
+      ValueKind::GetPtr(gp) if gp.src() == ptr => is_local(user),
+      ValueKind::GetElemPtr(gep) if gep.src() == ptr => is_local(user),
+      _ => false,
+    })
+  }
+
+  /// Converts the given global constant to an initializer, returns `None`
+  /// if it can not be copied into a function.
+  ///
+  /// Addresses of global allocations are only allowed at the top level,
+  /// since elements of local aggregates must be constants.
+  fn init(program: &Program, value: Value, top: bool) -> Option<Init> {
+    let data = program.borrow_value(value);
+    Some(match data.kind() {
+      ValueKind::Integer(i) => Init::Integer(i.value()),
+      ValueKind::ZeroInit(_) => Init::ZeroInit(data.ty().clone()),
+      ValueKind::Undef(_) => Init::Undef(data.ty().clone()),
+      ValueKind::Aggregate(agg) => Init::Aggregate(
+        (agg.elems().iter())
+          .map(|e| Self::init(program, *e, false))
+          .collect::<Option<_>>()?,
+      ),
+      ValueKind::GlobalAlloc(_) if top => Init::Global(value),
+      _ => return None,
+    })
+  }
+
+  /// Replaces the given global allocation with a local allocation in the
+  /// given function. Returns the local allocation and the store of the
+  /// initializer, which are not yet inserted into the layout.
+  fn localize(program: &mut Program, func: Function, alloc: Value, init: Init) -> Vec<Value> {
+    let (name, ty, align) = {
+      let data = program.borrow_value(alloc);
+      let ty = match data.ty().kind() {
+        TypeKind::Pointer(base) => base.clone(),
+        _ => panic!("invalid global allocation"),
+      };
+      let align = match data.kind() {
+        ValueKind::GlobalAlloc(ga) => ga.align(),
+        _ => panic!("invalid global allocation"),
+      };
+      (data.name().clone(), ty, align)
+    };
+    let name = name.map(|n| Self::local_name(program.func(func), &n));
+    let dfg = program.func_mut(func).dfg_mut();
+    let local = dfg.new_value().alloc_with_align(ty, align);
+    dfg.set_value_name(local, name);
+    dfg.replace_uses_with(alloc, local);
+    let mut insts = vec![local];
+    if !matches!(init, Init::Undef(_)) {
+      let init = Self::build_init(dfg, init);
+      insts.push(dfg.new_value().store(init, local));
+    }
+    program.remove_value(alloc);
+    insts
+  }
+
+  /// Returns the local name of the given global name, which is not taken
+  /// by any value or basic block in the given function.
+  fn local_name(data: &FunctionData, name: &str) -> String {
+    let values = data
+      .dfg()
+      .values()
+      .values()
+      .filter_map(|v| v.name().clone());
+    let bbs = data.dfg().bbs().values().filter_map(|bb| bb.name().clone());
+    let taken: HashSet<_> = values.chain(bbs).collect();
+    let name = format!("%{}", &name[1..]);
+    if !taken.contains(&name) {
+      return name;
+    }
+    (1..)
+      .map(|i| format!("{}_{}", name, i))
+      .find(|n| !taken.contains(n))
+      .unwrap()
+  }
+
+  /// Builds the given initializer in the given data flow graph.
+  fn build_init(dfg: &mut DataFlowGraph, init: Init) -> Value {
+    match init {
+      Init::Integer(i) => dfg.new_value().integer(i),
+      Init::ZeroInit(ty) => dfg.new_value().zero_init(ty),
+      Init::Undef(ty) => dfg.new_value().undef(ty),
+      Init::Aggregate(elems) => {
+        let elems = elems
+          .into_iter()
+          .map(|e| Self::build_init(dfg, e))
+          .collect();
+        dfg.new_value().aggregate(elems)
+      }
+      Init::Global(value) => value,
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::back::KoopaGenerator;
+  use crate::front::Driver;
+
+  #[test]
+  fn localize_main_globals() {
+    let driver: Driver<_> = r#"decl @use(*i32)
+
+global @a = alloc i32, 10
+global @b = alloc [i32, 2], {1, undef}
+global @c = alloc i32, zeroinit
+global @d = alloc i32, undef
+global @e = alloc i32, 1
+global @f = alloc i32, 2
+global @p = alloc *i32, @f
+
+fun @get(): i32 {
+%entry:
+  %0 = load @c
+  ret %0
+}
+
+fun @main(): i32 {
+%entry:
+  %0 = load @a
+  %b = getelemptr @b, 1
+  store %0, %b
+  %1 = call @get()
+  store %1, @d
+  call @use(@e)
+  %2 = load @p
+  ret %0
+}
+"#
+    .into();
+    let mut program = driver.generate_program().unwrap();
+    LocalizeMainGlobals::new().run_on(&mut program);
+    assert_eq!(program.verify(), Ok(()));
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen.generate_on(&program).unwrap();
+    // `@c` is used by `@get`, `@e` escapes, `@f` is used by `@p`,
+    // `%b` is taken in `@main`
+    assert_eq!(
+      std::str::from_utf8(&gen.writer()).unwrap(),
+      r#"decl @use(*i32)
+
+global @c = alloc i32, zeroinit
+global @e = alloc i32, 1
+global @f = alloc i32, 2
+
+fun @get(): i32 {
+%entry:
+  %0 = load @c
+  ret %0
+}
+
+fun @main(): i32 {
+%entry:
+  %a = alloc i32
+  store 10, %a
+  %b_1 = alloc [i32, 2]
+  store {1, undef}, %b_1
+  %d = alloc i32
+  %p = alloc *i32
+  store @f, %p
+  %0 = load %a
+  %b = getelemptr %b_1, 1
+  store %0, %b
+  %1 = call @get()
+  store %1, %d
+  call @use(@e)
+  %2 = load %p
+  ret %0
+}
+"#
+    );
+  }
+
+  #[test]
+  fn skip_called_main() {
+    let driver: Driver<_> = r#"global @a = alloc i32, 10
+
+fun @main(): i32 {
+%entry:
+  %0 = load @a
+  ret %0
+}
+
+fun @f(): i32 {
+%entry:
+  %0 = call @main()
+  ret %0
+}
+"#
+    .into();
+    let mut program = driver.generate_program().unwrap();
+    LocalizeMainGlobals::new().run_on(&mut program);
+    assert_eq!(program.inst_layout().len(), 1);
+  }
+}
@@ -0,0 +1,508 @@
+//! SSA construction pass that promotes stack slots to SSA values.
+
+use crate::ir::analysis::DominatorTree;
+use crate::ir::builder_traits::*;
+use crate::ir::values::block_arg_ref;
+use crate::ir::{BasicBlock, Function, FunctionData, TypeKind, Value, ValueKind};
+use crate::opt::FunctionPass;
+use std::collections::{HashMap, HashSet};
+
+/// Promotes stack slots (`alloc`s) to SSA values and basic block
+/// parameters, this is the inverse of [`Linearize`](super::Linearize)
+/// up to naming.
+///
+/// An `alloc` in the entry basic block is promoted if it is only used
+/// as the source of `load`s and the destination of `store`s, and all
+/// these uses are in reachable basic blocks.
+///
+/// Basic block parameters are placed at the iterated dominance frontier
+/// of the stores, and pruned by liveness: a parameter is only created
+/// if the slot may be loaded before being stored again. Loads of slots
+/// that are never stored yield `undef`.
+#[derive(Default)]
+pub struct Mem2Reg;
+
+impl FunctionPass for Mem2Reg {
+  fn run_on(&mut self, _: Function, data: &mut FunctionData) {
+    let entry = match data.layout().entry_bb() {
+      Some(entry) => entry,
+      None => return,
+    };
+    let dom = DominatorTree::compute(data);
+    let slots = self.promotable_slots(data, entry, &dom);
+    if slots.is_empty() {
+      return;
+    }
+    let cfg = Cfg::new(data, &dom);
+    let params = self.insert_params(data, entry, &cfg, &slots);
+    self.rename(data, entry, &dom, &slots, &params);
+    // remove promoted slots
+    for slot in slots {
+      data.layout_mut().bb_mut(entry).insts_mut().remove(&slot);
+      data.dfg_mut().remove_value(slot);
+    }
+  }
+}
+
+impl Mem2Reg {
+  /// Creates a new SSA construction pass.
+  pub fn new() -> Self {
+    Self
+  }
+
+  /// Returns all promotable slots in layout order.
+  fn promotable_slots(
+    &self,
+    data: &FunctionData,
+    entry: BasicBlock,
+    dom: &DominatorTree,
+  ) -> Vec<Value> {
+    let insts = data.layout().bbs().node(&entry).unwrap().insts();
+    insts
+      .keys()
+      .copied()
+      .filter(|slot| matches!(data.dfg().value(*slot).kind(), ValueKind::Alloc(_)))
+      .filter(|slot| {
+        data.dfg().value(*slot).used_by().iter().all(|user| {
+          let promotable = match data.dfg().value(*user).kind() {
+            ValueKind::Load(load) => load.src() == *slot,
+            ValueKind::Store(store) => store.dest() == *slot && store.value() != *slot,
+            _ => false,
+          };
+          promotable
+            && data
+              .layout()
+              .parent_bb(*user)
+              .is_some_and(|bb| dom.is_reachable(bb))
+        })
+      })
+      .collect()
+  }
+
+  /// Creates basic block parameters for the given slots.
+  ///
+  /// Returns the new parameters of each basic block, paired with the
+  /// corresponding slots.
+  fn insert_params(
+    &self,
+    data: &mut FunctionData,
+    entry: BasicBlock,
+    cfg: &Cfg,
+    slots: &[Value],
+  ) -> HashMap<BasicBlock, Vec<(Value, Value)>> {
+    // collect stores and upward-exposed loads of slots in each basic block
+    let promoted: HashSet<_> = slots.iter().copied().collect();
+    let mut defs: HashMap<Value, HashSet<BasicBlock>> = HashMap::new();
+    let mut uses: HashMap<Value, HashSet<BasicBlock>> = HashMap::new();
+    for &bb in &cfg.order {
+      let mut stored = HashSet::new();
+      for inst in data.layout().bbs().node(&bb).unwrap().insts().keys() {
+        match data.dfg().value(*inst).kind() {
+          ValueKind::Load(load)
+            if promoted.contains(&load.src()) && !stored.contains(&load.src()) =>
+          {
+            uses.entry(load.src()).or_default().insert(bb);
+          }
+          ValueKind::Store(store) if promoted.contains(&store.dest()) => {
+            stored.insert(store.dest());
+            defs.entry(store.dest()).or_default().insert(bb);
+          }
+          _ => {}
+        }
+      }
+    }
+    // place parameters for each slot
+    let mut placed: HashMap<BasicBlock, Vec<Value>> = HashMap::new();
+    for &slot in slots {
+      let defs = defs.remove(&slot).unwrap_or_default();
+      let live_in = cfg.live_in(uses.remove(&slot).unwrap_or_default(), &defs);
+      let mut worklist: Vec<_> = defs.iter().copied().collect();
+      let mut visited = HashSet::new();
+      while let Some(bb) = worklist.pop() {
+        for &df in cfg.frontiers.get(&bb).into_iter().flatten() {
+          if df != entry && live_in.contains(&df) && visited.insert(df) {
+            placed.entry(df).or_default().push(slot);
+            worklist.push(df);
+          }
+        }
+      }
+    }
+    // create parameters in the order of slots
+    let order: HashMap<_, _> = slots.iter().enumerate().map(|(i, s)| (*s, i)).collect();
+    let mut params = HashMap::new();
+    for (bb, mut bb_slots) in placed {
+      bb_slots.sort_unstable_by_key(|s| order[s]);
+      let bb_params = bb_slots
+        .into_iter()
+        .map(|slot| {
+          let ty = match data.dfg().value(slot).ty().kind() {
+            TypeKind::Pointer(base) => base.clone(),
+            _ => panic!("invalid alloc type"),
+          };
+          let index = data.dfg().bb(bb).params().len();
+          let param = data.dfg_mut().new_value().raw(block_arg_ref(index, ty));
+          data.dfg_mut().bb_mut(bb).params_mut().push(param);
+          (slot, param)
+        })
+        .collect();
+      params.insert(bb, bb_params);
+    }
+    params
+  }
+
+  /// Replaces loads and stores of slots with SSA values in dominator
+  /// tree order, and passes the current values of slots to the new
+  /// parameters of successors.
+  fn rename(
+    &self,
+    data: &mut FunctionData,
+    entry: BasicBlock,
+    dom: &DominatorTree,
+    slots: &[Value],
+    params: &HashMap<BasicBlock, Vec<(Value, Value)>>,
+  ) {
+    let promoted: HashSet<_> = slots.iter().copied().collect();
+    // children of basic blocks in the dominator tree, in layout order
+    let mut children: HashMap<_, Vec<_>> = HashMap::new();
+    for &bb in data.layout().bbs().keys() {
+      if let Some(idom) = dom.idom(bb) {
+        children.entry(idom).or_default().push(bb);
+      }
+    }
+    let mut undefs = HashMap::new();
+    let mut stack = vec![(entry, HashMap::new())];
+    while let Some((bb, mut values)) = stack.pop() {
+      for (slot, param) in params.get(&bb).into_iter().flatten() {
+        values.insert(*slot, *param);
+      }
+      let insts: Vec<_> = data
+        .layout()
+        .bbs()
+        .node(&bb)
+        .unwrap()
+        .insts()
+        .keys()
+        .copied()
+        .collect();
+      for inst in insts {
+        match data.dfg().value(inst).kind().clone() {
+          ValueKind::Load(load) if promoted.contains(&load.src()) => {
+            let value = self.current_value(data, load.src(), &values, &mut undefs);
+            data.dfg_mut().replace_uses_with(inst, value);
+            data.layout_mut().bb_mut(bb).insts_mut().remove(&inst);
+            data.dfg_mut().remove_value(inst);
+          }
+          ValueKind::Store(store) if promoted.contains(&store.dest()) => {
+            values.insert(store.dest(), store.value());
+            data.layout_mut().bb_mut(bb).insts_mut().remove(&inst);
+            data.dfg_mut().remove_value(inst);
+          }
+          _ => {}
+        }
+      }
+      if let Some(term) = data.terminator(bb) {
+        // slots that are not stored yet are undefined
+        let targets: Vec<_> = data.dfg().value(term).kind().bb_uses().collect();
+        for (slot, _) in targets.iter().filter_map(|t| params.get(t)).flatten() {
+          let value = self.current_value(data, *slot, &values, &mut undefs);
+          values.insert(*slot, value);
+        }
+        self.pass_args(data, term, &values, params);
+      }
+      for &child in children.get(&bb).into_iter().flatten().rev() {
+        stack.push((child, values.clone()));
+      }
+    }
+  }
+
+  /// Returns the current value of the given slot, or `undef` if
+  /// the slot is not stored yet.
+  fn current_value(
+    &self,
+    data: &mut FunctionData,
+    slot: Value,
+    values: &HashMap<Value, Value>,
+    undefs: &mut HashMap<Value, Value>,
+  ) -> Value {
+    if let Some(value) = values.get(&slot) {
+      return *value;
+    }
+    *undefs.entry(slot).or_insert_with(|| {
+      let ty = match data.dfg().value(slot).ty().kind() {
+        TypeKind::Pointer(base) => base.clone(),
+        _ => panic!("invalid alloc type"),
+      };
+      data.dfg_mut().new_value().undef(ty)
+    })
+  }
+
+  /// Appends the current values of slots to the arguments of the given
+  /// terminator, for each target with new parameters.
+  fn pass_args(
+    &self,
+    data: &mut FunctionData,
+    term: Value,
+    values: &HashMap<Value, Value>,
+    params: &HashMap<BasicBlock, Vec<(Value, Value)>>,
+  ) {
+    let mut changed = false;
+    let mut new_args = |target: BasicBlock, args: &mut Vec<Value>| {
+      if let Some(ps) = params.get(&target) {
+        args.extend(ps.iter().map(|(slot, _)| values[slot]));
+        changed = true;
+      }
+    };
+    let mut inst = data.dfg().value(term).clone();
+    match inst.kind_mut() {
+      ValueKind::Branch(br) => {
+        new_args(br.true_bb(), br.true_args_mut());
+        new_args(br.false_bb(), br.false_args_mut());
+      }
+      ValueKind::Jump(jump) => new_args(jump.target(), jump.args_mut()),
+      ValueKind::Switch(switch) => {
+        new_args(switch.default_bb(), switch.default_args_mut());
+        for (_, bb, args) in switch.cases_mut() {
+          new_args(*bb, args);
+        }
+      }
+      _ => {}
+    }
+    if changed {
+      data.dfg_mut().replace_value_with(term).raw(inst);
+    }
+  }
+}
+
+/// Control flow graph of reachable basic blocks.
+struct Cfg {
+  /// Reachable basic blocks in layout order.
+  order: Vec<BasicBlock>,
+  /// Predecessors of basic blocks.
+  preds: HashMap<BasicBlock, Vec<BasicBlock>>,
+  /// Dominance frontiers of basic blocks.
+  frontiers: HashMap<BasicBlock, HashSet<BasicBlock>>,
+}
+
+impl Cfg {
+  fn new(data: &FunctionData, dom: &DominatorTree) -> Self {
+    let order: Vec<_> = data
+      .layout()
+      .bbs()
+      .keys()
+      .copied()
+      .filter(|bb| dom.is_reachable(*bb))
+      .collect();
+    let mut preds: HashMap<_, Vec<_>> = HashMap::new();
+    for &bb in &order {
+      if let Some(term) = data.terminator(bb) {
+        for target in data.dfg().value(term).kind().bb_uses() {
+          preds.entry(target).or_default().push(bb);
+        }
+      }
+    }
+    // dominance frontiers, by Cooper, Harvey and Kennedy
+    let mut frontiers: HashMap<_, HashSet<_>> = HashMap::new();
+    for (&bb, bb_preds) in &preds {
+      let idom = match dom.idom(bb) {
+        Some(idom) if bb_preds.len() > 1 => idom,
+        _ => continue,
+      };
+      for &pred in bb_preds {
+        let mut runner = pred;
+        while runner != idom {
+          frontiers.entry(runner).or_default().insert(bb);
+          runner = dom.idom(runner).unwrap();
+        }
+      }
+    }
+    Self {
+      order,
+      preds,
+      frontiers,
+    }
+  }
+
+  /// Returns basic blocks where the slot is live at the entry, given
+  /// basic blocks that load the slot before storing it, and basic
+  /// blocks that store the slot.
+  fn live_in(&self, uses: HashSet<BasicBlock>, defs: &HashSet<BasicBlock>) -> HashSet<BasicBlock> {
+    let mut worklist: Vec<_> = uses.iter().copied().collect();
+    let mut live_in = uses;
+    while let Some(bb) = worklist.pop() {
+      for &pred in self.preds.get(&bb).into_iter().flatten() {
+        if !defs.contains(&pred) && live_in.insert(pred) {
+          worklist.push(pred);
+        }
+      }
+    }
+    live_in
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::back::KoopaGenerator;
+  use crate::front::Driver;
+  use crate::opt::{Linearize, Pass, PassManager};
+  use std::str;
+
+  fn run(src: &str, passes: Vec<Pass>) -> String {
+    let driver: Driver<_> = src.into();
+    let mut program = driver.generate_program().unwrap();
+    let mut passman = PassManager::new();
+    passes.into_iter().for_each(|p| passman.register(p));
+    passman.run_passes(&mut program);
+    assert_eq!(program.verify(), Ok(()));
+    let mut gen = KoopaGenerator::new(Vec::new());
+    gen.generate_on(&program).unwrap();
+    str::from_utf8(&gen.writer()).unwrap().into()
+  }
+
+  #[test]
+  fn promote_slots() {
+    let src = r#"fun @sum(@n: i32): i32 {
+%entry:
+  %s = alloc i32
+  %i = alloc i32
+  %p = alloc i32
+  store 0, %s
+  store 0, %i
+  jump %cond
+
+%cond:
+  %0 = load %i
+  %1 = lt %0, @n
+  br %1, %body, %end
+
+%body:
+  %2 = load %s
+  %3 = add %2, %0
+  store %3, %s
+  %4 = add %0, 1
+  store %4, %i
+  jump %cond
+
+%end:
+  %5 = load %s
+  %6 = getptr %p, 0
+  %7 = load %6
+  %8 = add %5, %7
+  ret %8
+}
+"#;
+    let expected = r#"fun @sum(@n: i32): i32 {
+%entry:
+  %p = alloc i32
+  jump %cond(0, 0)
+
+%cond(%0: i32, %1: i32):
+  %2 = lt %1, @n
+  br %2, %body, %end
+
+%body:
+  %3 = add %0, %1
+  %4 = add %1, 1
+  jump %cond(%3, %4)
+
+%end:
+  %5 = getptr %p, 0
+  %6 = load %5
+  %7 = add %0, %6
+  ret %7
+}
+"#;
+    assert_eq!(
+      run(src, vec![Pass::Function(Box::new(Mem2Reg::new()))]),
+      expected
+    );
+  }
+
+  #[test]
+  fn undef_and_pruning() {
+    let src = r#"fun @f(@a: i32): i32 {
+%entry:
+  %x = alloc i32
+  %y = alloc i32
+  br @a, %then, %else
+
+%then:
+  store 1, %x
+  store 1, %y
+  jump %end
+
+%else:
+  store 2, %y
+  jump %end
+
+%end:
+  %0 = load %x
+  ret %0
+}
+"#;
+    let expected = r#"fun @f(@a: i32): i32 {
+%entry:
+  br @a, %then, %else
+
+%then:
+  jump %end(1)
+
+%else:
+  jump %end(undef)
+
+%end(%0: i32):
+  ret %0
+}
+"#;
+    assert_eq!(
+      run(src, vec![Pass::Function(Box::new(Mem2Reg::new()))]),
+      expected
+    );
+  }
+
+  #[test]
+  fn round_trip() {
+    let src = r#"fun @swap(@a: i32, @b: i32): i32 {
+%entry:
+  jump %loop(@a, @b, 0)
+
+%loop(%x: i32, %y: i32, %i: i32):
+  %cond = lt %i, 3
+  %next = add %i, 1
+  br %cond, %loop(%y, %x, %next), %end(%x)
+
+%end(%ans: i32):
+  ret %ans
+}
+
+fun @main(): i32 {
+%entry:
+  br 1, %a, %b
+
+%a:
+  jump %end(1)
+
+%b:
+  jump %end(2)
+
+%end(%ans: i32):
+  ret %ans
+}
+"#;
+    let linearize = || Pass::Function(Box::new(Linearize::new()));
+    let mem2reg = || Pass::Function(Box::new(Mem2Reg::new()));
+    let hash = |src: &str| {
+      let driver: Driver<_> = src.into();
+      driver.generate_program().unwrap().content_hash()
+    };
+    // parameters of basic blocks with only one predecessor are
+    // replaced with the arguments
+    let expected = src.replace("%end(%x)", "%end").replace(
+      "%end(%ans: i32):\n  ret %ans\n}\n\nfun",
+      "%end:\n  ret %x\n}\n\nfun",
+    );
+    let ssa = run(src, vec![linearize(), mem2reg()]);
+    assert_eq!(hash(&ssa), hash(&expected));
+    assert_eq!(run(&ssa, vec![linearize(), mem2reg()]), ssa);
+  }
+}
@@ -7,6 +7,7 @@
 //!   function pass trait ([`FunctionPass`](pass::FunctionPass)).
 //! * The pass manager ([`PassManager`]) that holds all registered passes,
 //!   and uses them to optimize the given Koopa IR program.
+//! * Some built-in passes, like [`Linearize`] and [`GepFolding`].
 //!
 //! # Example
 //!
@@ -37,8 +38,30 @@
 //! passman.run_passes(&mut program);
 //! ```
 
+mod branch_fold;
+mod const_merge;
+mod copy_prop;
+mod dead_arg;
+mod dead_store;
+mod gep_fold;
+mod infer_purity;
+mod ip_const_prop;
+mod linearize;
+mod localize_main_globals;
+mod mem2reg;
 mod pass;
 mod passman;
 
+pub use branch_fold::BranchFolding;
+pub use const_merge::ConstantMerge;
+pub use copy_prop::CopyPropagation;
+pub use dead_arg::DeadArgElimination;
+pub use dead_store::DeadStoreElimination;
+pub use gep_fold::GepFolding;
+pub use infer_purity::InferPurity;
+pub use ip_const_prop::IpConstProp;
+pub use linearize::Linearize;
+pub use localize_main_globals::LocalizeMainGlobals;
+pub use mem2reg::Mem2Reg;
 pub use pass::*;
 pub use passman::PassManager;